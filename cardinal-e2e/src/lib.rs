@@ -0,0 +1,138 @@
+//! The chain the app's background thread wires, in one process: a
+//! [`CacheSet`] walked or read back from its cache file, an event stream
+//! resuming from the set's last event id, every batch handed to the set, and
+//! queries on the result. `tests/smoke.rs` drives it over a real tree.
+//!
+//! Nothing here sleeps. [`Session::sync`] writes a marker file and applies
+//! batches until the marker's own event comes through, events arriving in
+//! order, so everything changed before it is applied by then.
+
+use anyhow::{Context, Result, bail};
+use cardinal_sdk::{EventBatch, EventStreamBuilder, EventWatcher};
+use search_cache::{CacheError, CacheSet, SearchCache, SearchOptions};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+/// FSEvents latency of the stream, low so that syncing is quick.
+const LATENCY: f64 = 0.05;
+
+pub struct Session {
+    root: PathBuf,
+    cache: CacheSet,
+    watcher: EventWatcher,
+    /// The stream delivered `HistoryDone`.
+    history_done: bool,
+    /// Markers written by [`Self::sync`] so far.
+    syncs: usize,
+}
+
+impl Session {
+    /// Walk `root`, to be flushed to `cache_path`, and watch it.
+    pub fn walk(root: &Path, cache_path: PathBuf) -> Result<Self> {
+        let mut cache = CacheSet::new();
+        cache.insert(SearchCache::walk_fs(root.to_path_buf()), cache_path)?;
+        Ok(Self::watch(root, cache))
+    }
+
+    /// Read `root` back from `cache_path`, as the app does at launch, and
+    /// watch it from the last event the file had applied.
+    pub fn load(root: &Path, cache_path: PathBuf) -> Result<Self, CacheError> {
+        let mut cache = CacheSet::new();
+        let loaded = SearchCache::try_read_persistent_cache(root, &cache_path, None, None)?;
+        cache.insert(loaded, cache_path)?;
+        Ok(Self::watch(root, cache))
+    }
+
+    fn watch(root: &Path, cache: CacheSet) -> Self {
+        let (_, watcher) = EventStreamBuilder::new([root.to_string_lossy()])
+            .since(cache.last_event_id())
+            .latency(LATENCY)
+            .spawn();
+        Self {
+            root: root.to_path_buf(),
+            cache,
+            watcher,
+            history_done: false,
+            syncs: 0,
+        }
+    }
+
+    /// Apply batches until the stream is done replaying its history.
+    pub fn wait_for_history(&mut self, timeout: Duration) -> Result<()> {
+        let deadline = Instant::now() + timeout;
+        while !self.history_done {
+            let batch = self
+                .next_batch(deadline)
+                .context("Waiting for HistoryDone")?;
+            self.apply(batch)?;
+        }
+        Ok(())
+    }
+
+    /// Apply batches until everything changed on disk so far is in the cache.
+    /// The markers are dotfiles, left out of searches by default.
+    pub fn sync(&mut self, timeout: Duration) -> Result<()> {
+        self.syncs += 1;
+        let marker = self.root.join(format!(".e2e-sync-{}", self.syncs));
+        fs::write(&marker, b"")?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let batch = self
+                .next_batch(deadline)
+                .with_context(|| format!("Waiting for the event of {marker:?}"))?;
+            let seen = batch.iter().any(|event| event.path == marker);
+            self.apply(batch)?;
+            if seen {
+                return Ok(());
+            }
+        }
+    }
+
+    fn next_batch(&self, deadline: Instant) -> Result<EventBatch> {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        Ok(self.watcher.recv_timeout(timeout)?)
+    }
+
+    /// Hand `batch` to the cache set like the background thread does, minus
+    /// the rescan it would start: here one means a bug.
+    fn apply(&mut self, batch: EventBatch) -> Result<()> {
+        self.history_done |= batch.history_done();
+        let rescans = self.cache.handle_fs_events(batch.events);
+        if !rescans.is_empty() {
+            bail!("Events asked for a rescan of {:?}", self.root);
+        }
+        Ok(())
+    }
+
+    /// Paths `query` finds with the default options, relative to the root
+    /// and sorted.
+    pub fn search(&self, query: &str) -> Result<Vec<String>> {
+        let outcome = self.cache.search_with_options(
+            query,
+            None,
+            SearchOptions::default().with_skip_history(true),
+            CancellationToken::noop(),
+        )?;
+        let mut paths: Vec<String> = outcome
+            .nodes
+            .into_iter()
+            .filter_map(|node| self.cache.node_path(node))
+            .filter_map(|path| {
+                let relative = path.strip_prefix(&self.root).ok()?;
+                Some(relative.to_string_lossy().into_owned())
+            })
+            .collect();
+        paths.sort();
+        Ok(paths)
+    }
+
+    /// Stop watching and write the cache file, as the app does on exit.
+    pub fn flush(self) -> Result<()> {
+        drop(self.watcher);
+        self.cache.flush_to_files()
+    }
+}
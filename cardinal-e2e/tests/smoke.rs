@@ -0,0 +1,117 @@
+//! Walk a fixture tree, follow real file system events, persist and reload,
+//! checking the queries at every step. Ignored by default since it depends on
+//! the machine's event stream, run it with
+//! `cargo test -p cardinal-e2e -- --ignored`.
+
+use anyhow::Result;
+use cardinal_e2e::Session;
+use search_cache::testkit::TreeSpec;
+use std::{fs, path::Path, time::Duration};
+use tempdir::TempDir;
+
+/// Longest wait for the events of one step, generous for loaded CI machines.
+const TIMEOUT: Duration = Duration::from_secs(20);
+
+#[track_caller]
+fn assert_finds(session: &Session, query: &str, expected: &[&str]) {
+    assert_eq!(
+        session.search(query).unwrap(),
+        expected,
+        "results of {query:?}"
+    );
+}
+
+/// What the tree holds after [`mutate`], checked before and after a reload.
+fn check_mutated(session: &Session) {
+    assert_finds(
+        session,
+        "report",
+        &["archive/old-report.txt", "docs/final-report.txt"],
+    );
+    assert_finds(session, "summary", &["docs/summary.txt"]);
+    assert_finds(session, "ext:rs", &["src/main.rs"]);
+    assert_finds(session, "idea", &["drafts/idea.txt"]);
+    assert_finds(session, "archive", &["archive"]);
+    assert_finds(session, "ext:md", &["docs/notes.md"]);
+    assert_finds(session, "settings", &[".config/settings.json"]);
+    // The sync markers are dotfiles.
+    assert_finds(session, "e2e-sync", &[]);
+}
+
+/// Creates, a modification, a rename, a delete, a folder created with a
+/// file in it and a folder moved with its contents.
+fn mutate(root: &Path) -> Result<()> {
+    fs::write(root.join("docs/summary.txt"), b"summary")?;
+    fs::write(root.join("docs/notes.md"), b"updated notes")?;
+    fs::rename(
+        root.join("docs/report.txt"),
+        root.join("docs/final-report.txt"),
+    )?;
+    fs::remove_file(root.join("src/lib.rs"))?;
+    fs::create_dir(root.join("drafts"))?;
+    fs::write(root.join("drafts/idea.txt"), b"idea")?;
+    fs::rename(root.join("docs/archive"), root.join("archive"))?;
+    Ok(())
+}
+
+#[test]
+#[ignore = "follows the machine's real file system events"]
+fn walk_watch_persist_and_reload() -> Result<()> {
+    let tmp = TreeSpec::new()
+        .file("docs/report.txt")
+        .file("docs/notes.md")
+        .file("docs/archive/old-report.txt")
+        .sized_file("photos/beach.png", 4096)
+        .file("src/main.rs")
+        .file("src/lib.rs")
+        .file(".config/settings.json")
+        .create("cardinal_e2e");
+    // FSEvents reports `/private/var/...` for `/var/...`.
+    let root = tmp.path().canonicalize()?;
+    // Outside the watched tree, so writing it isn't an event of its own.
+    let cache_dir = TempDir::new("cardinal_e2e_cache")?;
+    let cache_path = cache_dir.path().join("cache.zstd");
+
+    let mut session = Session::walk(&root, cache_path.clone())?;
+    session.wait_for_history(TIMEOUT)?;
+    assert_finds(
+        &session,
+        "report",
+        &["docs/archive/old-report.txt", "docs/report.txt"],
+    );
+    assert_finds(&session, "ext:rs", &["src/lib.rs", "src/main.rs"]);
+    assert_finds(&session, "ext:png size:>1kb", &["photos/beach.png"]);
+    assert_finds(&session, "settings", &[".config/settings.json"]);
+
+    mutate(&root)?;
+    session.sync(TIMEOUT)?;
+    check_mutated(&session);
+    session.flush()?;
+
+    // Launching again resumes from the event id the file was flushed at.
+    let mut session = Session::load(&root, cache_path.clone())?;
+    check_mutated(&session);
+    session.wait_for_history(TIMEOUT)?;
+    check_mutated(&session);
+
+    fs::remove_dir_all(root.join("drafts"))?;
+    fs::write(root.join("photos/sunset.png"), b"sunset")?;
+    session.sync(TIMEOUT)?;
+    assert_finds(&session, "idea", &[]);
+    assert_finds(&session, "drafts", &[]);
+    assert_finds(
+        &session,
+        "ext:png",
+        &["photos/beach.png", "photos/sunset.png"],
+    );
+    session.flush()?;
+
+    let session = Session::load(&root, cache_path)?;
+    assert_finds(
+        &session,
+        "ext:png",
+        &["photos/beach.png", "photos/sunset.png"],
+    );
+    assert_finds(&session, "idea", &[]);
+    Ok(())
+}
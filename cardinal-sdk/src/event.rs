@@ -1,7 +1,7 @@
 use crate::{EventFlag, FSEventStreamEventId, ScanType};
+use libc::dev_t;
 use std::{
-    ffi::{CStr, OsStr},
-    os::unix::ffi::OsStrExt,
+    ops::Deref,
     path::{Path, PathBuf},
 };
 
@@ -16,7 +16,12 @@ pub struct FsEvent {
 }
 
 impl FsEvent {
+    #[cfg(target_os = "macos")]
     pub(crate) unsafe fn from_raw(path: *const i8, flag: u32, id: u64) -> Self {
+        use std::{
+            ffi::{CStr, OsStr},
+            os::unix::ffi::OsStrExt,
+        };
         let path = unsafe { CStr::from_ptr(path) };
         let path = OsStr::from_bytes(path.to_bytes());
         let path = PathBuf::from(path);
@@ -33,6 +38,54 @@ impl FsEvent {
     }
 }
 
+/// Events delivered together by an [`crate::EventStreamHandle`], with the
+/// stream they came from. Derefs to the events.
+#[derive(Debug)]
+pub struct EventBatch {
+    /// Device of the first watched path, 0 when unknown (e.g. for replayed
+    /// events).
+    pub device: dev_t,
+    /// [`crate::event_stream_uuid`] of `device` when the stream started, all
+    /// zeroes when the volume keeps no event history.
+    pub stream_uuid: [u8; 16],
+    pub events: Vec<FsEvent>,
+}
+
+impl EventBatch {
+    /// Whether the batch ends the replay of the stream's history.
+    pub fn history_done(&self) -> bool {
+        self.events
+            .iter()
+            .any(|event| event.flag.contains(EventFlag::HistoryDone))
+    }
+}
+
+impl Deref for EventBatch {
+    type Target = [FsEvent];
+
+    fn deref(&self) -> &Self::Target {
+        &self.events
+    }
+}
+
+impl IntoIterator for EventBatch {
+    type Item = FsEvent;
+    type IntoIter = std::vec::IntoIter<FsEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a EventBatch {
+    type Item = &'a FsEvent;
+    type IntoIter = std::slice::Iter<'a, FsEvent>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.events.iter()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
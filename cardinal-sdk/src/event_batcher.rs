@@ -0,0 +1,625 @@
+use crate::{EventBatch, EventFlag, FsEvent, ScanType, dev_of_path, event_stream_uuid};
+use crossbeam_channel::{Receiver, Sender, after, never, select};
+use libc::dev_t;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+/// How [`crate::EventWatcher`] groups FSEvents callbacks before delivering them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventWatcherConfig {
+    /// Longest time the first event of a batch waits before delivery.
+    pub max_delay: Duration,
+    /// Deliver right away once this many events are pending.
+    pub max_batch: usize,
+}
+
+impl Default for EventWatcherConfig {
+    fn default() -> Self {
+        Self {
+            max_delay: Duration::from_millis(250),
+            max_batch: 10_000,
+        }
+    }
+}
+
+/// Requests from an [`crate::EventStreamHandle`] to its batcher, sent over a
+/// zero-capacity channel so they take effect before the sender goes on.
+pub(crate) enum BatcherControl {
+    Pause,
+    Resume,
+    /// Paths to rescan when events were dropped while paused. The first one
+    /// also names the device of the batches from then on. The sender is
+    /// dropped once they are, so events sent afterwards leave under them:
+    /// taking the request alone doesn't order it after those.
+    SetRoots(Vec<PathBuf>, Sender<()>),
+}
+
+/// Device and FSEvents stream UUID of the first of `roots`, zeroes for
+/// whatever isn't known.
+pub(crate) fn stream_origin(roots: &[PathBuf]) -> (dev_t, [u8; 16]) {
+    let Some(device) = roots.first().and_then(|root| dev_of_path(root).ok()) else {
+        return (0, [0; 16]);
+    };
+    (device, event_stream_uuid(device).unwrap_or_default())
+}
+
+/// Forward events from `source` to `sink` in batches, stamped with the
+/// origin of `roots`, until either side or `control` disconnects.
+///
+/// A `HistoryDone` marker flushes the pending batch immediately. While paused,
+/// up to `queue_size` events are kept back; past that they are dropped (except
+/// `HistoryDone` markers) and resuming sends one `MustScanSubDirs` event per root
+/// instead, with the highest event id dropped.
+pub(crate) fn run_batcher(
+    source: Receiver<Vec<FsEvent>>,
+    sink: Sender<EventBatch>,
+    control: Receiver<BatcherControl>,
+    roots: Vec<PathBuf>,
+    queue_size: usize,
+    config: EventWatcherConfig,
+) {
+    let (device, stream_uuid) = stream_origin(&roots);
+    let mut batcher = Batcher {
+        sink,
+        device,
+        stream_uuid,
+        roots,
+        queue_size,
+        config,
+        pending: Vec::new(),
+        deadline: None,
+        paused: false,
+        overflow: None,
+    };
+    loop {
+        let timeout = match batcher.deadline {
+            Some(deadline) => after(deadline.saturating_duration_since(Instant::now())),
+            None => never(),
+        };
+        let delivered = select! {
+            recv(source) -> events => {
+                match events {
+                    Ok(events) => batcher.push(events),
+                    Err(_) => {
+                        if !batcher.pending.is_empty() {
+                            let _ = batcher.flush();
+                        }
+                        return;
+                    }
+                }
+            }
+            recv(timeout) -> _ => batcher.flush(),
+            recv(control) -> request => {
+                let Ok(request) = request else {
+                    return;
+                };
+                // Requests are handed over synchronously, so events sent before
+                // one are already queued: handle them first.
+                for events in source.try_iter() {
+                    if batcher.push(events).is_err() {
+                        return;
+                    }
+                }
+                batcher.apply(request)
+            }
+        };
+        if delivered.is_err() {
+            return;
+        }
+    }
+}
+
+struct Batcher {
+    sink: Sender<EventBatch>,
+    device: dev_t,
+    stream_uuid: [u8; 16],
+    roots: Vec<PathBuf>,
+    queue_size: usize,
+    config: EventWatcherConfig,
+    pending: Vec<FsEvent>,
+    deadline: Option<Instant>,
+    paused: bool,
+    /// Highest event id dropped since the queue overflowed while paused.
+    overflow: Option<u64>,
+}
+
+/// The sink disconnected.
+struct Disconnected;
+
+impl Batcher {
+    fn push(&mut self, events: Vec<FsEvent>) -> Result<(), Disconnected> {
+        if self.paused {
+            if let Some(dropped) = &mut self.overflow {
+                keep_markers(events, &mut self.pending, dropped);
+            } else {
+                self.pending.extend(events);
+                if self.pending.len() > self.queue_size {
+                    let mut dropped = 0;
+                    keep_markers(
+                        std::mem::take(&mut self.pending),
+                        &mut self.pending,
+                        &mut dropped,
+                    );
+                    self.overflow = Some(dropped);
+                }
+            }
+            return Ok(());
+        }
+        let history_done = events
+            .iter()
+            .any(|event| event.flag.contains(EventFlag::HistoryDone));
+        if self.pending.is_empty() {
+            self.deadline = Some(Instant::now() + self.config.max_delay);
+        }
+        self.pending.extend(events);
+        if history_done || self.pending.len() >= self.config.max_batch {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, request: BatcherControl) -> Result<(), Disconnected> {
+        match request {
+            BatcherControl::Pause => {
+                self.paused = true;
+                self.deadline = None;
+            }
+            BatcherControl::Resume if self.paused => {
+                self.paused = false;
+                return self.flush();
+            }
+            BatcherControl::Resume => {}
+            BatcherControl::SetRoots(roots, _applied) => {
+                // Whatever the old stream delivered leaves under its origin.
+                if !self.paused {
+                    self.flush()?;
+                }
+                (self.device, self.stream_uuid) = stream_origin(&roots);
+                self.roots = roots;
+            }
+        }
+        Ok(())
+    }
+
+    /// Send the pending events, after the rescans replacing dropped ones.
+    fn flush(&mut self) -> Result<(), Disconnected> {
+        self.deadline = None;
+        let mut events = match self.overflow.take() {
+            Some(id) => rescan_events(&self.roots, id),
+            None => Vec::new(),
+        };
+        events.extend(coalesce_events(std::mem::take(&mut self.pending)));
+        if events.is_empty() {
+            return Ok(());
+        }
+        let batch = EventBatch {
+            device: self.device,
+            stream_uuid: self.stream_uuid,
+            events,
+        };
+        self.sink.send(batch).map_err(|_| Disconnected)
+    }
+}
+
+/// Move the `HistoryDone` markers of `events` to `kept`, raising `dropped` to
+/// the highest id of the others.
+fn keep_markers(events: Vec<FsEvent>, kept: &mut Vec<FsEvent>, dropped: &mut u64) {
+    for event in events {
+        if event.flag.contains(EventFlag::HistoryDone) {
+            kept.push(event);
+        } else {
+            *dropped = (*dropped).max(event.id);
+        }
+    }
+}
+
+/// What FSEvents reports when it drops events itself: rescan every root.
+fn rescan_events(roots: &[PathBuf], id: u64) -> Vec<FsEvent> {
+    roots
+        .iter()
+        .map(|root| FsEvent {
+            path: root.clone(),
+            flag: EventFlag::MustScanSubDirs | EventFlag::UserDropped | EventFlag::ItemIsDir,
+            id,
+        })
+        .collect()
+}
+
+/// Keep one event per path, with the highest-priority flag and the latest event id.
+///
+/// Each merged event takes the place of the last event for its path, so it still follows
+/// everything the path's final change came after. `HistoryDone` markers are passed through
+/// untouched.
+pub(crate) fn coalesce_events(events: Vec<FsEvent>) -> Vec<FsEvent> {
+    let mut slots: Vec<Option<FsEvent>> = Vec::with_capacity(events.len());
+    let mut positions: HashMap<PathBuf, usize> = HashMap::with_capacity(events.len());
+    for mut event in events {
+        if event.flag.contains(EventFlag::HistoryDone) {
+            slots.push(Some(event));
+            continue;
+        }
+        if let Some(position) = positions.insert(event.path.clone(), slots.len()) {
+            let earlier = slots[position].take().expect("slot holds the path's event");
+            // On a tie the later flag describes the current state better.
+            if flag_priority(earlier.flag) > flag_priority(event.flag) {
+                event.flag = earlier.flag;
+            }
+            event.id = event.id.max(earlier.id);
+        }
+        slots.push(Some(event));
+    }
+    slots.into_iter().flatten().collect()
+}
+
+fn flag_priority(flag: EventFlag) -> u8 {
+    match flag.scan_type() {
+        ScanType::ReScan => 3,
+        ScanType::Folder => 2,
+        ScanType::SingleNode => 1,
+        ScanType::Nop => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventStreamBuilder, EventWatcher};
+    use crossbeam_channel::{RecvTimeoutError, unbounded};
+
+    fn event(path: &str, flag: EventFlag, id: u64) -> FsEvent {
+        FsEvent {
+            path: PathBuf::from(path),
+            flag,
+            id,
+        }
+    }
+
+    fn file_events(prefix: &str, count: usize) -> Vec<FsEvent> {
+        (0..count)
+            .map(|i| {
+                event(
+                    &format!("/tmp/{prefix}{i}"),
+                    EventFlag::ItemCreated | EventFlag::ItemIsFile,
+                    i as u64,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bursts_are_delivered_once_per_delay() {
+        let (sender, source) = unbounded();
+        let watcher = EventWatcher::from_source(
+            source,
+            EventWatcherConfig {
+                max_delay: Duration::from_millis(300),
+                max_batch: 10_000,
+            },
+        );
+        let start = Instant::now();
+        for i in 0..20 {
+            sender.send(file_events(&format!("burst{i}_"), 5)).unwrap();
+        }
+        assert_eq!(
+            watcher
+                .recv_timeout(Duration::from_millis(100))
+                .unwrap_err(),
+            RecvTimeoutError::Timeout
+        );
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(start.elapsed() >= Duration::from_millis(300));
+        assert_eq!(batch.len(), 100);
+        assert!(watcher.try_recv().is_err());
+    }
+
+    #[test]
+    fn full_batches_are_delivered_without_waiting() {
+        let (sender, source) = unbounded();
+        let watcher = EventWatcher::from_source(
+            source,
+            EventWatcherConfig {
+                max_delay: Duration::from_secs(60),
+                max_batch: 100,
+            },
+        );
+        sender.send(file_events("a", 60)).unwrap();
+        assert!(watcher.recv_timeout(Duration::from_millis(100)).is_err());
+        sender.send(file_events("b", 60)).unwrap();
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 120);
+
+        sender.send(file_events("c", 10)).unwrap();
+        assert!(watcher.recv_timeout(Duration::from_millis(100)).is_err());
+        // Closing the source flushes whatever is still pending.
+        drop(sender);
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 10);
+    }
+
+    #[test]
+    fn history_done_is_never_delayed() {
+        let (sender, source) = unbounded();
+        let watcher = EventWatcher::from_source(
+            source,
+            EventWatcherConfig {
+                max_delay: Duration::from_secs(60),
+                max_batch: 10_000,
+            },
+        );
+        sender.send(file_events("a", 3)).unwrap();
+        sender
+            .send(vec![
+                event(
+                    "/tmp/a0",
+                    EventFlag::ItemModified | EventFlag::ItemIsFile,
+                    10,
+                ),
+                event("", EventFlag::HistoryDone, 11),
+            ])
+            .unwrap();
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        let paths: Vec<_> = batch.iter().map(|e| e.path.to_str().unwrap()).collect();
+        assert_eq!(paths, ["/tmp/a1", "/tmp/a2", "/tmp/a0", ""]);
+        assert_eq!(batch[2].id, 10);
+        assert_eq!(batch[3].flag, EventFlag::HistoryDone);
+    }
+
+    fn paused_watcher(queue_size: usize) -> (Sender<Vec<FsEvent>>, crate::EventStreamHandle) {
+        let (sender, source) = unbounded();
+        let handle = EventStreamBuilder::new(["/tmp/root"])
+            .queue_size(queue_size)
+            .batching(EventWatcherConfig {
+                max_delay: Duration::from_millis(10),
+                max_batch: 10_000,
+            })
+            .spawn_with_source(source);
+        handle.pause();
+        (sender, handle)
+    }
+
+    #[test]
+    fn paused_events_are_delivered_on_resume() {
+        let (sender, handle) = paused_watcher(100);
+        sender.send(file_events("a", 10)).unwrap();
+        sender
+            .send(vec![event("", EventFlag::HistoryDone, 10)])
+            .unwrap();
+        // Neither the delay nor the history marker flush while paused.
+        assert!(handle.recv_timeout(Duration::from_millis(200)).is_err());
+
+        handle.resume();
+        let batch = handle.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 11);
+        assert_eq!(batch[10].flag, EventFlag::HistoryDone);
+
+        // Resuming twice delivers nothing more.
+        handle.resume();
+        assert!(handle.recv_timeout(Duration::from_millis(100)).is_err());
+    }
+
+    #[test]
+    fn resume_delivers_buffered_events_before_later_ones() {
+        let (sender, handle) = paused_watcher(100);
+        sender.send(file_events("early", 3)).unwrap();
+        assert!(handle.recv_timeout(Duration::from_millis(100)).is_err());
+        handle.resume();
+        sender.send(file_events("late", 3)).unwrap();
+
+        let mut paths = Vec::new();
+        while paths.len() < 6 {
+            let batch = handle.recv_timeout(Duration::from_secs(5)).unwrap();
+            paths.extend(batch.into_iter().map(|e| e.path));
+        }
+        let names: Vec<_> = paths.iter().map(|path| path.to_str().unwrap()).collect();
+        assert_eq!(
+            names,
+            [
+                "/tmp/early0",
+                "/tmp/early1",
+                "/tmp/early2",
+                "/tmp/late0",
+                "/tmp/late1",
+                "/tmp/late2"
+            ]
+        );
+    }
+
+    #[test]
+    fn overflow_while_paused_asks_for_a_rescan() {
+        let (sender, handle) = paused_watcher(5);
+        sender.send(file_events("a", 4)).unwrap();
+        sender.send(file_events("b", 4)).unwrap();
+        sender
+            .send(vec![
+                event("/tmp/c", EventFlag::ItemIsFile | EventFlag::ItemCreated, 40),
+                event("", EventFlag::HistoryDone, 41),
+            ])
+            .unwrap();
+        handle.resume();
+
+        let batch = handle.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch[0].path, PathBuf::from("/tmp/root"));
+        assert!(batch[0].flag.contains(EventFlag::MustScanSubDirs));
+        assert_eq!(batch[0].flag.scan_type(), ScanType::Folder);
+        assert_eq!(batch[0].id, 40);
+        assert_eq!(batch[1].flag, EventFlag::HistoryDone);
+
+        // The next pause starts with an empty queue again.
+        handle.pause();
+        sender.send(file_events("d", 2)).unwrap();
+        handle.resume();
+        let batch = handle.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert!(!batch[0].flag.contains(EventFlag::MustScanSubDirs));
+    }
+
+    #[test]
+    fn batches_name_the_device_of_the_first_root() {
+        let (sender, watcher) = eager_watcher();
+        sender.send(file_events("a", 3)).unwrap();
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((batch.device, batch.stream_uuid), (0, [0; 16]));
+        assert!(!batch.history_done());
+
+        let root = std::env::temp_dir();
+        watcher.set_paths([root.to_str().unwrap()]);
+        sender
+            .send(vec![
+                event("/tmp/b", EventFlag::ItemCreated | EventFlag::ItemIsFile, 4),
+                event("/tmp/c", EventFlag::ItemCreated | EventFlag::ItemIsFile, 5),
+                event("", EventFlag::HistoryDone, 6),
+            ])
+            .unwrap();
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.device, crate::dev_of_path(&root).unwrap());
+        assert_ne!(batch.device, 0);
+        assert!(batch.history_done());
+    }
+
+    #[test]
+    fn overflow_rescans_the_current_roots() {
+        let (sender, handle) = paused_watcher(1);
+        handle.set_paths(["/tmp/other", "/tmp/more"]);
+        sender.send(file_events("a", 3)).unwrap();
+        handle.resume();
+        let batch = handle.recv_timeout(Duration::from_secs(5)).unwrap();
+        let roots: Vec<_> = batch.iter().map(|e| e.path.to_str().unwrap()).collect();
+        assert_eq!(roots, ["/tmp/other", "/tmp/more"]);
+    }
+
+    /// A watcher delivering every batch of 3 events right away.
+    fn eager_watcher() -> (Sender<Vec<FsEvent>>, EventWatcher) {
+        let (sender, source) = unbounded();
+        let watcher = EventWatcher::from_source(
+            source,
+            EventWatcherConfig {
+                max_delay: Duration::from_secs(60),
+                max_batch: 3,
+            },
+        );
+        (sender, watcher)
+    }
+
+    fn wait_for_batches(watcher: &EventWatcher, batches: usize) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while watcher.pending_len() < batches {
+            assert!(Instant::now() < deadline, "batches weren't delivered");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn clear_returns_the_number_of_dropped_events() {
+        let (sender, watcher) = eager_watcher();
+        assert_eq!(watcher.pending_len(), 0);
+        assert_eq!(watcher.clear(), 0);
+        for prefix in ["a", "b", "c"] {
+            sender.send(file_events(prefix, 3)).unwrap();
+        }
+        wait_for_batches(&watcher, 3);
+        assert_eq!(watcher.pending_len(), 3);
+        assert_eq!(watcher.clear(), 9);
+        assert_eq!(watcher.pending_len(), 0);
+
+        sender.send(file_events("d", 3)).unwrap();
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch[0].path, PathBuf::from("/tmp/d0"));
+    }
+
+    #[test]
+    fn drain_returns_the_pending_batches() {
+        let (sender, watcher) = eager_watcher();
+        assert!(watcher.drain().is_empty());
+        sender.send(file_events("a", 3)).unwrap();
+        sender.send(file_events("b", 3)).unwrap();
+        wait_for_batches(&watcher, 2);
+
+        let batches = watcher.drain();
+        let paths: Vec<Vec<&str>> = batches
+            .iter()
+            .map(|batch| batch.iter().map(|e| e.path.to_str().unwrap()).collect())
+            .collect();
+        assert_eq!(
+            paths,
+            [
+                ["/tmp/a0", "/tmp/a1", "/tmp/a2"],
+                ["/tmp/b0", "/tmp/b1", "/tmp/b2"],
+            ]
+        );
+        assert_eq!(watcher.pending_len(), 0);
+
+        sender.send(file_events("c", 3)).unwrap();
+        let batch = watcher.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(batch.len(), 3);
+    }
+
+    #[test]
+    fn coalesce_keeps_highest_priority_flag_and_latest_id() {
+        let events = vec![
+            event("/tmp/dir", EventFlag::ItemIsDir | EventFlag::ItemCreated, 1),
+            event(
+                "/tmp/file",
+                EventFlag::ItemIsFile | EventFlag::ItemCreated,
+                2,
+            ),
+            event("/tmp/dir", EventFlag::ItemInodeMetaMod, 3),
+            event(
+                "/tmp/file",
+                EventFlag::ItemIsFile | EventFlag::ItemRemoved,
+                4,
+            ),
+            event("/tmp", EventFlag::RootChanged, 5),
+            event("/tmp", EventFlag::ItemIsDir | EventFlag::ItemModified, 6),
+        ];
+        let coalesced = coalesce_events(events);
+        let summary: Vec<_> = coalesced
+            .iter()
+            .map(|e| (e.path.to_str().unwrap(), e.flag, e.id))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("/tmp/dir", EventFlag::ItemIsDir | EventFlag::ItemCreated, 3),
+                (
+                    "/tmp/file",
+                    EventFlag::ItemIsFile | EventFlag::ItemRemoved,
+                    4
+                ),
+                ("/tmp", EventFlag::RootChanged, 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn coalesce_keeps_the_order_of_the_last_change() {
+        // The file is created again after the directory is removed: its merged
+        // event must still come after the removal.
+        let events = vec![
+            event("/tmp/a", EventFlag::ItemIsFile | EventFlag::ItemModified, 1),
+            event("/tmp/d", EventFlag::ItemIsDir | EventFlag::ItemRemoved, 2),
+            event("/tmp/a", EventFlag::ItemIsFile | EventFlag::ItemCreated, 3),
+            event("", EventFlag::HistoryDone, 4),
+        ];
+        let coalesced = coalesce_events(events);
+        let order: Vec<_> = coalesced
+            .iter()
+            .map(|e| (e.path.to_str().unwrap(), e.id))
+            .collect();
+        assert_eq!(order, [("/tmp/d", 2), ("/tmp/a", 3), ("", 4)]);
+    }
+
+    #[test]
+    fn coalesce_never_merges_history_done() {
+        let events = vec![
+            event("", EventFlag::HistoryDone, 1),
+            event("", EventFlag::HistoryDone, 2),
+            event("", EventFlag::ItemIsFile | EventFlag::ItemModified, 3),
+        ];
+        let coalesced = coalesce_events(events);
+        assert_eq!(coalesced.len(), 3);
+    }
+}
@@ -1,34 +1,66 @@
 use bitflags::bitflags;
 bitflags! {
+    /// `kFSEventStreamEventFlag*`, with the values FSEvents gives them so the
+    /// fallback watcher of other platforms reports the same bits.
     #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct EventFlag: u32 {
-        const None = objc2_core_services::kFSEventStreamEventFlagNone;
-        const MustScanSubDirs = objc2_core_services::kFSEventStreamEventFlagMustScanSubDirs;
-        const UserDropped = objc2_core_services::kFSEventStreamEventFlagUserDropped;
-        const KernelDropped = objc2_core_services::kFSEventStreamEventFlagKernelDropped;
-        const EventIdsWrapped = objc2_core_services::kFSEventStreamEventFlagEventIdsWrapped;
-        const HistoryDone = objc2_core_services::kFSEventStreamEventFlagHistoryDone;
-        const RootChanged = objc2_core_services::kFSEventStreamEventFlagRootChanged;
-        const Mount = objc2_core_services::kFSEventStreamEventFlagMount;
-        const Unmount = objc2_core_services::kFSEventStreamEventFlagUnmount;
-        const ItemCreated = objc2_core_services::kFSEventStreamEventFlagItemCreated;
-        const ItemRemoved = objc2_core_services::kFSEventStreamEventFlagItemRemoved;
-        const ItemInodeMetaMod = objc2_core_services::kFSEventStreamEventFlagItemInodeMetaMod;
-        const ItemRenamed = objc2_core_services::kFSEventStreamEventFlagItemRenamed;
-        const ItemModified = objc2_core_services::kFSEventStreamEventFlagItemModified;
-        const ItemFinderInfoMod = objc2_core_services::kFSEventStreamEventFlagItemFinderInfoMod;
-        const ItemChangeOwner = objc2_core_services::kFSEventStreamEventFlagItemChangeOwner;
-        const ItemXattrMod = objc2_core_services::kFSEventStreamEventFlagItemXattrMod;
-        const ItemIsFile = objc2_core_services::kFSEventStreamEventFlagItemIsFile;
-        const ItemIsDir = objc2_core_services::kFSEventStreamEventFlagItemIsDir;
-        const ItemIsSymlink = objc2_core_services::kFSEventStreamEventFlagItemIsSymlink;
-        const OwnEvent = objc2_core_services::kFSEventStreamEventFlagOwnEvent;
-        const IsHardlink = objc2_core_services::kFSEventStreamEventFlagItemIsHardlink;
-        const IsLastHardlink = objc2_core_services::kFSEventStreamEventFlagItemIsLastHardlink;
-        const Cloned = objc2_core_services::kFSEventStreamEventFlagItemCloned;
+        const None = 0x0;
+        const MustScanSubDirs = 0x1;
+        const UserDropped = 0x2;
+        const KernelDropped = 0x4;
+        const EventIdsWrapped = 0x8;
+        const HistoryDone = 0x10;
+        const RootChanged = 0x20;
+        const Mount = 0x40;
+        const Unmount = 0x80;
+        const ItemCreated = 0x100;
+        const ItemRemoved = 0x200;
+        const ItemInodeMetaMod = 0x400;
+        const ItemRenamed = 0x800;
+        const ItemModified = 0x1000;
+        const ItemFinderInfoMod = 0x2000;
+        const ItemChangeOwner = 0x4000;
+        const ItemXattrMod = 0x8000;
+        const ItemIsFile = 0x10000;
+        const ItemIsDir = 0x20000;
+        const ItemIsSymlink = 0x40000;
+        const OwnEvent = 0x80000;
+        const IsHardlink = 0x100000;
+        const IsLastHardlink = 0x200000;
+        const Cloned = 0x400000;
     }
 }
 
+// The values above are FSEvents' own.
+#[cfg(target_os = "macos")]
+const _: () = {
+    use objc2_core_services::*;
+    assert!(EventFlag::None.bits() == kFSEventStreamEventFlagNone);
+    assert!(EventFlag::MustScanSubDirs.bits() == kFSEventStreamEventFlagMustScanSubDirs);
+    assert!(EventFlag::UserDropped.bits() == kFSEventStreamEventFlagUserDropped);
+    assert!(EventFlag::KernelDropped.bits() == kFSEventStreamEventFlagKernelDropped);
+    assert!(EventFlag::EventIdsWrapped.bits() == kFSEventStreamEventFlagEventIdsWrapped);
+    assert!(EventFlag::HistoryDone.bits() == kFSEventStreamEventFlagHistoryDone);
+    assert!(EventFlag::RootChanged.bits() == kFSEventStreamEventFlagRootChanged);
+    assert!(EventFlag::Mount.bits() == kFSEventStreamEventFlagMount);
+    assert!(EventFlag::Unmount.bits() == kFSEventStreamEventFlagUnmount);
+    assert!(EventFlag::ItemCreated.bits() == kFSEventStreamEventFlagItemCreated);
+    assert!(EventFlag::ItemRemoved.bits() == kFSEventStreamEventFlagItemRemoved);
+    assert!(EventFlag::ItemInodeMetaMod.bits() == kFSEventStreamEventFlagItemInodeMetaMod);
+    assert!(EventFlag::ItemRenamed.bits() == kFSEventStreamEventFlagItemRenamed);
+    assert!(EventFlag::ItemModified.bits() == kFSEventStreamEventFlagItemModified);
+    assert!(EventFlag::ItemFinderInfoMod.bits() == kFSEventStreamEventFlagItemFinderInfoMod);
+    assert!(EventFlag::ItemChangeOwner.bits() == kFSEventStreamEventFlagItemChangeOwner);
+    assert!(EventFlag::ItemXattrMod.bits() == kFSEventStreamEventFlagItemXattrMod);
+    assert!(EventFlag::ItemIsFile.bits() == kFSEventStreamEventFlagItemIsFile);
+    assert!(EventFlag::ItemIsDir.bits() == kFSEventStreamEventFlagItemIsDir);
+    assert!(EventFlag::ItemIsSymlink.bits() == kFSEventStreamEventFlagItemIsSymlink);
+    assert!(EventFlag::OwnEvent.bits() == kFSEventStreamEventFlagOwnEvent);
+    assert!(EventFlag::IsHardlink.bits() == kFSEventStreamEventFlagItemIsHardlink);
+    assert!(EventFlag::IsLastHardlink.bits() == kFSEventStreamEventFlagItemIsLastHardlink);
+    assert!(EventFlag::Cloned.bits() == kFSEventStreamEventFlagItemCloned);
+};
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum EventType {
     Unknown,
@@ -51,6 +83,13 @@ pub enum ScanType {
     Nop,
 }
 
+/// A volume appearing or disappearing at the event path, see [`EventFlag::volume_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeChange {
+    Mounted,
+    Unmounted,
+}
+
 impl EventFlag {
     pub fn event_type(&self) -> EventType {
         if self.contains(EventFlag::IsHardlink) | self.contains(EventFlag::IsLastHardlink) {
@@ -66,6 +105,17 @@ impl EventFlag {
         }
     }
 
+    /// Whether the event reports a volume mounted or unmounted at its path.
+    pub fn volume_change(&self) -> Option<VolumeChange> {
+        if self.contains(EventFlag::Unmount) {
+            Some(VolumeChange::Unmounted)
+        } else if self.contains(EventFlag::Mount) {
+            Some(VolumeChange::Mounted)
+        } else {
+            None
+        }
+    }
+
     pub fn scan_type(&self) -> ScanType {
         let event_type = self.event_type();
         let is_dir = matches!(event_type, EventType::Dir);
@@ -73,6 +123,13 @@ impl EventFlag {
             ScanType::Nop
         } else if self.contains(EventFlag::RootChanged) {
             ScanType::ReScan
+        } else if self.contains(EventFlag::Unmount) {
+            // The files are still there, just not reachable: scanning the mount
+            // point would report everything below it as removed.
+            ScanType::Nop
+        } else if self.contains(EventFlag::Mount) {
+            // Catch up on what changed while the volume was detached.
+            ScanType::Folder
         } else {
             // Strange event, doesn't know when it happens, processing it using a generic way
             // e.g. new event: fs_event=FsEvent { path: "/.docid/16777229/changed/782/src=0,dst=41985052", flag: kFSEventStreamEventFlagNone, id: 471533015 }
@@ -142,6 +199,25 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_volume_change() {
+        assert_eq!(
+            EventFlag::Mount.volume_change(),
+            Some(VolumeChange::Mounted)
+        );
+        assert_eq!(
+            EventFlag::Unmount.volume_change(),
+            Some(VolumeChange::Unmounted)
+        );
+        assert_eq!(
+            (EventFlag::ItemRemoved | EventFlag::ItemIsDir).volume_change(),
+            None
+        );
+        // Unmounting must not look like the mount point was deleted.
+        assert_eq!(EventFlag::Unmount.scan_type(), ScanType::Nop);
+        assert_eq!(EventFlag::Mount.scan_type(), ScanType::Folder);
+    }
+
     #[test]
     fn test_scan_type_must_scan_subdirs() {
         // MustScanSubDirs => Folder
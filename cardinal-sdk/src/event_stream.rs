@@ -1,198 +1,313 @@
-use crate::FsEvent;
-use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
-use dispatch2::{DispatchQueue, DispatchQueueAttr, DispatchRetained};
-use libc::dev_t;
-use objc2_core_foundation::{CFArray, CFString, CFTimeInterval};
-use objc2_core_services::{
-    ConstFSEventStreamRef, FSEventStreamContext, FSEventStreamCreate, FSEventStreamEventFlags,
-    FSEventStreamEventId, FSEventStreamGetDeviceBeingWatched, FSEventStreamInvalidate,
-    FSEventStreamRef, FSEventStreamRelease, FSEventStreamSetDispatchQueue, FSEventStreamStart,
-    FSEventStreamStop, kFSEventStreamCreateFlagFileEvents, kFSEventStreamCreateFlagNoDefer,
-    kFSEventStreamCreateFlagWatchRoot,
+use crate::{
+    EventBatch, EventStream, FSEventStreamEventId, FsEvent,
+    event_batcher::{BatcherControl, EventWatcherConfig, run_batcher},
+    utils::current_event_id,
 };
+use bitflags::bitflags;
+use crossbeam_channel::{Receiver, Sender, bounded, never, unbounded};
+use libc::dev_t;
 use std::{
-    ffi::c_void,
     ops::{Deref, DerefMut},
-    ptr::NonNull,
-    slice,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
 };
 
-type EventsCallback = Box<dyn FnMut(Vec<FsEvent>) + Send>;
+pub(crate) type EventsCallback = Box<dyn FnMut(Vec<FsEvent>) + Send>;
 
-pub struct EventStream {
-    stream: FSEventStreamRef,
-}
+/// `kFSEventStreamEventIdSinceNow`: only deliver events that happen after the stream starts.
+pub const EVENT_ID_SINCE_NOW: FSEventStreamEventId = u64::MAX;
 
-unsafe impl Send for EventStream {}
+bitflags! {
+    /// The `kFSEventStreamCreateFlag*` options a stream is created with.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct StreamFlags: u32 {
+        const NoDefer = 0x2;
+        const WatchRoot = 0x4;
+        /// Don't report changes made by this process. FSEvents only.
+        const IgnoreSelf = 0x8;
+        const FileEvents = 0x10;
+        /// Tag changes made by this process with `EventFlag::OwnEvent`. FSEvents only.
+        const MarkSelf = 0x20;
+    }
+}
 
-impl Drop for EventStream {
-    fn drop(&mut self) {
-        unsafe {
-            FSEventStreamRelease(self.stream);
-        }
+impl Default for StreamFlags {
+    fn default() -> Self {
+        StreamFlags::NoDefer | StreamFlags::FileEvents | StreamFlags::WatchRoot
     }
 }
 
-impl EventStream {
-    pub fn new(
-        paths: &[&str],
-        since_event_id: FSEventStreamEventId,
-        latency: CFTimeInterval,
-        callback: EventsCallback,
-    ) -> Self {
-        unsafe extern "C-unwind" fn drop_callback(info: *const c_void) {
-            let _cb: Box<EventsCallback> = unsafe { Box::from_raw(info as _) };
-        }
+/// Configures and starts an event stream whose events reach an
+/// [`EventStreamHandle`]: FSEvents on macOS, inotify on Linux (see
+/// [`EventStream`] for what the latter can't do).
+#[derive(Debug, Clone)]
+pub struct EventStreamBuilder {
+    paths: Vec<String>,
+    since: FSEventStreamEventId,
+    latency: f64,
+    flags: StreamFlags,
+    queue_size: usize,
+    batching: EventWatcherConfig,
+}
 
-        unsafe extern "C-unwind" fn raw_callback(
-            _stream: ConstFSEventStreamRef, // ConstFSEventStreamRef streamRef
-            callback_info: *mut c_void,     // void *clientCallBackInfo
-            num_events: usize,              // size_t numEvents
-            event_paths: NonNull<c_void>,   // void *eventPaths
-            event_flags: NonNull<FSEventStreamEventFlags>, // const FSEventStreamEventFlags eventFlags[]
-            event_ids: NonNull<FSEventStreamEventId>,      // const FSEventStreamEventId eventIds[]
-        ) {
-            let event_paths = unsafe {
-                slice::from_raw_parts(event_paths.as_ptr() as *const *const i8, num_events)
-            };
-            let event_flags = unsafe { slice::from_raw_parts(event_flags.as_ptr(), num_events) };
-            let event_ids = unsafe { slice::from_raw_parts(event_ids.as_ptr(), num_events) };
-            let events: Vec<_> = event_paths
-                .iter()
-                .zip(event_flags)
-                .zip(event_ids)
-                .map(|((&path, &flag), &id)| unsafe { FsEvent::from_raw(path, flag, id) })
-                .collect();
-
-            let callback = unsafe { (callback_info as *mut EventsCallback).as_mut() }.unwrap();
-            callback(events);
+impl EventStreamBuilder {
+    /// Watch `paths`, by default from now on, with a 0.1s latency and the
+    /// default [`StreamFlags`].
+    pub fn new<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        Self {
+            paths: paths.into_iter().map(Into::into).collect(),
+            since: EVENT_ID_SINCE_NOW,
+            latency: 0.1,
+            flags: StreamFlags::default(),
+            queue_size: 100_000,
+            batching: EventWatcherConfig::default(),
         }
+    }
 
-        let paths: Vec<_> = paths.iter().map(|&x| CFString::from_str(x)).collect();
-        let paths = CFArray::from_retained_objects(&paths);
-        let mut context = FSEventStreamContext {
-            version: 0,
-            info: Box::leak(Box::new(callback)) as *mut _ as *mut _,
-            retain: None,
-            release: Some(drop_callback),
-            copyDescription: None,
-        };
+    /// Replay the events after `event_id` before the live ones.
+    pub fn since(mut self, event_id: FSEventStreamEventId) -> Self {
+        self.since = event_id;
+        self
+    }
 
-        let stream: FSEventStreamRef = unsafe {
-            FSEventStreamCreate(
-                None,
-                Some(raw_callback),
-                &mut context,
-                paths.as_opaque(),
-                since_event_id,
-                latency,
-                kFSEventStreamCreateFlagNoDefer
-                    | kFSEventStreamCreateFlagFileEvents
-                    | kFSEventStreamCreateFlagWatchRoot,
-            )
-        };
-        Self { stream }
-    }
-
-    // Start the FSEventStream with a dispatch queue.
-    pub fn spawn(self) -> Option<EventStreamWithQueue> {
-        let queue = DispatchQueue::new("cardinal-sdk-queue", DispatchQueueAttr::SERIAL);
-        unsafe { FSEventStreamSetDispatchQueue(self.stream, Some(&queue)) };
-        let result = unsafe { FSEventStreamStart(self.stream) };
-        if !result {
-            unsafe { FSEventStreamStop(self.stream) };
-            unsafe { FSEventStreamInvalidate(self.stream) };
-            return None;
-        }
-        let stream = self.stream;
-        Some(EventStreamWithQueue { stream, queue })
+    /// Seconds the stream waits to group changes before calling back.
+    pub fn latency(mut self, seconds: f64) -> Self {
+        self.latency = seconds;
+        self
     }
 
-    // Get device id being watched by this event stream.
-    pub fn dev(&self) -> dev_t {
-        unsafe { FSEventStreamGetDeviceBeingWatched(self.stream.cast_const()) }
+    pub fn flags(mut self, flags: StreamFlags) -> Self {
+        self.flags = flags;
+        self
     }
-}
 
-/// FSEventStream with dispatch queue.
-///
-/// Dropping this struct will stop the FSEventStream and release the dispatch queue.
-pub struct EventStreamWithQueue {
-    stream: FSEventStreamRef,
-    #[allow(dead_code)]
-    queue: DispatchRetained<DispatchQueue>,
-}
+    /// How many events are kept while the handle is paused. Beyond that they
+    /// are dropped and resuming reports a rescan of the watched paths instead.
+    pub fn queue_size(mut self, events: usize) -> Self {
+        self.queue_size = events;
+        self
+    }
 
-impl Drop for EventStreamWithQueue {
-    fn drop(&mut self) {
-        unsafe {
-            FSEventStreamStop(self.stream);
-            FSEventStreamInvalidate(self.stream);
-        }
+    /// How callbacks are grouped before they reach the receiver.
+    pub fn batching(mut self, config: EventWatcherConfig) -> Self {
+        self.batching = config;
+        self
+    }
+
+    /// Start the stream, returning the device of the first watched path and
+    /// the handle receiving its events.
+    pub fn spawn(self) -> (dev_t, EventStreamHandle) {
+        let (raw_sender, raw_receiver) = unbounded();
+        let last_event_id = Arc::new(AtomicU64::new(if self.since == EVENT_ID_SINCE_NOW {
+            current_event_id()
+        } else {
+            self.since
+        }));
+        let new_stream = {
+            let last_event_id = last_event_id.clone();
+            let (latency, flags) = (self.latency, self.flags);
+            move |paths: &[String]| {
+                let raw_sender = raw_sender.clone();
+                let callback_event_id = last_event_id.clone();
+                let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+                EventStream::with_flags(
+                    &paths,
+                    last_event_id.load(Ordering::Acquire),
+                    latency,
+                    flags,
+                    Box::new(move |events: Vec<FsEvent>| {
+                        if let Some(id) = events.iter().map(|event| event.id).max() {
+                            callback_event_id.fetch_max(id, Ordering::AcqRel);
+                        }
+                        let _ = raw_sender.send(events);
+                    }),
+                )
+            }
+        };
+        let stream = new_stream(&self.paths);
+        let dev = stream.dev();
+        let (stream_commands, stream_commands_rx) = unbounded::<Vec<String>>();
+        std::thread::Builder::new()
+            .name("cardinal-sdk-event-watcher".to_string())
+            .spawn(move || {
+                let mut running = stream.spawn().expect("failed to spawn event stream");
+                // Each path change restarts the stream from the last event seen, so
+                // nothing is missed in between. Disconnection stops it.
+                while let Ok(paths) = stream_commands_rx.recv() {
+                    drop(running);
+                    running = new_stream(&paths)
+                        .spawn()
+                        .expect("failed to spawn event stream");
+                }
+                drop(running);
+            })
+            .unwrap();
+        let mut handle =
+            EventStreamHandle::start(raw_receiver, self.paths, self.queue_size, self.batching);
+        handle.stream_commands = Some(stream_commands);
+        (dev, handle)
+    }
+
+    /// Deliver the batches of `source` through the handle instead of an
+    /// FSEventStream, e.g. to replay recorded events.
+    pub fn spawn_with_source(self, source: Receiver<Vec<FsEvent>>) -> EventStreamHandle {
+        EventStreamHandle::start(source, self.paths, self.queue_size, self.batching)
     }
 }
 
-pub struct EventWatcher {
-    receiver: Receiver<Vec<FsEvent>>,
-    _cancellation_token: Sender<()>,
+/// Receiving end of an event stream, deref to the [`EventBatch`] receiver.
+///
+/// Dropping it stops the stream.
+pub struct EventStreamHandle {
+    receiver: Receiver<EventBatch>,
+    control: Sender<BatcherControl>,
+    /// New path sets for the stream thread, `None` when the events don't come from FSEvents.
+    stream_commands: Option<Sender<Vec<String>>>,
 }
 
-impl Deref for EventWatcher {
-    type Target = Receiver<Vec<FsEvent>>;
+/// The handle most callers know; kept as the name of watchers started with [`EventWatcher::spawn`].
+pub type EventWatcher = EventStreamHandle;
+
+impl Deref for EventStreamHandle {
+    type Target = Receiver<EventBatch>;
 
     fn deref(&self) -> &Self::Target {
         &self.receiver
     }
 }
 
-impl DerefMut for EventWatcher {
+impl DerefMut for EventStreamHandle {
     fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.receiver
     }
 }
 
-impl EventWatcher {
+impl EventStreamHandle {
+    fn start(
+        source: Receiver<Vec<FsEvent>>,
+        paths: Vec<String>,
+        queue_size: usize,
+        config: EventWatcherConfig,
+    ) -> Self {
+        let (control, control_rx) = bounded(0);
+        let (sender, receiver) = unbounded();
+        let roots = paths.into_iter().map(PathBuf::from).collect();
+        std::thread::Builder::new()
+            .name("cardinal-sdk-event-batcher".to_string())
+            .spawn(move || run_batcher(source, sender, control_rx, roots, queue_size, config))
+            .unwrap();
+        Self {
+            receiver,
+            control,
+            stream_commands: None,
+        }
+    }
+
+    /// A handle that never delivers a batch, and never reports the stream
+    /// closed either, e.g. in place of a stopped stream.
     pub fn noop() -> Self {
         Self {
-            receiver: unbounded().1,
-            _cancellation_token: bounded::<()>(1).0,
+            receiver: never(),
+            control: bounded(0).0,
+            stream_commands: None,
         }
     }
 
+    /// Watch `path` with the default [`EventWatcherConfig`].
     pub fn spawn(
         path: String,
         since_event_id: FSEventStreamEventId,
         latency: f64,
     ) -> (dev_t, EventWatcher) {
-        let (_cancellation_token, cancellation_token_rx) = bounded::<()>(1);
-        let (sender, receiver) = unbounded();
-        let stream = EventStream::new(
-            &[&path],
-            since_event_id,
-            latency,
-            Box::new(move |events| {
-                let _ = sender.send(events);
-            }),
-        );
-        let dev = stream.dev();
-        std::thread::Builder::new()
-            .name("cardinal-sdk-event-watcher".to_string())
-            .spawn(move || {
-                let _stream_and_queue = stream.spawn().expect("failed to spawn event stream");
-                let _ = cancellation_token_rx.recv();
-            })
-            .unwrap();
-        (
-            dev,
-            EventWatcher {
-                receiver,
-                _cancellation_token,
-            },
-        )
+        Self::spawn_with_config(path, since_event_id, latency, EventWatcherConfig::default())
+    }
+
+    /// Watch `path`, batching FSEvents callbacks as described by `config`.
+    ///
+    /// `latency` is still passed to the FSEventStream; `config` controls how the
+    /// resulting callbacks are grouped before they reach the receiver.
+    pub fn spawn_with_config(
+        path: String,
+        since_event_id: FSEventStreamEventId,
+        latency: f64,
+        config: EventWatcherConfig,
+    ) -> (dev_t, EventWatcher) {
+        EventStreamBuilder::new([path])
+            .since(since_event_id)
+            .latency(latency)
+            .batching(config)
+            .spawn()
+    }
+
+    /// Batch events from an arbitrary source instead of an FSEventStream.
+    pub fn from_source(source: Receiver<Vec<FsEvent>>, config: EventWatcherConfig) -> Self {
+        EventStreamBuilder::new(Vec::<String>::new())
+            .batching(config)
+            .spawn_with_source(source)
+    }
+
+    /// Hold events back, e.g. while the consumer rebuilds its index. They are
+    /// delivered on [`Self::resume`], or replaced by a rescan of the watched
+    /// paths when more than the queue size arrived in between.
+    pub fn pause(&self) {
+        let _ = self.control.send(BatcherControl::Pause);
+    }
+
+    pub fn resume(&self) {
+        let _ = self.control.send(BatcherControl::Resume);
+    }
+
+    /// Drop the batches delivered but not received yet, e.g. before a rescan
+    /// makes them moot, returning how many events they held. Events the
+    /// batcher still holds back aren't affected.
+    pub fn clear(&self) -> usize {
+        self.receiver.try_iter().map(|batch| batch.len()).sum()
+    }
+
+    /// Take the batches delivered but not received yet, in order, leaving the
+    /// receiver empty.
+    pub fn drain(&self) -> Vec<EventBatch> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// Number of batches waiting to be received, cheap enough to poll.
+    pub fn pending_len(&self) -> usize {
+        self.receiver.len()
+    }
+
+    /// Watch `paths` instead. The stream is restarted from the last event it
+    /// delivered, so its history is replayed (ending with a `HistoryDone`).
+    /// The inotify fallback has no history, it only starts over. Returns once
+    /// the batcher stamps batches with the new origin.
+    pub fn set_paths<I, P>(&self, paths: I)
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<String>,
+    {
+        let paths: Vec<String> = paths.into_iter().map(Into::into).collect();
+        let roots = paths.iter().map(PathBuf::from).collect();
+        let (applied, applied_rx) = bounded(0);
+        if self
+            .control
+            .send(BatcherControl::SetRoots(roots, applied))
+            .is_ok()
+        {
+            let _ = applied_rx.recv();
+        }
+        if let Some(stream_commands) = &self.stream_commands {
+            let _ = stream_commands.send(paths);
+        }
     }
 }
 
-#[cfg(all(test, target_os = "macos"))]
+#[cfg(test)]
 mod tests {
     use super::*;
     use crate::utils::current_event_id;
@@ -250,4 +365,85 @@ mod tests {
             "respawned watcher failed to deliver file change event"
         );
     }
+
+    #[test]
+    #[cfg(target_os = "macos")]
+    fn paused_stream_delivers_changes_on_resume() {
+        let temp_dir = tempdir().expect("failed to create tempdir");
+        let watched_root = temp_dir
+            .path()
+            .canonicalize()
+            .expect("failed to canonicalize");
+        let watch_path = watched_root.to_str().expect("tempdir path should be utf8");
+
+        let (_, handle) = EventStreamBuilder::new([watch_path])
+            .latency(0.05)
+            .flags(StreamFlags::default() | StreamFlags::MarkSelf)
+            .spawn();
+        std::thread::sleep(Duration::from_millis(500));
+        handle.pause();
+
+        let created_file = watched_root.join("paused_event.txt");
+        std::fs::write(&created_file, "cardinal").expect("failed to write test file");
+        assert_eq!(
+            handle.recv_timeout(Duration::from_secs(1)).unwrap_err(),
+            RecvTimeoutError::Timeout,
+            "events were delivered while paused"
+        );
+
+        handle.resume();
+        let batch = handle
+            .recv_timeout(Duration::from_secs(5))
+            .expect("buffered events should follow resume");
+        let event = batch
+            .iter()
+            .find(|event| event.path.starts_with(&created_file))
+            .expect("the paused change is delivered");
+        assert!(event.flag.contains(crate::EventFlag::OwnEvent));
+    }
+
+    #[test]
+    fn batches_carry_the_stream_origin() {
+        let temp_dir = tempdir().expect("failed to create tempdir");
+        let watched_root = temp_dir
+            .path()
+            .canonicalize()
+            .expect("failed to canonicalize");
+        let watch_path = watched_root.to_str().expect("tempdir path should be utf8");
+
+        let (_, handle) = EventStreamBuilder::new([watch_path])
+            .latency(0.05)
+            .batching(EventWatcherConfig {
+                max_delay: Duration::from_millis(10),
+                max_batch: 10_000,
+            })
+            .spawn();
+        std::thread::sleep(Duration::from_millis(500));
+
+        let mut origins = Vec::new();
+        for name in ["first.txt", "second.txt"] {
+            let created_file = watched_root.join(name);
+            std::fs::write(&created_file, "cardinal").expect("failed to write test file");
+            let deadline = Instant::now() + Duration::from_secs(5);
+            loop {
+                assert!(Instant::now() < deadline, "{name} wasn't delivered");
+                let Ok(batch) = handle.recv_timeout(Duration::from_millis(200)) else {
+                    continue;
+                };
+                origins.push((batch.device, batch.stream_uuid));
+                if batch
+                    .iter()
+                    .any(|event| event.path.starts_with(&created_file))
+                {
+                    break;
+                }
+            }
+        }
+
+        let device = crate::dev_of_path(&watched_root).unwrap();
+        assert_ne!(device, 0);
+        let uuid = crate::event_stream_uuid(device).unwrap_or_default();
+        assert!(origins.len() >= 2);
+        assert!(origins.iter().all(|&origin| origin == (device, uuid)));
+    }
 }
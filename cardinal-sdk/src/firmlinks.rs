@@ -0,0 +1,259 @@
+use std::{
+    borrow::Cow,
+    fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+/// Where macOS lists the firmlinks of the system volume.
+pub const FIRMLINKS_TABLE: &str = "/usr/share/firmlinks";
+/// Mount point of the data volume the firmlinks point into.
+pub const DATA_VOLUME: &str = "/System/Volumes/Data";
+
+/// A folder reachable from two paths: `canonical` on the read-only system
+/// volume (`/Users`) and `shadow` on the data volume
+/// (`/System/Volumes/Data/Users`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Firmlink {
+    pub canonical: PathBuf,
+    pub shadow: PathBuf,
+}
+
+/// Firmlinks of the system volume. Walking `/` reaches every firmlinked
+/// folder twice, once through each side; the canonical side is the one kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Firmlinks {
+    links: Vec<Firmlink>,
+}
+
+impl Firmlinks {
+    /// Parse a firmlink table: one `/canonical<TAB>relative` line per link,
+    /// `relative` being the shadow's path inside `data_volume`. Malformed
+    /// lines are skipped.
+    pub fn parse(table: &str, data_volume: &Path) -> Self {
+        let links = table
+            .lines()
+            .filter_map(|line| {
+                let (canonical, relative) = line.split_once('\t')?;
+                let (canonical, relative) = (canonical.trim(), relative.trim());
+                let canonical = Path::new(canonical);
+                if !canonical.is_absolute() || relative.is_empty() {
+                    return None;
+                }
+                Some(Firmlink {
+                    canonical: canonical.to_path_buf(),
+                    shadow: data_volume.join(relative.trim_start_matches('/')),
+                })
+            })
+            .collect();
+        Self { links }
+    }
+
+    /// [`FIRMLINKS_TABLE`], read once. Empty when it can't be read, e.g.
+    /// before Catalina.
+    pub fn system() -> &'static Self {
+        static SYSTEM: OnceLock<Firmlinks> = OnceLock::new();
+        SYSTEM.get_or_init(|| match fs::read_to_string(FIRMLINKS_TABLE) {
+            Ok(table) => Self::parse(&table, Path::new(DATA_VOLUME)),
+            Err(_) => Self::default(),
+        })
+    }
+
+    pub fn links(&self) -> &[Firmlink] {
+        &self.links
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.is_empty()
+    }
+
+    /// The links a walk of `root` reaches through both sides.
+    pub fn within(&self, root: &Path) -> Self {
+        let links = self
+            .links
+            .iter()
+            .filter(|link| link.canonical.starts_with(root) && link.shadow.starts_with(root))
+            .cloned()
+            .collect();
+        Self { links }
+    }
+
+    /// `ignore_paths` plus the shadow of every link that none of them covers
+    /// already.
+    pub fn ignoring_shadows(&self, mut ignore_paths: Vec<PathBuf>) -> Vec<PathBuf> {
+        for link in &self.links {
+            if !ignore_paths
+                .iter()
+                .any(|ignored| link.shadow.starts_with(ignored))
+            {
+                ignore_paths.push(link.shadow.clone());
+            }
+        }
+        ignore_paths
+    }
+
+    /// The links whose shadow `ignore_paths` covers: what a walk skipping
+    /// them only sees through the canonical side.
+    pub fn skipped_by(&self, ignore_paths: &[PathBuf]) -> Self {
+        let links = self
+            .links
+            .iter()
+            .filter(|link| {
+                ignore_paths
+                    .iter()
+                    .any(|ignored| link.shadow.starts_with(ignored))
+            })
+            .cloned()
+            .collect();
+        Self { links }
+    }
+
+    /// `path` through the canonical side when it goes through a shadow:
+    /// `/System/Volumes/Data/Users/me` becomes `/Users/me`.
+    pub fn normalize<'p>(&self, path: &'p Path) -> Cow<'p, Path> {
+        for link in &self.links {
+            if let Ok(rest) = path.strip_prefix(&link.shadow) {
+                return Cow::Owned(if rest.as_os_str().is_empty() {
+                    link.canonical.clone()
+                } else {
+                    link.canonical.join(rest)
+                });
+            }
+        }
+        Cow::Borrowed(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TABLE: &str = "/Users\tUsers\n\
+        /Applications\tApplications\n\
+        /usr/local\tusr/local\n\
+        \n\
+        malformed line\n\
+        relative\tpath\n";
+
+    fn firmlinks() -> Firmlinks {
+        Firmlinks::parse(TABLE, Path::new(DATA_VOLUME))
+    }
+
+    fn paths(paths: &[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn test_parse_skips_malformed_lines() {
+        let firmlinks = firmlinks();
+        assert_eq!(
+            firmlinks.links(),
+            [
+                Firmlink {
+                    canonical: PathBuf::from("/Users"),
+                    shadow: PathBuf::from("/System/Volumes/Data/Users"),
+                },
+                Firmlink {
+                    canonical: PathBuf::from("/Applications"),
+                    shadow: PathBuf::from("/System/Volumes/Data/Applications"),
+                },
+                Firmlink {
+                    canonical: PathBuf::from("/usr/local"),
+                    shadow: PathBuf::from("/System/Volumes/Data/usr/local"),
+                },
+            ]
+        );
+        assert!(Firmlinks::parse("", Path::new(DATA_VOLUME)).is_empty());
+    }
+
+    #[test]
+    fn test_normalize() {
+        let firmlinks = firmlinks();
+        let normalize = |path: &str| firmlinks.normalize(Path::new(path)).into_owned();
+        assert_eq!(
+            normalize("/System/Volumes/Data/Users/me/a.txt"),
+            Path::new("/Users/me/a.txt")
+        );
+        assert_eq!(
+            normalize("/System/Volumes/Data/usr/local/bin"),
+            Path::new("/usr/local/bin")
+        );
+        assert_eq!(normalize("/System/Volumes/Data/Users"), Path::new("/Users"));
+        // Components are compared whole, other data volume paths stay.
+        assert_eq!(
+            normalize("/System/Volumes/Data/UsersBackup"),
+            Path::new("/System/Volumes/Data/UsersBackup")
+        );
+        assert_eq!(
+            normalize("/System/Volumes/Data/private/tmp"),
+            Path::new("/System/Volumes/Data/private/tmp")
+        );
+        assert!(matches!(
+            firmlinks.normalize(Path::new("/Users/me")),
+            Cow::Borrowed(_)
+        ));
+    }
+
+    #[test]
+    fn test_within_keeps_links_walked_twice() {
+        let firmlinks = firmlinks();
+        assert_eq!(firmlinks.within(Path::new("/")), firmlinks);
+        // Only one side is walked, nothing is duplicated.
+        assert!(firmlinks.within(Path::new("/Users")).is_empty());
+        assert!(firmlinks.within(Path::new(DATA_VOLUME)).is_empty());
+    }
+
+    #[test]
+    fn test_shadows_join_the_ignore_paths() {
+        let firmlinks = firmlinks();
+        assert_eq!(
+            firmlinks.ignoring_shadows(Vec::new()),
+            paths(&[
+                "/System/Volumes/Data/Users",
+                "/System/Volumes/Data/Applications",
+                "/System/Volumes/Data/usr/local",
+            ])
+        );
+        // Already covered shadows aren't added again.
+        assert_eq!(
+            firmlinks.ignoring_shadows(paths(&[DATA_VOLUME])),
+            paths(&[DATA_VOLUME])
+        );
+        assert_eq!(
+            firmlinks.ignoring_shadows(paths(&["/System/Volumes/Data/usr", "/Volumes"])),
+            paths(&[
+                "/System/Volumes/Data/usr",
+                "/Volumes",
+                "/System/Volumes/Data/Users",
+                "/System/Volumes/Data/Applications",
+            ])
+        );
+    }
+
+    #[test]
+    fn test_skipped_by() {
+        let firmlinks = firmlinks();
+        assert_eq!(firmlinks.skipped_by(&paths(&[DATA_VOLUME])), firmlinks);
+        assert!(firmlinks.skipped_by(&[]).is_empty());
+        let skipped = firmlinks.skipped_by(&paths(&["/System/Volumes/Data/Users"]));
+        assert_eq!(skipped.links().len(), 1);
+        assert_eq!(skipped.links()[0].canonical, Path::new("/Users"));
+    }
+
+    #[test]
+    #[ignore = "reads the firmlinks of the running system"]
+    fn test_system_firmlinks() {
+        let firmlinks = Firmlinks::system();
+        assert!(
+            firmlinks
+                .links()
+                .iter()
+                .any(|link| link.canonical == Path::new("/Users"))
+        );
+        assert_eq!(
+            firmlinks.normalize(Path::new("/System/Volumes/Data/Users")),
+            Path::new("/Users")
+        );
+        assert!(Path::new("/System/Volumes/Data/Users").is_dir());
+    }
+}
@@ -0,0 +1,153 @@
+//! The FSEvents backend of [`crate::EventStream`].
+
+use crate::{FsEvent, StreamFlags, event_stream::EventsCallback};
+use dispatch2::{DispatchQueue, DispatchQueueAttr, DispatchRetained};
+use libc::dev_t;
+use objc2_core_foundation::{CFArray, CFString, CFTimeInterval};
+use objc2_core_services::{
+    ConstFSEventStreamRef, FSEventStreamContext, FSEventStreamCreate, FSEventStreamEventFlags,
+    FSEventStreamEventId, FSEventStreamGetDeviceBeingWatched, FSEventStreamInvalidate,
+    FSEventStreamRef, FSEventStreamRelease, FSEventStreamSetDispatchQueue, FSEventStreamStart,
+    FSEventStreamStop, kFSEventStreamCreateFlagFileEvents, kFSEventStreamCreateFlagIgnoreSelf,
+    kFSEventStreamCreateFlagMarkSelf, kFSEventStreamCreateFlagNoDefer,
+    kFSEventStreamCreateFlagWatchRoot, kFSEventStreamEventIdSinceNow,
+};
+use std::{ffi::c_void, ptr::NonNull, slice};
+
+// The values of `StreamFlags` and `EVENT_ID_SINCE_NOW` are FSEvents' own.
+const _: () = {
+    assert!(StreamFlags::NoDefer.bits() == kFSEventStreamCreateFlagNoDefer);
+    assert!(StreamFlags::WatchRoot.bits() == kFSEventStreamCreateFlagWatchRoot);
+    assert!(StreamFlags::IgnoreSelf.bits() == kFSEventStreamCreateFlagIgnoreSelf);
+    assert!(StreamFlags::FileEvents.bits() == kFSEventStreamCreateFlagFileEvents);
+    assert!(StreamFlags::MarkSelf.bits() == kFSEventStreamCreateFlagMarkSelf);
+    assert!(crate::EVENT_ID_SINCE_NOW == kFSEventStreamEventIdSinceNow);
+};
+
+pub struct EventStream {
+    stream: FSEventStreamRef,
+}
+
+unsafe impl Send for EventStream {}
+
+impl Drop for EventStream {
+    fn drop(&mut self) {
+        unsafe {
+            FSEventStreamRelease(self.stream);
+        }
+    }
+}
+
+impl EventStream {
+    /// Create a stream with the default [`StreamFlags`].
+    pub fn new(
+        paths: &[&str],
+        since_event_id: FSEventStreamEventId,
+        latency: CFTimeInterval,
+        callback: EventsCallback,
+    ) -> Self {
+        Self::with_flags(
+            paths,
+            since_event_id,
+            latency,
+            StreamFlags::default(),
+            callback,
+        )
+    }
+
+    pub fn with_flags(
+        paths: &[&str],
+        since_event_id: FSEventStreamEventId,
+        latency: CFTimeInterval,
+        flags: StreamFlags,
+        callback: EventsCallback,
+    ) -> Self {
+        unsafe extern "C-unwind" fn drop_callback(info: *const c_void) {
+            let _cb: Box<EventsCallback> = unsafe { Box::from_raw(info as _) };
+        }
+
+        unsafe extern "C-unwind" fn raw_callback(
+            _stream: ConstFSEventStreamRef, // ConstFSEventStreamRef streamRef
+            callback_info: *mut c_void,     // void *clientCallBackInfo
+            num_events: usize,              // size_t numEvents
+            event_paths: NonNull<c_void>,   // void *eventPaths
+            event_flags: NonNull<FSEventStreamEventFlags>, // const FSEventStreamEventFlags eventFlags[]
+            event_ids: NonNull<FSEventStreamEventId>,      // const FSEventStreamEventId eventIds[]
+        ) {
+            let event_paths = unsafe {
+                slice::from_raw_parts(event_paths.as_ptr() as *const *const i8, num_events)
+            };
+            let event_flags = unsafe { slice::from_raw_parts(event_flags.as_ptr(), num_events) };
+            let event_ids = unsafe { slice::from_raw_parts(event_ids.as_ptr(), num_events) };
+            let events: Vec<_> = event_paths
+                .iter()
+                .zip(event_flags)
+                .zip(event_ids)
+                .map(|((&path, &flag), &id)| unsafe { FsEvent::from_raw(path, flag, id) })
+                .collect();
+
+            let callback = unsafe { (callback_info as *mut EventsCallback).as_mut() }.unwrap();
+            callback(events);
+        }
+
+        let paths: Vec<_> = paths.iter().map(|&x| CFString::from_str(x)).collect();
+        let paths = CFArray::from_retained_objects(&paths);
+        let mut context = FSEventStreamContext {
+            version: 0,
+            info: Box::leak(Box::new(callback)) as *mut _ as *mut _,
+            retain: None,
+            release: Some(drop_callback),
+            copyDescription: None,
+        };
+
+        let stream: FSEventStreamRef = unsafe {
+            FSEventStreamCreate(
+                None,
+                Some(raw_callback),
+                &mut context,
+                paths.as_opaque(),
+                since_event_id,
+                latency,
+                flags.bits(),
+            )
+        };
+        Self { stream }
+    }
+
+    // Start the FSEventStream with a dispatch queue.
+    pub fn spawn(self) -> Option<EventStreamWithQueue> {
+        let queue = DispatchQueue::new("cardinal-sdk-queue", DispatchQueueAttr::SERIAL);
+        unsafe { FSEventStreamSetDispatchQueue(self.stream, Some(&queue)) };
+        let result = unsafe { FSEventStreamStart(self.stream) };
+        if !result {
+            unsafe { FSEventStreamStop(self.stream) };
+            unsafe { FSEventStreamInvalidate(self.stream) };
+            return None;
+        }
+        let stream = self.stream;
+        Some(EventStreamWithQueue { stream, queue })
+    }
+
+    // Get device id being watched by this event stream.
+    pub fn dev(&self) -> dev_t {
+        unsafe { FSEventStreamGetDeviceBeingWatched(self.stream.cast_const()) }
+    }
+}
+
+/// FSEventStream with dispatch queue.
+///
+/// Dropping this struct will stop the FSEventStream and release the dispatch queue.
+pub struct EventStreamWithQueue {
+    stream: FSEventStreamRef,
+    #[allow(dead_code)]
+    queue: DispatchRetained<DispatchQueue>,
+}
+
+impl Drop for EventStreamWithQueue {
+    fn drop(&mut self) {
+        unsafe {
+            FSEventStreamStop(self.stream);
+            FSEventStreamInvalidate(self.stream);
+        }
+    }
+}
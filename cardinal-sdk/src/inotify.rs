@@ -0,0 +1,379 @@
+//! The inotify backend of [`crate::EventStream`], for Linux.
+//!
+//! It reports the changes made while it runs, with coarser flags than
+//! FSEvents:
+//! - there is no history: `since_event_id` is ignored and `HistoryDone` is
+//!   delivered as soon as the stream starts;
+//! - event ids are microseconds since the Unix epoch rather than a count
+//!   kept by the volume, see [`crate::current_event_id`];
+//! - a write is `ItemModified`, any attribute or xattr change is
+//!   `ItemInodeMetaMod`, both halves of a move are `ItemRenamed`, and the
+//!   type bits are only `ItemIsDir`, `ItemIsSymlink` or `ItemIsFile`;
+//! - `IgnoreSelf` and `MarkSelf` are ignored, `OwnEvent` is never set;
+//! - a directory created or moved in is reported once, its content is left to
+//!   the folder scan the event asks for;
+//! - a queue overflow asks for a rescan of every root.
+
+use crate::{EventFlag, FSEventStreamEventId, FsEvent, StreamFlags, event_stream::EventsCallback};
+use libc::dev_t;
+use std::{
+    collections::HashMap,
+    ffi::{CString, OsStr},
+    fs, io, mem,
+    os::{
+        fd::{AsRawFd, FromRawFd, OwnedFd},
+        unix::ffi::OsStrExt,
+    },
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    thread::JoinHandle,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+/// Id of the last event of the process's streams. Ids are microseconds since
+/// the Unix epoch, bumped to stay increasing, so they go on increasing across
+/// runs and tell when an event happened.
+static LAST_EVENT_ID: AtomicU64 = AtomicU64::new(0);
+
+fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_micros() as u64)
+}
+
+pub(crate) fn current_event_id() -> FSEventStreamEventId {
+    let now = now_micros();
+    LAST_EVENT_ID.fetch_max(now, Ordering::AcqRel).max(now)
+}
+
+fn next_event_id() -> FSEventStreamEventId {
+    let now = now_micros();
+    let last = LAST_EVENT_ID
+        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |last| {
+            Some((last + 1).max(now))
+        })
+        .unwrap_or_else(|last| last);
+    (last + 1).max(now)
+}
+
+/// The id events got at unix time `timestamp`, in seconds.
+pub(crate) fn event_id_at(timestamp: i64) -> FSEventStreamEventId {
+    u64::try_from(timestamp).map_or(0, |secs| secs.saturating_mul(1_000_000))
+}
+
+const WATCH_MASK: u32 = libc::IN_CREATE
+    | libc::IN_DELETE
+    | libc::IN_MODIFY
+    | libc::IN_CLOSE_WRITE
+    | libc::IN_ATTRIB
+    | libc::IN_MOVED_FROM
+    | libc::IN_MOVED_TO
+    | libc::IN_DELETE_SELF
+    | libc::IN_MOVE_SELF
+    | libc::IN_DONT_FOLLOW;
+
+/// The inotify counterpart of the FSEvents stream, with the same interface.
+pub struct EventStream {
+    roots: Vec<PathBuf>,
+    latency: Duration,
+    no_defer: bool,
+    callback: EventsCallback,
+}
+
+impl EventStream {
+    /// Create a stream with the default [`StreamFlags`].
+    pub fn new(
+        paths: &[&str],
+        since_event_id: FSEventStreamEventId,
+        latency: f64,
+        callback: EventsCallback,
+    ) -> Self {
+        Self::with_flags(
+            paths,
+            since_event_id,
+            latency,
+            StreamFlags::default(),
+            callback,
+        )
+    }
+
+    /// `since_event_id` is ignored, inotify has no history to replay.
+    pub fn with_flags(
+        paths: &[&str],
+        _since_event_id: FSEventStreamEventId,
+        latency: f64,
+        flags: StreamFlags,
+        callback: EventsCallback,
+    ) -> Self {
+        Self {
+            roots: paths.iter().map(PathBuf::from).collect(),
+            latency: Duration::from_secs_f64(latency.max(0.0)),
+            no_defer: flags.contains(StreamFlags::NoDefer),
+            callback,
+        }
+    }
+
+    /// Watch the roots from a thread of their own, `None` when inotify can't
+    /// be set up.
+    pub fn spawn(self) -> Option<EventStreamWithQueue> {
+        let inotify = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if inotify < 0 {
+            return None;
+        }
+        let inotify = unsafe { OwnedFd::from_raw_fd(inotify) };
+        let stop = unsafe { libc::eventfd(0, libc::EFD_NONBLOCK | libc::EFD_CLOEXEC) };
+        if stop < 0 {
+            return None;
+        }
+        let stop = unsafe { OwnedFd::from_raw_fd(stop) };
+        let mut watcher = Watcher {
+            inotify,
+            watches: HashMap::new(),
+            roots: self.roots,
+        };
+        for root in watcher.roots.clone() {
+            watcher.watch_tree(&root);
+        }
+        let stop_fd = stop.as_raw_fd();
+        let (latency, no_defer, callback) = (self.latency, self.no_defer, self.callback);
+        let thread = std::thread::Builder::new()
+            .name("cardinal-sdk-inotify".to_string())
+            .spawn(move || watcher.run(stop_fd, latency, no_defer, callback))
+            .ok()?;
+        Some(EventStreamWithQueue {
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// Device of the first root, 0 when it can't be stat'ed.
+    pub fn dev(&self) -> dev_t {
+        self.roots
+            .first()
+            .and_then(|root| crate::dev_of_path(root).ok())
+            .unwrap_or(0)
+    }
+}
+
+/// A running inotify stream, named after its FSEvents counterpart.
+///
+/// Dropping it stops the stream and waits for its thread.
+pub struct EventStreamWithQueue {
+    stop: OwnedFd,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl Drop for EventStreamWithQueue {
+    fn drop(&mut self) {
+        let one = 1u64;
+        unsafe {
+            libc::write(
+                self.stop.as_raw_fd(),
+                (&raw const one).cast(),
+                mem::size_of::<u64>(),
+            )
+        };
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+struct Watcher {
+    inotify: OwnedFd,
+    /// Watched directory of each watch descriptor.
+    watches: HashMap<i32, PathBuf>,
+    roots: Vec<PathBuf>,
+}
+
+impl Watcher {
+    fn run(mut self, stop: i32, latency: Duration, no_defer: bool, mut callback: EventsCallback) {
+        let history_done = self.roots.first().cloned().unwrap_or_default();
+        callback(vec![FsEvent {
+            path: history_done,
+            flag: EventFlag::HistoryDone,
+            id: next_event_id(),
+        }]);
+        let mut pending = Vec::new();
+        let mut deadline: Option<Instant> = None;
+        let mut last_callback: Option<Instant> = None;
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let timeout = match deadline {
+                Some(deadline) => deadline
+                    .saturating_duration_since(Instant::now())
+                    .as_millis()
+                    .try_into()
+                    .unwrap_or(i32::MAX),
+                None => -1,
+            };
+            let mut fds = [
+                libc::pollfd {
+                    fd: self.inotify.as_raw_fd(),
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+                libc::pollfd {
+                    fd: stop,
+                    events: libc::POLLIN,
+                    revents: 0,
+                },
+            ];
+            let ready = unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) };
+            if ready < 0 && io::Error::last_os_error().kind() != io::ErrorKind::Interrupted {
+                return;
+            }
+            if fds[1].revents != 0 {
+                return;
+            }
+            if fds[0].revents != 0 {
+                let before = pending.len();
+                self.read_events(&mut buf, &mut pending);
+                if deadline.is_none() && pending.len() > before {
+                    // Like FSEvents' NoDefer: the first change after a quiet
+                    // period goes out right away.
+                    let quiet = last_callback.is_none_or(|last| last.elapsed() >= latency);
+                    deadline = Some(if no_defer && quiet {
+                        Instant::now()
+                    } else {
+                        Instant::now() + latency
+                    });
+                }
+            }
+            if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+                deadline = None;
+                last_callback = Some(Instant::now());
+                callback(mem::take(&mut pending));
+            }
+        }
+    }
+
+    /// Turn what inotify has queued into events.
+    fn read_events(&mut self, buf: &mut [u8], events: &mut Vec<FsEvent>) {
+        loop {
+            let len =
+                unsafe { libc::read(self.inotify.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len()) };
+            if len <= 0 {
+                return;
+            }
+            let mut offset = 0;
+            while offset < len as usize {
+                let raw: libc::inotify_event = unsafe {
+                    buf.as_ptr()
+                        .add(offset)
+                        .cast::<libc::inotify_event>()
+                        .read_unaligned()
+                };
+                let name_start = offset + mem::size_of::<libc::inotify_event>();
+                let name = &buf[name_start..name_start + raw.len as usize];
+                let name = &name[..name.iter().position(|&b| b == 0).unwrap_or(name.len())];
+                offset = name_start + raw.len as usize;
+                self.translate(raw.wd, raw.mask, OsStr::from_bytes(name), events);
+            }
+        }
+    }
+
+    fn translate(&mut self, wd: i32, mask: u32, name: &OsStr, events: &mut Vec<FsEvent>) {
+        if mask & libc::IN_Q_OVERFLOW != 0 {
+            events.extend(self.roots.iter().map(|root| FsEvent {
+                path: root.clone(),
+                flag: EventFlag::MustScanSubDirs | EventFlag::KernelDropped,
+                id: next_event_id(),
+            }));
+            return;
+        }
+        if mask & libc::IN_IGNORED != 0 {
+            self.watches.remove(&wd);
+            return;
+        }
+        let Some(dir) = self.watches.get(&wd) else {
+            return;
+        };
+        if name.is_empty() {
+            // The watched directory itself. Changes to it are also reported
+            // to its parent's watch, only the roots need these.
+            if !self.roots.contains(dir) {
+                return;
+            }
+            let flag = if mask & libc::IN_UNMOUNT != 0 {
+                EventFlag::Unmount
+            } else if mask & (libc::IN_DELETE_SELF | libc::IN_MOVE_SELF) != 0 {
+                EventFlag::RootChanged
+            } else {
+                return;
+            };
+            events.push(FsEvent {
+                path: dir.clone(),
+                flag,
+                id: next_event_id(),
+            });
+            return;
+        }
+        let path = dir.join(name);
+        let mut flag = if mask & libc::IN_CREATE != 0 {
+            EventFlag::ItemCreated
+        } else if mask & libc::IN_DELETE != 0 {
+            EventFlag::ItemRemoved
+        } else if mask & (libc::IN_MOVED_FROM | libc::IN_MOVED_TO) != 0 {
+            EventFlag::ItemRenamed
+        } else if mask & (libc::IN_MODIFY | libc::IN_CLOSE_WRITE) != 0 {
+            EventFlag::ItemModified
+        } else if mask & libc::IN_ATTRIB != 0 {
+            EventFlag::ItemInodeMetaMod
+        } else {
+            return;
+        };
+        if mask & libc::IN_ISDIR != 0 {
+            flag |= EventFlag::ItemIsDir;
+            if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                self.watch_tree(&path);
+            } else if mask & libc::IN_MOVED_FROM != 0 {
+                self.unwatch_tree(&path);
+            }
+        } else if path.symlink_metadata().is_ok_and(|m| m.is_symlink()) {
+            flag |= EventFlag::ItemIsSymlink;
+        } else {
+            flag |= EventFlag::ItemIsFile;
+        }
+        events.push(FsEvent {
+            path,
+            flag,
+            id: next_event_id(),
+        });
+    }
+
+    /// Watch `dir` and the directories under it, not following symlinks.
+    fn watch_tree(&mut self, dir: &Path) {
+        let Ok(c_path) = CString::new(dir.as_os_str().as_bytes()) else {
+            return;
+        };
+        let wd = unsafe {
+            libc::inotify_add_watch(self.inotify.as_raw_fd(), c_path.as_ptr(), WATCH_MASK)
+        };
+        if wd < 0 {
+            return;
+        }
+        self.watches.insert(wd, dir.to_path_buf());
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+                self.watch_tree(&entry.path());
+            }
+        }
+    }
+
+    /// Stop watching `dir` and the directories under it, e.g. once moved
+    /// away: their watches would report the old paths.
+    fn unwatch_tree(&mut self, dir: &Path) {
+        let inotify = self.inotify.as_raw_fd();
+        self.watches.retain(|&wd, path| {
+            let moved = path.starts_with(dir);
+            if moved {
+                unsafe { libc::inotify_rm_watch(inotify, wd) };
+            }
+            !moved
+        });
+    }
+}
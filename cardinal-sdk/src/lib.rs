@@ -1,10 +1,34 @@
 mod event;
+mod event_batcher;
 mod event_flag;
 mod event_stream;
+mod firmlinks;
+#[cfg(target_os = "macos")]
+mod fsevents;
+#[cfg(target_os = "linux")]
+mod inotify;
 mod utils;
 
-pub use event::FsEvent;
-pub use event_flag::{EventFlag, EventType, ScanType};
-pub use event_stream::{EventStream, EventWatcher};
-pub use objc2_core_services::FSEventStreamEventId;
-pub use utils::{current_event_id, event_id_to_timestamp};
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+compile_error!("cardinal-sdk watches files with FSEvents on macOS and inotify on Linux only");
+
+pub use event::{EventBatch, FsEvent};
+pub use event_batcher::EventWatcherConfig;
+pub use event_flag::{EventFlag, EventType, ScanType, VolumeChange};
+pub use event_stream::{
+    EVENT_ID_SINCE_NOW, EventStreamBuilder, EventStreamHandle, EventWatcher, StreamFlags,
+};
+pub use firmlinks::{DATA_VOLUME, FIRMLINKS_TABLE, Firmlink, Firmlinks};
+#[cfg(target_os = "macos")]
+pub use fsevents::EventStream;
+#[cfg(target_os = "linux")]
+pub use inotify::EventStream;
+pub use utils::{
+    VolumeInfo, current_event_id, date_added_of_path, dev_of_cstr, dev_of_path,
+    event_id_to_timestamp, event_stream_uuid, list_volumes, volume_of_path, xattr_of_path,
+};
+
+/// Ids of the events of a stream, increasing. FSEvents' are per volume and
+/// survive reboots; the inotify fallback's are microseconds since the Unix
+/// epoch, so they keep increasing across runs as long as the clock does.
+pub type FSEventStreamEventId = u64;
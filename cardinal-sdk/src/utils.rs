@@ -1,6 +1,30 @@
 use libc::dev_t;
-use objc2_core_services::{FSEventsGetCurrentEventId, FSEventsGetLastEventIdForDeviceBeforeTime};
-use std::{collections::HashMap, time::SystemTime};
+#[cfg(target_os = "macos")]
+use objc2_core_services::{
+    FSEventsCopyUUIDForDevice, FSEventsGetCurrentEventId, FSEventsGetLastEventIdForDeviceBeforeTime,
+};
+#[cfg(target_os = "macos")]
+use std::mem::MaybeUninit;
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString, OsStr},
+    io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+/// A mounted filesystem, see [`list_volumes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeInfo {
+    pub mount_point: PathBuf,
+    pub dev: dev_t,
+    /// e.g. `apfs`, `smbfs`.
+    pub fs_type: String,
+    /// FSEvents keeps an event history for the volume, so watching it can resume
+    /// from an old event id. Never on Linux.
+    pub supports_history: bool,
+}
 
 pub fn current_timestamp() -> i64 {
     SystemTime::now()
@@ -9,14 +33,272 @@ pub fn current_timestamp() -> i64 {
         .unwrap_or_default()
 }
 
+#[cfg(target_os = "macos")]
 pub fn current_event_id() -> u64 {
     unsafe { FSEventsGetCurrentEventId() }
 }
 
+/// Id of the last event of the process's inotify streams, or of one happening
+/// now if that's later: their ids are microseconds since the Unix epoch.
+#[cfg(target_os = "linux")]
+pub fn current_event_id() -> u64 {
+    crate::inotify::current_event_id()
+}
+
+/// UUID of the FSEvents stream of volume `dev`. It changes whenever the
+/// volume's event history is reset, after which event ids start over.
+/// `None` for volumes without a persistent event history.
+#[cfg(target_os = "macos")]
+pub fn event_stream_uuid(dev: dev_t) -> Option<[u8; 16]> {
+    let uuid = unsafe { FSEventsCopyUUIDForDevice(dev) }?;
+    let b = uuid.uuid_bytes();
+    Some([
+        b.byte0, b.byte1, b.byte2, b.byte3, b.byte4, b.byte5, b.byte6, b.byte7, b.byte8, b.byte9,
+        b.byte10, b.byte11, b.byte12, b.byte13, b.byte14, b.byte15,
+    ])
+}
+
+/// Always `None`: no Linux volume keeps an event history.
+#[cfg(target_os = "linux")]
+pub fn event_stream_uuid(_dev: dev_t) -> Option<[u8; 16]> {
+    None
+}
+
+/// Device id of the volume `path` lives on. Doesn't follow a trailing symlink.
+pub fn dev_of_path(path: &Path) -> io::Result<dev_t> {
+    let metadata = path.symlink_metadata()?;
+    dev_t::try_from(metadata.dev()).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("device id {} out of range", metadata.dev()),
+        )
+    })
+}
+
+/// Like [`dev_of_path`], for callers that already hold a C string.
+pub fn dev_of_cstr(path: &CStr) -> io::Result<dev_t> {
+    dev_of_path(Path::new(OsStr::from_bytes(path.to_bytes())))
+}
+
+/// When `path` was added to its folder, in seconds since the Unix epoch. This is
+/// what Spotlight reports as `kMDItemDateAdded`; `None` when the volume doesn't
+/// record it. Doesn't follow a trailing symlink.
+#[cfg(target_os = "macos")]
+pub fn date_added_of_path(path: &Path) -> io::Result<Option<i64>> {
+    // Attributes come back packed on 4 bytes, in bitmap order.
+    #[repr(C, packed(4))]
+    struct AddedTimeAttrs {
+        length: u32,
+        returned: libc::attribute_set_t,
+        added: libc::timespec,
+    }
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    let mut request = libc::attrlist {
+        bitmapcount: libc::ATTR_BIT_MAP_COUNT,
+        reserved: 0,
+        commonattr: libc::ATTR_CMN_RETURNED_ATTRS | libc::ATTR_CMN_ADDEDTIME,
+        volattr: 0,
+        dirattr: 0,
+        fileattr: 0,
+        forkattr: 0,
+    };
+    let mut attrs = MaybeUninit::<AddedTimeAttrs>::zeroed();
+    let ret = unsafe {
+        libc::getattrlist(
+            path.as_ptr(),
+            (&raw mut request).cast(),
+            attrs.as_mut_ptr().cast(),
+            size_of::<AddedTimeAttrs>(),
+            libc::FSOPT_NOFOLLOW,
+        )
+    };
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let attrs = unsafe { attrs.assume_init() };
+    let returned = attrs.returned;
+    if returned.commonattr & libc::ATTR_CMN_ADDEDTIME == 0 {
+        return Ok(None);
+    }
+    let added = attrs.added;
+    Ok(Some(added.tv_sec))
+}
+
+/// Always `None` once `path` is found: Linux volumes don't record it.
+#[cfg(target_os = "linux")]
+pub fn date_added_of_path(path: &Path) -> io::Result<Option<i64>> {
+    path.symlink_metadata().map(|_| None)
+}
+
+/// Value of the extended attribute `name` of `path`, `None` when it isn't set.
+/// Doesn't follow a trailing symlink.
+pub fn xattr_of_path(path: &Path, name: &CStr) -> io::Result<Option<Vec<u8>>> {
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    loop {
+        let len = unsafe { getxattr_nofollow(&path, name, std::ptr::null_mut(), 0) };
+        if len < 0 {
+            let error = io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(ENOATTR) => Ok(None),
+                _ => Err(error),
+            };
+        }
+        let mut value = vec![0u8; len as usize];
+        let read = unsafe { getxattr_nofollow(&path, name, value.as_mut_ptr(), value.len()) };
+        if read >= 0 {
+            value.truncate(read as usize);
+            return Ok(Some(value));
+        }
+        let error = io::Error::last_os_error();
+        match error.raw_os_error() {
+            // Removed or grown between the two calls.
+            Some(ENOATTR) => return Ok(None),
+            Some(libc::ERANGE) => continue,
+            _ => return Err(error),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+const ENOATTR: i32 = libc::ENOATTR;
+#[cfg(target_os = "linux")]
+const ENOATTR: i32 = libc::ENODATA;
+
+#[cfg(target_os = "macos")]
+unsafe fn getxattr_nofollow(path: &CStr, name: &CStr, value: *mut u8, size: usize) -> isize {
+    unsafe {
+        libc::getxattr(
+            path.as_ptr(),
+            name.as_ptr(),
+            value.cast(),
+            size,
+            0,
+            libc::XATTR_NOFOLLOW,
+        )
+    }
+}
+
+#[cfg(target_os = "linux")]
+unsafe fn getxattr_nofollow(path: &CStr, name: &CStr, value: *mut u8, size: usize) -> isize {
+    unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), value.cast(), size) }
+}
+
+/// Every mounted volume. Mount points that can't be stat'ed (e.g. a network share that went
+/// away) are left out, use [`dev_of_path`] on them to find out why.
+#[cfg(target_os = "macos")]
+pub fn list_volumes() -> io::Result<Vec<VolumeInfo>> {
+    // getfsstat fills a caller-owned buffer, unlike getmntinfo's shared static one.
+    let count = unsafe { libc::getfsstat(std::ptr::null_mut(), 0, libc::MNT_NOWAIT) };
+    if count < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Leave room for volumes mounted between the two calls.
+    let capacity = count as usize + 4;
+    let mut buf: Vec<MaybeUninit<libc::statfs>> = Vec::with_capacity(capacity);
+    let bufsize = (capacity * size_of::<libc::statfs>()) as libc::c_int;
+    let count = unsafe { libc::getfsstat(buf.as_mut_ptr().cast(), bufsize, libc::MNT_NOWAIT) };
+    if count < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { buf.set_len(count as usize) };
+    Ok(buf
+        .into_iter()
+        .filter_map(|stat| {
+            let stat = unsafe { stat.assume_init() };
+            let mount_point = PathBuf::from(OsStr::from_bytes(c_chars_to_bytes(&stat.f_mntonname)));
+            let fs_type =
+                String::from_utf8_lossy(c_chars_to_bytes(&stat.f_fstypename)).into_owned();
+            let dev = dev_of_path(&mount_point).ok()?;
+            let supports_history = unsafe { FSEventsCopyUUIDForDevice(dev) }.is_some();
+            Some(VolumeInfo {
+                mount_point,
+                dev,
+                fs_type,
+                supports_history,
+            })
+        })
+        .collect())
+}
+
+/// Every mounted volume, from `/proc/self/mounts`. As on macOS, mount points
+/// that can't be stat'ed are left out.
+#[cfg(target_os = "linux")]
+pub fn list_volumes() -> io::Result<Vec<VolumeInfo>> {
+    let mounts = std::fs::read("/proc/self/mounts")?;
+    Ok(mounts
+        .split(|&b| b == b'\n')
+        .filter_map(|line| {
+            // device mount_point fs_type options dump pass
+            let mut fields = line.split(|&b| b == b' ');
+            let _device = fields.next()?;
+            let mount_point =
+                PathBuf::from(OsStr::from_bytes(&unescape_mount_field(fields.next()?)));
+            let fs_type =
+                String::from_utf8_lossy(&unescape_mount_field(fields.next()?)).into_owned();
+            let dev = dev_of_path(&mount_point).ok()?;
+            Some(VolumeInfo {
+                mount_point,
+                dev,
+                fs_type,
+                supports_history: false,
+            })
+        })
+        .collect())
+}
+
+/// `/proc/self/mounts` writes spaces, tabs, newlines and backslashes as
+/// three-digit octal escapes.
+#[cfg(target_os = "linux")]
+fn unescape_mount_field(field: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(field.len());
+    let mut i = 0;
+    while i < field.len() {
+        let escape = field.get(i + 1..i + 4).filter(|digits| {
+            field[i] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit))
+        });
+        match escape {
+            Some(digits) => {
+                out.push(
+                    digits
+                        .iter()
+                        .fold(0u8, |acc, digit| acc * 8 + (digit - b'0')),
+                );
+                i += 4;
+            }
+            None => {
+                out.push(field[i]);
+                i += 1;
+            }
+        }
+    }
+    out
+}
+
+/// The volume whose mount point is the longest prefix of `path`.
+pub fn volume_of_path<'v>(volumes: &'v [VolumeInfo], path: &Path) -> Option<&'v VolumeInfo> {
+    volumes
+        .iter()
+        .filter(|volume| path.starts_with(&volume.mount_point))
+        .max_by_key(|volume| volume.mount_point.as_os_str().len())
+}
+
+#[cfg(target_os = "macos")]
+fn c_chars_to_bytes(chars: &[libc::c_char]) -> &[u8] {
+    let len = chars.iter().position(|&c| c == 0).unwrap_or(chars.len());
+    unsafe { std::slice::from_raw_parts(chars.as_ptr().cast(), len) }
+}
+
+#[cfg(target_os = "macos")]
 pub fn last_event_id_before_time(dev: dev_t, timestamp: i64) -> u64 {
     unsafe { FSEventsGetLastEventIdForDeviceBeforeTime(dev, timestamp as f64) }
 }
 
+/// inotify event ids are times already, see [`current_event_id`].
+#[cfg(target_os = "linux")]
+pub fn last_event_id_before_time(_dev: dev_t, timestamp: i64) -> u64 {
+    crate::inotify::event_id_at(timestamp)
+}
+
 /// Given a device id, an event id, and a cache mapping timestamps to last event ids before them,
 /// perform a binary search to find the timestamp corresponding to the event id.
 pub fn event_id_to_timestamp(dev: dev_t, event_id: u64, cache: &mut HashMap<i64, u64>) -> i64 {
@@ -1,5 +1,8 @@
-use cardinal_sdk::{EventFlag, EventType, ScanType, event_id_to_timestamp};
-use std::collections::HashMap;
+use cardinal_sdk::{
+    EventFlag, EventType, ScanType, date_added_of_path, dev_of_cstr, dev_of_path,
+    event_id_to_timestamp, event_stream_uuid, list_volumes, volume_of_path,
+};
+use std::{collections::HashMap, path::Path};
 
 // NOTE: Cannot deterministically assert macOS FSEvents ids; focus on logical properties of event_id_to_timestamp.
 #[test]
@@ -58,3 +61,63 @@ fn event_type_and_scan_type_cross_matrix() {
         ScanType::SingleNode
     ));
 }
+
+#[test]
+fn list_volumes_contains_root() {
+    let volumes = list_volumes().expect("getfsstat should succeed");
+    let root = volumes
+        .iter()
+        .find(|volume| volume.mount_point == Path::new("/"))
+        .expect("/ should be mounted");
+    assert_eq!(root.dev, dev_of_path(Path::new("/")).unwrap());
+    assert!(!root.fs_type.is_empty());
+    assert_eq!(volume_of_path(&volumes, Path::new("/")), Some(root));
+    // Every path lives on some volume, at worst on /.
+    assert!(volume_of_path(&volumes, Path::new("/no/such/path")).is_some());
+}
+
+#[test]
+fn dev_of_file_matches_its_parent() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("probe.txt");
+    std::fs::write(&file, b"x").unwrap();
+    assert_eq!(
+        dev_of_path(&file).unwrap(),
+        dev_of_path(dir.path()).unwrap()
+    );
+    assert_eq!(
+        dev_of_cstr(c"/").unwrap(),
+        dev_of_path(Path::new("/")).unwrap()
+    );
+    let missing = dev_of_path(&dir.path().join("missing")).unwrap_err();
+    assert_eq!(missing.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn date_added_of_new_file_is_recent() {
+    let dir = tempfile::tempdir().unwrap();
+    let file = dir.path().join("added.txt");
+    std::fs::write(&file, b"x").unwrap();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64;
+    // Volumes without the attribute report None.
+    if let Some(added) = date_added_of_path(&file).unwrap() {
+        assert!((now - 60..=now + 60).contains(&added), "{added} vs {now}");
+    }
+    let missing = date_added_of_path(&dir.path().join("missing")).unwrap_err();
+    assert_eq!(missing.kind(), std::io::ErrorKind::NotFound);
+}
+
+#[test]
+fn event_stream_uuid_is_stable_and_matches_history_support() {
+    let root = dev_of_path(Path::new("/")).unwrap();
+    assert_eq!(event_stream_uuid(root), event_stream_uuid(root));
+    for volume in list_volumes().unwrap() {
+        assert_eq!(
+            event_stream_uuid(volume.dev).is_some(),
+            volume.supports_history
+        );
+    }
+}
@@ -33,7 +33,65 @@ use std::fmt;
 
 /// Parses an Everything-like query string into a structured expression tree.
 pub fn parse_query(input: &str) -> Result<Query, ParseError> {
-    Parser::new(input).parse()
+    parse_query_with_options(input, ParseOptions::default())
+}
+
+/// [`parse_query`] with a choice of how AND and OR group.
+///
+/// ```
+/// use cardinal_syntax::{parse_query_with_options, Expr, ParseOptions, Precedence};
+/// let options = ParseOptions { precedence: Precedence::Conventional, ..Default::default() };
+/// let query = parse_query_with_options("a OR b c", options).unwrap();
+/// let Expr::Or(parts) = query.expr else { panic!() };
+/// assert!(matches!(&parts[1], Expr::And(_)));
+/// ```
+pub fn parse_query_with_options(input: &str, options: ParseOptions) -> Result<Query, ParseError> {
+    Parser::new(input, options).parse()
+}
+
+/// Default [`ParseOptions::max_depth`].
+pub const MAX_GROUP_DEPTH: usize = 128;
+
+/// Options for [`parse_query_with_options`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub precedence: Precedence,
+    /// How deep `(...)` and `<...>` groups may nest, [`MAX_GROUP_DEPTH`] by
+    /// default. The parser and the passes over its tree recurse once per
+    /// level, so deeper queries fail with [`ParseError::too_deep`] set
+    /// instead of overflowing the stack. `None` doesn't limit it.
+    ///
+    /// ```
+    /// use cardinal_syntax::{parse_query_with_options, ParseOptions};
+    /// let options = ParseOptions { max_depth: Some(2), ..Default::default() };
+    /// assert!(parse_query_with_options("((a) b)", options).is_ok());
+    /// let err = parse_query_with_options("(((a)))", options).unwrap_err();
+    /// assert_eq!(err.too_deep, Some(3));
+    /// ```
+    pub max_depth: Option<usize>,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            precedence: Precedence::default(),
+            max_depth: Some(MAX_GROUP_DEPTH),
+        }
+    }
+}
+
+/// How AND (the `AND` keyword or plain whitespace between terms) and OR (`|`
+/// or `OR`) group. NOT (`!` or `NOT`) binds tightest either way, and keywords
+/// are case-insensitive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Precedence {
+    /// Everything's rules, OR binds tighter than AND: `a OR b c` is
+    /// `(a OR b) AND c`.
+    #[default]
+    Everything,
+    /// NOT > AND > OR as in most boolean languages: `a OR b c` is
+    /// `a OR (b AND c)`.
+    Conventional,
 }
 
 /// User input normalized into a single expression tree.
@@ -323,6 +381,13 @@ pub enum FilterKind {
     /// assert!(matches!(filter.kind, FilterKind::Size));
     /// ```
     Size,
+    /// Allocated (on-disk) size comparisons (`disksize:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("disksize:>1GB").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::DiskSize));
+    /// ```
+    DiskSize,
     /// Date modified (`dm:` / `datemodified:`).
     /// ```
     /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
@@ -344,6 +409,13 @@ pub enum FilterKind {
     /// assert!(matches!(filter.kind, FilterKind::DateAccessed));
     /// ```
     DateAccessed,
+    /// Date added to its folder (`dt:` / `dateadded:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("dt:thismonth").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::DateAdded));
+    /// ```
+    DateAdded,
     /// Date run (`dr:` / `daterun:`).
     /// ```
     /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
@@ -365,6 +437,13 @@ pub enum FilterKind {
     /// assert!(matches!(filter.kind, FilterKind::InFolder));
     /// ```
     InFolder,
+    /// Restrict to descendants of any folder with a matching name (`under:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("under:node_modules").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::Under));
+    /// ```
+    Under,
     /// Limit to the folder itself (`nosubfolders:`).
     /// ```
     /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
@@ -372,6 +451,34 @@ pub enum FilterKind {
     /// assert!(matches!(filter.kind, FilterKind::NoSubfolders));
     /// ```
     NoSubfolders,
+    /// Limit how many levels below the search root or folder a result may be (`depth:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("depth:<=2").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::Depth));
+    /// ```
+    Depth,
+    /// Match names starting with the argument (`startswith:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("startswith:IMG_").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::StartsWith));
+    /// ```
+    StartsWith,
+    /// Match names ending with the argument (`endswith:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("endswith:.tar.gz").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::EndsWith));
+    /// ```
+    EndsWith,
+    /// Match the argument's letters against the word initials of names (`initials:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("initials:SC").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::Initials));
+    /// ```
+    Initials,
     /// Require a folder containing matching children (`child:`).
     /// ```
     /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
@@ -379,6 +486,13 @@ pub enum FilterKind {
     /// assert!(matches!(filter.kind, FilterKind::Child));
     /// ```
     Child,
+    /// Match a property of the item itself (`is:hardlinked`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("is:hardlinked").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::Is));
+    /// ```
+    Is,
     /// Match file-system attributes (`attrib:`).
     /// ```
     /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
@@ -519,6 +633,20 @@ pub enum FilterKind {
     /// assert!(matches!(filter.kind, FilterKind::Content));
     /// ```
     Content,
+    /// User-assigned labels (`tag:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("tag:keep").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::Tag));
+    /// ```
+    Tag,
+    /// Finder tags, by name or color (`fintag:`).
+    /// ```
+    /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
+    /// let Expr::Term(Term::Filter(filter)) = parse_query("fintag:red").unwrap().expr else { panic!() };
+    /// assert!(matches!(filter.kind, FilterKind::FinderTag));
+    /// ```
+    FinderTag,
     /// Temporarily disable whole filename matching (`nowholefilename:`).
     /// ```
     /// use cardinal_syntax::{parse_query, Expr, Term, FilterKind};
@@ -548,14 +676,22 @@ impl FilterKind {
             "doc" => FilterKind::Doc,
             "exe" => FilterKind::Exe,
             "size" => FilterKind::Size,
+            "disksize" => FilterKind::DiskSize,
             "dm" | "datemodified" => FilterKind::DateModified,
             "dc" | "datecreated" => FilterKind::DateCreated,
             "da" | "dateaccessed" => FilterKind::DateAccessed,
+            "dt" | "dateadded" => FilterKind::DateAdded,
             "dr" | "daterun" => FilterKind::DateRun,
             "parent" => FilterKind::Parent,
             "infolder" => FilterKind::InFolder,
+            "under" => FilterKind::Under,
             "nosubfolders" => FilterKind::NoSubfolders,
+            "depth" => FilterKind::Depth,
+            "startswith" => FilterKind::StartsWith,
+            "endswith" => FilterKind::EndsWith,
+            "initials" => FilterKind::Initials,
             "child" => FilterKind::Child,
+            "is" => FilterKind::Is,
             "attrib" => FilterKind::Attribute,
             "attribdupe" => FilterKind::AttributeDuplicate,
             "dmdupe" => FilterKind::DateModifiedDuplicate,
@@ -576,6 +712,8 @@ impl FilterKind {
             "bitdepth" => FilterKind::BitDepth,
             "case" => FilterKind::CaseSensitive,
             "content" => FilterKind::Content,
+            "tag" => FilterKind::Tag,
+            "fintag" => FilterKind::FinderTag,
             "nowholefilename" => FilterKind::NoWholeFilename,
             _ => FilterKind::Custom(name.to_string()),
         }
@@ -736,6 +874,10 @@ pub enum ComparisonOp {
 pub struct ParseError {
     pub message: String,
     pub position: usize,
+    /// Set to the depth reached when groups nest deeper than
+    /// [`ParseOptions::max_depth`], `position` is then the opening delimiter
+    /// that went past it.
+    pub too_deep: Option<usize>,
 }
 
 impl fmt::Display for ParseError {
@@ -754,19 +896,23 @@ struct Parser<'a> {
     input: &'a str,
     pos: usize,
     group_stack: Vec<char>,
+    precedence: Precedence,
+    max_depth: Option<usize>,
 }
 
 impl<'a> Parser<'a> {
-    fn new(input: &'a str) -> Self {
+    fn new(input: &'a str, options: ParseOptions) -> Self {
         Self {
             input,
             pos: 0,
             group_stack: Vec::new(),
+            precedence: options.precedence,
+            max_depth: options.max_depth,
         }
     }
 
     fn parse(mut self) -> Result<Query, ParseError> {
-        let expr = self.parse_and()?;
+        let expr = self.parse_expr()?;
         self.skip_ws();
         if !self.eof() {
             return Err(self.error("unexpected trailing characters"));
@@ -774,6 +920,13 @@ impl<'a> Parser<'a> {
         Ok(Query { expr })
     }
 
+    fn parse_expr(&mut self) -> Result<Expr, ParseError> {
+        match self.precedence {
+            Precedence::Everything => self.parse_and(),
+            Precedence::Conventional => self.parse_disjunction(),
+        }
+    }
+
     // AND has the lowest precedence and is implicit between whitespace-delimited
     // terms. We accumulate a Vec instead of nesting binary nodes so callers get
     // a normalized structure regardless of how many terms are chained.
@@ -846,6 +999,69 @@ impl<'a> Parser<'a> {
         }
     }
 
+    // Conventional counterpart of `parse_or`: OR is the loosest operator and its
+    // operands are AND chains. Empty operands are kept the same way.
+    fn parse_disjunction(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+        loop {
+            parts.push(self.parse_conjunction()?);
+            self.skip_ws();
+            let matched = if self.peek_char() == Some('|') {
+                self.advance_char();
+                true
+            } else {
+                self.consume_keyword("OR")
+            };
+            if !matched {
+                break;
+            }
+        }
+
+        if parts.len() == 1 {
+            Ok(parts.remove(0))
+        } else {
+            Ok(Expr::Or(parts))
+        }
+    }
+
+    // Conventional counterpart of `parse_and`, which also stops before an OR.
+    fn parse_conjunction(&mut self) -> Result<Expr, ParseError> {
+        let mut parts = Vec::new();
+        let mut pending_keyword_and = false;
+        loop {
+            self.skip_ws();
+            if self.consume_keyword("AND") {
+                if parts.is_empty() {
+                    parts.push(Expr::Empty);
+                }
+                pending_keyword_and = true;
+                continue;
+            }
+            if self.eof()
+                || self.is_at_group_close()
+                || self.peek_char() == Some('|')
+                || self.at_keyword("OR")
+            {
+                if pending_keyword_and {
+                    parts.push(Expr::Empty);
+                }
+                break;
+            }
+            let expr = self.parse_not()?;
+            if matches!(expr, Expr::Empty) {
+                break;
+            }
+            parts.push(expr);
+            pending_keyword_and = false;
+        }
+
+        match parts.len() {
+            0 => Ok(Expr::Empty),
+            1 => Ok(parts.remove(0)),
+            _ => Ok(Expr::And(parts)),
+        }
+    }
+
     // NOT binds tighter than OR/AND and Everything allows chains like
     // `!!!foo`, so we count prefixes and only wrap once if the parity is odd.
     fn parse_not(&mut self) -> Result<Expr, ParseError> {
@@ -900,9 +1116,20 @@ impl<'a> Parser<'a> {
     }
 
     fn parse_group(&mut self, closing: char) -> Result<Expr, ParseError> {
+        let depth = self.group_stack.len() + 1;
+        if let Some(max_depth) = self.max_depth
+            && depth > max_depth
+        {
+            // Checked before recursing any further, this is what keeps the
+            // stack bounded.
+            return Err(ParseError {
+                too_deep: Some(depth),
+                ..self.error(format!("groups nest deeper than {max_depth} levels"))
+            });
+        }
         self.advance_char(); // consume opening token
         self.group_stack.push(closing);
-        let expr = self.parse_and()?;
+        let expr = self.parse_expr()?;
         self.group_stack.pop();
         self.skip_ws();
         if self.peek_char() == Some(closing) {
@@ -1096,6 +1323,7 @@ impl<'a> Parser<'a> {
         Err(ParseError {
             message: "missing closing quote".into(),
             position: quote_pos,
+            too_deep: None,
         })
     }
 
@@ -1127,6 +1355,13 @@ impl<'a> Parser<'a> {
         true
     }
 
+    fn at_keyword(&mut self, keyword: &str) -> bool {
+        let start = self.pos;
+        let found = self.consume_keyword(keyword);
+        self.pos = start;
+        found
+    }
+
     fn current_closer_is(&self, ch: char) -> bool {
         matches!(self.group_stack.last(), Some(&closer) if closer == ch)
     }
@@ -1157,6 +1392,7 @@ impl<'a> Parser<'a> {
         ParseError {
             message: message.into(),
             position: self.pos,
+            too_deep: None,
         }
     }
 
@@ -1344,6 +1580,7 @@ fn allows_hyphen_range(kind: &FilterKind) -> bool {
         FilterKind::DateCreated
             | FilterKind::DateModified
             | FilterKind::DateAccessed
+            | FilterKind::DateAdded
             | FilterKind::DateRun
     )
 }
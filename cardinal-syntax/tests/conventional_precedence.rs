@@ -0,0 +1,206 @@
+use cardinal_syntax::*;
+
+fn w(s: &str) -> Expr {
+    Expr::Term(Term::Word(s.to_string()))
+}
+
+fn and(parts: Vec<Expr>) -> Expr {
+    Expr::And(parts)
+}
+
+fn or(parts: Vec<Expr>) -> Expr {
+    Expr::Or(parts)
+}
+
+fn not(expr: Expr) -> Expr {
+    Expr::Not(Box::new(expr))
+}
+
+fn parse_with(input: &str, precedence: Precedence) -> Expr {
+    parse_query_with_options(
+        input,
+        ParseOptions {
+            precedence,
+            ..Default::default()
+        },
+    )
+    .unwrap()
+    .expr
+}
+
+fn conventional(input: &str) -> Expr {
+    parse_with(input, Precedence::Conventional)
+}
+
+fn everything(input: &str) -> Expr {
+    parse_with(input, Precedence::Everything)
+}
+
+#[test]
+fn default_options_keep_everything_precedence() {
+    assert_eq!(ParseOptions::default().precedence, Precedence::Everything);
+    for input in [
+        "a b|c d",
+        "a OR b AND c",
+        "!a b | c",
+        "<a | b> c",
+        "a AND b OR NOT c d",
+        "| a",
+        "a AND",
+    ] {
+        assert_eq!(
+            parse_query(input).unwrap().expr,
+            everything(input),
+            "`{input}`"
+        );
+    }
+}
+
+#[test]
+fn and_binds_tighter_than_or() {
+    assert_eq!(
+        conventional("a b|c d"),
+        or(vec![and(vec![w("a"), w("b")]), and(vec![w("c"), w("d")])])
+    );
+    assert_eq!(
+        conventional("a OR b c"),
+        or(vec![w("a"), and(vec![w("b"), w("c")])])
+    );
+    assert_eq!(
+        conventional("a b OR c"),
+        or(vec![and(vec![w("a"), w("b")]), w("c")])
+    );
+    assert_eq!(
+        conventional("a | b | c d"),
+        or(vec![w("a"), w("b"), and(vec![w("c"), w("d")])])
+    );
+    // The same inputs under Everything's rules, for contrast.
+    assert_eq!(
+        everything("a OR b c"),
+        and(vec![or(vec![w("a"), w("b")]), w("c")])
+    );
+    assert_eq!(
+        everything("a b OR c"),
+        and(vec![w("a"), or(vec![w("b"), w("c")])])
+    );
+}
+
+#[test]
+fn explicit_and_matches_implicit_and() {
+    let pairs = [
+        ("a AND b", "a b"),
+        ("a AND b OR c", "a b OR c"),
+        ("a OR b AND c", "a OR b c"),
+        ("a AND b | c AND d", "a b | c d"),
+        ("NOT a AND b", "NOT a b"),
+        ("<a AND b> OR c", "<a b> OR c"),
+    ];
+    for (explicit, implicit) in pairs {
+        assert_eq!(
+            conventional(explicit),
+            conventional(implicit),
+            "`{explicit}` vs `{implicit}`"
+        );
+    }
+}
+
+#[test]
+fn keywords_are_case_insensitive() {
+    let expected = or(vec![w("a"), and(vec![w("b"), not(w("c"))])]);
+    for input in [
+        "a OR b AND NOT c",
+        "a or b and not c",
+        "a Or b And Not c",
+        "a | b !c",
+    ] {
+        assert_eq!(conventional(input), expected, "`{input}`");
+    }
+    // Words that merely start with a keyword stay words.
+    assert_eq!(
+        conventional("orange andes"),
+        and(vec![w("orange"), w("andes")])
+    );
+}
+
+#[test]
+fn not_binds_tightest() {
+    assert_eq!(
+        conventional("!a b | c"),
+        or(vec![and(vec![not(w("a")), w("b")]), w("c")])
+    );
+    assert_eq!(
+        conventional("a | NOT b c"),
+        or(vec![w("a"), and(vec![not(w("b")), w("c")])])
+    );
+    assert_eq!(
+        conventional("NOT <a | b> c"),
+        and(vec![not(or(vec![w("a"), w("b")])), w("c")])
+    );
+}
+
+#[test]
+fn groups_use_the_same_precedence() {
+    assert_eq!(
+        conventional("<a b | c> d"),
+        and(vec![or(vec![and(vec![w("a"), w("b")]), w("c")]), w("d")])
+    );
+    assert_eq!(
+        conventional("(a OR b c) OR d"),
+        or(vec![or(vec![w("a"), and(vec![w("b"), w("c")])]), w("d")])
+    );
+    assert_eq!(
+        everything("<a b | c> d"),
+        and(vec![and(vec![w("a"), or(vec![w("b"), w("c")])]), w("d")])
+    );
+}
+
+#[test]
+fn filters_and_phrases_are_operands() {
+    let expr = conventional("ext:rs \"main file\" | size:>1mb");
+    let parts = match &expr {
+        Expr::Or(parts) => parts,
+        other => panic!("expected Or, got {other:?}"),
+    };
+    assert_eq!(parts.len(), 2);
+    assert!(matches!(&parts[0], Expr::And(inner) if inner.len() == 2));
+    assert!(matches!(&parts[1], Expr::Term(Term::Filter(f)) if f.kind == FilterKind::Size));
+}
+
+#[test]
+fn empty_operands_match_everything_mode() {
+    assert_eq!(conventional("| a"), or(vec![Expr::Empty, w("a")]));
+    assert_eq!(conventional("a |"), or(vec![w("a"), Expr::Empty]));
+    assert_eq!(conventional("a OR"), or(vec![w("a"), Expr::Empty]));
+    assert_eq!(conventional("a AND"), and(vec![w("a"), Expr::Empty]));
+    assert_eq!(conventional("AND a"), and(vec![Expr::Empty, w("a")]));
+    assert_eq!(
+        conventional("a AND | b"),
+        or(vec![and(vec![w("a"), Expr::Empty]), w("b")])
+    );
+    assert_eq!(conventional(""), Expr::Empty);
+    for input in ["| a", "a |", "a OR", "a AND", "AND a", ""] {
+        assert_eq!(conventional(input), everything(input), "`{input}`");
+    }
+}
+
+#[test]
+fn single_operators_agree_across_modes() {
+    for input in ["a b c", "a | b | c", "a AND b AND c", "!a", "<a | b>", "a"] {
+        assert_eq!(conventional(input), everything(input), "`{input}`");
+    }
+}
+
+#[test]
+fn errors_are_unchanged() {
+    for input in ["<a b", "a b>", "a | <b"] {
+        let options = ParseOptions {
+            precedence: Precedence::Conventional,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_query_with_options(input, options).unwrap_err(),
+            parse_query(input).unwrap_err(),
+            "`{input}`"
+        );
+    }
+}
@@ -24,18 +24,27 @@ fn maps_known_filter_names() {
         ("doc", FilterKind::Doc),
         ("exe", FilterKind::Exe),
         ("size", FilterKind::Size),
+        ("disksize", FilterKind::DiskSize),
         ("dm", FilterKind::DateModified),
         ("datemodified", FilterKind::DateModified),
         ("dc", FilterKind::DateCreated),
         ("datecreated", FilterKind::DateCreated),
         ("da", FilterKind::DateAccessed),
         ("dateaccessed", FilterKind::DateAccessed),
+        ("dt", FilterKind::DateAdded),
+        ("dateadded", FilterKind::DateAdded),
         ("dr", FilterKind::DateRun),
         ("daterun", FilterKind::DateRun),
         ("parent", FilterKind::Parent),
         ("infolder", FilterKind::InFolder),
+        ("under", FilterKind::Under),
         ("nosubfolders", FilterKind::NoSubfolders),
+        ("depth", FilterKind::Depth),
+        ("startswith", FilterKind::StartsWith),
+        ("endswith", FilterKind::EndsWith),
+        ("initials", FilterKind::Initials),
         ("child", FilterKind::Child),
+        ("is", FilterKind::Is),
         ("attrib", FilterKind::Attribute),
         ("attribdupe", FilterKind::AttributeDuplicate),
         ("dmdupe", FilterKind::DateModifiedDuplicate),
@@ -56,6 +65,8 @@ fn maps_known_filter_names() {
         ("bitdepth", FilterKind::BitDepth),
         ("case", FilterKind::CaseSensitive),
         ("content", FilterKind::Content),
+        ("tag", FilterKind::Tag),
+        ("fintag", FilterKind::FinderTag),
         ("nowholefilename", FilterKind::NoWholeFilename),
     ];
 
@@ -1,4 +1,5 @@
 mod common;
+use cardinal_syntax::{MAX_GROUP_DEPTH, ParseOptions, parse_query, parse_query_with_options};
 use common::*;
 
 #[test]
@@ -55,3 +56,34 @@ fn reports_unmatched_opening() {
     let err = parse_err("<foo bar");
     assert!(err.message.contains("expected '>'"));
 }
+
+fn nested(depth: usize) -> String {
+    let mut query = "a".to_string();
+    for level in 0..depth {
+        query = format!("(w{level} | {query})");
+    }
+    query
+}
+
+#[test]
+fn groups_nested_past_the_limit_are_rejected() {
+    let err = parse_err(&nested(1_000));
+    assert_eq!(err.too_deep, Some(MAX_GROUP_DEPTH + 1));
+    // Points at the opening parenthesis that went too deep.
+    assert_eq!(&nested(1_000)[err.position..][..1], "(");
+
+    assert!(parse_query(&nested(MAX_GROUP_DEPTH)).is_ok());
+    assert_eq!(parse_err("(a").too_deep, None);
+}
+
+#[test]
+fn group_depth_limit_is_configurable() {
+    let options = |max_depth| ParseOptions {
+        max_depth,
+        ..Default::default()
+    };
+    let err = parse_query_with_options("<a (b)>", options(Some(1))).unwrap_err();
+    assert_eq!(err.too_deep, Some(2));
+    assert!(parse_query_with_options("<a (b)>", options(Some(2))).is_ok());
+    assert!(parse_query_with_options(&nested(MAX_GROUP_DEPTH + 50), options(None)).is_ok());
+}
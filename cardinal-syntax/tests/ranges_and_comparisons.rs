@@ -31,6 +31,10 @@ fn hyphenated_ranges_for_dates() {
     let expr = parse_ok("dm:2023-01-01..2023-12-31");
     filter_is_kind(&expr, &FilterKind::DateModified);
     filter_arg_is_range_dots(&expr, Some("2023-01-01"), Some("2023-12-31"));
+
+    let expr = parse_ok("dt:2024-03-01-2024-03-31");
+    filter_is_kind(&expr, &FilterKind::DateAdded);
+    filter_arg_is_range_hyphen(&expr, "2024-03-01", "2024-03-31");
 }
 
 #[test]
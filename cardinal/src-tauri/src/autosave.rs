@@ -0,0 +1,216 @@
+use std::{
+    cmp::Reverse,
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+use tracing::warn;
+
+/// Decides when the background thread writes the cache while running, so a
+/// crash only loses what happened since: once `interval` has passed or
+/// `max_events` events came in since the last save, whichever is first. Either
+/// limit can be turned off, and nothing is saved until an event changed the
+/// cache.
+#[derive(Debug)]
+pub struct AutosaveSchedule {
+    interval: Option<Duration>,
+    max_events: Option<u64>,
+    last_save: Instant,
+    events: u64,
+}
+
+impl AutosaveSchedule {
+    pub fn new(interval: Option<Duration>, max_events: Option<u64>, now: Instant) -> Self {
+        Self {
+            interval,
+            max_events,
+            last_save: now,
+            events: 0,
+        }
+    }
+
+    /// Take new limits, counting from the last save.
+    pub fn set_limits(&mut self, interval: Option<Duration>, max_events: Option<u64>) {
+        self.interval = interval;
+        self.max_events = max_events;
+    }
+
+    pub fn record_events(&mut self, count: u64) {
+        self.events += count;
+    }
+
+    /// When the next save is due, already passed once enough events came
+    /// in; `None` while there's nothing to save or no limit applies.
+    pub fn deadline(&self) -> Option<Instant> {
+        if self.events == 0 {
+            return None;
+        }
+        if self.max_events.is_some_and(|max| self.events >= max) {
+            return Some(self.last_save);
+        }
+        Some(self.last_save + self.interval?)
+    }
+
+    /// A save started at `now`; events after it count toward the next one.
+    pub fn saved(&mut self, now: Instant) {
+        self.last_save = now;
+        self.events = 0;
+    }
+}
+
+/// Load the most recently written of `paths` that `load` accepts, trying the
+/// older ones when it fails. Missing files come last. Every failure is
+/// returned, newest first, if none loads.
+pub fn load_newest<T, E: Display>(
+    paths: &[PathBuf],
+    mut load: impl FnMut(&Path) -> Result<T, E>,
+) -> Result<(PathBuf, T), Vec<(PathBuf, E)>> {
+    let mut paths: Vec<(Option<SystemTime>, &PathBuf)> = paths
+        .iter()
+        .map(|path| {
+            let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+            (modified, path)
+        })
+        .collect();
+    paths.sort_by_key(|&(modified, _)| Reverse(modified));
+    let mut failures = Vec::new();
+    for (_, path) in paths {
+        match load(path) {
+            Ok(loaded) => return Ok((path.clone(), loaded)),
+            Err(e) => {
+                warn!("Failed to load cache from {path:?}: {e}");
+                failures.push((path.clone(), e));
+            }
+        }
+    }
+    Err(failures)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, process};
+
+    fn schedule(
+        interval_secs: Option<u64>,
+        max_events: Option<u64>,
+    ) -> (AutosaveSchedule, Instant) {
+        let now = Instant::now();
+        let interval = interval_secs.map(Duration::from_secs);
+        (AutosaveSchedule::new(interval, max_events, now), now)
+    }
+
+    #[test]
+    fn test_interval_only_counts_once_something_changed() {
+        let (mut schedule, start) = schedule(Some(600), Some(100));
+        let at = |secs| start + Duration::from_secs(secs);
+        assert_eq!(schedule.deadline(), None);
+
+        schedule.record_events(1);
+        assert_eq!(schedule.deadline(), Some(at(600)));
+
+        schedule.saved(at(700));
+        assert_eq!(schedule.deadline(), None);
+        schedule.record_events(5);
+        assert_eq!(schedule.deadline(), Some(at(1300)));
+    }
+
+    #[test]
+    fn test_event_count_triggers_before_the_interval() {
+        let (mut schedule, start) = schedule(Some(600), Some(100));
+        schedule.record_events(60);
+        assert_eq!(schedule.deadline(), Some(start + Duration::from_secs(600)));
+        schedule.record_events(40);
+        assert_eq!(schedule.deadline(), Some(start));
+        let now = start + Duration::from_secs(5);
+        schedule.saved(now);
+        assert_eq!(schedule.deadline(), None);
+        schedule.record_events(99);
+        assert_eq!(schedule.deadline(), Some(now + Duration::from_secs(600)));
+    }
+
+    #[test]
+    fn test_disabled_limits() {
+        let (mut schedule, start) = schedule(None, Some(10));
+        schedule.record_events(9);
+        assert_eq!(schedule.deadline(), None);
+        schedule.record_events(1);
+        assert_eq!(schedule.deadline(), Some(start));
+
+        schedule.set_limits(Some(Duration::from_secs(60)), None);
+        schedule.record_events(1_000_000);
+        assert_eq!(schedule.deadline(), Some(start + Duration::from_secs(60)));
+        schedule.set_limits(None, None);
+        assert_eq!(schedule.deadline(), None);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("cardinal-autosave-{}-{name}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Write `contents` to `dir/name`, last modified `age` ago.
+    fn cache_file(dir: &Path, name: &str, contents: &str, age: Duration) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(SystemTime::now() - age)
+            .unwrap();
+        path
+    }
+
+    fn load(path: &Path) -> Result<String, String> {
+        let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        if contents == "corrupt" {
+            Err("corrupt".to_string())
+        } else {
+            Ok(contents)
+        }
+    }
+
+    #[test]
+    fn test_newest_valid_cache_file_wins() {
+        let dir = temp_dir("newest");
+        let exit = cache_file(&dir, "cardinal.db", "exit", Duration::from_secs(3600));
+        let periodic = cache_file(&dir, "autosave.db", "periodic", Duration::from_secs(60));
+        assert_eq!(
+            load_newest(&[exit.clone(), periodic.clone()], load).unwrap(),
+            (periodic.clone(), "periodic".to_string())
+        );
+
+        // A clean exit writes after the last periodic save.
+        let exit = cache_file(&dir, "cardinal.db", "exit", Duration::ZERO);
+        assert_eq!(
+            load_newest(&[exit.clone(), periodic.clone()], load).unwrap(),
+            (exit, "exit".to_string())
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_older_file_is_used_when_the_newest_does_not_load() {
+        let dir = temp_dir("fallback");
+        let exit = cache_file(&dir, "cardinal.db", "exit", Duration::from_secs(3600));
+        let periodic = cache_file(&dir, "autosave.db", "corrupt", Duration::from_secs(60));
+        let missing = dir.join("missing.db");
+        let mut tried = Vec::new();
+        let loaded = load_newest(&[missing.clone(), exit.clone(), periodic.clone()], |path| {
+            tried.push(path.to_path_buf());
+            load(path)
+        });
+        assert_eq!(loaded.unwrap(), (exit.clone(), "exit".to_string()));
+        assert_eq!(tried, [periodic.clone(), exit.clone()]);
+
+        let exit = cache_file(&dir, "cardinal.db", "corrupt", Duration::from_secs(3600));
+        let failures =
+            load_newest(&[missing.clone(), exit.clone(), periodic.clone()], load).unwrap_err();
+        let failed: Vec<PathBuf> = failures.into_iter().map(|(path, _)| path).collect();
+        assert_eq!(failed, [periodic, exit, missing]);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,60 +1,379 @@
 use crate::{
+    AUTOSAVE_PATH,
+    autosave::AutosaveSchedule,
     commands::SearchJob,
+    folder_size::FolderSizeRequest,
     lifecycle::{AppLifecycleState, load_app_state, update_app_state},
+    pause::{EventPause, PAUSED_EVENTS_CAP, Resumed},
+    settings::Settings,
+    tray,
 };
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose};
-use cardinal_sdk::{EventFlag, EventWatcher};
-use crossbeam_channel::{Receiver, Sender};
+use cardinal_sdk::{EventFlag, EventStreamBuilder, EventWatcher, FsEvent, current_event_id};
+use crossbeam_channel::{Receiver, Sender, bounded};
 use rayon::spawn;
 use search_cache::{
-    HandleFSEError, SearchCache, SearchOptions, SearchOutcome, SearchResultNode, SlabIndex,
+    CacheSet, CursorId, HandleFSEError, MetadataErrorCounts, PendingRescan, QueryNote, SearchCache,
+    SearchOptions, SearchResult, SearchResultNode, SetIndex, SetOutcome, ValidationState,
+    WalkProgress,
 };
+use search_cancel::CancellationToken;
 use serde::Serialize;
 use std::{
-    path::PathBuf,
-    sync::atomic::{AtomicBool, Ordering},
-    time::{Duration, SystemTime, UNIX_EPOCH},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 use tauri::{AppHandle, Emitter};
-use tracing::info;
+use tracing::{info, warn};
+
+/// What the background thread is busy with, as shown by the status bar.
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum IndexState {
+    Idle,
+    /// Building the index from scratch after launch.
+    Walking,
+    ProcessingEvents,
+    /// Processing the events the watcher replays from before launch, until
+    /// it reports the end of the history.
+    ReplayingHistory,
+    Rescanning,
+    /// Writing the cache to disk, before quitting or in the background
+    /// every so often.
+    Saving,
+    /// The user paused indexing, events are held back until it's resumed.
+    Paused,
+}
+
+/// Payload of `status_bar_update`.
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexStatus {
+    pub state: IndexState,
+    pub events_processed: u64,
+    pub files_indexed: usize,
+    /// Events received but not applied to the index yet.
+    pub pending_events: usize,
+    /// Rows on screen renamed or removed since they were shown.
+    pub stale_results: usize,
+}
+
+/// Minimum time between two `status_bar_update` events, except on state changes.
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Decides which status updates are worth an event: state changes go out
+/// right away, other updates at most once per interval, keeping the latest.
+#[derive(Debug)]
+struct StatusThrottle {
+    interval: Duration,
+    /// When the last update went out and its state.
+    last_sent: Option<(Instant, IndexState)>,
+    /// The latest update held back since then.
+    pending: Option<IndexStatus>,
+}
+
+impl StatusThrottle {
+    fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_sent: None,
+            pending: None,
+        }
+    }
+
+    /// Return `status` if it should be emitted now, otherwise keep it for later.
+    fn offer(&mut self, status: IndexStatus, now: Instant) -> Option<IndexStatus> {
+        let due = match self.last_sent {
+            None => true,
+            Some((sent_at, state)) => {
+                is_transition(state, status.state) || now >= sent_at + self.interval
+            }
+        };
+        if due {
+            self.pending = None;
+            self.last_sent = Some((now, status.state));
+            Some(status)
+        } else {
+            self.pending = Some(status);
+            None
+        }
+    }
+
+    /// When the held back update is due, if there is one.
+    fn deadline(&self) -> Option<Instant> {
+        self.pending.as_ref()?;
+        self.last_sent.map(|(sent_at, _)| sent_at + self.interval)
+    }
+
+    /// Return the held back update once its deadline has passed.
+    fn take_due(&mut self, now: Instant) -> Option<IndexStatus> {
+        if self.deadline().is_none_or(|deadline| now < deadline) {
+            return None;
+        }
+        let status = self.pending.take()?;
+        self.last_sent = Some((now, status.state));
+        Some(status)
+    }
+}
+
+/// Idle, or Saving during a background save, and ProcessingEvents (or
+/// ReplayingHistory) alternate with every event batch, so only changes
+/// involving the other states bypass the rate limit.
+fn is_transition(from: IndexState, to: IndexState) -> bool {
+    from != to
+        && !matches!(
+            (from, to),
+            (
+                IndexState::Idle | IndexState::Saving,
+                IndexState::ProcessingEvents | IndexState::ReplayingHistory
+            ) | (
+                IndexState::ProcessingEvents | IndexState::ReplayingHistory,
+                IndexState::Idle | IndexState::Saving
+            )
+        )
+}
+
+/// Emits `status_bar_update` through a [`StatusThrottle`]. Shared with the
+/// walk's progress callback, which runs on rayon threads; clones share the
+/// throttle.
+#[derive(Clone)]
+pub struct StatusReporter {
+    app_handle: AppHandle,
+    throttle: Arc<Mutex<StatusThrottle>>,
+}
+
+impl StatusReporter {
+    pub fn new(app_handle: AppHandle) -> Self {
+        Self {
+            app_handle,
+            throttle: Arc::new(Mutex::new(StatusThrottle::new(STATUS_INTERVAL))),
+        }
+    }
+
+    pub fn report(&self, status: IndexStatus) {
+        let status = self.throttle.lock().unwrap().offer(status, Instant::now());
+        if let Some(status) = status {
+            self.emit(status);
+        }
+    }
+
+    /// When [`Self::flush_due`] has something to emit.
+    fn deadline(&self) -> Option<Instant> {
+        self.throttle.lock().unwrap().deadline()
+    }
+
+    /// Emit the update held back by the rate limit once it's due.
+    fn flush_due(&self) {
+        let status = self.throttle.lock().unwrap().take_due(Instant::now());
+        if let Some(status) = status {
+            self.emit(status);
+        }
+    }
+
+    fn emit(&self, status: IndexStatus) {
+        tray::show_status(&self.app_handle, &status);
+        self.app_handle.emit("status_bar_update", status).unwrap();
+    }
+}
 
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
-pub struct StatusBarUpdate {
-    pub scanned_files: usize,
-    pub processed_events: usize,
+pub struct IndexProgress {
+    pub files_seen: usize,
+    pub dirs_seen: usize,
+    pub current_path: String,
+    pub elapsed_ms: u64,
+}
+
+/// Complete results of a search whose first response was partial.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchRefinedPayload {
+    pub version: u64,
+    pub results: Vec<SetIndex>,
+    pub highlights: Vec<String>,
+    pub notes: Vec<QueryNote>,
+    pub metadata_errors: MetadataErrorCounts,
+    /// Pages `results` with their metadata, see `cursor_page`.
+    pub cursor: CursorId,
+}
+
+/// Payload of `rescan_complete`, emitted once the rescanned tree is swapped in.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RescanCompletePayload {
+    pub files_indexed: usize,
+    /// FSEvents that arrived during the walk and were replayed on the new tree.
+    pub replayed_events: usize,
+}
+
+/// Payload of `index_compacted`, emitted when event batches got the slab
+/// rebuilt and result indices renumbered.
+#[derive(Serialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct IndexCompactedPayload {
+    pub slots_reclaimed: usize,
+    pub bytes_reclaimed: usize,
 }
 
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct IconPayload {
-    pub slab_index: SlabIndex,
+    pub slab_index: SetIndex,
     pub icon: String,
 }
 
+/// Source id of the watch root's cache, the only member of the app's set.
+const WATCH_ROOT: usize = 0;
+/// Nodes that keep metadata fetched by searches, beyond that the least
+/// recently used is stat'ed again when needed.
+const METADATA_BUDGET: usize = 1_000_000;
+/// Rebuild the slab once more than this share of it is holes, e.g. after a
+/// large folder was deleted or a volume was unplugged.
+const AUTO_COMPACT_HOLE_RATIO: f64 = 0.5;
+
 pub struct BackgroundLoopChannels {
-    pub finish_rx: Receiver<Sender<Option<SearchCache>>>,
+    pub finish_rx: Receiver<Sender<Option<CacheSet>>>,
     pub search_rx: Receiver<SearchJob>,
-    pub result_tx: Sender<Result<SearchOutcome>>,
-    pub node_info_rx: Receiver<Vec<SlabIndex>>,
+    /// Outcomes of the searches, with a cursor over the complete ones.
+    pub result_tx: Sender<SearchResult<(SetOutcome, Option<CursorId>)>>,
+    pub node_info_rx: Receiver<Vec<SetIndex>>,
     pub node_info_results_tx: Sender<Vec<SearchResultNode>>,
-    pub icon_viewport_rx: Receiver<(u64, Vec<SlabIndex>)>,
-    pub rescan_rx: Receiver<()>,
+    pub icon_viewport_rx: Receiver<(u64, Vec<SetIndex>)>,
+    pub command_rx: Receiver<BackgroundCommand>,
     pub icon_update_tx: Sender<IconPayload>,
 }
 
-pub fn emit_status_bar_update(
-    app_handle: &AppHandle,
-    scanned_files: usize,
-    processed_events: usize,
-) {
-    app_handle
+/// Requests that change what the background thread indexes or how it watches.
+#[derive(Debug, Clone)]
+pub enum BackgroundCommand {
+    /// Walk the watch root again from scratch.
+    Rescan,
+    /// Switch to new settings, already validated and saved.
+    ApplySettings(Settings),
+    /// The user opened this path, boost it in frecency ranked results.
+    RecordAccess(PathBuf),
+    /// Put a tag, already validated, on the node at this path.
+    AddTag(PathBuf, String),
+    /// Take a tag off the node at this path.
+    RemoveTag(PathBuf, String),
+    /// Tell what became of these results since they were shown.
+    Validate(Vec<SetIndex>, Sender<Vec<ValidationState>>),
+    /// Expand these results with their metadata, e.g. to export them.
+    Expand(Vec<SetIndex>, Sender<Vec<SearchResultNode>>),
+    /// Collect the files below a folder for the folder size workers.
+    FolderSize(FolderSizeRequest),
+    /// Hold FSEvents back instead of applying them, or apply them again.
+    SetPaused(bool),
+    /// Expand a page of a cursor's results: its id, offset and limit.
+    CursorPage(
+        CursorId,
+        usize,
+        usize,
+        Sender<SearchResult<Option<Vec<SearchResultNode>>>>,
+    ),
+    /// The frontend is done paging these results.
+    CloseCursor(CursorId),
+}
+
+/// Work needed to bring the cache and the watcher in line after a command,
+/// from cheapest to most expensive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CommandEffect {
+    /// Only settings read per request (e.g. `max_results`) changed.
+    None,
+    /// Same tree, but the FSEvents stream must be re-created.
+    RestartWatcher,
+    /// Walk the (possibly new) watch root again, applying the current excludes.
+    Rescan,
+}
+
+/// Open a cursor over `results`, closing the one over the previous results.
+fn reopen_cursor(cache: &CacheSet, last: &mut Option<CursorId>, results: &[SetIndex]) -> CursorId {
+    if let Some(previous) = last.take() {
+        cache.close_cursor(previous);
+    }
+    *last.insert(cache.open_cursor_over(results.to_vec()))
+}
+
+/// Add or remove `tag` on the node at `path`, returning whether it changed.
+fn change_tag(cache: &mut CacheSet, path: &Path, tag: &str, add: bool) -> bool {
+    let Some(index) = cache.node_index_for_path_ci(path) else {
+        warn!("Not tagging {path:?}, it isn't indexed");
+        return false;
+    };
+    if !add {
+        return cache.remove_tag(index, tag);
+    }
+    cache.add_tag(index, tag).unwrap_or_else(|e| {
+        warn!("Failed to tag {path:?}: {e:?}");
+        false
+    })
+}
+
+/// Update `settings` according to `command` and return what has to be redone.
+fn apply_command(settings: &mut Settings, command: BackgroundCommand) -> CommandEffect {
+    match command {
+        BackgroundCommand::Rescan => CommandEffect::Rescan,
+        BackgroundCommand::RecordAccess(_)
+        | BackgroundCommand::AddTag(..)
+        | BackgroundCommand::RemoveTag(..)
+        | BackgroundCommand::Validate(..)
+        | BackgroundCommand::Expand(..)
+        | BackgroundCommand::FolderSize(_)
+        | BackgroundCommand::SetPaused(_)
+        | BackgroundCommand::CursorPage(..)
+        | BackgroundCommand::CloseCursor(_) => CommandEffect::None,
+        BackgroundCommand::ApplySettings(new) => {
+            let effect = if new.watch_roots != settings.watch_roots
+                || new.excludes != settings.excludes
+                || new.index_firmlink_duplicates != settings.index_firmlink_duplicates
+            {
+                CommandEffect::Rescan
+            } else if new.fse_latency != settings.fse_latency {
+                CommandEffect::RestartWatcher
+            } else {
+                CommandEffect::None
+            };
+            *settings = new;
+            effect
+        }
+    }
+}
+
+/// How many of the rows on screen were renamed or removed since they were shown.
+fn count_stale(cache: &CacheSet, pinned: &[SetIndex]) -> usize {
+    cache
+        .validate(pinned)
+        .iter()
+        .filter(|state| **state != ValidationState::Valid)
+        .count()
+}
+
+/// Searches running longer than this answer with partial results first.
+const PARTIAL_RESULTS_DEADLINE: Duration = Duration::from_millis(150);
+
+/// Forward filesystem walk progress to the status bar and as an `index_progress` event.
+pub fn emit_index_progress(status: &StatusReporter, state: IndexState, progress: WalkProgress) {
+    status.report(IndexStatus {
+        state,
+        events_processed: 0,
+        files_indexed: progress.files_seen + progress.dirs_seen,
+        pending_events: 0,
+        stale_results: 0,
+    });
+    status
+        .app_handle
         .emit(
-            "status_bar_update",
-            StatusBarUpdate {
-                scanned_files,
-                processed_events,
+            "index_progress",
+            IndexProgress {
+                files_seen: progress.files_seen,
+                dirs_seen: progress.dirs_seen,
+                current_path: progress.current_path.to_string_lossy().into_owned(),
+                elapsed_ms: progress.elapsed.as_millis() as u64,
             },
         )
         .unwrap();
@@ -78,11 +397,11 @@ struct RecentEvent {
 
 pub fn run_background_event_loop(
     app_handle: &AppHandle,
-    mut cache: SearchCache,
+    status: &StatusReporter,
+    mut cache: CacheSet,
     mut event_watcher: EventWatcher,
     channels: BackgroundLoopChannels,
-    watch_root: &str,
-    fse_latency_secs: f64,
+    mut settings: Settings,
 ) {
     let BackgroundLoopChannels {
         finish_rx,
@@ -91,15 +410,76 @@ pub fn run_background_event_loop(
         node_info_rx,
         node_info_results_tx,
         icon_viewport_rx,
-        rescan_rx,
+        command_rx,
         icon_update_tx,
     } = channels;
-    let mut processed_events = 0usize;
+    cache.set_metadata_budget(Some(METADATA_BUDGET));
+    cache.set_auto_compact(Some(AUTO_COMPACT_HOLE_RATIO));
+    // Rows on screen, whose metadata is never evicted.
+    let mut pinned: Vec<SetIndex> = Vec::new();
+    let mut stale_results = 0;
+    let mut processed_events = 0u64;
     let mut history_ready = load_app_state() == AppLifecycleState::Ready;
+    // Complete results of the latest search, what "search in results" narrows.
+    let mut last_results: Option<(u64, Vec<SetIndex>)> = None;
+    // Cursor over them, only the latest results are paged.
+    let mut last_cursor: Option<CursorId> = None;
+    // Searches keep hitting the old tree while a rescan walks the new one.
+    let mut rescan: Option<PendingRescan> = None;
+    let no_rescan = crossbeam_channel::never();
+    let mut autosave = AutosaveSchedule::new(
+        settings.autosave_interval(),
+        settings.autosave_max_events(),
+        Instant::now(),
+    );
+    // Outcome of the background save in progress.
+    let mut saving: Option<Receiver<Result<()>>> = None;
+    let no_save = crossbeam_channel::never();
+    // Indexing paused from the tray, events are held back meanwhile.
+    let mut pause = EventPause::new(PAUSED_EVENTS_CAP);
     loop {
+        // Events to apply once the select is done: a batch, or the ones held
+        // back while indexing was paused.
+        let mut arrived: Option<Vec<FsEvent>> = None;
+        let status_due = status
+            .deadline()
+            .map(crossbeam_channel::at)
+            .unwrap_or_else(crossbeam_channel::never);
+        // One save at a time, and only of a tree that caught up with the event history.
+        let autosave_due = autosave
+            .deadline()
+            .filter(|_| saving.is_none() && rescan.is_none() && history_ready)
+            .map(crossbeam_channel::at)
+            .unwrap_or_else(crossbeam_channel::never);
         crossbeam_channel::select! {
+            recv(status_due) -> _ => status.flush_due(),
+            recv(autosave_due) -> _ => {
+                autosave.saved(Instant::now());
+                saving = start_autosave(status, &cache, processed_events, stale_results);
+            }
+            recv(saving.as_ref().unwrap_or(&no_save)) -> saved => {
+                saving = None;
+                match saved.expect("Autosave thread exited") {
+                    Ok(()) => info!("Cache saved in the background to {:?}", &*AUTOSAVE_PATH),
+                    Err(e) => warn!("Background cache save failed: {e:?}"),
+                }
+                if rescan.is_none() {
+                    report_settled(status, &cache, &pause, processed_events, false, stale_results);
+                }
+            }
             recv(finish_rx) -> tx => {
                 let tx = tx.expect("Finish channel closed");
+                if let Some(saving) = saving.take() {
+                    // Let it finish, the exit-time file is written after it.
+                    let _ = saving.recv();
+                }
+                status.report(IndexStatus {
+                    state: IndexState::Saving,
+                    events_processed: processed_events,
+                    files_indexed: cache.get_total_files(),
+                    pending_events: 0,
+                    stale_results,
+                });
                 tx.send(Some(cache)).expect("Failed to send cache");
                 return;
             }
@@ -107,11 +487,72 @@ pub fn run_background_event_loop(
                 let SearchJob {
                     query,
                     options,
+                    version,
+                    within,
                     cancellation_token,
                 } = job.expect("Search channel closed");
-                let opts = SearchOptions::from(options);
-                let payload = cache.search_with_options(&query, opts, cancellation_token);
+                if let Some(previous) = within {
+                    let payload = match &last_results {
+                        Some((last, base)) if *last == previous => cache.search_within(
+                            base,
+                            &query,
+                            options.scope,
+                            SearchOptions::from(options),
+                            cancellation_token,
+                        ),
+                        _ => Err(anyhow!("Results of search {previous} are no longer available").into()),
+                    };
+                    let payload = payload.map(|mut outcome| {
+                        settings.limit_results(&mut outcome.nodes);
+                        last_results = Some((version, outcome.nodes.clone()));
+                        let cursor = reopen_cursor(&cache, &mut last_cursor, &outcome.nodes);
+                        (outcome, Some(cursor))
+                    });
+                    result_tx.send(payload).expect("Failed to send result");
+                    continue;
+                }
+                let opts = SearchOptions::from(options).with_deadline(PARTIAL_RESULTS_DEADLINE);
+                let payload = cache.search_with_options(&query, options.scope, opts, cancellation_token);
+                let payload = payload.map(|mut outcome| {
+                    settings.limit_results(&mut outcome.nodes);
+                    last_results = (!outcome.partial).then(|| (version, outcome.nodes.clone()));
+                    // Partial results are replaced by the refined ones, no use paging them.
+                    let cursor = (!outcome.partial)
+                        .then(|| reopen_cursor(&cache, &mut last_cursor, &outcome.nodes));
+                    (outcome, cursor)
+                });
+                let refine = matches!(&payload, Ok((outcome, _)) if outcome.partial)
+                    && !cancellation_token.is_superseded();
                 result_tx.send(payload).expect("Failed to send result");
+                if refine {
+                    // Show the partial results right away and finish the query in full.
+                    let opts = SearchOptions::from(options).with_strict_cancellation(true);
+                    if let Ok(SetOutcome {
+                        nodes: mut results,
+                        highlights,
+                        notes,
+                        metadata_errors,
+                        ..
+                    }) = cache.search_with_options(&query, options.scope, opts, cancellation_token)
+                    {
+                        settings.limit_results(&mut results);
+                        last_results = Some((version, results.clone()));
+                        let cursor = reopen_cursor(&cache, &mut last_cursor, &results);
+                        app_handle
+                            .emit(
+                                "search_refined",
+                                SearchRefinedPayload {
+                                    version,
+                                    results,
+                                    highlights,
+                                    notes,
+                                    metadata_errors,
+                                    cursor,
+                                },
+                            )
+                            .unwrap();
+                    }
+                }
             }
             recv(node_info_rx) -> results => {
                 let results = results.expect("Node info channel closed");
@@ -120,6 +561,10 @@ pub fn run_background_event_loop(
             }
             recv(icon_viewport_rx) -> update => {
                 let (_request_id, viewport) = update.expect("Icon viewport channel closed");
+                cache.unpin_metadata(&pinned);
+                cache.pin_metadata(&viewport);
+                pinned.clone_from(&viewport);
+                stale_results = count_stale(&cache, &pinned);
 
                 let nodes = cache.expand_file_nodes(&viewport);
                 let icon_jobs: Vec<_> = viewport
@@ -148,102 +593,438 @@ pub fn run_background_event_loop(
                         });
                     });
             }
-            recv(rescan_rx) -> request => {
-                request.expect("Rescan channel closed");
-                info!("Manual rescan requested");
-                perform_rescan(
-                    app_handle,
-                    &mut cache,
-                    &mut event_watcher,
-                    watch_root,
-                    fse_latency_secs,
-                    &mut history_ready,
-                );
+            recv(command_rx) -> command => {
+                let command = command.expect("Command channel closed");
+                if let BackgroundCommand::RecordAccess(path) = &command {
+                    if let Some(index) = cache.node_index_for_path_ci(path) {
+                        cache.record_access(index);
+                    }
+                    continue;
+                }
+                if let BackgroundCommand::Validate(results, reply) = &command {
+                    let _ = reply.send(cache.validate(results));
+                    continue;
+                }
+                if let BackgroundCommand::Expand(results, reply) = &command {
+                    let _ = reply.send(cache.expand_file_nodes(results));
+                    continue;
+                }
+                if let BackgroundCommand::CursorPage(cursor, offset, limit, reply) = &command {
+                    let _ = reply.send(cache.cursor_page(*cursor, *offset, *limit));
+                    continue;
+                }
+                if let BackgroundCommand::CloseCursor(cursor) = &command {
+                    cache.close_cursor(*cursor);
+                    continue;
+                }
+                if let BackgroundCommand::FolderSize(request) = &command {
+                    // Only the in-memory part, the workers stat what isn't known.
+                    let job = cache.folder_size_job(request.index, CancellationToken::noop());
+                    request.clone().submit(job);
+                    continue;
+                }
+                if let BackgroundCommand::AddTag(path, tag) | BackgroundCommand::RemoveTag(path, tag) = &command {
+                    let add = matches!(command, BackgroundCommand::AddTag(..));
+                    // Tags are only kept in the cache file, save them like any other change.
+                    if change_tag(&mut cache, path, tag, add) {
+                        autosave.record_events(1);
+                    }
+                    continue;
+                }
+                info!("Background command: {command:?}");
+                if let BackgroundCommand::SetPaused(paused) = command {
+                    let resumed = if paused {
+                        pause.pause();
+                        Resumed::Nothing
+                    } else {
+                        pause.resume()
+                    };
+                    match resumed {
+                        Resumed::Nothing => {
+                            if rescan.is_none() {
+                                report_settled(status, &cache, &pause, processed_events, saving.is_some(), stale_results);
+                            }
+                        }
+                        Resumed::Replay(events) => {
+                            info!("Applying {} events held back during the pause", events.len());
+                            arrived = Some(events);
+                        }
+                        Resumed::Rescan { dropped } => {
+                            info!("{dropped} events came in during the pause, rescanning instead");
+                            rescan = Some(start_rescan(
+                                app_handle,
+                                status,
+                                &cache,
+                                &mut event_watcher,
+                                &mut pause,
+                                &settings,
+                                &mut history_ready,
+                            ));
+                        }
+                    }
+                }
+                let effect = apply_command(&mut settings, command);
+                autosave.set_limits(settings.autosave_interval(), settings.autosave_max_events());
+                match effect {
+                    CommandEffect::None => {}
+                    CommandEffect::RestartWatcher => {
+                        event_watcher = spawn_event_watcher(&mut cache, &settings);
+                    }
+                    CommandEffect::Rescan => {
+                        cache
+                            .member_mut(WATCH_ROOT)
+                            .expect("Watch root left the cache set")
+                            .set_ignore_paths(settings.index_ignore_paths());
+                        // A rescan still walking is superseded, its tree is dropped.
+                        rescan = Some(start_rescan(
+                            app_handle,
+                            status,
+                            &cache,
+                            &mut event_watcher,
+                            &mut pause,
+                            &settings,
+                            &mut history_ready,
+                        ));
+                    }
+                }
             }
-            recv(event_watcher) -> events => {
-                let events = events.expect("Event stream closed");
-                processed_events += events.len();
-
-                emit_status_bar_update(app_handle, cache.get_total_files(), processed_events);
-
-                let mut snapshots = Vec::with_capacity(events.len());
-                for event in events.iter() {
-                    if event.flag == EventFlag::HistoryDone {
-                        history_ready = true;
+            recv(rescan.as_ref().map_or(&no_rescan, PendingRescan::walked)) -> walked => {
+                let pending = rescan.take().expect("Rescan walked without a rescan");
+                let walked = walked.expect("Rescan thread exited");
+                let stopped = walked.is_none();
+                let replayed_events = pending.buffered_events();
+                let replayed = cache
+                    .member_mut(WATCH_ROOT)
+                    .expect("Watch root left the cache set")
+                    .finish_rescan(pending, walked);
+                last_results = None;
+                follow_compactions(app_handle, &mut cache, &mut pinned, &mut last_results);
+                stale_results = count_stale(&cache, &pinned);
+                if stopped {
+                    event_watcher = EventWatcher::noop();
+                } else {
+                    if history_ready {
                         update_app_state(app_handle, AppLifecycleState::Ready);
-                    } else if history_ready {
-                        snapshots.push(EventSnapshot {
-                            path: event.path.clone(),
-                            event_id: event.id,
-                            flag: event.flag,
-                            timestamp: unix_timestamp_now(),
-                        });
                     }
+                    app_handle
+                        .emit(
+                            "rescan_complete",
+                            RescanCompletePayload {
+                                files_indexed: cache.get_total_files(),
+                                replayed_events,
+                            },
+                        )
+                        .unwrap();
                 }
-
-                let handle_result = cache.handle_fs_events(events);
-                if let Err(HandleFSEError::Rescan) = handle_result {
+                if let Err(HandleFSEError::Rescan) = replayed {
                     info!("!!!!!!!!!! Rescan triggered !!!!!!!!");
-                    perform_rescan(
+                    rescan = Some(start_rescan(
                         app_handle,
-                        &mut cache,
+                        status,
+                        &cache,
                         &mut event_watcher,
-                        watch_root,
-                        fse_latency_secs,
+                        &mut pause,
+                        &settings,
                         &mut history_ready,
-                    );
+                    ));
+                } else {
+                    report_settled(status, &cache, &pause, processed_events, saving.is_some(), stale_results);
                 }
-
-                if history_ready && !snapshots.is_empty() {
-                    forward_new_events(app_handle, &snapshots);
+            }
+            recv(event_watcher) -> batch => {
+                let batch = batch.expect("Event stream closed");
+                arrived = pause.offer(batch.events);
+                // A rescan walking reports its own progress.
+                if arrived.is_none() && rescan.is_none() {
+                    report_settled(status, &cache, &pause, processed_events, saving.is_some(), stale_results);
                 }
             }
         }
+
+        if arrived.is_none() {
+            // Nodes searches found deleted behind our back go through the
+            // event path like real removals, held back while paused.
+            let vanished = cache.take_vanished();
+            if !vanished.is_empty() {
+                info!("Removing {} nodes missing on disk", vanished.len());
+                arrived = pause.offer(vanished);
+            }
+        }
+        let Some(events) = arrived else {
+            continue;
+        };
+        if let Some(rescan) = &mut rescan {
+            // The new tree gets them, and the app is ready once it's swapped in.
+            history_ready |= events
+                .iter()
+                .any(|event| event.flag.contains(EventFlag::HistoryDone));
+            processed_events += events.len() as u64;
+            autosave.record_events(events.len() as u64);
+            rescan.buffer_events(events);
+            status.report(IndexStatus {
+                state: IndexState::Rescanning,
+                events_processed: processed_events,
+                files_indexed: cache.get_total_files(),
+                pending_events: rescan.buffered_events(),
+                stale_results,
+            });
+            continue;
+        }
+        status.report(IndexStatus {
+            state: if cache.replaying() {
+                IndexState::ReplayingHistory
+            } else {
+                IndexState::ProcessingEvents
+            },
+            events_processed: processed_events,
+            files_indexed: cache.get_total_files(),
+            pending_events: events.len(),
+            stale_results,
+        });
+        processed_events += events.len() as u64;
+        autosave.record_events(events.len() as u64);
+
+        let mut snapshots = Vec::with_capacity(events.len());
+        for event in &events {
+            if event.flag == EventFlag::HistoryDone {
+                history_ready = true;
+                update_app_state(app_handle, AppLifecycleState::Ready);
+            } else if history_ready {
+                snapshots.push(EventSnapshot {
+                    path: event.path.clone(),
+                    event_id: event.id,
+                    flag: event.flag,
+                    timestamp: unix_timestamp_now(),
+                });
+            }
+        }
+
+        let rescans = cache.handle_fs_events(events);
+        follow_compactions(app_handle, &mut cache, &mut pinned, &mut last_results);
+        stale_results = count_stale(&cache, &pinned);
+        if rescans.contains(&WATCH_ROOT) {
+            info!("!!!!!!!!!! Rescan triggered !!!!!!!!");
+            rescan = Some(start_rescan(
+                app_handle,
+                status,
+                &cache,
+                &mut event_watcher,
+                &mut pause,
+                &settings,
+                &mut history_ready,
+            ));
+        } else {
+            report_idle(
+                status,
+                &cache,
+                processed_events,
+                saving.is_some(),
+                stale_results,
+            );
+        }
+
+        if history_ready && !snapshots.is_empty() {
+            forward_new_events(app_handle, &snapshots);
+        }
     }
 }
 
-fn perform_rescan(
+/// Renumber the nodes the loop holds after automatic compactions and tell the
+/// frontend, whose result indices are stale from then on.
+fn follow_compactions(
     app_handle: &AppHandle,
-    cache: &mut SearchCache,
+    cache: &mut CacheSet,
+    pinned: &mut Vec<SetIndex>,
+    last_results: &mut Option<(u64, Vec<SetIndex>)>,
+) {
+    let compactions = cache.take_compactions();
+    if compactions.is_empty() {
+        return;
+    }
+    let mut payload = IndexCompactedPayload {
+        slots_reclaimed: 0,
+        bytes_reclaimed: 0,
+    };
+    for (source, stats) in &compactions {
+        stats.remap.apply_to_set(*source, pinned);
+        if let Some((_, results)) = last_results {
+            stats.remap.apply_to_set(*source, results);
+        }
+        payload.slots_reclaimed += stats.slots_reclaimed();
+        payload.bytes_reclaimed += stats.bytes_reclaimed;
+    }
+    info!(
+        "Slab compacted: {} slots, {} bytes reclaimed",
+        payload.slots_reclaimed, payload.bytes_reclaimed
+    );
+    app_handle.emit("index_compacted", payload).unwrap();
+}
+
+/// Start walking the watch root of `settings` from scratch in the background;
+/// it differs from the cache's root after the user picked another one. The
+/// watcher starts over from now on that root, what it reports during the walk
+/// is buffered for the new tree. Searches are answered from the old tree
+/// meanwhile, so the app only goes back to Updating.
+fn start_rescan(
+    app_handle: &AppHandle,
+    status: &StatusReporter,
+    cache: &CacheSet,
     event_watcher: &mut EventWatcher,
-    watch_root: &str,
-    fse_latency_secs: f64,
+    pause: &mut EventPause,
+    settings: &Settings,
     history_ready: &mut bool,
-) {
-    *event_watcher = EventWatcher::noop();
-    update_app_state(app_handle, AppLifecycleState::Initializing);
-    emit_status_bar_update(app_handle, 0, 0);
+) -> PendingRescan {
+    update_app_state(app_handle, AppLifecycleState::Updating);
+    status.report(IndexStatus {
+        state: IndexState::Rescanning,
+        events_processed: 0,
+        files_indexed: 0,
+        pending_events: 0,
+        stale_results: 0,
+    });
     *history_ready = false;
 
-    let walk_data = cache.walk_data();
-    let walking_done = AtomicBool::new(false);
-    let stopped = std::thread::scope(|s| {
-        s.spawn(|| {
-            while !walking_done.load(Ordering::Relaxed) {
-                let dirs = walk_data.num_dirs.load(Ordering::Relaxed);
-                let files = walk_data.num_files.load(Ordering::Relaxed);
-                let total = dirs + files;
-                emit_status_bar_update(app_handle, total, 0);
-                std::thread::sleep(Duration::from_millis(100));
-            }
-        });
-        // If rescan is cancelled, we have nothing to do
-        let stopped = cache.rescan_with_walk_data(&walk_data).is_none();
-        walking_done.store(true, Ordering::Relaxed);
-        stopped
+    let root = settings.watch_root();
+    // The walk sees whatever they were about.
+    let dropped = event_watcher.clear();
+    let dropped = dropped + pause.discard();
+    if dropped > 0 {
+        info!("Dropped {dropped} pending events for the rescan");
+    }
+    *event_watcher = watch_root_since(root, current_event_id(), settings);
+    let status = status.clone();
+    let cache = cache
+        .member(WATCH_ROOT)
+        .expect("Watch root left the cache set");
+    cache.rewalk_async(
+        root.to_path_buf(),
+        Some(Box::new(move |progress| {
+            emit_index_progress(&status, IndexState::Rescanning, progress)
+        })),
+    )
+}
+
+/// Encode the watch root's cache and write it to [`AUTOSAVE_PATH`] on a
+/// thread of its own, searches and events go on meanwhile. The receiver gets
+/// the outcome; `None` if there was nothing to write.
+fn start_autosave(
+    status: &StatusReporter,
+    cache: &CacheSet,
+    events_processed: u64,
+    stale_results: usize,
+) -> Option<Receiver<Result<()>>> {
+    let started = Instant::now();
+    let member = cache
+        .member(WATCH_ROOT)
+        .expect("Watch root left the cache set");
+    let save = encode_for_autosave(member)?;
+    info!(
+        "Snapshot taken in {:?}, saving it in the background",
+        started.elapsed()
+    );
+    status.report(IndexStatus {
+        state: IndexState::Saving,
+        events_processed,
+        files_indexed: cache.get_total_files(),
+        pending_events: 0,
+        stale_results,
     });
+    let (saved_tx, saved_rx) = bounded(1);
+    thread::Builder::new()
+        .name("cardinal-autosave".to_string())
+        .spawn(move || {
+            let _ = saved_tx.send(save());
+        })
+        .expect("Failed to spawn the autosave thread");
+    Some(saved_rx)
+}
 
-    *event_watcher = if stopped {
-        EventWatcher::noop()
-    } else {
-        EventWatcher::spawn(
-            watch_root.to_string(),
-            cache.last_event_id(),
-            fse_latency_secs,
-        )
-        .1
+/// What the autosave thread runs: write the cache encoded here.
+#[cfg(not(feature = "snapshots"))]
+fn encode_for_autosave(cache: &SearchCache) -> Option<impl FnOnce() -> Result<()> + Send + use<>> {
+    let snapshot = match cache.snapshot() {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            warn!("Failed to snapshot the cache: {e:?}");
+            return None;
+        }
     };
-    update_app_state(app_handle, AppLifecycleState::Updating);
+    info!("Encoded {} bytes", snapshot.len());
+    Some(move || snapshot.write_to_file(&AUTOSAVE_PATH))
+}
+
+/// What the autosave thread runs: encode the tree as it is now, then write
+/// it, without holding up events for the encoding.
+#[cfg(feature = "snapshots")]
+fn encode_for_autosave(cache: &SearchCache) -> Option<impl FnOnce() -> Result<()> + Send + use<>> {
+    let snapshot = cache.search_snapshot();
+    Some(move || snapshot.encode()?.write_to_file(&AUTOSAVE_PATH))
+}
+
+/// Report that the background thread caught up, still Saving while a
+/// background save is writing.
+pub fn report_idle(
+    status: &StatusReporter,
+    cache: &CacheSet,
+    events_processed: u64,
+    saving: bool,
+    stale_results: usize,
+) {
+    status.report(IndexStatus {
+        state: if saving {
+            IndexState::Saving
+        } else {
+            IndexState::Idle
+        },
+        events_processed,
+        files_indexed: cache.get_total_files(),
+        pending_events: 0,
+        stale_results,
+    });
+}
+
+/// Like [`report_idle`], but while indexing is paused report that instead,
+/// with the events held back as pending.
+fn report_settled(
+    status: &StatusReporter,
+    cache: &CacheSet,
+    pause: &EventPause,
+    events_processed: u64,
+    saving: bool,
+    stale_results: usize,
+) {
+    if !pause.is_paused() {
+        report_idle(status, cache, events_processed, saving, stale_results);
+        return;
+    }
+    status.report(IndexStatus {
+        state: IndexState::Paused,
+        events_processed,
+        files_indexed: cache.get_total_files(),
+        pending_events: pause.pending(),
+        stale_results,
+    });
+}
+
+/// Watch the watch root's cache for events after the last one it has seen.
+/// The stream replays the history since then first, the cache set treats it
+/// as such until `HistoryDone`.
+pub fn spawn_event_watcher(cache: &mut CacheSet, settings: &Settings) -> EventWatcher {
+    cache.begin_replay();
+    let since = cache.last_event_id();
+    let root = cache
+        .member(WATCH_ROOT)
+        .expect("Watch root left the cache set")
+        .root();
+    watch_root_since(root, since, settings)
+}
+
+fn watch_root_since(root: &Path, since: u64, settings: &Settings) -> EventWatcher {
+    EventStreamBuilder::new([root.to_string_lossy()])
+        .since(since)
+        .latency(settings.fse_latency)
+        .spawn()
+        .1
 }
 
 fn unix_timestamp_now() -> i64 {
@@ -276,3 +1057,135 @@ fn forward_new_events(app_handle: &AppHandle, snapshots: &[EventSnapshot]) {
 
     let _ = app_handle.emit("fs_events_batch", new_events);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn status(state: IndexState, events_processed: u64) -> IndexStatus {
+        IndexStatus {
+            state,
+            events_processed,
+            files_indexed: 10,
+            pending_events: 0,
+            stale_results: 0,
+        }
+    }
+
+    #[test]
+    fn test_status_throttle_coalesces_updates() {
+        let mut throttle = StatusThrottle::new(Duration::from_millis(250));
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        let processing = |n| status(IndexState::ProcessingEvents, n);
+        // The first update goes out right away.
+        assert_eq!(throttle.offer(processing(1), at(0)), Some(processing(1)));
+        assert_eq!(throttle.deadline(), None);
+        // Later ones within the interval are held back, the latest wins.
+        assert_eq!(throttle.offer(processing(2), at(50)), None);
+        assert_eq!(throttle.offer(processing(3), at(100)), None);
+        assert_eq!(throttle.deadline(), Some(at(250)));
+        assert_eq!(throttle.take_due(at(200)), None);
+        assert_eq!(throttle.take_due(at(250)), Some(processing(3)));
+        assert_eq!(throttle.take_due(at(600)), None);
+        // Batches keep flipping between idle and processing without bypassing the limit.
+        assert_eq!(throttle.offer(status(IndexState::Idle, 3), at(300)), None);
+        assert_eq!(throttle.offer(processing(4), at(500)), Some(processing(4)));
+    }
+
+    #[test]
+    fn test_status_throttle_forces_state_transitions() {
+        let mut throttle = StatusThrottle::new(Duration::from_millis(250));
+        let now = Instant::now();
+        assert!(throttle.offer(status(IndexState::Idle, 0), now).is_some());
+        assert!(
+            throttle
+                .offer(status(IndexState::ProcessingEvents, 1), now)
+                .is_none()
+        );
+        // A transition supersedes the held back update.
+        let rescanning = status(IndexState::Rescanning, 0);
+        assert_eq!(throttle.offer(rescanning.clone(), now), Some(rescanning));
+        assert_eq!(throttle.deadline(), None);
+        assert!(
+            throttle
+                .offer(status(IndexState::Rescanning, 0), now)
+                .is_none()
+        );
+        let idle = status(IndexState::Idle, 1);
+        assert_eq!(throttle.offer(idle.clone(), now), Some(idle));
+        let saving = status(IndexState::Saving, 1);
+        assert_eq!(throttle.offer(saving.clone(), now), Some(saving));
+        // Events processed during a background save don't bypass the limit either.
+        assert!(
+            throttle
+                .offer(status(IndexState::ProcessingEvents, 2), now)
+                .is_none()
+        );
+        assert!(throttle.offer(status(IndexState::Saving, 2), now).is_none());
+        let idle = status(IndexState::Idle, 2);
+        assert_eq!(throttle.offer(idle.clone(), now), Some(idle));
+    }
+
+    fn apply(settings: &mut Settings, f: impl FnOnce(&mut Settings)) -> CommandEffect {
+        let mut new = settings.clone();
+        f(&mut new);
+        apply_command(settings, BackgroundCommand::ApplySettings(new))
+    }
+
+    #[test]
+    fn test_rescan_command_keeps_settings() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            apply_command(&mut settings, BackgroundCommand::Rescan),
+            CommandEffect::Rescan
+        );
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[test]
+    fn test_apply_settings_effects() {
+        let mut settings = Settings::default();
+        assert_eq!(apply(&mut settings, |_| {}), CommandEffect::None);
+        assert_eq!(
+            apply(&mut settings, |s| s.max_results = 100),
+            CommandEffect::None
+        );
+        assert_eq!(settings.max_results, 100);
+        assert_eq!(
+            apply(&mut settings, |s| s.fse_latency = 1.0),
+            CommandEffect::RestartWatcher
+        );
+        assert_eq!(
+            apply(&mut settings, |s| s.excludes.push("/private/var".into())),
+            CommandEffect::Rescan
+        );
+        assert_eq!(
+            apply(&mut settings, |s| s.watch_roots =
+                vec![PathBuf::from("/Users")]),
+            CommandEffect::Rescan
+        );
+        assert_eq!(settings.watch_root(), std::path::Path::new("/Users"));
+        assert_eq!(settings.excludes.len(), 2);
+        assert_eq!(
+            apply(&mut settings, |s| s.index_firmlink_duplicates = true),
+            CommandEffect::Rescan
+        );
+    }
+
+    #[test]
+    fn test_most_expensive_change_wins() {
+        let mut settings = Settings::default();
+        assert_eq!(
+            apply(&mut settings, |s| {
+                s.fse_latency = 2.0;
+                s.excludes.clear();
+                s.max_results = 10;
+            }),
+            CommandEffect::Rescan
+        );
+        assert_eq!(settings.fse_latency, 2.0);
+        assert_eq!(settings.max_results, 10);
+        assert_eq!(settings.ignore_paths(), None);
+    }
+}
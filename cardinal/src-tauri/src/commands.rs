@@ -1,16 +1,36 @@
 use crate::{
     LOGIC_START,
-    lifecycle::{EXIT_REQUESTED, load_app_state},
+    background::BackgroundCommand,
+    export::{
+        EXPORT_PROGRESS_EVENT, ExportFormatPayload, ExportReceipt, choose_export_path,
+        export_to_file,
+    },
+    folder_size::{FolderSizeTicket, FolderSizes},
+    lifecycle::{AppLifecycleState, EXIT_REQUESTED, load_app_state},
+    quick_search,
+    settings::{SETTINGS_PATH, Settings},
     window_controls::{WindowToggle, activate_window, hide_window, toggle_window},
 };
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use base64::{Engine as _, engine::general_purpose};
-use crossbeam_channel::{Receiver, Sender};
-use search_cache::{SearchOptions, SearchOutcome, SearchResultNode, SlabIndex, SlabNodeMetadata};
+use crossbeam_channel::{Receiver, Sender, bounded};
+use fswalk::NodeFileType;
+use parking_lot::Mutex;
+use search_cache::{
+    CursorId, MetadataErrorCounts, QueryNote, RankMode, Scorer, SearchError, SearchOptions,
+    SearchResult, SearchResultNode, SetIndex, SetOutcome, SlabNodeMetadata, ValidationState,
+    normalize_tag,
+};
 use search_cancel::CancellationToken;
 use serde::{Deserialize, Serialize};
-use std::{process::Command, sync::atomic::Ordering};
-use tauri::{AppHandle, Manager, State};
+use std::{
+    ffi::OsString,
+    os::unix::ffi::OsStringExt,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tauri::{AppHandle, Emitter, Manager, State};
 use tracing::{info, warn};
 
 #[derive(Debug, Clone, Copy, Deserialize, Default)]
@@ -18,11 +38,60 @@ use tracing::{info, warn};
 pub struct SearchOptionsPayload {
     #[serde(default)]
     pub case_insensitive: bool,
+    /// Keep dotfiles and files flagged hidden, see
+    /// [`SearchOptions::include_hidden`].
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// `none`, `recentFirst` or `frecency`; recently modified files come first
+    /// when omitted.
+    #[serde(default)]
+    pub rank: Option<RankMode>,
+    /// `recentFirst` or `shallowFirst`, one of the built-in scorers, ranking
+    /// in place of `rank`.
+    #[serde(default)]
+    pub scorer: Option<ScorerPayload>,
+    /// Index of a folder result to search inside of.
+    #[serde(default)]
+    pub scope: Option<SetIndex>,
 }
 
+/// `scope` is left out, it also names the member of the cache set the folder
+/// is in and goes to [`search_cache::CacheSet::search_with_options`] itself.
 impl From<SearchOptionsPayload> for SearchOptions {
-    fn from(SearchOptionsPayload { case_insensitive }: SearchOptionsPayload) -> Self {
-        SearchOptions { case_insensitive }
+    fn from(
+        SearchOptionsPayload {
+            case_insensitive,
+            include_hidden,
+            rank,
+            scorer,
+            scope: _,
+        }: SearchOptionsPayload,
+    ) -> Self {
+        let options = SearchOptions::default()
+            .with_case_insensitive(case_insensitive)
+            .with_include_hidden(include_hidden)
+            .with_rank(rank.unwrap_or(RankMode::RecentFirst));
+        match scorer {
+            Some(scorer) => options.with_scorer(scorer.into()),
+            None => options,
+        }
+    }
+}
+
+/// Scorers the frontend can pick by name, closures don't cross IPC.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScorerPayload {
+    RecentFirst,
+    ShallowFirst,
+}
+
+impl From<ScorerPayload> for Scorer {
+    fn from(scorer: ScorerPayload) -> Self {
+        match scorer {
+            ScorerPayload::RecentFirst => Scorer::RECENT_FIRST,
+            ScorerPayload::ShallowFirst => Scorer::SHALLOW_FIRST,
+        }
     }
 }
 
@@ -30,29 +99,39 @@ impl From<SearchOptionsPayload> for SearchOptions {
 pub struct SearchJob {
     pub query: String,
     pub options: SearchOptionsPayload,
+    pub version: u64,
+    /// Version of the search whose results this one narrows down.
+    pub within: Option<u64>,
     pub cancellation_token: CancellationToken,
 }
 
 pub struct SearchState {
     search_tx: Sender<SearchJob>,
-    result_rx: Receiver<Result<SearchOutcome>>,
+    result_rx: Receiver<SearchResult<(SetOutcome, Option<CursorId>)>>,
 
-    node_info_tx: Sender<Vec<SlabIndex>>,
+    node_info_tx: Sender<Vec<SetIndex>>,
     node_info_results_rx: Receiver<Vec<SearchResultNode>>,
 
-    icon_viewport_tx: Sender<(u64, Vec<SlabIndex>)>,
-    rescan_tx: Sender<()>,
+    icon_viewport_tx: Sender<(u64, Vec<SetIndex>)>,
+    command_tx: Sender<BackgroundCommand>,
+
+    settings: Mutex<Settings>,
+    /// Set by `cancel_export` to stop the running export.
+    export_cancelled: AtomicBool,
+    folder_sizes: FolderSizes,
 }
 
 impl SearchState {
     #[allow(clippy::too_many_arguments)]
     pub fn new(
         search_tx: Sender<SearchJob>,
-        result_rx: Receiver<Result<SearchOutcome>>,
-        node_info_tx: Sender<Vec<SlabIndex>>,
+        result_rx: Receiver<SearchResult<(SetOutcome, Option<CursorId>)>>,
+        node_info_tx: Sender<Vec<SetIndex>>,
         node_info_results_rx: Receiver<Vec<SearchResultNode>>,
-        icon_viewport_tx: Sender<(u64, Vec<SlabIndex>)>,
-        rescan_tx: Sender<()>,
+        icon_viewport_tx: Sender<(u64, Vec<SetIndex>)>,
+        command_tx: Sender<BackgroundCommand>,
+        settings: Settings,
+        folder_sizes: FolderSizes,
     ) -> Self {
         Self {
             search_tx,
@@ -60,28 +139,86 @@ impl SearchState {
             node_info_tx,
             node_info_results_rx,
             icon_viewport_tx,
-            rescan_tx,
+            command_tx,
+            settings: Mutex::new(settings),
+            export_cancelled: AtomicBool::new(false),
+            folder_sizes,
         }
     }
 }
 
 #[derive(Serialize)]
 pub struct NodeInfo {
+    /// Display form of the path, lossy for non-UTF-8 file names.
     pub path: String,
+    /// Base64 of the exact path bytes, only sent when `path` is lossy. Pass it
+    /// back to path actions so they operate on the real file.
+    pub raw_path: Option<String>,
     pub metadata: Option<NodeInfoMetadata>,
     pub icon: Option<String>,
+    /// User tags, sorted.
+    pub tags: Vec<String>,
+}
+
+/// What the frontend gets when `search` fails, so it can tell a typo in the
+/// query from an index still being built.
+#[derive(Debug, Serialize)]
+pub struct SearchErrorPayload {
+    /// One of `parse`, `unknown_variable`, `cancelled`, `index_busy`, `invalid_scope`,
+    /// `too_broad`, `too_deep`, `cursor_invalidated` or `internal`.
+    pub code: &'static str,
+    pub message: String,
+    /// Byte offset in the query the error points at.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<usize>,
+}
+
+impl From<SearchError> for SearchErrorPayload {
+    fn from(err: SearchError) -> Self {
+        Self {
+            code: err.code(),
+            message: err.to_string(),
+            span: err.span(),
+        }
+    }
+}
+
+impl SearchErrorPayload {
+    fn internal(message: String) -> Self {
+        Self {
+            code: "internal",
+            message,
+            span: None,
+        }
+    }
 }
 
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct SearchResponse {
-    pub results: Vec<SlabIndex>,
+    pub results: Vec<SetIndex>,
     pub highlights: Vec<String>,
+    /// Results are what was found before the deadline, the complete ones
+    /// follow as a `search_refined` event.
+    pub partial: bool,
+    /// Shown as hints under the search box.
+    pub notes: Vec<QueryNote>,
+    /// Files the filters couldn't stat, counted in the Full Disk Access hint.
+    pub metadata_errors: MetadataErrorCounts,
+    /// Pages `results` with their metadata, `None` for partial results.
+    pub cursor: Option<CursorId>,
 }
 
 #[derive(Serialize)]
 pub struct NodeInfoMetadata {
     pub r#type: u8,
     pub size: u64,
+    /// Bytes allocated on disk, `None` until the metadata is fetched again
+    /// for nodes loaded from an older cache file.
+    pub allocated: Option<u64>,
+    /// Inode number and hard link count, `None` in the same case as `allocated`.
+    pub inode: Option<u64>,
+    pub nlink: Option<u32>,
     pub ctime: u32,
     pub mtime: u32,
 }
@@ -91,6 +228,9 @@ impl NodeInfoMetadata {
         Self {
             r#type: metadata.r#type() as u8,
             size: metadata.size(),
+            allocated: metadata.allocated(),
+            inode: metadata.inode(),
+            nlink: metadata.nlink(),
             ctime: metadata.ctime().map(|x| x.get()).unwrap_or_default(),
             mtime: metadata.mtime().map(|x| x.get()).unwrap_or_default(),
         }
@@ -103,7 +243,36 @@ pub async fn search(
     options: Option<SearchOptionsPayload>,
     version: u64,
     state: State<'_, SearchState>,
-) -> Result<SearchResponse, String> {
+) -> Result<SearchResponse, SearchErrorPayload> {
+    run_search(query, options, version, None, &state)
+}
+
+/// Run `query` over the results of the search numbered `previous_version`
+/// only ("search in results"). Fails if a newer search or a rescan replaced
+/// them in the meantime.
+#[tauri::command]
+pub async fn search_in_results(
+    query: String,
+    options: Option<SearchOptionsPayload>,
+    version: u64,
+    previous_version: u64,
+    state: State<'_, SearchState>,
+) -> Result<SearchResponse, SearchErrorPayload> {
+    run_search(query, options, version, Some(previous_version), &state)
+}
+
+fn run_search(
+    query: String,
+    options: Option<SearchOptionsPayload>,
+    version: u64,
+    within: Option<u64>,
+    state: &SearchState,
+) -> Result<SearchResponse, SearchErrorPayload> {
+    // The background thread is busy with the first walk after launch and
+    // would only answer once it's over. Rescans keep answering from the old tree.
+    if load_app_state() == AppLifecycleState::Initializing {
+        return Err(SearchError::IndexBusy.into());
+    }
     let options = options.unwrap_or_default();
     let cancellation_token = CancellationToken::new(version);
     state
@@ -111,35 +280,45 @@ pub async fn search(
         .send(SearchJob {
             query,
             options,
+            version,
+            within,
             cancellation_token,
         })
-        .map_err(|e| format!("Failed to send search request: {e:?}"))?;
+        .map_err(|e| {
+            SearchErrorPayload::internal(format!("Failed to send search request: {e:?}"))
+        })?;
 
-    let search_result = state
+    let (outcome, cursor) = state
         .result_rx
         .recv()
-        .map_err(|e| format!("Failed to receive search result: {e:?}"))?
-        .map(|res| {
-            let SearchOutcome { nodes, highlights } = res;
-            let results = match nodes {
-                Some(list) => list,
-                None => {
-                    info!("Search {version} was cancelled");
-                    Vec::new()
-                }
-            };
-            SearchResponse {
-                results,
-                highlights,
+        .map_err(|e| {
+            SearchErrorPayload::internal(format!("Failed to receive search result: {e:?}"))
+        })?
+        .inspect_err(|err| {
+            if matches!(err, SearchError::Cancelled) {
+                info!("Search {version} was cancelled");
             }
-        });
-
-    search_result.map_err(|e| format!("Failed to process search result: {e:?}"))
+        })?;
+    let SetOutcome {
+        nodes,
+        highlights,
+        partial,
+        notes,
+        metadata_errors,
+    } = outcome;
+    Ok(SearchResponse {
+        results: nodes,
+        highlights,
+        partial,
+        notes,
+        metadata_errors,
+        cursor,
+    })
 }
 
 #[tauri::command]
 pub async fn get_nodes_info(
-    results: Vec<SlabIndex>,
+    results: Vec<SetIndex>,
     state: State<'_, SearchState>,
 ) -> Result<Vec<NodeInfo>, String> {
     if results.is_empty() {
@@ -156,31 +335,91 @@ pub async fn get_nodes_info(
         .recv()
         .map_err(|e| format!("Failed to receive node info results: {e:?}"))?;
 
-    let node_infos = nodes
-        .into_iter()
-        .map(|SearchResultNode { path, metadata }| {
-            let path = path.to_string_lossy().into_owned();
-            let icon = fs_icon::icon_of_path_ns(&path).map(|data| {
-                format!(
-                    "data:image/png;base64,{}",
-                    general_purpose::STANDARD.encode(data)
-                )
-            });
-            NodeInfo {
-                path,
-                icon,
-                metadata: metadata.as_ref().map(NodeInfoMetadata::from_metadata),
-            }
-        })
-        .collect();
+    Ok(nodes.into_iter().map(node_info).collect())
+}
+
+/// What a row of the results shows of `node`.
+fn node_info(node: SearchResultNode) -> NodeInfo {
+    let raw_path = node
+        .path
+        .to_str()
+        .is_none()
+        .then(|| general_purpose::STANDARD.encode(node.path_bytes()));
+    let SearchResultNode {
+        path,
+        metadata,
+        tags,
+        ..
+    } = node;
+    let path = path.to_string_lossy().into_owned();
+    // Unreachable paths (e.g. disconnected network volumes) stall
+    // `iconForFile`, so fall back to the generic icon for their type.
+    let icon = if metadata.is_unaccessible() {
+        generic_icon_of(&path, metadata.file_type_hint())
+    } else {
+        fs_icon::icon_of_path_ns(&path)
+    };
+    let icon = icon.map(|data| {
+        format!(
+            "data:image/png;base64,{}",
+            general_purpose::STANDARD.encode(data)
+        )
+    });
+    NodeInfo {
+        path,
+        raw_path,
+        icon,
+        metadata: metadata.as_ref().map(NodeInfoMetadata::from_metadata),
+        tags,
+    }
+}
+
+/// Up to `limit` results of the search that returned `cursor`, from `offset`
+/// on. `None` once a newer search closed the cursor, `cursor_invalidated`
+/// once the index changed; `get_nodes_info` still loads rows then.
+#[tauri::command]
+pub async fn cursor_page(
+    cursor: CursorId,
+    offset: usize,
+    limit: usize,
+    state: State<'_, SearchState>,
+) -> Result<Option<Vec<NodeInfo>>, SearchErrorPayload> {
+    let (reply_tx, reply_rx) = bounded(1);
+    state
+        .command_tx
+        .send(BackgroundCommand::CursorPage(
+            cursor, offset, limit, reply_tx,
+        ))
+        .map_err(|e| SearchErrorPayload::internal(format!("Failed to send page request: {e:?}")))?;
+    let nodes = reply_rx.recv().map_err(|e| {
+        SearchErrorPayload::internal(format!("Failed to receive page results: {e:?}"))
+    })??;
+    Ok(nodes.map(|nodes| nodes.into_iter().map(node_info).collect()))
+}
 
-    Ok(node_infos)
+#[tauri::command]
+pub async fn close_cursor(cursor: CursorId, state: State<'_, SearchState>) -> Result<(), String> {
+    state
+        .command_tx
+        .send(BackgroundCommand::CloseCursor(cursor))
+        .map_err(|e| format!("Failed to send close cursor request: {e:?}"))
+}
+
+fn generic_icon_of(path: &str, file_type: NodeFileType) -> Option<Vec<u8>> {
+    if file_type == NodeFileType::Dir {
+        return fs_icon::icon_of_folder(32.0);
+    }
+    let ext = Path::new(path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default();
+    fs_icon::icon_of_extension(ext, 32.0)
 }
 
 #[tauri::command]
 pub async fn update_icon_viewport(
     id: u64,
-    viewport: Vec<SlabIndex>,
+    viewport: Vec<SetIndex>,
     state: State<'_, SearchState>,
 ) -> Result<(), String> {
     state
@@ -197,14 +436,176 @@ pub async fn get_app_status() -> Result<String, String> {
 #[tauri::command]
 pub async fn trigger_rescan(state: State<'_, SearchState>) -> Result<(), String> {
     state
-        .rescan_tx
-        .send(())
+        .command_tx
+        .send(BackgroundCommand::Rescan)
         .map_err(|e| format!("Failed to request rescan: {e:?}"))?;
     Ok(())
 }
 
 #[tauri::command]
-pub fn open_in_finder(path: String) -> Result<(), String> {
+pub async fn get_settings(state: State<'_, SearchState>) -> Result<Settings, String> {
+    Ok(state.settings.lock().clone())
+}
+
+/// What the frontend gets when `update_settings` fails.
+#[derive(Debug, Serialize)]
+pub struct SettingsErrorPayload {
+    /// One of `invalid`, `shortcut_unavailable` or `internal`.
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl SettingsErrorPayload {
+    fn new(code: &'static str, err: anyhow::Error) -> Self {
+        Self {
+            code,
+            message: format!("{err:#}"),
+        }
+    }
+}
+
+/// Validate, persist and apply `settings`. Changing the watch root or the
+/// excludes makes the background thread rescan, changing the quick search
+/// shortcut registers the new one right away.
+#[tauri::command]
+pub async fn update_settings(
+    settings: Settings,
+    app: AppHandle,
+    state: State<'_, SearchState>,
+) -> Result<(), SettingsErrorPayload> {
+    settings
+        .validate()
+        .map_err(|e| SettingsErrorPayload::new("invalid", e))?;
+    // Hold the lock until the command is queued so concurrent updates reach
+    // the background thread in the order they were saved.
+    let mut current = state.settings.lock();
+    if *current == settings {
+        return Ok(());
+    }
+    if current.quick_search_shortcut != settings.quick_search_shortcut {
+        quick_search::register_shortcut(
+            &app,
+            &current.quick_search_shortcut,
+            &settings.quick_search_shortcut,
+        )
+        .map_err(|e| SettingsErrorPayload::new("shortcut_unavailable", e))?;
+    }
+    settings
+        .save(&SETTINGS_PATH)
+        .map_err(|e| SettingsErrorPayload::new("internal", e))?;
+    *current = settings.clone();
+    state
+        .command_tx
+        .send(BackgroundCommand::ApplySettings(settings))
+        .map_err(|e| {
+            SettingsErrorPayload::new("internal", anyhow!("Failed to apply settings: {e:?}"))
+        })
+}
+
+/// Prefer the exact bytes from `NodeInfo::raw_path` when the frontend has them.
+fn action_path(path: String, raw_path: Option<String>) -> Result<PathBuf, String> {
+    match raw_path {
+        Some(raw_path) => general_purpose::STANDARD
+            .decode(raw_path)
+            .map(|bytes| PathBuf::from(OsString::from_vec(bytes)))
+            .map_err(|e| format!("Invalid raw path: {e}")),
+        None => Ok(PathBuf::from(path)),
+    }
+}
+
+/// What became of a result since it was shown, see `validate_results`.
+#[derive(Serialize)]
+pub struct ValidationPayload {
+    /// One of `valid`, `renamed` or `gone`.
+    pub state: &'static str,
+    /// Where a renamed result is now, lossy like `NodeInfo::path`.
+    pub path: Option<String>,
+    pub raw_path: Option<String>,
+}
+
+impl From<ValidationState> for ValidationPayload {
+    fn from(state: ValidationState) -> Self {
+        match state {
+            ValidationState::Valid => Self {
+                state: "valid",
+                path: None,
+                raw_path: None,
+            },
+            ValidationState::Renamed(path) => Self {
+                state: "renamed",
+                raw_path: path
+                    .to_str()
+                    .is_none()
+                    .then(|| general_purpose::STANDARD.encode(path.as_os_str().as_encoded_bytes())),
+                path: Some(path.to_string_lossy().into_owned()),
+            },
+            ValidationState::Gone => Self {
+                state: "gone",
+                path: None,
+                raw_path: None,
+            },
+        }
+    }
+}
+
+fn validate(state: &SearchState, results: Vec<SetIndex>) -> Result<Vec<ValidationState>, String> {
+    let (reply_tx, reply_rx) = bounded(1);
+    state
+        .command_tx
+        .send(BackgroundCommand::Validate(results, reply_tx))
+        .map_err(|e| format!("Failed to send validation request: {e:?}"))?;
+    reply_rx
+        .recv()
+        .map_err(|e| format!("Failed to receive validation results: {e:?}"))
+}
+
+/// Whether the rows showing `results` are still accurate, in the same order:
+/// renamed ones come with their new path, gone ones should be dropped.
+#[tauri::command]
+pub async fn validate_results(
+    results: Vec<SetIndex>,
+    state: State<'_, SearchState>,
+) -> Result<Vec<ValidationPayload>, String> {
+    if results.is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(validate(&state, results)?
+        .into_iter()
+        .map(ValidationPayload::from)
+        .collect())
+}
+
+/// The path a row action should use: the row's own, or where the node at
+/// `slab_index` was renamed to since the row was shown. A node that's gone
+/// is an error rather than a path that no longer exists.
+fn current_action_path(
+    state: &SearchState,
+    path: String,
+    raw_path: Option<String>,
+    slab_index: Option<SetIndex>,
+) -> Result<PathBuf, String> {
+    let path = action_path(path, raw_path)?;
+    let Some(slab_index) = slab_index else {
+        return Ok(path);
+    };
+    match validate(state, vec![slab_index])?.pop() {
+        Some(ValidationState::Renamed(new_path)) => {
+            info!("{path:?} was renamed to {new_path:?}");
+            Ok(new_path)
+        }
+        Some(ValidationState::Gone) => Err(format!("{} no longer exists", path.display())),
+        Some(ValidationState::Valid) | None => Ok(path),
+    }
+}
+
+#[tauri::command]
+pub async fn open_in_finder(
+    path: String,
+    raw_path: Option<String>,
+    slab_index: Option<SetIndex>,
+    state: State<'_, SearchState>,
+) -> Result<(), String> {
+    let path = current_action_path(&state, path, raw_path, slab_index)?;
     Command::new("open")
         .arg("-R")
         .arg(&path)
@@ -214,16 +615,129 @@ pub fn open_in_finder(path: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-pub fn open_path(path: String) -> Result<(), String> {
+pub async fn open_path(
+    path: String,
+    raw_path: Option<String>,
+    slab_index: Option<SetIndex>,
+    state: State<'_, SearchState>,
+) -> Result<(), String> {
+    let path = current_action_path(&state, path, raw_path, slab_index)?;
     Command::new("open")
         .arg(&path)
         .spawn()
         .map_err(|e| format!("Failed to open path: {e}"))?;
+    // Only feeds the frecency ranking, opening succeeded either way.
+    if let Err(e) = state.command_tx.send(BackgroundCommand::RecordAccess(path)) {
+        warn!("Failed to record access: {e:?}");
+    }
     Ok(())
 }
 
+/// Write `results` as `format` to a file the user picks in the save panel,
+/// emitting `export_progress` along the way for large exports. `None` if the
+/// user dismissed the panel or cancelled the export with `cancel_export`.
+#[tauri::command]
+pub async fn export_results(
+    format: ExportFormatPayload,
+    results: Vec<SetIndex>,
+    app_handle: AppHandle,
+    state: State<'_, SearchState>,
+) -> Result<Option<ExportReceipt>, String> {
+    let Some(path) = choose_export_path(format).map_err(|e| format!("{e:#}"))? else {
+        return Ok(None);
+    };
+    state.export_cancelled.store(false, Ordering::Relaxed);
+    export_to_file(
+        &path,
+        format,
+        &results,
+        &state.export_cancelled,
+        |batch| {
+            let (reply_tx, reply_rx) = bounded(1);
+            state
+                .command_tx
+                .send(BackgroundCommand::Expand(batch.to_vec(), reply_tx))
+                .map_err(|e| anyhow!("Failed to send export request: {e:?}"))?;
+            reply_rx
+                .recv()
+                .map_err(|e| anyhow!("Failed to receive exported results: {e:?}"))
+        },
+        |progress| {
+            if let Err(e) = app_handle.emit(EXPORT_PROGRESS_EVENT, progress) {
+                warn!("Failed to report export progress: {e:?}");
+            }
+        },
+    )
+    .map_err(|e| format!("Failed to export results: {e:#}"))
+}
+
+/// Stop the running export, its partial file is removed.
+#[tauri::command]
+pub fn cancel_export(state: State<'_, SearchState>) {
+    state.export_cancelled.store(true, Ordering::Relaxed);
+}
+
+/// Start computing the recursive size of the folder `index`, or join the
+/// computation already running for it. The size is emitted as
+/// `folder_size_ready` once known.
+#[tauri::command]
+pub fn folder_size(
+    index: SetIndex,
+    state: State<'_, SearchState>,
+) -> Result<FolderSizeTicket, String> {
+    let (ticket, request) = state.folder_sizes.request(index);
+    if let Some(request) = request
+        && let Err(e) = state
+            .command_tx
+            .send(BackgroundCommand::FolderSize(request))
+    {
+        state.folder_sizes.cancel(ticket);
+        return Err(format!("Failed to request folder size: {e:?}"));
+    }
+    Ok(ticket)
+}
+
+/// The row showing the folder of `ticket` went away, stop computing its size
+/// unless another row waits for it.
+#[tauri::command]
+pub fn cancel_folder_size(ticket: FolderSizeTicket, state: State<'_, SearchState>) {
+    state.folder_sizes.cancel(ticket);
+}
+
+/// Tag the node at `path`. The tag is checked here so a bad one is reported
+/// to the user, the cache applies it in the background.
 #[tauri::command]
-pub fn preview_with_quicklook(path: String) -> Result<(), String> {
+pub fn add_tag(
+    path: String,
+    raw_path: Option<String>,
+    tag: String,
+    state: State<'_, SearchState>,
+) -> Result<(), String> {
+    let path = action_path(path, raw_path)?;
+    let tag = normalize_tag(&tag).map_err(|e| format!("Invalid tag: {e}"))?;
+    state
+        .command_tx
+        .send(BackgroundCommand::AddTag(path, tag.into()))
+        .map_err(|e| format!("Failed to add tag: {e:?}"))
+}
+
+#[tauri::command]
+pub fn remove_tag(
+    path: String,
+    raw_path: Option<String>,
+    tag: String,
+    state: State<'_, SearchState>,
+) -> Result<(), String> {
+    let path = action_path(path, raw_path)?;
+    state
+        .command_tx
+        .send(BackgroundCommand::RemoveTag(path, tag))
+        .map_err(|e| format!("Failed to remove tag: {e:?}"))
+}
+
+#[tauri::command]
+pub fn preview_with_quicklook(path: String, raw_path: Option<String>) -> Result<(), String> {
+    let path = action_path(path, raw_path)?;
     Command::new("qlmanage")
         .arg("-p")
         .arg(&path)
@@ -265,6 +779,16 @@ pub fn activate_main_window(app: AppHandle) {
     }
 }
 
+#[tauri::command]
+pub fn toggle_quick_search(app: AppHandle) {
+    quick_search::toggle_quick_search(&app);
+}
+
+#[tauri::command]
+pub fn hide_quick_search(app: AppHandle) {
+    quick_search::hide_quick_search(&app);
+}
+
 #[tauri::command]
 pub fn toggle_main_window(app: AppHandle) {
     if let Some(window) = app.get_webview_window("main") {
@@ -0,0 +1,306 @@
+use anyhow::{Context, Result, bail};
+use search_cache::{ExportFormat, ExportSummary, ExportWriter, SearchResultNode, SetIndex};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tracing::{info, warn};
+
+pub const EXPORT_PROGRESS_EVENT: &str = "export_progress";
+/// Results expanded per round trip to the background thread, which applies
+/// FSEvents in between.
+const EXPORT_BATCH: usize = 1024;
+/// Exports of more results than this report their progress.
+const EXPORT_PROGRESS_MIN_ROWS: usize = 10_000;
+/// Error number of AppleScript dialogs dismissed with Cancel.
+const USER_CANCELED: &str = "(-128)";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormatPayload {
+    Csv,
+    /// One JSON object per line.
+    Json,
+}
+
+impl ExportFormatPayload {
+    fn format(self) -> ExportFormat {
+        match self {
+            ExportFormatPayload::Csv => ExportFormat::Csv,
+            ExportFormatPayload::Json => ExportFormat::JsonLines,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormatPayload::Csv => "csv",
+            ExportFormatPayload::Json => "jsonl",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportProgressPayload {
+    /// Results handled so far, skipped ones included.
+    pub processed: usize,
+    pub total: usize,
+}
+
+/// What `export_results` wrote.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportReceipt {
+    pub path: String,
+    pub rows: usize,
+    /// Results no longer indexed, left out.
+    pub skipped: usize,
+}
+
+/// Ask where to save the export with the system save panel. `None` if the
+/// user cancelled it.
+pub fn choose_export_path(format: ExportFormatPayload) -> Result<Option<PathBuf>> {
+    let script = format!(
+        "POSIX path of (choose file name with prompt \"Export results\" default name \"Cardinal Results.{}\")",
+        format.extension()
+    );
+    let output = Command::new("osascript")
+        .arg("-e")
+        .arg(script)
+        .output()
+        .context("Failed to show the save panel")?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains(USER_CANCELED) {
+            return Ok(None);
+        }
+        bail!("Save panel failed: {}", stderr.trim());
+    }
+    let path = PathBuf::from(String::from_utf8_lossy(&output.stdout).trim_end_matches('\n'));
+    if path.extension().is_some() {
+        Ok(Some(path))
+    } else {
+        Ok(Some(path.with_extension(format.extension())))
+    }
+}
+
+/// Write `results` to `path`. Returns `None` when `cancelled` was set midway,
+/// the partial file is removed then, as on errors.
+pub fn export_to_file(
+    path: &Path,
+    format: ExportFormatPayload,
+    results: &[SetIndex],
+    cancelled: &AtomicBool,
+    expand: impl FnMut(&[SetIndex]) -> Result<Vec<SearchResultNode>>,
+    progress: impl FnMut(ExportProgressPayload),
+) -> Result<Option<ExportReceipt>> {
+    let file = File::create(path).with_context(|| format!("Failed to create {path:?}"))?;
+    let mut writer = BufWriter::new(file);
+    let exported = stream_export(results, format, &mut writer, cancelled, expand, progress);
+    drop(writer);
+    match exported {
+        Ok(Some(summary)) => {
+            info!("Exported {} results to {path:?}", summary.rows);
+            Ok(Some(ExportReceipt {
+                path: path.to_string_lossy().into_owned(),
+                rows: summary.rows,
+                skipped: summary.skipped,
+            }))
+        }
+        Ok(None) | Err(_) => {
+            if let Err(e) = fs::remove_file(path) {
+                warn!("Failed to remove partial export {path:?}: {e:?}");
+            }
+            exported.map(|_| None)
+        }
+    }
+}
+
+/// Expand `results` a batch at a time with `expand` and write them to
+/// `writer`, checking `cancelled` before each batch. `progress` hears after
+/// every batch of large exports.
+fn stream_export(
+    results: &[SetIndex],
+    format: ExportFormatPayload,
+    writer: &mut dyn Write,
+    cancelled: &AtomicBool,
+    mut expand: impl FnMut(&[SetIndex]) -> Result<Vec<SearchResultNode>>,
+    mut progress: impl FnMut(ExportProgressPayload),
+) -> Result<Option<ExportSummary>> {
+    let mut export = ExportWriter::new(format.format(), writer)?;
+    let mut processed = 0;
+    for batch in results.chunks(EXPORT_BATCH) {
+        if cancelled.load(Ordering::Relaxed) {
+            info!("Export cancelled after {processed} results");
+            return Ok(None);
+        }
+        export.write_nodes(&expand(batch)?)?;
+        processed += batch.len();
+        if results.len() > EXPORT_PROGRESS_MIN_ROWS {
+            progress(ExportProgressPayload {
+                processed,
+                total: results.len(),
+            });
+        }
+    }
+    export.finish().map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::anyhow;
+    use search_cache::{SlabIndex, SlabNodeMetadataCompact};
+
+    fn results(count: usize) -> Vec<SetIndex> {
+        (0..count)
+            .map(|i| SetIndex::new(0, SlabIndex::new(i)))
+            .collect()
+    }
+
+    /// Every node exists, at `/export/<index>.txt`.
+    fn expand(batch: &[SetIndex]) -> Result<Vec<SearchResultNode>> {
+        Ok(batch
+            .iter()
+            .map(|node| SearchResultNode {
+                path: PathBuf::from(format!("/export/{}.txt", node.index().get())),
+                metadata: SlabNodeMetadataCompact::unaccessible(),
+                source: node.source(),
+                tags: Vec::new(),
+                highlights: None,
+                display_path: String::new(),
+            })
+            .collect())
+    }
+
+    #[test]
+    fn test_stream_export_small() {
+        let mut out = Vec::new();
+        let mut reports = Vec::new();
+        let summary = stream_export(
+            &results(3),
+            ExportFormatPayload::Json,
+            &mut out,
+            &AtomicBool::new(false),
+            expand,
+            |progress| reports.push(progress),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(summary.rows, 3);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 3);
+        assert!(out.lines().next().unwrap().contains("\"/export/0.txt\""));
+        assert!(reports.is_empty());
+    }
+
+    #[test]
+    fn test_stream_export_reports_progress() {
+        let total = EXPORT_PROGRESS_MIN_ROWS + 1;
+        let mut out = Vec::new();
+        let mut reports = Vec::new();
+        let mut batches = 0;
+        let summary = stream_export(
+            &results(total),
+            ExportFormatPayload::Csv,
+            &mut out,
+            &AtomicBool::new(false),
+            |batch| {
+                batches += 1;
+                assert!(batch.len() <= EXPORT_BATCH);
+                expand(batch)
+            },
+            |progress| reports.push(progress),
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(summary.rows, total);
+        // Header and a line per result.
+        assert_eq!(String::from_utf8(out).unwrap().lines().count(), total + 1);
+        assert_eq!(reports.len(), batches);
+        assert!(reports.is_sorted_by_key(|progress| progress.processed));
+        assert_eq!(
+            reports.last(),
+            Some(&ExportProgressPayload {
+                processed: total,
+                total
+            })
+        );
+    }
+
+    #[test]
+    fn test_stream_export_cancelled() {
+        let cancelled = AtomicBool::new(false);
+        let mut out = Vec::new();
+        let mut batches = 0;
+        let summary = stream_export(
+            &results(10 * EXPORT_BATCH),
+            ExportFormatPayload::Json,
+            &mut out,
+            &cancelled,
+            |batch| {
+                batches += 1;
+                if batches == 2 {
+                    cancelled.store(true, Ordering::Relaxed);
+                }
+                expand(batch)
+            },
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(summary, None);
+        assert_eq!(batches, 2);
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.lines().count(), 2 * EXPORT_BATCH);
+    }
+
+    #[test]
+    fn test_export_to_file_removes_partial_file() {
+        let dir = std::env::temp_dir().join(format!("cardinal-export-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("results.csv");
+
+        let receipt = export_to_file(
+            &path,
+            ExportFormatPayload::Csv,
+            &results(5),
+            &AtomicBool::new(false),
+            expand,
+            |_| {},
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(receipt.rows, 5);
+        assert_eq!(receipt.path, path.to_string_lossy());
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 6);
+
+        let cancelled = AtomicBool::new(true);
+        let receipt = export_to_file(
+            &path,
+            ExportFormatPayload::Csv,
+            &results(5),
+            &cancelled,
+            expand,
+            |_| {},
+        )
+        .unwrap();
+        assert_eq!(receipt, None);
+        assert!(!path.exists());
+
+        let failed = export_to_file(
+            &path,
+            ExportFormatPayload::Csv,
+            &results(5),
+            &AtomicBool::new(false),
+            |_| Err(anyhow!("background thread gone")),
+            |_| {},
+        );
+        assert!(failed.is_err());
+        assert!(!path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,357 @@
+use crossbeam_channel::{Receiver, Sender, unbounded};
+use parking_lot::Mutex;
+use search_cache::{FolderSizeJob, SetIndex};
+use search_cancel::CancellationToken;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+};
+use tracing::warn;
+
+pub const FOLDER_SIZE_READY_EVENT: &str = "folder_size_ready";
+/// Folders sized at the same time, the other requests wait for a worker.
+const FOLDER_SIZE_WORKERS: usize = 2;
+
+/// Names one `folder_size` computation, shared by the requests it coalesced.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FolderSizeTicket(u64);
+
+/// Payload of `folder_size_ready`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FolderSizeReady {
+    pub index: SetIndex,
+    pub bytes: u64,
+    pub file_count: usize,
+}
+
+#[derive(Debug)]
+struct InFlight {
+    index: SetIndex,
+    /// Requests waiting for the size, it is dropped once none is left.
+    holders: usize,
+    /// The worker computing it, `None` while queued.
+    worker: Option<usize>,
+}
+
+/// Folder size computations from request to completion. Requests for a
+/// folder already being sized join its ticket instead of starting another
+/// computation, and a ticket is only dropped once every request cancelled it.
+#[derive(Debug, Default)]
+pub struct FolderSizeTickets {
+    last_ticket: u64,
+    by_index: HashMap<SetIndex, FolderSizeTicket>,
+    in_flight: HashMap<FolderSizeTicket, InFlight>,
+}
+
+impl FolderSizeTickets {
+    /// The ticket for sizing `index`, and whether it is new and its
+    /// computation must be started.
+    pub fn request(&mut self, index: SetIndex) -> (FolderSizeTicket, bool) {
+        if let Some(&ticket) = self.by_index.get(&index) {
+            self.in_flight
+                .get_mut(&ticket)
+                .expect("indexed tickets are in flight")
+                .holders += 1;
+            return (ticket, false);
+        }
+        // Tickets start at 1, see `WorkerSlots`.
+        self.last_ticket += 1;
+        let ticket = FolderSizeTicket(self.last_ticket);
+        self.by_index.insert(index, ticket);
+        self.in_flight.insert(
+            ticket,
+            InFlight {
+                index,
+                holders: 1,
+                worker: None,
+            },
+        );
+        (ticket, true)
+    }
+
+    /// Drop one request of `ticket`. Returns the worker to stop when that was
+    /// the last one and the computation is running.
+    pub fn cancel(&mut self, ticket: FolderSizeTicket) -> Option<usize> {
+        let in_flight = self.in_flight.get_mut(&ticket)?;
+        in_flight.holders -= 1;
+        if in_flight.holders > 0 {
+            return None;
+        }
+        let in_flight = self.remove(ticket)?;
+        in_flight.worker
+    }
+
+    /// `worker` takes `ticket` up. False if it was cancelled meanwhile.
+    pub fn begin(&mut self, ticket: FolderSizeTicket, worker: usize) -> bool {
+        match self.in_flight.get_mut(&ticket) {
+            Some(in_flight) => {
+                in_flight.worker = Some(worker);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The computation of `ticket` is over, returns the folder it sized
+    /// unless it was cancelled.
+    pub fn finish(&mut self, ticket: FolderSizeTicket) -> Option<SetIndex> {
+        self.remove(ticket).map(|in_flight| in_flight.index)
+    }
+
+    fn remove(&mut self, ticket: FolderSizeTicket) -> Option<InFlight> {
+        let in_flight = self.in_flight.remove(&ticket)?;
+        self.by_index.remove(&in_flight.index);
+        Some(in_flight)
+    }
+}
+
+/// Ticket each worker is computing, the active version of its
+/// [`CancellationToken`]. Storing 0 there cancels it.
+type WorkerSlots = &'static [AtomicU64];
+
+/// What the background thread sends back for a [`FolderSizeRequest`]: the
+/// files to add up, `None` if the folder isn't indexed.
+type QueuedJob = (FolderSizeTicket, Option<FolderSizeJob>);
+
+/// A computation to start: the background thread collects the files below
+/// `index` and [`Self::submit`]s them to the workers.
+#[derive(Debug, Clone)]
+pub struct FolderSizeRequest {
+    pub index: SetIndex,
+    ticket: FolderSizeTicket,
+    jobs_tx: Sender<QueuedJob>,
+}
+
+impl FolderSizeRequest {
+    pub fn submit(self, job: Option<FolderSizeJob>) {
+        if self.jobs_tx.send((self.ticket, job)).is_err() {
+            warn!("Folder size workers are gone, dropping {:?}", self.ticket);
+        }
+    }
+}
+
+/// Sizes folders on a few worker threads, sending each size to `ready_tx`
+/// as it completes.
+pub struct FolderSizes {
+    tickets: Arc<Mutex<FolderSizeTickets>>,
+    jobs_tx: Sender<QueuedJob>,
+    slots: WorkerSlots,
+}
+
+impl FolderSizes {
+    pub fn new(ready_tx: Sender<FolderSizeReady>) -> Self {
+        let tickets = Arc::new(Mutex::new(FolderSizeTickets::default()));
+        let (jobs_tx, jobs_rx) = unbounded();
+        // The workers, and so the slots, live as long as the app.
+        let slots: WorkerSlots = Box::leak(
+            (0..FOLDER_SIZE_WORKERS)
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+        );
+        for worker in 0..FOLDER_SIZE_WORKERS {
+            let tickets = tickets.clone();
+            let jobs_rx = jobs_rx.clone();
+            let ready_tx = ready_tx.clone();
+            thread::Builder::new()
+                .name(format!("cardinal-folder-size-{worker}"))
+                .spawn(move || run_worker(worker, slots, &tickets, jobs_rx, ready_tx))
+                .expect("Failed to spawn a folder size worker");
+        }
+        Self {
+            tickets,
+            jobs_tx,
+            slots,
+        }
+    }
+
+    /// The ticket for sizing `index`, with the request to hand to the
+    /// background thread if no computation for it is in flight yet.
+    pub fn request(&self, index: SetIndex) -> (FolderSizeTicket, Option<FolderSizeRequest>) {
+        let (ticket, start) = self.tickets.lock().request(index);
+        let request = start.then(|| FolderSizeRequest {
+            index,
+            ticket,
+            jobs_tx: self.jobs_tx.clone(),
+        });
+        (ticket, request)
+    }
+
+    /// Drop a request of `ticket`, stopping its computation if it was the
+    /// last one waiting for it.
+    pub fn cancel(&self, ticket: FolderSizeTicket) {
+        let mut tickets = self.tickets.lock();
+        if let Some(worker) = tickets.cancel(ticket) {
+            // Still under the lock, so the worker can't have moved on to
+            // another ticket yet.
+            self.slots[worker].store(0, Ordering::SeqCst);
+        }
+    }
+}
+
+fn run_worker(
+    worker: usize,
+    slots: WorkerSlots,
+    tickets: &Mutex<FolderSizeTickets>,
+    jobs_rx: Receiver<QueuedJob>,
+    ready_tx: Sender<FolderSizeReady>,
+) {
+    for (ticket, job) in jobs_rx {
+        let Some(job) = job else {
+            tickets.lock().finish(ticket);
+            continue;
+        };
+        let token = {
+            let mut tickets = tickets.lock();
+            if !tickets.begin(ticket, worker) {
+                continue;
+            }
+            CancellationToken::with_active_version(&slots[worker], ticket.0)
+        };
+        let size = job.run(token);
+        let index = tickets.lock().finish(ticket);
+        if let (Some(index), Some(size)) = (index, size) {
+            let ready = FolderSizeReady {
+                index,
+                bytes: size.bytes,
+                file_count: size.file_count,
+            };
+            if ready_tx.send(ready).is_err() {
+                return;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use search_cache::{CacheSet, SearchCache};
+    use std::{
+        fs,
+        path::{Path, PathBuf},
+        process,
+        time::Duration,
+    };
+
+    fn index(n: u32) -> SetIndex {
+        SetIndex::new(0, search_cache::SlabIndex::new(n as usize))
+    }
+
+    #[test]
+    fn test_requests_for_one_folder_coalesce() {
+        let mut tickets = FolderSizeTickets::default();
+        let (first, start) = tickets.request(index(1));
+        assert!(start);
+        let (joined, start) = tickets.request(index(1));
+        assert_eq!(joined, first);
+        assert!(!start);
+        let (other, start) = tickets.request(index(2));
+        assert_ne!(other, first);
+        assert!(start);
+
+        assert!(tickets.begin(first, 0));
+        assert_eq!(tickets.finish(first), Some(index(1)));
+        // Done, the next request computes it again.
+        let (again, start) = tickets.request(index(1));
+        assert_ne!(again, first);
+        assert!(start);
+    }
+
+    #[test]
+    fn test_cancel_drops_a_ticket_once_nobody_waits() {
+        let mut tickets = FolderSizeTickets::default();
+        let (ticket, _) = tickets.request(index(1));
+        tickets.request(index(1));
+        assert!(tickets.begin(ticket, 1));
+        assert_eq!(tickets.cancel(ticket), None);
+        // The last request stops the worker.
+        assert_eq!(tickets.cancel(ticket), Some(1));
+        assert_eq!(tickets.finish(ticket), None);
+        assert_eq!(tickets.cancel(ticket), None);
+
+        // Cancelled before a worker took it up.
+        let (queued, _) = tickets.request(index(2));
+        assert_eq!(tickets.cancel(queued), None);
+        assert!(!tickets.begin(queued, 0));
+        assert!(tickets.request(index(2)).1);
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("cardinal-folder-size-{}-{name}", process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("folder/nested")).unwrap();
+        fs::write(dir.join("folder/a.bin"), vec![0u8; 300]).unwrap();
+        fs::write(dir.join("folder/nested/b.bin"), vec![0u8; 45]).unwrap();
+        fs::write(dir.join("outside.bin"), vec![0u8; 1000]).unwrap();
+        dir
+    }
+
+    fn cache_set(dir: &Path) -> (CacheSet, SetIndex) {
+        let mut set = CacheSet::new();
+        set.insert(
+            SearchCache::walk_fs_with_ignore(dir.to_path_buf(), Vec::new()),
+            dir.join("cache.db"),
+        )
+        .unwrap();
+        let folder = set.node_index_for_raw_path(&dir.join("folder")).unwrap();
+        (set, folder)
+    }
+
+    #[test]
+    fn test_folder_size_is_sent_when_ready() {
+        let dir = temp_dir("ready");
+        let (set, folder) = cache_set(&dir);
+        let (ready_tx, ready_rx) = unbounded();
+        let sizes = FolderSizes::new(ready_tx);
+
+        let (ticket, request) = sizes.request(folder);
+        let request = request.expect("first request starts the computation");
+        let (joined, again) = sizes.request(folder);
+        assert_eq!(joined, ticket);
+        assert!(again.is_none());
+        request.submit(set.folder_size_job(folder, CancellationToken::noop()));
+
+        let ready = ready_rx.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            ready,
+            FolderSizeReady {
+                index: folder,
+                bytes: 345,
+                file_count: 2,
+            }
+        );
+        // Coalesced requests get the one event.
+        assert!(ready_rx.recv_timeout(Duration::from_millis(100)).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_cancelled_folder_size_is_not_sent() {
+        let dir = temp_dir("cancelled");
+        let (set, folder) = cache_set(&dir);
+        let (ready_tx, ready_rx) = unbounded();
+        let sizes = FolderSizes::new(ready_tx);
+
+        let (ticket, request) = sizes.request(folder);
+        sizes.cancel(ticket);
+        request
+            .unwrap()
+            .submit(set.folder_size_job(folder, CancellationToken::noop()));
+        // A folder that isn't indexed anymore releases its ticket too.
+        let (_, request) = sizes.request(index(999_999));
+        request.unwrap().submit(None);
+        assert!(ready_rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        // Neither is in flight anymore.
+        assert!(sizes.request(folder).1.is_some());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+use crate::{CACHE_PATH, window_controls::activate_window};
+use anyhow::{Context, Result, anyhow, bail};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    io::{BufRead, BufReader, Read, Write},
+    os::unix::net::{UnixListener, UnixStream},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    thread,
+};
+use tauri::{AppHandle, Emitter, Manager, Url};
+use tracing::{info, warn};
+
+pub const RUN_QUERY_EVENT: &str = "run_query";
+const URL_SCHEME: &str = "cardinal";
+/// Longest message a second launch may send, queries included.
+const MAX_MESSAGE_LEN: u64 = 64 * 1024;
+
+/// Where the running instance listens for later launches.
+static SOCKET_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CACHE_PATH.with_file_name("cardinal.sock"));
+
+static FRONTEND: Mutex<Frontend> = Mutex::new(Frontend {
+    ready: false,
+    pending_query: None,
+});
+
+struct Frontend {
+    /// Set once the frontend asked for [`take_pending_query`], queries that
+    /// come in before that are kept instead of being emitted unheard.
+    ready: bool,
+    pending_query: Option<String>,
+}
+
+/// What a launch of Cardinal asks of the instance that's already running.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum InstanceMessage {
+    /// Bring the main window forward.
+    Activate,
+    /// Bring the main window forward and search for `query`.
+    RunQuery { query: String },
+}
+
+impl InstanceMessage {
+    /// Read a launch's arguments (without the program name): `--query <q>`,
+    /// `--query=<q>` or a `cardinal://search?q=<q>` URL. Anything else is
+    /// left alone, macOS adds arguments of its own.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self> {
+        let mut args = args.into_iter();
+        let mut query = None;
+        while let Some(arg) = args.next() {
+            if arg == "--query" {
+                let value = args.next().context("--query needs a value")?;
+                query = Some(value);
+            } else if let Some(value) = arg.strip_prefix("--query=") {
+                query = Some(value.to_string());
+            } else if arg.starts_with(&format!("{URL_SCHEME}:")) {
+                query = Some(query_from_url(&arg)?);
+            }
+        }
+        match query {
+            Some(query) if query.trim().is_empty() => bail!("--query needs a value"),
+            Some(query) => Ok(Self::RunQuery { query }),
+            None => Ok(Self::Activate),
+        }
+    }
+
+    /// One JSON line.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut line = serde_json::to_vec(self).expect("instance messages serialize");
+        line.push(b'\n');
+        line
+    }
+
+    pub fn decode(line: &[u8]) -> Result<Self> {
+        serde_json::from_slice(line.trim_ascii()).context("Malformed instance message")
+    }
+}
+
+/// The query of a `cardinal://search?q=<query>` URL, percent-decoded.
+pub fn query_from_url(raw: &str) -> Result<String> {
+    let url = Url::parse(raw).with_context(|| format!("Invalid URL {raw:?}"))?;
+    if url.scheme() != URL_SCHEME {
+        bail!("Not a {URL_SCHEME}:// URL: {raw:?}");
+    }
+    if url.host_str() != Some("search") || !matches!(url.path(), "" | "/") {
+        bail!("Unknown {URL_SCHEME}:// action in {raw:?}, expected {URL_SCHEME}://search?q=...");
+    }
+    let query = url
+        .query_pairs()
+        .find_map(|(key, value)| (key == "q").then(|| value.into_owned()))
+        .ok_or_else(|| anyhow!("No query in {raw:?}, expected {URL_SCHEME}://search?q=..."))?;
+    if query.trim().is_empty() {
+        bail!("Empty query in {raw:?}");
+    }
+    Ok(query)
+}
+
+/// Hand `message` to the instance already running, if any. `false` when
+/// nothing listens, so this launch should carry on as the only instance.
+pub fn forward(message: &InstanceMessage) -> bool {
+    forward_to(&SOCKET_PATH, message)
+}
+
+fn forward_to(socket: &Path, message: &InstanceMessage) -> bool {
+    let Ok(mut stream) = UnixStream::connect(socket) else {
+        return false;
+    };
+    match stream.write_all(&message.encode()) {
+        Ok(()) => true,
+        Err(err) => {
+            warn!("Running instance didn't take the launch: {err}");
+            false
+        }
+    }
+}
+
+/// Take launches forwarded by later instances from now on.
+pub fn listen(app: AppHandle) -> Result<()> {
+    listen_at(&SOCKET_PATH, move |message| handle(&app, message))
+}
+
+/// Remove the socket, later launches start an instance of their own.
+pub fn stop_listening() {
+    let _ = fs::remove_file(&*SOCKET_PATH);
+}
+
+fn listen_at(socket: &Path, on_message: impl Fn(InstanceMessage) + Send + 'static) -> Result<()> {
+    // `forward` found no one listening, so whatever is left here is stale.
+    let _ = fs::remove_file(socket);
+    let listener = UnixListener::bind(socket)
+        .with_context(|| format!("Failed to listen on {}", socket.display()))?;
+    thread::Builder::new()
+        .name("instance-listener".to_string())
+        .spawn(move || {
+            for stream in listener.incoming() {
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(err) => {
+                        warn!("Failed to accept a launch: {err}");
+                        continue;
+                    }
+                };
+                let reader = BufReader::new(stream.take(MAX_MESSAGE_LEN));
+                for line in reader.split(b'\n') {
+                    let line = match line {
+                        Ok(line) => line,
+                        Err(err) => {
+                            warn!("Failed to read a launch: {err}");
+                            break;
+                        }
+                    };
+                    match InstanceMessage::decode(&line) {
+                        Ok(message) => on_message(message),
+                        Err(err) => warn!("Ignoring launch: {err:#}"),
+                    }
+                }
+            }
+        })
+        .context("Failed to spawn the instance listener")?;
+    Ok(())
+}
+
+/// Focus the main window and pass a query on to the frontend.
+pub fn handle(app: &AppHandle, message: InstanceMessage) {
+    info!("Launch handed over: {message:?}");
+    match app.get_webview_window("main") {
+        Some(window) => activate_window(&window),
+        None => warn!("Launch handed over but main window is unavailable"),
+    }
+    if let InstanceMessage::RunQuery { query } = message {
+        run_query(app, query);
+    }
+}
+
+/// Emit [`RUN_QUERY_EVENT`], or keep the query for [`take_pending_query`]
+/// while the frontend isn't listening yet.
+pub fn run_query(app: &AppHandle, query: String) {
+    {
+        let mut frontend = FRONTEND.lock();
+        if !frontend.ready {
+            frontend.pending_query = Some(query);
+            return;
+        }
+    }
+    if let Err(err) = app.emit(RUN_QUERY_EVENT, query) {
+        warn!("Failed to emit {RUN_QUERY_EVENT}: {err}");
+    }
+}
+
+/// Called by the frontend once it listens for [`RUN_QUERY_EVENT`]: the query a
+/// launch passed before then, if any.
+#[tauri::command]
+pub async fn take_pending_query() -> Result<Option<String>, String> {
+    let mut frontend = FRONTEND.lock();
+    frontend.ready = true;
+    Ok(frontend.pending_query.take())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{sync::mpsc, time::Duration};
+
+    fn args(args: &[&str]) -> Result<InstanceMessage> {
+        InstanceMessage::from_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn query_message(query: &str) -> InstanceMessage {
+        InstanceMessage::RunQuery {
+            query: query.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_query_from_url() {
+        assert_eq!(
+            query_from_url("cardinal://search?q=ext:pdf%20report").unwrap(),
+            "ext:pdf report"
+        );
+        // `open` passes the URL as typed, spaces and all.
+        assert_eq!(
+            query_from_url("cardinal://search?q=ext:pdf report").unwrap(),
+            "ext:pdf report"
+        );
+        assert_eq!(
+            query_from_url("cardinal://search/?x=1&q=a+b%3Bc").unwrap(),
+            "a b;c"
+        );
+        assert_eq!(
+            query_from_url("cardinal://search?q=%E6%97%A5%E8%A8%98").unwrap(),
+            "日記"
+        );
+        for malformed in [
+            "cardinal://",
+            "cardinal://search",
+            "cardinal://search?q=",
+            "cardinal://search?q=%20",
+            "cardinal://search?query=x",
+            "cardinal://open?q=x",
+            "cardinal://search/more?q=x",
+            "https://search?q=x",
+            "not a url",
+            "",
+        ] {
+            assert!(query_from_url(malformed).is_err(), "{malformed:?}");
+        }
+    }
+
+    #[test]
+    fn test_message_from_args() {
+        assert_eq!(args(&[]).unwrap(), InstanceMessage::Activate);
+        assert_eq!(args(&["-psn_0_12345"]).unwrap(), InstanceMessage::Activate);
+        assert_eq!(
+            args(&["--query", "ext:pdf report"]).unwrap(),
+            query_message("ext:pdf report")
+        );
+        assert_eq!(args(&["--query=*.rs"]).unwrap(), query_message("*.rs"));
+        assert_eq!(
+            args(&["cardinal://search?q=tag:keep"]).unwrap(),
+            query_message("tag:keep")
+        );
+        // The last one wins.
+        assert_eq!(
+            args(&["--query", "a", "--query=b"]).unwrap(),
+            query_message("b")
+        );
+        assert!(args(&["--query"]).is_err());
+        assert!(args(&["--query="]).is_err());
+        assert!(args(&["cardinal://open?q=x"]).is_err());
+    }
+
+    #[test]
+    fn test_message_encoding() {
+        for message in [
+            InstanceMessage::Activate,
+            query_message("ext:pdf \"annual report\"\nsecond line"),
+        ] {
+            let line = message.encode();
+            assert_eq!(line.iter().filter(|&&byte| byte == b'\n').count(), 1);
+            assert!(line.ends_with(b"\n"));
+            assert_eq!(InstanceMessage::decode(&line).unwrap(), message);
+        }
+        assert_eq!(
+            InstanceMessage::decode(br#"{"kind":"run_query","query":"x"}"#).unwrap(),
+            query_message("x")
+        );
+        for malformed in [&b""[..], b"{}", b"{\"kind\":\"quit\"}", b"\xff"] {
+            assert!(InstanceMessage::decode(malformed).is_err());
+        }
+    }
+
+    #[test]
+    fn test_forward_to_listener() {
+        let dir = std::env::temp_dir().join(format!("cardinal-instance-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let socket = dir.join("test.sock");
+        assert!(!forward_to(&socket, &InstanceMessage::Activate));
+
+        // A stale socket file doesn't stop the next instance from listening.
+        fs::write(&socket, b"").unwrap();
+        let (tx, rx) = mpsc::channel();
+        listen_at(&socket, move |message| tx.send(message).unwrap()).unwrap();
+        assert!(forward_to(&socket, &query_message("ext:md")));
+        assert!(forward_to(&socket, &InstanceMessage::Activate));
+        let received = |rx: &mpsc::Receiver<_>| rx.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(received(&rx), query_message("ext:md"));
+        assert_eq!(received(&rx), InstanceMessage::Activate);
+
+        // Garbage is skipped, the connection's later lines still count.
+        let mut stream = UnixStream::connect(&socket).unwrap();
+        stream.write_all(b"garbage\n").unwrap();
+        stream.write_all(&query_message("after").encode()).unwrap();
+        drop(stream);
+        assert_eq!(received(&rx), query_message("after"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,30 +1,46 @@
+mod autosave;
 mod background;
 mod commands;
+mod export;
+mod folder_size;
+mod instance;
 mod lifecycle;
+mod pause;
+mod quick_search;
+mod settings;
+mod tray;
 mod window_controls;
 
 use anyhow::{Context, Result};
+use autosave::load_newest;
 use background::{
-    BackgroundLoopChannels, IconPayload, emit_status_bar_update, run_background_event_loop,
+    BackgroundCommand, BackgroundLoopChannels, IconPayload, IndexState, StatusReporter,
+    emit_index_progress, report_idle, run_background_event_loop, spawn_event_watcher,
 };
-use cardinal_sdk::EventWatcher;
 use commands::{
-    SearchJob, SearchState, activate_main_window, get_app_status, get_nodes_info, hide_main_window,
-    open_in_finder, open_path, preview_with_quicklook, request_app_exit, search, start_logic,
-    toggle_main_window, trigger_rescan, update_icon_viewport,
+    SearchJob, SearchState, activate_main_window, add_tag, cancel_export, cancel_folder_size,
+    close_cursor, cursor_page, export_results, folder_size, get_app_status, get_nodes_info,
+    get_settings, hide_main_window, hide_quick_search, open_in_finder, open_path,
+    preview_with_quicklook, remove_tag, request_app_exit, search, search_in_results, start_logic,
+    toggle_main_window, toggle_quick_search, trigger_rescan, update_icon_viewport, update_settings,
+    validate_results,
 };
 use crossbeam_channel::{Receiver, RecvTimeoutError, Sender, bounded, unbounded};
+use folder_size::{FOLDER_SIZE_READY_EVENT, FolderSizeReady, FolderSizes};
+use instance::{InstanceMessage, take_pending_query};
 use lifecycle::{
     APP_QUIT, AppLifecycleState, EXIT_REQUESTED, emit_app_state, load_app_state, update_app_state,
 };
 use once_cell::sync::OnceCell;
-use search_cache::{SearchCache, SearchOutcome, SearchResultNode, SlabIndex, WalkData};
+use quick_search::QUICK_SEARCH_LABEL;
+use search_cache::{
+    CacheError, CacheSet, CursorId, SearchCache, SearchResult, SearchResultNode, SetIndex,
+    SetOutcome, WalkData,
+};
+use settings::{SETTINGS_PATH, Settings};
 use std::{
     path::PathBuf,
-    sync::{
-        LazyLock, Once,
-        atomic::{AtomicBool, Ordering},
-    },
+    sync::{LazyLock, Once, atomic::Ordering},
     time::Duration,
 };
 use tauri::{Emitter, Manager, RunEvent, WindowEvent};
@@ -41,6 +57,10 @@ static CACHE_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
         .config_dir()
         .join("cardinal.db")
 });
+/// Written every so often while running, so a crash doesn't lose everything
+/// since launch; the newest of it and [`CACHE_PATH`] is loaded.
+pub(crate) static AUTOSAVE_PATH: LazyLock<PathBuf> =
+    LazyLock::new(|| CACHE_PATH.with_file_name("cardinal.autosave.db"));
 pub(crate) static LOGIC_START: OnceCell<Sender<()>> = OnceCell::new();
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -52,18 +72,30 @@ pub fn run() -> Result<()> {
         builder.with_max_level(LevelFilter::INFO).init();
     }
 
-    let (finish_tx, finish_rx) = bounded::<Sender<Option<SearchCache>>>(1);
+    let launch = InstanceMessage::from_args(std::env::args().skip(1)).unwrap_or_else(|err| {
+        warn!("Ignoring the command line: {err:#}");
+        InstanceMessage::Activate
+    });
+    if instance::forward(&launch) {
+        info!("Cardinal is already running, handed {launch:?} over to it");
+        return Ok(());
+    }
+
+    let (finish_tx, finish_rx) = bounded::<Sender<Option<CacheSet>>>(1);
     let (search_tx, search_rx) = unbounded::<SearchJob>();
-    let (result_tx, result_rx) = unbounded::<Result<SearchOutcome>>();
-    let (node_info_tx, node_info_rx) = unbounded::<Vec<SlabIndex>>();
+    let (result_tx, result_rx) = unbounded::<SearchResult<(SetOutcome, Option<CursorId>)>>();
+    let (node_info_tx, node_info_rx) = unbounded::<Vec<SetIndex>>();
     let (node_info_results_tx, node_info_results_rx) = unbounded::<Vec<SearchResultNode>>();
-    let (icon_viewport_tx, icon_viewport_rx) = unbounded::<(u64, Vec<SlabIndex>)>();
-    let (rescan_tx, rescan_rx) = unbounded::<()>();
+    let (icon_viewport_tx, icon_viewport_rx) = unbounded::<(u64, Vec<SetIndex>)>();
+    let (command_tx, command_rx) = unbounded::<BackgroundCommand>();
     let (icon_update_tx, icon_update_rx) = unbounded::<IconPayload>();
+    let (folder_size_tx, folder_size_rx) = unbounded::<FolderSizeReady>();
     let (logic_start_tx, logic_start_rx) = bounded(1);
     LOGIC_START
         .set(logic_start_tx)
         .expect("LOGIC_START channel already initialized");
+    let settings = Settings::load(&SETTINGS_PATH);
+    info!("Loaded settings: {settings:?}");
 
     let mut builder = tauri::Builder::default();
     #[cfg(not(feature = "dev"))]
@@ -77,10 +109,37 @@ pub fn run() -> Result<()> {
         .plugin(tauri_plugin_macos_permissions::init())
         .plugin(tauri_plugin_window_state::Builder::new().build())
         .on_window_event(|window, event| {
+            if window.label() == QUICK_SEARCH_LABEL {
+                match event {
+                    // Like Spotlight, clicking elsewhere dismisses it.
+                    WindowEvent::Focused(false) => {
+                        quick_search::hide_quick_search(window.app_handle())
+                    }
+                    WindowEvent::CloseRequested { api, .. }
+                        if !EXIT_REQUESTED.load(Ordering::Relaxed) =>
+                    {
+                        api.prevent_close();
+                        quick_search::hide_quick_search(window.app_handle());
+                    }
+                    _ => {}
+                }
+                return;
+            }
+
             if window.label() != "main" {
                 return;
             }
 
+            if let WindowEvent::ThemeChanged(_) = event {
+                // Icons already sent were rendered for the other appearance.
+                let appearance = fs_icon::current_appearance();
+                info!("Appearance changed to {appearance:?}, reloading icons");
+                if let Err(e) = window.emit("appearance_changed", ()) {
+                    warn!("Failed to send the appearance change: {e:?}");
+                }
+                return;
+            }
+
             let WindowEvent::CloseRequested { api, .. } = event else {
                 return;
             };
@@ -101,29 +160,66 @@ pub fn run() -> Result<()> {
             }
         });
 
+    let quick_search_shortcut = settings.quick_search_shortcut.clone();
+    let tray_command_tx = command_tx.clone();
     let app = builder
+        .setup(move |app| {
+            // The window may be closed, the shortcut still brings up quick search.
+            if let Err(err) =
+                quick_search::register_shortcut(app.handle(), "", &quick_search_shortcut)
+            {
+                warn!("Quick search shortcut not registered: {err:#}");
+            }
+            if let Err(err) = instance::listen(app.handle().clone()) {
+                warn!("Later launches won't reach this instance: {err:#}");
+            }
+            if let InstanceMessage::RunQuery { query } = launch {
+                instance::run_query(app.handle(), query);
+            }
+            if let Err(err) = tray::create_tray(app.handle(), tray_command_tx) {
+                warn!("Menu bar item not created: {err:#}");
+            }
+            Ok(())
+        })
         .manage(SearchState::new(
             search_tx,
             result_rx,
             node_info_tx,
             node_info_results_rx,
             icon_viewport_tx.clone(),
-            rescan_tx.clone(),
+            command_tx.clone(),
+            settings.clone(),
+            FolderSizes::new(folder_size_tx),
         ))
         .invoke_handler(tauri::generate_handler![
             search,
+            search_in_results,
             get_nodes_info,
+            cursor_page,
+            close_cursor,
+            validate_results,
+            export_results,
+            cancel_export,
+            folder_size,
+            cancel_folder_size,
             update_icon_viewport,
             get_app_status,
             trigger_rescan,
+            get_settings,
+            update_settings,
             open_in_finder,
             open_path,
             preview_with_quicklook,
+            add_tag,
+            remove_tag,
             request_app_exit,
             start_logic,
             hide_main_window,
             activate_main_window,
             toggle_main_window,
+            toggle_quick_search,
+            hide_quick_search,
+            take_pending_query,
         ])
         .build(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -136,11 +232,24 @@ pub fn run() -> Result<()> {
         node_info_rx,
         node_info_results_tx,
         icon_viewport_rx,
-        rescan_rx,
+        command_rx,
         icon_update_tx,
     };
     emit_app_state(app_handle);
     let icon_update_rx = &icon_update_rx;
+    // The folder size workers keep their sender for the whole run, so this
+    // one isn't scoped.
+    let folder_size_handle = app_handle.clone();
+    std::thread::Builder::new()
+        .name("cardinal-folder-size-events".into())
+        .spawn(move || {
+            for ready in folder_size_rx {
+                if let Err(e) = folder_size_handle.emit(FOLDER_SIZE_READY_EVENT, ready) {
+                    warn!("Failed to send folder size: {e:?}");
+                }
+            }
+        })
+        .context("Failed to spawn the folder size event thread")?;
     std::thread::scope(move |s| {
         s.spawn(|| {
             while let Ok(icon) = icon_update_rx.recv() {
@@ -160,12 +269,13 @@ pub fn run() -> Result<()> {
                 return;
             }
 
-            run_logic_thread(app_handle, channels);
+            run_logic_thread(app_handle, channels, settings);
         });
 
         app.run(move |app_handle, event| match event {
             RunEvent::Exit => {
                 APP_QUIT.store(true, Ordering::Relaxed);
+                instance::stop_listening();
                 flush_cache_to_file_once(&finish_tx);
             }
             RunEvent::ExitRequested { api, code, .. } => {
@@ -185,6 +295,17 @@ pub fn run() -> Result<()> {
                     app_handle.exit(0);
                 }
             }
+            // `cardinal://` links, whether they launched the app or not.
+            RunEvent::Opened { urls } => {
+                for url in urls {
+                    match instance::query_from_url(url.as_str()) {
+                        Ok(query) => {
+                            instance::handle(app_handle, InstanceMessage::RunQuery { query })
+                        }
+                        Err(err) => warn!("Ignoring opened URL: {err:#}"),
+                    }
+                }
+            }
             RunEvent::Reopen {
                 has_visible_windows,
                 ..
@@ -204,102 +325,118 @@ pub fn run() -> Result<()> {
     Ok(())
 }
 
-fn run_logic_thread(app_handle: &tauri::AppHandle, channels: BackgroundLoopChannels) {
-    const WATCH_ROOT: &str = "/";
-    const FSE_LATENCY_SECS: f64 = 0.1;
-    let path = PathBuf::from(WATCH_ROOT);
-    let ignore_paths = vec![PathBuf::from("/System/Volumes/Data")];
+fn run_logic_thread(
+    app_handle: &tauri::AppHandle,
+    channels: BackgroundLoopChannels,
+    settings: Settings,
+) {
+    let path = settings.watch_root().to_path_buf();
+    let ignore_paths = settings.index_ignore_paths();
+    let status = StatusReporter::new(app_handle.clone());
 
-    let mut cache = match SearchCache::try_read_persistent_cache(
-        &path,
-        &CACHE_PATH,
-        Some(ignore_paths.clone()),
-        Some(&APP_QUIT),
-    ) {
-        Ok(cached) => {
-            info!("Loaded existing cache");
-            emit_status_bar_update(app_handle, cached.get_total_files(), 0);
-            cached
+    let (mut cache, failed) = load_cache_set(path, ignore_paths.clone());
+    // Members whose cache file couldn't be used are walked again.
+    for (path, cache_path, e) in failed {
+        if matches!(e, CacheError::Corrupt { .. }) {
+            warn!("Discarding corrupted cache: {e}");
         }
-        Err(e) => {
-            info!("Walking filesystem: {:?}", e);
-            let walk_data = WalkData::new(Some(ignore_paths.clone()), false, Some(&APP_QUIT));
-            let walking_done = AtomicBool::new(false);
-            let cache = std::thread::scope(|s| {
-                s.spawn(|| {
-                    while !walking_done.load(Ordering::Relaxed) {
-                        let dirs = walk_data.num_dirs.load(Ordering::Relaxed);
-                        let files = walk_data.num_files.load(Ordering::Relaxed);
-                        let total = dirs + files;
-                        emit_status_bar_update(app_handle, total, 0);
-                        std::thread::sleep(Duration::from_millis(100));
-                    }
-                });
-                let cache = SearchCache::walk_fs_with_walk_data(
-                    path.clone(),
-                    &walk_data,
-                    Some(ignore_paths.clone()),
-                    Some(&APP_QUIT),
-                );
-
-                walking_done.store(true, Ordering::Relaxed);
-                cache
-            });
+        info!("Walking filesystem: {:?}", e);
+        let report_progress =
+            |progress| emit_index_progress(&status, IndexState::Walking, progress);
+        let walk_data = WalkData::builder()
+            .ignore_directories(ignore_paths.clone())
+            .cancel(Some(&APP_QUIT))
+            .progress(&report_progress)
+            .build()
+            .expect("walk options without limits are valid");
+        let walked = SearchCache::walk_fs_with_walk_data(
+            path,
+            &walk_data,
+            ignore_paths.clone(),
+            Some(&APP_QUIT),
+        );
 
-            let Some(cache) = cache else {
-                info!("Walk filesystem cancelled, app quitting");
-                channels
-                    .finish_rx
-                    .recv()
-                    .expect("Failed to receive finish signal")
-                    .send(None)
-                    .expect("Failed to send None cache");
-                return;
-            };
-
-            emit_status_bar_update(app_handle, cache.get_total_files(), 0);
-
-            cache
-        }
-    };
+        let Some(walked) = walked else {
+            info!("Walk filesystem cancelled, app quitting");
+            channels
+                .finish_rx
+                .recv()
+                .expect("Failed to receive finish signal")
+                .send(None)
+                .expect("Failed to send None cache");
+            return;
+        };
+        cache
+            .insert(walked, cache_path)
+            .expect("Walked root is already in the cache set");
+    }
 
-    let event_watcher = EventWatcher::spawn(
-        WATCH_ROOT.to_string(),
-        cache.last_event_id(),
-        FSE_LATENCY_SECS,
-    )
-    .1;
+    report_idle(&status, &cache, 0, false, 0);
+    let event_watcher = spawn_event_watcher(&mut cache, &settings);
     if load_app_state() != AppLifecycleState::Ready {
         update_app_state(app_handle, AppLifecycleState::Updating);
     }
     info!("Started background processing thread");
     run_background_event_loop(
         app_handle,
+        &status,
         cache,
         event_watcher,
         channels,
-        WATCH_ROOT,
-        FSE_LATENCY_SECS,
+        settings,
     );
 
     info!("Background thread exited");
 }
 
-fn flush_cache_to_file_once(finish_tx: &Sender<Sender<Option<SearchCache>>>) {
+/// Load the watch root from whichever of [`CACHE_PATH`] and [`AUTOSAVE_PATH`]
+/// was written last and still loads; either way it's flushed to
+/// [`CACHE_PATH`] on exit. Fails like [`CacheSet::load`] when neither does.
+fn load_cache_set(
+    root: PathBuf,
+    ignore_paths: Option<Vec<PathBuf>>,
+) -> (CacheSet, Vec<(PathBuf, PathBuf, CacheError)>) {
+    let mut set = CacheSet::new();
+    let loaded = load_newest(&[CACHE_PATH.clone(), AUTOSAVE_PATH.clone()], |cache_path| {
+        SearchCache::try_read_persistent_cache(
+            &root,
+            cache_path,
+            ignore_paths.clone(),
+            Some(&APP_QUIT),
+        )
+    });
+    match loaded {
+        Ok((cache_path, cache)) => {
+            info!("Loaded existing cache from {cache_path:?}");
+            set.insert(cache, CACHE_PATH.clone())
+                .expect("Cache set is empty");
+            (set, Vec::new())
+        }
+        Err(failures) => {
+            let (_, e) = failures
+                .into_iter()
+                .next()
+                .expect("No cache file was tried");
+            (set, vec![(root, CACHE_PATH.clone(), e)])
+        }
+    }
+}
+
+fn flush_cache_to_file_once(finish_tx: &Sender<Sender<Option<CacheSet>>>) {
     static FLUSH_ONCE: Once = Once::new();
     if load_app_state() != AppLifecycleState::Ready {
         info!("App not fully initialized, skipping cache flush");
         return;
     }
     FLUSH_ONCE.call_once(move || {
-        let (cache_tx, cache_rx) = bounded::<Option<SearchCache>>(1);
+        let (cache_tx, cache_rx) = bounded::<Option<CacheSet>>(1);
         finish_tx
             .send(cache_tx)
             .context("cache_tx is closed")
             .unwrap();
         if let Some(cache) = cache_rx.recv().context("cache_tx is closed").unwrap() {
             cache
-                .flush_to_file(&CACHE_PATH)
+                .flush_to_files()
                 .context("Failed to write cache to file")
                 .unwrap();
 
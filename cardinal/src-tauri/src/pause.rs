@@ -0,0 +1,187 @@
+use cardinal_sdk::FsEvent;
+
+/// Events held back while indexing is paused, at most this many; past it
+/// they're dropped and the watch root is walked again on resume.
+pub const PAUSED_EVENTS_CAP: usize = 100_000;
+
+/// Whether FSEvents are applied as they come in or held back until the user
+/// resumes indexing. Held back events are kept in order up to a cap. Past it
+/// they're all dropped and only the need for a rescan is kept, like a
+/// `MustScanSubDirs` on the watch root, so a long pause costs a walk rather
+/// than memory. The watcher's channel is still drained meanwhile.
+#[derive(Debug)]
+pub struct EventPause {
+    cap: usize,
+    state: PauseState,
+}
+
+#[derive(Debug)]
+enum PauseState {
+    Running,
+    Paused(Vec<FsEvent>),
+    /// Paused, and more events than the cap came in.
+    Overflowed {
+        dropped: usize,
+    },
+}
+
+/// What becomes of the events held back once indexing resumes.
+#[derive(Debug)]
+pub enum Resumed {
+    /// Nothing came in, or indexing wasn't paused.
+    Nothing,
+    /// Apply these, in the order they came in.
+    Replay(Vec<FsEvent>),
+    /// Too many came in, they were dropped: walk the watch root again.
+    Rescan { dropped: usize },
+}
+
+impl EventPause {
+    pub fn new(cap: usize) -> Self {
+        Self {
+            cap,
+            state: PauseState::Running,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        !matches!(self.state, PauseState::Running)
+    }
+
+    /// Start holding events back, keeping those already held back.
+    pub fn pause(&mut self) {
+        if !self.is_paused() {
+            self.state = PauseState::Paused(Vec::new());
+        }
+    }
+
+    /// Hand `events` back to be applied, or hold them back while paused.
+    pub fn offer(&mut self, events: Vec<FsEvent>) -> Option<Vec<FsEvent>> {
+        match &mut self.state {
+            PauseState::Running => return Some(events),
+            PauseState::Paused(held) if held.len() + events.len() <= self.cap => {
+                held.extend(events);
+            }
+            PauseState::Paused(held) => {
+                self.state = PauseState::Overflowed {
+                    dropped: held.len() + events.len(),
+                };
+            }
+            PauseState::Overflowed { dropped } => *dropped += events.len(),
+        }
+        None
+    }
+
+    /// Events held back or dropped since the pause.
+    pub fn pending(&self) -> usize {
+        match &self.state {
+            PauseState::Running => 0,
+            PauseState::Paused(held) => held.len(),
+            PauseState::Overflowed { dropped } => *dropped,
+        }
+    }
+
+    /// Forget the events held back, staying paused, e.g. when a rescan
+    /// starts and the walk sees what they were about. Returns their number.
+    pub fn discard(&mut self) -> usize {
+        let pending = self.pending();
+        if self.is_paused() {
+            self.state = PauseState::Paused(Vec::new());
+        }
+        pending
+    }
+
+    /// Apply events as they come in again.
+    pub fn resume(&mut self) -> Resumed {
+        match std::mem::replace(&mut self.state, PauseState::Running) {
+            PauseState::Running => Resumed::Nothing,
+            PauseState::Paused(held) if held.is_empty() => Resumed::Nothing,
+            PauseState::Paused(held) => Resumed::Replay(held),
+            PauseState::Overflowed { dropped } => Resumed::Rescan { dropped },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cardinal_sdk::EventFlag;
+    use std::path::PathBuf;
+
+    fn events(ids: std::ops::Range<u64>) -> Vec<FsEvent> {
+        ids.map(|id| FsEvent {
+            path: PathBuf::from(format!("/tmp/file{id}")),
+            flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            id,
+        })
+        .collect()
+    }
+
+    fn ids(events: &[FsEvent]) -> Vec<u64> {
+        events.iter().map(|event| event.id).collect()
+    }
+
+    #[test]
+    fn test_running_hands_events_straight_back() {
+        let mut pause = EventPause::new(10);
+        assert!(!pause.is_paused());
+        let offered = pause.offer(events(0..3)).unwrap();
+        assert_eq!(ids(&offered), [0, 1, 2]);
+        assert_eq!(pause.pending(), 0);
+        assert!(matches!(pause.resume(), Resumed::Nothing));
+    }
+
+    #[test]
+    fn test_paused_events_replay_in_order_on_resume() {
+        let mut pause = EventPause::new(10);
+        pause.pause();
+        assert!(matches!(pause.resume(), Resumed::Nothing));
+
+        pause.pause();
+        assert!(pause.offer(events(0..3)).is_none());
+        // Pausing again keeps what was held back.
+        pause.pause();
+        assert!(pause.offer(events(3..5)).is_none());
+        assert_eq!(pause.pending(), 5);
+        let Resumed::Replay(held) = pause.resume() else {
+            panic!("held back events aren't replayed");
+        };
+        assert_eq!(ids(&held), [0, 1, 2, 3, 4]);
+        assert!(!pause.is_paused());
+        assert_eq!(ids(&pause.offer(events(5..6)).unwrap()), [5]);
+    }
+
+    #[test]
+    fn test_overflow_drops_everything_and_asks_for_a_rescan() {
+        let mut pause = EventPause::new(4);
+        pause.pause();
+        // Up to the cap, they're kept.
+        assert!(pause.offer(events(0..4)).is_none());
+        assert_eq!(pause.pending(), 4);
+        assert!(pause.offer(events(4..6)).is_none());
+        assert!(pause.offer(events(6..7)).is_none());
+        assert_eq!(pause.pending(), 7);
+        assert!(pause.is_paused());
+        assert!(matches!(pause.resume(), Resumed::Rescan { dropped: 7 }));
+        assert!(!pause.is_paused());
+        assert!(matches!(pause.resume(), Resumed::Nothing));
+    }
+
+    #[test]
+    fn test_discard_stays_paused() {
+        let mut pause = EventPause::new(4);
+        assert_eq!(pause.discard(), 0);
+        assert!(!pause.is_paused());
+
+        pause.pause();
+        pause.offer(events(0..6));
+        assert_eq!(pause.discard(), 6);
+        assert!(pause.is_paused());
+        // Overflowing before doesn't matter any more.
+        pause.offer(events(6..8));
+        let Resumed::Replay(held) = pause.resume() else {
+            panic!("events after the discard aren't replayed");
+        };
+        assert_eq!(ids(&held), [6, 7]);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::window_controls::{WindowToggle, hide_window, toggle_window};
+use anyhow::{Result, anyhow, bail};
+use std::str::FromStr;
+use tauri::{AppHandle, Manager, Runtime, WebviewUrl, WebviewWindow, WebviewWindowBuilder};
+use tauri_plugin_global_shortcut::{GlobalShortcutExt, Shortcut, ShortcutState};
+use tracing::{error, info, warn};
+
+pub const QUICK_SEARCH_LABEL: &str = "quick-search";
+pub const DEFAULT_QUICK_SEARCH_SHORTCUT: &str = "Alt+Space";
+
+const QUICK_SEARCH_WIDTH: f64 = 680.0;
+const QUICK_SEARCH_HEIGHT: f64 = 420.0;
+
+/// Parse a shortcut like `Alt+Space` or `CmdOrCtrl+Shift+K`. An empty string
+/// disables the shortcut. A modifier is required so the shortcut can't take
+/// a plain key away from every other app.
+pub fn parse_shortcut(shortcut: &str) -> Result<Option<Shortcut>> {
+    let shortcut = shortcut.trim();
+    if shortcut.is_empty() {
+        return Ok(None);
+    }
+    let parsed =
+        Shortcut::from_str(shortcut).map_err(|e| anyhow!("Invalid shortcut {shortcut:?}: {e}"))?;
+    if parsed.mods.is_empty() {
+        bail!("Shortcut {shortcut:?} needs at least one modifier");
+    }
+    Ok(Some(parsed))
+}
+
+/// Make `new` the quick search shortcut in place of `old`. If `new` can't be
+/// registered, e.g. because another app owns it, `old` is restored and the
+/// error returned.
+pub fn register_shortcut<R: Runtime>(app: &AppHandle<R>, old: &str, new: &str) -> Result<()> {
+    let old = parse_shortcut(old).ok().flatten();
+    let new = parse_shortcut(new)?;
+    let global_shortcut = app.global_shortcut();
+    if let Some(old) = old {
+        if global_shortcut.is_registered(old) {
+            if let Err(err) = global_shortcut.unregister(old) {
+                warn!(?err, "Failed to unregister quick search shortcut {old}");
+            }
+        }
+    }
+    let Some(new) = new else {
+        info!("Quick search shortcut disabled");
+        return Ok(());
+    };
+    match global_shortcut.on_shortcut(new, on_shortcut) {
+        Ok(()) => {
+            info!("Quick search shortcut registered: {new}");
+            Ok(())
+        }
+        Err(err) => {
+            if let Some(old) = old {
+                if let Err(err) = global_shortcut.on_shortcut(old, on_shortcut) {
+                    error!(?err, "Failed to restore quick search shortcut {old}");
+                }
+            }
+            Err(anyhow!("Shortcut {new} is unavailable: {err}"))
+        }
+    }
+}
+
+fn on_shortcut<R: Runtime>(
+    app: &AppHandle<R>,
+    _shortcut: &Shortcut,
+    event: tauri_plugin_global_shortcut::ShortcutEvent,
+) {
+    if event.state == ShortcutState::Released {
+        toggle_quick_search(app);
+    }
+}
+
+/// Show the quick search window, creating it on first use, or hide it when
+/// it's already in front.
+pub fn toggle_quick_search<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window(QUICK_SEARCH_LABEL) {
+        if matches!(toggle_window(&window), WindowToggle::Hidden) {
+            info!("Quick search hidden");
+        }
+        return;
+    }
+    match build_window(app) {
+        Ok(window) => {
+            toggle_window(&window);
+            info!("Quick search window created");
+        }
+        Err(err) => error!(?err, "Failed to create quick search window"),
+    }
+}
+
+pub fn hide_quick_search<R: Runtime>(app: &AppHandle<R>) {
+    if let Some(window) = app.get_webview_window(QUICK_SEARCH_LABEL) {
+        hide_window(&window);
+    }
+}
+
+/// The window is borderless and floats above other apps; its geometry is fixed
+/// here so the frontend can't leave it in a state it can't recover from.
+fn build_window<R: Runtime>(app: &AppHandle<R>) -> tauri::Result<WebviewWindow<R>> {
+    WebviewWindowBuilder::new(
+        app,
+        QUICK_SEARCH_LABEL,
+        WebviewUrl::App("index.html?window=quick-search".into()),
+    )
+    .title("Cardinal Quick Search")
+    .inner_size(QUICK_SEARCH_WIDTH, QUICK_SEARCH_HEIGHT)
+    .center()
+    .resizable(false)
+    .decorations(false)
+    .transparent(true)
+    .always_on_top(true)
+    .skip_taskbar(true)
+    .visible(false)
+    .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tauri_plugin_global_shortcut::{Code, Modifiers};
+
+    #[test]
+    fn test_parse_shortcut() {
+        let shortcut = parse_shortcut(DEFAULT_QUICK_SEARCH_SHORTCUT)
+            .unwrap()
+            .unwrap();
+        assert_eq!(shortcut.mods, Modifiers::ALT);
+        assert_eq!(shortcut.key, Code::Space);
+
+        let shortcut = parse_shortcut(" Option+Shift+K ").unwrap().unwrap();
+        assert_eq!(shortcut.mods, Modifiers::ALT | Modifiers::SHIFT);
+        assert_eq!(shortcut.key, Code::KeyK);
+
+        let shortcut = parse_shortcut("CmdOrCtrl+F").unwrap().unwrap();
+        assert_eq!(shortcut.mods, Modifiers::SUPER);
+        assert_eq!(shortcut, parse_shortcut("cmd+f").unwrap().unwrap());
+    }
+
+    #[test]
+    fn test_empty_shortcut_disables() {
+        assert_eq!(parse_shortcut("").unwrap(), None);
+        assert_eq!(parse_shortcut("   ").unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_shortcut_rejects_invalid() {
+        let error = |shortcut: &str| parse_shortcut(shortcut).unwrap_err().to_string();
+        assert!(error("Space").contains("needs at least one modifier"));
+        assert!(error("Alt+NotAKey").contains("Invalid shortcut"));
+        assert!(error("Alt++Space").contains("Invalid shortcut"));
+        assert!(error("Alt+K+J").contains("Invalid shortcut"));
+        assert!(error("Alt+").contains("Invalid shortcut"));
+    }
+}
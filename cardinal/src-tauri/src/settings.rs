@@ -0,0 +1,266 @@
+use crate::quick_search::{DEFAULT_QUICK_SEARCH_SHORTCUT, parse_shortcut};
+use anyhow::{Context, Result, bail};
+use search_cache::SearchCache;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+    time::Duration,
+};
+use tracing::warn;
+
+pub static SETTINGS_PATH: LazyLock<PathBuf> = LazyLock::new(|| {
+    directories::ProjectDirs::from("", "", "Cardinal")
+        .expect(
+            "Failed to get ProjectDirs: no valid home directory \
+                path could be retrieved from the operating system",
+        )
+        .data_dir()
+        .join("settings.json")
+});
+
+/// User settings, stored as JSON in [`SETTINGS_PATH`]. Missing fields take
+/// their default, so older files keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    /// Directories to index and watch. Only a single root is supported for now.
+    pub watch_roots: Vec<PathBuf>,
+    /// Absolute paths skipped while walking the watch root.
+    pub excludes: Vec<String>,
+    /// Maximum number of results returned by `search`, 0 means no limit.
+    pub max_results: usize,
+    /// FSEvents latency in seconds.
+    pub fse_latency: f64,
+    /// Global shortcut toggling the quick search window, e.g. `Alt+Space`.
+    /// Empty disables it.
+    pub quick_search_shortcut: String,
+    /// Minutes between background saves of the cache, 0 saves on exit only.
+    pub autosave_minutes: u64,
+    /// Events after which the cache is saved in the background without
+    /// waiting for `autosave_minutes`, 0 disables it.
+    pub autosave_events: u64,
+    /// Also index the data volume side of the system firmlinks
+    /// (`/System/Volumes/Data/Users` next to `/Users`), listing their files
+    /// twice. Off by default.
+    pub index_firmlink_duplicates: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            watch_roots: vec![PathBuf::from("/")],
+            excludes: vec!["/System/Volumes/Data".to_string()],
+            max_results: 0,
+            fse_latency: 0.1,
+            quick_search_shortcut: DEFAULT_QUICK_SEARCH_SHORTCUT.to_string(),
+            autosave_minutes: 10,
+            autosave_events: 100_000,
+            index_firmlink_duplicates: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Read the settings file, falling back to the defaults if it is missing
+    /// or can't be parsed.
+    pub fn load(path: &Path) -> Self {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Self::default(),
+            Err(e) => {
+                warn!("Failed to read settings from {path:?}, using defaults: {e}");
+                return Self::default();
+            }
+        };
+        match serde_json::from_slice::<Self>(&data)
+            .map_err(anyhow::Error::from)
+            .and_then(|settings| settings.validate().map(|()| settings))
+        {
+            Ok(settings) => settings,
+            Err(e) => {
+                warn!("Invalid settings in {path:?}, using defaults: {e}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create settings directory {parent:?}"))?;
+        }
+        let data = serde_json::to_vec_pretty(self).context("Failed to serialize settings")?;
+        fs::write(path, data).with_context(|| format!("Failed to write settings to {path:?}"))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        match self.watch_roots.as_slice() {
+            [root] if root.is_absolute() => {}
+            [root] => bail!("Watch root must be an absolute path: {root:?}"),
+            [] => bail!("A watch root is required"),
+            _ => bail!("Only a single watch root is supported"),
+        }
+        if let Some(exclude) = self
+            .excludes
+            .iter()
+            .find(|exclude| !Path::new(exclude).is_absolute())
+        {
+            bail!("Excluded paths must be absolute: {exclude:?}");
+        }
+        if !self.fse_latency.is_finite() || self.fse_latency < 0.0 {
+            bail!("Invalid FSEvents latency: {}", self.fse_latency);
+        }
+        parse_shortcut(&self.quick_search_shortcut)?;
+        Ok(())
+    }
+
+    pub fn watch_root(&self) -> &Path {
+        &self.watch_roots[0]
+    }
+
+    /// Excludes in the form `SearchCache` and `WalkData` take.
+    pub fn ignore_paths(&self) -> Option<Vec<PathBuf>> {
+        if self.excludes.is_empty() {
+            None
+        } else {
+            Some(self.excludes.iter().map(PathBuf::from).collect())
+        }
+    }
+
+    /// [`Self::ignore_paths`] plus the data volume side of the firmlinks
+    /// under the watch root, unless `index_firmlink_duplicates` is set.
+    pub fn index_ignore_paths(&self) -> Option<Vec<PathBuf>> {
+        if self.index_firmlink_duplicates {
+            self.ignore_paths()
+        } else {
+            SearchCache::firmlink_ignore_paths(self.watch_root(), self.ignore_paths())
+        }
+    }
+
+    /// `autosave_minutes` as a duration, `None` when it's off.
+    pub fn autosave_interval(&self) -> Option<Duration> {
+        (self.autosave_minutes != 0).then(|| Duration::from_secs(self.autosave_minutes * 60))
+    }
+
+    /// `autosave_events`, `None` when it's off.
+    pub fn autosave_max_events(&self) -> Option<u64> {
+        (self.autosave_events != 0).then_some(self.autosave_events)
+    }
+
+    /// Apply `max_results` to a result list.
+    pub fn limit_results<T>(&self, results: &mut Vec<T>) {
+        if self.max_results != 0 {
+            results.truncate(self.max_results);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process;
+
+    fn temp_settings_path(name: &str) -> PathBuf {
+        std::env::temp_dir()
+            .join(format!("cardinal-settings-{}-{name}", process::id()))
+            .join("settings.json")
+    }
+
+    #[test]
+    fn test_defaults_match_hardcoded_behavior() {
+        let settings = Settings::default();
+        assert_eq!(settings.watch_root(), Path::new("/"));
+        assert_eq!(
+            settings.ignore_paths(),
+            Some(vec![PathBuf::from("/System/Volumes/Data")])
+        );
+        assert_eq!(settings.fse_latency, 0.1);
+        assert_eq!(settings.quick_search_shortcut, "Alt+Space");
+        assert_eq!(settings.autosave_interval(), Some(Duration::from_secs(600)));
+        assert_eq!(settings.autosave_max_events(), Some(100_000));
+        let mut results = vec![1; 100];
+        settings.limit_results(&mut results);
+        assert_eq!(results.len(), 100);
+        settings.validate().unwrap();
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let settings = Settings {
+            watch_roots: vec![PathBuf::from("/Users/me")],
+            excludes: vec!["/Users/me/Library".to_string()],
+            max_results: 500,
+            fse_latency: 0.5,
+            quick_search_shortcut: "Cmd+Shift+K".to_string(),
+            autosave_minutes: 0,
+            autosave_events: 5000,
+            index_firmlink_duplicates: true,
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(
+            json,
+            r#"{"watchRoots":["/Users/me"],"excludes":["/Users/me/Library"],"maxResults":500,"fseLatency":0.5,"quickSearchShortcut":"Cmd+Shift+K","autosaveMinutes":0,"autosaveEvents":5000,"indexFirmlinkDuplicates":true}"#
+        );
+        assert_eq!(settings.autosave_interval(), None);
+        assert_eq!(serde_json::from_str::<Settings>(&json).unwrap(), settings);
+
+        let partial: Settings = serde_json::from_str(r#"{"maxResults":20}"#).unwrap();
+        assert_eq!(
+            partial,
+            Settings {
+                max_results: 20,
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_save_then_load() {
+        let path = temp_settings_path("roundtrip");
+        let settings = Settings {
+            max_results: 3,
+            ..Settings::default()
+        };
+        settings.save(&path).unwrap();
+        assert_eq!(Settings::load(&path), settings);
+        let mut results = vec![1, 2, 3, 4];
+        settings.limit_results(&mut results);
+        assert_eq!(results, [1, 2, 3]);
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_missing_or_corrupt_file_falls_back_to_defaults() {
+        let path = temp_settings_path("corrupt");
+        assert_eq!(Settings::load(&path), Settings::default());
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, b"{\"watchRoots\": [\"/\"").unwrap();
+        assert_eq!(Settings::load(&path), Settings::default());
+
+        // Well-formed JSON with unusable values is treated the same way.
+        fs::write(&path, br#"{"watchRoots":["relative/path"]}"#).unwrap();
+        assert_eq!(Settings::load(&path), Settings::default());
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_validate_rejects_unsupported_settings() {
+        let with = |f: fn(&mut Settings)| {
+            let mut settings = Settings::default();
+            f(&mut settings);
+            settings.validate()
+        };
+        assert!(with(|s| s.watch_roots.clear()).is_err());
+        assert!(with(|s| s.watch_roots.push(PathBuf::from("/Volumes/Data"))).is_err());
+        assert!(with(|s| s.excludes.push("tmp".to_string())).is_err());
+        assert!(with(|s| s.fse_latency = -1.0).is_err());
+        assert!(with(|s| s.fse_latency = f64::NAN).is_err());
+        assert!(with(|s| s.quick_search_shortcut = "K".to_string()).is_err());
+        assert!(with(|s| s.quick_search_shortcut = "Alt+Nope".to_string()).is_err());
+        assert!(with(|s| s.excludes.clear()).is_ok());
+        assert!(with(|s| s.quick_search_shortcut.clear()).is_ok());
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{
+    background::{BackgroundCommand, IndexState, IndexStatus},
+    commands::request_app_exit,
+    window_controls::activate_window,
+};
+use crossbeam_channel::Sender;
+use std::sync::Mutex;
+use tauri::{
+    AppHandle, Manager,
+    image::Image,
+    menu::{CheckMenuItem, Menu, MenuItem, PredefinedMenuItem},
+    tray::TrayIconBuilder,
+};
+use tracing::{error, warn};
+
+const TRAY_ID: &str = "cardinal-tray";
+const MENU_OPEN: &str = "open";
+const MENU_PAUSE: &str = "pause";
+const MENU_RESCAN: &str = "rescan";
+const MENU_QUIT: &str = "quit";
+/// Side of the status icon in pixels, drawn for retina menu bars.
+const ICON_SIZE: u32 = 36;
+
+/// State the icon was last drawn for, it's only redrawn when that changes.
+static SHOWN_STATE: Mutex<Option<IndexState>> = Mutex::new(None);
+
+/// Put Cardinal in the menu bar, with an icon following the index state and
+/// a menu to open the window, pause indexing, rescan or quit. Commands go to
+/// the background thread through `command_tx`.
+pub fn create_tray(app: &AppHandle, command_tx: Sender<BackgroundCommand>) -> tauri::Result<()> {
+    let pause =
+        CheckMenuItem::with_id(app, MENU_PAUSE, "Pause Indexing", true, false, None::<&str>)?;
+    let menu = Menu::with_items(
+        app,
+        &[
+            &MenuItem::with_id(app, MENU_OPEN, "Open Cardinal", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &pause,
+            &MenuItem::with_id(app, MENU_RESCAN, "Rescan Now", true, None::<&str>)?,
+            &PredefinedMenuItem::separator(app)?,
+            &MenuItem::with_id(app, MENU_QUIT, "Quit Cardinal", true, None::<&str>)?,
+        ],
+    )?;
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(state_icon(IndexState::Idle))
+        .icon_as_template(true)
+        .tooltip("Cardinal")
+        .menu(&menu)
+        .show_menu_on_left_click(true)
+        .on_menu_event(move |app, event| match event.id().as_ref() {
+            MENU_OPEN => match app.get_webview_window("main") {
+                Some(window) => activate_window(&window),
+                None => warn!("Open requested from the tray but main window is unavailable"),
+            },
+            MENU_PAUSE => {
+                // The item checks itself when clicked.
+                let paused = pause.is_checked().unwrap_or(false);
+                send(&command_tx, BackgroundCommand::SetPaused(paused));
+            }
+            MENU_RESCAN => send(&command_tx, BackgroundCommand::Rescan),
+            // Through the exit request, so the cache gets flushed.
+            MENU_QUIT => {
+                let _ = request_app_exit(app.clone());
+            }
+            _ => {}
+        })
+        .build(app)?;
+    Ok(())
+}
+
+fn send(command_tx: &Sender<BackgroundCommand>, command: BackgroundCommand) {
+    if let Err(err) = command_tx.send(command) {
+        error!(
+            ?err,
+            "Failed to send a tray command to the background thread"
+        );
+    }
+}
+
+/// Reflect `status` on the tray icon and its tooltip, if there is one.
+pub fn show_status(app: &AppHandle, status: &IndexStatus) {
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+    let mut shown = SHOWN_STATE.lock().unwrap();
+    if *shown != Some(status.state) {
+        *shown = Some(status.state);
+        if let Err(err) = tray
+            .set_icon(Some(state_icon(status.state)))
+            .and_then(|()| tray.set_icon_as_template(true))
+        {
+            error!(?err, "Failed to update the tray icon");
+        }
+    }
+    let tooltip = format!(
+        "Cardinal: {}, {} files indexed",
+        state_label(status),
+        status.files_indexed
+    );
+    if let Err(err) = tray.set_tooltip(Some(tooltip)) {
+        error!(?err, "Failed to update the tray tooltip");
+    }
+}
+
+fn state_label(status: &IndexStatus) -> String {
+    match status.state {
+        IndexState::Idle => "idle".to_string(),
+        IndexState::Walking => "indexing".to_string(),
+        IndexState::ProcessingEvents => "processing events".to_string(),
+        IndexState::ReplayingHistory => "replaying events since last launch".to_string(),
+        IndexState::Rescanning => "rescanning".to_string(),
+        IndexState::Saving => "saving".to_string(),
+        IndexState::Paused => format!("paused, {} events pending", status.pending_events),
+    }
+}
+
+/// A template image, which macOS tints to match the menu bar: a ring, empty
+/// when idle, with a dot while events are processed, half full while walking
+/// or rescanning, full while saving, and with two bars while paused.
+fn state_icon(state: IndexState) -> Image<'static> {
+    let center = (ICON_SIZE - 1) as f32 / 2.0;
+    let radius = center - 1.0;
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            // In units of the radius, from the center.
+            let dx = (x as f32 - center) / radius;
+            let dy = (y as f32 - center) / radius;
+            let distance = (dx * dx + dy * dy).sqrt();
+            let ring = (0.8..=1.0).contains(&distance);
+            let inside = distance < 0.8;
+            let lit = ring
+                || match state {
+                    IndexState::Idle => false,
+                    IndexState::ProcessingEvents | IndexState::ReplayingHistory => distance <= 0.35,
+                    IndexState::Walking | IndexState::Rescanning => inside && dy > 0.0,
+                    IndexState::Saving => inside,
+                    IndexState::Paused => (0.15..=0.4).contains(&dx.abs()) && dy.abs() <= 0.4,
+                };
+            rgba.extend_from_slice(&[0, 0, 0, if lit { 255 } else { 0 }]);
+        }
+    }
+    Image::new_owned(rgba, ICON_SIZE, ICON_SIZE)
+}
@@ -9,7 +9,14 @@ import PreferencesOverlay from './components/PreferencesOverlay';
 import StatusBar from './components/StatusBar';
 import type { StatusTabKey } from './components/StatusBar';
 import type { SearchResultItem } from './types/search';
-import type { AppLifecycleStatus, StatusBarUpdatePayload } from './types/ipc';
+import type { SlabIndex } from './types/slab';
+import type {
+  AppLifecycleStatus,
+  ExportFormat,
+  ExportProgressPayload,
+  ExportReceipt,
+  IndexStatusPayload,
+} from './types/ipc';
 import { useColumnResize } from './hooks/useColumnResize';
 import { useContextMenu } from './hooks/useContextMenu';
 import { useFileSearch } from './hooks/useFileSearch';
@@ -24,7 +31,7 @@ import { listen } from '@tauri-apps/api/event';
 import type { UnlistenFn } from '@tauri-apps/api/event';
 import { useTranslation } from 'react-i18next';
 import { useFullDiskAccessPermission } from './hooks/useFullDiskAccessPermission';
-import { OPEN_PREFERENCES_EVENT } from './constants/appEvents';
+import { EXPORT_RESULTS_EVENT, OPEN_PREFERENCES_EVENT } from './constants/appEvents';
 import { getAllPathsInRange } from './utils/selection';
 import type { DisplayState } from './components/StateDisplay';
 
@@ -43,6 +50,7 @@ function App() {
     searchParams,
     updateSearchParams,
     queueSearch,
+    handleSearch,
     resetSearchQuery,
     cancelPendingSearches,
     handleStatusUpdate,
@@ -51,10 +59,12 @@ function App() {
   } = useFileSearch();
   const {
     results,
+    cursor,
     scannedFiles,
     processedEvents,
     currentQuery,
     highlightTerms,
+    queryNotes,
     showLoadingUI,
     initialFetchCompleted,
     durationMs,
@@ -79,7 +89,7 @@ function App() {
   const searchInputRef = useRef<HTMLInputElement | null>(null);
   const isMountedRef = useRef(false);
   const { colWidths, onResizeStart, autoFitColumns } = useColumnResize();
-  const { caseSensitive } = searchParams;
+  const { caseSensitive, includeHidden } = searchParams;
   const { eventColWidths, onEventResizeStart, autoFitEventColumns } = useEventColumnWidths();
   const { filteredEvents, eventFilterQuery, setEventFilterQuery } = useRecentFSEvents({
     caseSensitive,
@@ -144,11 +154,12 @@ function App() {
     requestPermission: requestFullDiskAccessPermission,
   } = useFullDiskAccessPermission();
   const [isPreferencesOpen, setIsPreferencesOpen] = useState(false);
+  const [exportProgress, setExportProgress] = useState<ExportProgressPayload | null>(null);
 
-  const activePath =
-    activeRowIndex !== null
-      ? (virtualListRef.current?.getItem?.(activeRowIndex)?.path ?? null)
-      : null;
+  const activeItem =
+    activeRowIndex !== null ? virtualListRef.current?.getItem?.(activeRowIndex) : undefined;
+  const activePath = activeItem?.path ?? null;
+  const activeRawPath = activeItem?.rawPath;
 
   useEffect(() => {
     if (isCheckingFullDiskAccess) {
@@ -170,19 +181,34 @@ function App() {
     });
   }, []);
 
+  // Queries handed over by `cardinal://search?q=...` links or `--query` launches.
+  const runQuery = useCallback(
+    (query: string) => {
+      setActiveTab('files');
+      if (searchInputRef.current) {
+        searchInputRef.current.value = query;
+      }
+      queueSearch(query);
+      focusSearchInput();
+    },
+    [focusSearchInput, queueSearch],
+  );
+
   useEffect(() => {
     isMountedRef.current = true;
     let unlistenStatus: UnlistenFn | undefined;
     let unlistenLifecycle: UnlistenFn | undefined;
     let unlistenQuickLaunch: UnlistenFn | undefined;
+    let unlistenRunQuery: UnlistenFn | undefined;
+    let unlistenCompacted: UnlistenFn | undefined;
 
     const setupListeners = async (): Promise<void> => {
-      unlistenStatus = await listen<StatusBarUpdatePayload>('status_bar_update', (event) => {
+      unlistenStatus = await listen<IndexStatusPayload>('status_bar_update', (event) => {
         if (!isMountedRef.current) return;
         const payload = event.payload;
         if (!payload) return;
-        const { scannedFiles, processedEvents } = payload;
-        handleStatusUpdate(scannedFiles, processedEvents);
+        const { filesIndexed, eventsProcessed } = payload;
+        handleStatusUpdate(filesIndexed, eventsProcessed);
       });
 
       unlistenLifecycle = await listen<AppLifecycleStatus>('app_lifecycle_state', (event) => {
@@ -196,6 +222,22 @@ function App() {
         if (!isMountedRef.current) return;
         focusSearchInput();
       });
+
+      unlistenCompacted = await listen('index_compacted', () => {
+        if (!isMountedRef.current) return;
+        // Result indices were renumbered, search again for fresh ones.
+        void handleSearch();
+      });
+
+      unlistenRunQuery = await listen<string>('run_query', (event) => {
+        if (!isMountedRef.current || !event.payload) return;
+        runQuery(event.payload);
+      });
+      // A launch may have passed a query before we listened.
+      const pendingQuery = await invoke<string | null>('take_pending_query');
+      if (pendingQuery && isMountedRef.current) {
+        runQuery(pendingQuery);
+      }
     };
 
     void setupListeners();
@@ -205,13 +247,56 @@ function App() {
       unlistenStatus?.();
       unlistenLifecycle?.();
       unlistenQuickLaunch?.();
+      unlistenRunQuery?.();
+      unlistenCompacted?.();
     };
-  }, [focusSearchInput, handleStatusUpdate, setLifecycleState]);
+  }, [focusSearchInput, handleSearch, handleStatusUpdate, runQuery, setLifecycleState]);
 
   useEffect(() => {
     focusSearchInput();
   }, [focusSearchInput]);
 
+  const handleExport = useCallback(
+    async (format: ExportFormat) => {
+      if (!results.length) {
+        return;
+      }
+      const unlistenProgress = await listen<ExportProgressPayload>('export_progress', (event) => {
+        if (!isMountedRef.current || !event.payload) return;
+        setExportProgress(event.payload);
+      });
+      try {
+        const receipt = await invoke<ExportReceipt | null>('export_results', { format, results });
+        if (receipt) {
+          console.info(`Exported ${receipt.rows} results to ${receipt.path}`);
+        }
+      } catch (error) {
+        console.error('Failed to export results', error);
+      } finally {
+        unlistenProgress();
+        if (isMountedRef.current) {
+          setExportProgress(null);
+        }
+      }
+    },
+    [results],
+  );
+
+  const cancelExport = useCallback(() => {
+    invoke('cancel_export').catch((error) => {
+      console.error('Failed to cancel export', error);
+    });
+  }, []);
+
+  useEffect(() => {
+    const handleExportRequest = (event: Event) => {
+      void handleExport((event as CustomEvent<ExportFormat>).detail);
+    };
+
+    window.addEventListener(EXPORT_RESULTS_EVENT, handleExportRequest);
+    return () => window.removeEventListener(EXPORT_RESULTS_EVENT, handleExportRequest);
+  }, [handleExport]);
+
   useEffect(() => {
     const handleOpenPreferences = () => setIsPreferencesOpen(true);
 
@@ -269,14 +354,16 @@ function App() {
       }
 
       event.preventDefault();
-      invoke('preview_with_quicklook', { path: activePath }).catch((error) => {
-        console.error('Failed to preview file with Quick Look', error);
-      });
+      invoke('preview_with_quicklook', { path: activePath, rawPath: activeRawPath }).catch(
+        (error) => {
+          console.error('Failed to preview file with Quick Look', error);
+        },
+      );
     };
 
     window.addEventListener('keydown', handleKeyDown);
     return () => window.removeEventListener('keydown', handleKeyDown);
-  }, [activePath, activeTab]);
+  }, [activePath, activeRawPath, activeTab]);
 
   useEffect(() => {
     if (activeTab !== 'files') {
@@ -344,9 +431,13 @@ function App() {
           return;
         }
         event.preventDefault();
-        invoke('open_in_finder', { path: activePath }).catch((error) => {
-          console.error('Failed to reveal file in Finder', error);
-        });
+        // The slab index lets the backend follow a rename since the row was loaded.
+        const slabIndex = activeRowIndex !== null ? results[activeRowIndex] : undefined;
+        invoke('open_in_finder', { path: activePath, rawPath: activeRawPath, slabIndex }).catch(
+          (error) => {
+            console.error('Failed to reveal file in Finder', error);
+          },
+        );
         return;
       }
 
@@ -365,7 +456,7 @@ function App() {
 
     window.addEventListener('keydown', handleGlobalShortcuts);
     return () => window.removeEventListener('keydown', handleGlobalShortcuts);
-  }, [focusSearchInput, activeTab, activePath]);
+  }, [focusSearchInput, activeTab, activePath, activeRawPath, activeRowIndex, results]);
 
   useEffect(() => {
     if (activeRowIndex == null) {
@@ -416,6 +507,13 @@ function App() {
     [updateSearchParams],
   );
 
+  const onToggleIncludeHidden = useCallback(
+    (event: ChangeEvent<HTMLInputElement>) => {
+      updateSearchParams({ includeHidden: event.target.checked });
+    },
+    [updateSearchParams],
+  );
+
   useEffect(() => {
     // Reset vertical scroll and prefetch initial rows to keep first render responsive
     const list = virtualListRef.current;
@@ -449,11 +547,11 @@ function App() {
     [selectedPaths, showFilesContextMenu],
   );
 
-  const handleRowOpen = useCallback((path: string) => {
+  const handleRowOpen = useCallback((path: string, rawPath?: string, slabIndex?: SlabIndex) => {
     if (!path) {
       return;
     }
-    invoke('open_path', { path }).catch((error) => {
+    invoke('open_path', { path, rawPath, slabIndex }).catch((error) => {
       console.error('Failed to open file', error);
     });
   }, []);
@@ -471,7 +569,7 @@ function App() {
           style={{ ...rowStyle, width: 'var(--columns-total)' }} // Enforce column width CSS vars for virtualization rows
           onContextMenu={(event, contextPath) => handleRowContextMenu(event, contextPath)}
           onSelect={handleRowSelect}
-          onOpen={handleRowOpen}
+          onOpen={(openPath, rawPath) => handleRowOpen(openPath, rawPath, results[rowIndex])}
           isSelected={isSelected}
           selectedPaths={selectedPaths}
           caseInsensitive={!caseSensitive}
@@ -483,6 +581,7 @@ function App() {
       handleRowContextMenu,
       handleRowSelect,
       handleRowOpen,
+      results,
       selectedPaths,
       caseSensitive,
       highlightTerms,
@@ -542,6 +641,16 @@ function App() {
     ? t('app.fullDiskAccess.status.checking')
     : t('app.fullDiskAccess.status.disabled');
   const caseSensitiveLabel = t('search.options.caseSensitive');
+  const includeHiddenLabel = t('search.options.includeHidden');
+  const queryNoteLabels = queryNotes.map((note) => {
+    if (note.kind === 'parentNotIndexed') {
+      return t('search.notes.parentNotIndexed', { path: note.path });
+    }
+    if (note.kind === 'metadataPermissionDenied') {
+      return t('search.notes.metadataPermissionDenied', { count: note.count ?? 0 });
+    }
+    return t(`search.notes.${note.kind}`);
+  });
   const searchPlaceholder =
     activeTab === 'files' ? t('search.placeholder.files') : t('search.placeholder.events');
   const permissionSteps = [
@@ -561,6 +670,10 @@ function App() {
           caseSensitive={caseSensitive}
           onToggleCaseSensitive={onToggleCaseSensitive}
           caseSensitiveLabel={caseSensitiveLabel}
+          includeHidden={includeHidden}
+          onToggleIncludeHidden={onToggleIncludeHidden}
+          includeHiddenLabel={includeHiddenLabel}
+          notes={queryNoteLabels}
         />
         <div className="results-container" style={containerStyle}>
           {activeTab === 'events' ? (
@@ -583,6 +696,7 @@ function App() {
               currentQuery={currentQuery}
               virtualListRef={virtualListRef}
               results={results}
+              cursor={cursor}
               rowHeight={ROW_HEIGHT}
               overscan={OVERSCAN_ROW_COUNT}
               renderRow={renderRow}
@@ -590,6 +704,20 @@ function App() {
             />
           )}
         </div>
+        {exportProgress && (
+          <div className="export-progress" role="status">
+            <progress value={exportProgress.processed} max={exportProgress.total} />
+            <span className="export-progress__label">
+              {t('export.progress', {
+                processed: exportProgress.processed.toLocaleString(),
+                total: exportProgress.total.toLocaleString(),
+              })}
+            </span>
+            <button type="button" className="export-progress__cancel" onClick={cancelExport}>
+              {t('export.cancel')}
+            </button>
+          </div>
+        )}
         <StatusBar
           scannedFiles={scannedFiles}
           processedEvents={processedEvents}
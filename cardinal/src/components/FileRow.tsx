@@ -10,7 +10,7 @@ type FileRowProps = {
   rowIndex: number;
   style?: CSSProperties;
   onContextMenu?: (event: ReactMouseEvent<HTMLDivElement>, path: string) => void;
-  onOpen?: (path: string) => void;
+  onOpen?: (path: string, rawPath?: string) => void;
   onSelect?: (
     path: string,
     rowIndex: number,
@@ -81,7 +81,7 @@ export const FileRow = memo(function FileRow({
   const handleDoubleClick = (e: ReactMouseEvent<HTMLDivElement>) => {
     e.preventDefault();
     if (path && onOpen) {
-      onOpen(path);
+      onOpen(path, item.rawPath);
     }
   };
 
@@ -18,6 +18,7 @@ type FilesTabContentProps = {
   currentQuery: string;
   virtualListRef: React.RefObject<VirtualListHandle | null>;
   results: SlabIndex[];
+  cursor: number | null;
   rowHeight: number;
   overscan: number;
   renderRow: (
@@ -37,6 +38,7 @@ export function FilesTabContent({
   currentQuery,
   virtualListRef,
   results,
+  cursor,
   rowHeight,
   overscan,
   renderRow,
@@ -56,6 +58,7 @@ export function FilesTabContent({
           <VirtualList
             ref={virtualListRef}
             results={results}
+            cursor={cursor}
             rowHeight={rowHeight}
             overscan={overscan}
             renderRow={renderRow}
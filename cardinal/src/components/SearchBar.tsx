@@ -8,6 +8,11 @@ type SearchBarProps = {
   caseSensitive: boolean;
   onToggleCaseSensitive: (event: ChangeEvent<HTMLInputElement>) => void;
   caseSensitiveLabel: string;
+  includeHidden: boolean;
+  onToggleIncludeHidden: (event: ChangeEvent<HTMLInputElement>) => void;
+  includeHiddenLabel: string;
+  // Hints on why filters of the last query matched less than expected.
+  notes?: string[];
 };
 
 export function SearchBar({
@@ -17,6 +22,10 @@ export function SearchBar({
   caseSensitive,
   onToggleCaseSensitive,
   caseSensitiveLabel,
+  includeHidden,
+  onToggleIncludeHidden,
+  includeHiddenLabel,
+  notes = [],
 }: SearchBarProps): React.JSX.Element {
   return (
     <div className="search-container">
@@ -44,8 +53,29 @@ export function SearchBar({
             </span>
             <span className="sr-only">{caseSensitiveLabel}</span>
           </label>
+          <label className="search-option" title={includeHiddenLabel}>
+            <input
+              type="checkbox"
+              checked={includeHidden}
+              onChange={onToggleIncludeHidden}
+              aria-label={includeHiddenLabel}
+            />
+            <span className="search-option__display" aria-hidden="true">
+              .*
+            </span>
+            <span className="sr-only">{includeHiddenLabel}</span>
+          </label>
         </div>
       </div>
+      {notes.length > 0 && (
+        <ul className="search-notes" aria-live="polite">
+          {notes.map((note) => (
+            <li key={note} className="search-note">
+              {note}
+            </li>
+          ))}
+        </ul>
+      )}
     </div>
   );
 }
@@ -25,6 +25,8 @@ export type VirtualListHandle = {
 
 type VirtualListProps = {
   results?: SlabIndex[];
+  // Backend cursor over `results`, rows are paged through it when set.
+  cursor?: number | null;
   rowHeight?: number;
   overscan?: number;
   renderRow: (
@@ -38,7 +40,7 @@ type VirtualListProps = {
 
 // Virtualized list with lazy row hydration and synchronized column scrolling
 export const VirtualList = forwardRef<VirtualListHandle, VirtualListProps>(function VirtualList(
-  { results = [], rowHeight = 24, overscan = 5, renderRow, onScrollSync, className = '' },
+  { results = [], cursor = null, rowHeight = 24, overscan = 5, renderRow, onScrollSync, className = '' },
   ref,
 ) {
   // ----- refs -----
@@ -54,7 +56,7 @@ export const VirtualList = forwardRef<VirtualListHandle, VirtualListProps>(funct
   const rowCount = resultsList.length;
 
   // ----- data loader -----
-  const { cache, ensureRangeLoaded } = useDataLoader(resultsList);
+  const { cache, ensureRangeLoaded } = useDataLoader(resultsList, cursor);
 
   // Virtualized height powers the scrollbar math
   const totalHeight = rowCount * rowHeight;
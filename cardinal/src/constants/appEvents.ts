@@ -1 +1,2 @@
 export const OPEN_PREFERENCES_EVENT = 'cardinal:open-preferences';
+export const EXPORT_RESULTS_EVENT = 'cardinal:export-results';
@@ -5,7 +5,11 @@ import type { UnlistenFn } from '@tauri-apps/api/event';
 import type { NodeInfoResponse, SearchResultItem } from '../types/search';
 import type { SlabIndex } from '../types/slab';
 import { toSlabIndex } from '../types/slab';
-import type { IconUpdatePayload, IconUpdateWirePayload } from '../types/ipc';
+import type {
+  IconUpdatePayload,
+  IconUpdateWirePayload,
+  SearchErrorPayload,
+} from '../types/ipc';
 
 type IconUpdateEventPayload = readonly IconUpdateWirePayload[] | null | undefined;
 
@@ -18,16 +22,23 @@ const fromNodeInfo = (node: NodeInfoResponse): SearchResultItem => {
   const metadata = node.metadata ?? undefined;
   const base: SearchResultItem = {
     path: node.path,
+    rawPath: node.raw_path ?? undefined,
     metadata,
     size: node.size ?? metadata?.size,
     mtime: node.mtime ?? metadata?.mtime,
     ctime: node.ctime ?? metadata?.ctime,
     icon: normalizeIcon(node.icon),
+    tags: node.tags ?? undefined,
   };
   return base;
 };
 
-export function useDataLoader(results: SlabIndex[]) {
+const isCursorInvalidated = (error: unknown): boolean =>
+  typeof error === 'object' &&
+  error !== null &&
+  (error as SearchErrorPayload).code === 'cursor_invalidated';
+
+export function useDataLoader(results: SlabIndex[], cursor: number | null = null) {
   const loadingRef = useRef<Set<number>>(new Set());
   const versionRef = useRef(0);
   const cacheRef = useRef<DataLoaderCache>(new Map());
@@ -39,6 +50,8 @@ export function useDataLoader(results: SlabIndex[]) {
     return initial;
   });
   const resultsRef = useRef<SlabIndex[]>([]);
+  const cursorRef = useRef<number | null>(null);
+  const lastRangeRef = useRef<{ start: number; end: number } | null>(null);
 
   // Reset loading state whenever the result source changes.
   useEffect(() => {
@@ -48,6 +61,7 @@ export function useDataLoader(results: SlabIndex[]) {
     const nextCache = new Map<number, SearchResultItem>();
     cacheRef.current = nextCache;
     resultsRef.current = results;
+    cursorRef.current = cursor;
     const indexMap = new Map<SlabIndex, number>();
     resultsRef.current.forEach((value, index) => {
       if (value != null) {
@@ -56,7 +70,7 @@ export function useDataLoader(results: SlabIndex[]) {
     });
     indexMapRef.current = indexMap;
     setCache(nextCache);
-  }, [results]);
+  }, [results, cursor]);
 
   useEffect(() => {
     let unlistenIconUpdate: UnlistenFn | undefined;
@@ -122,7 +136,41 @@ export function useDataLoader(results: SlabIndex[]) {
     };
   }, []);
 
+  // One page of the cursor spanning the rows, or the rows by index once the
+  // cursor is gone or the index changed under it.
+  const fetchRows = useCallback(
+    async (rows: number[], list: SlabIndex[]): Promise<NodeInfoResponse[]> => {
+      const cursor = cursorRef.current;
+      if (cursor !== null) {
+        const offset = rows[0];
+        const limit = rows[rows.length - 1] - offset + 1;
+        try {
+          const page = await invoke<NodeInfoResponse[] | null>('cursor_page', {
+            cursor,
+            offset,
+            limit,
+          });
+          if (page) {
+            return rows.map((row) => page[row - offset]);
+          }
+        } catch (error) {
+          if (!isCursorInvalidated(error)) {
+            throw error;
+          }
+        }
+        if (cursorRef.current === cursor) {
+          cursorRef.current = null;
+        }
+      }
+      return invoke<NodeInfoResponse[]>('get_nodes_info', {
+        results: rows.map((row) => list[row]),
+      });
+    },
+    [],
+  );
+
   const ensureRangeLoaded = useCallback(async (start: number, end: number) => {
+    lastRangeRef.current = { start, end };
     const list = resultsRef.current;
     const total = list.length;
     if (start < 0 || end < start || total === 0) return;
@@ -136,8 +184,7 @@ export function useDataLoader(results: SlabIndex[]) {
     if (needLoading.length === 0) return;
     const versionAtRequest = versionRef.current;
     try {
-      const slice = needLoading.map((i) => list[i]);
-      const fetched = await invoke<NodeInfoResponse[]>('get_nodes_info', { results: slice });
+      const fetched = await fetchRows(needLoading, list);
       if (versionRef.current !== versionAtRequest) {
         needLoading.forEach((i) => loadingRef.current.delete(i));
         return;
@@ -185,7 +232,33 @@ export function useDataLoader(results: SlabIndex[]) {
       needLoading.forEach((i) => loadingRef.current.delete(i));
       console.error('Failed loading rows', err);
     }
-  }, []);
+  }, [fetchRows]);
+
+  // Rows carry icons rendered for the old appearance, load the visible ones
+  // again. QuickLook thumbnails don't depend on it and are kept.
+  useEffect(() => {
+    let unlistenAppearance: UnlistenFn | undefined;
+    (async () => {
+      try {
+        unlistenAppearance = await listen('appearance_changed', () => {
+          versionRef.current += 1;
+          loadingRef.current.clear();
+          const nextCache = new Map<number, SearchResultItem>();
+          cacheRef.current = nextCache;
+          setCache(nextCache);
+          const range = lastRangeRef.current;
+          if (range) {
+            void ensureRangeLoaded(range.start, range.end);
+          }
+        });
+      } catch (error) {
+        console.error('Failed to listen appearance_changed', error);
+      }
+    })();
+    return () => {
+      unlistenAppearance?.();
+    };
+  }, [ensureRangeLoaded]);
 
   return { cache, ensureRangeLoaded };
 }
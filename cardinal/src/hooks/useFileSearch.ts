@@ -1,19 +1,37 @@
 import { useReducer, useRef, useCallback, useEffect } from 'react';
 import type { MutableRefObject } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import type { UnlistenFn } from '@tauri-apps/api/event';
 import { SEARCH_DEBOUNCE_MS } from '../constants';
-import type { AppLifecycleStatus, SearchResponsePayload } from '../types/ipc';
+import type {
+  AppLifecycleStatus,
+  MetadataErrorCounts,
+  QueryNote,
+  SearchErrorPayload,
+  SearchRefinedPayload,
+  SearchResponsePayload,
+} from '../types/ipc';
 import type { SlabIndex } from '../types/slab';
 import { toSlabIndexArray } from '../types/slab';
 
 type SearchError = string | Error | null;
 
+const isSearchErrorPayload = (value: unknown): value is SearchErrorPayload =>
+  typeof value === 'object' &&
+  value !== null &&
+  typeof (value as SearchErrorPayload).code === 'string' &&
+  typeof (value as SearchErrorPayload).message === 'string';
+
 type SearchState = {
   results: SlabIndex[];
+  // Backend cursor over `results`, null when rows are loaded by index.
+  cursor: number | null;
   scannedFiles: number;
   processedEvents: number;
   currentQuery: string;
   highlightTerms: string[];
+  queryNotes: QueryNote[];
   showLoadingUI: boolean;
   initialFetchCompleted: boolean;
   durationMs: number | null;
@@ -25,6 +43,7 @@ type SearchState = {
 type SearchParams = {
   query: string;
   caseSensitive: boolean;
+  includeHidden: boolean;
 };
 
 type SearchAction =
@@ -35,10 +54,12 @@ type SearchAction =
       type: 'SEARCH_SUCCESS';
       payload: {
         results: SlabIndex[];
+        cursor: number | null;
         query: string;
         duration: number;
         count: number;
         highlightTerms: string[];
+        queryNotes: QueryNote[];
       };
     }
   | {
@@ -52,10 +73,12 @@ type SearchAction =
 
 const initialSearchState: SearchState = {
   results: [],
+  cursor: null,
   scannedFiles: 0,
   processedEvents: 0,
   currentQuery: '',
   highlightTerms: [],
+  queryNotes: [],
   showLoadingUI: false,
   initialFetchCompleted: false,
   durationMs: null,
@@ -67,8 +90,34 @@ const initialSearchState: SearchState = {
 const initialSearchParams: SearchParams = {
   query: '',
   caseSensitive: false,
+  includeHidden: false,
+};
+
+const toHighlightTerms = (highlights: unknown): string[] =>
+  Array.isArray(highlights)
+    ? highlights.filter((term): term is string => typeof term === 'string')
+    : [];
+
+const toQueryNotes = (notes: unknown): QueryNote[] =>
+  Array.isArray(notes)
+    ? notes.filter(
+        (note): note is QueryNote =>
+          typeof note === 'object' && note !== null && typeof (note as QueryNote).kind === 'string',
+      )
+    : [];
+
+// The Full Disk Access note shows how many files couldn't be examined.
+const withPermissionDeniedCount = (notes: QueryNote[], metadataErrors: unknown): QueryNote[] => {
+  const count = (metadataErrors as MetadataErrorCounts | undefined)?.permissionDenied;
+  return notes.map((note) =>
+    note.kind === 'metadataPermissionDenied' && typeof count === 'number'
+      ? { ...note, count }
+      : note,
+  );
 };
 
+const toCursor = (cursor: unknown): number | null => (typeof cursor === 'number' ? cursor : null);
+
 const cancelTimer = (timerRef: MutableRefObject<ReturnType<typeof setTimeout> | null>) => {
   if (timerRef.current) {
     clearTimeout(timerRef.current);
@@ -100,8 +149,10 @@ function reducer(state: SearchState, action: SearchAction): SearchState {
       return {
         ...state,
         results: action.payload.results,
+        cursor: action.payload.cursor,
         currentQuery: action.payload.query,
         highlightTerms: action.payload.highlightTerms,
+        queryNotes: action.payload.queryNotes,
         showLoadingUI: false,
         initialFetchCompleted: true,
         durationMs: action.payload.duration,
@@ -117,6 +168,7 @@ function reducer(state: SearchState, action: SearchAction): SearchState {
         durationMs: action.payload.duration,
         resultCount: 0,
         highlightTerms: [],
+        queryNotes: [],
       };
     case 'SET_LIFECYCLE_STATE':
       return {
@@ -150,7 +202,9 @@ export function useFileSearch(): UseFileSearchResult {
   const [state, dispatch] = useReducer(reducer, initialSearchState);
   const latestSearchRef = useRef<SearchParams>(initialSearchParams);
   const searchVersionRef = useRef(0);
+  const searchStartRef = useRef(0);
   const hasInitialSearchRunRef = useRef(false);
+  const pendingUntilIndexedRef = useRef(false);
   const debounceTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
   const loadingDelayTimerRef = useRef<ReturnType<typeof setTimeout> | null>(null);
 
@@ -200,8 +254,9 @@ export function useFileSearch(): UseFileSearchResult {
     const requestVersion = searchVersionRef.current + 1;
     searchVersionRef.current = requestVersion;
 
-    const { query, caseSensitive } = nextSearch;
+    const { query, caseSensitive, includeHidden } = nextSearch;
     const startTs = performance.now();
+    searchStartRef.current = startTs;
     const isInitial = !hasInitialSearchRunRef.current;
 
     dispatch({ type: 'SEARCH_REQUEST', payload: { immediate: isInitial } });
@@ -219,15 +274,18 @@ export function useFileSearch(): UseFileSearchResult {
         query,
         options: {
           caseInsensitive: !caseSensitive,
+          includeHidden,
         },
         version: requestVersion,
       });
 
       const slabResults = Array.isArray(rawResults?.results) ? rawResults.results : [];
       const searchResults = toSlabIndexArray(slabResults);
-      const highlightTerms = Array.isArray(rawResults?.highlights)
-        ? rawResults.highlights.filter((term): term is string => typeof term === 'string')
-        : [];
+      const highlightTerms = toHighlightTerms(rawResults?.highlights);
+      const queryNotes = withPermissionDeniedCount(
+        toQueryNotes(rawResults?.notes),
+        rawResults?.metadataErrors,
+      );
 
       if (searchVersionRef.current !== requestVersion) {
         return;
@@ -242,10 +300,12 @@ export function useFileSearch(): UseFileSearchResult {
         type: 'SEARCH_SUCCESS',
         payload: {
           results: searchResults,
+          cursor: toCursor(rawResults?.cursor),
           query,
           duration,
           count: searchResults.length,
           highlightTerms,
+          queryNotes,
         },
       });
     } catch (error) {
@@ -255,13 +315,29 @@ export function useFileSearch(): UseFileSearchResult {
         return;
       }
 
+      if (isSearchErrorPayload(error)) {
+        if (error.code === 'cancelled') {
+          return;
+        }
+        if (error.code === 'index_busy') {
+          // Keep the spinner up, the search is re-run once the index is ready.
+          pendingUntilIndexedRef.current = true;
+          return;
+        }
+      }
+
       cancelTimer(loadingDelayTimerRef);
 
       const endTs = performance.now();
       const duration = endTs - startTs;
 
-      const normalisedError =
-        error instanceof Error ? error : error ? String(error) : 'An unknown error occurred.';
+      const normalisedError = isSearchErrorPayload(error)
+        ? error.message
+        : error instanceof Error
+          ? error
+          : error
+            ? String(error)
+            : 'An unknown error occurred.';
 
       dispatch({
         type: 'SEARCH_FAILURE',
@@ -293,6 +369,44 @@ export function useFileSearch(): UseFileSearchResult {
 
   useEffect(() => cancelPendingSearches, [cancelPendingSearches]);
 
+  // Partial responses are followed by the complete results of the same search version.
+  useEffect(() => {
+    let unlistenRefined: UnlistenFn | undefined;
+    (async () => {
+      try {
+        unlistenRefined = await listen<SearchRefinedPayload>('search_refined', (event) => {
+          const payload = event?.payload;
+          if (!payload || payload.version !== searchVersionRef.current) {
+            return;
+          }
+          const searchResults = toSlabIndexArray(
+            Array.isArray(payload.results) ? payload.results : [],
+          );
+          dispatch({
+            type: 'SEARCH_SUCCESS',
+            payload: {
+              results: searchResults,
+              cursor: toCursor(payload.cursor),
+              query: latestSearchRef.current.query,
+              duration: performance.now() - searchStartRef.current,
+              count: searchResults.length,
+              highlightTerms: toHighlightTerms(payload.highlights),
+              queryNotes: withPermissionDeniedCount(
+                toQueryNotes(payload.notes),
+                payload.metadataErrors,
+              ),
+            },
+          });
+        });
+      } catch (error) {
+        console.error('Failed to listen for refined search results:', error);
+      }
+    })();
+    return () => {
+      unlistenRefined?.();
+    };
+  }, []);
+
   useEffect(() => {
     if (!hasInitialSearchRunRef.current) {
       void handleSearch({ query: '' });
@@ -304,7 +418,15 @@ export function useFileSearch(): UseFileSearchResult {
     }
 
     void handleSearch();
-  }, [handleSearch, searchParams.caseSensitive]);
+  }, [handleSearch, searchParams.caseSensitive, searchParams.includeHidden]);
+
+  useEffect(() => {
+    if (state.lifecycleState === 'Initializing' || !pendingUntilIndexedRef.current) {
+      return;
+    }
+    pendingUntilIndexedRef.current = false;
+    void handleSearch();
+  }, [handleSearch, state.lifecycleState]);
 
   const requestRescan = useCallback(async () => {
     try {
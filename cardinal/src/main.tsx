@@ -5,12 +5,18 @@ import App from './App';
 import { initializeAppMenu } from './menu';
 import { initializeTray } from './tray';
 import { initializeGlobalShortcuts } from './utils/globalShortcuts';
+import { initializeQuickSearchWindow, isQuickSearchWindow } from './utils/quickSearchWindow';
 import { initializeThemePreference } from './theme';
 
 initializeThemePreference();
-void initializeTray();
-void initializeGlobalShortcuts();
-void initializeAppMenu();
+// The tray, menu and shortcuts belong to the app, only the main window sets them up.
+if (isQuickSearchWindow()) {
+  initializeQuickSearchWindow();
+} else {
+  void initializeTray();
+  void initializeGlobalShortcuts();
+  void initializeAppMenu();
+}
 
 const rootElement = document.getElementById('root');
 
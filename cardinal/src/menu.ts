@@ -3,7 +3,8 @@ import { invoke } from '@tauri-apps/api/core';
 import { Menu, MenuItem, PredefinedMenuItem, Submenu } from '@tauri-apps/api/menu';
 import { openUrl } from '@tauri-apps/plugin-opener';
 import i18n from './i18n/config';
-import { OPEN_PREFERENCES_EVENT } from './constants/appEvents';
+import { EXPORT_RESULTS_EVENT, OPEN_PREFERENCES_EVENT } from './constants/appEvents';
+import type { ExportFormat } from './types/ipc';
 
 const HELP_UPDATES_URL = 'https://github.com/cardisoft/cardinal/releases';
 
@@ -55,6 +56,24 @@ async function buildAppMenu(): Promise<void> {
     ],
   });
 
+  const fileSubmenu = await Submenu.new({
+    id: 'menu.file',
+    text: i18n.t('menu.file'),
+    items: [
+      await MenuItem.new({
+        id: 'menu.export_csv',
+        text: i18n.t('menu.exportCsv'),
+        accelerator: 'CmdOrCtrl+Shift+E',
+        action: () => requestExport('csv'),
+      }),
+      await MenuItem.new({
+        id: 'menu.export_json',
+        text: i18n.t('menu.exportJson'),
+        action: () => requestExport('json'),
+      }),
+    ],
+  });
+
   const editSubmenu = await Submenu.new({
     id: 'menu.edit',
     text: i18n.t('menu.edit'),
@@ -100,7 +119,7 @@ async function buildAppMenu(): Promise<void> {
   await helpSubmenu.setAsHelpMenuForNSApp().catch(() => {});
 
   const menu = await Menu.new({
-    items: [appSubmenu, editSubmenu, viewSubmenu, windowSubmenu, helpSubmenu],
+    items: [appSubmenu, fileSubmenu, editSubmenu, viewSubmenu, windowSubmenu, helpSubmenu],
   });
   await menu.setAsAppMenu();
 }
@@ -136,3 +155,10 @@ function openPreferencesOverlay(): void {
   const event = new Event(OPEN_PREFERENCES_EVENT);
   window.dispatchEvent(event);
 }
+
+function requestExport(format: ExportFormat): void {
+  if (typeof window === 'undefined') {
+    return;
+  }
+  window.dispatchEvent(new CustomEvent<ExportFormat>(EXPORT_RESULTS_EVENT, { detail: format }));
+}
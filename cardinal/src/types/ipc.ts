@@ -1,8 +1,62 @@
 import type { SlabIndex } from './slab';
 
-export type StatusBarUpdatePayload = {
-  scannedFiles: number;
-  processedEvents: number;
+export type IndexState =
+  | 'idle'
+  | 'walking'
+  | 'processingEvents'
+  | 'replayingHistory'
+  | 'rescanning'
+  | 'saving'
+  | 'paused';
+
+export type IndexStatusPayload = {
+  state: IndexState;
+  eventsProcessed: number;
+  filesIndexed: number;
+  pendingEvents: number;
+  // Rows on screen renamed or removed since they were shown.
+  staleResults: number;
+};
+
+// What `validate_results` reports for each result, in the same order.
+export type ValidationPayload = {
+  state: 'valid' | 'renamed' | 'gone';
+  // Where a renamed result is now.
+  path: string | null;
+  raw_path: string | null;
+};
+
+export type ExportFormat = 'csv' | 'json';
+
+// Value of `export_results`, null when the save panel or the export was cancelled.
+export type ExportReceipt = {
+  path: string;
+  rows: number;
+  // Results no longer indexed, left out.
+  skipped: number;
+};
+
+// Emitted as `export_progress` while exporting more than 10,000 results.
+export type ExportProgressPayload = {
+  processed: number;
+  total: number;
+};
+
+// Value of `folder_size`, what `cancel_folder_size` takes.
+export type FolderSizeTicket = number;
+
+// Emitted as `folder_size_ready` once a folder size requested with `folder_size` is known.
+export type FolderSizeReadyPayload = {
+  index: number;
+  bytes: number;
+  fileCount: number;
+};
+
+export type IndexProgressPayload = {
+  filesSeen: number;
+  dirsSeen: number;
+  currentPath: string;
+  elapsedMs: number;
 };
 
 export type IconUpdateWirePayload = {
@@ -24,7 +78,81 @@ export type RecentEventPayload = {
 
 export type AppLifecycleStatus = 'Initializing' | 'Updating' | 'Ready';
 
+// Why filters matched less than expected, shown as hints under the search box.
+export type QueryNote =
+  | { kind: 'parentNotIndexed'; path: string }
+  | { kind: 'filterExcludesAllDirectories' }
+  | { kind: 'emptyExtensionList' }
+  | { kind: 'filterArmTruncated' }
+  // `count` is filled in from `metadataErrors.permissionDenied`.
+  | { kind: 'metadataPermissionDenied'; count?: number };
+
+// Files the filters of a search couldn't stat, by why.
+export type MetadataErrorCounts = {
+  permissionDenied: number;
+  notFound: number;
+  other: number;
+};
+
 export type SearchResponsePayload = {
   results: number[];
   highlights?: string[];
+  // Found before the backend deadline; complete results follow as `search_refined`.
+  partial?: boolean;
+  notes?: QueryNote[];
+  metadataErrors?: MetadataErrorCounts;
+  // Pages the results with their metadata through `cursor_page`; null for partial results.
+  cursor?: number | null;
+};
+
+export type SearchErrorCode =
+  | 'parse'
+  | 'unknown_variable'
+  | 'cancelled'
+  | 'index_busy'
+  | 'invalid_scope'
+  | 'too_broad'
+  | 'too_deep'
+  | 'cursor_invalidated'
+  | 'internal';
+
+// Rejection value of the `search` command.
+export type SearchErrorPayload = {
+  code: SearchErrorCode;
+  message: string;
+  // Byte offset in the query the error points at.
+  span?: number;
+};
+
+// Value of `get_settings` and argument of `update_settings`.
+export type AppSettings = {
+  watchRoots: string[];
+  excludes: string[];
+  // 0 means no limit.
+  maxResults: number;
+  fseLatency: number;
+  // Global shortcut toggling the quick search window, e.g. `Alt+Space`; empty disables it.
+  quickSearchShortcut: string;
+  // Background cache saves: minutes between them and events that trigger one early; 0 turns either off.
+  autosaveMinutes: number;
+  autosaveEvents: number;
+  // Also index `/System/Volumes/Data/Users` and the like next to `/Users`, listing their files twice.
+  indexFirmlinkDuplicates: boolean;
+};
+
+export type SettingsErrorCode = 'invalid' | 'shortcut_unavailable' | 'internal';
+
+// Rejection value of the `update_settings` command.
+export type SettingsErrorPayload = {
+  code: SettingsErrorCode;
+  message: string;
+};
+
+export type SearchRefinedPayload = {
+  version: number;
+  results: number[];
+  highlights?: string[];
+  notes?: QueryNote[];
+  metadataErrors?: MetadataErrorCounts;
+  cursor?: number;
 };
@@ -1,24 +1,35 @@
 export type SearchResultMetadata = Readonly<{
   type: number;
   size: number;
+  // Bytes allocated on disk, smaller than `size` for sparse and cloned files.
+  allocated?: number | null;
+  // Hard links of one file share the inode, `nlink` counts them.
+  inode?: number | null;
+  nlink?: number | null;
   mtime: number;
   ctime: number;
 }>;
 
 export type SearchResultItem = Readonly<{
   path: string;
+  // Base64 of the exact path bytes, only present when `path` is a lossy rendering.
+  rawPath?: string;
   metadata?: SearchResultMetadata;
   size?: number;
   mtime?: number;
   ctime?: number;
   icon?: string;
+  // User tags, sorted. Set with the `add_tag` and `remove_tag` commands.
+  tags?: readonly string[];
 }>;
 
 export type NodeInfoResponse = Readonly<{
   path: string;
+  raw_path?: string | null;
   icon?: string | null;
   metadata?: SearchResultMetadata | null;
   size?: number | null;
   mtime?: number | null;
   ctime?: number | null;
+  tags?: string[] | null;
 }>;
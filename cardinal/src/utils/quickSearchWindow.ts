@@ -0,0 +1,16 @@
+import { invoke } from '@tauri-apps/api/core';
+
+// Label and query parameter of the window created by `toggle_quick_search`.
+export const QUICK_SEARCH_WINDOW_LABEL = 'quick-search';
+
+export function isQuickSearchWindow(): boolean {
+  return new URLSearchParams(window.location.search).get('window') === QUICK_SEARCH_WINDOW_LABEL;
+}
+
+export function initializeQuickSearchWindow(): void {
+  window.addEventListener('keydown', (event) => {
+    if (event.key === 'Escape' && !event.defaultPrevented) {
+      void invoke('hide_quick_search');
+    }
+  });
+}
@@ -0,0 +1,177 @@
+//! PNG files cached on disk, so the webview can load icons by path instead
+//! of receiving their bytes over IPC.
+
+use crate::{Appearance, IconOptions, icon_of_path_with};
+use std::{
+    ffi::OsStr,
+    fs::{self, File, FileTimes},
+    io,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const EXTENSION: &str = "png";
+
+/// Cache key of the icon of `path` rendered with `opts`, built from the
+/// canonical path, the file's modification time and size, and the options,
+/// with [`Appearance::System`] keyed as the appearance it renders for now.
+/// Editing the file changes its key, so a stale icon is never returned; the
+/// old file is left for [`prune_cache`]. The key only depends on its inputs,
+/// so it stays valid across runs. `None` if `path` can't be stat'ed.
+pub fn cache_key(path: &Path, opts: IconOptions) -> Option<u64> {
+    let canonical = path.canonicalize().ok()?;
+    let metadata = fs::metadata(&canonical).ok()?;
+    let mtime = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Some(key_of(&canonical, mtime, metadata.len(), resolved(opts)))
+}
+
+fn resolved(opts: IconOptions) -> IconOptions {
+    IconOptions {
+        appearance: opts.appearance.resolve(),
+        ..opts
+    }
+}
+
+fn key_of(canonical: &Path, mtime: Duration, size: u64, opts: IconOptions) -> u64 {
+    // FNV-1a: std's hashers may change between releases, the keys must not.
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    let appearance = match opts.appearance {
+        Appearance::Light => 0,
+        Appearance::Dark => 1,
+        Appearance::System => 2,
+    };
+    let fields: [&[u8]; 9] = [
+        canonical.as_os_str().as_bytes(),
+        // Paths can't contain NUL, so the path ends unambiguously.
+        &[0],
+        &mtime.as_secs().to_le_bytes(),
+        &mtime.subsec_nanos().to_le_bytes(),
+        &size.to_le_bytes(),
+        &[u8::from(opts.thumbnail)],
+        &opts.thumbnail_size.to_bits().to_le_bytes(),
+        &opts.icon_size.to_bits().to_le_bytes(),
+        &[appearance],
+    ];
+    fields
+        .iter()
+        .flat_map(|field| field.iter())
+        .fold(OFFSET_BASIS, |hash, &byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+}
+
+/// Like [`icon_of_path_with`], but returns the path of a PNG in `cache_dir`
+/// instead of its bytes. An existing file for the same key is reused,
+/// otherwise the icon is rendered and written to a temporary file that is
+/// renamed into place, so concurrent calls for one key never expose a
+/// partially written file.
+pub fn icon_of_path_cached(path: &str, opts: IconOptions, cache_dir: &Path) -> Option<PathBuf> {
+    // Rendered for the appearance keyed, even if the system switches meanwhile.
+    let opts = resolved(opts);
+    let key = cache_key(Path::new(path), opts)?;
+    let cached = cache_dir.join(format!("{key:016x}.{EXTENSION}"));
+    if cached.is_file() {
+        // Keeps recently shown icons out of reach of `prune_cache`.
+        let _ = touch(&cached, SystemTime::now());
+        return Some(cached);
+    }
+    let data = icon_of_path_with(path, opts)?;
+    fs::create_dir_all(cache_dir).ok()?;
+    static TEMP_FILES: AtomicU64 = AtomicU64::new(0);
+    let temp = cache_dir.join(format!(
+        ".{key:016x}.{}.{}.tmp",
+        process::id(),
+        TEMP_FILES.fetch_add(1, Ordering::Relaxed)
+    ));
+    match fs::write(&temp, data).and_then(|()| fs::rename(&temp, &cached)) {
+        Ok(()) => Some(cached),
+        Err(_) => {
+            let _ = fs::remove_file(&temp);
+            None
+        }
+    }
+}
+
+/// Remove the least recently used icons from `cache_dir` until the remaining
+/// ones take at most `max_bytes`. A file's last use is the later of its access
+/// and modification times, cache hits bump both. Files other than cached icons
+/// are left alone. Returns the number of bytes freed.
+pub fn prune_cache(cache_dir: &Path, max_bytes: u64) -> io::Result<u64> {
+    let entries = match fs::read_dir(cache_dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e),
+    };
+    let mut icons = Vec::new();
+    let mut total = 0;
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension() != Some(OsStr::new(EXTENSION)) {
+            continue;
+        }
+        let Ok(metadata) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        if !metadata.is_file() {
+            continue;
+        }
+        let last_used = [metadata.accessed(), metadata.modified()]
+            .into_iter()
+            .filter_map(Result::ok)
+            .max()
+            .unwrap_or(UNIX_EPOCH);
+        total += metadata.len();
+        icons.push((last_used, metadata.len(), path));
+    }
+    icons.sort_unstable_by(|(used_a, _, path_a), (used_b, _, path_b)| {
+        used_a.cmp(used_b).then_with(|| path_a.cmp(path_b))
+    });
+    let mut freed = 0;
+    for (_, len, path) in icons {
+        if total - freed <= max_bytes {
+            break;
+        }
+        match fs::remove_file(&path) {
+            Ok(()) => freed += len,
+            // Pruned concurrently, it doesn't count as freed here.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(freed)
+}
+
+fn touch(path: &Path, time: SystemTime) -> io::Result<()> {
+    File::options()
+        .write(true)
+        .open(path)?
+        .set_times(FileTimes::new().set_accessed(time).set_modified(time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_is_pinned() {
+        // Changing the key orphans every cached icon, bump this on purpose only.
+        let key = key_of(
+            Path::new("/Users/demo/a.png"),
+            Duration::new(1_700_000_000, 5),
+            1024,
+            IconOptions {
+                appearance: Appearance::Light,
+                ..IconOptions::default()
+            },
+        );
+        assert_eq!(key, 0xa7e3_a9e3_76b1_9f24);
+    }
+}
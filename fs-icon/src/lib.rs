@@ -1,15 +1,32 @@
+mod disk_cache;
+
 use block2::RcBlock;
 use crossbeam_channel::bounded;
+pub use disk_cache::{cache_key, icon_of_path_cached, prune_cache};
 use objc2::{AnyThread, rc::Retained};
-use objc2_app_kit::{NSBitmapImageFileType, NSBitmapImageRep, NSImage, NSWorkspace};
+use objc2_app_kit::{
+    NSAppearance, NSAppearanceName, NSAppearanceNameAqua, NSAppearanceNameDarkAqua,
+    NSBitmapImageFileType, NSBitmapImageRep, NSColor, NSCompositingOperation, NSImage,
+    NSRectFillUsingOperation, NSWorkspace,
+};
 use objc2_core_foundation::{CFNumber, CFString, CFURL, Type};
-use objc2_foundation::{NSData, NSDictionary, NSError, NSSize, NSString, NSURL};
+use objc2_foundation::{
+    NSData, NSDictionary, NSError, NSRect, NSSize, NSString, NSURL, NSUserDefaults, ns_string,
+};
 use objc2_image_io::{CGImageSource, kCGImagePropertyPixelHeight, kCGImagePropertyPixelWidth};
 use objc2_quick_look_thumbnailing::{
     QLThumbnailGenerationRequest, QLThumbnailGenerationRequestRepresentationTypes,
     QLThumbnailGenerator, QLThumbnailRepresentation,
 };
-use std::ffi::c_void;
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::c_void,
+    sync::{
+        LazyLock, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
 
 pub fn scale_with_aspect_ratio(
     width: f64,
@@ -23,75 +40,251 @@ pub fn scale_with_aspect_ratio(
     (width * ratio, height * ratio)
 }
 
+/// How [`icon_of_path_with`] renders a path.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct IconOptions {
+    /// Try a QuickLook thumbnail first, only images get one.
+    pub thumbnail: bool,
+    /// Box the thumbnail is scaled into.
+    pub thumbnail_size: f64,
+    /// Box the NSWorkspace icon is rendered into.
+    pub icon_size: f64,
+    /// Appearance the NSWorkspace icon is rendered for, thumbnails don't
+    /// depend on it.
+    pub appearance: Appearance,
+}
+
+impl Default for IconOptions {
+    fn default() -> Self {
+        Self {
+            thumbnail: true,
+            thumbnail_size: THUMBNAIL_SIZE,
+            // zoom in and you will see that the small icon in Finder is 32x32
+            icon_size: 32.0,
+            appearance: Appearance::System,
+        }
+    }
+}
+
+/// Light or dark mode. NSWorkspace renders icons for the drawing appearance
+/// current at the time, which on a background thread is light mode whatever
+/// the system uses, so icons name the one they are rendered for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Appearance {
+    Light,
+    Dark,
+    /// The system's appearance when the icon is rendered, see [`current_appearance`].
+    System,
+}
+
+impl Appearance {
+    /// `Light` or `Dark`, `System` being replaced by [`current_appearance`].
+    pub fn resolve(self) -> Self {
+        match self {
+            Self::System => current_appearance(),
+            appearance => appearance,
+        }
+    }
+
+    fn name(self) -> &'static NSAppearanceName {
+        match self.resolve() {
+            Self::Dark => unsafe { NSAppearanceNameDarkAqua },
+            _ => unsafe { NSAppearanceNameAqua },
+        }
+    }
+}
+
+/// Whether the system is in light or dark mode, read from the user defaults
+/// so the answer is the same on every thread. Icons rendered before a switch
+/// are stale after it: the app listens for the change and fetches the
+/// visible ones again.
+pub fn current_appearance() -> Appearance {
+    let style =
+        NSUserDefaults::standardUserDefaults().stringForKey(ns_string!("AppleInterfaceStyle"));
+    match style {
+        Some(style) if style.to_string().eq_ignore_ascii_case("dark") => Appearance::Dark,
+        _ => Appearance::Light,
+    }
+}
+
+/// Run `render` with `appearance` as the drawing appearance, so icons and the
+/// colors they are tinted with come out for it.
+fn render_in<T>(appearance: Appearance, render: impl FnOnce() -> Option<T>) -> Option<T> {
+    let Some(appearance) = NSAppearance::appearanceNamed(appearance.name()) else {
+        return render();
+    };
+    // The block may only be `Fn`, it's called once.
+    let state = RefCell::new((Some(render), None));
+    appearance.performAsCurrentDrawingAppearance(&RcBlock::new(|| {
+        let mut state = state.borrow_mut();
+        if let Some(render) = state.0.take() {
+            state.1 = render();
+        }
+    }));
+    state.into_inner().1
+}
+
+const THUMBNAIL_SIZE: f64 = 64.0;
+
 pub fn icon_of_path(path: &str) -> Option<Vec<u8>> {
-    if let Some(data) = icon_of_path_ql(path) {
-        return Some(data);
+    icon_of_path_with(path, IconOptions::default())
+}
+
+pub fn icon_of_path_with(path: &str, opts: IconOptions) -> Option<Vec<u8>> {
+    if opts.thumbnail {
+        if let Some(data) = thumbnail_of_path(path, opts.thumbnail_size) {
+            return Some(data);
+        }
     }
-    icon_of_path_ns(path)
+    workspace_icon_of_path(path, opts.icon_size, opts.appearance)
 }
 
-// https://stackoverflow.com/questions/73062803/resizing-nsimage-keeping-aspect-ratio-reducing-the-image-size-while-trying-to-sc
 pub fn icon_of_path_ns(path: &str) -> Option<Vec<u8>> {
+    let opts = IconOptions::default();
+    workspace_icon_of_path(path, opts.icon_size, opts.appearance)
+}
+
+// https://stackoverflow.com/questions/73062803/resizing-nsimage-keeping-aspect-ratio-reducing-the-image-size-while-trying-to-sc
+fn workspace_icon_of_path(path: &str, size: f64, appearance: Appearance) -> Option<Vec<u8>> {
     objc2::rc::autoreleasepool(|_| -> Option<Vec<u8>> {
-        let path_ns = NSString::from_str(path);
-        let image = NSWorkspace::sharedWorkspace().iconForFile(&path_ns);
+        render_in(appearance, || {
+            let path_ns = NSString::from_str(path);
+            let image = NSWorkspace::sharedWorkspace().iconForFile(&path_ns);
+            png_of_icon(image, size)
+        })
+    })
+}
 
-        let png_data: Retained<NSData> = (|| -> Option<_> {
-            unsafe {
-                // https://stackoverflow.com/questions/66270656/macos-determine-real-size-of-icon-returned-from-iconforfile-method
-                for image in image.representations().iter() {
-                    let size = image.size();
-                    if size.width > 31.0
-                        && size.height > 31.0
-                        && size.width < 33.0
-                        && size.height < 33.0
-                    {
-                        // println!("representation: {}x{}", size.width, size.height);
-                        let new_image = NSImage::imageWithSize_flipped_drawingHandler(
-                            NSSize::new(size.width, size.height),
-                            false,
-                            &block2::RcBlock::new(move |rect| {
-                                image.drawInRect(rect);
-                                true.into()
-                            }),
-                        );
-                        return NSBitmapImageRep::imageRepWithData(
-                            &*new_image.TIFFRepresentation()?,
-                        )?
+/// Generic icon Finder shows for files with extension `ext`, rendered into a
+/// `size`x`size` box for the system's appearance. Never touches the
+/// filesystem, so it's safe to call for paths on disconnected volumes.
+/// Unknown extensions yield the generic document icon.
+pub fn icon_of_extension(ext: &str, size: f64) -> Option<Vec<u8>> {
+    icon_of_extension_in(ext, size, Appearance::System)
+}
+
+/// [`icon_of_extension`] rendered for `appearance`.
+pub fn icon_of_extension_in(ext: &str, size: f64, appearance: Appearance) -> Option<Vec<u8>> {
+    let ext = ext.trim_start_matches('.').to_ascii_lowercase();
+    generic_icon(ext, size, appearance)
+}
+
+/// Generic folder icon rendered into a `size`x`size` box for the system's
+/// appearance.
+pub fn icon_of_folder(size: f64) -> Option<Vec<u8>> {
+    icon_of_folder_in(size, Appearance::System)
+}
+
+/// [`icon_of_folder`] rendered for `appearance`.
+pub fn icon_of_folder_in(size: f64, appearance: Appearance) -> Option<Vec<u8>> {
+    generic_icon(FOLDER_FILE_TYPE.to_string(), size, appearance)
+}
+
+const FOLDER_FILE_TYPE: &str = "public.folder";
+
+type GenericIconCache = HashMap<(String, u64, Appearance), Option<Vec<u8>>>;
+
+// Generic icons only change with the appearance, so they are rendered at most
+// once per (file type, size, light or dark).
+static GENERIC_ICONS: LazyLock<Mutex<GenericIconCache>> = LazyLock::new(Default::default);
+static GENERIC_ICON_RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Number of generic icons rendered so far, cache hits excluded.
+#[doc(hidden)]
+pub fn generic_icon_render_count() -> usize {
+    GENERIC_ICON_RENDERS.load(Ordering::Relaxed)
+}
+
+fn generic_icon(file_type: String, size: f64, appearance: Appearance) -> Option<Vec<u8>> {
+    let key = (file_type, size.to_bits(), appearance.resolve());
+    if let Some(data) = GENERIC_ICONS.lock().unwrap().get(&key) {
+        return data.clone();
+    }
+    let data = objc2::rc::autoreleasepool(|_| -> Option<Vec<u8>> {
+        GENERIC_ICON_RENDERS.fetch_add(1, Ordering::Relaxed);
+        render_in(key.2, || {
+            let file_type = NSString::from_str(&key.0);
+            #[allow(deprecated)]
+            let image = NSWorkspace::sharedWorkspace().iconForFileType(&file_type);
+            png_of_icon(image, size)
+        })
+    });
+    GENERIC_ICONS
+        .lock()
+        .unwrap()
+        .entry(key)
+        .or_insert(data)
+        .clone()
+}
+
+/// Encode `image` as PNG, preferring a native representation of `size`x`size`
+/// and otherwise scaling it into that box. Template images are only a mask,
+/// drawn as is they'd be a black square: they get the label color of the
+/// current drawing appearance, like AppKit tints them in a view.
+fn png_of_icon(image: Retained<NSImage>, size: f64) -> Option<Vec<u8>> {
+    let template = image.isTemplate();
+    let png_data: Retained<NSData> = (|| -> Option<_> {
+        unsafe {
+            // https://stackoverflow.com/questions/66270656/macos-determine-real-size-of-icon-returned-from-iconforfile-method
+            for image in image.representations().iter() {
+                let rep_size = image.size();
+                if rep_size.width > size - 1.0
+                    && rep_size.height > size - 1.0
+                    && rep_size.width < size + 1.0
+                    && rep_size.height < size + 1.0
+                {
+                    // println!("representation: {}x{}", rep_size.width, rep_size.height);
+                    let new_image = NSImage::imageWithSize_flipped_drawingHandler(
+                        NSSize::new(rep_size.width, rep_size.height),
+                        false,
+                        &block2::RcBlock::new(move |rect| {
+                            image.drawInRect(rect);
+                            if template {
+                                tint(rect);
+                            }
+                            true.into()
+                        }),
+                    );
+                    return NSBitmapImageRep::imageRepWithData(&*new_image.TIFFRepresentation()?)?
                         .representationUsingType_properties(
                             NSBitmapImageFileType::PNG,
                             &NSDictionary::new(),
                         );
-                    }
                 }
             }
-            // zoom in and you will see that the small icon in Finder is 32x32, here we keep it at 64x64 for better visibility
-            let (new_width, new_height) = {
-                let width = 32.0;
-                let height = 32.0;
-                // keep aspect ratio
-                let old_width = image.size().width;
-                let old_height = image.size().height;
-                scale_with_aspect_ratio(old_width, old_height, width, height)
-            };
-            unsafe {
-                let new_image = NSImage::imageWithSize_flipped_drawingHandler(
-                    NSSize::new(new_width, new_height),
-                    false,
-                    &block2::RcBlock::new(move |rect| {
-                        image.drawInRect(rect);
-                        true.into()
-                    }),
-                );
-                NSBitmapImageRep::imageRepWithData(&*new_image.TIFFRepresentation()?)?
-                    .representationUsingType_properties(
-                        NSBitmapImageFileType::PNG,
-                        &NSDictionary::new(),
-                    )
-            }
-        })()?;
-        Some(png_data.to_vec())
-    })
+        }
+        let (new_width, new_height) = {
+            // keep aspect ratio
+            let old_width = image.size().width;
+            let old_height = image.size().height;
+            scale_with_aspect_ratio(old_width, old_height, size, size)
+        };
+        unsafe {
+            let new_image = NSImage::imageWithSize_flipped_drawingHandler(
+                NSSize::new(new_width, new_height),
+                false,
+                &block2::RcBlock::new(move |rect| {
+                    image.drawInRect(rect);
+                    if template {
+                        tint(rect);
+                    }
+                    true.into()
+                }),
+            );
+            NSBitmapImageRep::imageRepWithData(&*new_image.TIFFRepresentation()?)?
+                .representationUsingType_properties(
+                    NSBitmapImageFileType::PNG,
+                    &NSDictionary::new(),
+                )
+        }
+    })()?;
+    Some(png_data.to_vec())
+}
+
+/// Paint the label color over what was drawn in `rect`, keeping its alpha.
+fn tint(rect: NSRect) {
+    NSColor::labelColor().set();
+    NSRectFillUsingOperation(rect, NSCompositingOperation::SourceAtop);
 }
 
 pub fn image_dimension(image_path: &str) -> Option<(f64, f64)> {
@@ -117,14 +310,16 @@ pub fn image_dimension(image_path: &str) -> Option<(f64, f64)> {
 }
 
 pub fn icon_of_path_ql(path: &str) -> Option<Vec<u8>> {
+    thumbnail_of_path(path, THUMBNAIL_SIZE)
+}
+
+fn thumbnail_of_path(path: &str, size: f64) -> Option<Vec<u8>> {
     // We only get QLThumbnail for image, get NSWorkspace icon for other file types.
     // Therefore we just error out when image_dimension is not found.
     let (width, height) = image_dimension(path)?;
     objc2::rc::autoreleasepool(|_| -> Option<Vec<u8>> {
-        const THUMBNAIL_SIZE: f64 = 64.0;
         const THUMBNAIL_SCALE: f64 = 1.0;
-        let (width, height) =
-            scale_with_aspect_ratio(width, height, THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+        let (width, height) = scale_with_aspect_ratio(width, height, size, size);
         // use a slightly larger thumbnail size with 0.5 scale
         let path_url = NSURL::fileURLWithPath(&NSString::from_str(path));
         let generator = unsafe { QLThumbnailGenerator::sharedGenerator() };
@@ -179,6 +374,50 @@ mod tests {
         std::fs::write("/tmp/icon.png", data).unwrap();
     }
 
+    #[test]
+    fn test_icon_of_path_differs_between_appearances() {
+        let folder = std::env::current_dir()
+            .unwrap()
+            .to_string_lossy()
+            .into_owned();
+        let icon_in = |appearance| {
+            let opts = IconOptions {
+                thumbnail: false,
+                appearance,
+                ..IconOptions::default()
+            };
+            icon_of_path_with(&folder, opts).unwrap()
+        };
+        let light = icon_in(Appearance::Light);
+        let dark = icon_in(Appearance::Dark);
+        assert!(light.starts_with(b"\x89PNG"));
+        assert!(dark.starts_with(b"\x89PNG"));
+        assert_ne!(light, dark);
+        assert_eq!(icon_in(Appearance::System), icon_in(current_appearance()));
+    }
+
+    #[test]
+    fn test_template_images_are_tinted_for_the_appearance() {
+        let render = |appearance| {
+            render_in(appearance, || {
+                let image = NSImage::imageWithSize_flipped_drawingHandler(
+                    NSSize::new(16.0, 16.0),
+                    false,
+                    &RcBlock::new(|rect| {
+                        NSColor::blackColor().set();
+                        NSRectFillUsingOperation(rect, NSCompositingOperation::Copy);
+                        true.into()
+                    }),
+                );
+                image.setTemplate(true);
+                png_of_icon(image, 16.0)
+            })
+            .unwrap()
+        };
+        // Black in light mode, but not a black square in dark mode.
+        assert_ne!(render(Appearance::Light), render(Appearance::Dark));
+    }
+
     #[test]
     fn test_icon_of_path_ql_normal() {
         let data = icon_of_path_ql("../cardinal/mac-icon_1024x1024.png").unwrap();
@@ -195,6 +434,26 @@ mod tests {
         icon_of_path_ql(&pwd).expect("should fail for non-image file");
     }
 
+    #[test]
+    fn test_icon_of_extension_distinct_types() {
+        let pdf = icon_of_extension("pdf", 32.0).unwrap();
+        let txt = icon_of_extension("txt", 32.0).unwrap();
+        assert!(pdf.starts_with(b"\x89PNG"));
+        assert!(txt.starts_with(b"\x89PNG"));
+        assert_ne!(pdf, txt);
+        // Leading dot and case are ignored.
+        assert_eq!(icon_of_extension(".PDF", 32.0).unwrap(), pdf);
+    }
+
+    #[test]
+    fn test_icon_of_extension_unknown_falls_back_to_document() {
+        let unknown = icon_of_extension("cardinal-no-such-ext", 32.0).unwrap();
+        let other_unknown = icon_of_extension("another-no-such-ext", 32.0).unwrap();
+        assert!(!unknown.is_empty());
+        assert_eq!(unknown, other_unknown);
+        assert_ne!(unknown, icon_of_folder(32.0).unwrap());
+    }
+
     #[test]
     fn test_icon_dimension() {
         let (width, height) = image_dimension("../cardinal/mac-icon_1024x1024.png").unwrap();
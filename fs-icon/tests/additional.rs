@@ -1,4 +1,7 @@
-use fs_icon::{icon_of_path, scale_with_aspect_ratio};
+use fs_icon::{
+    generic_icon_render_count, icon_of_extension, icon_of_folder, icon_of_path,
+    scale_with_aspect_ratio,
+};
 
 #[test]
 fn scale_extreme_aspect_ratios() {
@@ -28,3 +31,19 @@ fn icon_of_path_fallback_for_non_image() {
     let data = icon_of_path(cwd.to_str().unwrap()).expect("fallback icon should exist");
     assert!(!data.is_empty());
 }
+
+#[test]
+fn generic_icons_are_rendered_once_per_type_and_size() {
+    // Only test in this binary touching generic icons, so the counter is stable.
+    let folder = icon_of_folder(16.0).expect("folder icon should exist");
+    let rtf = icon_of_extension("rtf", 48.0).expect("rtf icon should exist");
+    let renders = generic_icon_render_count();
+    for _ in 0..3 {
+        assert_eq!(icon_of_folder(16.0).unwrap(), folder);
+        assert_eq!(icon_of_extension("rtf", 48.0).unwrap(), rtf);
+    }
+    assert_eq!(generic_icon_render_count(), renders);
+    // A new size is a new cache entry.
+    icon_of_extension("rtf", 64.0).unwrap();
+    assert_eq!(generic_icon_render_count(), renders + 1);
+}
@@ -0,0 +1,170 @@
+use fs_icon::{
+    Appearance, IconOptions, cache_key, current_appearance, icon_of_path_cached, prune_cache,
+};
+use std::{
+    fs::{self, File, FileTimes},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+/// A fresh directory under the system temp dir, removed on drop.
+struct ScratchDir(PathBuf);
+
+impl ScratchDir {
+    fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("fs-icon-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn set_times(path: &Path, time: SystemTime) {
+    File::options()
+        .write(true)
+        .open(path)
+        .unwrap()
+        .set_times(FileTimes::new().set_accessed(time).set_modified(time))
+        .unwrap();
+}
+
+fn file_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn cache_key_is_stable() {
+    let scratch = ScratchDir::new("key");
+    let source = scratch.0.join("notes.txt");
+    fs::write(&source, b"notes").unwrap();
+    let opts = IconOptions::default();
+    let key = cache_key(&source, opts).unwrap();
+    assert_eq!(cache_key(&source, opts), Some(key));
+    // Another spelling of the same file shares the key.
+    let dotted = scratch.0.join(".").join("notes.txt");
+    assert_eq!(cache_key(&dotted, opts), Some(key));
+
+    let other = IconOptions {
+        icon_size: 16.0,
+        ..opts
+    };
+    assert_ne!(cache_key(&source, other), Some(key));
+    let no_thumbnail = IconOptions {
+        thumbnail: false,
+        ..opts
+    };
+    assert_ne!(cache_key(&source, no_thumbnail), Some(key));
+    assert_eq!(cache_key(&scratch.0.join("missing.txt"), opts), None);
+}
+
+#[test]
+fn cache_keys_separate_appearances() {
+    let scratch = ScratchDir::new("appearance");
+    let source = scratch.0.join("notes.txt");
+    fs::write(&source, b"notes").unwrap();
+    let key = |appearance| {
+        let opts = IconOptions {
+            appearance,
+            ..IconOptions::default()
+        };
+        cache_key(&source, opts).unwrap()
+    };
+    assert_ne!(key(Appearance::Light), key(Appearance::Dark));
+    // The system's appearance shares the key of the one it is now.
+    assert_eq!(key(Appearance::System), key(current_appearance()));
+}
+
+#[test]
+fn cached_icon_is_reused_until_the_source_changes() {
+    let scratch = ScratchDir::new("freshness");
+    let cache_dir = scratch.0.join("cache");
+    let source = scratch.0.join("notes.txt");
+    fs::write(&source, b"notes").unwrap();
+    let source_str = source.to_str().unwrap();
+    let opts = IconOptions::default();
+
+    let first = icon_of_path_cached(source_str, opts, &cache_dir).expect("icon should be cached");
+    assert!(first.starts_with(&cache_dir));
+    let data = fs::read(&first).unwrap();
+    assert!(data.starts_with(b"\x89PNG"));
+    assert_eq!(
+        icon_of_path_cached(source_str, opts, &cache_dir),
+        Some(first.clone())
+    );
+
+    let later = SystemTime::now() + Duration::from_secs(60);
+    set_times(&source, later);
+    let second = icon_of_path_cached(source_str, opts, &cache_dir).expect("icon should be cached");
+    assert_ne!(first, second);
+    assert!(second.is_file());
+}
+
+#[test]
+fn concurrent_calls_share_one_complete_file() {
+    let scratch = ScratchDir::new("concurrent");
+    let cache_dir = scratch.0.join("cache");
+    let source = scratch.0.join("notes.txt");
+    fs::write(&source, b"notes").unwrap();
+    let paths: Vec<Option<PathBuf>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                scope.spawn(|| {
+                    icon_of_path_cached(
+                        source.to_str().unwrap(),
+                        IconOptions::default(),
+                        &cache_dir,
+                    )
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+    let first = paths[0].clone().expect("icon should be cached");
+    assert!(paths.iter().all(|path| path.as_ref() == Some(&first)));
+    assert!(fs::read(&first).unwrap().starts_with(b"\x89PNG"));
+    // No temporary files are left behind.
+    assert_eq!(file_names(&cache_dir).len(), 1);
+}
+
+#[test]
+fn prune_evicts_least_recently_used_icons() {
+    let scratch = ScratchDir::new("prune");
+    let dir = &scratch.0;
+    let now = SystemTime::now();
+    for (age, name) in [
+        (40, "oldest.png"),
+        (30, "old.png"),
+        (20, "new.png"),
+        (10, "newest.png"),
+    ] {
+        let path = dir.join(name);
+        fs::write(&path, [0u8; 100]).unwrap();
+        set_times(&path, now - Duration::from_secs(age));
+    }
+    fs::write(dir.join("notes.txt"), [0u8; 1000]).unwrap();
+
+    assert_eq!(prune_cache(dir, 400).unwrap(), 0);
+    assert_eq!(prune_cache(dir, 250).unwrap(), 200);
+    assert_eq!(file_names(dir), ["new.png", "newest.png", "notes.txt"]);
+
+    // Using an icon moves it to the back of the queue.
+    set_times(&dir.join("new.png"), now);
+    assert_eq!(prune_cache(dir, 100).unwrap(), 100);
+    assert_eq!(file_names(dir), ["new.png", "notes.txt"]);
+
+    assert_eq!(prune_cache(dir, 0).unwrap(), 100);
+    assert_eq!(file_names(dir), ["notes.txt"]);
+    assert_eq!(prune_cache(&dir.join("missing"), 0).unwrap(), 0);
+}
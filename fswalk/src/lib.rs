@@ -1,30 +1,68 @@
+use crossbeam_channel::{Receiver, Sender, bounded, unbounded};
 use rayon::{iter::ParallelBridge, prelude::ParallelIterator};
 use serde::{Deserialize, Serialize};
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::{
+    collections::HashMap,
+    ffi::OsStr,
     fs::{self, Metadata},
     io::{Error, ErrorKind},
     num::NonZeroU64,
-    os::unix::fs::MetadataExt,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
     path::{Path, PathBuf},
-    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
-    time::UNIX_EPOCH,
+    sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant, UNIX_EPOCH},
 };
 
-#[derive(Serialize, Debug)]
+/// Minimum time between two progress reports of a walk.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Events buffered between the walker and the tree builder of [`walk_it`].
+const WALK_CHANNEL_CAPACITY: usize = 4096;
+
+#[derive(Serialize, Deserialize, Debug)]
 pub struct Node {
-    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub children: Vec<Node>,
     pub name: Box<str>,
+    /// Original name bytes when the file name isn't valid UTF-8, `name` holds its lossy form then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_name: Option<Box<[u8]>>,
     pub metadata: Option<NodeMetadata>,
 }
 
+/// Split a file name into its (lossy) UTF-8 form and, only if the conversion lost
+/// information, the original bytes.
+pub fn split_file_name(name: &OsStr) -> (Box<str>, Option<Box<[u8]>>) {
+    match name.to_str() {
+        Some(name) => (name.into(), None),
+        None => (name.to_string_lossy().into(), Some(name.as_bytes().into())),
+    }
+}
+
+/// [`NodeMetadata::file_flags`] bit for `UF_HIDDEN`, set by `chflags hidden`.
+/// macOS mirrors Finder's invisible bit into it, so it covers both.
+pub const HIDDEN_FLAG: u8 = 1;
+
+/// `UF_HIDDEN` of `<sys/stat.h>`.
+#[cfg(target_os = "macos")]
+const UF_HIDDEN: u32 = 0x8000;
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub struct NodeMetadata {
     pub r#type: NodeFileType,
     pub size: u64,
+    /// Bytes allocated on disk (`st_blocks * 512`), less than `size` for sparse
+    /// and cloned files.
+    pub allocated: u64,
+    /// `st_ino`, shared by all hard links of a file on the same volume.
+    pub inode: u64,
+    /// `st_nlink`, more than one for hard-linked files.
+    pub nlink: u32,
     pub ctime: Option<NonZeroU64>,
     pub mtime: Option<NonZeroU64>,
+    /// The `st_flags` searches care about, [`HIDDEN_FLAG`]. Always 0 off macOS.
+    #[serde(default)]
+    pub file_flags: u8,
 }
 
 impl From<Metadata> for NodeMetadata {
@@ -37,6 +75,9 @@ impl NodeMetadata {
     fn new(metadata: &Metadata) -> Self {
         let r#type = metadata.file_type().into();
         let size = metadata.size();
+        let allocated = metadata.blocks().saturating_mul(512);
+        let inode = metadata.ino();
+        let nlink = u32::try_from(metadata.nlink()).unwrap_or(u32::MAX);
         let ctime = metadata
             .created()
             .ok()
@@ -47,11 +88,26 @@ impl NodeMetadata {
             .ok()
             .and_then(|x| x.duration_since(UNIX_EPOCH).ok())
             .and_then(|x| NonZeroU64::new(x.as_secs()));
+        #[cfg(target_os = "macos")]
+        let file_flags = {
+            use std::os::macos::fs::MetadataExt as _;
+            if metadata.st_flags() & UF_HIDDEN != 0 {
+                HIDDEN_FLAG
+            } else {
+                0
+            }
+        };
+        #[cfg(not(target_os = "macos"))]
+        let file_flags = 0;
         Self {
             r#type,
             size,
+            allocated,
+            inode,
+            nlink,
             ctime,
             mtime,
+            file_flags,
         }
     }
 }
@@ -80,15 +136,225 @@ impl From<fs::FileType> for NodeFileType {
     }
 }
 
+/// Snapshot of a running walk, see [`WalkData::with_progress`].
+#[derive(Debug, Clone)]
+pub struct WalkProgress {
+    pub files_seen: usize,
+    pub dirs_seen: usize,
+    /// The directory being entered when the report was made.
+    pub current_path: PathBuf,
+    pub elapsed: Duration,
+}
+
+type ProgressCallback<'w> = &'w (dyn Fn(WalkProgress) + Send + Sync);
+
+struct ProgressReporter<'w> {
+    callback: ProgressCallback<'w>,
+    started: Instant,
+    /// Milliseconds since `started` of the last report, `u64::MAX` before the first one.
+    last_report_ms: AtomicU64,
+}
+
+impl std::fmt::Debug for ProgressReporter<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressReporter")
+            .field("started", &self.started)
+            .field("last_report_ms", &self.last_report_ms)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Final counts of a walk, see [`WalkData::totals`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WalkTotals {
+    pub files: usize,
+    pub dirs: usize,
+    /// Sum of the sizes of the files whose metadata was fetched.
+    pub bytes: u64,
+    /// Files left out of `bytes` because their metadata wasn't fetched or couldn't be read.
+    pub unsized_files: usize,
+}
+
 #[derive(Debug)]
 pub struct WalkData<'w> {
     pub num_files: AtomicUsize,
     pub num_dirs: AtomicUsize,
+    /// Total size of the files counted in `num_files`, as far as their metadata was fetched.
+    pub num_bytes: AtomicU64,
+    /// Files counted in `num_files` but not in `num_bytes`.
+    pub num_unsized: AtomicUsize,
     /// Cancellation will be checked periodically.
     cancel: Option<&'w AtomicBool>,
+    /// See [`WalkDataBuilder::also_cancel`].
+    also_cancel: Option<&'w AtomicBool>,
     ignore_directories: Option<Vec<PathBuf>>,
     /// If set, metadata will be collected for each file node(folder node will get free metadata).
     need_metadata: bool,
+    progress: Option<ProgressReporter<'w>>,
+    /// See [`WalkDataBuilder::sort_children`].
+    sort_children: bool,
+    /// See [`WalkDataBuilder::max_depth`].
+    max_depth: Option<usize>,
+    symlinks: SymlinkPolicy,
+    /// See [`WalkDataBuilder::count_bytes`].
+    count_bytes: bool,
+    /// See [`WalkDataBuilder::max_entries`].
+    max_entries: Option<usize>,
+    /// Entries below the root taken so far, only counted with `max_entries`.
+    num_entries: AtomicUsize,
+    truncated: AtomicBool,
+}
+
+/// What a walk does with symbolic links below its root. They are never followed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Report them as [`NodeFileType::Symlink`] entries.
+    #[default]
+    Keep,
+    /// Leave them out of the walk.
+    Skip,
+}
+
+/// Why [`WalkDataBuilder::build`] refused a combination of options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WalkDataError {
+    /// `max_entries(0)` would leave nothing but the root.
+    ZeroMaxEntries,
+}
+
+impl std::fmt::Display for WalkDataError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ZeroMaxEntries => f.write_str("max_entries must be at least 1"),
+        }
+    }
+}
+
+impl std::error::Error for WalkDataError {}
+
+/// Options of a walk, see [`WalkData::builder`]. The defaults are those of
+/// [`WalkData::simple`]`(false)`.
+pub struct WalkDataBuilder<'w> {
+    ignore_directories: Option<Vec<PathBuf>>,
+    need_metadata: bool,
+    cancel: Option<&'w AtomicBool>,
+    also_cancel: Option<&'w AtomicBool>,
+    progress: Option<ProgressCallback<'w>>,
+    sort_children: bool,
+    max_depth: Option<usize>,
+    symlinks: SymlinkPolicy,
+    count_bytes: bool,
+    max_entries: Option<usize>,
+}
+
+impl<'w> WalkDataBuilder<'w> {
+    /// Directories to leave out, compared with the walked paths as they are.
+    pub fn ignore_directories(mut self, directories: Option<Vec<PathBuf>>) -> Self {
+        self.ignore_directories = directories;
+        self
+    }
+
+    /// Add one directory to leave out.
+    pub fn ignore_directory(mut self, directory: PathBuf) -> Self {
+        self.ignore_directories
+            .get_or_insert_with(Vec::new)
+            .push(directory);
+        self
+    }
+
+    /// Stat every file. Folders get their metadata either way.
+    pub fn need_metadata(mut self, need_metadata: bool) -> Self {
+        self.need_metadata = need_metadata;
+        self
+    }
+
+    /// Stop the walk once `cancel` is set, checked periodically.
+    pub fn cancel(mut self, cancel: Option<&'w AtomicBool>) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// A second flag stopping the walk like [`Self::cancel`], e.g. one of this
+    /// walk alone next to one shared by every walk.
+    pub fn also_cancel(mut self, cancel: Option<&'w AtomicBool>) -> Self {
+        self.also_cancel = cancel;
+        self
+    }
+
+    /// See [`WalkData::with_progress`].
+    pub fn progress(mut self, callback: ProgressCallback<'w>) -> Self {
+        self.progress = Some(callback);
+        self
+    }
+
+    /// Sort the children of every [`Node`] of [`walk_it`] by name, on by
+    /// default. [`walk_stream`] reports entries as they are read regardless.
+    pub fn sort_children(mut self, sort_children: bool) -> Self {
+        self.sort_children = sort_children;
+        self
+    }
+
+    /// Levels below the root to report: 0 is the root alone, 1 its entries
+    /// without reading the folders among them, and so on. Unlimited by default.
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// See [`SymlinkPolicy`], kept by default.
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Sum up file sizes into [`WalkTotals::bytes`] and
+    /// [`WalkTotals::unsized_files`], on by default. Both stay 0 when off.
+    pub fn count_bytes(mut self, count_bytes: bool) -> Self {
+        self.count_bytes = count_bytes;
+        self
+    }
+
+    /// Report at most this many entries below the root; the walk still
+    /// finishes and [`WalkData::truncated`] tells whether some were left out.
+    /// Which ones is up to the parallel walk.
+    pub fn max_entries(mut self, max_entries: Option<usize>) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    pub fn build(self) -> Result<WalkData<'w>, WalkDataError> {
+        if self.max_entries == Some(0) {
+            return Err(WalkDataError::ZeroMaxEntries);
+        }
+        let Self {
+            ignore_directories,
+            need_metadata,
+            cancel,
+            also_cancel,
+            progress,
+            sort_children,
+            max_depth,
+            symlinks,
+            count_bytes,
+            max_entries,
+        } = self;
+        let walk_data = WalkData {
+            cancel,
+            also_cancel,
+            ignore_directories,
+            sort_children,
+            max_depth,
+            symlinks,
+            count_bytes,
+            max_entries,
+            ..WalkData::simple(need_metadata)
+        };
+        Ok(match progress {
+            Some(callback) => walk_data.with_progress(callback),
+            None => walk_data,
+        })
+    }
 }
 
 impl<'w> WalkData<'w> {
@@ -96,23 +362,120 @@ impl<'w> WalkData<'w> {
         Self {
             num_files: AtomicUsize::new(0),
             num_dirs: AtomicUsize::new(0),
+            num_bytes: AtomicU64::new(0),
+            num_unsized: AtomicUsize::new(0),
             cancel: None,
+            also_cancel: None,
             ignore_directories: None,
             need_metadata,
+            progress: None,
+            sort_children: true,
+            max_depth: None,
+            symlinks: SymlinkPolicy::Keep,
+            count_bytes: true,
+            max_entries: None,
+            num_entries: AtomicUsize::new(0),
+            truncated: AtomicBool::new(false),
+        }
+    }
+
+    pub fn builder() -> WalkDataBuilder<'w> {
+        WalkDataBuilder {
+            ignore_directories: None,
+            need_metadata: false,
+            cancel: None,
+            also_cancel: None,
+            progress: None,
+            sort_children: true,
+            max_depth: None,
+            symlinks: SymlinkPolicy::Keep,
+            count_bytes: true,
+            max_entries: None,
         }
     }
 
+    #[deprecated(note = "use `WalkData::builder()`")]
     pub fn new(
         ignore_directories: Option<Vec<PathBuf>>,
         need_metadata: bool,
         cancel: Option<&'w AtomicBool>,
     ) -> Self {
         Self {
-            num_files: AtomicUsize::new(0),
-            num_dirs: AtomicUsize::new(0),
             cancel,
             ignore_directories,
-            need_metadata,
+            ..Self::simple(need_metadata)
+        }
+    }
+
+    /// Report progress to `callback` while walking, at most once per [`PROGRESS_INTERVAL`]
+    /// plus once when the walk finishes.
+    pub fn with_progress(mut self, callback: ProgressCallback<'w>) -> Self {
+        self.progress = Some(ProgressReporter {
+            callback,
+            started: Instant::now(),
+            last_report_ms: AtomicU64::new(u64::MAX),
+        });
+        self
+    }
+
+    fn report_progress(&self, current_path: &Path, force: bool) {
+        let Some(progress) = &self.progress else {
+            return;
+        };
+        let elapsed = progress.started.elapsed();
+        let now_ms = elapsed.as_millis() as u64;
+        if force {
+            progress.last_report_ms.store(now_ms, Ordering::Relaxed);
+        } else {
+            let last_ms = progress.last_report_ms.load(Ordering::Relaxed);
+            let due = last_ms == u64::MAX
+                || now_ms.saturating_sub(last_ms) >= PROGRESS_INTERVAL.as_millis() as u64;
+            // Only the worker winning the race reports, the others carry on walking.
+            if !due
+                || progress
+                    .last_report_ms
+                    .compare_exchange(last_ms, now_ms, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_err()
+            {
+                return;
+            }
+        }
+        (progress.callback)(WalkProgress {
+            files_seen: self.num_files.load(Ordering::Relaxed),
+            dirs_seen: self.num_dirs.load(Ordering::Relaxed),
+            current_path: current_path.to_path_buf(),
+            elapsed,
+        });
+    }
+
+    pub fn totals(&self) -> WalkTotals {
+        WalkTotals {
+            files: self.num_files.load(Ordering::Relaxed),
+            dirs: self.num_dirs.load(Ordering::Relaxed),
+            bytes: self.num_bytes.load(Ordering::Relaxed),
+            unsized_files: self.num_unsized.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Some entries were left out because of [`WalkDataBuilder::max_entries`].
+    pub fn truncated(&self) -> bool {
+        self.truncated.load(Ordering::Relaxed)
+    }
+
+    /// Count a file, and its size if its metadata was fetched. No stat is
+    /// made here, files without metadata are only counted as unsized.
+    fn count_file(&self, metadata: Option<&NodeMetadata>) {
+        self.num_files.fetch_add(1, Ordering::Relaxed);
+        if !self.count_bytes {
+            return;
+        }
+        match metadata {
+            Some(metadata) => {
+                self.num_bytes.fetch_add(metadata.size, Ordering::Relaxed);
+            }
+            None => {
+                self.num_unsized.fetch_add(1, Ordering::Relaxed);
+            }
         }
     }
 
@@ -122,115 +485,304 @@ impl<'w> WalkData<'w> {
             .map(|paths| paths.iter().any(|ignore| ignore == path))
             .unwrap_or(false)
     }
+
+    /// Whether one more entry below the root may be reported.
+    fn take_entry(&self) -> bool {
+        let Some(max_entries) = self.max_entries else {
+            return true;
+        };
+        if self.num_entries.fetch_add(1, Ordering::Relaxed) < max_entries {
+            return true;
+        }
+        self.truncated.store(true, Ordering::Relaxed);
+        false
+    }
+
+    fn is_cancelled(&self) -> bool {
+        [self.cancel, self.also_cancel]
+            .into_iter()
+            .flatten()
+            .any(|x| x.load(Ordering::Relaxed))
+    }
+}
+
+/// Identifies a directory within one [`walk_stream`].
+pub type DirId = u64;
+
+/// One step of a [`walk_stream`].
+///
+/// Directories are walked in parallel, so events of different directories interleave. Within
+/// one directory, `EnterDir` comes before the events of its children and `LeaveDir` after them.
+#[derive(Debug)]
+pub enum WalkEvent {
+    /// `parent` is `None` only for the walk root, which is reported this way even if it
+    /// isn't a directory.
+    EnterDir {
+        id: DirId,
+        parent: Option<DirId>,
+        path: PathBuf,
+        metadata: Option<NodeMetadata>,
+    },
+    File {
+        parent: DirId,
+        name: Box<str>,
+        raw_name: Option<Box<[u8]>>,
+        metadata: Option<NodeMetadata>,
+    },
+    /// Everything below directory `id` has been reported.
+    LeaveDir { id: DirId },
+    /// `path` couldn't be read, the walk carries on without it.
+    Error { path: PathBuf, error: Error },
 }
 
 pub fn walk_it(dir: &Path, walk_data: &WalkData) -> Option<Node> {
-    walk(dir, walk_data)
+    let sort_children = walk_data.sort_children;
+    let metadata = stat_entry(dir, walk_data)?;
+    let is_dir = metadata.as_ref().is_some_and(Metadata::is_dir);
+    if !is_dir || walk_data.max_depth == Some(0) {
+        // A single entry yields two events, not worth a builder thread.
+        let (tx, rx) = unbounded();
+        walk_stream_from(dir, metadata, walk_data, tx);
+        return build_tree(rx, sort_children);
+    }
+    let (tx, rx) = bounded(WALK_CHANNEL_CAPACITY);
+    std::thread::scope(|s| {
+        let builder = s.spawn(move || build_tree(rx, sort_children));
+        walk_stream_from(dir, metadata, walk_data, tx);
+        builder.join().expect("walk tree builder panicked")
+    })
+}
+
+/// Walk `dir`, sending what is found to `tx` instead of building a [`Node`] tree.
+///
+/// The walk holds no more than the directories being read, so with a bounded channel its
+/// memory use doesn't grow with the number of entries. It stops early on cancellation or
+/// once the receiver is dropped; the root's `LeaveDir` is only sent if it ran to completion.
+pub fn walk_stream(dir: &Path, walk_data: &WalkData, tx: Sender<WalkEvent>) {
+    if let Some(metadata) = stat_entry(dir, walk_data) {
+        walk_stream_from(dir, metadata, walk_data, tx);
+    }
+}
+
+fn walk_stream_from(
+    dir: &Path,
+    metadata: Option<Metadata>,
+    walk_data: &WalkData,
+    tx: Sender<WalkEvent>,
+) {
+    let walker = StreamWalker {
+        walk_data,
+        tx,
+        next_id: AtomicU64::new(0),
+    };
+    if walker.walk_entry(dir, metadata, None, 0) {
+        walk_data.report_progress(dir, true);
+    }
 }
 
-fn walk(path: &Path, walk_data: &WalkData) -> Option<Node> {
+/// Metadata of an entry the walk should report, `None` if it is ignored or gone.
+///
+/// The inner `None` is an entry that exists but couldn't be read.
+fn stat_entry(path: &Path, walk_data: &WalkData) -> Option<Option<Metadata>> {
     if walk_data.should_ignore(path) {
         return None;
     }
     // doesn't traverse symlink
-    let metadata = match path.symlink_metadata() {
-        Ok(metadata) => Some(metadata),
+    match path.symlink_metadata() {
+        Ok(metadata) => Some(Some(metadata)),
         // If it's not found, we definitely don't want it.
-        Err(e) if e.kind() == ErrorKind::NotFound => return None,
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
         // If it's permission denied or something, we still want to insert it into the tree.
         Err(e) => {
             if handle_error_and_retry(&e) {
                 // doesn't traverse symlink
-                path.symlink_metadata().ok()
+                Some(path.symlink_metadata().ok())
             } else {
-                None
+                Some(None)
             }
         }
-    };
-    let children = if metadata.as_ref().map(|x| x.is_dir()).unwrap_or_default() {
-        walk_data.num_dirs.fetch_add(1, Ordering::Relaxed);
-        let read_dir = fs::read_dir(path);
-        match read_dir {
-            Ok(entries) => entries
-                .into_iter()
-                .par_bridge()
-                .filter_map(|entry| {
-                    match &entry {
-                        Ok(entry) => {
-                            if walk_data
-                                .cancel
-                                .map(|x| x.load(Ordering::Relaxed))
-                                .unwrap_or_default()
-                            {
-                                return None;
-                            }
-                            if walk_data.should_ignore(path) {
-                                return None;
-                            }
-                            // doesn't traverse symlink
-                            if let Ok(data) = entry.file_type() {
-                                if data.is_dir() {
-                                    return walk(&entry.path(), walk_data);
-                                } else {
-                                    walk_data.num_files.fetch_add(1, Ordering::Relaxed);
-                                    let name = entry
-                                        .file_name()
-                                        .to_string_lossy()
-                                        .into_owned()
-                                        .into_boxed_str();
-                                    return Some(Node {
-                                        children: vec![],
-                                        name,
-                                        metadata: walk_data
-                                            .need_metadata
-                                            .then_some(entry)
-                                            .and_then(|entry| {
-                                                // doesn't traverse symlink
-                                                entry.metadata().ok().map(NodeMetadata::from)
-                                            }),
-                                    });
-                                }
-                            }
-                        }
-                        Err(failed) => {
-                            if handle_error_and_retry(failed) {
-                                return walk(path, walk_data);
-                            }
+    }
+}
+
+struct StreamWalker<'a, 'w> {
+    walk_data: &'a WalkData<'w>,
+    tx: Sender<WalkEvent>,
+    next_id: AtomicU64,
+}
+
+impl StreamWalker<'_, '_> {
+    /// Returns false if the walk should stop.
+    fn send(&self, event: WalkEvent) -> bool {
+        self.tx.send(event).is_ok()
+    }
+
+    /// `depth` is the number of levels `path` is below the walk root.
+    fn walk(&self, path: &Path, parent: Option<DirId>, depth: usize) -> bool {
+        match stat_entry(path, self.walk_data) {
+            Some(metadata) => self.walk_entry(path, metadata, parent, depth),
+            None => true,
+        }
+    }
+
+    fn walk_entry(
+        &self,
+        path: &Path,
+        metadata: Option<Metadata>,
+        parent: Option<DirId>,
+        depth: usize,
+    ) -> bool {
+        let walk_data = self.walk_data;
+        let is_dir = metadata.as_ref().map(|x| x.is_dir()).unwrap_or_default();
+        let metadata = metadata.map(NodeMetadata::from);
+        if !is_dir {
+            walk_data.count_file(metadata.as_ref());
+            if let Some(parent) = parent {
+                let (name, raw_name) = path.file_name().map(split_file_name).unwrap_or_default();
+                return self.send(WalkEvent::File {
+                    parent,
+                    name,
+                    raw_name,
+                    metadata,
+                });
+            }
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if !self.send(WalkEvent::EnterDir {
+            id,
+            parent,
+            path: path.to_path_buf(),
+            metadata,
+        }) {
+            return false;
+        }
+        if is_dir {
+            walk_data.num_dirs.fetch_add(1, Ordering::Relaxed);
+            walk_data.report_progress(path, false);
+            let read_children = walk_data.max_depth.is_none_or(|max| depth < max);
+            if read_children && !self.walk_children(path, id, depth + 1) {
+                return false;
+            }
+        }
+        !walk_data.is_cancelled() && self.send(WalkEvent::LeaveDir { id })
+    }
+
+    /// `depth` is the level of the children.
+    fn walk_children(&self, path: &Path, id: DirId, depth: usize) -> bool {
+        let walk_data = self.walk_data;
+        let entries = loop {
+            match fs::read_dir(path) {
+                Ok(entries) => break entries,
+                Err(failed) if handle_error_and_retry(&failed) => continue,
+                Err(error) => {
+                    return self.send(WalkEvent::Error {
+                        path: path.to_path_buf(),
+                        error,
+                    });
+                }
+            }
+        };
+        entries.par_bridge().all(|entry| {
+            if walk_data.is_cancelled() {
+                return false;
+            }
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(error) => {
+                    return self.send(WalkEvent::Error {
+                        path: path.to_path_buf(),
+                        error,
+                    });
+                }
+            };
+            // doesn't traverse symlink
+            let Ok(file_type) = entry.file_type() else {
+                return true;
+            };
+            if file_type.is_symlink() && walk_data.symlinks == SymlinkPolicy::Skip {
+                return true;
+            }
+            if !walk_data.take_entry() {
+                return true;
+            }
+            if file_type.is_dir() {
+                return self.walk(&entry.path(), Some(id), depth);
+            }
+            let (name, raw_name) = split_file_name(&entry.file_name());
+            let metadata = walk_data.need_metadata.then_some(&entry).and_then(|entry| {
+                // doesn't traverse symlink
+                entry.metadata().ok().map(NodeMetadata::from)
+            });
+            walk_data.count_file(metadata.as_ref());
+            self.send(WalkEvent::File {
+                parent: id,
+                name,
+                raw_name,
+                metadata,
+            })
+        })
+    }
+}
+
+/// Assemble the events of a [`walk_stream`] into its tree, `None` if the walk didn't finish.
+fn build_tree(rx: Receiver<WalkEvent>, sort_children: bool) -> Option<Node> {
+    struct OpenDir {
+        parent: Option<DirId>,
+        node: Node,
+    }
+
+    let mut open: HashMap<DirId, OpenDir> = HashMap::new();
+    for event in rx {
+        match event {
+            WalkEvent::EnterDir {
+                id,
+                parent,
+                path,
+                metadata,
+            } => {
+                let (name, raw_name) = path.file_name().map(split_file_name).unwrap_or_default();
+                let node = Node {
+                    children: vec![],
+                    name,
+                    raw_name,
+                    metadata,
+                };
+                open.insert(id, OpenDir { parent, node });
+            }
+            WalkEvent::File {
+                parent,
+                name,
+                raw_name,
+                metadata,
+            } => {
+                if let Some(dir) = open.get_mut(&parent) {
+                    dir.node.children.push(Node {
+                        children: vec![],
+                        name,
+                        raw_name,
+                        metadata,
+                    });
+                }
+            }
+            WalkEvent::LeaveDir { id } => {
+                let Some(OpenDir { parent, mut node }) = open.remove(&id) else {
+                    continue;
+                };
+                if sort_children {
+                    node.children.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+                }
+                match parent {
+                    Some(parent) => {
+                        if let Some(dir) = open.get_mut(&parent) {
+                            dir.node.children.push(node);
                         }
                     }
-                    None
-                })
-                .collect(),
-            Err(failed) => {
-                if handle_error_and_retry(&failed) {
-                    return walk(path, walk_data);
-                } else {
-                    vec![]
+                    None => return Some(node),
                 }
             }
+            WalkEvent::Error { .. } => {}
         }
-    } else {
-        walk_data.num_files.fetch_add(1, Ordering::Relaxed);
-        vec![]
-    };
-    if walk_data
-        .cancel
-        .map(|x| x.load(Ordering::Relaxed))
-        .unwrap_or_default()
-    {
-        return None;
     }
-    let name = path
-        .file_name()
-        .map(|x| x.to_string_lossy().into_owned().into_boxed_str())
-        .unwrap_or_default();
-    let mut children = children;
-    children.sort_unstable_by(|a, b| a.name.cmp(&b.name));
-    Some(Node {
-        children,
-        name,
-        metadata: metadata.map(NodeMetadata::from),
-    })
+    None
 }
 
 fn handle_error_and_retry(failed: &Error) -> bool {
@@ -414,11 +966,12 @@ mod tests {
     #[ignore]
     fn test_search_root() {
         let done = AtomicBool::new(false);
-        let walk_data = WalkData::new(
-            Some(vec![PathBuf::from("/System/Volumes/Data")]),
-            false,
-            None,
-        );
+        let walk_data = WalkData::builder()
+            .ignore_directory(PathBuf::from("/System/Volumes/Data"))
+            .need_metadata(false)
+            .cancel(None)
+            .build()
+            .unwrap();
         std::thread::scope(|s| {
             s.spawn(|| {
                 let node = walk_it(Path::new("/"), &walk_data).unwrap();
@@ -440,11 +993,12 @@ mod tests {
     #[ignore]
     fn test_search_simulator() {
         let done = AtomicBool::new(false);
-        let walk_data = WalkData::new(
-            Some(vec![PathBuf::from("/System/Volumes/Data")]),
-            true,
-            None,
-        );
+        let walk_data = WalkData::builder()
+            .ignore_directory(PathBuf::from("/System/Volumes/Data"))
+            .need_metadata(true)
+            .cancel(None)
+            .build()
+            .unwrap();
         std::thread::scope(|s| {
             s.spawn(|| {
                 let node = walk_it(
@@ -466,15 +1020,72 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_walk_keeps_raw_non_utf8_names() {
+        let tmp = TempDir::new("fswalk_non_utf8").unwrap();
+        let raw: &[u8] = b"caf\xe9.txt";
+        let name = OsStr::from_bytes(raw);
+        // APFS rejects names that aren't valid UTF-8, nothing to test there.
+        if fs::File::create(tmp.path().join(name)).is_err() {
+            return;
+        }
+        fs::File::create(tmp.path().join("plain.txt")).unwrap();
+        let node = walk_it(tmp.path(), &WalkData::simple(false)).unwrap();
+        let non_utf8 = node
+            .children
+            .iter()
+            .find(|c| c.raw_name.is_some())
+            .expect("non-UTF-8 child should keep its raw name");
+        assert_eq!(non_utf8.raw_name.as_deref(), Some(raw));
+        assert_eq!(&*non_utf8.name, "caf\u{FFFD}.txt");
+        let plain = node
+            .children
+            .iter()
+            .find(|c| &*c.name == "plain.txt")
+            .unwrap();
+        assert!(plain.raw_name.is_none());
+    }
+
+    #[test]
+    fn test_split_file_name() {
+        assert_eq!(split_file_name(OsStr::new("a.txt")), ("a.txt".into(), None));
+        let (name, raw) = split_file_name(OsStr::from_bytes(b"\xff"));
+        assert_eq!(&*name, "\u{FFFD}");
+        assert_eq!(raw.as_deref(), Some(&b"\xff"[..]));
+    }
+
+    #[test]
+    fn test_either_cancel_flag_stops_the_walk() {
+        let tmp = TempDir::new("fswalk_also_cancel").unwrap();
+        fs::create_dir_all(tmp.path().join("a/b")).unwrap();
+        let shared = AtomicBool::new(false);
+        let own = AtomicBool::new(false);
+        let walk = || {
+            let walk_data = WalkData::builder()
+                .cancel(Some(&shared))
+                .also_cancel(Some(&own))
+                .build()
+                .unwrap();
+            walk_it(tmp.path(), &walk_data)
+        };
+        assert!(walk().is_some());
+        own.store(true, Ordering::Relaxed);
+        assert!(walk().is_none());
+        own.store(false, Ordering::Relaxed);
+        shared.store(true, Ordering::Relaxed);
+        assert!(walk().is_none());
+    }
+
     #[test]
     fn test_search_cancel() {
         let cancel = AtomicBool::new(false);
         let done = AtomicBool::new(false);
-        let walk_data = WalkData::new(
-            Some(vec![PathBuf::from("/System/Volumes/Data")]),
-            false,
-            Some(&cancel),
-        );
+        let walk_data = WalkData::builder()
+            .ignore_directory(PathBuf::from("/System/Volumes/Data"))
+            .need_metadata(false)
+            .cancel(Some(&cancel))
+            .build()
+            .unwrap();
         std::thread::scope(|s| {
             s.spawn(|| {
                 let node = walk_it(Path::new("/"), &walk_data);
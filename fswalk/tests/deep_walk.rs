@@ -28,7 +28,11 @@ fn ignores_directories_and_collects_metadata() {
     let tmp = TempDir::new("fswalk_deep").unwrap();
     build_deep_fixture(tmp.path());
     let ignore = vec![tmp.path().join("skip_dir")];
-    let walk_data = WalkData::new(Some(ignore), true, None);
+    let walk_data = WalkData::builder()
+        .ignore_directories(Some(ignore))
+        .need_metadata(true)
+        .build()
+        .unwrap();
     let tree = walk_it(tmp.path(), &walk_data).expect("root node");
 
     // Ensure skip_dir absent
@@ -71,7 +75,7 @@ fn cancellation_stops_traversal_early() {
         fs::create_dir(tmp.path().join(format!("dir_{i}"))).unwrap();
     }
     let cancel = AtomicBool::new(false);
-    let walk_data = WalkData::new(None, false, Some(&cancel));
+    let walk_data = WalkData::builder().cancel(Some(&cancel)).build().unwrap();
     cancel.store(true, Ordering::Relaxed); // cancel immediately
     let node = walk_it(tmp.path(), &walk_data);
     assert!(
@@ -79,3 +83,29 @@ fn cancellation_stops_traversal_early() {
         "expected immediate cancellation to abort traversal"
     );
 }
+
+#[test]
+fn progress_reports_are_rate_limited_and_end_with_final_counts() {
+    let tmp = TempDir::new("fswalk_progress").unwrap();
+    for i in 0..50 {
+        let dir = tmp.path().join(format!("dir{i}"));
+        fs::create_dir(&dir).unwrap();
+        for j in 0..5 {
+            fs::write(dir.join(format!("file{j}.txt")), b"x").unwrap();
+        }
+    }
+    let reports = std::sync::Mutex::new(Vec::new());
+    let callback = |progress: fswalk::WalkProgress| reports.lock().unwrap().push(progress);
+    let walk_data = WalkData::simple(false).with_progress(&callback);
+    walk_it(tmp.path(), &walk_data).expect("root node");
+
+    let reports = reports.into_inner().unwrap();
+    // The first directory reports right away, then the walk is far below 100ms per report.
+    assert!(!reports.is_empty());
+    assert!(reports.len() < 51 + 1);
+    let last = reports.last().unwrap();
+    assert_eq!(last.dirs_seen, 51);
+    assert_eq!(last.files_seen, 250);
+    assert_eq!(last.current_path, tmp.path());
+    assert!(reports.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+}
@@ -0,0 +1,248 @@
+use fswalk::{Node, NodeFileType, SymlinkPolicy, WalkData, WalkDataError, walk_it};
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use tempdir::TempDir;
+
+fn build_fixture(root: &Path) {
+    // /root
+    //   a.txt
+    //   /b
+    //      c.txt
+    //      /d
+    //         e.txt
+    //   link -> a.txt
+    fs::create_dir_all(root.join("b/d")).unwrap();
+    fs::write(root.join("a.txt"), b"aaa").unwrap();
+    fs::write(root.join("b/c.txt"), b"cc").unwrap();
+    fs::write(root.join("b/d/e.txt"), b"e").unwrap();
+    std::os::unix::fs::symlink(root.join("a.txt"), root.join("link")).unwrap();
+}
+
+fn walk(walk_data: &WalkData) -> (TempDir, Node) {
+    let tmp = TempDir::new("fswalk_options").unwrap();
+    build_fixture(tmp.path());
+    let node = walk_it(tmp.path(), walk_data).expect("root node");
+    (tmp, node)
+}
+
+fn names(node: &Node) -> Vec<&str> {
+    node.children.iter().map(|child| &*child.name).collect()
+}
+
+fn child<'n>(node: &'n Node, name: &str) -> &'n Node {
+    node.children
+        .iter()
+        .find(|child| &*child.name == name)
+        .unwrap_or_else(|| panic!("no {name} in {:?}", names(node)))
+}
+
+fn count_entries(node: &Node) -> usize {
+    node.children
+        .iter()
+        .map(|child| 1 + count_entries(child))
+        .sum()
+}
+
+#[test]
+fn defaults_match_simple() {
+    let walk_data = WalkData::builder().build().unwrap();
+    let (_tmp, node) = walk(&walk_data);
+    assert_eq!(names(&node), ["a.txt", "b", "link"]);
+    assert_eq!(names(child(&node, "b")), ["c.txt", "d"]);
+    assert!(child(&node, "a.txt").metadata.is_none());
+    assert!(!walk_data.truncated());
+    let totals = walk_data.totals();
+    assert_eq!((totals.files, totals.dirs), (4, 3));
+}
+
+#[test]
+fn ignore_directories_leave_them_out() {
+    let tmp = TempDir::new("fswalk_options").unwrap();
+    build_fixture(tmp.path());
+    let walk_data = WalkData::builder()
+        .ignore_directory(tmp.path().join("b/d"))
+        .build()
+        .unwrap();
+    let node = walk_it(tmp.path(), &walk_data).unwrap();
+    assert_eq!(names(child(&node, "b")), ["c.txt"]);
+
+    let walk_data = WalkData::builder()
+        .ignore_directories(Some(vec![tmp.path().join("b")]))
+        .build()
+        .unwrap();
+    let node = walk_it(tmp.path(), &walk_data).unwrap();
+    assert_eq!(names(&node), ["a.txt", "link"]);
+}
+
+#[test]
+fn need_metadata_stats_files() {
+    let walk_data = WalkData::builder().need_metadata(true).build().unwrap();
+    let (_tmp, node) = walk(&walk_data);
+    assert_eq!(child(&node, "a.txt").metadata.unwrap().size, 3);
+    assert_eq!(
+        child(&node, "link").metadata.unwrap().r#type,
+        NodeFileType::Symlink
+    );
+    // The link's own size is the length of its target path.
+    let totals = walk_data.totals();
+    assert!(totals.bytes > 3 + 2 + 1);
+    assert_eq!(totals.unsized_files, 0);
+}
+
+#[test]
+fn cancel_and_progress_are_wired_up() {
+    let cancel = AtomicBool::new(true);
+    let tmp = TempDir::new("fswalk_options").unwrap();
+    build_fixture(tmp.path());
+    let walk_data = WalkData::builder().cancel(Some(&cancel)).build().unwrap();
+    assert!(walk_it(tmp.path(), &walk_data).is_none());
+
+    cancel.store(false, Ordering::Relaxed);
+    let reports = Mutex::new(Vec::new());
+    let callback = |progress: fswalk::WalkProgress| reports.lock().unwrap().push(progress);
+    let walk_data = WalkData::builder()
+        .cancel(Some(&cancel))
+        .progress(&callback)
+        .build()
+        .unwrap();
+    assert!(walk_it(tmp.path(), &walk_data).is_some());
+    let reports = reports.into_inner().unwrap();
+    assert_eq!(reports.last().unwrap().files_seen, 4);
+}
+
+#[test]
+fn sort_children_can_be_turned_off() {
+    let tmp = TempDir::new("fswalk_options").unwrap();
+    for i in 0..50 {
+        fs::write(tmp.path().join(format!("{i:02}")), b"").unwrap();
+    }
+    let sorted = WalkData::builder().sort_children(true).build().unwrap();
+    let node = walk_it(tmp.path(), &sorted).unwrap();
+    assert!(names(&node).is_sorted());
+
+    let unsorted = WalkData::builder().sort_children(false).build().unwrap();
+    let node = walk_it(tmp.path(), &unsorted).unwrap();
+    let mut names = names(&node);
+    assert_eq!(names.len(), 50);
+    names.sort_unstable();
+    assert_eq!(names.first(), Some(&"00"));
+    assert_eq!(names.last(), Some(&"49"));
+}
+
+#[test]
+fn single_entry_roots() {
+    let tmp = TempDir::new("fswalk_options").unwrap();
+    build_fixture(tmp.path());
+    let walk_data = WalkData::builder().build().unwrap();
+    let file = walk_it(&tmp.path().join("a.txt"), &walk_data).expect("file node");
+    assert_eq!(&*file.name, "a.txt");
+    assert!(file.children.is_empty());
+    let totals = walk_data.totals();
+    assert_eq!((totals.files, totals.dirs), (1, 0));
+
+    assert!(walk_it(&tmp.path().join("missing"), &walk_data).is_none());
+
+    let cancel = AtomicBool::new(true);
+    let cancelled = WalkData::builder().cancel(Some(&cancel)).build().unwrap();
+    assert!(walk_it(&tmp.path().join("a.txt"), &cancelled).is_none());
+}
+
+#[test]
+fn max_depth_limits_the_levels_reported() {
+    let depth = |max_depth| {
+        let walk_data = WalkData::builder().max_depth(max_depth).build().unwrap();
+        let (_tmp, node) = walk(&walk_data);
+        (node, walk_data.totals())
+    };
+
+    let (root_only, totals) = depth(Some(0));
+    assert!(root_only.children.is_empty());
+    assert_eq!((totals.files, totals.dirs), (0, 1));
+
+    let (top, totals) = depth(Some(1));
+    assert_eq!(names(&top), ["a.txt", "b", "link"]);
+    assert!(child(&top, "b").children.is_empty());
+    assert_eq!((totals.files, totals.dirs), (2, 2));
+
+    let (two, _) = depth(Some(2));
+    assert_eq!(names(child(&two, "b")), ["c.txt", "d"]);
+    assert!(child(child(&two, "b"), "d").children.is_empty());
+
+    let (all, _) = depth(None);
+    assert_eq!(names(child(child(&all, "b"), "d")), ["e.txt"]);
+}
+
+#[test]
+fn symlinks_can_be_skipped() {
+    let walk_data = WalkData::builder()
+        .symlinks(SymlinkPolicy::Skip)
+        .build()
+        .unwrap();
+    let (_tmp, node) = walk(&walk_data);
+    assert_eq!(names(&node), ["a.txt", "b"]);
+    assert_eq!(walk_data.totals().files, 3);
+
+    let walk_data = WalkData::builder()
+        .symlinks(SymlinkPolicy::Keep)
+        .build()
+        .unwrap();
+    let (_tmp, node) = walk(&walk_data);
+    assert_eq!(names(&node), ["a.txt", "b", "link"]);
+}
+
+#[test]
+fn count_bytes_can_be_turned_off() {
+    let walk_data = WalkData::builder()
+        .need_metadata(true)
+        .count_bytes(false)
+        .build()
+        .unwrap();
+    let (_tmp, _node) = walk(&walk_data);
+    let totals = walk_data.totals();
+    assert_eq!(totals.files, 4);
+    assert_eq!((totals.bytes, totals.unsized_files), (0, 0));
+
+    let walk_data = WalkData::builder().count_bytes(true).build().unwrap();
+    let (_tmp, _node) = walk(&walk_data);
+    assert_eq!(walk_data.totals().unsized_files, 4);
+}
+
+#[test]
+fn max_entries_truncates_the_walk() {
+    let walk_data = WalkData::builder().max_entries(Some(2)).build().unwrap();
+    let (_tmp, node) = walk(&walk_data);
+    assert_eq!(count_entries(&node), 2);
+    assert!(walk_data.truncated());
+
+    let walk_data = WalkData::builder().max_entries(Some(6)).build().unwrap();
+    let (_tmp, node) = walk(&walk_data);
+    assert_eq!(count_entries(&node), 6);
+    assert!(!walk_data.truncated());
+}
+
+#[test]
+fn invalid_options_fail_to_build() {
+    let error = WalkData::builder()
+        .max_entries(Some(0))
+        .build()
+        .unwrap_err();
+    assert_eq!(error, WalkDataError::ZeroMaxEntries);
+    assert_eq!(error.to_string(), "max_entries must be at least 1");
+}
+
+#[test]
+#[allow(deprecated)]
+fn deprecated_constructor_still_works() {
+    let tmp = TempDir::new("fswalk_options").unwrap();
+    build_fixture(tmp.path());
+    let walk_data = WalkData::new(Some(vec![tmp.path().join("b")]), true, None);
+    let node = walk_it(tmp.path(), &walk_data).unwrap();
+    assert_eq!(names(&node), ["a.txt", "link"]);
+    assert!(child(&node, "a.txt").metadata.is_some());
+}
@@ -0,0 +1,247 @@
+use crossbeam_channel::unbounded;
+use fswalk::{DirId, Node, WalkData, WalkEvent, WalkTotals, split_file_name, walk_it, walk_stream};
+use std::{collections::HashMap, fs, path::Path, sync::atomic::Ordering};
+use tempdir::TempDir;
+
+fn build_fixture(root: &Path) {
+    // /root
+    //   /alpha
+    //      /nested
+    //         deep.txt
+    //      a.txt
+    //   /beta
+    //   /gamma
+    //      g1.log
+    //      g2.log
+    //   top.md
+    fs::create_dir_all(root.join("alpha/nested")).unwrap();
+    fs::create_dir(root.join("beta")).unwrap();
+    fs::create_dir(root.join("gamma")).unwrap();
+    fs::write(root.join("alpha/nested/deep.txt"), b"d").unwrap();
+    fs::write(root.join("alpha/a.txt"), b"a").unwrap();
+    fs::write(root.join("gamma/g1.log"), b"1").unwrap();
+    fs::write(root.join("gamma/g2.log"), b"2").unwrap();
+    fs::write(root.join("top.md"), b"t").unwrap();
+}
+
+/// Name, whether metadata is present, and children sorted by name.
+#[derive(Debug, PartialEq, Eq)]
+struct Shape {
+    name: String,
+    has_metadata: bool,
+    children: Vec<Shape>,
+}
+
+impl Shape {
+    fn new(name: &str, has_metadata: bool, mut children: Vec<Shape>) -> Self {
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+        Self {
+            name: name.to_string(),
+            has_metadata,
+            children,
+        }
+    }
+
+    fn of(node: &Node) -> Self {
+        Self::new(
+            &node.name,
+            node.metadata.is_some(),
+            node.children.iter().map(Shape::of).collect(),
+        )
+    }
+}
+
+/// Rebuild the tree from `events` using only the ids they carry.
+fn reconstruct(events: &[WalkEvent]) -> Shape {
+    struct Dir {
+        name: String,
+        has_metadata: bool,
+        files: Vec<Shape>,
+        subdirs: Vec<DirId>,
+    }
+
+    let mut dirs: HashMap<DirId, Dir> = HashMap::new();
+    let mut root = None;
+    let mut left = Vec::new();
+    for event in events {
+        match event {
+            WalkEvent::EnterDir {
+                id,
+                parent,
+                path,
+                metadata,
+            } => {
+                assert!(!dirs.contains_key(id), "directory {id} entered twice");
+                let (name, _) = split_file_name(path.file_name().unwrap());
+                dirs.insert(
+                    *id,
+                    Dir {
+                        name: name.into(),
+                        has_metadata: metadata.is_some(),
+                        files: vec![],
+                        subdirs: vec![],
+                    },
+                );
+                match parent {
+                    Some(parent) => {
+                        assert!(
+                            !left.contains(parent),
+                            "entered {id} after leaving its parent"
+                        );
+                        dirs.get_mut(parent).unwrap().subdirs.push(*id);
+                    }
+                    None => root = Some(*id),
+                }
+            }
+            WalkEvent::File {
+                parent,
+                name,
+                metadata,
+                ..
+            } => {
+                assert!(
+                    !left.contains(parent),
+                    "file reported after leaving its parent"
+                );
+                dirs.get_mut(parent).unwrap().files.push(Shape::new(
+                    name,
+                    metadata.is_some(),
+                    vec![],
+                ));
+            }
+            WalkEvent::LeaveDir { id } => {
+                assert!(dirs.contains_key(id), "left {id} before entering it");
+                left.push(*id);
+            }
+            WalkEvent::Error { path, error } => panic!("unexpected error at {path:?}: {error}"),
+        }
+    }
+
+    fn assemble(id: DirId, dirs: &mut HashMap<DirId, Dir>) -> Shape {
+        let dir = dirs.remove(&id).unwrap();
+        let mut children = dir.files;
+        children.extend(dir.subdirs.into_iter().map(|id| assemble(id, dirs)));
+        Shape::new(&dir.name, dir.has_metadata, children)
+    }
+
+    let root = root.expect("root directory should be entered");
+    assert_eq!(left.last(), Some(&root), "root should be left last");
+    assert_eq!(left.len(), dirs.len());
+    assemble(root, &mut dirs)
+}
+
+fn collect_events(root: &Path, walk_data: &WalkData) -> Vec<WalkEvent> {
+    let (tx, rx) = unbounded();
+    walk_stream(root, walk_data, tx);
+    rx.into_iter().collect()
+}
+
+#[test]
+fn stream_reconstructs_walk_it_tree() {
+    let tmp = TempDir::new("fswalk_stream").unwrap();
+    build_fixture(tmp.path());
+    for need_metadata in [false, true] {
+        let events = collect_events(tmp.path(), &WalkData::simple(need_metadata));
+        let tree = walk_it(tmp.path(), &WalkData::simple(need_metadata)).unwrap();
+        assert_eq!(reconstruct(&events), Shape::of(&tree));
+    }
+}
+
+#[test]
+fn stream_counts_match_events() {
+    let tmp = TempDir::new("fswalk_stream_counts").unwrap();
+    build_fixture(tmp.path());
+    let walk_data = WalkData::simple(false);
+    let events = collect_events(tmp.path(), &walk_data);
+    let entered = events
+        .iter()
+        .filter(|e| matches!(e, WalkEvent::EnterDir { .. }))
+        .count();
+    let files = events
+        .iter()
+        .filter(|e| matches!(e, WalkEvent::File { .. }))
+        .count();
+    assert_eq!(entered, 5);
+    assert_eq!(files, 5);
+    assert_eq!(walk_data.num_dirs.load(Ordering::Relaxed), 5);
+    assert_eq!(walk_data.num_files.load(Ordering::Relaxed), 5);
+}
+
+#[test]
+fn byte_totals_cover_files_with_metadata() {
+    let tmp = TempDir::new("fswalk_stream_bytes").unwrap();
+    fs::create_dir(tmp.path().join("sub")).unwrap();
+    fs::write(tmp.path().join("ten.bin"), [0u8; 10]).unwrap();
+    fs::write(tmp.path().join("sub/hundred.bin"), [0u8; 100]).unwrap();
+    fs::write(tmp.path().join("sub/empty.bin"), b"").unwrap();
+
+    let walk_data = WalkData::simple(true);
+    walk_it(tmp.path(), &walk_data).unwrap();
+    assert_eq!(
+        walk_data.totals(),
+        WalkTotals {
+            files: 3,
+            dirs: 2,
+            bytes: 110,
+            unsized_files: 0,
+        }
+    );
+
+    // Without metadata nothing is stat'ed, so nothing is sized.
+    let walk_data = WalkData::simple(false);
+    walk_it(tmp.path(), &walk_data).unwrap();
+    let totals = walk_data.totals();
+    assert_eq!(
+        (totals.files, totals.bytes, totals.unsized_files),
+        (3, 0, 3)
+    );
+}
+
+#[test]
+fn unreadable_files_are_counted_unsized() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let tmp = TempDir::new("fswalk_stream_unsized").unwrap();
+    let locked = tmp.path().join("locked");
+    fs::create_dir(&locked).unwrap();
+    fs::write(locked.join("secret.bin"), [0u8; 7]).unwrap();
+    fs::write(tmp.path().join("open.bin"), [0u8; 5]).unwrap();
+    // Listable but not searchable: names are read, stat fails.
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o644)).unwrap();
+    let stat_denied = fs::symlink_metadata(locked.join("secret.bin")).is_err();
+
+    let walk_data = WalkData::simple(true);
+    walk_it(tmp.path(), &walk_data).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    let totals = walk_data.totals();
+    assert_eq!(totals.files, 2);
+    // Permissions don't apply to root.
+    if stat_denied {
+        assert_eq!((totals.bytes, totals.unsized_files), (5, 1));
+    } else {
+        assert_eq!((totals.bytes, totals.unsized_files), (12, 0));
+    }
+}
+
+#[test]
+fn ignored_directories_produce_no_events() {
+    let tmp = TempDir::new("fswalk_stream_ignore").unwrap();
+    build_fixture(tmp.path());
+    let walk_data = WalkData::builder()
+        .ignore_directory(tmp.path().join("gamma"))
+        .build()
+        .unwrap();
+    let events = collect_events(tmp.path(), &walk_data);
+    let shape = reconstruct(&events);
+    assert!(!shape.children.iter().any(|c| c.name == "gamma"));
+    assert_eq!(shape.children.len(), 3);
+}
+
+#[test]
+fn dropped_receiver_stops_the_walk() {
+    let tmp = TempDir::new("fswalk_stream_dropped").unwrap();
+    build_fixture(tmp.path());
+    let (tx, rx) = unbounded();
+    drop(rx);
+    walk_stream(tmp.path(), &WalkData::simple(false), tx);
+}
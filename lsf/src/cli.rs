@@ -8,4 +8,8 @@ pub struct Cli {
     pub refresh: bool,
     #[clap(long, default_value = "/")]
     pub path: PathBuf,
+    /// Keep dotfiles and `chflags hidden` files in the results, `/hidden`
+    /// toggles it later.
+    #[clap(long)]
+    pub include_hidden: bool,
 }
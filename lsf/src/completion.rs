@@ -0,0 +1,110 @@
+use std::fs;
+
+/// Filter keywords offered when completing a word.
+const KEYWORDS: &[&str] = &["ext:", "size:", "dm:", "type:", "parent:", "infolder:"];
+/// Keywords whose argument is a folder.
+const FOLDER_KEYWORDS: &[&str] = &["parent:", "infolder:"];
+
+/// Completions for the word before byte `pos` of `line`, with the byte offset
+/// the completions replace from. Filter keywords complete by prefix, and the
+/// argument of `parent:` / `infolder:` completes to folders read from disk.
+pub fn complete(line: &str, pos: usize) -> (usize, Vec<String>) {
+    let head = &line[..pos];
+    let start = head
+        .char_indices()
+        .rev()
+        .find(|(_, c)| c.is_whitespace())
+        .map_or(0, |(i, c)| i + c.len_utf8());
+    let word = &head[start..];
+    if let Some(keyword) = FOLDER_KEYWORDS
+        .iter()
+        .find(|keyword| word.starts_with(**keyword))
+    {
+        let start = start + keyword.len();
+        return (start, complete_folder(&head[start..]));
+    }
+    let word = word.to_ascii_lowercase();
+    let keywords = KEYWORDS
+        .iter()
+        .filter(|keyword| keyword.starts_with(&word))
+        .map(|keyword| keyword.to_string())
+        .collect();
+    (start, keywords)
+}
+
+/// Folders whose path starts with `prefix`, relative to the working directory
+/// unless `prefix` is absolute. Hidden folders only show once the name starts
+/// with a dot.
+fn complete_folder(prefix: &str) -> Vec<String> {
+    let (folder, name) = match prefix.rfind('/') {
+        Some(slash) => prefix.split_at(slash + 1),
+        None => ("", prefix),
+    };
+    let listed = if folder.is_empty() { "." } else { folder };
+    let Ok(entries) = fs::read_dir(listed) else {
+        return Vec::new();
+    };
+    let mut folders: Vec<_> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|entry| {
+            entry.starts_with(name) && (name.starts_with('.') || !entry.starts_with('.'))
+        })
+        .map(|entry| format!("{folder}{entry}/"))
+        .collect();
+    folders.sort();
+    folders
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_keywords_complete_by_prefix() {
+        assert_eq!(
+            complete("", 0),
+            (0, KEYWORDS.iter().map(|k| k.to_string()).collect())
+        );
+        assert_eq!(complete("report e", 8), (7, vec!["ext:".to_string()]));
+        assert_eq!(complete("report IN", 9), (7, vec!["infolder:".to_string()]));
+        assert_eq!(complete("report", 6), (0, Vec::<String>::new()));
+        // Only the word before the cursor counts.
+        assert_eq!(complete("s report", 1), (0, vec!["size:".to_string()]));
+    }
+
+    #[test]
+    fn test_folder_keywords_complete_folders_from_disk() {
+        let tmp = TempDir::new("lsf_complete").unwrap();
+        let root = tmp.path().to_str().unwrap();
+        fs::create_dir(tmp.path().join("docs")).unwrap();
+        fs::create_dir(tmp.path().join("downloads")).unwrap();
+        fs::create_dir(tmp.path().join(".dotfiles")).unwrap();
+        fs::write(tmp.path().join("dossier.txt"), b"").unwrap();
+
+        let line = format!("report parent:{root}/do");
+        assert_eq!(
+            complete(&line, line.len()),
+            (
+                14,
+                vec![format!("{root}/docs/"), format!("{root}/downloads/")]
+            )
+        );
+        let line = format!("infolder:{root}/");
+        let (start, folders) = complete(&line, line.len());
+        assert_eq!(start, 9);
+        assert_eq!(
+            folders,
+            [format!("{root}/docs/"), format!("{root}/downloads/")]
+        );
+        let line = format!("infolder:{root}/.d");
+        assert_eq!(
+            complete(&line, line.len()).1,
+            [format!("{root}/.dotfiles/")]
+        );
+        let line = format!("parent:{root}/missing/");
+        assert!(complete(&line, line.len()).1.is_empty());
+    }
+}
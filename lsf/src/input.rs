@@ -0,0 +1,123 @@
+use crate::completion::complete;
+use anyhow::{Context as _, Result};
+use rustyline::{
+    Context, Editor, ExternalPrinter, Helper, completion::Completer, error::ReadlineError,
+    highlight::Highlighter, hint::Hinter, history::FileHistory, validate::Validator,
+};
+use std::{
+    fs,
+    io::{self, IsTerminal, Stdin, Write},
+    path::PathBuf,
+};
+
+const PROMPT: &str = "> ";
+
+/// Where query lines come from: a line editor with history, recall and
+/// completion on a terminal, plain reads when stdin is piped.
+pub enum LineReader {
+    Editor {
+        editor: Box<Editor<QueryHelper, FileHistory>>,
+        history: PathBuf,
+    },
+    Plain(Stdin),
+}
+
+impl LineReader {
+    /// The history is loaded from, and saved to, `history` on a terminal.
+    pub fn new(history: PathBuf) -> Result<Self> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return Ok(Self::Plain(stdin));
+        }
+        let mut editor = Editor::new().context("Failed to set up the line editor")?;
+        editor.set_helper(Some(QueryHelper));
+        // Missing on the first run.
+        let _ = editor.load_history(&history);
+        Ok(Self::Editor {
+            editor: Box::new(editor),
+            history,
+        })
+    }
+
+    /// Prints above the line being edited, `None` when the caller has to
+    /// print the prompt again itself.
+    pub fn printer(&mut self) -> Result<Option<Box<dyn ExternalPrinter + Send>>> {
+        match self {
+            Self::Editor { editor, .. } => Ok(Some(Box::new(
+                editor
+                    .create_external_printer()
+                    .context("Failed to create the printer")?,
+            ))),
+            Self::Plain(_) => Ok(None),
+        }
+    }
+
+    /// Next line, `None` at the end of input. `prompt` is ignored by the line
+    /// editor, which always shows it. Ctrl-C drops the line being edited.
+    pub fn read_line(&mut self, prompt: bool) -> Result<Option<String>> {
+        match self {
+            Self::Editor { editor, .. } => match editor.readline(PROMPT) {
+                Ok(line) => {
+                    if !line.trim().is_empty() {
+                        editor.add_history_entry(line.trim())?;
+                    }
+                    Ok(Some(line))
+                }
+                Err(ReadlineError::Interrupted) => Ok(Some(String::new())),
+                Err(ReadlineError::Eof) => Ok(None),
+                Err(e) => Err(e.into()),
+            },
+            Self::Plain(stdin) => {
+                if prompt {
+                    print!("{PROMPT}");
+                    io::stdout().flush()?;
+                }
+                let mut line = String::new();
+                Ok((stdin.read_line(&mut line)? > 0).then_some(line))
+            }
+        }
+    }
+
+    /// Whether lines are piped in, e.g. by a script, rather than typed.
+    pub fn is_plain(&self) -> bool {
+        matches!(self, Self::Plain(_))
+    }
+
+    pub fn save_history(&mut self) -> Result<()> {
+        if let Self::Editor { editor, history } = self {
+            if let Some(folder) = history.parent() {
+                fs::create_dir_all(folder)?;
+            }
+            editor
+                .save_history(history)
+                .with_context(|| format!("Failed to save the history to {history:?}"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Completes filter keywords and folders, see [`complete`].
+pub struct QueryHelper;
+
+impl Completer for QueryHelper {
+    type Candidate = String;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        Ok(complete(line, pos))
+    }
+}
+
+impl Hinter for QueryHelper {
+    type Hint = String;
+}
+
+impl Highlighter for QueryHelper {}
+
+impl Validator for QueryHelper {}
+
+impl Helper for QueryHelper {}
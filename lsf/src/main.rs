@@ -1,19 +1,25 @@
 mod cli;
+mod completion;
+mod input;
+mod worker;
 
 use anyhow::{Context, Result};
 use cardinal_sdk::EventWatcher;
 use clap::Parser;
 use cli::Cli;
 use crossbeam_channel::{Sender, bounded, unbounded};
-use search_cache::{HandleFSEError, SearchCache, SearchResultNode};
-use search_cancel::CancellationToken;
+use input::LineReader;
+use search_cache::{DeletedEntry, ExportSummary, SearchCache, SearchError};
 use std::{
+    fmt::Write as _,
     io::Write,
     path::{Path, PathBuf},
 };
 use tracing_subscriber::{EnvFilter, filter::LevelFilter};
+use worker::{SearchReply, Searcher, WorkerChannels, run_worker};
 
 const CACHE_PATH: &str = "target/cache.zstd";
+const HISTORY_PATH: &str = "target/lsf_history";
 const IGNORE_PATH: &str = "/System/Volumes/Data"; // macOS specific ignore path
 
 fn main() -> Result<()> {
@@ -26,6 +32,7 @@ fn main() -> Result<()> {
 
     let cli = Cli::parse();
     let path = cli.path;
+    let include_hidden = cli.include_hidden;
     let mut cache = if cli.refresh {
         println!("Walking filesystem...");
         SearchCache::walk_fs_with_ignore(path, vec![PathBuf::from(IGNORE_PATH)])
@@ -46,77 +53,134 @@ fn main() -> Result<()> {
     println!("Cache is: {cache:?}");
 
     let (finish_tx, finish_rx) = bounded::<Sender<SearchCache>>(1);
-    let (search_tx, search_rx) = unbounded::<String>();
-    let (search_result_tx, search_result_rx) = unbounded::<Result<Vec<SearchResultNode>>>();
+    let (search_tx, search_rx) = unbounded();
+    let (search_result_tx, search_result_rx) = unbounded::<SearchReply>();
+    let (deleted_tx, deleted_rx) = unbounded::<String>();
+    let (deleted_result_tx, deleted_result_rx) = unbounded::<Result<Vec<DeletedEntry>>>();
+    let (export_tx, export_rx) = unbounded::<PathBuf>();
+    let (export_result_tx, export_result_rx) = unbounded::<Result<ExportSummary>>();
+    let channels = WorkerChannels {
+        finish_rx,
+        search_rx,
+        search_result_tx,
+        deleted_rx,
+        deleted_result_tx,
+        export_rx,
+        export_result_tx,
+    };
 
     std::thread::spawn(move || {
-        let (dev, mut event_watcher) =
-            EventWatcher::spawn("/".to_string(), cache.last_event_id(), 0.1);
+        let (dev, event_watcher) = EventWatcher::spawn("/".to_string(), cache.last_event_id(), 0.1);
         println!("Processing changes of dev:{dev} during preparation.");
-        loop {
-            crossbeam_channel::select! {
-                recv(finish_rx) -> tx => {
-                    let tx = tx.expect("finish_tx is closed");
-                    tx.send(cache).expect("finish_tx is closed");
-                    break;
+        run_worker(cache, channels, "/", event_watcher);
+        println!("fsevent processing is done");
+    });
+
+    let mut reader = LineReader::new(PathBuf::from(HISTORY_PATH))?;
+    let mut printer = reader.printer()?;
+
+    // Results are printed as they come so that a new query can be typed (and
+    // cancel the running one) before the previous results are in.
+    std::thread::spawn(move || {
+        let mut stdout = std::io::stdout();
+        while let Ok(SearchReply { generation, result }) = search_result_rx.recv() {
+            let mut out = String::new();
+            match result {
+                Ok(paths) => {
+                    for (i, path) in paths.into_iter().enumerate() {
+                        writeln!(out, "[{i}] {path:?}").unwrap();
+                    }
                 }
-                recv(search_rx) -> query => {
-                    let query = query.expect("search_tx is closed");
-                    let files = cache.query_files(query, CancellationToken::noop()).map(|x| x.unwrap());
-                    search_result_tx
-                        .send(files)
-                        .expect("search_result_tx is closed");
+                Err(SearchError::Cancelled) => {
+                    writeln!(out, "query #{generation} cancelled").unwrap()
                 }
-                recv(event_watcher) -> events => {
-                    let events = events.expect("event_stream is closed");
-                    if let Err(HandleFSEError::Rescan) = cache.handle_fs_events(events) {
-                        println!("!!!!!!!!!! Rescan triggered !!!!!!!!");
-                        // Here we clear event_watcher first as rescan may take a lot of time
-                        #[allow(unused_assignments)]
-                        {
-                            event_watcher = EventWatcher::noop();
-                        }
-                        cache.rescan();
-                        event_watcher = EventWatcher::spawn("/".to_string(), cache.last_event_id(), 0.1).1;
-                    }
+                Err(e) => writeln!(out, "Failed to search: {e:?}").unwrap(),
+            }
+            // The line editor redraws the prompt under the output by itself.
+            match &mut printer {
+                Some(printer) => printer.print(out).unwrap(),
+                None => {
+                    print!("{out}> ");
+                    stdout.flush().unwrap();
                 }
             }
         }
-        println!("fsevent processing is done");
     });
 
-    let stdin = std::io::stdin();
-    let mut stdout = std::io::stdout();
+    // Scripted queries would crowd the typed ones out of the frecency history.
+    let mut searcher = Searcher::new(search_tx, include_hidden, reader.is_plain());
+    let mut prompt = true;
     loop {
-        print!("> ");
-        stdout.flush().unwrap();
-        let mut line = String::new();
-        stdin.read_line(&mut line).unwrap();
+        let Some(line) = reader.read_line(prompt)? else {
+            break;
+        };
+        prompt = true;
         let line = line.trim();
         if line.is_empty() {
             continue;
         } else if line == "/bye" {
             break;
-        }
-
-        search_tx
-            .send(line.to_string())
-            .context("search_tx is closed")?;
-        let search_result = search_result_rx
-            .recv()
-            .context("search_result_rx is closed")?;
-        match search_result {
-            Ok(path_set) => {
-                for (i, path) in path_set.into_iter().enumerate() {
-                    println!("[{i}] {:?} {:?}", path.path, path.metadata);
+        } else if line == "/hidden" {
+            if searcher.toggle_hidden() {
+                println!("hidden files are shown");
+            } else {
+                println!("hidden files are left out unless the query has is:hidden");
+            }
+            continue;
+        } else if let Some(query) = line.strip_prefix("/deleted") {
+            deleted_tx
+                .send(query.trim().to_string())
+                .context("deleted_tx is closed")?;
+            let deleted_result = deleted_result_rx
+                .recv()
+                .context("deleted_result_rx is closed")?;
+            match deleted_result {
+                Ok(entries) => {
+                    for (i, entry) in entries.into_iter().enumerate() {
+                        println!(
+                            "[{i}] {:?} {:?} deleted at {} (event {})",
+                            entry.path(),
+                            entry.file_type,
+                            entry.deleted_at,
+                            entry.event_id
+                        );
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to search deleted files: {e:?}");
                 }
             }
-            Err(e) => {
-                eprintln!("Failed to search: {e:?}");
+            continue;
+        } else if let Some(path) = line.strip_prefix("/export") {
+            let path = path.trim();
+            if path.is_empty() {
+                eprintln!("Usage: /export <file.csv|file.jsonl>");
+                continue;
+            }
+            export_tx
+                .send(PathBuf::from(path))
+                .context("export_tx is closed")?;
+            match export_result_rx
+                .recv()
+                .context("export_result_rx is closed")?
+            {
+                Ok(summary) => println!(
+                    "exported {} rows to {path} ({} gone, {} without metadata)",
+                    summary.rows, summary.skipped, summary.missing_metadata
+                ),
+                Err(e) => eprintln!("Failed to export results: {e:?}"),
             }
+            continue;
         }
+
+        searcher.submit(line.to_string())?;
+        // The printing thread shows the prompt again with the results.
+        prompt = false;
     }
 
+    if let Err(e) = reader.save_history() {
+        eprintln!("{e:?}");
+    }
     let (cache_tx, cache_rx) = bounded::<SearchCache>(1);
     finish_tx.send(cache_tx).context("cache_tx is closed")?;
     let cache = cache_rx.recv().context("cache_tx is closed")?;
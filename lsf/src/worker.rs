@@ -0,0 +1,337 @@
+use anyhow::{Context, Result};
+use cardinal_sdk::{EventBatch, EventWatcher, current_event_id};
+use crossbeam_channel::{Receiver, Sender};
+use search_cache::{
+    DeletedEntry, ExportFormat, ExportSummary, HandleFSEError, PendingRescan, SearchCache,
+    SearchError, SearchOptions, SearchResult, SlabIndex,
+};
+use search_cancel::CancellationToken;
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+
+/// A query for the worker thread, tagged with its generation.
+pub struct SearchRequest {
+    query: String,
+    generation: u64,
+    include_hidden: bool,
+    skip_history: bool,
+    token: CancellationToken,
+}
+
+pub struct SearchReply {
+    pub generation: u64,
+    /// `SearchError::Cancelled` once a newer query was submitted.
+    pub result: SearchResult<Vec<PathBuf>>,
+}
+
+/// Hands out query generations to the REPL.
+pub struct Searcher {
+    tx: Sender<SearchRequest>,
+    generation: u64,
+    /// See [`SearchOptions::include_hidden`].
+    include_hidden: bool,
+    /// Keep the queries out of the cache's query history, for scripted input.
+    skip_history: bool,
+}
+
+impl Searcher {
+    pub fn new(tx: Sender<SearchRequest>, include_hidden: bool, skip_history: bool) -> Self {
+        Self {
+            tx,
+            generation: 0,
+            include_hidden,
+            skip_history,
+        }
+    }
+
+    /// Flip whether the next queries keep hidden files, returning the new setting.
+    pub fn toggle_hidden(&mut self) -> bool {
+        self.include_hidden = !self.include_hidden;
+        self.include_hidden
+    }
+
+    /// Queue `query` and return its generation. Any query still running or
+    /// waiting in the channel is cancelled right away.
+    pub fn submit(&mut self, query: String) -> Result<u64> {
+        self.generation += 1;
+        let token = CancellationToken::new(self.generation);
+        self.tx
+            .send(SearchRequest {
+                query,
+                generation: self.generation,
+                include_hidden: self.include_hidden,
+                skip_history: self.skip_history,
+                token,
+            })
+            .context("search_tx is closed")?;
+        Ok(self.generation)
+    }
+}
+
+pub struct WorkerChannels {
+    pub finish_rx: Receiver<Sender<SearchCache>>,
+    pub search_rx: Receiver<SearchRequest>,
+    pub search_result_tx: Sender<SearchReply>,
+    pub deleted_rx: Receiver<String>,
+    pub deleted_result_tx: Sender<Result<Vec<DeletedEntry>>>,
+    /// Where to export the results of the last completed query.
+    pub export_rx: Receiver<PathBuf>,
+    pub export_result_tx: Sender<Result<ExportSummary>>,
+}
+
+/// Serve queries and apply FSEvents of `watch_root` until the cache is asked back.
+pub fn run_worker(
+    mut cache: SearchCache,
+    channels: WorkerChannels,
+    watch_root: &str,
+    mut event_watcher: EventWatcher,
+) {
+    let WorkerChannels {
+        finish_rx,
+        search_rx,
+        search_result_tx,
+        deleted_rx,
+        deleted_result_tx,
+        export_rx,
+        export_result_tx,
+    } = channels;
+    let mut last_results = Vec::new();
+    // Searches keep hitting the old tree while a rescan walks the new one.
+    let mut rescan: Option<PendingRescan> = None;
+    let no_rescan = crossbeam_channel::never();
+    loop {
+        crossbeam_channel::select! {
+            recv(finish_rx) -> tx => {
+                let tx = tx.expect("finish_tx is closed");
+                tx.send(cache).expect("finish_tx is closed");
+                break;
+            }
+            recv(search_rx) -> request => {
+                let request = request.expect("search_tx is closed");
+                search_result_tx
+                    .send(run_search(&cache, request, &mut last_results))
+                    .expect("search_result_tx is closed");
+            }
+            recv(export_rx) -> path => {
+                let path = path.expect("export_tx is closed");
+                export_result_tx
+                    .send(export_results(&cache, &last_results, &path))
+                    .expect("export_result_tx is closed");
+            }
+            recv(deleted_rx) -> query => {
+                let query = query.expect("deleted_tx is closed");
+                let entries = cache.search_deleted(&query, CancellationToken::noop()).map_err(anyhow::Error::from);
+                deleted_result_tx
+                    .send(entries)
+                    .expect("deleted_result_tx is closed");
+            }
+            recv(rescan.as_ref().map_or(&no_rescan, PendingRescan::walked)) -> walked => {
+                let pending = rescan.take().expect("walked fired without a rescan");
+                let walked = walked.expect("rescan thread is gone");
+                last_results.clear();
+                let replayed = cache.finish_rescan(pending, walked);
+                println!("Rescan complete, {} files", cache.get_total_files());
+                if let Err(HandleFSEError::Rescan) = replayed {
+                    rescan = Some(start_rescan(&cache, &mut event_watcher, watch_root));
+                }
+            }
+            recv(event_watcher) -> batch => {
+                let EventBatch { events, .. } = batch.expect("event_stream is closed");
+                if let Some(rescan) = &mut rescan {
+                    rescan.buffer_events(events);
+                } else if let Err(HandleFSEError::Rescan) = cache.handle_fs_events(events) {
+                    println!("!!!!!!!!!! Rescan triggered !!!!!!!!");
+                    rescan = Some(start_rescan(&cache, &mut event_watcher, watch_root));
+                }
+            }
+        }
+    }
+}
+
+/// Walk the tree again in the background. The watcher starts over from now,
+/// what it reports during the walk is buffered for the new tree.
+fn start_rescan(
+    cache: &SearchCache,
+    event_watcher: &mut EventWatcher,
+    watch_root: &str,
+) -> PendingRescan {
+    *event_watcher = EventWatcher::spawn(watch_root.to_string(), current_event_id(), 0.1).1;
+    cache.rescan_async()
+}
+
+/// Run `request`, keeping its nodes in `last_results` when it completes.
+fn run_search(
+    cache: &SearchCache,
+    request: SearchRequest,
+    last_results: &mut Vec<SlabIndex>,
+) -> SearchReply {
+    let SearchRequest {
+        query,
+        generation,
+        include_hidden,
+        skip_history,
+        token,
+    } = request;
+    // Queries superseded while queued aren't worth starting.
+    let result = if token.is_superseded() {
+        Err(SearchError::Cancelled)
+    } else {
+        let options = SearchOptions::default()
+            .with_strict_cancellation(true)
+            .with_include_hidden(include_hidden)
+            .with_skip_history(skip_history);
+        cache
+            .search_with_options(&query, options, token)
+            .and_then(|outcome| {
+                if outcome.partial || token.is_superseded() {
+                    Err(SearchError::Cancelled)
+                } else {
+                    let paths = outcome
+                        .nodes
+                        .iter()
+                        .filter_map(|&index| cache.node_path(index))
+                        .collect();
+                    *last_results = outcome.nodes;
+                    Ok(paths)
+                }
+            })
+    };
+    SearchReply { generation, result }
+}
+
+fn export_results(cache: &SearchCache, nodes: &[SlabIndex], path: &Path) -> Result<ExportSummary> {
+    let format = ExportFormat::from_path(path)
+        .with_context(|| format!("{path:?} should end with .csv, .json or .jsonl"))?;
+    let file = File::create(path).with_context(|| format!("Failed to create {path:?}"))?;
+    cache.export_results(nodes, format, &mut BufWriter::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::{bounded, unbounded};
+    use std::fs;
+    use tempdir::TempDir;
+
+    struct Worker {
+        searcher: Searcher,
+        search_result_rx: Receiver<SearchReply>,
+        export_tx: Sender<PathBuf>,
+        export_result_rx: Receiver<Result<ExportSummary>>,
+        finish_tx: Sender<Sender<SearchCache>>,
+        /// Kept open, the worker stops when it's closed.
+        _deleted_tx: Sender<String>,
+        handle: std::thread::JoinHandle<()>,
+    }
+
+    impl Worker {
+        /// Starts the worker once `queue` submitted its queries, so they are
+        /// all waiting before it gets to the first one.
+        fn spawn(
+            cache: SearchCache,
+            root: &Path,
+            skip_history: bool,
+            queue: impl FnOnce(&mut Searcher),
+        ) -> Self {
+            let (finish_tx, finish_rx) = bounded::<Sender<SearchCache>>(1);
+            let (search_tx, search_rx) = unbounded();
+            let (search_result_tx, search_result_rx) = unbounded();
+            let (deleted_tx, deleted_rx) = unbounded();
+            let (deleted_result_tx, _deleted_result_rx) = unbounded();
+            let (export_tx, export_rx) = unbounded();
+            let (export_result_tx, export_result_rx) = unbounded();
+            let channels = WorkerChannels {
+                finish_rx,
+                search_rx,
+                search_result_tx,
+                deleted_rx,
+                deleted_result_tx,
+                export_rx,
+                export_result_tx,
+            };
+            let mut searcher = Searcher::new(search_tx, false, skip_history);
+            queue(&mut searcher);
+            let root = root.to_string_lossy().into_owned();
+            let handle = std::thread::spawn(move || {
+                run_worker(cache, channels, &root, EventWatcher::noop())
+            });
+            Self {
+                searcher,
+                search_result_rx,
+                export_tx,
+                export_result_rx,
+                finish_tx,
+                _deleted_tx: deleted_tx,
+                handle,
+            }
+        }
+
+        /// Stop the worker and take the cache back.
+        fn finish(self) -> SearchCache {
+            let (cache_tx, cache_rx) = bounded(1);
+            self.finish_tx.send(cache_tx).unwrap();
+            let cache = cache_rx.recv().unwrap();
+            self.handle.join().unwrap();
+            cache
+        }
+    }
+
+    #[test]
+    fn test_new_query_cancels_the_previous_one() {
+        let tmp = TempDir::new("lsf_worker").unwrap();
+        fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+        fs::write(tmp.path().join("beta.txt"), b"b").unwrap();
+        let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+
+        let mut generations = (0, 0);
+        let worker = Worker::spawn(cache, tmp.path(), false, |searcher| {
+            generations.0 = searcher.submit("alpha".to_string()).unwrap();
+            generations.1 = searcher.submit("beta".to_string()).unwrap();
+        });
+        let (first, second) = generations;
+        assert!(second > first);
+
+        let reply = worker.search_result_rx.recv().unwrap();
+        assert_eq!(reply.generation, first);
+        assert!(matches!(reply.result, Err(SearchError::Cancelled)));
+        let reply = worker.search_result_rx.recv().unwrap();
+        assert_eq!(reply.generation, second);
+        assert_eq!(reply.result.unwrap(), [tmp.path().join("beta.txt")]);
+
+        // Exports hold the results of the last completed query only.
+        let csv = tmp.path().join("results.csv");
+        worker.export_tx.send(csv.clone()).unwrap();
+        let summary = worker.export_result_rx.recv().unwrap().unwrap();
+        assert_eq!(summary.rows, 1);
+        let exported = fs::read_to_string(&csv).unwrap();
+        assert!(exported.contains(",beta.txt,1,"));
+        assert!(!exported.contains("alpha"));
+        worker
+            .export_tx
+            .send(tmp.path().join("results.txt"))
+            .unwrap();
+        assert!(worker.export_result_rx.recv().unwrap().is_err());
+
+        let cache = worker.finish();
+        // The cancelled query never made it into the history.
+        assert_eq!(cache.suggest_queries("", 10), ["beta"]);
+    }
+
+    #[test]
+    fn test_scripted_queries_skip_the_history() {
+        let tmp = TempDir::new("lsf_worker_scripted").unwrap();
+        fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+        let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+
+        let mut worker = Worker::spawn(cache, tmp.path(), true, |_| {});
+        worker.searcher.submit("alpha".to_string()).unwrap();
+        let reply = worker.search_result_rx.recv().unwrap();
+        assert_eq!(reply.result.unwrap(), [tmp.path().join("alpha.txt")]);
+
+        let cache = worker.finish();
+        assert!(cache.suggest_queries("", 10).is_empty());
+    }
+}
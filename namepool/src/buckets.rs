@@ -0,0 +1,133 @@
+use core::str;
+
+/// Buckets keyed by a lowercase ASCII byte.
+const SINGLES: usize = 128;
+/// Buckets keyed by a pair of lowercase ASCII bytes.
+const PAIRS: usize = SINGLES * SINGLES;
+
+/// A pooled string by address. The bytes live in a box of an `Entry` that
+/// is never dropped nor mutated before the pool, see `Entry::pooled_name`.
+#[derive(Clone, Copy)]
+pub(crate) struct RawName {
+    ptr: *const u8,
+    len: usize,
+}
+
+// SAFETY: the pointer is only read, under the pool's lock or while the pool
+// owning the bytes is borrowed, and the bytes are never written to.
+unsafe impl Send for RawName {}
+
+impl RawName {
+    pub(crate) fn new(name: &str) -> Self {
+        Self {
+            ptr: name.as_ptr(),
+            len: name.len(),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The pool owning the bytes must be borrowed for `'pool`.
+    pub(crate) unsafe fn get<'pool>(self) -> &'pool str {
+        unsafe { str::from_raw_parts(self.ptr, self.len) }
+    }
+}
+
+/// Which ASCII names contain each lowercase ASCII byte and each pair of
+/// adjacent ones, so a search for a literal only confirms the names holding
+/// its rarest pair. Names are only ever added, like to the pool.
+pub(crate) struct NameBuckets {
+    /// ASCII names by id, ids being the order they were added in.
+    ascii: Vec<RawName>,
+    singles: Vec<Vec<u32>>,
+    pairs: Vec<Vec<u32>>,
+    /// Names with other characters, and their NFC form. Case folding and NFC
+    /// can turn those into ASCII, so they are candidates for every hint.
+    other: Vec<(RawName, RawName)>,
+}
+
+impl NameBuckets {
+    pub(crate) fn new() -> Self {
+        Self {
+            ascii: Vec::new(),
+            singles: vec![Vec::new(); SINGLES],
+            pairs: vec![Vec::new(); PAIRS],
+            other: Vec::new(),
+        }
+    }
+
+    /// Add a name new to the pool, with its NFC form.
+    pub(crate) fn insert(&mut self, name: &str, nfc: &str) {
+        if !name.is_ascii() {
+            self.other.push((RawName::new(name), RawName::new(nfc)));
+            return;
+        }
+        let id = u32::try_from(self.ascii.len()).expect("fewer than 2^32 names");
+        self.ascii.push(RawName::new(name));
+        let lower: Vec<usize> = name
+            .bytes()
+            .map(|byte| usize::from(byte.to_ascii_lowercase()))
+            .collect();
+        let mut singles = [false; SINGLES];
+        for &byte in &lower {
+            if !singles[byte] {
+                singles[byte] = true;
+                self.singles[byte].push(id);
+            }
+        }
+        let mut pairs: Vec<usize> = lower.windows(2).map(|w| w[0] * SINGLES + w[1]).collect();
+        pairs.sort_unstable();
+        pairs.dedup();
+        for pair in pairs {
+            self.pairs[pair].push(id);
+        }
+    }
+
+    /// The names that may contain `hint` ignoring ASCII case: the ASCII ones
+    /// holding its rarest pair of adjacent ASCII characters (its rarest ASCII
+    /// character if it has no such pair) and all the others. `None` when
+    /// `hint` has no ASCII character to narrow with.
+    pub(crate) fn candidates(&self, hint: &str) -> Option<Candidates<'_>> {
+        // Bytes of multi-byte characters are all >= 128, two adjacent ASCII
+        // bytes are two adjacent ASCII characters.
+        let bytes: Vec<usize> = hint
+            .bytes()
+            .map(|byte| usize::from(byte.to_ascii_lowercase()))
+            .collect();
+        let ids = bytes
+            .windows(2)
+            .filter(|w| w[0] < SINGLES && w[1] < SINGLES)
+            .map(|w| &self.pairs[w[0] * SINGLES + w[1]])
+            .min_by_key(|ids| ids.len())
+            .or_else(|| {
+                bytes
+                    .iter()
+                    .filter(|&&byte| byte < SINGLES)
+                    .map(|&byte| &self.singles[byte])
+                    .min_by_key(|ids| ids.len())
+            })?;
+        Some(Candidates { buckets: self, ids })
+    }
+}
+
+/// See [`NameBuckets::candidates`].
+pub(crate) struct Candidates<'b> {
+    buckets: &'b NameBuckets,
+    ids: &'b [u32],
+}
+
+impl<'b> Candidates<'b> {
+    pub(crate) fn len(&self) -> usize {
+        self.ids.len() + self.buckets.other.len()
+    }
+
+    /// Each candidate with its NFC form, the ASCII ones in the order they
+    /// were added first.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (RawName, RawName)> + 'b {
+        let ascii = &self.buckets.ascii;
+        self.ids
+            .iter()
+            .map(|&id| (ascii[id as usize], ascii[id as usize]))
+            .chain(self.buckets.other.iter().copied())
+    }
+}
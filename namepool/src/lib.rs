@@ -1,12 +1,122 @@
 #![feature(str_from_raw_parts)]
+mod buckets;
+
+use buckets::NameBuckets;
 use core::str;
+use icu_normalizer::ComposingNormalizerBorrowed;
 use parking_lot::Mutex;
 use regex::Regex;
 use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
-use std::collections::BTreeSet;
+use std::{
+    borrow::{Borrow, Cow},
+    cell::Cell,
+    cmp::Ordering,
+    collections::BTreeSet,
+    ops::ControlFlow,
+};
 
 pub struct NamePool {
-    inner: Mutex<BTreeSet<Box<str>>>,
+    inner: Mutex<Pool>,
+}
+
+struct Pool {
+    entries: BTreeSet<Entry>,
+    buckets: NameBuckets,
+}
+
+impl Pool {
+    fn insert(&mut self, name: &str) -> &Entry {
+        if !self.entries.contains(name) {
+            self.entries.insert(Entry::new(name));
+            let entry = self.entries.get(name).unwrap();
+            self.buckets.insert(entry.pooled_name(), entry.pooled_nfc());
+        }
+        self.entries.get(name).unwrap()
+    }
+}
+
+/// A pooled name, ordered by the name itself.
+struct Entry {
+    name: Box<str>,
+    /// NFC form of `name` if it isn't NFC already, e.g. the decomposed accents
+    /// macOS stores. Computed once on push so searches don't normalize names.
+    nfc: Option<Box<str>>,
+    /// Opaque value attached by [`NamePool::push_with_tag`]. Set at most once.
+    tag: Cell<Option<u32>>,
+}
+
+impl Entry {
+    fn new(name: &str) -> Self {
+        let nfc = match to_nfc(name) {
+            Cow::Borrowed(_) => None,
+            Cow::Owned(nfc) => Some(nfc.into_boxed_str()),
+        };
+        Self {
+            name: name.into(),
+            nfc,
+            tag: Cell::new(None),
+        }
+    }
+
+    /// The name with the lifetime of the pool rather than of the lock guard.
+    fn pooled_name<'pool>(&self) -> &'pool str {
+        // SAFETY: the bytes live in the `name` box's own heap allocation. The
+        // set only ever inserts: entries are never removed nor replaced, their
+        // names are never mutated (only the tag is), and rebalancing the tree moves the `Entry` (the box
+        // pointer), never the allocation it points to. The allocation is
+        // therefore valid and unchanged until the pool is dropped, which the
+        // `'pool` borrow of the pool rules out for as long as the name is used.
+        unsafe { str::from_raw_parts(self.name.as_ptr(), self.name.len()) }
+    }
+
+    /// Like [`Self::pooled_name`], for the NFC form: the `nfc` box is never
+    /// replaced either.
+    fn pooled_nfc<'pool>(&self) -> &'pool str {
+        match &self.nfc {
+            // SAFETY: as for `name`.
+            Some(nfc) => unsafe { str::from_raw_parts(nfc.as_ptr(), nfc.len()) },
+            None => self.pooled_name(),
+        }
+    }
+
+    fn nfc(&self) -> &str {
+        self.nfc.as_deref().unwrap_or(&self.name)
+    }
+}
+
+impl Borrow<str> for Entry {
+    fn borrow(&self) -> &str {
+        &self.name
+    }
+}
+
+impl PartialEq for Entry {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Entry {}
+
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+/// NFC form of `text`, borrowed when it is normalized already.
+pub fn to_nfc(text: &str) -> Cow<'_, str> {
+    const NFC: ComposingNormalizerBorrowed<'static> = ComposingNormalizerBorrowed::new_nfc();
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    NFC.normalize(text)
 }
 
 impl std::fmt::Debug for NamePool {
@@ -26,88 +136,237 @@ impl Default for NamePool {
 impl NamePool {
     pub fn new() -> Self {
         Self {
-            inner: Mutex::new(BTreeSet::new()),
+            inner: Mutex::new(Pool {
+                entries: BTreeSet::new(),
+                buckets: NameBuckets::new(),
+            }),
         }
     }
 
     pub fn len(&self) -> usize {
-        self.inner.lock().len()
+        self.inner.lock().entries.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.inner.lock().is_empty()
+        self.inner.lock().entries.is_empty()
     }
 
-    /// This function add a name into last cache line, if the last cache line is
-    /// full, a new cache line will be added.
+    /// Add `name` to the pool unless it is there already, and return the pooled
+    /// copy. Pushing the same name again returns the very same `&str`.
     ///
-    /// # Panic
-    ///
-    /// This function will panic if a new CacheLine cannot hold the given name.
-    ///
-    /// Returns (line_num, str_offset)
-    ///
-    /// One important feature of NamePool is that the returned offset is stable
-    /// and won't be overwritten.
+    /// The returned name stays valid and unchanged for as long as the pool is
+    /// borrowed, whatever other threads push or search meanwhile.
     pub fn push<'c>(&'c self, name: &str) -> &'c str {
+        self.inner.lock().insert(name).pooled_name()
+    }
+
+    /// Like [`push`](Self::push), also attaching `tag` to the name unless it
+    /// has one already. Returns the pooled name and the tag it ends up with,
+    /// so the first tag pushed for a name wins.
+    ///
+    /// Tags are opaque to the pool; names pushed with plain `push` have none
+    /// until tagged here.
+    pub fn push_with_tag<'c>(&'c self, name: &str, tag: u32) -> (&'c str, u32) {
         let mut inner = self.inner.lock();
-        if !inner.contains(name) {
-            inner.insert(name.into());
-        }
-        let existing = inner.get(name).unwrap();
-        unsafe { str::from_raw_parts(existing.as_ptr(), existing.len()) }
+        let entry = inner.insert(name);
+        let tag = entry.tag.get().unwrap_or(tag);
+        entry.tag.set(Some(tag));
+        (entry.pooled_name(), tag)
     }
 
-    pub fn search_substr<'search, 'pool: 'search>(
+    /// The tag attached to `name`, `None` when it isn't pooled or untagged.
+    pub fn tag_of(&self, name: &str) -> Option<u32> {
+        self.inner
+            .lock()
+            .entries
+            .get(name)
+            .and_then(|entry| entry.tag.get())
+    }
+
+    /// Calls `visit` with each name accepted by `matches`, in name order, until
+    /// it returns `Break`. Returns `None` when cancelled.
+    ///
+    /// The pool stays locked while visiting, so `visit` must not push names.
+    fn visit<'pool>(
         &'pool self,
-        substr: &'search str,
+        matches: impl Fn(&Entry) -> bool,
         cancellation_token: CancellationToken,
-    ) -> Option<BTreeSet<&'pool str>> {
-        let mut result = BTreeSet::new();
-        for (i, x) in self.inner.lock().iter().enumerate() {
+        mut visit: impl FnMut(&'pool str) -> ControlFlow<()>,
+    ) -> Option<()> {
+        for (i, x) in self.inner.lock().entries.iter().enumerate() {
             if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
                 return None;
             }
-            if x.contains(substr) {
-                result.insert(unsafe { str::from_raw_parts(x.as_ptr(), x.len()) });
+            if matches(x) && visit(x.pooled_name()).is_break() {
+                break;
             }
         }
+        Some(())
+    }
+
+    fn collect(
+        &self,
+        matches: impl Fn(&Entry) -> bool,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&str>> {
+        let mut result = BTreeSet::new();
+        self.visit(matches, cancellation_token, |name| {
+            result.insert(name);
+            ControlFlow::Continue(())
+        })?;
         Some(result)
     }
 
-    pub fn search_suffix<'search, 'pool: 'search>(
+    /// Names whose NFC form satisfies `predicate`, so that a predicate built
+    /// from an NFC needle matches names regardless of how they are composed.
+    pub fn search_nfc(
+        &self,
+        predicate: impl Fn(&str) -> bool,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&str>> {
+        self.collect(|x| predicate(x.nfc()), cancellation_token)
+    }
+
+    /// Like [`search_nfc`](Self::search_nfc), but only checking the names
+    /// that may contain `hint`. Every name `predicate` accepts must contain
+    /// `hint`, ignoring ASCII case. The names holding the rarest pair of
+    /// adjacent ASCII characters of `hint` (its rarest ASCII character if it
+    /// has no such pair) are checked, plus the names that aren't ASCII; the
+    /// whole pool when `hint` has no ASCII character.
+    pub fn search_nfc_hinted(
+        &self,
+        hint: &str,
+        predicate: impl Fn(&str) -> bool,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&str>> {
+        self.collect_hinted(hint, true, predicate, cancellation_token)
+    }
+
+    /// [`search_nfc_hinted`](Self::search_nfc_hinted) with `predicate` called
+    /// on the names as they are.
+    pub fn search_hinted(
+        &self,
+        hint: &str,
+        predicate: impl Fn(&str) -> bool,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&str>> {
+        self.collect_hinted(hint, false, predicate, cancellation_token)
+    }
+
+    /// How many names a hinted search for `hint` checks, `None` when it
+    /// checks them all.
+    pub fn hinted_candidates(&self, hint: &str) -> Option<usize> {
+        let inner = self.inner.lock();
+        inner
+            .buckets
+            .candidates(hint)
+            .map(|candidates| candidates.len())
+    }
+
+    fn collect_hinted(
+        &self,
+        hint: &str,
+        nfc: bool,
+        predicate: impl Fn(&str) -> bool,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&str>> {
+        {
+            let inner = self.inner.lock();
+            if let Some(candidates) = inner.buckets.candidates(hint) {
+                let mut result = BTreeSet::new();
+                for (i, (name, nfc_name)) in candidates.iter().enumerate() {
+                    if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
+                        return None;
+                    }
+                    // SAFETY: `self` is borrowed for as long as the result.
+                    let (name, nfc_name): (&str, &str) = unsafe { (name.get(), nfc_name.get()) };
+                    if predicate(if nfc { nfc_name } else { name }) {
+                        result.insert(name);
+                    }
+                }
+                return Some(result);
+            }
+        }
+        if nfc {
+            self.search_nfc(predicate, cancellation_token)
+        } else {
+            self.collect(|x| predicate(&x.name), cancellation_token)
+        }
+    }
+
+    pub fn search_substr<'search, 'pool: 'search>(
         &'pool self,
-        suffix: &'search str,
+        substr: &'search str,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&'pool str>> {
+        self.collect(|x| x.name.contains(substr), cancellation_token)
+    }
+
+    /// Streams the names containing `substr` into `visit`, stopping early when
+    /// it returns `Break`.
+    pub fn search_substr_visit<'pool>(
+        &'pool self,
+        substr: &str,
+        cancellation_token: CancellationToken,
+        visit: impl FnMut(&'pool str) -> ControlFlow<()>,
+    ) -> Option<()> {
+        self.visit(|x| x.name.contains(substr), cancellation_token, visit)
+    }
+
+    /// The first `limit` names, in name order, containing `substr`.
+    pub fn search_substr_limited<'search, 'pool: 'search>(
+        &'pool self,
+        substr: &'search str,
+        limit: usize,
         cancellation_token: CancellationToken,
     ) -> Option<BTreeSet<&'pool str>> {
         let mut result = BTreeSet::new();
-        for (i, x) in self.inner.lock().iter().enumerate() {
-            if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
-                return None;
-            }
-            if x.ends_with(suffix) {
-                result.insert(unsafe { str::from_raw_parts(x.as_ptr(), x.len()) });
-            }
+        if limit == 0 {
+            return Some(result);
         }
+        self.search_substr_visit(substr, cancellation_token, |name| {
+            result.insert(name);
+            if result.len() < limit {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        })?;
         Some(result)
     }
 
+    pub fn search_suffix<'search, 'pool: 'search>(
+        &'pool self,
+        suffix: &'search str,
+        cancellation_token: CancellationToken,
+    ) -> Option<BTreeSet<&'pool str>> {
+        self.collect(|x| x.name.ends_with(suffix), cancellation_token)
+    }
+
+    pub fn search_suffix_visit<'pool>(
+        &'pool self,
+        suffix: &str,
+        cancellation_token: CancellationToken,
+        visit: impl FnMut(&'pool str) -> ControlFlow<()>,
+    ) -> Option<()> {
+        self.visit(|x| x.name.ends_with(suffix), cancellation_token, visit)
+    }
+
     pub fn search_prefix<'search, 'pool: 'search>(
         &'pool self,
         prefix: &'search str,
         cancellation_token: CancellationToken,
     ) -> Option<BTreeSet<&'pool str>> {
-        let mut result = BTreeSet::new();
-        for (i, x) in self.inner.lock().iter().enumerate() {
-            if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
-                return None;
-            }
-            if x.starts_with(prefix) {
-                result.insert(unsafe { str::from_raw_parts(x.as_ptr(), x.len()) });
-            }
-        }
+        self.collect(|x| x.name.starts_with(prefix), cancellation_token)
+    }
 
-        Some(result)
+    pub fn search_prefix_visit<'pool>(
+        &'pool self,
+        prefix: &str,
+        cancellation_token: CancellationToken,
+        visit: impl FnMut(&'pool str) -> ControlFlow<()>,
+    ) -> Option<()> {
+        self.visit(|x| x.name.starts_with(prefix), cancellation_token, visit)
     }
 
     pub fn search_regex<'search, 'pool: 'search>(
@@ -115,17 +374,16 @@ impl NamePool {
         pattern: &Regex,
         cancellation_token: CancellationToken,
     ) -> Option<BTreeSet<&'pool str>> {
-        let mut result = BTreeSet::new();
-        for (i, x) in self.inner.lock().iter().enumerate() {
-            if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
-                return None;
-            }
-            let existing = unsafe { str::from_raw_parts(x.as_ptr(), x.len()) };
-            if pattern.is_match(existing) {
-                result.insert(existing);
-            }
-        }
-        Some(result)
+        self.collect(|x| pattern.is_match(&x.name), cancellation_token)
+    }
+
+    pub fn search_regex_visit<'pool>(
+        &'pool self,
+        pattern: &Regex,
+        cancellation_token: CancellationToken,
+        visit: impl FnMut(&'pool str) -> ControlFlow<()>,
+    ) -> Option<()> {
+        self.visit(|x| pattern.is_match(&x.name), cancellation_token, visit)
     }
 
     // `exact` should starts with a '\0', and ends with a '\0',
@@ -135,16 +393,16 @@ impl NamePool {
         exact: &'search str,
         cancellation_token: CancellationToken,
     ) -> Option<BTreeSet<&'pool str>> {
-        let mut result = BTreeSet::new();
-        for (i, x) in self.inner.lock().iter().enumerate() {
-            if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
-                return None;
-            }
-            if &**x == exact {
-                result.insert(unsafe { str::from_raw_parts(x.as_ptr(), x.len()) });
-            }
-        }
-        Some(result)
+        self.collect(|x| &*x.name == exact, cancellation_token)
+    }
+
+    pub fn search_exact_visit<'pool>(
+        &'pool self,
+        exact: &str,
+        cancellation_token: CancellationToken,
+        visit: impl FnMut(&'pool str) -> ControlFlow<()>,
+    ) -> Option<()> {
+        self.visit(|x| &*x.name == exact, cancellation_token, visit)
     }
 }
 
@@ -249,6 +507,37 @@ mod tests {
         assert_eq!(s1, "hello");
     }
 
+    #[test]
+    fn test_push_with_tag_first_writer_wins() {
+        let pool = NamePool::new();
+        let (s1, tag1) = pool.push_with_tag("hello", 7);
+        let (s2, tag2) = pool.push_with_tag("hello", 9);
+        assert_eq!(tag1, 7);
+        assert_eq!(tag2, 7);
+        assert!(std::ptr::eq(s1, s2));
+        assert_eq!(pool.tag_of("hello"), Some(7));
+        assert_eq!(pool.len(), 1);
+    }
+
+    #[test]
+    fn test_push_with_tag_interop_with_push() {
+        let pool = NamePool::new();
+        let plain = pool.push("plain");
+        assert_eq!(pool.tag_of("plain"), None);
+        let (tagged, tag) = pool.push_with_tag("plain", 3);
+        assert!(std::ptr::eq(plain, tagged));
+        assert_eq!(tag, 3);
+        assert_eq!(pool.tag_of("plain"), Some(3));
+
+        // Pushing a tagged name again keeps both the name and the tag.
+        let (first, _) = pool.push_with_tag("tagged", 5);
+        assert!(std::ptr::eq(pool.push("tagged"), first));
+        assert_eq!(pool.tag_of("tagged"), Some(5));
+
+        assert_eq!(pool.tag_of("missing"), None);
+        assert_eq!(pool.len(), 2);
+    }
+
     #[test]
     fn test_search_substr() {
         let pool = NamePool::new();
@@ -901,4 +1190,213 @@ mod tests {
         let result = substr(&pool, "1");
         assert_eq!(result.len(), 271);
     }
+
+    fn line_pool() -> NamePool {
+        let pool = NamePool::new();
+        for i in 0..100 {
+            pool.push(&format!("line1_{i}"));
+            pool.push(&format!("line2_{i}"));
+        }
+        pool
+    }
+
+    #[test]
+    fn test_search_substr_limited() {
+        let pool = line_pool();
+        let all = substr(&pool, "line");
+        let limited = guard(pool.search_substr_limited("line", 150, CancellationToken::noop()));
+        assert_eq!(limited.len(), 150);
+        // The first hits in name order, spanning both groups of names.
+        assert_eq!(
+            limited,
+            all.iter().copied().take(150).collect::<BTreeSet<_>>()
+        );
+        assert!(limited.iter().any(|name| name.starts_with("line2_")));
+
+        let limited = guard(pool.search_substr_limited("line", 1000, CancellationToken::noop()));
+        assert_eq!(limited, all);
+        let limited = guard(pool.search_substr_limited("line", 0, CancellationToken::noop()));
+        assert!(limited.is_empty());
+        let limited = guard(pool.search_substr_limited("line1_", 5, CancellationToken::noop()));
+        assert!(limited.iter().all(|name| name.starts_with("line1_")));
+        assert_eq!(limited.len(), 5);
+    }
+
+    #[test]
+    fn test_visit_break_stops_scanning() {
+        let pool = line_pool();
+        let mut visited = 0;
+        guard(
+            pool.search_substr_visit("_", CancellationToken::noop(), |_| {
+                visited += 1;
+                if visited == 3 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            }),
+        );
+        assert_eq!(visited, 3);
+
+        let mut visited = Vec::new();
+        guard(
+            pool.search_prefix_visit("line2_", CancellationToken::noop(), |name| {
+                visited.push(name);
+                ControlFlow::Break(())
+            }),
+        );
+        assert_eq!(visited, ["line2_0"]);
+    }
+
+    fn insert_into<'s, 'pool>(
+        names: &'s mut BTreeSet<&'pool str>,
+    ) -> impl FnMut(&'pool str) -> ControlFlow<()> + 's {
+        |name| {
+            names.insert(name);
+            ControlFlow::Continue(())
+        }
+    }
+
+    #[test]
+    fn test_visit_matches_set_results() {
+        let pool = line_pool();
+        pool.push("line1_7.txt");
+        pool.push("notes.txt");
+        let token = CancellationToken::noop;
+        for needle in ["line1_", "7", "1_9", ".txt", "notes.txt", "missing", ""] {
+            let mut names = BTreeSet::new();
+            guard(pool.search_substr_visit(needle, token(), insert_into(&mut names)));
+            assert_eq!(names, substr(&pool, needle), "substr {needle:?}");
+
+            let mut names = BTreeSet::new();
+            guard(pool.search_prefix_visit(needle, token(), insert_into(&mut names)));
+            assert_eq!(names, prefix_search(&pool, needle), "prefix {needle:?}");
+
+            let mut names = BTreeSet::new();
+            guard(pool.search_suffix_visit(needle, token(), insert_into(&mut names)));
+            assert_eq!(names, suffix_search(&pool, needle), "suffix {needle:?}");
+
+            let mut names = BTreeSet::new();
+            guard(pool.search_exact_visit(needle, token(), insert_into(&mut names)));
+            assert_eq!(names, exact_search(&pool, needle), "exact {needle:?}");
+
+            let regex = Regex::new(&regex::escape(needle)).unwrap();
+            let mut names = BTreeSet::new();
+            guard(pool.search_regex_visit(&regex, token(), insert_into(&mut names)));
+            assert_eq!(names, regex_search(&pool, &regex), "regex {needle:?}");
+        }
+    }
+
+    #[test]
+    fn test_visit_cancelled_returns_none() {
+        let pool = line_pool();
+        let token = CancellationToken::new(30);
+        let _ = CancellationToken::new(31);
+        let mut visited = 0;
+        let outcome = pool.search_substr_visit("line", token, |_| {
+            visited += 1;
+            ControlFlow::Continue(())
+        });
+        assert!(outcome.is_none());
+        assert_eq!(visited, 0);
+        assert!(pool.search_substr_limited("line", 10, token).is_none());
+    }
+
+    #[test]
+    fn test_to_nfc_composes_only_when_needed() {
+        assert!(matches!(to_nfc("plain.txt"), Cow::Borrowed(_)));
+        assert!(matches!(to_nfc("caf\u{e9}"), Cow::Borrowed(_)));
+        assert_eq!(to_nfc("cafe\u{301}"), "caf\u{e9}");
+    }
+
+    #[test]
+    fn test_search_nfc_ignores_composition() {
+        let pool = NamePool::new();
+        let decomposed = pool.push("cafe\u{301} menu.txt");
+        let composed = pool.push("caf\u{e9}.md");
+        pool.push("cafe.txt");
+        // Names are stored as they were pushed.
+        assert_eq!(decomposed, "cafe\u{301} menu.txt");
+        assert_eq!(pool.len(), 3);
+
+        let hits =
+            guard(pool.search_nfc(|name| name.contains("caf\u{e9}"), CancellationToken::noop()));
+        assert_eq!(hits, BTreeSet::from([decomposed, composed]));
+        // Byte-wise search still tells them apart.
+        assert_eq!(substr(&pool, "caf\u{e9}"), BTreeSet::from([composed]));
+    }
+
+    #[test]
+    fn test_search_nfc_cancelled_returns_none() {
+        let pool = NamePool::new();
+        pool.push("alpha");
+        let token = CancellationToken::new(20);
+        let _ = CancellationToken::new(21);
+        assert!(pool.search_nfc(|_| true, token).is_none());
+    }
+
+    fn hinted_pool() -> NamePool {
+        let pool = NamePool::new();
+        for idx in 0..1000 {
+            pool.push(&format!("file{idx:04}.txt"));
+        }
+        for name in [
+            "Report.pdf",
+            "quarterly report.docx",
+            "REPORTS",
+            "cafe\u{301} report",
+            "\u{212A}elvin",
+            "r",
+        ] {
+            pool.push(name);
+        }
+        pool
+    }
+
+    #[test]
+    fn test_hinted_search_matches_full_scan() {
+        let pool = hinted_pool();
+        for needle in [
+            "report", "ort", "r", "txt", "0042", "é", "kelvin", "K", "xyz", "",
+        ] {
+            let lower = needle.to_lowercase();
+            let caseless = |name: &str| name.to_lowercase().contains(&lower);
+            let exact = |name: &str| name.contains(needle);
+            assert_eq!(
+                guard(pool.search_nfc_hinted(needle, caseless, CancellationToken::noop())),
+                guard(pool.search_nfc(caseless, CancellationToken::noop())),
+                "{needle:?}"
+            );
+            assert_eq!(
+                guard(pool.search_hinted(needle, exact, CancellationToken::noop())),
+                substr(&pool, needle),
+                "{needle:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_hinted_search_confirms_few_names() {
+        let pool = hinted_pool();
+        // The three ASCII names with "po" and the two that aren't ASCII.
+        assert_eq!(pool.hinted_candidates("report"), Some(5));
+        assert_eq!(pool.hinted_candidates("RE"), Some(5));
+        // Every `fileNNNN.txt` has "t" and "00", but few have "42".
+        assert!(pool.hinted_candidates("0042").unwrap() < 25);
+        assert!(pool.hinted_candidates("t").unwrap() > 1000);
+        assert_eq!(pool.hinted_candidates("é"), None);
+        assert_eq!(pool.hinted_candidates(""), None);
+        // Names pushed later are bucketed as well.
+        pool.push("renamed");
+        assert_eq!(pool.hinted_candidates("report"), Some(5));
+        assert_eq!(pool.hinted_candidates("ren"), Some(3));
+    }
+
+    #[test]
+    fn test_hinted_search_cancelled_returns_none() {
+        let pool = hinted_pool();
+        let token = CancellationToken::new(30);
+        let _ = CancellationToken::new(31);
+        assert!(pool.search_nfc_hinted("txt", |_| true, token).is_none());
+    }
 }
@@ -0,0 +1,139 @@
+//! Pushes and searches racing from several threads. Names handed out by the
+//! pool must stay valid and deduplicated however the set grows meanwhile.
+
+use namepool::NamePool;
+use search_cancel::CancellationToken;
+use std::{
+    sync::{
+        Barrier,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+
+const THREADS: usize = 8;
+const NAMES_PER_THREAD: usize = 2_000;
+
+fn name(thread: usize, i: usize) -> String {
+    format!("t{thread}_name_{i:05}.txt")
+}
+
+#[test]
+fn test_early_names_survive_growth() {
+    let pool = NamePool::new();
+    let early: Vec<(String, &str)> = (0..64)
+        .map(|i| {
+            let owned = format!("early_{i}");
+            let pooled = pool.push(&owned);
+            (owned, pooled)
+        })
+        .collect();
+    let pointers: Vec<*const u8> = early.iter().map(|(_, pooled)| pooled.as_ptr()).collect();
+
+    // Plenty of inserts to split and rebalance the tree many times over.
+    for i in 0..50_000 {
+        pool.push(&format!("filler_{i}"));
+    }
+
+    for ((owned, pooled), pointer) in early.iter().zip(pointers) {
+        assert_eq!(pooled, owned);
+        assert_eq!(pooled.as_ptr(), pointer);
+        assert_eq!(pool.push(owned).as_ptr(), pointer);
+    }
+}
+
+#[test]
+fn test_concurrent_pushes_dedup() {
+    let pool = NamePool::new();
+    let barrier = Barrier::new(THREADS);
+    let pushed: Vec<Vec<&str>> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..THREADS)
+            .map(|_| {
+                let (pool, barrier) = (&pool, &barrier);
+                // Every thread pushes the same names, racing on each insert.
+                scope.spawn(move || {
+                    barrier.wait();
+                    (0..NAMES_PER_THREAD)
+                        .map(|i| pool.push(&name(0, i)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        handles.into_iter().map(|h| h.join().unwrap()).collect()
+    });
+
+    assert_eq!(pool.len(), NAMES_PER_THREAD);
+    for names in &pushed[1..] {
+        for (i, (name_a, name_b)) in pushed[0].iter().zip(names).enumerate() {
+            assert_eq!(*name_a, name(0, i));
+            assert_eq!(name_a.as_ptr(), name_b.as_ptr());
+        }
+    }
+}
+
+#[test]
+fn test_push_and_search_interleaved() {
+    let pool = NamePool::new();
+    let early: Vec<&str> = (0..100).map(|i| pool.push(&format!("early_{i}"))).collect();
+    let done = AtomicBool::new(false);
+    let barrier = Barrier::new(THREADS + 2);
+
+    let pushed: Vec<Vec<&str>> = thread::scope(|scope| {
+        let searchers: Vec<_> = (0..2)
+            .map(|_| {
+                let (pool, done, barrier, early) = (&pool, &done, &barrier, &early);
+                scope.spawn(move || {
+                    barrier.wait();
+                    loop {
+                        let finished = done.load(Ordering::Acquire);
+                        // Names pushed by now may or may not show up, the early
+                        // ones always do, at their pooled address.
+                        let found = pool
+                            .search_prefix("early_", CancellationToken::noop())
+                            .unwrap();
+                        assert_eq!(found.len(), early.len());
+                        for name in early {
+                            assert!(found.get(name).is_some_and(|f| f.as_ptr() == name.as_ptr()));
+                        }
+                        let found = pool
+                            .search_substr("_name_", CancellationToken::noop())
+                            .unwrap();
+                        assert!(found.iter().all(|name| name.ends_with(".txt")));
+                        if finished {
+                            assert_eq!(found.len(), THREADS * NAMES_PER_THREAD);
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect();
+        let pushers: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let (pool, barrier) = (&pool, &barrier);
+                scope.spawn(move || {
+                    barrier.wait();
+                    (0..NAMES_PER_THREAD)
+                        .map(|i| pool.push(&name(thread, i)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        let pushed = pushers.into_iter().map(|h| h.join().unwrap()).collect();
+        done.store(true, Ordering::Release);
+        for searcher in searchers {
+            searcher.join().unwrap();
+        }
+        pushed
+    });
+
+    assert_eq!(pool.len(), early.len() + THREADS * NAMES_PER_THREAD);
+    for (i, name) in early.iter().enumerate() {
+        assert_eq!(*name, format!("early_{i}"));
+    }
+    for (thread, names) in pushed.iter().enumerate() {
+        for (i, pooled) in names.iter().enumerate() {
+            assert_eq!(*pooled, name(thread, i));
+            assert_eq!(pool.push(pooled).as_ptr(), pooled.as_ptr());
+        }
+    }
+}
@@ -1,45 +1,201 @@
 use crate::{
-    FileNodes, NameIndex, SearchOptions, SearchResultNode, SlabIndex, SlabNode,
-    SlabNodeMetadataCompact, State, ThinSlab,
-    highlight::derive_highlight_terms,
-    persistent::{PersistentStorage, read_cache_from_file, write_cache_to_file},
-    query_preprocessor::expand_query_home_dirs,
+    AccessLog, CacheError, CompactStats, DeletedEntry, FileNodes, FinderTag, MetadataExtras,
+    NameAndParent, NameIndex, NodeSlab, OptionSlabIndex, PathDisplay, QueryHistory, QueryNote,
+    RawNames, SearchError, SearchOptions, SearchResult, SearchResultNode, SegmentMatcher, SetIndex,
+    SlabIndex, SlabNode, SlabNodeMetadataCompact, State, SubnodeOrder, Tags, Tombstones,
+    build_segment_matchers,
+    cursors::{CursorResults, Cursors},
+    event_ids::{AppliedEventIds, EventIdCounts, EventIdStats},
+    first_seen::event_timestamp,
+    highlight::{NameHighlighter, derive_highlight_terms},
+    link_checks::LinkCheck,
+    metadata_budget::MetadataBudget,
+    metadata_errors::{MetadataErrorCounts, MetadataErrors},
+    path_display::{PathDisplayer, display_absolute},
+    persistent::{
+        CacheSnapshot, PersistentStorage, StorageSections, check_event_stream,
+        read_cache_from_file, write_storage_to_file,
+    },
+    query_history::HistoryEntry,
+    query_notes::QueryNotes,
+    query_preprocessor::{expand_query_home_dirs, home_dir},
+    query_variables::expand_variables,
+    renames::{PendingRename, RecentRenames},
+    result_budget::ResultBudget,
 };
 use anyhow::{Context, Result, anyhow};
-use cardinal_sdk::{EventFlag, FsEvent, ScanType, current_event_id};
-use cardinal_syntax::{optimize_query, parse_query};
-use fswalk::{Node, NodeMetadata, WalkData, walk_it};
-use hashbrown::HashSet;
-use namepool::NamePool;
+use cardinal_sdk::{
+    EventFlag, Firmlinks, FsEvent, ScanType, VolumeChange, current_event_id, dev_of_path,
+    event_stream_uuid,
+};
+use cardinal_syntax::{Expr, optimize_query, parse_query_with_options};
+use crossbeam_channel::Receiver;
+use fswalk::{
+    Node, NodeFileType, NodeMetadata, WalkData, WalkProgress, WalkTotals, split_file_name, walk_it,
+};
+use hashbrown::{HashMap, HashSet};
+use namepool::{NamePool, to_nfc};
+use query_segmentation::query_segmentation;
+use rayon::prelude::*;
 use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
 use std::{
-    ffi::OsStr,
+    borrow::Cow,
+    cell::OnceCell,
+    collections::BTreeSet,
+    ffi::{OsStr, OsString},
+    fs,
+    hash::{DefaultHasher, Hasher},
     io::ErrorKind,
+    os::unix::ffi::OsStrExt,
     path::{Path, PathBuf},
-    sync::{LazyLock, atomic::AtomicBool},
+    sync::{
+        Arc, LazyLock, Mutex, MutexGuard, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
     time::Instant,
 };
 use thin_vec::ThinVec;
 use tracing::{debug, info};
 use typed_num::Num;
 
+/// Searches (`search_with_options`, `search_within`, `query_files*`,
+/// `expand_file_nodes`, `export_results`) only need `&self`: lazily fetched
+/// metadata lives in atomics and the query history behind a mutex, so one
+/// cache can serve several searching threads at once. Anything that changes the
+/// tree (FSEvents, rescans) takes `&mut self`; share the cache behind an
+/// `RwLock` to interleave the two.
 pub struct SearchCache {
     pub(crate) file_nodes: FileNodes,
-    last_event_id: u64,
+    pub(crate) last_event_id: u64,
     pub(crate) name_index: NameIndex,
-    ignore_paths: Option<Vec<PathBuf>>,
+    pub(crate) ignore_paths: Option<Vec<PathBuf>>,
+    /// See [`Self::set_firmlinks`].
+    pub(crate) firmlinks: Firmlinks,
     stop: Option<&'static AtomicBool>,
+    /// Written by searches through `&self`.
+    pub(crate) query_history: Mutex<QueryHistory>,
+    /// Written through `&self` like the query history, see [`Self::record_access`].
+    pub(crate) access_log: Mutex<AccessLog>,
+    pub(crate) tombstones: Tombstones,
+    /// Unix time at which FSEvents first reported a node, see [`Self::first_seen`].
+    pub(crate) first_seen: HashMap<SlabIndex, i64>,
+    /// Counts of the walk the tree was built from, `None` when loaded from disk.
+    pub(crate) walk_totals: Option<WalkTotals>,
+    /// Mount points of unmounted volumes whose nodes are kept but hidden.
+    pub(crate) offline_roots: BTreeSet<PathBuf>,
+    /// See [`Self::set_metadata_budget`].
+    pub(crate) metadata_budget: MetadataBudget,
+    /// See [`Self::add_tag`].
+    pub(crate) tags: Tags,
+    /// Fetched by searches through `&self`, see [`Self::finder_tags`].
+    pub(crate) finder_tags: Mutex<HashMap<SlabIndex, Arc<[FinderTag]>>>,
+    /// Symlink targets checked by `is:brokenlink`, see [`Self::is_broken_link`].
+    pub(crate) link_checks: Mutex<HashMap<SlabIndex, LinkCheck>>,
+    /// Allocated sizes, inodes and file flags of the nodes whose metadata was
+    /// fetched, see [`Self::node_metadata`].
+    pub(crate) metadata_extras: Mutex<ExtrasTable>,
+    /// Failed stats, see [`SearchOutcome::metadata_errors`].
+    pub(crate) metadata_errors: MetadataErrors,
+    /// See [`Self::event_id_stats`].
+    pub(crate) event_ids: EventIdStats,
+    /// Which replayed events [`Self::skip_replayed`] can drop.
+    pub(crate) applied_event_ids: AppliedEventIds,
+    /// Nodes FSEvents reported renamed, see [`Self::validate`].
+    pub(crate) recent_renames: RecentRenames,
+    /// See [`Self::set_auto_compact`].
+    pub(crate) auto_compact: Option<f64>,
+    /// Automatic compactions not taken yet, see [`Self::take_compaction`].
+    pub(crate) compaction: Option<CompactStats>,
+    /// See [`Self::set_invariant_checks`].
+    invariant_checks: bool,
+    /// Results kept by [`Self::open_cursor`].
+    pub(crate) cursors: Mutex<Cursors<CursorResults>>,
+    /// See [`Self::tree_changed`].
+    pub(crate) tree_version: u64,
+}
+
+/// Fill in what `query_files*` and cursor pages show of expanded results:
+/// the highlights of their names and their display paths.
+pub(crate) fn decorate_results(
+    nodes: &mut [SearchResultNode],
+    name_highlighter: Option<&NameHighlighter>,
+    path_displayer: &PathDisplayer,
+) {
+    for node in nodes {
+        if let Some(highlighter) = name_highlighter {
+            let name = node.path.file_name().unwrap_or_default();
+            node.highlights = name.to_str().map(|name| highlighter.ranges(name));
+        }
+        node.display_path = path_displayer.display(&node.path);
+    }
 }
 
+/// What [`SearchCache::search_with_options`] found.
 #[derive(Debug, Clone)]
 pub struct SearchOutcome {
-    pub nodes: Option<Vec<SlabIndex>>,
+    /// Matching nodes, in the order asked for by [`SearchOptions::rank`].
+    pub nodes: Vec<SlabIndex>,
+    /// Terms of the query worth highlighting in the matched names.
     pub highlights: Vec<String>,
+    /// Evaluation was cut short by the token or the deadline and `nodes` is
+    /// what was accumulated so far, see [`SearchOptions::strict_cancellation`].
+    /// Nothing is promised about how it relates to the full result: an AND
+    /// chain stopped early holds extra nodes, an OR group misses some, and
+    /// negating or nesting them mixes both. When unset the result is complete,
+    /// even if the token fired once evaluation was over.
+    pub partial: bool,
+    /// Hints on why filters matched less than the user likely expected, empty
+    /// for most queries.
+    pub notes: Vec<QueryNote>,
+    /// Nodes the filters couldn't stat while evaluating, which failed them.
+    /// Permission errors also add [`QueryNote::MetadataPermissionDenied`].
+    pub metadata_errors: MetadataErrorCounts,
+    /// Set when [`SearchOptions::collect_highlights`] asks for the ranges of
+    /// the result names.
+    pub(crate) name_highlighter: Option<NameHighlighter>,
+    /// Shows the result paths as [`SearchOptions::path_display`] asks.
+    pub(crate) path_displayer: PathDisplayer,
 }
 
 impl SearchOutcome {
-    fn new(nodes: Option<Vec<SlabIndex>>, highlights: Vec<String>) -> Self {
-        Self { nodes, highlights }
+    fn new(
+        nodes: Vec<SlabIndex>,
+        highlights: Vec<String>,
+        partial: bool,
+        notes: QueryNotes,
+    ) -> Self {
+        Self {
+            nodes,
+            highlights,
+            partial,
+            notes: notes.into_vec(),
+            metadata_errors: MetadataErrorCounts::default(),
+            name_highlighter: None,
+            path_displayer: PathDisplayer::absolute(),
+        }
+    }
+
+    fn with_name_highlighter(mut self, expr: &Expr, options: SearchOptions) -> Self {
+        self.name_highlighter = options
+            .collect_highlights
+            .then(|| NameHighlighter::new(expr, options));
+        self
+    }
+
+    fn with_metadata_errors(mut self, metadata_errors: MetadataErrorCounts) -> Self {
+        self.metadata_errors = metadata_errors;
+        self
+    }
+
+    fn with_path_displayer(mut self, path_displayer: PathDisplayer) -> Self {
+        self.path_displayer = path_displayer;
+        self
+    }
+
+    /// `path`, one of the result paths, as [`SearchOptions::path_display`]
+    /// asks to show it.
+    pub fn display_path(&self, path: &Path) -> String {
+        self.path_displayer.display(path)
     }
 }
 
@@ -51,46 +207,87 @@ impl std::fmt::Debug for SearchCache {
             .field("slab_root", &self.file_nodes.root())
             .field("slab.len()", &self.file_nodes.len())
             .field("name_index.len()", &self.name_index.len())
+            .field("query_history.len()", &self.query_history().len())
+            .field("tombstones.len()", &self.tombstones.len())
+            .field("first_seen.len()", &self.first_seen.len())
+            .field("tags.len()", &self.tags.len())
+            .field("walk_totals", &self.walk_totals)
+            .field("offline_roots", &self.offline_roots)
             .finish()
     }
 }
 
 impl SearchCache {
     /// The `path` is the root path of the constructed cache and fsevent watch path.
+    ///
+    /// Fails with [`CacheError::EventStreamReset`] when the FSEvents history of
+    /// the volume no longer continues from the stored event id.
     pub fn try_read_persistent_cache(
         path: &Path,
         cache_path: &Path,
         ignore_paths: Option<Vec<PathBuf>>,
         cancel: Option<&'static AtomicBool>,
-    ) -> Result<Self> {
-        read_cache_from_file(cache_path)
-            .and_then(|x| {
-                (x.path == path)
-                    .then_some(())
-                    .ok_or_else(|| {
-                        anyhow!(
-                            "Inconsistent root path: expected: {:?}, actual: {:?}",
-                            path,
-                            &x.path
-                        )
-                    })
-                    .map(|()| x)
-            })
-            .map(
-                |PersistentStorage {
-                     version: _,
-                     path,
-                     slab_root,
-                     slab,
-                     name_index,
-                     last_event_id,
-                 }| {
-                    // name pool construction speed is fast enough that caching it doesn't worth it.
-                    let name_index = NameIndex::construct_name_pool(name_index);
-                    let slab = FileNodes::new(path, slab, slab_root);
-                    Self::new(slab, last_event_id, name_index, ignore_paths, cancel)
-                },
+    ) -> std::result::Result<Self, CacheError> {
+        let PersistentStorage {
+            version: _,
+            path: stored_path,
+            slab_root,
+            slab,
+            name_index,
+            raw_names,
+            last_event_id,
+            query_history,
+            tombstones,
+            event_stream_uuid: stored_uuid,
+            allocated_sizes,
+            links,
+            access_log,
+            first_seen,
+            tags,
+            file_flags,
+        } = read_cache_from_file(cache_path)?;
+        if stored_path != path {
+            return Err(anyhow!(
+                "Inconsistent root path: expected: {:?}, actual: {:?}",
+                path,
+                &stored_path
             )
+            .into());
+        }
+        let current_uuid = dev_of_path(path).ok().and_then(event_stream_uuid);
+        check_event_stream(stored_uuid, last_event_id, current_uuid, current_event_id())?;
+        // name pool construction speed is fast enough that caching it doesn't worth it.
+        let name_index = NameIndex::construct_name_pool(name_index);
+        let slab = FileNodes::new(stored_path, slab, slab_root, raw_names);
+        let mut cache = Self::new(slab, last_event_id, name_index, ignore_paths, cancel);
+        // The watcher resumes at `last_event_id` and replays what the cache may hold.
+        cache.begin_replay();
+        cache.query_history = Mutex::new(query_history);
+        cache.access_log = Mutex::new(access_log);
+        cache.tombstones = tombstones;
+        cache.tags = tags;
+        let mut extras = ExtrasTable::new();
+        for (index, allocated) in allocated_sizes {
+            extras.entry(index).or_default().allocated = Some(allocated);
+        }
+        for (index, inode, nlink) in links {
+            extras.entry(index).or_default().links = Some((inode, nlink));
+        }
+        for (index, flags) in file_flags {
+            extras.entry(index).or_default().file_flags = flags;
+        }
+        extras.retain(|&index, _| {
+            cache
+                .file_nodes
+                .get(index)
+                .is_some_and(|node| node.metadata.is_some())
+        });
+        cache.metadata_extras = Mutex::new(extras);
+        cache.first_seen = first_seen
+            .into_iter()
+            .filter(|&(index, _)| cache.file_nodes.get(index).is_some())
+            .collect();
+        Ok(cache)
     }
 
     /// Get the total number of files and directories in the cache.
@@ -98,23 +295,75 @@ impl SearchCache {
         self.file_nodes.len()
     }
 
+    /// Mount points of volumes unmounted since the tree was built. Their nodes
+    /// stay in the tree, so a remount only rescans what changed, but searches
+    /// leave them out unless [`SearchOptions::include_offline`] is set. Not
+    /// written to the cache file.
+    pub fn offline_roots(&self) -> &BTreeSet<PathBuf> {
+        &self.offline_roots
+    }
+
+    /// File, folder and byte counts of the walk that built the tree. Sizes are
+    /// only known for files whose metadata the walk fetched, see
+    /// [`WalkTotals::unsized_files`]. `None` for caches loaded from disk.
+    pub fn walk_totals(&self) -> Option<WalkTotals> {
+        self.walk_totals
+    }
+
     pub fn walk_fs_with_ignore(path: PathBuf, ignore_paths: Vec<PathBuf>) -> Self {
         let ignore_paths_opt = if ignore_paths.is_empty() {
             None
         } else {
             Some(ignore_paths)
         };
-        Self::walk_fs_with_walk_data(
-            path,
-            &WalkData::new(ignore_paths_opt.clone(), false, None),
-            ignore_paths_opt,
-            None,
-        )
-        .unwrap()
+        let walk_data = WalkData::builder()
+            .ignore_directories(ignore_paths_opt.clone())
+            .build()
+            .expect("walk options without limits are valid");
+        Self::walk_fs_with_walk_data(path, &walk_data, ignore_paths_opt, None).unwrap()
     }
 
+    /// Index `path`, skipping the data volume side of the firmlinks under it
+    /// (see [`Self::firmlink_ignore_paths`]), so `/` doesn't list every user
+    /// file twice. [`Self::walk_fs_with_ignore`] walks what it's told to.
     pub fn walk_fs(path: PathBuf) -> Self {
-        Self::walk_fs_with_walk_data(path, &WalkData::new(None, false, None), None, None).unwrap()
+        Self::walk_fs_with_progress(path, |_| {})
+    }
+
+    /// Like [`Self::walk_fs`], reporting progress about every [`fswalk::PROGRESS_INTERVAL`].
+    pub fn walk_fs_with_progress(
+        path: PathBuf,
+        progress: impl Fn(WalkProgress) + Send + Sync,
+    ) -> Self {
+        let ignore_paths = Self::firmlink_ignore_paths(&path, None);
+        let walk_data = WalkData::builder()
+            .ignore_directories(ignore_paths.clone())
+            .progress(&progress)
+            .build()
+            .expect("walk options without limits are valid");
+        Self::walk_fs_with_walk_data(path, &walk_data, ignore_paths, None).unwrap()
+    }
+
+    /// `ignore_paths` plus the shadows of the system firmlinks a walk of
+    /// `root` would reach twice, `/System/Volumes/Data/Users` next to `/Users`
+    /// and so on. Those are the ones [`Self::handle_fs_events`] then
+    /// reports on the canonical side. Leave them out to index both.
+    pub fn firmlink_ignore_paths(
+        root: &Path,
+        ignore_paths: Option<Vec<PathBuf>>,
+    ) -> Option<Vec<PathBuf>> {
+        let ignore_paths = Firmlinks::system()
+            .within(root)
+            .ignoring_shadows(ignore_paths.unwrap_or_default());
+        (!ignore_paths.is_empty()).then_some(ignore_paths)
+    }
+
+    /// Replace the system firmlink table, read from
+    /// [`cardinal_sdk::FIRMLINKS_TABLE`] by default. Events under the shadow
+    /// of a link the ignore paths skip are applied to its canonical side,
+    /// where the walk put the files.
+    pub fn set_firmlinks(&mut self, firmlinks: Firmlinks) {
+        self.firmlinks = firmlinks;
     }
 
     /// This function is expected to be called with WalkData which metadata is not fetched.
@@ -129,7 +378,7 @@ impl SearchCache {
         fn walkfs_to_slab(
             path: &Path,
             walk_data: &WalkData,
-        ) -> Option<(SlabIndex, ThinSlab<SlabNode>, NameIndex)> {
+        ) -> Option<(SlabIndex, NodeSlab, NameIndex, RawNames, ExtrasTable)> {
             // Build the tree of file names in parallel first (we cannot construct the slab directly
             // because slab nodes reference each other and we prefer to avoid locking).
             let visit_time = Instant::now();
@@ -142,9 +391,18 @@ impl SearchCache {
 
             // Then create the slab.
             let slab_time = Instant::now();
-            let mut slab = ThinSlab::new();
+            let mut slab = NodeSlab::new();
             let mut name_index = NameIndex::default();
-            let slab_root = construct_node_slab_name_index(None, &node, &mut slab, &mut name_index);
+            let mut raw_names = RawNames::new();
+            let mut extras = ExtrasTable::new();
+            let slab_root = construct_node_slab_name_index(
+                None,
+                &node,
+                &mut slab,
+                &mut name_index,
+                &mut raw_names,
+                &mut extras,
+            );
             info!(
                 "Slab & NameIndex construction time: {:?}, slab root: {:?}, slab len: {:?}",
                 slab_time.elapsed(),
@@ -152,23 +410,20 @@ impl SearchCache {
                 slab.len()
             );
 
-            Some((slab_root, slab, name_index))
+            Some((slab_root, slab, name_index, raw_names, extras))
         }
 
         let last_event_id = current_event_id();
-        let (slab_root, slab, name_index) = walkfs_to_slab(&path, walk_data)?;
-        let slab = FileNodes::new(path, slab, slab_root);
+        let (slab_root, slab, name_index, raw_names, extras) = walkfs_to_slab(&path, walk_data)?;
+        let slab = FileNodes::new(path, slab, slab_root, raw_names);
         // metadata cache inits later
-        Some(Self::new(
-            slab,
-            last_event_id,
-            name_index,
-            ignore_paths,
-            cancel,
-        ))
+        let mut cache = Self::new(slab, last_event_id, name_index, ignore_paths, cancel);
+        cache.metadata_extras = Mutex::new(extras);
+        cache.walk_totals = Some(walk_data.totals());
+        Some(cache)
     }
 
-    fn new(
+    pub(crate) fn new(
         slab: FileNodes,
         last_event_id: u64,
         name_index: NameIndex,
@@ -180,7 +435,31 @@ impl SearchCache {
             last_event_id,
             name_index,
             ignore_paths,
+            firmlinks: Firmlinks::system().clone(),
             stop: cancel,
+            query_history: Mutex::default(),
+            access_log: Mutex::default(),
+            tombstones: Tombstones::default(),
+            first_seen: HashMap::new(),
+            walk_totals: None,
+            offline_roots: BTreeSet::new(),
+            metadata_budget: MetadataBudget::default(),
+            tags: Tags::default(),
+            finder_tags: Mutex::default(),
+            link_checks: Mutex::default(),
+            metadata_extras: Mutex::default(),
+            metadata_errors: MetadataErrors::default(),
+            event_ids: EventIdStats {
+                max_seen: last_event_id,
+                ..EventIdStats::default()
+            },
+            applied_event_ids: AppliedEventIds::new(last_event_id),
+            recent_renames: RecentRenames::default(),
+            auto_compact: None,
+            compaction: None,
+            invariant_checks: false,
+            cursors: Mutex::default(),
+            tree_version: 0,
         }
     }
 
@@ -189,25 +468,281 @@ impl SearchCache {
     }
 
     #[cfg(test)]
-    pub fn search(&mut self, line: &str) -> Result<Vec<SlabIndex>> {
+    pub fn search(&self, line: &str) -> SearchResult<Vec<SlabIndex>> {
         self.search_with_options(line, SearchOptions::default(), CancellationToken::noop())
-            .map(|outcome| outcome.nodes.unwrap_or_default())
+            .map(|outcome| outcome.nodes)
     }
 
+    /// Evaluate the query `line`, the main entry point of the crate.
+    ///
+    /// ```no_run
+    /// use search_cache::{SearchCache, SearchOptions};
+    /// use search_cancel::CancellationToken;
+    ///
+    /// let cache = SearchCache::walk_fs("/Users/me/Documents".into());
+    /// let options = SearchOptions::default().with_case_insensitive(true);
+    /// let outcome = cache.search_with_options("report ext:pdf", options, CancellationToken::noop())?;
+    /// for path in cache.node_paths(&outcome.nodes).into_iter().flatten() {
+    ///     println!("{}", path.display());
+    /// }
+    /// # Ok::<(), search_cache::SearchError>(())
+    /// ```
     pub fn search_with_options(
-        &mut self,
+        &self,
         line: &str,
         options: SearchOptions,
         cancellation_token: CancellationToken,
-    ) -> Result<SearchOutcome> {
-        let parsed = parse_query(line).map_err(|err| anyhow!("Failed to parse query: {err}"))?;
+    ) -> SearchResult<SearchOutcome> {
+        let with_variables = expand_variables(line, self.root(), options)?;
+        let parsed = parse_query_with_options(&with_variables, options.parse_options())?;
         let expanded = expand_query_home_dirs(parsed);
         let optimized = optimize_query(expanded);
         let highlights = derive_highlight_terms(&optimized.expr);
+        let errors_before = self.metadata_errors.counts();
         let search_time = Instant::now();
-        let result = self.evaluate_expr(&optimized.expr, options, cancellation_token);
+        let cancellation_token = match options.deadline {
+            Some(deadline) => cancellation_token.with_deadline(search_time + deadline),
+            None => cancellation_token,
+        };
+        let notes = QueryNotes::default();
+        let budget = ResultBudget::new(options.max_intermediate_results);
+        let result = match options.scope_to {
+            Some(scope) => {
+                self.check_scope(scope)?;
+                self.evaluate_scoped(
+                    &optimized.expr,
+                    scope,
+                    options,
+                    cancellation_token,
+                    &notes,
+                    &budget,
+                )
+            }
+            None => self.evaluate_expr(
+                &optimized.expr,
+                options,
+                cancellation_token,
+                &notes,
+                &budget,
+            ),
+        };
         info!("Search time: {:?}", search_time.elapsed());
-        result.map(|nodes| SearchOutcome::new(nodes, highlights))
+        let nodes = self.drop_offline(result?, options);
+        let mut nodes = self.drop_hidden(nodes, &optimized.expr, options);
+        let metadata_errors = self.note_metadata_errors(errors_before, &notes);
+        match options.scorer {
+            Some(scorer) => self.rank_by_score(&mut nodes, scorer, &highlights),
+            None => self.rank(&mut nodes, options.rank),
+        }
+        let partial = notes.was_cut_short();
+        // Cut short searches were likely superseded by a newer query, don't remember them.
+        if !partial && !options.skip_history {
+            self.query_history().record(line);
+        }
+        Ok(SearchOutcome::new(nodes, highlights, partial, notes)
+            .with_metadata_errors(metadata_errors)
+            .with_name_highlighter(&optimized.expr, options)
+            .with_path_displayer(self.path_displayer(&optimized.expr, options)))
+    }
+
+    /// Run `line` over `base` only, typically the results of a previous search
+    /// ("search in results"). Cheaper than searching both queries ANDed, since
+    /// names are only checked for the nodes of `base`. Nodes removed since
+    /// `base` was computed are dropped. The outcome is never partial and the
+    /// query isn't recorded in the history.
+    pub fn search_within(
+        &self,
+        base: &[SlabIndex],
+        line: &str,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<SearchOutcome> {
+        let with_variables = expand_variables(line, self.root(), options)?;
+        let parsed = parse_query_with_options(&with_variables, options.parse_options())?;
+        let expanded = expand_query_home_dirs(parsed);
+        let optimized = optimize_query(expanded);
+        let highlights = derive_highlight_terms(&optimized.expr);
+        let scope = match options.scope_to {
+            Some(scope) => {
+                self.check_scope(scope)?;
+                HashSet::from([scope])
+            }
+            None => HashSet::new(),
+        };
+        let mut ancestors = self.ancestors_in(&scope);
+        let base = base
+            .iter()
+            .copied()
+            .filter(|&index| self.file_nodes.get(index).is_some())
+            .filter(|&index| scope.is_empty() || ancestors.contains(index))
+            .collect();
+        let errors_before = self.metadata_errors.counts();
+        let search_time = Instant::now();
+        let notes = QueryNotes::default();
+        let budget = ResultBudget::new(options.max_intermediate_results);
+        let result = self.evaluate_within(
+            &optimized.expr,
+            base,
+            options,
+            cancellation_token,
+            &notes,
+            &budget,
+        );
+        info!("Search within results time: {:?}", search_time.elapsed());
+        let nodes = self.drop_offline(result?, options);
+        let mut nodes = self.drop_hidden(nodes, &optimized.expr, options);
+        let metadata_errors = self.note_metadata_errors(errors_before, &notes);
+        if cancellation_token.is_cancelled() {
+            return Err(SearchError::Cancelled);
+        }
+        match options.scorer {
+            Some(scorer) => self.rank_by_score(&mut nodes, scorer, &highlights),
+            None => self.rank(&mut nodes, options.rank),
+        }
+        Ok(SearchOutcome::new(nodes, highlights, false, notes)
+            .with_metadata_errors(metadata_errors)
+            .with_name_highlighter(&optimized.expr, options)
+            .with_path_displayer(self.path_displayer(&optimized.expr, options)))
+    }
+
+    /// Stats that failed since `before`, noting the permission errors.
+    fn note_metadata_errors(
+        &self,
+        before: MetadataErrorCounts,
+        notes: &QueryNotes,
+    ) -> MetadataErrorCounts {
+        let errors = self.metadata_errors.counts().since(before);
+        if errors.permission_denied > 0 {
+            notes.push(QueryNote::MetadataPermissionDenied);
+        }
+        errors
+    }
+
+    /// How the paths of a search of `expr` are shown, the scope being the one
+    /// `depth:` counts from.
+    fn path_displayer(&self, expr: &Expr, options: SearchOptions) -> PathDisplayer {
+        let base = match options.path_display {
+            PathDisplay::Absolute => return PathDisplayer::absolute(),
+            PathDisplay::RootRelative => None,
+            PathDisplay::ScopeRelative => self.scope_root(expr, options.scope_to),
+        };
+        let base = base.unwrap_or_else(|| self.file_nodes.root());
+        match self.node_path(base) {
+            Some(path) => PathDisplayer::relative_to(path),
+            None => PathDisplayer::absolute(),
+        }
+    }
+
+    /// Remove the nodes on unmounted volumes unless `options` asks for them.
+    /// A search can only be scoped to a folder that is still in the tree.
+    fn check_scope(&self, scope: SlabIndex) -> SearchResult<()> {
+        match self.file_nodes.get(scope) {
+            Some(node) if node.metadata.file_type_hint() == NodeFileType::Dir => Ok(()),
+            _ => Err(SearchError::InvalidScope(SetIndex::new(0, scope))),
+        }
+    }
+
+    fn drop_offline(&self, mut nodes: Vec<SlabIndex>, options: SearchOptions) -> Vec<SlabIndex> {
+        if options.include_offline || self.offline_roots.is_empty() {
+            return nodes;
+        }
+        let roots: HashSet<SlabIndex> = self
+            .offline_roots
+            .iter()
+            .filter_map(|root| self.node_index_for_raw_path(root))
+            .collect();
+        let mut ancestors = self.ancestors_in(&roots);
+        nodes.retain(|&index| !roots.contains(&index) && !ancestors.contains(index));
+        nodes
+    }
+
+    /// Locks the history, don't hold the guard across a search.
+    pub fn query_history(&self) -> MutexGuard<'_, QueryHistory> {
+        // The history stays consistent even if a recording thread panicked.
+        self.query_history
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Previously searched queries starting with `prefix`, best frecency first.
+    pub fn suggest_queries(&self, prefix: &str, limit: usize) -> Vec<String> {
+        self.query_history().suggest(prefix, limit)
+    }
+
+    pub fn tombstones(&self) -> &Tombstones {
+        &self.tombstones
+    }
+
+    /// Keep fetched metadata on at most `max_entries` nodes. Beyond that the
+    /// least recently used reverts to `None` and is stat'ed again when a search
+    /// needs it. `None`, the default, keeps all of it.
+    pub fn set_metadata_budget(&mut self, max_entries: Option<usize>) {
+        self.metadata_budget
+            .set_max_entries(max_entries, &self.file_nodes, &self.metadata_extras);
+    }
+
+    pub fn metadata_budget(&self) -> Option<usize> {
+        self.metadata_budget.max_entries()
+    }
+
+    /// Number of nodes whose metadata the budget evicted so far.
+    pub fn metadata_evictions(&self) -> usize {
+        self.metadata_budget.evicted()
+    }
+
+    /// Exempt the metadata of `nodes` from eviction until it's unpinned, e.g.
+    /// while they are on screen. Pins are dropped by full rescans.
+    pub fn pin_metadata(&mut self, nodes: &[SlabIndex]) {
+        for &node in nodes {
+            self.metadata_budget.pin(node);
+        }
+    }
+
+    pub fn unpin_metadata(&mut self, nodes: &[SlabIndex]) {
+        for &node in nodes {
+            self.metadata_budget.unpin(node);
+        }
+    }
+
+    /// How many deleted entries [`Self::search_deleted`] can find, see [`crate::TOMBSTONE_CAPACITY`].
+    pub fn set_tombstone_capacity(&mut self, capacity: usize) {
+        self.tombstones.set_capacity(capacity);
+    }
+
+    /// Recently deleted entries whose names match every whitespace separated term of `query`,
+    /// most recent first. Terms are matched like name terms of a regular search, including
+    /// `/` separated path segments; an empty query returns every tombstone.
+    pub fn search_deleted(
+        &self,
+        query: &str,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<Vec<DeletedEntry>> {
+        let terms = query
+            .split_whitespace()
+            .map(|term| {
+                let segments = query_segmentation(term);
+                if segments.is_empty() {
+                    return Err(anyhow!("Unprocessable term: {term:?}").into());
+                }
+                build_segment_matchers(&segments, SearchOptions::default())
+                    .map_err(|err| SearchError::from(anyhow!("Invalid regex pattern: {err}")))
+            })
+            .collect::<SearchResult<Vec<_>>>()?;
+        let mut found = Vec::new();
+        for (i, entry) in self.tombstones.iter().enumerate() {
+            if i % CANCEL_CHECK_INTERVAL == 0 && cancellation_token.is_cancelled() {
+                return Err(SearchError::Cancelled);
+            }
+            let path = entry.path().to_string_lossy();
+            let components: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+            if terms
+                .iter()
+                .all(|matchers| path_tail_matches(&components, matchers))
+            {
+                found.push(entry.clone());
+            }
+        }
+        Ok(found)
     }
 
     /// Get the path of the node in the slab.
@@ -215,6 +750,12 @@ impl SearchCache {
         self.file_nodes.node_path(index)
     }
 
+    /// Paths of many nodes at once, `None` for the ones no longer in the slab.
+    /// Cheaper than [`Self::node_path`] per node when they share ancestors.
+    pub fn node_paths(&self, indexes: &[SlabIndex]) -> Vec<Option<PathBuf>> {
+        self.file_nodes.node_paths(indexes)
+    }
+
     /// Locate the slab index for a path relative to the watch root.
     pub fn node_index_for_relative_path(&self, relative: &Path) -> Option<SlabIndex> {
         let mut current = self.file_nodes.root();
@@ -225,83 +766,284 @@ impl SearchCache {
             let next = self.file_nodes[current]
                 .children
                 .iter()
-                .find_map(|&child| {
-                    let name = self.file_nodes[child].name_and_parent.as_str();
-                    if OsStr::new(name) == segment {
-                        Some(child)
-                    } else {
-                        None
-                    }
-                })?;
+                .find(|&&child| self.file_nodes.node_name(child) == segment)
+                .copied()?;
             current = next;
         }
         Some(current)
     }
 
+    /// Check that no folder in the tree has two children with the same name
+    /// bytes, naming the first such folder otherwise.
+    pub fn verify_integrity(&self) -> Result<()> {
+        let mut stack = vec![self.file_nodes.root()];
+        while let Some(index) = stack.pop() {
+            if let Some(name) = self.duplicate_child(index) {
+                return Err(anyhow!(
+                    "{:?} has more than one child named {name:?}",
+                    self.node_path(index).unwrap_or_default()
+                ));
+            }
+            stack.extend_from_slice(&self.file_nodes[index].children);
+        }
+        Ok(())
+    }
+
+    /// Check that the name index lists every node of the tree once, under its
+    /// name and in path order (see [`NameIndex`]), describing the first
+    /// discrepancy otherwise.
+    pub fn verify_name_index(&self) -> Result<()> {
+        let mut listed = HashSet::new();
+        for (name, indices) in self.name_index.iter() {
+            if indices.is_empty() {
+                return Err(anyhow!("{name:?} is indexed without any node"));
+            }
+            let mut previous: Option<PathBuf> = None;
+            for &index in indices.iter() {
+                let node = self
+                    .file_nodes
+                    .get(index)
+                    .ok_or_else(|| anyhow!("{name:?} lists {index:?}, which isn't in the tree"))?;
+                let node_name = node.name_and_parent.as_str();
+                if node_name != name {
+                    return Err(anyhow!("{name:?} lists {index:?}, named {node_name:?}"));
+                }
+                if !listed.insert(index) {
+                    return Err(anyhow!("{index:?} is listed more than once"));
+                }
+                let path = self
+                    .file_nodes
+                    .node_path(index)
+                    .ok_or_else(|| anyhow!("{name:?} lists {index:?}, which has no path"))?;
+                if previous.as_ref().is_some_and(|previous| *previous >= path) {
+                    return Err(anyhow!("{name:?} isn't in path order at {path:?}"));
+                }
+                previous = Some(path);
+            }
+        }
+        match self
+            .file_nodes
+            .iter()
+            .find(|(index, _)| !listed.contains(index))
+        {
+            Some((index, _)) => Err(anyhow!(
+                "{:?} is missing from the name index",
+                self.node_path(index).unwrap_or_default()
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Check [`Self::verify_integrity`] and [`Self::verify_name_index`] after
+    /// every change of the tree (event batches, rescans, compactions),
+    /// panicking on the first failure. Each check walks the whole tree, so
+    /// this is for tests and debugging.
+    pub fn set_invariant_checks(&mut self, enabled: bool) {
+        self.invariant_checks = enabled;
+    }
+
+    /// See [`Self::set_invariant_checks`].
+    pub(crate) fn check_invariants(&self, after: &str) {
+        if !self.invariant_checks {
+            return;
+        }
+        if let Err(e) = self
+            .verify_integrity()
+            .and_then(|()| self.verify_name_index())
+        {
+            panic!("cache invariants broken after {after}: {e}");
+        }
+    }
+
+    /// A name shared by several children of `parent`, if any.
+    fn duplicate_child(&self, parent: SlabIndex) -> Option<&OsStr> {
+        let mut names = HashSet::new();
+        self.file_nodes[parent]
+            .children
+            .iter()
+            .map(|&child| self.file_nodes.node_name(child))
+            .find(|&name| !names.insert(name))
+    }
+
     /// Locate the slab index for an absolute path when it belongs to the watch root.
     pub fn node_index_for_raw_path(&self, raw_path: &Path) -> Option<SlabIndex> {
         let relative = raw_path.strip_prefix(self.file_nodes.path()).ok()?;
         self.node_index_for_relative_path(relative)
     }
 
-    /// Get all subnode indices of a given node index(including itself).
+    /// Like [`Self::node_index_for_raw_path`], but a component also matches
+    /// names differing only in ASCII case or Unicode normalization, as paths
+    /// from other apps (drag and drop, the pasteboard, Spotlight) may on a
+    /// case-insensitive volume. When several children match, the one named
+    /// exactly like the component wins.
+    pub fn node_index_for_path_ci(&self, path: &Path) -> Option<SlabIndex> {
+        let mut components = path.components();
+        for root_component in self.file_nodes.path().components() {
+            let component = components.next()?;
+            if component != root_component
+                && fold_name(component.as_os_str()) != fold_name(root_component.as_os_str())
+            {
+                return None;
+            }
+        }
+        let mut current = self.file_nodes.root();
+        for segment in components.map(|component| component.as_os_str()) {
+            let children = &self.file_nodes[current].children;
+            let exact = children
+                .iter()
+                .find(|&&child| self.file_nodes.node_name(child) == segment);
+            current = match exact {
+                Some(&child) => child,
+                None => {
+                    let folded = fold_name(segment);
+                    *children
+                        .iter()
+                        .find(|&&child| fold_name(self.file_nodes.node_name(child)) == folded)?
+                }
+            };
+        }
+        Some(current)
+    }
+
+    /// Same as [`Self::node_index_for_raw_path`] but takes the exact path bytes,
+    /// e.g. from [`SearchResultNode::path_bytes`].
+    pub fn node_index_for_path_bytes(&self, path_bytes: &[u8]) -> Option<SlabIndex> {
+        self.node_index_for_raw_path(Path::new(OsStr::from_bytes(path_bytes)))
+    }
+
+    /// Every descendant of `index` in depth-first order, see
+    /// [`SearchCache::subnodes_page`] to fetch them in pages instead.
     pub fn all_subnodes(
         &self,
         index: SlabIndex,
         cancel: CancellationToken,
     ) -> Option<Vec<SlabIndex>> {
-        let mut result = Vec::new();
-        let mut i = 0;
-        self.all_subnodes_recursive(index, &mut result, &mut i, cancel)?;
-        Some(result)
+        self.subnodes_page(index, 0, usize::MAX, SubnodeOrder::DepthFirst, cancel)
+            .map(|page| page.items)
     }
 
-    fn all_subnodes_recursive(
+    /// Groups `indexes` by the file they refer to: hard links of one file end
+    /// up in the same group, every other node in a group of its own. Groups
+    /// are ordered by their first node in `indexes`, nodes no longer in the
+    /// cache are left out.
+    pub fn group_by_inode(&self, indexes: &[SlabIndex]) -> Vec<Vec<SlabIndex>> {
+        let mut groups: Vec<Vec<SlabIndex>> = Vec::new();
+        let mut group_of_inode: HashMap<_, usize> = HashMap::new();
+        for &index in indexes {
+            if self.file_nodes.get(index).is_none() {
+                continue;
+            }
+            // Inodes are only unique within a volume. Only the few nodes with
+            // other links need the device looked up.
+            let key = match self.node_links(index) {
+                Some((inode, nlink)) if nlink > 1 => self
+                    .node_path(index)
+                    .and_then(|path| dev_of_path(&path).ok())
+                    .map(|dev| (dev, inode)),
+                _ => None,
+            };
+            let Some(key) = key else {
+                groups.push(vec![index]);
+                continue;
+            };
+            match group_of_inode.get(&key) {
+                Some(&group) => groups[group].push(index),
+                None => {
+                    group_of_inode.insert(key, groups.len());
+                    groups.push(vec![index]);
+                }
+            }
+        }
+        groups
+    }
+
+    /// Total size of the files below `index`. With `count_hard_links_once` a
+    /// file linked several times below `index` only counts once. `None` if
+    /// cancelled.
+    pub fn folder_size(
         &self,
         index: SlabIndex,
-        out: &mut Vec<SlabIndex>,
-        i: &mut usize,
+        count_hard_links_once: bool,
         cancel: CancellationToken,
-    ) -> Option<()> {
-        for &child in &self.file_nodes[index].children {
-            if *i % CANCEL_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
-                return None;
-            }
-            *i += 1;
-            out.push(child);
-            self.all_subnodes_recursive(child, out, i, cancel)?;
+    ) -> Option<u64> {
+        let mut files = self.all_subnodes(index, cancel)?;
+        files.retain(|&node| self.file_nodes[node].metadata.file_type_hint() == NodeFileType::File);
+        if count_hard_links_once {
+            files = self
+                .group_by_inode(&files)
+                .into_iter()
+                .map(|group| group[0])
+                .collect();
         }
-        Some(())
+        Some(
+            files
+                .into_iter()
+                .filter_map(|node| self.node_size_bytes(node))
+                .sum(),
+        )
     }
 
     fn push_node(&mut self, node: SlabNode) -> SlabIndex {
         let node_name = node.name_and_parent;
         let index = self.file_nodes.insert(node);
+        self.recent_renames.forget(index);
         self.name_index
             .add_index(node_name.as_str(), index, &self.file_nodes);
+        if self.file_nodes[index].metadata.is_some() {
+            self.metadata_budget
+                .fetched(&self.file_nodes, &self.metadata_extras, index);
+        }
         index
     }
 
-    /// Removes a node by path and its children recursively.
-    fn remove_node_path(&mut self, path: &Path) -> Option<SlabIndex> {
+    /// Removes a node by path and its children recursively, leaving tombstones for them.
+    fn remove_node_path(&mut self, path: &Path, event_id: u64) -> Option<SlabIndex> {
         let mut current = self.file_nodes.root();
         for name in path.components().map(|x| x.as_os_str()) {
             if let Some(&index) = self.file_nodes[current]
                 .children
                 .iter()
-                .find(|&&x| self.file_nodes[x].name_and_parent.as_str() == name)
+                .find(|&&x| self.file_nodes.node_name(x) == name)
             {
                 current = index;
             } else {
                 return None;
             }
         }
+        self.bury_subtree(current, event_id);
         self.remove_node(current);
         Some(current)
     }
 
+    /// Record tombstones for `index` and its descendants, at most as many as the buffer keeps.
+    fn bury_subtree(&mut self, index: SlabIndex, event_id: u64) {
+        let mut stack = vec![index];
+        let mut buried = Vec::new();
+        while let Some(current) = stack.pop() {
+            if buried.len() >= self.tombstones.capacity() {
+                break;
+            }
+            let node = &self.file_nodes[current];
+            let metadata = node.metadata.get();
+            let file_type = match metadata.as_ref() {
+                Some(metadata) => metadata.r#type(),
+                None if !node.children.is_empty() => NodeFileType::Dir,
+                None => metadata.file_type_hint(),
+            };
+            stack.extend_from_slice(&node.children);
+            if let Some(path) = self.node_path(current) {
+                buried.push(DeletedEntry::new(&path, file_type, metadata, event_id));
+            }
+        }
+        // Push descendants first so that the removed root ends up most recent.
+        for entry in buried.into_iter().rev() {
+            self.tombstones.push(entry);
+        }
+    }
+
     // Blindly try create node chain, it doesn't check if the path is really exist on disk.
-    fn create_node_chain(&mut self, path: &Path) -> SlabIndex {
+    // Created nodes are stamped as first seen at `now`.
+    fn create_node_chain(&mut self, path: &Path, now: i64) -> SlabIndex {
         let mut current = self.file_nodes.root();
         let mut current_path = self.file_nodes.path().to_path_buf();
         for name in path.components().map(|x| x.as_os_str()) {
@@ -309,7 +1051,7 @@ impl SearchCache {
             current = if let Some(&index) = self.file_nodes[current]
                 .children
                 .iter()
-                .find(|&&x| self.file_nodes[x].name_and_parent.as_str() == name)
+                .find(|&&x| self.file_nodes.node_name(x) == name)
             {
                 index
             } else {
@@ -317,17 +1059,18 @@ impl SearchCache {
                 let metadata = std::fs::symlink_metadata(&current_path)
                     .map(NodeMetadata::from)
                     .ok();
-                let name = NAME_POOL.push(name.to_string_lossy().as_ref());
-                let node = SlabNode::new(
-                    Some(current),
-                    name,
-                    match metadata {
-                        Some(metadata) => SlabNodeMetadataCompact::some(metadata),
-                        None => SlabNodeMetadataCompact::unaccessible(),
-                    },
-                );
-                let index = self.push_node(node);
+                let (name, raw_name) = split_file_name(name);
+                let name = NAME_POOL.push(&name);
+                let metadata = match metadata {
+                    Some(metadata) => SlabNodeMetadataCompact::some(metadata),
+                    None => SlabNodeMetadataCompact::unaccessible(),
+                };
+                let index = self.push_node(SlabNode::new(Some(current), name, metadata));
+                self.set_metadata_extras(index, metadata.extras());
+                self.file_nodes.set_raw_name(index, raw_name);
                 self.file_nodes[current].add_children(index);
+                self.first_seen.insert(index, now);
+                self.tombstones.forget(&current_path);
                 index
             };
         }
@@ -337,33 +1080,47 @@ impl SearchCache {
     // `Self::scan_path_recursive`function returns index of the constructed node(with metadata provided).
     // - If path is not under the watch root, None is returned.
     // - Procedure contains metadata fetching, if metadata fetching failed, None is returned.
-    fn scan_path_recursive(&mut self, raw_path: &Path) -> Option<SlabIndex> {
+    fn scan_path_recursive(&mut self, raw_path: &Path, event_id: u64) -> Option<SlabIndex> {
         // Ensure path is under the watch root
         let Ok(path) = raw_path.strip_prefix(self.file_nodes.path()) else {
             return None;
         };
         if raw_path.symlink_metadata().err().map(|e| e.kind()) == Some(ErrorKind::NotFound) {
-            self.remove_node_path(path);
+            self.remove_node_path(path, event_id);
             return None;
         };
         let parent = path.parent().expect(
             "scan_path_recursive doesn't expected to scan root(should be filtered outside)",
         );
+        let now = event_timestamp(self.file_nodes.path(), event_id);
         // Ensure node of the path parent is existed
-        let parent = self.create_node_chain(parent);
-        // Remove node(if exists) and do a full rescan
-        if let Some(&old_node) = self.file_nodes[parent].children.iter().find(|&&x| {
-            path.file_name() == Some(OsStr::new(self.file_nodes[x].name_and_parent.as_str()))
-        }) {
+        let parent = self.create_node_chain(parent, now);
+        let old_node = self.file_nodes[parent]
+            .children
+            .iter()
+            .find(|&&x| path.file_name() == Some(self.file_nodes.node_name(x)))
+            .copied();
+        // For incremental data, we need metadata
+        let walk_data = WalkData::builder()
+            .ignore_directories(self.ignore_paths.clone())
+            .need_metadata(true)
+            .cancel(self.stop)
+            .build()
+            .expect("walk options without limits are valid");
+        let node = walk_it(raw_path, &walk_data)
+            .map(|node| self.create_node_slab_update_name_index_and_name_pool(Some(parent), &node));
+        if let Some(node) = node {
+            self.stamp_first_seen(old_node, node, now);
+        }
+        // Remove node(if exists), the rescan replaces it
+        if let Some(old_node) = old_node {
             self.remove_node(old_node);
         }
-        // For incremental data, we need metadata
-        let walk_data = WalkData::new(self.ignore_paths.clone(), true, self.stop);
-        walk_it(raw_path, &walk_data).map(|node| {
-            let node = self.create_node_slab_update_name_index_and_name_pool(Some(parent), &node);
+        node.inspect(|&node| {
             // Push the newly created node to the parent's children
             self.file_nodes[parent].add_children(node);
-            node
+            self.forget_existing_tombstones_under(raw_path);
+            debug_assert_eq!(self.duplicate_child(parent), None);
         })
     }
 
@@ -371,33 +1128,52 @@ impl SearchCache {
     // - If path is not under the watch root, None is returned.
     // - Procedure contains metadata fetching, if metadata fetching failed, None is returned.
     #[allow(dead_code)]
-    fn scan_path_nonrecursive(&mut self, raw_path: &Path) -> Option<SlabIndex> {
+    fn scan_path_nonrecursive(&mut self, raw_path: &Path, event_id: u64) -> Option<SlabIndex> {
         // Ensure path is under the watch root
         let Ok(path) = raw_path.strip_prefix(self.file_nodes.path()) else {
             return None;
         };
         if raw_path.symlink_metadata().err().map(|e| e.kind()) == Some(ErrorKind::NotFound) {
-            self.remove_node_path(path);
+            self.remove_node_path(path, event_id);
             return None;
         };
-        Some(self.create_node_chain(path))
+        let now = event_timestamp(self.file_nodes.path(), event_id);
+        Some(self.create_node_chain(path, now))
     }
 
     pub fn walk_data(&self) -> WalkData<'static> {
-        WalkData::new(self.ignore_paths.clone(), false, self.stop)
+        WalkData::builder()
+            .ignore_directories(self.ignore_paths.clone())
+            .cancel(self.stop)
+            .build()
+            .expect("walk options without limits are valid")
+    }
+
+    /// Root path of the indexed tree.
+    pub fn root(&self) -> &Path {
+        self.file_nodes.path()
+    }
+
+    /// Paths skipped by later walks; takes effect on the next rescan.
+    pub fn set_ignore_paths(&mut self, ignore_paths: Option<Vec<PathBuf>>) {
+        self.ignore_paths = ignore_paths;
     }
 
     pub fn rescan_with_walk_data(&mut self, walk_data: &WalkData) -> Option<()> {
-        let Some(new_cache) = Self::walk_fs_with_walk_data(
-            self.file_nodes.path().to_path_buf(),
-            walk_data,
-            self.ignore_paths.clone(),
-            self.stop,
-        ) else {
+        self.rewalk_with_walk_data(self.file_nodes.path().to_path_buf(), walk_data)
+    }
+
+    /// Index `root` from scratch, e.g. when the watch root changed. Query
+    /// history, tombstones and tags are kept. If cancelled, None is returned and the
+    /// cache is left untouched.
+    pub fn rewalk_with_walk_data(&mut self, root: PathBuf, walk_data: &WalkData) -> Option<()> {
+        let Some(new_cache) =
+            Self::walk_fs_with_walk_data(root, walk_data, self.ignore_paths.clone(), self.stop)
+        else {
             info!("Rescan cancelled.");
             return None;
         };
-        *self = new_cache;
+        self.replace_tree(new_cache);
         Some(())
     }
 
@@ -405,24 +1181,237 @@ impl SearchCache {
         // Remove all memory consuming cache early for memory consumption in Self::walk_fs_new.
         let Some(new_cache) = Self::walk_fs_with_walk_data(
             self.file_nodes.path().to_path_buf(),
-            &WalkData::new(self.ignore_paths.clone(), false, self.stop),
+            &self.walk_data(),
             self.ignore_paths.clone(),
             self.stop,
         ) else {
             info!("Rescan cancelled.");
             return;
         };
-        *self = new_cache;
+        self.replace_tree(new_cache);
+    }
+
+    /// Like [`Self::rescan`], but the walk runs on a background thread and the
+    /// tree is left alone until [`Self::finish_rescan`], so searches keep being
+    /// answered from it in the meantime.
+    pub fn rescan_async(&self) -> PendingRescan {
+        self.rewalk_async(self.file_nodes.path().to_path_buf(), None)
+    }
+
+    /// [`Self::rewalk_with_walk_data`] on a background thread, see [`Self::rescan_async`].
+    pub fn rewalk_async(&self, root: PathBuf, progress: Option<RescanProgress>) -> PendingRescan {
+        let (walked_tx, walked) = crossbeam_channel::bounded(1);
+        let ignore_paths = self.ignore_paths.clone();
+        let stop = self.stop;
+        let superseded = Arc::new(AtomicBool::new(false));
+        let walk_superseded = Arc::clone(&superseded);
+        std::thread::Builder::new()
+            .name("search-cache-rescan".to_string())
+            .spawn(move || {
+                let walk_data = WalkData::builder()
+                    .ignore_directories(ignore_paths.clone())
+                    .cancel(stop)
+                    .also_cancel(Some(&walk_superseded));
+                let walk_data = match &progress {
+                    Some(progress) => walk_data.progress(progress.as_ref()),
+                    None => walk_data,
+                }
+                .build()
+                .expect("walk options without limits are valid");
+                let new_cache = Self::walk_fs_with_walk_data(root, &walk_data, ignore_paths, stop);
+                // Nobody is waiting anymore if a newer rescan replaced this one.
+                let _ = walked_tx.send(new_cache);
+            })
+            .expect("failed to spawn rescan thread");
+        PendingRescan {
+            walked,
+            events: Vec::new(),
+            superseded,
+        }
+    }
+
+    /// Swap in the tree `rescan` walked, as received from
+    /// [`PendingRescan::walked`], then replay the events buffered during the
+    /// walk against it. Both happen before the next search sees the cache. A
+    /// cancelled walk (`walked` is `None`) keeps the old tree and applies the
+    /// events to it instead. `Err(HandleFSEError::Rescan)` means the events
+    /// asked for yet another rescan.
+    pub fn finish_rescan(
+        &mut self,
+        mut rescan: PendingRescan,
+        walked: Option<SearchCache>,
+    ) -> Result<(), HandleFSEError> {
+        match walked {
+            Some(new_cache) => self.replace_tree(new_cache),
+            None => info!("Rescan cancelled."),
+        }
+        let events = std::mem::take(&mut rescan.events);
+        if events.is_empty() {
+            return Ok(());
+        }
+        info!(
+            "Replaying {} events buffered during the rescan",
+            events.len()
+        );
+        self.handle_fs_events(events)
+    }
+
+    /// Stop indexing `path`, which another member of a [`crate::CacheSet`]
+    /// indexes now: its subtree leaves the tree without tombstones and later
+    /// walks skip it.
+    pub(crate) fn hand_over(&mut self, path: &Path) {
+        if let Some(index) = self
+            .node_index_for_raw_path(path)
+            .filter(|&index| index != self.file_nodes.root())
+        {
+            self.remove_node(index);
+            self.tree_changed();
+        }
+        self.ignore_paths
+            .get_or_insert_with(Vec::new)
+            .push(path.to_path_buf());
+    }
+
+    /// Swap in the tree of a freshly walked cache; everything else stays. State
+    /// keyed by slab index follows its paths into the new tree (first-seen
+    /// stamps, the access log) or starts over (fetched Finder tags, link checks,
+    /// recent renames, metadata budget pins). Offline roots stay offline unless
+    /// the walk found their volume.
+    fn replace_tree(&mut self, new_cache: Self) {
+        let Self {
+            file_nodes,
+            last_event_id,
+            name_index,
+            walk_totals,
+            metadata_extras,
+            ..
+        } = new_cache;
+        let first_seen: Vec<(PathBuf, i64)> = self
+            .first_seen
+            .drain()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|(index, stamp)| Some((self.node_path(index)?, stamp)))
+            .collect();
+        let accesses: Vec<(PathBuf, HistoryEntry)> = self
+            .access_log
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .drain()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|(index, entry)| Some((self.node_path(index)?, entry)))
+            .collect();
+        self.file_nodes = file_nodes;
+        self.name_index = name_index;
+        self.walk_totals = walk_totals;
+        self.metadata_extras = metadata_extras;
+        self.finder_tags = Mutex::default();
+        self.link_checks = Mutex::default();
+        self.recent_renames = RecentRenames::default();
+        self.compaction = None;
+        self.metadata_budget
+            .reset(&self.file_nodes, &self.metadata_extras);
+        // The walk took the current id, which mustn't take the stored one back
+        // if the clock behind it was set back (inotify ids are wall time).
+        self.update_last_event_id(last_event_id);
+        self.applied_event_ids.reset(last_event_id);
+        self.event_ids.max_seen = self.event_ids.max_seen.max(self.last_event_id);
+        // The walk saw the disk as it is now, there is no history left to replay.
+        self.event_ids.replaying = false;
+        self.tree_changed();
+        for (path, stamp) in first_seen {
+            if let Some(index) = self.node_index_for_raw_path(&path) {
+                self.first_seen.insert(index, stamp);
+            }
+        }
+        let accesses: Vec<(SlabIndex, HistoryEntry)> = accesses
+            .into_iter()
+            .filter_map(|(path, entry)| Some((self.node_index_for_raw_path(&path)?, entry)))
+            .collect();
+        let access_log = self
+            .access_log
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        for (index, entry) in accesses {
+            access_log.restore(index, entry);
+        }
+        // Volumes the walk found again are back online.
+        let remounted: Vec<PathBuf> = self
+            .offline_roots
+            .iter()
+            .filter(|root| self.node_index_for_raw_path(root).is_some())
+            .cloned()
+            .collect();
+        for root in remounted {
+            self.offline_roots.remove(&root);
+        }
+        let root = self.file_nodes.path().to_path_buf();
+        self.forget_existing_tombstones_under(&root);
+        self.check_invariants("a rescan");
+    }
+
+    /// Drop tombstones under `path` whose path is back in the tree.
+    fn forget_existing_tombstones_under(&mut self, path: &Path) {
+        // Tombstones of one folder's children share a single lookup of the folder.
+        let mut folders: HashMap<&Path, Option<SlabIndex>> = HashMap::new();
+        let existing: Vec<PathBuf> = self
+            .tombstones
+            .under(path)
+            .map(DeletedEntry::path)
+            .filter(|&entry_path| {
+                let (Some(folder), Some(name)) = (entry_path.parent(), entry_path.file_name())
+                else {
+                    return self.node_index_for_raw_path(entry_path).is_some();
+                };
+                let folder = *folders
+                    .entry(folder)
+                    .or_insert_with(|| self.node_index_for_raw_path(folder));
+                folder.is_some_and(|folder| {
+                    self.file_nodes[folder]
+                        .children
+                        .iter()
+                        .any(|&child| self.file_nodes.node_name(child) == name)
+                })
+            })
+            .map(Path::to_path_buf)
+            .collect();
+        drop(folders);
+        for entry_path in existing {
+            self.tombstones.forget(&entry_path);
+        }
     }
 
     /// Removes a node and its children recursively by index.
     fn remove_node(&mut self, index: SlabIndex) {
         fn remove_single_node(cache: &mut SearchCache, index: SlabIndex) {
             if let Some(node) = cache.file_nodes.try_remove(index) {
+                cache.file_nodes.set_raw_name(index, None);
                 let removed = cache
                     .name_index
                     .remove_index(node.name_and_parent.as_str(), index);
                 assert!(removed, "inconsistent name index and node");
+                cache.first_seen.remove(&index);
+                cache
+                    .finder_tags
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&index);
+                cache
+                    .link_checks
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&index);
+                cache
+                    .metadata_extras
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .remove(&index);
+                cache
+                    .access_log
+                    .get_mut()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .forget(index);
             }
         }
 
@@ -438,27 +1427,91 @@ impl SearchCache {
     }
 
     pub fn flush_to_file(self, cache_path: &Path) -> Result<()> {
+        self.with_storage(|storage| write_storage_to_file(cache_path, storage))
+            .context("Write cache to file failed.")
+    }
+
+    /// Encode the cache in memory without consuming it; the snapshot is
+    /// compressed and written by [`CacheSnapshot::write_to_file`], e.g. on
+    /// another thread while this one goes on answering searches.
+    pub fn snapshot(&self) -> Result<CacheSnapshot> {
+        self.with_storage(CacheSnapshot::encode)
+    }
+
+    fn with_storage<T>(&self, f: impl FnOnce(&StorageSections<'_, NameIndex>) -> T) -> T {
         let Self {
-            file_nodes: slab,
+            file_nodes,
             last_event_id,
             name_index,
             ignore_paths: _,
+            firmlinks: _,
             stop: _,
+            query_history,
+            access_log,
+            tombstones,
+            first_seen,
+            walk_totals: _,
+            offline_roots: _,
+            metadata_budget: _,
+            tags,
+            finder_tags: _,
+            link_checks: _,
+            metadata_extras,
+            metadata_errors: _,
+            event_ids: _,
+            applied_event_ids: _,
+            recent_renames: _,
+            auto_compact: _,
+            compaction: _,
+            invariant_checks: _,
+            cursors: _,
+            tree_version: _,
         } = self;
-        let (path, slab_root, slab) = slab.into_parts();
-        let name_index = name_index.into_persistent();
-        write_cache_to_file(
-            cache_path,
-            PersistentStorage {
-                version: Num,
-                path,
-                slab_root,
-                slab,
-                name_index,
-                last_event_id,
-            },
-        )
-        .context("Write cache to file failed.")
+        let mut extras: Vec<_> = metadata_extras
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .iter()
+            .filter(|&(&index, _)| file_nodes[index].metadata.is_some())
+            .map(|(&index, &extras)| (index, extras))
+            .collect();
+        extras.sort_unstable_by_key(|&(index, _)| index);
+        let allocated_sizes: Vec<_> = extras
+            .iter()
+            .filter_map(|&(index, extras)| Some((index, extras.allocated?)))
+            .collect();
+        let links: Vec<_> = extras
+            .iter()
+            .filter_map(|&(index, extras)| {
+                let (inode, nlink) = extras.links?;
+                Some((index, inode, nlink))
+            })
+            .collect();
+        let file_flags: Vec<_> = extras
+            .iter()
+            .filter_map(|&(index, extras)| {
+                (extras.file_flags != 0).then_some((index, extras.file_flags))
+            })
+            .collect();
+        let first_seen: Vec<_> = first_seen.iter().map(|(&index, &at)| (index, at)).collect();
+        let path = file_nodes.path();
+        f(&StorageSections {
+            version: Num,
+            last_event_id: *last_event_id,
+            path,
+            slab_root: file_nodes.root(),
+            slab: file_nodes,
+            name_index,
+            raw_names: file_nodes.raw_names(),
+            query_history: &query_history.lock().unwrap_or_else(PoisonError::into_inner),
+            tombstones,
+            event_stream_uuid: dev_of_path(path).ok().and_then(event_stream_uuid),
+            allocated_sizes: &allocated_sizes,
+            links: &links,
+            access_log: &access_log.lock().unwrap_or_else(PoisonError::into_inner),
+            first_seen: &first_seen,
+            tags,
+            file_flags: &file_flags,
+        })
     }
 
     fn update_last_event_id(&mut self, event_id: u64) {
@@ -476,70 +1529,105 @@ impl SearchCache {
 
     /// Note that this function doesn't fetch metadata(even if it's not cahced) for the nodes.
     pub fn query_files(
-        &mut self,
+        &self,
         query: String,
         cancellation_token: CancellationToken,
-    ) -> Result<Option<Vec<SearchResultNode>>> {
+    ) -> SearchResult<Vec<SearchResultNode>> {
         self.query_files_with_options(query, SearchOptions::default(), cancellation_token)
     }
 
     pub fn query_files_with_options(
-        &mut self,
+        &self,
         query: String,
         options: SearchOptions,
         cancellation_token: CancellationToken,
-    ) -> Result<Option<Vec<SearchResultNode>>> {
+    ) -> SearchResult<Vec<SearchResultNode>> {
         self.search_with_options(&query, options, cancellation_token)
             .map(|outcome| {
-                outcome
-                    .nodes
-                    .map(|nodes| self.expand_file_nodes_inner::<false>(&nodes))
+                let mut nodes = self.expand_file_nodes_inner::<false>(&outcome.nodes);
+                decorate_results(
+                    &mut nodes,
+                    outcome.name_highlighter.as_ref(),
+                    &outcome.path_displayer,
+                );
+                nodes
             })
     }
 
     /// Returns a node info vector with the same length as the input nodes.
     /// If the given node is not found, an empty SearchResultNode is returned.
-    pub fn expand_file_nodes(&mut self, nodes: &[SlabIndex]) -> Vec<SearchResultNode> {
+    pub fn expand_file_nodes(&self, nodes: &[SlabIndex]) -> Vec<SearchResultNode> {
         self.expand_file_nodes_inner::<true>(nodes)
     }
 
     fn expand_file_nodes_inner<const FETCH_META: bool>(
-        &mut self,
+        &self,
         nodes: &[SlabIndex],
     ) -> Vec<SearchResultNode> {
+        let home = home_dir().map(PathBuf::from);
         nodes
             .iter()
             .copied()
-            .map(|node_index| {
-                let path = self.node_path(node_index);
+            .zip(self.node_paths(nodes))
+            .map(|(node_index, path)| {
                 let metadata = self
                     .file_nodes
-                    .get_mut(node_index)
+                    .get(node_index)
                     .map(|node| {
                         match (node.metadata.state(), &path) {
                             (State::None, Some(path)) if FETCH_META => {
                                 // try fetching metadata if it's not cached and cache them
+                                #[cfg(test)]
+                                crate::query::count_metadata_stat();
                                 let metadata = match std::fs::symlink_metadata(path) {
                                     Ok(metadata) => SlabNodeMetadataCompact::some(metadata.into()),
                                     Err(_) => SlabNodeMetadataCompact::unaccessible(),
                                 };
-                                node.metadata = metadata;
+                                self.store_metadata(node_index, metadata);
+                                self.metadata_budget.fetched(
+                                    &self.file_nodes,
+                                    &self.metadata_extras,
+                                    node_index,
+                                );
                                 metadata
                             }
-                            _ => node.metadata,
+                            _ => {
+                                node.metadata.touch();
+                                self.node_metadata(node_index)
+                            }
                         }
                     })
                     .unwrap_or_else(SlabNodeMetadataCompact::unaccessible);
+                let tags = path
+                    .as_deref()
+                    .map(|path| self.tags.get(path).map(String::from).collect())
+                    .unwrap_or_default();
+                let path = path.unwrap_or_default();
                 SearchResultNode {
-                    path: path.unwrap_or_default(),
+                    display_path: display_absolute(&path, home.as_deref()),
+                    path,
                     metadata,
+                    source: 0,
+                    tags,
+                    highlights: None,
                 }
             })
             .collect()
     }
 
     pub fn handle_fs_events(&mut self, events: Vec<FsEvent>) -> Result<(), HandleFSEError> {
+        let pending = self.prepare_fs_events(events)?;
+        self.commit_fs_events(pending.fetch());
+        Ok(())
+    }
+
+    /// First step of [`Self::handle_fs_events`]: find the paths to rescan and the indexed
+    /// directories they go into. Only reads the tree, the filesystem is left to
+    /// [`PendingEvents::fetch`].
+    pub fn prepare_fs_events(&self, events: Vec<FsEvent>) -> Result<PendingEvents, HandleFSEError> {
+        let events = self.normalize_firmlinks(events);
         let max_event_id = events.iter().map(|e| e.id).max();
+        let (events, event_ids) = self.skip_replayed(events);
         // If rescan needed, early exit.
         if events.iter().any(|event| {
             if event.flag.contains(EventFlag::HistoryDone) {
@@ -554,23 +1642,464 @@ impl SearchCache {
         }) {
             return Err(HandleFSEError::Rescan);
         }
+        let volume_changes = volume_changes(&events, self.file_nodes.path());
+        let renames = self.pending_renames(&events);
+        let mut scans = Vec::new();
+        let mut deferred = Vec::new();
         for scan_path in scan_paths(events) {
+            let Ok(path) = scan_path.strip_prefix(self.file_nodes.path()) else {
+                continue;
+            };
+            // Whatever happened before the volume went away is reconciled on remount.
+            if volume_changes.iter().any(|(mount_point, change)| {
+                *change == VolumeChange::Unmounted && scan_path.starts_with(mount_point)
+            }) {
+                continue;
+            }
             info!("Scanning path: {scan_path:?}");
-            let folder = self.scan_path_recursive(&scan_path);
+            match path
+                .parent()
+                .and_then(|parent| self.node_index_for_relative_path(parent))
+            {
+                Some(parent) => scans.push(PendingScan {
+                    parent,
+                    path: scan_path,
+                    case_variant: false,
+                }),
+                None => deferred.push(scan_path),
+            }
+        }
+        // Group by directory, keeping event order within each one.
+        scans.sort_by_key(|scan| scan.parent);
+        for group in scans.chunk_by_mut(|a, b| a.parent == b.parent) {
+            self.flag_case_variants(group);
+        }
+        Ok(PendingEvents {
+            scans,
+            deferred,
+            volume_changes,
+            renames,
+            event_id: max_event_id.unwrap_or(self.last_event_id),
+            max_event_id,
+            event_ids,
+            ignore_paths: self.ignore_paths.clone(),
+            stop: self.stop,
+        })
+    }
+
+    /// Move `events` on a skipped firmlink shadow to the canonical side, see
+    /// [`Self::set_firmlinks`].
+    fn normalize_firmlinks(&self, mut events: Vec<FsEvent>) -> Vec<FsEvent> {
+        let firmlinks = self
+            .firmlinks
+            .within(self.file_nodes.path())
+            .skipped_by(self.ignore_paths.as_deref().unwrap_or_default());
+        if firmlinks.is_empty() {
+            return events;
+        }
+        for event in &mut events {
+            if let Cow::Owned(path) = firmlinks.normalize(&event.path) {
+                event.path = path;
+            }
+        }
+        events
+    }
+
+    /// Flag the scans of `group`, all of one parent, whose name differs only
+    /// in case from a child of the parent or from another scan. A case-only
+    /// rename on a case-insensitive volume reports both spellings, which stat
+    /// alike, so the fetch has to look up which one is on disk.
+    fn flag_case_variants(&self, group: &mut [PendingScan]) {
+        let mut by_fold: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (position, scan) in group.iter().enumerate() {
+            if let Some(name) = scan.path.file_name() {
+                by_fold
+                    .entry(case_fold_hash(name))
+                    .or_default()
+                    .push(position);
+            }
+        }
+        let name = |position: usize| group[position].path.file_name().unwrap_or_default();
+        let mut flagged = Vec::new();
+        for positions in by_fold.values() {
+            for (i, &a) in positions.iter().enumerate() {
+                for &b in &positions[i + 1..] {
+                    if differ_only_in_case(name(a), name(b)) {
+                        flagged.extend([a, b]);
+                    }
+                }
+            }
+        }
+        for &child in &self.file_nodes[group[0].parent].children {
+            let child_name = self.file_nodes.node_name(child);
+            if let Some(positions) = by_fold.get(&case_fold_hash(child_name)) {
+                flagged.extend(
+                    positions
+                        .iter()
+                        .filter(|&&position| differ_only_in_case(name(position), child_name)),
+                );
+            }
+        }
+        for position in flagged {
+            group[position].case_variant = true;
+        }
+    }
+
+    /// Give `index` the name of `node`, its freshly walked self after a rename
+    /// that only changed the case of its name, keeping its slab index. The
+    /// children are replaced by the walked ones like for any rescanned folder.
+    fn rename_in_place(&mut self, index: SlabIndex, node: &Node, now: i64) {
+        let old = self.file_nodes[index].name_and_parent;
+        let removed = self.name_index.remove_index(old.as_str(), index);
+        assert!(removed, "inconsistent name index and node");
+        let name = NAME_POOL.push(&node.name);
+        self.file_nodes[index].name_and_parent =
+            NameAndParent::new(name, OptionSlabIndex::from_option(old.parent()));
+        self.file_nodes.set_raw_name(index, node.raw_name.clone());
+        self.name_index.add_index(name, index, &self.file_nodes);
+
+        let old_children = std::mem::take(&mut self.file_nodes[index].children);
+        let old_by_name: HashMap<OsString, SlabIndex> = old_children
+            .iter()
+            .map(|&child| (self.file_nodes.node_name(child).to_owned(), child))
+            .collect();
+        for child in &node.children {
+            let new = self.create_node_slab_update_name_index_and_name_pool(Some(index), child);
+            let old = old_by_name.get(self.file_nodes.node_name(new)).copied();
+            self.stamp_first_seen(old, new, now);
+            self.file_nodes[index].add_children(new);
+        }
+        for child in old_children {
+            self.remove_node(child);
+        }
+    }
+
+    /// Last step of [`Self::handle_fs_events`]: put the fetched nodes into the tree, then scan
+    /// the paths whose parent directory wasn't indexed, creating the missing ancestors.
+    ///
+    /// `fetched` must come from [`Self::prepare_fs_events`] on this cache, with no events
+    /// applied or rescans done in between.
+    pub fn commit_fs_events(&mut self, fetched: FetchedEvents) {
+        let FetchedEvents {
+            scans,
+            deferred,
+            volume_changes,
+            renames,
+            event_id,
+            max_event_id,
+            event_ids,
+        } = fetched;
+        if !scans.is_empty() || !deferred.is_empty() || !volume_changes.is_empty() {
+            self.tree_changed();
+        }
+        for (mount_point, change) in volume_changes {
+            match change {
+                VolumeChange::Unmounted => {
+                    info!("Volume unmounted: {mount_point:?}");
+                    self.offline_roots.insert(mount_point);
+                }
+                VolumeChange::Mounted => {
+                    // The mount point itself is rescanned with the rest of the batch.
+                    info!("Volume mounted: {mount_point:?}");
+                    self.offline_roots
+                        .retain(|root| !root.starts_with(&mount_point));
+                }
+            }
+        }
+        // Best effort: a link's target may change without an event for the link.
+        self.forget_link_checks_under(
+            scans
+                .iter()
+                .map(|scan| scan.path.as_path())
+                .chain(deferred.iter().map(PathBuf::as_path)),
+        );
+        let root = self.file_nodes.path().to_path_buf();
+        let added_at = OnceCell::new();
+        for group in scans.chunk_by(|a, b| a.parent == b.parent) {
+            let parent = group[0].parent;
+            // The child currently holding each scanned name, kept up to date as
+            // the group is applied so a name scanned twice replaces the node
+            // the first scan put in instead of adding a sibling.
+            let mut current: HashMap<&OsStr, SlabIndex> = {
+                let names: HashSet<&OsStr> = group
+                    .iter()
+                    .filter_map(|scan| scan.path.file_name())
+                    .collect();
+                self.file_nodes[parent]
+                    .children
+                    .iter()
+                    .filter_map(|&child| {
+                        names
+                            .get(self.file_nodes.node_name(child))
+                            .map(|&name| (name, child))
+                    })
+                    .collect()
+            };
+            // Both spellings of a case-only rename are fetched as the one on disk.
+            let mut applied = HashSet::new();
+            for scan in group {
+                let name = scan.path.file_name();
+                if let Some(name) = name
+                    && !applied.insert(name)
+                {
+                    continue;
+                }
+                let old_node = name.and_then(|name| current.remove(name));
+                if let (None, Some(name), FetchedNode::Present(Some(node))) =
+                    (old_node, name, &scan.node)
+                    && scan.case_variant
+                    && let Some(renamed) = self.file_nodes[parent]
+                        .children
+                        .iter()
+                        .copied()
+                        .find(|&child| differ_only_in_case(self.file_nodes.node_name(child), name))
+                {
+                    let now = *added_at.get_or_init(|| event_timestamp(&root, event_id));
+                    self.rename_in_place(renamed, node, now);
+                    self.forget_existing_tombstones_under(&scan.path);
+                    // The old spelling may still be scanned, e.g. as removed
+                    // on a case-sensitive volume, it no longer names this node.
+                    current.retain(|_, &mut child| child != renamed);
+                    current.insert(name, renamed);
+                    info!("Node renamed: {renamed:?}");
+                    continue;
+                }
+                match &scan.node {
+                    FetchedNode::Removed => {
+                        if let Some(old_node) = old_node {
+                            self.bury_subtree(old_node, event_id);
+                            self.remove_node(old_node);
+                        }
+                    }
+                    FetchedNode::Present(node) => {
+                        // Replace the node (if exists) with the freshly walked one.
+                        let index = node.as_ref().map(|node| {
+                            self.create_node_slab_update_name_index_and_name_pool(
+                                Some(parent),
+                                node,
+                            )
+                        });
+                        if let Some(index) = index {
+                            let now = *added_at.get_or_init(|| event_timestamp(&root, event_id));
+                            self.stamp_first_seen(old_node, index, now);
+                        }
+                        if let Some(old_node) = old_node {
+                            self.remove_node(old_node);
+                        }
+                        if let Some(index) = index {
+                            self.file_nodes[parent].add_children(index);
+                            self.forget_existing_tombstones_under(&scan.path);
+                            if let Some(name) = name {
+                                current.insert(name, index);
+                            }
+                            info!("Node changed: {index:?}");
+                        }
+                    }
+                }
+            }
+            debug_assert_eq!(self.duplicate_child(parent), None);
+        }
+        // Out-of-order deliveries, e.g. a file reported before the folder holding it.
+        for scan_path in deferred {
+            let folder = self.scan_path_recursive(&scan_path, event_id);
             if folder.is_some() {
                 info!("Node changed: {folder:?}");
             }
         }
+        self.record_renames(renames);
+        self.note_event_ids(event_ids, max_event_id);
         if let Some(max_event_id) = max_event_id {
             self.update_last_event_id(max_event_id);
         }
-        Ok(())
+        self.maybe_auto_compact();
+        self.check_invariants("an event batch");
+    }
+}
+
+/// An FSEvents batch resolved against the tree by [`SearchCache::prepare_fs_events`].
+///
+/// Holds no reference to the cache, so [`Self::fetch`] can run on another thread while the
+/// cache keeps answering searches.
+#[derive(Debug)]
+pub struct PendingEvents {
+    /// Sorted by parent.
+    scans: Vec<PendingScan>,
+    /// Paths whose parent directory isn't indexed (yet).
+    deferred: Vec<PathBuf>,
+    volume_changes: Vec<(PathBuf, VolumeChange)>,
+    renames: Vec<PendingRename>,
+    event_id: u64,
+    max_event_id: Option<u64>,
+    event_ids: EventIdCounts,
+    ignore_paths: Option<Vec<PathBuf>>,
+    stop: Option<&'static AtomicBool>,
+}
+
+#[derive(Debug)]
+struct PendingScan {
+    parent: SlabIndex,
+    path: PathBuf,
+    /// Another scan or child of `parent` is named like this one but for case.
+    case_variant: bool,
+}
+
+impl PendingEvents {
+    /// Number of paths that will be scanned.
+    pub fn len(&self) -> usize {
+        self.scans.len() + self.deferred.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Stat and walk the paths of the batch on the rayon pool.
+    pub fn fetch(self) -> FetchedEvents {
+        let Self {
+            scans,
+            deferred,
+            volume_changes,
+            renames,
+            event_id,
+            max_event_id,
+            event_ids,
+            ignore_paths,
+            stop,
+        } = self;
+        // For incremental data, we need metadata
+        let walk_data = WalkData::builder()
+            .ignore_directories(ignore_paths)
+            .need_metadata(true)
+            .cancel(stop)
+            .build()
+            .expect("walk options without limits are valid");
+        let mut scans: Vec<FetchedScan> = scans
+            .into_par_iter()
+            .map(
+                |PendingScan {
+                     parent,
+                     mut path,
+                     mut case_variant,
+                 }| {
+                    let node = if path.symlink_metadata().err().map(|e| e.kind())
+                        == Some(ErrorKind::NotFound)
+                    {
+                        case_variant = false;
+                        FetchedNode::Removed
+                    } else {
+                        if case_variant {
+                            match spelled_on_disk(&path) {
+                                Some(on_disk) => path = on_disk,
+                                None => case_variant = false,
+                            }
+                        }
+                        FetchedNode::Present(walk_it(&path, &walk_data))
+                    };
+                    FetchedScan {
+                        parent,
+                        path,
+                        case_variant,
+                        node,
+                    }
+                },
+            )
+            .collect();
+        // Case renames go first within their folder: on a case-sensitive volume the
+        // old spelling is scanned as removed, which must not take the node along.
+        scans.sort_by_key(|scan| (scan.parent, !scan.case_variant));
+        FetchedEvents {
+            scans,
+            deferred,
+            volume_changes,
+            renames,
+            event_id,
+            max_event_id,
+            event_ids,
+        }
     }
 }
 
+/// Output of [`PendingEvents::fetch`], applied by [`SearchCache::commit_fs_events`].
+#[derive(Debug)]
+pub struct FetchedEvents {
+    scans: Vec<FetchedScan>,
+    deferred: Vec<PathBuf>,
+    volume_changes: Vec<(PathBuf, VolumeChange)>,
+    renames: Vec<PendingRename>,
+    event_id: u64,
+    max_event_id: Option<u64>,
+    event_ids: EventIdCounts,
+}
+
+#[derive(Debug)]
+struct FetchedScan {
+    parent: SlabIndex,
+    /// Spelled like the entry on disk for case variants found present.
+    path: PathBuf,
+    /// A case variant whose entry on disk is the only one of its name but
+    /// for case, so a child spelled otherwise is that entry under its old
+    /// name.
+    case_variant: bool,
+    node: FetchedNode,
+}
+
+#[derive(Debug)]
+enum FetchedNode {
+    Removed,
+    /// None if the walk was cancelled or the path is ignored.
+    Present(Option<Node>),
+}
+
+/// Whether two names are the same but for ASCII case.
+fn differ_only_in_case(a: &OsStr, b: &OsStr) -> bool {
+    a != b && a.as_bytes().eq_ignore_ascii_case(b.as_bytes())
+}
+
+/// Hash of `name` with ASCII letters lowercased, equal for names that
+/// [`differ_only_in_case`].
+fn case_fold_hash(name: &OsStr) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    for byte in name.as_bytes() {
+        hasher.write_u8(byte.to_ascii_lowercase());
+    }
+    hasher.finish()
+}
+
+/// `path` with its file name spelled like the entry on disk, which may differ
+/// in case on a case-insensitive volume. `None` unless exactly one entry of
+/// the folder has that name but for case.
+fn spelled_on_disk(path: &Path) -> Option<PathBuf> {
+    let (folder, name) = (path.parent()?, path.file_name()?);
+    let mut matches = fs::read_dir(folder)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name())
+        .filter(|entry_name| entry_name.as_bytes().eq_ignore_ascii_case(name.as_bytes()));
+    let on_disk = matches.next()?;
+    matches.next().is_none().then(|| folder.join(on_disk))
+}
+
+/// Whether the last components of a path match `matchers` one to one, the last matcher
+/// matching the file name itself.
+fn path_tail_matches(components: &[&str], matchers: &[SegmentMatcher]) -> bool {
+    if matchers.len() > components.len() {
+        return false;
+    }
+    let tail = &components[components.len() - matchers.len()..];
+    matchers
+        .iter()
+        .zip(tail)
+        .all(|(matcher, component)| matcher.matches_name(component, SearchOptions::default()))
+}
+
 /// Compute the minimal set of paths that must be rescanned for a batch of FsEvents.
 ///
 /// Goals:
+/// Key under which [`SearchCache::node_index_for_path_ci`] compares names.
+fn fold_name(name: &OsStr) -> String {
+    to_nfc(&name.to_string_lossy()).to_ascii_lowercase()
+}
+
 /// 1. Filter out events that do not require incremental rescans (e.g. `ReScan` / `Nop` variants
 ///    such as RootChanged or HistoryDone). Higher-level logic either rebuilds the cache or simply
 ///    updates the event id for those.
@@ -582,8 +2111,8 @@ impl SearchCache {
 /// 4. Return the minimal cover—the smallest set of paths whose rescans still cover every change.
 ///
 /// Usage:
-/// - `SearchCache::handle_fs_events` iterates over the returned paths and calls
-///   `scan_path_recursive` on each of them to avoid redundant rescans of descendants or duplicates.
+/// - `SearchCache::prepare_fs_events` groups the returned paths by parent directory and the
+///   batch rescans each of them once, avoiding redundant rescans of descendants or duplicates.
 /// - High-frequency FSEvents often bubble many changes from the same subtree; merging them here
 ///   significantly reduces IO and metadata fetch work downstream.
 ///
@@ -640,6 +2169,23 @@ fn scan_paths(events: Vec<FsEvent>) -> Vec<PathBuf> {
     selected
 }
 
+/// The last mount or unmount of each volume under `root` in `events`, in the
+/// order those last changes happened.
+fn volume_changes(events: &[FsEvent], root: &Path) -> Vec<(PathBuf, VolumeChange)> {
+    let mut changes: Vec<(PathBuf, VolumeChange)> = Vec::new();
+    for event in events {
+        let Some(change) = event.flag.volume_change() else {
+            continue;
+        };
+        if !event.path.starts_with(root) {
+            continue;
+        }
+        changes.retain(|(mount_point, _)| *mount_point != event.path);
+        changes.push((event.path.clone(), change));
+    }
+    changes
+}
+
 fn path_depth(path: &Path) -> usize {
     path.components().count()
 }
@@ -660,6 +2206,50 @@ fn has_selected_ancestor(path: &Path, selected: &HashSet<PathBuf>) -> bool {
     false
 }
 
+/// Progress callback of [`SearchCache::rewalk_async`], called from the walk's threads.
+pub type RescanProgress = Box<dyn Fn(WalkProgress) + Send + Sync>;
+
+/// A walk running on a background thread while the old tree keeps answering
+/// searches, see [`SearchCache::rescan_async`]. Dropping it, e.g. by starting
+/// a newer rescan in its place, stops the walk.
+pub struct PendingRescan {
+    walked: Receiver<Option<SearchCache>>,
+    /// FSEvents that arrived during the walk, for the new tree.
+    events: Vec<FsEvent>,
+    /// Cancels this walk alone, the cache's stop flag cancels every walk.
+    superseded: Arc<AtomicBool>,
+}
+
+impl Drop for PendingRescan {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl PendingRescan {
+    /// Yields the walked tree once, `None` if the walk was cancelled. Meant for
+    /// a `select!` next to the other channels; hand what it yields to
+    /// [`SearchCache::finish_rescan`].
+    pub fn walked(&self) -> &Receiver<Option<SearchCache>> {
+        &self.walked
+    }
+
+    /// Keep `events` for the new tree instead of applying them to the old one.
+    pub fn buffer_events(&mut self, events: Vec<FsEvent>) {
+        self.events.extend(events);
+    }
+
+    /// Number of events buffered so far.
+    pub fn buffered_events(&self) -> usize {
+        self.events.len()
+    }
+
+    /// Stop the walk, [`Self::walked`] then yields `None` unless it finished already.
+    pub fn cancel(&self) {
+        self.superseded.store(true, Ordering::Relaxed);
+    }
+}
+
 /// Error type for `SearchCache::handle_fs_event`.
 #[derive(Debug)]
 pub enum HandleFSEError {
@@ -671,8 +2261,10 @@ pub enum HandleFSEError {
 fn construct_node_slab_name_index(
     parent: Option<SlabIndex>,
     node: &Node,
-    slab: &mut ThinSlab<SlabNode>,
+    slab: &mut NodeSlab,
     name_index: &mut NameIndex,
+    raw_names: &mut RawNames,
+    extras: &mut ExtrasTable,
 ) -> SlabIndex {
     let metadata = match node.metadata {
         Some(metadata) => SlabNodeMetadataCompact::some(metadata),
@@ -681,6 +2273,12 @@ fn construct_node_slab_name_index(
     let name = NAME_POOL.push(&node.name);
     let slab_node = SlabNode::new(parent, name, metadata);
     let index = slab.insert(slab_node);
+    if let Some(raw_name) = &node.raw_name {
+        raw_names.insert(index, raw_name.clone());
+    }
+    if !metadata.extras().is_empty() {
+        extras.insert(index, metadata.extras());
+    }
     unsafe {
         // SAFETY: fswalk sorts each directory's children by name before we recurse,
         // so this preorder traversal visits nodes in lexicographic path order.
@@ -689,7 +2287,9 @@ fn construct_node_slab_name_index(
     slab[index].children = node
         .children
         .iter()
-        .map(|node| construct_node_slab_name_index(Some(index), node, slab, name_index))
+        .map(|node| {
+            construct_node_slab_name_index(Some(index), node, slab, name_index, raw_names, extras)
+        })
         .collect();
     index
 }
@@ -712,6 +2312,8 @@ impl SearchCache {
         let name = NAME_POOL.push(&node.name);
         let slab_node = SlabNode::new(parent, name, metadata);
         let index = self.push_node(slab_node);
+        self.set_metadata_extras(index, metadata.extras());
+        self.file_nodes.set_raw_name(index, node.raw_name.clone());
         self.file_nodes[index].children = node
             .children
             .iter()
@@ -721,26 +2323,24 @@ impl SearchCache {
     }
 }
 
+/// [`MetadataExtras`] of the nodes that have any.
+pub(crate) type ExtrasTable = HashMap<SlabIndex, MetadataExtras>;
+
 pub static NAME_POOL: LazyLock<NamePool> = LazyLock::new(NamePool::new);
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::query::CONTENT_BUFFER_BYTES;
+    use crate::{SearchError, query::CONTENT_BUFFER_BYTES};
     use std::{fs, path::PathBuf};
     use tempdir::TempDir;
 
-    fn guard_indices(result: Result<SearchOutcome>) -> Vec<SlabIndex> {
-        result
-            .expect("search should succeed")
-            .nodes
-            .expect("noop cancellation token should not cancel")
+    fn guard_indices(result: SearchResult<SearchOutcome>) -> Vec<SlabIndex> {
+        result.expect("search should succeed").nodes
     }
 
-    fn guard_nodes(result: Result<Option<Vec<SearchResultNode>>>) -> Vec<SearchResultNode> {
-        result
-            .expect("query should succeed")
-            .expect("noop cancellation token should not cancel")
+    fn guard_nodes(result: SearchResult<Vec<SearchResultNode>>) -> Vec<SearchResultNode> {
+        result.expect("query should succeed")
     }
 
     fn query(cache: &mut SearchCache, query: impl Into<String>) -> Vec<SearchResultNode> {
@@ -751,6 +2351,7 @@ mod tests {
         Node {
             children,
             name: name.into(),
+            raw_name: None,
             metadata: None,
         }
     }
@@ -759,7 +2360,7 @@ mod tests {
         make_node(name, vec![])
     }
 
-    fn push_child(slab: &mut ThinSlab<SlabNode>, parent: SlabIndex, name: &str) -> SlabIndex {
+    fn push_child(slab: &mut NodeSlab, parent: SlabIndex, name: &str) -> SlabIndex {
         let idx = slab.insert(SlabNode::new(
             Some(parent),
             NAME_POOL.push(name),
@@ -770,7 +2371,7 @@ mod tests {
     }
 
     fn manual_target_tree_file_nodes() -> (FileNodes, [SlabIndex; 3]) {
-        let mut slab = ThinSlab::new();
+        let mut slab = NodeSlab::new();
         let root_idx = slab.insert(SlabNode::new(
             None,
             NAME_POOL.push("root"),
@@ -781,7 +2382,12 @@ mod tests {
         let root_target = push_child(&mut slab, root_idx, "target.txt");
         let alpha_target = push_child(&mut slab, alpha, "target.txt");
         let beta_target = push_child(&mut slab, beta, "target.txt");
-        let file_nodes = FileNodes::new(PathBuf::from("/virtual/root"), slab, root_idx);
+        let file_nodes = FileNodes::new(
+            PathBuf::from("/virtual/root"),
+            slab,
+            root_idx,
+            RawNames::new(),
+        );
         (file_nodes, [root_target, alpha_target, beta_target])
     }
 
@@ -795,10 +2401,18 @@ mod tests {
                 make_leaf("shared"),
             ],
         );
-        let mut slab = ThinSlab::new();
+        let mut slab = NodeSlab::new();
         let mut name_index = NameIndex::default();
-        let root = construct_node_slab_name_index(None, &tree, &mut slab, &mut name_index);
-        let file_nodes = FileNodes::new(PathBuf::from("/virtual/root"), slab, root);
+        let mut raw_names = RawNames::new();
+        let root = construct_node_slab_name_index(
+            None,
+            &tree,
+            &mut slab,
+            &mut name_index,
+            &mut raw_names,
+            &mut ExtrasTable::new(),
+        );
+        let file_nodes = FileNodes::new(PathBuf::from("/virtual/root"), slab, root, raw_names);
 
         let shared_entries = name_index.get("shared").expect("shared entries");
         assert_eq!(shared_entries.len(), 3);
@@ -836,6 +2450,68 @@ mod tests {
         assert_eq!(paths, sorted, "add_index must maintain lexicographic order");
     }
 
+    #[test]
+    fn test_name_index_add_index_keeps_nodes_sharing_a_path() {
+        let (file_nodes, targets) = manual_target_tree_file_nodes();
+        let mut name_index = NameIndex::default();
+        for &index in &targets {
+            name_index.add_index("target.txt", index, &file_nodes);
+        }
+        // Listed already: nothing changes.
+        name_index.add_index("target.txt", targets[1], &file_nodes);
+        assert_eq!(name_index.get("target.txt").unwrap().len(), 3);
+
+        // A node replacing another is indexed before the old one is removed.
+        let mut file_nodes = file_nodes;
+        let old = targets[1];
+        let parent = file_nodes[old].name_and_parent.parent();
+        let name = NAME_POOL.push("target.txt");
+        let new = file_nodes.insert(SlabNode::new(parent, name, SlabNodeMetadataCompact::none()));
+        name_index.add_index("target.txt", new, &file_nodes);
+        let entries: Vec<_> = name_index
+            .get("target.txt")
+            .unwrap()
+            .iter()
+            .copied()
+            .collect();
+        // By path: alpha/, beta/, then the root's own target.txt.
+        assert_eq!(entries, [old, new, targets[2], targets[0]]);
+        assert!(name_index.remove_index("target.txt", old));
+        let entries: Vec<_> = name_index
+            .get("target.txt")
+            .unwrap()
+            .iter()
+            .copied()
+            .collect();
+        assert_eq!(entries, [new, targets[2], targets[0]]);
+    }
+
+    #[test]
+    fn test_verify_name_index_reports_drift() {
+        let temp_dir = TempDir::new("verify_name_index").unwrap();
+        fs::create_dir(temp_dir.path().join("a")).unwrap();
+        fs::write(temp_dir.path().join("a/index.js"), b"").unwrap();
+        fs::write(temp_dir.path().join("index.js"), b"").unwrap();
+        let mut cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
+        cache.verify_name_index().unwrap();
+
+        let nested = cache
+            .node_index_for_raw_path(&temp_dir.path().join("a/index.js"))
+            .unwrap();
+        assert!(cache.name_index.remove_index("index.js", nested));
+        let error = cache.verify_name_index().unwrap_err().to_string();
+        assert!(error.contains("missing from the name index"), "{error}");
+
+        cache
+            .name_index
+            .add_index("index.js", nested, &cache.file_nodes);
+        cache.verify_name_index().unwrap();
+        let root = cache.file_nodes.root();
+        unsafe { cache.name_index.add_index_ordered("a", root) };
+        let error = cache.verify_name_index().unwrap_err().to_string();
+        assert!(error.contains("named"), "{error}");
+    }
+
     #[test]
     fn test_walk_fs_with_walk_data_preserves_name_index_order() {
         let temp_dir =
@@ -967,7 +2643,7 @@ mod tests {
         fs::File::create(dir.join("foo123.txt")).unwrap();
         fs::File::create(dir.join("bar.txt")).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let indices = cache.search("regex:foo\\d+").unwrap();
         assert_eq!(indices.len(), 1);
         let nodes = cache.expand_file_nodes(&indices);
@@ -987,7 +2663,7 @@ mod tests {
         fs::File::create(dir.join("foo.txt")).unwrap();
         fs::File::create(dir.join("bar.txt")).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let token = CancellationToken::new(10);
         let _ = CancellationToken::new(11); // cancel previous token
 
@@ -995,10 +2671,11 @@ mod tests {
             "bar !foo",
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             token,
         );
-        assert!(matches!(result, Ok(SearchOutcome { nodes: None, .. })));
+        assert!(matches!(result, Err(SearchError::Cancelled)));
     }
 
     #[test]
@@ -1009,9 +2686,10 @@ mod tests {
         fs::File::create(dir.join("Alpha.TXT")).unwrap();
         fs::File::create(dir.join("beta.txt")).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let indices =
             guard_indices(cache.search_with_options("alpha.txt", opts, CancellationToken::noop()));
@@ -1022,6 +2700,7 @@ mod tests {
 
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let miss =
             guard_indices(cache.search_with_options("gamma.txt", opts, CancellationToken::noop()));
@@ -1037,10 +2716,11 @@ mod tests {
         fs::File::create(dir.join("alphaTwo.md")).unwrap();
         fs::File::create(dir.join("beta.txt")).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
 
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let indices =
             guard_indices(cache.search_with_options("alpha*.md", opts, CancellationToken::noop()));
@@ -1050,6 +2730,7 @@ mod tests {
 
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let indices =
             guard_indices(cache.search_with_options("alpha*.md", opts, CancellationToken::noop()));
@@ -1067,9 +2748,10 @@ mod tests {
         fs::write(dir.join("notes.txt"), b"rust memchr finder\nsecond line").unwrap();
         fs::write(dir.join("other.txt"), b"nothing to see here").unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let indices = guard_indices(cache.search_with_options(
             "content:memchr",
@@ -1082,6 +2764,7 @@ mod tests {
 
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let insensitive = guard_indices(cache.search_with_options(
             "content:MEMCHR",
@@ -1103,9 +2786,10 @@ mod tests {
         payload.extend(std::iter::repeat_n(b'a', 32));
         fs::write(dir.join("large.bin"), &payload).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let indices = guard_indices(cache.search_with_options(
             "content:XYZ",
@@ -1125,12 +2809,13 @@ mod tests {
 
         fs::write(dir.join("letters.txt"), b"AaBb").unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
 
         let insensitive = guard_indices(cache.search_with_options(
             "content:a",
             SearchOptions {
                 case_insensitive: true,
+                ..Default::default()
             },
             CancellationToken::noop(),
         ));
@@ -1140,6 +2825,7 @@ mod tests {
             "content:a",
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             CancellationToken::noop(),
         ));
@@ -1150,6 +2836,7 @@ mod tests {
             "content:A",
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             CancellationToken::noop(),
         ));
@@ -1160,6 +2847,7 @@ mod tests {
             "content:z",
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             CancellationToken::noop(),
         ));
@@ -1179,11 +2867,12 @@ mod tests {
         payload.extend(std::iter::repeat_n(b'a', 32));
         fs::write(dir.join("boundary.bin"), &payload).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let indices = guard_indices(cache.search_with_options(
             "content:XYZ",
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             CancellationToken::noop(),
         ));
@@ -1208,12 +2897,13 @@ mod tests {
         payload.extend(std::iter::repeat_n(b'b', 16));
         fs::write(dir.join("long_needle.bin"), &payload).unwrap();
 
-        let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+        let cache = SearchCache::walk_fs(dir.to_path_buf());
         let query = format!("content:{needle}");
         let indices = guard_indices(cache.search_with_options(
             &query,
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             CancellationToken::noop(),
         ));
@@ -1239,10 +2929,10 @@ mod tests {
     }
 
     #[test]
-    fn test_search_with_options_cancelled_returns_none() {
+    fn test_search_with_options_cancelled_returns_cancelled() {
         let temp_dir = TempDir::new("search_with_options_cancelled").unwrap();
         fs::File::create(temp_dir.path().join("file_a.txt")).unwrap();
-        let mut cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
+        let cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
 
         let token = CancellationToken::new(2000);
         let _ = CancellationToken::new(2001);
@@ -1251,23 +2941,24 @@ mod tests {
             "file_a",
             SearchOptions {
                 case_insensitive: false,
+                ..Default::default()
             },
             token,
         );
-        assert!(matches!(result, Ok(SearchOutcome { nodes: None, .. })));
+        assert!(matches!(result, Err(SearchError::Cancelled)));
     }
 
     #[test]
-    fn test_query_files_cancelled_returns_none() {
+    fn test_query_files_cancelled_returns_cancelled() {
         let temp_dir = TempDir::new("query_files_cancelled").unwrap();
         fs::File::create(temp_dir.path().join("item.txt")).unwrap();
-        let mut cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
+        let cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
 
         let token = CancellationToken::new(3000);
         let _ = CancellationToken::new(3001);
 
         let result = cache.query_files("item.txt".to_string(), token);
-        assert!(matches!(result, Ok(None)));
+        assert!(matches!(result, Err(SearchError::Cancelled)));
     }
 
     #[test]
@@ -1566,7 +3257,7 @@ mod tests {
         fs::create_dir(root_path.join("subdir1")).expect("Failed to create subdir1");
         fs::File::create(root_path.join("subdir1/file2.txt")).expect("Failed to create file1.txt");
 
-        let mut cache = SearchCache::walk_fs(root_path.to_path_buf());
+        let cache = SearchCache::walk_fs(root_path.to_path_buf());
 
         // Directory nodes should always carry metadata.
         assert!(cache.file_nodes[cache.file_nodes.root()].metadata.is_some());
@@ -1640,8 +3331,8 @@ mod tests {
             "Expected 1 node for event_file.txt after event"
         );
         let file_node_idx = file_nodes.into_iter().next().unwrap();
-        let file_slab_meta = cache.file_nodes[file_node_idx]
-            .metadata
+        let file_slab_metadata = cache.file_nodes[file_node_idx].metadata.get();
+        let file_slab_meta = file_slab_metadata
             .as_ref()
             .expect("Metadata for event_file.txt should be populated by event handler");
         assert_eq!(
@@ -1681,8 +3372,8 @@ mod tests {
             "Expected 1 node for event_subdir after event"
         );
         let dir_node_idx = dir_nodes.into_iter().next().unwrap();
-        let dir_slab_meta = cache.file_nodes[dir_node_idx]
-            .metadata
+        let dir_slab_metadata = cache.file_nodes[dir_node_idx].metadata.get();
+        let dir_slab_meta = dir_slab_metadata
             .as_ref()
             .expect("Metadata for event_subdir should be populated by event handler");
         assert!(
@@ -1700,8 +3391,8 @@ mod tests {
             "Expected 1 node for file_in_event_subdir.txt after event"
         );
         let file_in_subdir_node_idx = file_in_subdir_nodes.into_iter().next().unwrap();
-        let file_in_subdir_slab_meta = cache.file_nodes[file_in_subdir_node_idx]
-            .metadata
+        let file_in_subdir_slab_metadata = cache.file_nodes[file_in_subdir_node_idx].metadata.get();
+        let file_in_subdir_slab_meta = file_in_subdir_slab_metadata
             .as_ref()
             .expect("Metadata for file_in_event_subdir.txt should be populated");
         assert_eq!(
@@ -1827,7 +3518,7 @@ mod tests {
     #[test]
     fn test_query_files_empty_query_string() {
         let temp_dir = TempDir::new("test_query_files_empty_q").unwrap();
-        let mut cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
+        let cache = SearchCache::walk_fs(temp_dir.path().to_path_buf());
         // Empty queries match everything.
         let result = cache.query_files("".to_string(), CancellationToken::noop());
         assert!(result.is_ok(), "empty query should succeed");
@@ -0,0 +1,694 @@
+//! Several independent caches searched as one, typically one per volume so an
+//! external disk keeps its cache file on the disk itself and unplugging it
+//! leaves the other caches alone.
+
+use crate::{
+    CacheError, CompactStats, CursorId, FolderSizeJob, MetadataErrorCounts, QueryNote, SearchCache,
+    SearchError, SearchOptions, SearchOutcome, SearchResult, SearchResultNode, SlabIndex,
+    SlabNodeMetadataCompact, ValidationState,
+    cursors::{Cursors, page},
+};
+use anyhow::{Result, anyhow, bail};
+use cardinal_sdk::{EventFlag, FsEvent, current_event_id};
+use fswalk::WalkTotals;
+use rayon::prelude::*;
+use search_cancel::CancellationToken;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError, atomic::AtomicBool},
+};
+use tracing::{info, warn};
+
+/// A node of a [`CacheSet`]: the source id of its member in the high 32 bits,
+/// its [`SlabIndex`] in the low 32 bits. Nodes of source 0 keep the number of
+/// their slab index, so a set with a single member is addressed like a lone
+/// cache.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(transparent)]
+pub struct SetIndex(u64);
+
+impl SetIndex {
+    pub fn new(source: usize, index: SlabIndex) -> Self {
+        let source = u32::try_from(source).expect("source id must fit in u32");
+        let index = u32::try_from(index.get()).expect("slab index must fit in u32");
+        Self((u64::from(source) << 32) | u64::from(index))
+    }
+
+    /// Source id of the member the node belongs to.
+    pub fn source(self) -> usize {
+        (self.0 >> 32) as usize
+    }
+
+    pub fn index(self) -> SlabIndex {
+        SlabIndex::from_u32(self.0 as u32)
+    }
+}
+
+/// Merged [`crate::SearchOutcome`]s of the members of a [`CacheSet`].
+#[derive(Debug, Clone, Default)]
+pub struct SetOutcome {
+    /// Each member's results in its own order, members by source id.
+    pub nodes: Vec<SetIndex>,
+    pub highlights: Vec<String>,
+    /// At least one member was cut short, see [`crate::SearchOutcome::partial`].
+    pub partial: bool,
+    /// The notes every searched member reported, so a folder that only one
+    /// member indexes isn't reported as missing by the others.
+    /// [`QueryNote::MetadataPermissionDenied`] is kept if any member has it.
+    pub notes: Vec<QueryNote>,
+    /// The members' [`crate::SearchOutcome::metadata_errors`] added up.
+    pub metadata_errors: MetadataErrorCounts,
+}
+
+struct Member {
+    cache: SearchCache,
+    /// Where [`CacheSet::flush_to_files`] writes this member.
+    cache_path: PathBuf,
+}
+
+/// Caches with their own roots and cache files, searched as one. A member's
+/// source id is its position at insertion and stays the same after other
+/// members are removed.
+///
+/// When a member's root lies inside another member's root, the outer member
+/// hands that subtree over: the nodes leave its tree and its later walks skip
+/// the path, so every file belongs to exactly one member.
+#[derive(Default)]
+pub struct CacheSet {
+    members: Vec<Option<Member>>,
+    /// Results kept by [`Self::open_cursor`].
+    cursors: Mutex<Cursors<SetCursorResults>>,
+}
+
+/// What a [`CacheSet`] cursor keeps of its search.
+#[derive(Debug)]
+struct SetCursorResults {
+    nodes: Vec<SetIndex>,
+    /// The members with their tree versions when the nodes were found.
+    versions: Vec<(usize, u64)>,
+}
+
+impl std::fmt::Debug for CacheSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.members().map(|(source, cache)| (source, cache.root())))
+            .finish()
+    }
+}
+
+impl CacheSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Read every `(root, cache_path)` member from its own cache file. Members
+    /// that fail to load are returned with the error instead, for the caller to
+    /// walk and [`Self::insert`]; the others don't depend on them.
+    pub fn load(
+        members: impl IntoIterator<Item = (PathBuf, PathBuf)>,
+        ignore_paths: Option<Vec<PathBuf>>,
+        cancel: Option<&'static AtomicBool>,
+    ) -> (Self, Vec<(PathBuf, PathBuf, CacheError)>) {
+        let mut set = Self::new();
+        let mut failed = Vec::new();
+        for (root, cache_path) in members {
+            let loaded = SearchCache::try_read_persistent_cache(
+                &root,
+                &cache_path,
+                ignore_paths.clone(),
+                cancel,
+            )
+            .and_then(|cache| Ok(set.insert(cache, cache_path.clone())?));
+            if let Err(err) = loaded {
+                failed.push((root, cache_path, err));
+            }
+        }
+        (set, failed)
+    }
+
+    /// Add `cache`, written to `cache_path` by [`Self::flush_to_files`], and
+    /// return its source id. Fails if a member already has the same root.
+    pub fn insert(&mut self, mut cache: SearchCache, cache_path: PathBuf) -> Result<usize> {
+        let root = cache.root().to_path_buf();
+        if self.members().any(|(_, other)| other.root() == root) {
+            bail!("{root:?} is already in the cache set");
+        }
+        for member in self.members.iter_mut().flatten() {
+            let other = member.cache.root().to_path_buf();
+            if root.starts_with(&other) {
+                member.cache.hand_over(&root);
+            } else if other.starts_with(&root) {
+                cache.hand_over(&other);
+            }
+        }
+        self.members.push(Some(Member { cache, cache_path }));
+        Ok(self.members.len() - 1)
+    }
+
+    /// Take a member out, e.g. when its volume was unplugged, with the path
+    /// it's meant to be flushed to. A member it was nested in doesn't get the
+    /// subtree back before its next rescan with different ignore paths.
+    pub fn remove(&mut self, source: usize) -> Option<(SearchCache, PathBuf)> {
+        let Member { cache, cache_path } = self.members.get_mut(source)?.take()?;
+        Some((cache, cache_path))
+    }
+
+    /// Members with their source ids.
+    pub fn members(&self) -> impl Iterator<Item = (usize, &SearchCache)> {
+        self.members
+            .iter()
+            .enumerate()
+            .filter_map(|(source, member)| Some((source, &member.as_ref()?.cache)))
+    }
+
+    pub fn member(&self, source: usize) -> Option<&SearchCache> {
+        Some(&self.members.get(source)?.as_ref()?.cache)
+    }
+
+    pub fn member_mut(&mut self, source: usize) -> Option<&mut SearchCache> {
+        Some(&mut self.members.get_mut(source)?.as_mut()?.cache)
+    }
+
+    pub fn cache_path(&self, source: usize) -> Option<&Path> {
+        Some(&self.members.get(source)?.as_ref()?.cache_path)
+    }
+
+    pub fn len(&self) -> usize {
+        self.members().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Source id of the member indexing `path`, the one with the longest root
+    /// containing it.
+    pub fn owner(&self, path: &Path) -> Option<usize> {
+        self.members()
+            .filter(|(_, cache)| path.starts_with(cache.root()))
+            .max_by_key(|(_, cache)| cache.root().components().count())
+            .map(|(source, _)| source)
+    }
+
+    /// Oldest event id all members have seen, where a single watcher for every
+    /// root resumes from. Now for an empty set.
+    pub fn last_event_id(&self) -> u64 {
+        self.members()
+            .map(|(_, cache)| cache.last_event_id)
+            .min()
+            .unwrap_or_else(current_event_id)
+    }
+
+    /// Whether a member still awaits the end of the replayed history, see
+    /// [`SearchCache::replaying`].
+    pub fn replaying(&self) -> bool {
+        self.members().any(|(_, cache)| cache.replaying())
+    }
+
+    /// [`SearchCache::begin_replay`] for every member, for a watcher
+    /// restarted at [`Self::last_event_id`].
+    pub fn begin_replay(&mut self) {
+        for member in self.members.iter_mut().flatten() {
+            member.cache.begin_replay();
+        }
+    }
+
+    /// Total number of files and directories of all members.
+    pub fn get_total_files(&self) -> usize {
+        self.members()
+            .map(|(_, cache)| cache.get_total_files())
+            .sum()
+    }
+
+    /// Sum of the members' [`SearchCache::walk_totals`], `None` as soon as one
+    /// of them was loaded from disk.
+    pub fn walk_totals(&self) -> Option<WalkTotals> {
+        self.members()
+            .map(|(_, cache)| cache.walk_totals())
+            .try_fold(WalkTotals::default(), |sum, totals| {
+                let totals = totals?;
+                Some(WalkTotals {
+                    files: sum.files + totals.files,
+                    dirs: sum.dirs + totals.dirs,
+                    bytes: sum.bytes + totals.bytes,
+                    unsized_files: sum.unsized_files + totals.unsized_files,
+                })
+            })
+    }
+
+    /// Run `line` on every member in parallel, or only inside `scope`, a folder
+    /// of one member. `options.scope_to` is replaced by `scope`. The first
+    /// member to fail, by source id, fails the search.
+    pub fn search_with_options(
+        &self,
+        line: &str,
+        scope: Option<SetIndex>,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<SetOutcome> {
+        self.fan_out(scope, options, |_, cache, options| {
+            cache.search_with_options(line, options, cancellation_token)
+        })
+    }
+
+    /// [`SearchCache::search_within`] on the members `base` has nodes of,
+    /// narrowed to `scope` like [`Self::search_with_options`].
+    pub fn search_within(
+        &self,
+        base: &[SetIndex],
+        line: &str,
+        scope: Option<SetIndex>,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<SetOutcome> {
+        self.fan_out(scope, options, |source, cache, options| {
+            let base: Vec<SlabIndex> = base
+                .iter()
+                .filter(|node| node.source() == source)
+                .map(|node| node.index())
+                .collect();
+            cache.search_within(&base, line, options, cancellation_token)
+        })
+    }
+
+    fn fan_out(
+        &self,
+        scope: Option<SetIndex>,
+        options: SearchOptions,
+        search: impl Fn(usize, &SearchCache, SearchOptions) -> SearchResult<SearchOutcome> + Sync,
+    ) -> SearchResult<SetOutcome> {
+        let outcomes: Vec<_> = self
+            .scoped_members(scope, options)?
+            .into_par_iter()
+            .map(|(source, cache, options)| {
+                (
+                    source,
+                    search(source, cache, options).map_err(|e| e.in_source(source)),
+                )
+            })
+            .collect();
+        let mut merged = SetOutcome::default();
+        for (i, (source, outcome)) in outcomes.into_iter().enumerate() {
+            let outcome = outcome?;
+            merged.nodes.extend(
+                outcome
+                    .nodes
+                    .into_iter()
+                    .map(|index| SetIndex::new(source, index)),
+            );
+            // Derived from the query alone, the same for every member.
+            merged.highlights = outcome.highlights;
+            merged.partial |= outcome.partial;
+            merged.metadata_errors.add(outcome.metadata_errors);
+            if i == 0 {
+                merged.notes = outcome.notes;
+            } else {
+                merged.notes.retain(|note| outcome.notes.contains(note));
+            }
+        }
+        if merged.metadata_errors.permission_denied > 0
+            && !merged.notes.contains(&QueryNote::MetadataPermissionDenied)
+        {
+            merged.notes.push(QueryNote::MetadataPermissionDenied);
+        }
+        Ok(merged)
+    }
+
+    /// The members to search and their options: every member, or only the
+    /// one `scope` is a folder of, with `options.scope_to` replaced by `scope`.
+    fn scoped_members(
+        &self,
+        scope: Option<SetIndex>,
+        options: SearchOptions,
+    ) -> SearchResult<Vec<(usize, &SearchCache, SearchOptions)>> {
+        Ok(match scope {
+            Some(folder) => {
+                let cache = self
+                    .member(folder.source())
+                    .ok_or(SearchError::InvalidScope(folder))?;
+                let options = SearchOptions {
+                    scope_to: Some(folder.index()),
+                    ..options
+                };
+                vec![(folder.source(), cache, options)]
+            }
+            None => {
+                let options = SearchOptions {
+                    scope_to: None,
+                    ..options
+                };
+                self.members()
+                    .map(|(source, cache)| (source, cache, options))
+                    .collect()
+            }
+        })
+    }
+
+    /// Results of `line` on every member, tagged with their source id, or only
+    /// inside `scope` like [`Self::search_with_options`]. Like
+    /// [`SearchCache::query_files_with_options`], no metadata is fetched.
+    pub fn query_files_with_options(
+        &self,
+        line: &str,
+        scope: Option<SetIndex>,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<Vec<SearchResultNode>> {
+        let results: Vec<_> = self
+            .scoped_members(scope, options)?
+            .into_par_iter()
+            .map(|(source, cache, options)| {
+                cache
+                    .query_files_with_options(line.to_string(), options, cancellation_token)
+                    .map(|nodes| {
+                        nodes
+                            .into_iter()
+                            .map(|node| SearchResultNode { source, ..node })
+                            .collect::<Vec<_>>()
+                    })
+                    .map_err(|e| e.in_source(source))
+            })
+            .collect();
+        let mut merged = Vec::new();
+        for nodes in results {
+            merged.extend(nodes?);
+        }
+        Ok(merged)
+    }
+
+    /// [`SearchCache::open_cursor`] for the whole set, searched like
+    /// [`Self::search_with_options`]. Pages come from
+    /// [`Self::expand_file_nodes`].
+    pub fn open_cursor(
+        &self,
+        line: &str,
+        scope: Option<SetIndex>,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<CursorId> {
+        let outcome = self.search_with_options(line, scope, options, cancellation_token)?;
+        if outcome.partial {
+            return Err(SearchError::Cancelled);
+        }
+        Ok(self.open_cursor_over(outcome.nodes))
+    }
+
+    /// Keep `nodes`, the results of a search already run, for
+    /// [`Self::cursor_page`].
+    pub fn open_cursor_over(&self, nodes: Vec<SetIndex>) -> CursorId {
+        let bytes = size_of_val(nodes.as_slice());
+        let results = SetCursorResults {
+            nodes,
+            versions: self.tree_versions(),
+        };
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .open(results, bytes)
+    }
+
+    /// [`SearchCache::cursor_page`] for a cursor of the set. Adding or
+    /// removing a member invalidates its cursors too.
+    pub fn cursor_page(
+        &self,
+        cursor: CursorId,
+        offset: usize,
+        limit: usize,
+    ) -> SearchResult<Option<Vec<SearchResultNode>>> {
+        let page = {
+            let mut cursors = self.cursors.lock().unwrap_or_else(PoisonError::into_inner);
+            let Some(results) = cursors.get(cursor) else {
+                return Ok(None);
+            };
+            if results.versions != self.tree_versions() {
+                return Err(SearchError::CursorInvalidated);
+            }
+            page(&results.nodes, offset, limit).to_vec()
+        };
+        Ok(Some(self.expand_file_nodes(&page)))
+    }
+
+    /// How many results the cursor holds, `None` once closed or evicted.
+    pub fn cursor_len(&self, cursor: CursorId) -> Option<usize> {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(cursor)
+            .map(|results| results.nodes.len())
+    }
+
+    /// Forget the results of `cursor`, returning whether it was open.
+    pub fn close_cursor(&self, cursor: CursorId) -> bool {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .close(cursor)
+    }
+
+    /// [`SearchCache::set_cursor_memory_budget`] for the cursors of the set.
+    pub fn set_cursor_memory_budget(&mut self, max_bytes: usize) {
+        self.cursors
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_max_bytes(max_bytes);
+    }
+
+    /// Bytes of results the open cursors of the set keep.
+    pub fn cursor_memory(&self) -> usize {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .bytes()
+    }
+
+    fn tree_versions(&self) -> Vec<(usize, u64)> {
+        self.members()
+            .map(|(source, cache)| (source, cache.tree_version))
+            .collect()
+    }
+
+    /// [`SearchCache::validate`] for nodes of any member, in the same order.
+    /// Nodes of removed members are gone.
+    pub fn validate(&self, nodes: &[SetIndex]) -> Vec<ValidationState> {
+        nodes
+            .iter()
+            .map(|node| {
+                self.member(node.source())
+                    .and_then(|cache| cache.validate(&[node.index()]).pop())
+                    .unwrap_or(ValidationState::Gone)
+            })
+            .collect()
+    }
+
+    /// [`SearchCache::expand_file_nodes`] for nodes of any member, in the
+    /// same order. Nodes of removed members come back empty and unaccessible.
+    pub fn expand_file_nodes(&self, nodes: &[SetIndex]) -> Vec<SearchResultNode> {
+        nodes
+            .iter()
+            .map(|node| {
+                let index = node.index();
+                let expanded = self
+                    .member(node.source())
+                    .and_then(|cache| cache.expand_file_nodes(&[index]).pop());
+                let expanded = expanded.unwrap_or_else(|| SearchResultNode {
+                    path: PathBuf::new(),
+                    metadata: SlabNodeMetadataCompact::unaccessible(),
+                    source: 0,
+                    tags: Vec::new(),
+                    highlights: None,
+                    display_path: String::new(),
+                });
+                SearchResultNode {
+                    source: node.source(),
+                    ..expanded
+                }
+            })
+            .collect()
+    }
+
+    /// Absolute path of `node`.
+    pub fn node_path(&self, node: SetIndex) -> Option<PathBuf> {
+        self.member(node.source())?.node_path(node.index())
+    }
+
+    /// Locate an absolute path in the member that owns it.
+    pub fn node_index_for_raw_path(&self, path: &Path) -> Option<SetIndex> {
+        let source = self.owner(path)?;
+        let index = self.member(source)?.node_index_for_raw_path(path)?;
+        Some(SetIndex::new(source, index))
+    }
+
+    /// Like [`Self::node_index_for_raw_path`] with
+    /// [`SearchCache::node_index_for_path_ci`]'s matching. The member owning the
+    /// path as given is asked first, then the others since the path's casing
+    /// may not match their roots.
+    pub fn node_index_for_path_ci(&self, path: &Path) -> Option<SetIndex> {
+        let owner = self.owner(path);
+        owner
+            .into_iter()
+            .chain(
+                self.members()
+                    .map(|(source, _)| source)
+                    .filter(|&source| Some(source) != owner),
+            )
+            .find_map(|source| {
+                let index = self.member(source)?.node_index_for_path_ci(path)?;
+                Some(SetIndex::new(source, index))
+            })
+    }
+
+    /// See [`SearchCache::record_access`].
+    pub fn record_access(&self, node: SetIndex) {
+        if let Some(cache) = self.member(node.source()) {
+            cache.record_access(node.index());
+        }
+    }
+
+    /// See [`SearchCache::folder_size_job`].
+    pub fn folder_size_job(
+        &self,
+        node: SetIndex,
+        cancel: CancellationToken,
+    ) -> Option<FolderSizeJob> {
+        self.member(node.source())?
+            .folder_size_job(node.index(), cancel)
+    }
+
+    /// See [`SearchCache::add_tag`].
+    pub fn add_tag(&mut self, node: SetIndex, tag: &str) -> Result<bool> {
+        self.member_mut(node.source())
+            .ok_or_else(|| anyhow!("no cache with source id {}", node.source()))?
+            .add_tag(node.index(), tag)
+    }
+
+    /// See [`SearchCache::remove_tag`].
+    pub fn remove_tag(&mut self, node: SetIndex, tag: &str) -> bool {
+        self.member_mut(node.source())
+            .is_some_and(|cache| cache.remove_tag(node.index(), tag))
+    }
+
+    /// [`SearchCache::set_metadata_budget`] for each member on its own.
+    pub fn set_metadata_budget(&mut self, max_entries: Option<usize>) {
+        for member in self.members.iter_mut().flatten() {
+            member.cache.set_metadata_budget(max_entries);
+        }
+    }
+
+    /// [`SearchCache::set_auto_compact`] for each member on its own.
+    pub fn set_auto_compact(&mut self, min_hole_ratio: Option<f64>) {
+        for member in self.members.iter_mut().flatten() {
+            member.cache.set_auto_compact(min_hole_ratio);
+        }
+    }
+
+    /// [`SearchCache::take_compaction`] of each member, by source id. Apply
+    /// their remaps with [`crate::IndexRemap::apply_to_set`].
+    pub fn take_compactions(&mut self) -> Vec<(usize, CompactStats)> {
+        self.members
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(source, member)| {
+                Some((source, member.as_mut()?.cache.take_compaction()?))
+            })
+            .collect()
+    }
+
+    /// See [`SearchCache::pin_metadata`].
+    pub fn pin_metadata(&mut self, nodes: &[SetIndex]) {
+        for &node in nodes {
+            if let Some(cache) = self.member_mut(node.source()) {
+                cache.pin_metadata(&[node.index()]);
+            }
+        }
+    }
+
+    pub fn unpin_metadata(&mut self, nodes: &[SetIndex]) {
+        for &node in nodes {
+            if let Some(cache) = self.member_mut(node.source()) {
+                cache.unpin_metadata(&[node.index()]);
+            }
+        }
+    }
+
+    /// Previously searched queries of all members, deduplicated. Every member
+    /// records the queries it answers, so they mostly agree.
+    pub fn suggest_queries(&self, prefix: &str, limit: usize) -> Vec<String> {
+        let mut suggestions: Vec<String> = Vec::new();
+        for (_, cache) in self.members() {
+            for query in cache.suggest_queries(prefix, limit) {
+                if suggestions.len() < limit && !suggestions.contains(&query) {
+                    suggestions.push(query);
+                }
+            }
+        }
+        suggestions
+    }
+
+    /// Hand every event to the member owning its path, see [`Self::owner`].
+    /// Events outside every root are dropped, but `HistoryDone` ends the
+    /// replay of every member. Returns the source ids of the members whose
+    /// batch asked for a full rescan ([`crate::HandleFSEError::Rescan`]); the
+    /// other members are up to date.
+    pub fn handle_fs_events(&mut self, events: Vec<FsEvent>) -> Vec<usize> {
+        let mut batches: Vec<Vec<FsEvent>> = Vec::new();
+        batches.resize_with(self.members.len(), Vec::new);
+        for event in events {
+            if event.flag.contains(EventFlag::HistoryDone) {
+                for (source, _) in self.members() {
+                    batches[source].push(FsEvent {
+                        path: event.path.clone(),
+                        flag: event.flag,
+                        id: event.id,
+                    });
+                }
+                continue;
+            }
+            match self.owner(&event.path) {
+                Some(source) => batches[source].push(event),
+                None => info!("Event outside the cache set: {:?}", event.path),
+            }
+        }
+        let mut rescans = Vec::new();
+        for (source, batch) in batches.into_iter().enumerate() {
+            if batch.is_empty() {
+                continue;
+            }
+            let cache = self.member_mut(source).expect("owner is a member");
+            if cache.handle_fs_events(batch).is_err() {
+                rescans.push(source);
+            }
+        }
+        rescans
+    }
+
+    /// [`SearchCache::take_vanished`] of every member, to hand to
+    /// [`Self::handle_fs_events`].
+    pub fn take_vanished(&self) -> Vec<FsEvent> {
+        self.members()
+            .flat_map(|(_, cache)| cache.take_vanished())
+            .collect()
+    }
+
+    /// Write every member to its own cache file. A failing member doesn't keep
+    /// the others from being written; the first error is returned once all
+    /// were tried.
+    pub fn flush_to_files(self) -> Result<()> {
+        let mut first_error = None;
+        let mut failed = 0;
+        for Member { cache, cache_path } in self.members.into_iter().flatten() {
+            let root = cache.root().to_path_buf();
+            if let Err(err) = cache.flush_to_file(&cache_path) {
+                warn!("Failed to flush the cache of {root:?} to {cache_path:?}: {err:?}");
+                failed += 1;
+                first_error.get_or_insert(err);
+            }
+        }
+        match first_error {
+            Some(err) => Err(err.context(format!(
+                "{failed} member(s) of the cache set failed to flush"
+            ))),
+            None => Ok(()),
+        }
+    }
+}
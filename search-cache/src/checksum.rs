@@ -0,0 +1,214 @@
+//! Streaming XXH64, used to checksum the persisted cache file. The digests
+//! match the reference implementation, so a cache can be checked with the
+//! `xxhsum -H64` command line tool.
+
+use std::io::{self, Write};
+
+const PRIME_1: u64 = 0x9E37_79B1_85EB_CA87;
+const PRIME_2: u64 = 0xC2B2_AE3D_27D4_EB4F;
+const PRIME_3: u64 = 0x1656_67B1_9E37_79F9;
+const PRIME_4: u64 = 0x85EB_CA77_C2B2_AE63;
+const PRIME_5: u64 = 0x27D4_EB2F_1656_67C5;
+
+const STRIPE_LEN: usize = 32;
+
+pub(crate) struct Xxh64 {
+    seed: u64,
+    acc: [u64; 4],
+    total_len: u64,
+    buffer: [u8; STRIPE_LEN],
+    buffered: usize,
+}
+
+impl Xxh64 {
+    pub(crate) fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            acc: [
+                seed.wrapping_add(PRIME_1).wrapping_add(PRIME_2),
+                seed.wrapping_add(PRIME_2),
+                seed,
+                seed.wrapping_sub(PRIME_1),
+            ],
+            total_len: 0,
+            buffer: [0; STRIPE_LEN],
+            buffered: 0,
+        }
+    }
+
+    pub(crate) fn update(&mut self, mut data: &[u8]) {
+        self.total_len += data.len() as u64;
+        if self.buffered + data.len() < STRIPE_LEN {
+            self.buffer[self.buffered..self.buffered + data.len()].copy_from_slice(data);
+            self.buffered += data.len();
+            return;
+        }
+        if self.buffered > 0 {
+            let (head, rest) = data.split_at(STRIPE_LEN - self.buffered);
+            self.buffer[self.buffered..].copy_from_slice(head);
+            let stripe = self.buffer;
+            self.consume_stripe(&stripe);
+            self.buffered = 0;
+            data = rest;
+        }
+        let mut stripes = data.chunks_exact(STRIPE_LEN);
+        for stripe in &mut stripes {
+            self.consume_stripe(stripe);
+        }
+        let rest = stripes.remainder();
+        self.buffer[..rest.len()].copy_from_slice(rest);
+        self.buffered = rest.len();
+    }
+
+    fn consume_stripe(&mut self, stripe: &[u8]) {
+        for (acc, lane) in self.acc.iter_mut().zip(stripe.chunks_exact(8)) {
+            *acc = round(*acc, read_u64(lane));
+        }
+    }
+
+    pub(crate) fn digest(&self) -> u64 {
+        let mut hash = if self.total_len >= STRIPE_LEN as u64 {
+            let [v1, v2, v3, v4] = self.acc;
+            let mut hash = v1
+                .rotate_left(1)
+                .wrapping_add(v2.rotate_left(7))
+                .wrapping_add(v3.rotate_left(12))
+                .wrapping_add(v4.rotate_left(18));
+            for acc in self.acc {
+                hash = merge_round(hash, acc);
+            }
+            hash
+        } else {
+            self.seed.wrapping_add(PRIME_5)
+        };
+        hash = hash.wrapping_add(self.total_len);
+
+        let mut rest = &self.buffer[..self.buffered];
+        while rest.len() >= 8 {
+            hash ^= round(0, read_u64(rest));
+            hash = hash
+                .rotate_left(27)
+                .wrapping_mul(PRIME_1)
+                .wrapping_add(PRIME_4);
+            rest = &rest[8..];
+        }
+        if rest.len() >= 4 {
+            hash ^=
+                u64::from(u32::from_le_bytes(rest[..4].try_into().unwrap())).wrapping_mul(PRIME_1);
+            hash = hash
+                .rotate_left(23)
+                .wrapping_mul(PRIME_2)
+                .wrapping_add(PRIME_3);
+            rest = &rest[4..];
+        }
+        for &byte in rest {
+            hash ^= u64::from(byte).wrapping_mul(PRIME_5);
+            hash = hash.rotate_left(11).wrapping_mul(PRIME_1);
+        }
+
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(PRIME_2);
+        hash ^= hash >> 29;
+        hash = hash.wrapping_mul(PRIME_3);
+        hash ^= hash >> 32;
+        hash
+    }
+}
+
+fn round(acc: u64, lane: u64) -> u64 {
+    acc.wrapping_add(lane.wrapping_mul(PRIME_2))
+        .rotate_left(31)
+        .wrapping_mul(PRIME_1)
+}
+
+fn merge_round(hash: u64, acc: u64) -> u64 {
+    (hash ^ round(0, acc))
+        .wrapping_mul(PRIME_1)
+        .wrapping_add(PRIME_4)
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    u64::from_le_bytes(bytes[..8].try_into().unwrap())
+}
+
+/// Passes writes through to `inner` while hashing and counting them.
+pub(crate) struct ChecksumWriter<W> {
+    inner: W,
+    hasher: Xxh64,
+    len: u64,
+}
+
+impl<W: Write> ChecksumWriter<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Xxh64::new(0),
+            len: 0,
+        }
+    }
+
+    /// Returns the inner writer with the length and checksum of everything
+    /// written through.
+    pub(crate) fn finish(self) -> (W, u64, u64) {
+        (self.inner, self.len, self.hasher.digest())
+    }
+}
+
+impl<W: Write> Write for ChecksumWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.len += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn xxh64(data: &[u8]) -> u64 {
+        let mut hasher = Xxh64::new(0);
+        hasher.update(data);
+        hasher.digest()
+    }
+
+    #[test]
+    fn matches_reference_digests() {
+        assert_eq!(xxh64(b""), 0xEF46_DB37_51D8_E999);
+        assert_eq!(xxh64(b"a"), 0xD24E_C4F1_A98C_6E5B);
+        assert_eq!(xxh64(b"abc"), 0x44BC_2CF5_AD77_0999);
+        assert_eq!(
+            xxh64(b"Nobody inspects the spammish repetition"),
+            0xFBCE_A83C_8A37_8BF1
+        );
+    }
+
+    #[test]
+    fn chunking_does_not_change_the_digest() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i * 31 % 251) as u8).collect();
+        let expected = xxh64(&data);
+        for chunk_len in [1, 3, 7, 8, 31, 32, 33, 64, 100, 999] {
+            let mut hasher = Xxh64::new(0);
+            for chunk in data.chunks(chunk_len) {
+                hasher.update(chunk);
+            }
+            assert_eq!(hasher.digest(), expected, "chunk length {chunk_len}");
+        }
+    }
+
+    #[test]
+    fn checksum_writer_passes_bytes_through() {
+        let mut writer = ChecksumWriter::new(Vec::new());
+        writer.write_all(b"Nobody inspects ").unwrap();
+        writer.write_all(b"the spammish repetition").unwrap();
+        let (inner, len, checksum) = writer.finish();
+        assert_eq!(inner, b"Nobody inspects the spammish repetition");
+        assert_eq!(len, 39);
+        assert_eq!(checksum, 0xFBCE_A83C_8A37_8BF1);
+    }
+}
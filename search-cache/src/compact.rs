@@ -0,0 +1,184 @@
+use crate::{OptionSlabIndex, SearchCache, SetIndex, SlabIndex};
+use std::{mem, sync::PoisonError};
+use tracing::info;
+
+/// Old to new [`SlabIndex`]es after a [`SearchCache::compact`].
+#[derive(Debug, Clone, Default)]
+pub struct IndexRemap {
+    /// By old index, `none` for the holes.
+    new: Vec<OptionSlabIndex>,
+}
+
+impl IndexRemap {
+    /// Nodes at `order` (ascending, the others being holes) move to `0..`.
+    pub(crate) fn new(slots: usize, order: &[SlabIndex]) -> Self {
+        let mut new = vec![OptionSlabIndex::none(); slots];
+        for (position, index) in order.iter().enumerate() {
+            new[index.get()] = OptionSlabIndex::some(SlabIndex::new(position));
+        }
+        Self { new }
+    }
+
+    /// Where the node that was at `old` is now, `None` if no node was there.
+    pub fn get(&self, old: SlabIndex) -> Option<SlabIndex> {
+        self.new.get(old.get())?.to_option()
+    }
+
+    /// Remap the nodes of member `source` in `nodes` in place, dropping the
+    /// ones that weren't in the slab. Nodes of other members are left alone.
+    pub fn apply_to_set(&self, source: usize, nodes: &mut Vec<SetIndex>) {
+        nodes.retain_mut(|node| {
+            if node.source() != source {
+                return true;
+            }
+            match self.get(node.index()) {
+                Some(index) => {
+                    *node = SetIndex::new(source, index);
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+
+    /// This remap followed by `next`.
+    fn then(self, next: &IndexRemap) -> IndexRemap {
+        let new = self
+            .new
+            .into_iter()
+            .map(|index| {
+                OptionSlabIndex::from_option(index.to_option().and_then(|index| next.get(index)))
+            })
+            .collect();
+        IndexRemap { new }
+    }
+
+    /// Rekey a map by index, dropping the entries of nodes that are gone.
+    pub(crate) fn rekey<V, M>(&self, map: &mut M)
+    where
+        M: Default + IntoIterator<Item = (SlabIndex, V)> + FromIterator<(SlabIndex, V)>,
+    {
+        *map = mem::take(map)
+            .into_iter()
+            .filter_map(|(index, value)| Some((self.get(index)?, value)))
+            .collect();
+    }
+}
+
+/// Outcome of a [`SearchCache::compact`].
+#[derive(Debug, Clone, Default)]
+pub struct CompactStats {
+    /// Slots of the slab before, holes included.
+    pub slots_before: usize,
+    /// Slots after, one per node.
+    pub slots_after: usize,
+    /// Shrinkage of the slab's backing file.
+    pub bytes_reclaimed: usize,
+    pub remap: IndexRemap,
+}
+
+impl CompactStats {
+    pub fn slots_reclaimed(&self) -> usize {
+        self.slots_before - self.slots_after
+    }
+
+    /// Two compactions in a row as one.
+    pub(crate) fn then(self, next: CompactStats) -> CompactStats {
+        CompactStats {
+            slots_before: self.slots_before,
+            slots_after: next.slots_after,
+            bytes_reclaimed: self.bytes_reclaimed + next.bytes_reclaimed,
+            remap: self.remap.then(&next.remap),
+        }
+    }
+}
+
+impl SearchCache {
+    /// Share of the slab's slots that are holes left by removed nodes.
+    pub fn hole_ratio(&self) -> f64 {
+        let slots = self.file_nodes.slots();
+        if slots == 0 {
+            return 0.0;
+        }
+        (slots - self.file_nodes.len()) as f64 / slots as f64
+    }
+
+    /// Rebuild the slab without the holes removed nodes left, giving their
+    /// memory back and sparing iterations from skipping them. Every node gets a
+    /// new [`SlabIndex`]: the returned remap tells where they went, indexes
+    /// held from before are meaningless otherwise. Names stay in the name pool,
+    /// only indexes change.
+    pub fn compact(&mut self) -> CompactStats {
+        let slots_before = self.file_nodes.slots();
+        let bytes_before = self.file_nodes.mapped_bytes();
+        let remap = self.file_nodes.compact();
+        self.name_index.remap(&remap);
+        remap.rekey(&mut self.first_seen);
+        self.access_log
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remap(&remap);
+        remap.rekey(
+            self.finder_tags
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        remap.rekey(
+            self.link_checks
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        remap.rekey(
+            self.metadata_extras
+                .get_mut()
+                .unwrap_or_else(PoisonError::into_inner),
+        );
+        self.metadata_budget.remap(&remap);
+        self.recent_renames.remap(&remap);
+        self.tree_changed();
+        let stats = CompactStats {
+            slots_before,
+            slots_after: self.file_nodes.slots(),
+            bytes_reclaimed: bytes_before.saturating_sub(self.file_nodes.mapped_bytes()),
+            remap,
+        };
+        info!(
+            "Compacted the slab: {} slots and {} bytes reclaimed",
+            stats.slots_reclaimed(),
+            stats.bytes_reclaimed
+        );
+        self.check_invariants("a compaction");
+        stats
+    }
+
+    /// Compact after event batches that leave more than `min_hole_ratio` of
+    /// the slab's slots empty, `None` (the default) never does. Compacting
+    /// renumbers the nodes, collect the remap with [`Self::take_compaction`].
+    pub fn set_auto_compact(&mut self, min_hole_ratio: Option<f64>) {
+        self.auto_compact = min_hole_ratio;
+    }
+
+    pub fn auto_compact(&self) -> Option<f64> {
+        self.auto_compact
+    }
+
+    /// The compactions done automatically since the last call, merged into one.
+    pub fn take_compaction(&mut self) -> Option<CompactStats> {
+        self.compaction.take()
+    }
+
+    /// Run an automatic compaction if the batch just applied left enough holes.
+    pub(crate) fn maybe_auto_compact(&mut self) {
+        let Some(min_hole_ratio) = self.auto_compact else {
+            return;
+        };
+        if self.hole_ratio() <= min_hole_ratio {
+            return;
+        }
+        let stats = self.compact();
+        self.compaction = Some(match self.compaction.take() {
+            Some(earlier) => earlier.then(stats),
+            None => stats,
+        });
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{
+    SearchCache, SearchError, SearchOptions, SearchOutcome, SearchResult, SearchResultNode,
+    SlabIndex, cache::decorate_results, highlight::NameHighlighter, path_display::PathDisplayer,
+};
+use search_cancel::CancellationToken;
+use serde::{Deserialize, Serialize};
+use std::{collections::VecDeque, sync::PoisonError};
+
+/// Open cursors a cache keeps, opening another closes the least recently used.
+pub const CURSOR_CAPACITY: usize = 4;
+
+/// Bytes of results the open cursors of a cache keep by default, see
+/// [`SearchCache::set_cursor_memory_budget`].
+pub const CURSOR_MEMORY_BUDGET: usize = 64 << 20;
+
+/// Names the results kept by [`SearchCache::open_cursor`] or
+/// [`crate::CacheSet::open_cursor`], unique within the cache that opened it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CursorId(u64);
+
+impl CursorId {
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
+/// Results kept for paging, the least recently used first, each with the
+/// bytes its nodes take.
+#[derive(Debug)]
+pub(crate) struct Cursors<T> {
+    open: VecDeque<(CursorId, T, usize)>,
+    next_id: u64,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl<T> Default for Cursors<T> {
+    fn default() -> Self {
+        Self {
+            open: VecDeque::new(),
+            next_id: 0,
+            bytes: 0,
+            max_bytes: CURSOR_MEMORY_BUDGET,
+        }
+    }
+}
+
+impl<T> Cursors<T> {
+    /// Keep `results`, whose nodes take `bytes`, closing the least recently
+    /// used cursors beyond [`CURSOR_CAPACITY`] or the memory budget. The new
+    /// cursor is kept even if it alone is over budget.
+    pub(crate) fn open(&mut self, results: T, bytes: usize) -> CursorId {
+        while self.open.len() == CURSOR_CAPACITY
+            || (!self.open.is_empty() && self.bytes + bytes > self.max_bytes)
+        {
+            self.pop_oldest();
+        }
+        let id = CursorId(self.next_id);
+        self.next_id += 1;
+        self.bytes += bytes;
+        self.open.push_back((id, results, bytes));
+        id
+    }
+
+    /// The results of `id`, now the most recently used, `None` once closed.
+    pub(crate) fn get(&mut self, id: CursorId) -> Option<&T> {
+        let position = self.open.iter().position(|(open, ..)| *open == id)?;
+        let cursor = self.open.remove(position)?;
+        self.open.push_back(cursor);
+        self.open.back().map(|(_, results, _)| results)
+    }
+
+    pub(crate) fn close(&mut self, id: CursorId) -> bool {
+        let Some(position) = self.open.iter().position(|(open, ..)| *open == id) else {
+            return false;
+        };
+        if let Some((_, _, bytes)) = self.open.remove(position) {
+            self.bytes -= bytes;
+        }
+        true
+    }
+
+    /// Close the least recently used cursors until the others fit in
+    /// `max_bytes`, all but the most recent if need be.
+    pub(crate) fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        while self.open.len() > 1 && self.bytes > self.max_bytes {
+            self.pop_oldest();
+        }
+    }
+
+    pub(crate) fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Bytes the nodes of the open cursors take.
+    pub(crate) fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    fn pop_oldest(&mut self) {
+        if let Some((_, _, bytes)) = self.open.pop_front() {
+            self.bytes -= bytes;
+        }
+    }
+}
+
+/// What a [`SearchCache`] cursor keeps of its search.
+#[derive(Debug)]
+pub(crate) struct CursorResults {
+    nodes: Vec<SlabIndex>,
+    name_highlighter: Option<NameHighlighter>,
+    path_displayer: PathDisplayer,
+    /// [`SearchCache::tree_version`] the nodes were found in.
+    tree_version: u64,
+}
+
+/// The `offset..offset + limit` part of `nodes`, clamped to its length.
+pub(crate) fn page<T>(nodes: &[T], offset: usize, limit: usize) -> &[T] {
+    let start = offset.min(nodes.len());
+    let end = start.saturating_add(limit).min(nodes.len());
+    &nodes[start..end]
+}
+
+impl SearchCache {
+    /// Run `line` once and keep its results for [`Self::cursor_page`], so
+    /// showing another page doesn't search again. Cut short searches aren't
+    /// kept, they fail with [`SearchError::Cancelled`].
+    pub fn open_cursor(
+        &self,
+        line: &str,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<CursorId> {
+        let outcome = self.search_with_options(line, options, cancellation_token)?;
+        if outcome.partial {
+            return Err(SearchError::Cancelled);
+        }
+        Ok(self.open_cursor_over(outcome))
+    }
+
+    /// Keep the results of a search already run for [`Self::cursor_page`].
+    pub fn open_cursor_over(&self, outcome: SearchOutcome) -> CursorId {
+        let bytes = size_of_val(outcome.nodes.as_slice());
+        let results = CursorResults {
+            nodes: outcome.nodes,
+            name_highlighter: outcome.name_highlighter,
+            path_displayer: outcome.path_displayer,
+            tree_version: self.tree_version,
+        };
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .open(results, bytes)
+    }
+
+    /// Up to `limit` results of the cursor from `offset` on, expanded like
+    /// [`Self::query_files_with_options`] would but with their metadata,
+    /// fetched for this page only. `None` once the cursor was closed or
+    /// evicted, [`SearchError::CursorInvalidated`] once the tree changed.
+    pub fn cursor_page(
+        &self,
+        cursor: CursorId,
+        offset: usize,
+        limit: usize,
+    ) -> SearchResult<Option<Vec<SearchResultNode>>> {
+        let (page, name_highlighter, path_displayer) = {
+            let mut cursors = self.cursors.lock().unwrap_or_else(PoisonError::into_inner);
+            let Some(results) = cursors.get(cursor) else {
+                return Ok(None);
+            };
+            if results.tree_version != self.tree_version {
+                return Err(SearchError::CursorInvalidated);
+            }
+            (
+                page(&results.nodes, offset, limit).to_vec(),
+                results.name_highlighter.clone(),
+                results.path_displayer.clone(),
+            )
+        };
+        // Metadata is fetched without holding the lock.
+        let mut nodes = self.expand_file_nodes(&page);
+        decorate_results(&mut nodes, name_highlighter.as_ref(), &path_displayer);
+        Ok(Some(nodes))
+    }
+
+    /// How many results the cursor holds, `None` once closed or evicted.
+    pub fn cursor_len(&self, cursor: CursorId) -> Option<usize> {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(cursor)
+            .map(|results| results.nodes.len())
+    }
+
+    /// Forget the results of `cursor`, returning whether it was open.
+    pub fn close_cursor(&self, cursor: CursorId) -> bool {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .close(cursor)
+    }
+
+    /// Keep at most `max_bytes` of results in open cursors, closing the
+    /// least recently used ones beyond. The most recent cursor stays open
+    /// even when its results alone take more.
+    pub fn set_cursor_memory_budget(&mut self, max_bytes: usize) {
+        self.cursors
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_max_bytes(max_bytes);
+    }
+
+    pub fn cursor_memory_budget(&self) -> usize {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .max_bytes()
+    }
+
+    /// Bytes of results the open cursors keep.
+    pub fn cursor_memory(&self) -> usize {
+        self.cursors
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .bytes()
+    }
+
+    /// Bumped by every change to the tree, see [`SearchError::CursorInvalidated`].
+    pub(crate) fn tree_changed(&mut self) {
+        self.tree_version += 1;
+    }
+}
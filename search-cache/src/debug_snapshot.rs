@@ -0,0 +1,276 @@
+use crate::{
+    FileNodes, NAME_POOL, NameIndex, NodeSlab, RawNames, SearchCache, SlabIndex, SlabNode,
+    SlabNodeMetadataCompact, cache::ExtrasTable,
+};
+use anyhow::{Context, Result, anyhow};
+use fswalk::{Node, NodeMetadata};
+use hashbrown::{HashMap, HashSet};
+use serde::{Deserialize, Serialize};
+use std::{
+    hash::{DefaultHasher, Hasher},
+    io::{Read, Write},
+    num::NonZeroU64,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+use typed_num::Num;
+
+const DEBUG_SNAPSHOT_VERSION: i64 = 1;
+
+/// Characters of hashed names. Lowercase only, so case-insensitive searches
+/// can't merge two hashed names the exact ones keep apart.
+const HASH_ALPHABET: &[u8] = b"abcdefghijklmnopqrstuvwxyz0123456789";
+
+/// How [`SearchCache::export_debug_snapshot`] writes names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SnapshotNames {
+    /// As they are, the snapshot then reproduces any query.
+    Plain,
+    /// Replaced by a hash keyed with `key` that keeps the extension, a
+    /// leading dot and the length in characters. Names equal in the tree stay
+    /// equal, so `ext:`, `size:`, date and type filters behave as on the
+    /// original, but name terms only find hashed names.
+    Hashed { key: u64 },
+}
+
+/// A subtree written by [`SearchCache::export_debug_snapshot`]: JSON of the
+/// walk tree with every node's metadata, children sorted by name.
+#[derive(Serialize, Deserialize)]
+struct DebugSnapshot {
+    version: Num<DEBUG_SNAPSHOT_VERSION>,
+    /// Path of the exported folder, hashed like the names if they are.
+    path: PathBuf,
+    hashed_names: bool,
+    root: Node,
+}
+
+impl SearchCache {
+    /// Write the subtree under `scope` to `out` as a self-contained snapshot
+    /// that [`Self::import_debug_snapshot`] turns back into a cache, so a
+    /// search bug can be reproduced without the rest of the disk.
+    ///
+    /// The metadata of every node is fetched first, the snapshot carries the
+    /// sizes and dates that filters read. Access and added dates stat the
+    /// file itself and aren't part of it. The same tree always gives the same
+    /// bytes.
+    pub fn export_debug_snapshot(
+        &self,
+        scope: SlabIndex,
+        names: SnapshotNames,
+        out: &mut dyn Write,
+    ) -> Result<()> {
+        let path = self
+            .node_path(scope)
+            .ok_or_else(|| anyhow!("Snapshot scope {scope:?} is not in the tree"))?;
+        let mut hasher = match names {
+            SnapshotNames::Plain => None,
+            SnapshotNames::Hashed { key } => Some(NameHasher::new(key)),
+        };
+        let path = match &mut hasher {
+            Some(hasher) => hasher.hash_path(&path),
+            None => path,
+        };
+        let mut root = self.snapshot_node(scope, &mut hasher);
+        // The root's name is the last component of its path, hashed there.
+        if hasher.is_some() {
+            root.raw_name = None;
+            root.name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into())
+                .unwrap_or_default();
+        }
+        let snapshot = DebugSnapshot {
+            version: Num,
+            path,
+            hashed_names: hasher.is_some(),
+            root,
+        };
+        serde_json::to_writer(out, &snapshot).context("Failed to write debug snapshot")
+    }
+
+    fn snapshot_node(&self, index: SlabIndex, hasher: &mut Option<NameHasher>) -> Node {
+        self.ensure_metadata(index);
+        let metadata = self.node_metadata(index);
+        let node = &self.file_nodes[index];
+        let mut children: Vec<Node> = node
+            .children
+            .iter()
+            .map(|&child| self.snapshot_node(child, hasher))
+            .collect();
+        if let Some(hasher) = hasher {
+            let mut taken = HashSet::new();
+            for child in &mut children {
+                child.name = hasher.hash_name(&child.name, &mut taken);
+                child.raw_name = None;
+            }
+        }
+        children.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        Node {
+            children,
+            name: node.name_and_parent.as_str().into(),
+            raw_name: self.file_nodes.raw_names().get(&index).cloned(),
+            metadata: metadata.as_ref().map(|metadata| NodeMetadata {
+                r#type: metadata.r#type(),
+                size: metadata.size(),
+                allocated: metadata.allocated().unwrap_or(metadata.size()),
+                inode: metadata.inode().unwrap_or_default(),
+                nlink: metadata.nlink().unwrap_or(1),
+                ctime: metadata.ctime().map(NonZeroU64::from),
+                mtime: metadata.mtime().map(NonZeroU64::from),
+                file_flags: metadata.file_flags(),
+            }),
+        }
+    }
+
+    /// Rebuild the cache of a snapshot written by
+    /// [`Self::export_debug_snapshot`], rooted at the exported folder. Nodes
+    /// keep the metadata they were exported with, the ones without any were
+    /// unaccessible and stay so. The cache doesn't follow FSEvents.
+    pub fn import_debug_snapshot(reader: impl Read) -> Result<Self> {
+        let DebugSnapshot {
+            version: _,
+            path,
+            hashed_names: _,
+            mut root,
+        } = serde_json::from_reader(reader).context("Failed to read debug snapshot")?;
+        // The name index is built in path order, don't trust the file for it.
+        sort_children(&mut root);
+        let mut slab = NodeSlab::new();
+        let mut name_index = NameIndex::default();
+        let mut raw_names = RawNames::new();
+        let mut extras = ExtrasTable::new();
+        let slab_root = construct_snapshot_slab(
+            None,
+            &root,
+            &mut slab,
+            &mut name_index,
+            &mut raw_names,
+            &mut extras,
+        );
+        let file_nodes = FileNodes::new(path, slab, slab_root, raw_names);
+        let mut cache = Self::new(file_nodes, 0, name_index, None, None);
+        cache.metadata_extras = Mutex::new(extras);
+        Ok(cache)
+    }
+}
+
+fn sort_children(node: &mut Node) {
+    node.children.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    node.children.iter_mut().for_each(sort_children);
+}
+
+fn construct_snapshot_slab(
+    parent: Option<SlabIndex>,
+    node: &Node,
+    slab: &mut NodeSlab,
+    name_index: &mut NameIndex,
+    raw_names: &mut RawNames,
+    extras: &mut ExtrasTable,
+) -> SlabIndex {
+    let metadata = match node.metadata {
+        Some(metadata) => SlabNodeMetadataCompact::some(metadata),
+        None => SlabNodeMetadataCompact::unaccessible(),
+    };
+    let name = NAME_POOL.push(&node.name);
+    let index = slab.insert(SlabNode::new(parent, name, metadata));
+    if let Some(raw_name) = &node.raw_name {
+        raw_names.insert(index, raw_name.clone());
+    }
+    if !metadata.extras().is_empty() {
+        extras.insert(index, metadata.extras());
+    }
+    unsafe {
+        // SAFETY: children were sorted by name, this preorder traversal visits
+        // nodes in lexicographic path order.
+        name_index.add_index_ordered(name, index);
+    }
+    slab[index].children = node
+        .children
+        .iter()
+        .map(|child| {
+            construct_snapshot_slab(Some(index), child, slab, name_index, raw_names, extras)
+        })
+        .collect();
+    index
+}
+
+/// Keyed, length and extension preserving hash of names, see
+/// [`SnapshotNames::Hashed`].
+struct NameHasher {
+    key: u64,
+    /// Hash of each name met so far, so equal names hash equally.
+    hashed: HashMap<Box<str>, Box<str>>,
+}
+
+impl NameHasher {
+    fn new(key: u64) -> Self {
+        Self {
+            key,
+            hashed: HashMap::new(),
+        }
+    }
+
+    fn hash_path(&mut self, path: &Path) -> PathBuf {
+        path.components()
+            .map(|component| match component {
+                Component::Normal(name) => {
+                    let name = name.to_string_lossy();
+                    PathBuf::from(&*self.hash_name(&name, &mut HashSet::new()))
+                }
+                other => PathBuf::from(other.as_os_str()),
+            })
+            .collect()
+    }
+
+    /// Hash `name`, avoiding the hashes in `taken` (its siblings') so that
+    /// the folder doesn't end up with two children of the same name.
+    fn hash_name(&mut self, name: &str, taken: &mut HashSet<Box<str>>) -> Box<str> {
+        if let Some(hashed) = self.hashed.get(name)
+            && taken.insert(hashed.clone())
+        {
+            return hashed.clone();
+        }
+        let (dot, rest) = match name.strip_prefix('.') {
+            Some(rest) => (".", rest),
+            None => ("", name),
+        };
+        let (stem, extension) = match rest.rfind('.') {
+            Some(pos) if pos > 0 && pos + 1 < rest.len() => rest.split_at(pos),
+            _ => (rest, ""),
+        };
+        let len = stem.chars().count();
+        // A collision with a sibling is retried with another salt, and with a
+        // longer stem once short stems may have run out.
+        for salt in 0u64.. {
+            let stem = self.hash_stem(stem, salt, len + (salt / 16) as usize);
+            let hashed: Box<str> = format!("{dot}{stem}{extension}").into();
+            if taken.insert(hashed.clone()) {
+                self.hashed
+                    .entry(name.into())
+                    .or_insert_with(|| hashed.clone());
+                return hashed;
+            }
+        }
+        unreachable!("salts never run out")
+    }
+
+    fn hash_stem(&self, stem: &str, salt: u64, len: usize) -> String {
+        let mut hashed = String::with_capacity(len);
+        let mut block = 0;
+        while hashed.len() < len {
+            let mut hasher = DefaultHasher::new();
+            hasher.write_u64(self.key);
+            hasher.write(stem.as_bytes());
+            hasher.write_u64(salt);
+            hasher.write_u64(block);
+            let mut bits = hasher.finish();
+            // 36^12 < 2^64, twelve characters per hash.
+            for _ in 0..12.min(len - hashed.len()) {
+                hashed.push(HASH_ALPHABET[(bits % 36) as usize] as char);
+                bits /= 36;
+            }
+            block += 1;
+        }
+        hashed
+    }
+}
@@ -0,0 +1,211 @@
+use crate::SetIndex;
+use cardinal_syntax::ParseError;
+use std::fmt;
+
+pub type SearchResult<T> = std::result::Result<T, SearchError>;
+
+/// Why a search didn't produce results.
+#[derive(Debug)]
+pub enum SearchError {
+    /// The query text isn't valid syntax.
+    Parse(ParseError),
+    /// A filter argument names a `%variable%` that doesn't exist, `position`
+    /// is the byte offset of its opening `%`.
+    UnknownVariable { name: String, position: usize },
+    /// A newer search (or the deadline) stopped this one before anything usable was found.
+    Cancelled,
+    /// The index is being (re)built and can't answer queries yet.
+    IndexBusy,
+    /// `SearchOptions::scope_to` isn't a folder in the cache (anymore). A lone
+    /// cache reports its scope as a node of source 0, a [`crate::CacheSet`]
+    /// as the node of the member it was given.
+    InvalidScope(SetIndex),
+    /// The intermediate results held this many entries at once, more than
+    /// `SearchOptions::max_intermediate_results`: the query needs more terms.
+    TooBroad { entries: usize },
+    /// Groups nest deeper than `SearchOptions::max_query_depth`, this is the
+    /// depth the parser stopped at.
+    TooDeep { depth: usize },
+    /// The tree changed since the cursor was opened, its results may be out
+    /// of date: open a new one.
+    CursorInvalidated,
+    /// Everything else, including filters with invalid arguments.
+    Internal(anyhow::Error),
+}
+
+impl SearchError {
+    /// Stable identifier the UI can branch on.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SearchError::Parse(_) => "parse",
+            SearchError::UnknownVariable { .. } => "unknown_variable",
+            SearchError::Cancelled => "cancelled",
+            SearchError::IndexBusy => "index_busy",
+            SearchError::InvalidScope(_) => "invalid_scope",
+            SearchError::TooBroad { .. } => "too_broad",
+            SearchError::TooDeep { .. } => "too_deep",
+            SearchError::CursorInvalidated => "cursor_invalidated",
+            SearchError::Internal(_) => "internal",
+        }
+    }
+
+    /// Byte offset in the query the error points at, if any.
+    pub fn span(&self) -> Option<usize> {
+        match self {
+            SearchError::Parse(err) => Some(err.position),
+            SearchError::UnknownVariable { position, .. } => Some(*position),
+            SearchError::Cancelled
+            | SearchError::IndexBusy
+            | SearchError::InvalidScope(_)
+            | SearchError::TooBroad { .. }
+            | SearchError::TooDeep { .. }
+            | SearchError::CursorInvalidated
+            | SearchError::Internal(_) => None,
+        }
+    }
+
+    /// The error as a [`crate::CacheSet`] reports it for its member `source`.
+    pub(crate) fn in_source(self, source: usize) -> Self {
+        match self {
+            SearchError::InvalidScope(scope) => {
+                SearchError::InvalidScope(SetIndex::new(source, scope.index()))
+            }
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for SearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SearchError::Parse(err) => write!(f, "Failed to parse query: {err}"),
+            SearchError::UnknownVariable { name, position } => {
+                write!(f, "Unknown variable %{name}% (at byte {position})")
+            }
+            SearchError::Cancelled => f.write_str("Search was cancelled"),
+            SearchError::IndexBusy => f.write_str("Index is being rebuilt"),
+            SearchError::InvalidScope(scope) => write!(
+                f,
+                "Search scope {} of source {} is no longer a folder",
+                scope.index().get(),
+                scope.source()
+            ),
+            SearchError::TooBroad { entries } => write!(
+                f,
+                "Query is too broad: it held {entries} intermediate results, add more terms"
+            ),
+            SearchError::TooDeep { depth } => {
+                write!(f, "Query nests groups too deeply, {depth} levels")
+            }
+            SearchError::CursorInvalidated => {
+                f.write_str("Results changed since the cursor was opened")
+            }
+            SearchError::Internal(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for SearchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SearchError::Parse(err) => Some(err),
+            SearchError::Internal(err) => Some(err.as_ref()),
+            SearchError::UnknownVariable { .. }
+            | SearchError::Cancelled
+            | SearchError::IndexBusy
+            | SearchError::InvalidScope(_)
+            | SearchError::TooBroad { .. }
+            | SearchError::TooDeep { .. }
+            | SearchError::CursorInvalidated => None,
+        }
+    }
+}
+
+impl From<ParseError> for SearchError {
+    fn from(err: ParseError) -> Self {
+        match err.too_deep {
+            Some(depth) => SearchError::TooDeep { depth },
+            None => SearchError::Parse(err),
+        }
+    }
+}
+
+impl From<anyhow::Error> for SearchError {
+    fn from(err: anyhow::Error) -> Self {
+        SearchError::Internal(err)
+    }
+}
+
+/// Why a persisted cache wasn't loaded. Either way the caller walks the filesystem instead.
+#[derive(Debug)]
+pub enum CacheError {
+    /// The FSEvents history of the volume was reset (or its ids went backwards)
+    /// since the cache was written, so its `last_event_id` can't be resumed from.
+    EventStreamReset,
+    /// The file's payload doesn't match the checksum in its envelope: it was
+    /// truncated or damaged after it was written.
+    Corrupt { expected: u64, actual: u64 },
+    /// Missing, undecodable, or written for another root.
+    Unusable(anyhow::Error),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::EventStreamReset => {
+                f.write_str("FSEvents stream was reset since the cache was written")
+            }
+            CacheError::Corrupt { expected, actual } => write!(
+                f,
+                "Cache file is corrupted: checksum {actual:016x}, expected {expected:016x}"
+            ),
+            CacheError::Unusable(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CacheError::Unusable(err) => Some(err.as_ref()),
+            CacheError::EventStreamReset | CacheError::Corrupt { .. } => None,
+        }
+    }
+}
+
+impl From<anyhow::Error> for CacheError {
+    fn from(err: anyhow::Error) -> Self {
+        CacheError::Unusable(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cardinal_syntax::parse_query;
+
+    #[test]
+    fn parse_errors_keep_their_position() {
+        let err: SearchError = parse_query("foo)").unwrap_err().into();
+        assert_eq!(err.code(), "parse");
+        assert!(err.span().is_some());
+        assert!(err.to_string().starts_with("Failed to parse query: "));
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn codes_and_messages_of_other_variants() {
+        let cancelled = SearchError::Cancelled;
+        assert_eq!(cancelled.code(), "cancelled");
+        assert_eq!(cancelled.span(), None);
+
+        let busy = SearchError::IndexBusy;
+        assert_eq!(busy.code(), "index_busy");
+        assert_eq!(busy.to_string(), "Index is being rebuilt");
+
+        let internal = SearchError::from(anyhow::anyhow!("ext: requires non-empty extensions"));
+        assert_eq!(internal.code(), "internal");
+        assert_eq!(internal.span(), None);
+        assert_eq!(internal.to_string(), "ext: requires non-empty extensions");
+    }
+}
@@ -0,0 +1,138 @@
+use crate::SearchCache;
+use cardinal_sdk::{EventFlag, FsEvent};
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Ids of applied events a cache remembers past the id it was walked or
+/// loaded at, the oldest are forgotten beyond. A replayed event whose id was
+/// forgotten is applied again, which only rescans its path.
+pub const APPLIED_EVENT_IDS: usize = 1 << 16;
+
+/// How the event ids a cache was handed compare to
+/// [`SearchCache::last_event_id`], see [`SearchCache::event_id_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EventIdStats {
+    /// Highest id handed to the cache, skipped events included.
+    pub max_seen: u64,
+    /// Events of the replayed history older than the stored id, skipped
+    /// because the cache already applied them.
+    pub replayed_duplicates: usize,
+    /// Other events older than the stored id, live ones and replayed ones
+    /// the cache never applied. They are applied, as the id only says where
+    /// the stream resumes, but never move it back.
+    pub out_of_order: usize,
+    /// Set from loading a cache from disk until the `HistoryDone` event: the
+    /// watcher resumed at [`SearchCache::last_event_id`] and is still
+    /// replaying what happened since.
+    pub replaying: bool,
+}
+
+/// Events dropped or let through by [`SearchCache::skip_replayed`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EventIdCounts {
+    pub(crate) replayed_duplicates: usize,
+    pub(crate) out_of_order: usize,
+    pub(crate) history_done: bool,
+    /// Ids of the events let through, recorded once they're applied.
+    pub(crate) kept: Vec<u64>,
+}
+
+/// The events a cache holds: all up to the id its tree was walked or loaded
+/// at, and those it applied since.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct AppliedEventIds {
+    through: u64,
+    /// Ids above `through`, at most [`APPLIED_EVENT_IDS`].
+    ids: BTreeSet<u64>,
+}
+
+impl AppliedEventIds {
+    pub(crate) fn new(through: u64) -> Self {
+        Self {
+            through,
+            ids: BTreeSet::new(),
+        }
+    }
+
+    fn contains(&self, id: u64) -> bool {
+        id <= self.through || self.ids.contains(&id)
+    }
+
+    fn record(&mut self, ids: &[u64]) {
+        self.ids
+            .extend(ids.iter().copied().filter(|&id| id > self.through));
+        while self.ids.len() > APPLIED_EVENT_IDS {
+            self.ids.pop_first();
+        }
+    }
+
+    /// A new tree walked at `through` holds everything up to it.
+    pub(crate) fn reset(&mut self, through: u64) {
+        self.through = through;
+        self.ids.retain(|&id| id > through);
+    }
+}
+
+impl SearchCache {
+    /// Event id counters of the cache since it was loaded or walked.
+    pub fn event_id_stats(&self) -> EventIdStats {
+        self.event_ids
+    }
+
+    /// Whether events are still replayed history, see
+    /// [`EventIdStats::replaying`].
+    pub fn replaying(&self) -> bool {
+        self.event_ids.replaying
+    }
+
+    /// Treat events as replayed history until the next `HistoryDone`, for a
+    /// watcher restarted at [`Self::last_event_id`].
+    pub fn begin_replay(&mut self) {
+        self.event_ids.replaying = true;
+    }
+
+    /// Drop the replayed events older than the stored id that were applied
+    /// already, counting them and the other events that are as old. An event
+    /// at the stored id is kept: [`Self::take_vanished`] removals carry it.
+    pub(crate) fn skip_replayed(&self, events: Vec<FsEvent>) -> (Vec<FsEvent>, EventIdCounts) {
+        let mut counts = EventIdCounts::default();
+        let mut replaying = self.event_ids.replaying;
+        let events = events
+            .into_iter()
+            .filter(|event| {
+                if event.flag.contains(EventFlag::HistoryDone) {
+                    replaying = false;
+                    counts.history_done = true;
+                    return true;
+                }
+                if event.id >= self.last_event_id {
+                    counts.kept.push(event.id);
+                    return true;
+                }
+                if replaying && self.applied_event_ids.contains(event.id) {
+                    counts.replayed_duplicates += 1;
+                    false
+                } else {
+                    counts.out_of_order += 1;
+                    counts.kept.push(event.id);
+                    true
+                }
+            })
+            .collect();
+        (events, counts)
+    }
+
+    pub(crate) fn note_event_ids(&mut self, counts: EventIdCounts, max_event_id: Option<u64>) {
+        self.applied_event_ids.record(&counts.kept);
+        let stats = &mut self.event_ids;
+        if let Some(max_event_id) = max_event_id {
+            stats.max_seen = stats.max_seen.max(max_event_id);
+        }
+        stats.replayed_duplicates += counts.replayed_duplicates;
+        stats.out_of_order += counts.out_of_order;
+        if counts.history_done {
+            stats.replaying = false;
+        }
+    }
+}
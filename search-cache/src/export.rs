@@ -0,0 +1,204 @@
+use crate::{SearchCache, SearchResultNode, SlabIndex};
+use anyhow::Result;
+use fswalk::NodeFileType;
+use jiff::Timestamp;
+use serde::Serialize;
+use std::{borrow::Cow, io::Write, path::Path};
+
+/// Rows are expanded (and their metadata fetched) this many at a time, so
+/// stat calls aren't interleaved with writes row by row.
+const EXPORT_BATCH: usize = 1024;
+
+const CSV_HEADER: &str = "path,name,size,modified,created,type,raw_path";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    /// RFC 4180 with a header row.
+    Csv,
+    /// One JSON object per line.
+    JsonLines,
+}
+
+impl ExportFormat {
+    /// Pick the format from a file name: `.json` and `.jsonl` export JSON lines, `.csv` CSV.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(ExportFormat::Csv),
+            "json" | "jsonl" => Some(ExportFormat::JsonLines),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    pub rows: usize,
+    /// Nodes no longer in the cache, left out of the export.
+    pub skipped: usize,
+    /// Rows whose path isn't valid UTF-8 and carries `raw_path`.
+    pub lossy_paths: usize,
+    /// Rows exported without size and dates because the file couldn't be stat'ed.
+    pub missing_metadata: usize,
+}
+
+#[derive(Serialize)]
+struct ExportRow<'a> {
+    /// Lossy for non-UTF-8 paths.
+    path: Cow<'a, str>,
+    name: Cow<'a, str>,
+    size: Option<u64>,
+    /// RFC 3339 in UTC.
+    modified: Option<String>,
+    created: Option<String>,
+    r#type: &'static str,
+    /// Hex of the exact path bytes, only when `path` is lossy.
+    raw_path: Option<String>,
+}
+
+impl<'a> ExportRow<'a> {
+    fn new(node: &'a SearchResultNode) -> Self {
+        let metadata = node.metadata.as_ref();
+        let path = node.path.to_string_lossy();
+        let raw_path = matches!(path, Cow::Owned(_)).then(|| hex_encode(node.path_bytes()));
+        let timestamp = |seconds: Option<std::num::NonZeroU32>| {
+            seconds
+                .and_then(|seconds| Timestamp::from_second(seconds.get().into()).ok())
+                .map(|timestamp| timestamp.to_string())
+        };
+        Self {
+            name: node
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy())
+                .unwrap_or_default(),
+            path,
+            size: metadata.as_ref().map(|metadata| metadata.size()),
+            modified: timestamp(metadata.as_ref().and_then(|metadata| metadata.mtime())),
+            created: timestamp(metadata.as_ref().and_then(|metadata| metadata.ctime())),
+            r#type: file_type_name(node.metadata.file_type_hint()),
+            raw_path,
+        }
+    }
+
+    fn write_csv(&self, writer: &mut dyn Write) -> std::io::Result<()> {
+        let size = self.size.map(|size| size.to_string());
+        let fields = [
+            &*self.path,
+            &*self.name,
+            size.as_deref().unwrap_or_default(),
+            self.modified.as_deref().unwrap_or_default(),
+            self.created.as_deref().unwrap_or_default(),
+            self.r#type,
+            self.raw_path.as_deref().unwrap_or_default(),
+        ];
+        for (i, field) in fields.into_iter().enumerate() {
+            if i > 0 {
+                writer.write_all(b",")?;
+            }
+            write_csv_field(writer, field)?;
+        }
+        writer.write_all(b"\r\n")
+    }
+}
+
+/// Writes expanded nodes as export rows, for exports that expand their nodes
+/// themselves (e.g. in batches, between other work). The header, if any, is
+/// written on creation.
+pub struct ExportWriter<'w> {
+    format: ExportFormat,
+    writer: &'w mut dyn Write,
+    summary: ExportSummary,
+}
+
+impl<'w> ExportWriter<'w> {
+    pub fn new(format: ExportFormat, writer: &'w mut dyn Write) -> Result<Self> {
+        if format == ExportFormat::Csv {
+            write!(writer, "{CSV_HEADER}\r\n")?;
+        }
+        Ok(Self {
+            format,
+            writer,
+            summary: ExportSummary::default(),
+        })
+    }
+
+    /// Write a row per node, skipping the ones that left the cache.
+    pub fn write_nodes(&mut self, nodes: &[SearchResultNode]) -> Result<()> {
+        for node in nodes {
+            // Removed nodes expand to an empty path.
+            if node.path.as_os_str().is_empty() {
+                self.summary.skipped += 1;
+                continue;
+            }
+            let row = ExportRow::new(node);
+            match self.format {
+                ExportFormat::Csv => row.write_csv(self.writer)?,
+                ExportFormat::JsonLines => {
+                    serde_json::to_writer(&mut *self.writer, &row)?;
+                    self.writer.write_all(b"\n")?;
+                }
+            }
+            self.summary.rows += 1;
+            self.summary.lossy_paths += usize::from(row.raw_path.is_some());
+            self.summary.missing_metadata += usize::from(row.size.is_none());
+        }
+        Ok(())
+    }
+
+    /// What was written so far.
+    pub fn summary(&self) -> ExportSummary {
+        self.summary
+    }
+
+    pub fn finish(self) -> Result<ExportSummary> {
+        self.writer.flush()?;
+        Ok(self.summary)
+    }
+}
+
+impl SearchCache {
+    /// Write `indexes` (e.g. the nodes of a [`crate::SearchOutcome`]) to `writer`
+    /// with their path, name, size, dates and type. Missing metadata is fetched
+    /// in batches; nodes that left the cache are skipped.
+    pub fn export_results(
+        &self,
+        indexes: &[SlabIndex],
+        format: ExportFormat,
+        writer: &mut dyn Write,
+    ) -> Result<ExportSummary> {
+        let mut export = ExportWriter::new(format, writer)?;
+        for batch in indexes.chunks(EXPORT_BATCH) {
+            export.write_nodes(&self.expand_file_nodes(batch))?;
+        }
+        export.finish()
+    }
+}
+
+fn file_type_name(file_type: NodeFileType) -> &'static str {
+    match file_type {
+        NodeFileType::File => "file",
+        NodeFileType::Dir => "dir",
+        NodeFileType::Symlink => "symlink",
+        NodeFileType::Unknown => "unknown",
+    }
+}
+
+/// Quote `field` if it holds a separator, a quote or a line break, doubling quotes.
+fn write_csv_field(writer: &mut dyn Write, field: &str) -> std::io::Result<()> {
+    if !field.contains([',', '"', '\n', '\r']) {
+        return writer.write_all(field.as_bytes());
+    }
+    writer.write_all(b"\"")?;
+    writer.write_all(field.replace('"', "\"\"").as_bytes())?;
+    writer.write_all(b"\"")
+}
+
+fn hex_encode(bytes: &[u8]) -> String {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    let mut out = String::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        out.push(DIGITS[usize::from(byte >> 4)] as char);
+        out.push(DIGITS[usize::from(byte & 0xf)] as char);
+    }
+    out
+}
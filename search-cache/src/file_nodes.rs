@@ -1,20 +1,42 @@
-use crate::{SlabIndex, SlabNode, ThinSlab};
+use crate::{IndexRemap, NameAndParent, OptionSlabIndex, SlabIndex, SlabNode};
 use std::{
+    collections::{BTreeMap, HashMap},
     ffi::OsStr,
+    mem,
     ops::{Deref, DerefMut},
+    os::unix::ffi::OsStrExt,
     path::{Path, PathBuf},
+    sync::Arc,
 };
 
+/// Storage of the tree's nodes: the mmap slab, or with the `snapshots`
+/// feature the chunked one [`crate::SearchCache::search_snapshot`] shares.
+#[cfg(not(feature = "snapshots"))]
+pub type NodeSlab = crate::ThinSlab<SlabNode>;
+#[cfg(feature = "snapshots")]
+pub type NodeSlab = crate::SharedSlab<SlabNode>;
+
+/// Original bytes of the (rare) file names that aren't valid UTF-8. The slab
+/// only stores their lossy form, which is what queries match against.
+pub type RawNames = BTreeMap<SlabIndex, Box<[u8]>>;
+
 #[derive(Debug)]
+#[cfg_attr(feature = "snapshots", derive(Clone))]
 pub struct FileNodes {
     path: PathBuf,
-    slab: ThinSlab<SlabNode>,
+    slab: NodeSlab,
     root: SlabIndex,
+    raw_names: RawNames,
 }
 
 impl FileNodes {
-    pub(crate) fn new(path: PathBuf, slab: ThinSlab<SlabNode>, root: SlabIndex) -> Self {
-        Self { path, slab, root }
+    pub(crate) fn new(path: PathBuf, slab: NodeSlab, root: SlabIndex, raw_names: RawNames) -> Self {
+        Self {
+            path,
+            slab,
+            root,
+            raw_names,
+        }
     }
 
     pub(crate) fn root(&self) -> SlabIndex {
@@ -25,29 +47,133 @@ impl FileNodes {
         let mut current = index;
         let mut segments = vec![];
         while let Some(parent) = self.slab.get(current)?.name_and_parent.parent() {
-            segments.push(self.slab.get(current)?.name_and_parent.as_str());
+            segments.push(self.node_name(current));
             current = parent;
         }
-        Some(
-            self.path
-                .iter()
-                .chain(segments.iter().rev().map(OsStr::new))
-                .collect(),
-        )
+        Some(self.path.iter().chain(segments.into_iter().rev()).collect())
+    }
+
+    /// [`Self::node_path`] of each of `indexes`, building the path of every
+    /// folder on the way only once, so siblings share their parent's work.
+    pub fn node_paths(&self, indexes: &[SlabIndex]) -> Vec<Option<PathBuf>> {
+        let mut folders = HashMap::new();
+        indexes
+            .iter()
+            .map(|&index| {
+                let Some(parent) = self.slab.get(index)?.name_and_parent.parent() else {
+                    return Some(self.path.iter().collect());
+                };
+                let mut path = (*self.folder_path(parent, &mut folders)?).clone();
+                path.push(self.node_name(index));
+                Some(path)
+            })
+            .collect()
+    }
+
+    /// Path of the folder `index`, memoized with its ancestors in `folders`.
+    /// `None` if it or one of its ancestors is no longer in the slab.
+    fn folder_path(
+        &self,
+        index: SlabIndex,
+        folders: &mut HashMap<SlabIndex, Arc<PathBuf>>,
+    ) -> Option<Arc<PathBuf>> {
+        // Climb to the closest folder built already, or to the root.
+        let mut unbuilt = vec![];
+        let mut current = index;
+        let mut path = loop {
+            if let Some(path) = folders.get(&current) {
+                break path.clone();
+            }
+            match self.slab.get(current)?.name_and_parent.parent() {
+                Some(parent) => {
+                    unbuilt.push(current);
+                    current = parent;
+                }
+                None => {
+                    let root = Arc::new(self.path.iter().collect::<PathBuf>());
+                    folders.insert(current, root.clone());
+                    break root;
+                }
+            }
+        };
+        for folder in unbuilt.into_iter().rev() {
+            let mut folder_path = (*path).clone();
+            folder_path.push(self.node_name(folder));
+            path = Arc::new(folder_path);
+            folders.insert(folder, path.clone());
+        }
+        Some(path)
+    }
+
+    /// Exact on-disk name of the node, the lossy UTF-8 name from the slab is
+    /// only used for matching.
+    ///
+    /// Panics if `index` isn't in the slab.
+    pub(crate) fn node_name(&self, index: SlabIndex) -> &OsStr {
+        match self.raw_names.get(&index) {
+            Some(raw) => OsStr::from_bytes(raw),
+            None => OsStr::new(self.slab[index].name_and_parent.as_str()),
+        }
+    }
+
+    pub(crate) fn raw_names(&self) -> &RawNames {
+        &self.raw_names
+    }
+
+    pub(crate) fn set_raw_name(&mut self, index: SlabIndex, raw_name: Option<Box<[u8]>>) {
+        match raw_name {
+            Some(raw_name) => self.raw_names.insert(index, raw_name),
+            None => self.raw_names.remove(&index),
+        };
     }
 
     pub(crate) fn path(&self) -> &Path {
         &self.path
     }
 
-    pub(crate) fn into_parts(self) -> (PathBuf, SlabIndex, ThinSlab<SlabNode>) {
-        let Self { path, slab, root } = self;
-        (path, root, slab)
+    /// Move every node to a fresh slab without holes, keeping their order, and
+    /// point parents, children, the root and raw names at the new indexes.
+    pub(crate) fn compact(&mut self) -> IndexRemap {
+        let mut old = mem::take(&mut self.slab);
+        let order: Vec<SlabIndex> = old.iter().map(|(index, _)| index).collect();
+        let remap = IndexRemap::new(old.slots(), &order);
+        for index in order {
+            let mut node = old.try_remove(index).expect("listed nodes are in the slab");
+            let parent = node
+                .name_and_parent
+                .parent()
+                .and_then(|parent| remap.get(parent));
+            node.name_and_parent = NameAndParent::new(
+                node.name_and_parent.as_str(),
+                OptionSlabIndex::from_option(parent),
+            );
+            node.children = node
+                .children
+                .iter()
+                .filter_map(|&child| remap.get(child))
+                .collect();
+            let new = self.slab.insert(node);
+            debug_assert_eq!(Some(new), remap.get(index));
+        }
+        self.root = remap.get(self.root).expect("the root is in the slab");
+        remap.rekey(&mut self.raw_names);
+        remap
+    }
+
+    #[cfg(test)]
+    pub(crate) fn into_parts(self) -> (PathBuf, SlabIndex, NodeSlab, RawNames) {
+        let Self {
+            path,
+            slab,
+            root,
+            raw_names,
+        } = self;
+        (path, root, slab, raw_names)
     }
 }
 
 impl Deref for FileNodes {
-    type Target = ThinSlab<SlabNode>;
+    type Target = NodeSlab;
 
     fn deref(&self) -> &Self::Target {
         &self.slab
@@ -0,0 +1,77 @@
+use crate::{SearchCache, SearchError, SearchResult, SlabIndex};
+use fswalk::NodeFileType;
+use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
+use std::time::Instant;
+use tracing::info;
+
+/// Where a filter with no base to narrow, like `ext:png` on its own, finds its
+/// nodes instead of going through every node of [`SearchCache::search_empty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FilterSource {
+    /// The name index, each name checked once: a name that doesn't match
+    /// skips all of its nodes. For filters that can reject on the name alone
+    /// (`ext:`, the extension groups of `type:`).
+    Names,
+    /// Every node in turn, read off the name index rather than collected
+    /// first. For filters that need the node itself (`size:`, dates).
+    Scan,
+}
+
+impl FilterSource {
+    fn pick(name_matches: Option<&dyn Fn(&str) -> bool>) -> Self {
+        match name_matches {
+            Some(_) => Self::Names,
+            None => Self::Scan,
+        }
+    }
+}
+
+impl SearchCache {
+    /// The nodes whose name passes `name_matches`, when given, and which pass
+    /// `node_matches`, in the order [`Self::search_empty`] would have them.
+    /// `filter` names the filter in the log, with the source picked and the
+    /// time it took.
+    pub(crate) fn filter_all(
+        &self,
+        filter: &str,
+        name_matches: Option<&dyn Fn(&str) -> bool>,
+        mut node_matches: impl FnMut(SlabIndex) -> bool,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let source = FilterSource::pick(name_matches);
+        let started = Instant::now();
+        let mut checked = 0usize;
+        let mut nodes = Vec::new();
+        for (name, indices) in self.name_index.iter() {
+            if name_matches.is_some_and(|matches| !matches(name)) {
+                continue;
+            }
+            for &index in indices.iter() {
+                // Like `filter_nodes`, metadata reads are slow so check often.
+                if checked % (CANCEL_CHECK_INTERVAL / 4) == 0 && token.is_cancelled() {
+                    return Err(SearchError::Cancelled);
+                }
+                checked += 1;
+                if node_matches(index) {
+                    nodes.push(index);
+                }
+            }
+        }
+        info!(
+            "{filter} filter from {source:?}: {} of {checked} nodes in {:?}",
+            nodes.len(),
+            started.elapsed()
+        );
+        Ok(nodes)
+    }
+
+    /// Whether every node of the tree is a folder, checked until a file shows
+    /// up. What `only_folders` tells of [`Self::search_empty`].
+    pub(crate) fn only_folders_indexed(&self) -> bool {
+        self.name_index.iter().all(|(_, indices)| {
+            indices
+                .iter()
+                .all(|&index| self.file_nodes[index].metadata.file_type_hint() == NodeFileType::Dir)
+        })
+    }
+}
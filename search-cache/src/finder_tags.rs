@@ -0,0 +1,196 @@
+use crate::{SearchCache, SlabIndex};
+use cardinal_sdk::xattr_of_path;
+use std::{
+    ffi::CStr,
+    sync::{Arc, PoisonError},
+};
+
+/// Where Finder keeps a file's tags, a binary plist array of strings.
+const USER_TAGS_XATTR: &CStr = c"com.apple.metadata:_kMDItemUserTags";
+
+/// The colors Finder offers for tags, numbered like its labels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FinderColor {
+    Gray = 1,
+    Green = 2,
+    Purple = 3,
+    Blue = 4,
+    Yellow = 5,
+    Red = 6,
+    Orange = 7,
+}
+
+impl FinderColor {
+    const ALL: [Self; 7] = [
+        Self::Gray,
+        Self::Green,
+        Self::Purple,
+        Self::Blue,
+        Self::Yellow,
+        Self::Red,
+        Self::Orange,
+    ];
+
+    pub fn from_label(label: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|color| *color as u8 == label)
+    }
+
+    /// Case-insensitive, `grey` is accepted too.
+    pub fn from_name(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("grey") {
+            return Some(Self::Gray);
+        }
+        Self::ALL
+            .into_iter()
+            .find(|color| color.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Gray => "gray",
+            Self::Green => "green",
+            Self::Purple => "purple",
+            Self::Blue => "blue",
+            Self::Yellow => "yellow",
+            Self::Red => "red",
+            Self::Orange => "orange",
+        }
+    }
+}
+
+/// A Finder tag of a file, read from its `_kMDItemUserTags` xattr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FinderTag {
+    pub name: String,
+    pub color: Option<FinderColor>,
+}
+
+impl FinderTag {
+    /// Finder stores a colored tag as `name\n<label>`, e.g. `Work\n6` for a red one.
+    pub fn parse(entry: &str) -> Self {
+        match entry.rsplit_once('\n') {
+            Some((name, label)) => Self {
+                name: name.to_string(),
+                color: label.parse().ok().and_then(FinderColor::from_label),
+            },
+            None => Self {
+                name: entry.to_string(),
+                color: None,
+            },
+        }
+    }
+
+    /// Whether `wanted`, a tag name or a color, names this tag. Case-insensitive.
+    pub fn matches(&self, wanted: &str) -> bool {
+        self.name.to_lowercase() == wanted.to_lowercase()
+            || self
+                .color
+                .is_some_and(|color| FinderColor::from_name(wanted) == Some(color))
+    }
+}
+
+impl SearchCache {
+    /// Finder tags of `index`, read once and kept until the node is replaced.
+    /// Files that can't be read count as untagged.
+    pub fn finder_tags(&self, index: SlabIndex) -> Arc<[FinderTag]> {
+        let cached = self
+            .finder_tags
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&index)
+            .cloned();
+        if let Some(tags) = cached {
+            return tags;
+        }
+        let tags: Arc<[FinderTag]> = self
+            .node_path(index)
+            .and_then(|path| xattr_of_path(&path, USER_TAGS_XATTR).ok().flatten())
+            .and_then(|plist| parse_string_array(&plist))
+            .unwrap_or_default()
+            .iter()
+            .map(|entry| FinderTag::parse(entry))
+            .collect();
+        self.finder_tags
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(index, tags.clone());
+        tags
+    }
+}
+
+/// The strings of a binary plist whose top object is an array of strings, the
+/// only shape `_kMDItemUserTags` has. `None` for anything else.
+pub(crate) fn parse_string_array(data: &[u8]) -> Option<Vec<String>> {
+    if !data.starts_with(b"bplist00") || data.len() < 8 + 32 {
+        return None;
+    }
+    let trailer = &data[data.len() - 32..];
+    let offset_size = usize::from(trailer[6]);
+    let ref_size = usize::from(trailer[7]);
+    let objects = be_uint(&trailer[8..16])?;
+    let top = be_uint(&trailer[16..24])?;
+    let table = be_uint(&trailer[24..32])?;
+    let offset_of = |object: usize| {
+        if object >= objects {
+            return None;
+        }
+        let at = table.checked_add(object.checked_mul(offset_size)?)?;
+        be_uint(data.get(at..at.checked_add(offset_size)?)?)
+    };
+
+    let (kind, count, refs) = object_header(data, offset_of(top)?)?;
+    if kind != 0xA {
+        return None;
+    }
+    (0..count)
+        .map(|i| {
+            let at = refs.checked_add(i.checked_mul(ref_size)?)?;
+            let object = be_uint(data.get(at..at.checked_add(ref_size)?)?)?;
+            let (kind, len, start) = object_header(data, offset_of(object)?)?;
+            match kind {
+                // ASCII
+                0x5 => {
+                    let bytes = data.get(start..start.checked_add(len)?)?;
+                    String::from_utf8(bytes.to_vec()).ok()
+                }
+                // UTF-16BE, `len` in code units
+                0x6 => {
+                    let bytes = data.get(start..start.checked_add(len.checked_mul(2)?)?)?;
+                    let units: Vec<u16> = bytes
+                        .chunks_exact(2)
+                        .map(|unit| u16::from_be_bytes([unit[0], unit[1]]))
+                        .collect();
+                    String::from_utf16(&units).ok()
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// `(type nibble, count, start of contents)` of the object at `at`. Counts of
+/// 15 and up follow the marker as an int object.
+fn object_header(data: &[u8], at: usize) -> Option<(u8, usize, usize)> {
+    let marker = *data.get(at)?;
+    let (kind, count) = (marker >> 4, marker & 0xF);
+    if count != 0xF {
+        return Some((kind, usize::from(count), at + 1));
+    }
+    let int_marker = *data.get(at + 1)?;
+    if int_marker >> 4 != 0x1 {
+        return None;
+    }
+    let width = 1usize.checked_shl(u32::from(int_marker & 0xF))?;
+    let count = be_uint(data.get(at + 2..at + 2 + width)?)?;
+    Some((kind, count, at + 2 + width))
+}
+
+fn be_uint(bytes: &[u8]) -> Option<usize> {
+    if bytes.is_empty() || bytes.len() > 8 {
+        return None;
+    }
+    let value = bytes
+        .iter()
+        .fold(0u64, |value, &byte| (value << 8) | u64::from(byte));
+    usize::try_from(value).ok()
+}
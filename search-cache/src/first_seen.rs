@@ -0,0 +1,60 @@
+use crate::{SearchCache, SlabIndex, query_history::unix_now};
+use cardinal_sdk::{dev_of_path, event_id_to_timestamp};
+use hashbrown::HashMap;
+use std::{ffi::OsStr, path::Path, time::Duration};
+
+/// Default of [`crate::SearchOptions::new_window`].
+pub const DEFAULT_NEW_WINDOW: Duration = Duration::from_secs(24 * 60 * 60);
+
+impl SearchCache {
+    /// Unix time at which FSEvents first reported `index`, `None` for nodes
+    /// that were already there when the tree was walked.
+    pub fn first_seen(&self, index: SlabIndex) -> Option<i64> {
+        self.first_seen.get(&index).copied()
+    }
+
+    /// Carry the stamps of `old`'s subtree over to `new`, the freshly walked
+    /// subtree replacing it, matching nodes by name. Nodes without a
+    /// counterpart are new and stamped with `now`. `old` is `None` when the
+    /// path wasn't indexed, so the whole subtree is new.
+    pub(crate) fn stamp_first_seen(&mut self, old: Option<SlabIndex>, new: SlabIndex, now: i64) {
+        let mut stack = vec![(old, new)];
+        while let Some((old, new)) = stack.pop() {
+            let stamp = match old {
+                Some(old) => self.first_seen(old),
+                None => Some(now),
+            };
+            if let Some(stamp) = stamp {
+                self.first_seen.insert(new, stamp);
+            }
+            let old_children: HashMap<&OsStr, SlabIndex> = old
+                .map(|old| {
+                    self.file_nodes[old]
+                        .children
+                        .iter()
+                        .map(|&child| (self.file_nodes.node_name(child), child))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let pairs: Vec<_> = self.file_nodes[new]
+                .children
+                .iter()
+                .map(|&child| {
+                    let name = self.file_nodes.node_name(child);
+                    (old_children.get(name).copied(), child)
+                })
+                .collect();
+            stack.extend(pairs);
+        }
+    }
+}
+
+/// When the event `event_id` happened on the volume of `root`. Events
+/// replayed from the FSEvents history map to when they were recorded, not
+/// to when they were handled.
+pub(crate) fn event_timestamp(root: &Path, event_id: u64) -> i64 {
+    match dev_of_path(root) {
+        Ok(dev) => event_id_to_timestamp(dev, event_id, &mut Default::default()),
+        Err(_) => unix_now() as i64,
+    }
+}
@@ -0,0 +1,78 @@
+use crate::{SearchCache, SlabIndex};
+use fswalk::NodeFileType;
+use search_cancel::CancellationToken;
+use std::{fs, path::PathBuf};
+
+/// Recursive size of a folder, see [`SearchCache::folder_size_job`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FolderSize {
+    /// Sum of the sizes of the files below the folder that could be read.
+    pub bytes: u64,
+    pub file_count: usize,
+}
+
+/// The files below a folder, added up by [`Self::run`] away from the cache.
+#[derive(Debug)]
+pub struct FolderSizeJob {
+    /// Files whose size the cache knows already.
+    known: FolderSize,
+    /// Files whose metadata wasn't fetched yet, stat'ed by `run`.
+    to_stat: Vec<PathBuf>,
+}
+
+impl FolderSizeJob {
+    /// Files that `run` has to stat.
+    pub fn files_to_stat(&self) -> usize {
+        self.to_stat.len()
+    }
+
+    /// Stat the files the cache has no size for and add everything up. `None`
+    /// if cancelled, which is checked before each file.
+    pub fn run(self, cancel: CancellationToken) -> Option<FolderSize> {
+        let mut size = self.known;
+        for path in self.to_stat {
+            if cancel.is_cancelled() {
+                return None;
+            }
+            // Files gone since they were indexed still count, like
+            // unaccessible ones, with no size.
+            if let Ok(metadata) = fs::symlink_metadata(&path) {
+                size.bytes += metadata.len();
+            }
+        }
+        Some(size)
+    }
+}
+
+impl SearchCache {
+    /// Like [`Self::folder_size`] without the hard link option, but only
+    /// collecting the files below `index` and the sizes already known, so the
+    /// cache is free again before the other files are stat'ed by
+    /// [`FolderSizeJob::run`]. `None` if `index` isn't in the tree or if
+    /// cancelled.
+    pub fn folder_size_job(
+        &self,
+        index: SlabIndex,
+        cancel: CancellationToken,
+    ) -> Option<FolderSizeJob> {
+        self.file_nodes.get(index)?;
+        let mut files = self.all_subnodes(index, cancel)?;
+        files.retain(|&node| self.file_nodes[node].metadata.file_type_hint() == NodeFileType::File);
+        let mut known = FolderSize {
+            bytes: 0,
+            file_count: files.len(),
+        };
+        let mut to_stat = Vec::new();
+        for node in files {
+            let metadata = self.file_nodes[node].metadata.get();
+            if let Some(metadata) = metadata.as_ref() {
+                known.bytes += metadata.size();
+            } else if metadata.is_none()
+                && let Some(path) = self.node_path(node)
+            {
+                to_stat.push(path);
+            }
+        }
+        Some(FolderSizeJob { known, to_stat })
+    }
+}
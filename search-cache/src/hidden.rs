@@ -0,0 +1,65 @@
+use crate::{SearchCache, SearchOptions, SlabIndex, cache::ExtrasTable};
+use cardinal_syntax::{Expr, FilterKind, Term};
+use fswalk::HIDDEN_FLAG;
+
+impl SearchCache {
+    /// Whether `index` is hidden: its name starts with a dot or it has the
+    /// `UF_HIDDEN` flag (`chflags hidden`, Finder's invisible bit). Stats the
+    /// node if its metadata isn't cached.
+    pub fn is_hidden(&self, index: SlabIndex) -> bool {
+        self.has_hidden_name(index) || {
+            self.ensure_metadata(index);
+            self.has_hidden_flag(&self.lock_metadata_extras(), index)
+        }
+    }
+
+    /// Whether the fetched metadata of `index` has the `UF_HIDDEN` flag.
+    fn has_hidden_flag(&self, extras: &ExtrasTable, index: SlabIndex) -> bool {
+        extras
+            .get(&index)
+            .is_some_and(|extras| extras.file_flags & HIDDEN_FLAG != 0)
+            && self.file_nodes[index].metadata.is_some()
+    }
+
+    fn has_hidden_name(&self, index: SlabIndex) -> bool {
+        index != self.file_nodes.root()
+            && self.file_nodes[index]
+                .name_and_parent
+                .as_str()
+                .starts_with('.')
+    }
+
+    /// Remove the hidden nodes unless `options` asks for them or `expr`
+    /// mentions `is:hidden`. Only what is known without stat'ing counts, see
+    /// [`SearchOptions::include_hidden`].
+    pub(crate) fn drop_hidden(
+        &self,
+        mut nodes: Vec<SlabIndex>,
+        expr: &Expr,
+        options: SearchOptions,
+    ) -> Vec<SlabIndex> {
+        if options.include_hidden || mentions_is_hidden(expr) {
+            return nodes;
+        }
+        // Without stat'ing, only nodes whose metadata is cached can have the flag.
+        let extras = self.lock_metadata_extras();
+        nodes
+            .retain(|&index| !self.has_hidden_name(index) && !self.has_hidden_flag(&extras, index));
+        nodes
+    }
+}
+
+fn mentions_is_hidden(expr: &Expr) -> bool {
+    match expr {
+        Expr::Empty | Expr::Term(Term::Word(_) | Term::Phrase(_) | Term::Regex(_)) => false,
+        Expr::Term(Term::Filter(filter)) => {
+            matches!(filter.kind, FilterKind::Is)
+                && filter
+                    .argument
+                    .as_ref()
+                    .is_some_and(|argument| argument.raw.eq_ignore_ascii_case("hidden"))
+        }
+        Expr::Not(inner) => mentions_is_hidden(inner),
+        Expr::And(parts) | Expr::Or(parts) => parts.iter().any(mentions_is_hidden),
+    }
+}
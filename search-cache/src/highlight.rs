@@ -1,6 +1,9 @@
+use crate::{SearchOptions, SegmentMatcher, build_segment_matchers, query::regex_matcher};
 use cardinal_syntax::{ArgumentKind, Expr, FilterArgument, Term};
+use namepool::to_nfc;
 use query_segmentation::{Segment, query_segmentation};
-use std::collections::BTreeSet;
+use regex::{Regex, RegexBuilder};
+use std::{borrow::Cow, collections::BTreeSet, ops::Range};
 
 pub fn derive_highlight_terms(expr: &Expr) -> Vec<String> {
     let mut collector = HighlightCollector::default();
@@ -111,6 +114,190 @@ fn segment_value<'a>(segment: &'a Segment<'a>) -> &'a str {
     }
 }
 
+/// Finds what the name terms of a query matched in a result's name, see
+/// [`SearchOptions::collect_highlights`]. Only the last segment of positive
+/// words and phrases and `regex:` terms count: negated terms match nothing in
+/// a result and filters don't look at the name the same way.
+#[derive(Debug, Clone)]
+pub(crate) struct NameHighlighter {
+    patterns: Vec<NamePattern>,
+    byte_exact: bool,
+}
+
+#[derive(Debug, Clone)]
+enum NamePattern {
+    Matcher(SegmentMatcher),
+    /// A wildcard segment, its literal runs captured: highlighting the whole
+    /// name `*.txt` matches says nothing.
+    Wildcard(Regex),
+}
+
+impl NameHighlighter {
+    pub(crate) fn new(expr: &Expr, options: SearchOptions) -> Self {
+        let mut highlighter = Self {
+            patterns: Vec::new(),
+            byte_exact: options.byte_exact,
+        };
+        highlighter.collect_expr(expr, options);
+        highlighter
+    }
+
+    fn collect_expr(&mut self, expr: &Expr, options: SearchOptions) {
+        match expr {
+            Expr::Empty | Expr::Not(_) => {}
+            Expr::And(parts) | Expr::Or(parts) => {
+                for part in parts {
+                    self.collect_expr(part, options);
+                }
+            }
+            Expr::Term(Term::Word(text) | Term::Phrase(text)) => {
+                let segments = query_segmentation(text);
+                let Some(last) = segments.last() else {
+                    return;
+                };
+                let value = segment_value(last);
+                let pattern = if value.contains(['*', '?']) {
+                    wildcard_capture_regex(value, options).map(NamePattern::Wildcard)
+                } else {
+                    build_segment_matchers(std::slice::from_ref(last), options)
+                        .ok()
+                        .and_then(|mut matchers| matchers.pop())
+                        .map(NamePattern::Matcher)
+                };
+                self.patterns.extend(pattern);
+            }
+            Expr::Term(Term::Regex(pattern)) => {
+                if let Ok(matcher) = regex_matcher(pattern, options) {
+                    self.patterns.push(NamePattern::Matcher(matcher));
+                }
+            }
+            Expr::Term(Term::Filter(_)) => {}
+        }
+    }
+
+    /// Byte ranges of `name` matched by the terms, sorted, with overlapping
+    /// and touching ones merged. They sit on char boundaries of `name` even
+    /// when it was compared in NFC.
+    pub(crate) fn ranges(&self, name: &str) -> Vec<(u32, u32)> {
+        if self.patterns.is_empty() {
+            return Vec::new();
+        }
+        let compared = if self.byte_exact {
+            Cow::Borrowed(name)
+        } else {
+            to_nfc(name)
+        };
+        let mut ranges: Vec<Range<usize>> = self
+            .patterns
+            .iter()
+            .flat_map(|pattern| pattern.ranges(&compared))
+            .collect();
+        if let Cow::Owned(normalized) = &compared {
+            let stops = nfc_stops(name, normalized);
+            for range in &mut ranges {
+                *range = widen_to_stops(range, &stops);
+            }
+        }
+        ranges.sort_unstable_by_key(|range| (range.start, range.end));
+        let mut merged: Vec<(u32, u32)> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            let (start, end) = (range.start as u32, range.end as u32);
+            match merged.last_mut() {
+                Some(last) if start <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((start, end)),
+            }
+        }
+        merged
+    }
+}
+
+impl NamePattern {
+    fn ranges(&self, candidate: &str) -> Vec<Range<usize>> {
+        match self {
+            NamePattern::Matcher(matcher) => matcher.match_ranges(candidate),
+            NamePattern::Wildcard(regex) => regex
+                .captures(candidate)
+                .map(|captures| {
+                    captures
+                        .iter()
+                        .skip(1)
+                        .flatten()
+                        .map(|group| group.range())
+                        .filter(|range| !range.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Like the wildcard matcher, `^...$`, with each run of literal characters in
+/// a capture group.
+fn wildcard_capture_regex(value: &str, options: SearchOptions) -> Option<Regex> {
+    let value = if options.byte_exact {
+        Cow::Borrowed(value)
+    } else {
+        to_nfc(value)
+    };
+    let mut pattern = String::from("^");
+    let mut literal = String::new();
+    for ch in value.chars() {
+        let wildcard = match ch {
+            '*' => ".*",
+            '?' => ".",
+            _ => {
+                literal.push(ch);
+                continue;
+            }
+        };
+        if !literal.is_empty() {
+            pattern.push_str(&format!("({})", regex::escape(&literal)));
+            literal.clear();
+        }
+        pattern.push_str(wildcard);
+    }
+    if !literal.is_empty() {
+        pattern.push_str(&format!("({})", regex::escape(&literal)));
+    }
+    pattern.push('$');
+    RegexBuilder::new(&pattern)
+        .case_insensitive(options.case_insensitive)
+        .build()
+        .ok()
+}
+
+/// Pairs of byte offsets, into `name` and into its NFC form `normalized`,
+/// where both have seen the same text: the char boundaries of `name` whose
+/// prefix normalizes to a prefix of `normalized`. Names are short, so
+/// normalizing every prefix is fine.
+fn nfc_stops(name: &str, normalized: &str) -> Vec<(usize, usize)> {
+    name.char_indices()
+        .map(|(offset, _)| offset)
+        .chain([name.len()])
+        .filter_map(|offset| {
+            let prefix = to_nfc(&name[..offset]);
+            normalized
+                .starts_with(prefix.as_ref())
+                .then_some((offset, prefix.len()))
+        })
+        .collect()
+}
+
+/// `range` of the NFC form as a range of the original name, grown to the
+/// closest stops around it so composed characters are covered whole.
+fn widen_to_stops(range: &Range<usize>, stops: &[(usize, usize)]) -> Range<usize> {
+    let start = stops
+        .iter()
+        .rev()
+        .find(|&&(_, normalized)| normalized <= range.start)
+        .map_or(0, |&(original, _)| original);
+    let end = stops
+        .iter()
+        .find(|&&(_, normalized)| normalized >= range.end)
+        .map_or(start, |&(original, _)| original);
+    start..end
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,173 @@
+use crate::SearchOptions;
+use hashbrown::HashMap;
+use namepool::to_nfc;
+use std::sync::{LazyLock, Mutex, MutexGuard, PoisonError};
+
+/// Word boundaries of every name `initials:` looked at, keyed by the address
+/// of the interned name. Pooled names are never removed nor changed, so an
+/// entry never needs invalidating.
+static BOUNDARIES: LazyLock<Mutex<HashMap<usize, Boundaries>>> = LazyLock::new(Default::default);
+
+#[cfg(test)]
+thread_local! {
+    /// How many times this thread extracted the boundaries of a name.
+    pub(crate) static BOUNDARY_COMPUTATIONS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+}
+
+/// Chars of a name's NFC form that start a word: bit `i` is set when the
+/// `i`th char does.
+#[derive(Debug, Default)]
+struct Boundaries {
+    /// Chars `0..64`.
+    head: u64,
+    /// Chars from 64 on, empty (and unallocated) for most names.
+    tail: Box<[u64]>,
+    /// Boundaries before the extension.
+    stem_words: u16,
+}
+
+impl Boundaries {
+    /// A word starts at the first letter or digit after a separator (any other
+    /// char, e.g. `_`, `-`, `.` or a space), at an uppercase letter following
+    /// a lowercase one (`searchCache`), at the last capital of a run followed
+    /// by a lowercase letter (`HTTPServer`), and wherever letters and digits
+    /// meet (`v2`).
+    fn of(name: &str) -> Self {
+        #[cfg(test)]
+        BOUNDARY_COMPUTATIONS.with(|computations| computations.set(computations.get() + 1));
+        let chars: Vec<char> = to_nfc(name).chars().collect();
+        let stem_end = chars
+            .iter()
+            .rposition(|&c| c == '.')
+            .filter(|&dot| dot > 0)
+            .unwrap_or(chars.len());
+        let mut boundaries = Self::default();
+        let mut tail = Vec::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if !c.is_alphanumeric() {
+                continue;
+            }
+            let starts_word = match i.checked_sub(1).map(|prev| chars[prev]) {
+                None => true,
+                Some(prev) if !prev.is_alphanumeric() => true,
+                Some(prev) => {
+                    prev.is_numeric() != c.is_numeric()
+                        || (c.is_uppercase() && prev.is_lowercase())
+                        || (c.is_uppercase()
+                            && prev.is_uppercase()
+                            && chars.get(i + 1).is_some_and(|next| next.is_lowercase()))
+                }
+            };
+            if !starts_word {
+                continue;
+            }
+            if i < 64 {
+                boundaries.head |= 1 << i;
+            } else {
+                let word = (i - 64) / 64;
+                tail.resize(tail.len().max(word + 1), 0);
+                tail[word] |= 1 << ((i - 64) % 64);
+            }
+            if i < stem_end {
+                boundaries.stem_words = boundaries.stem_words.saturating_add(1);
+            }
+        }
+        boundaries.tail = tail.into_boxed_slice();
+        boundaries
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        if i < 64 {
+            return self.head & (1 << i) != 0;
+        }
+        let i = i - 64;
+        self.tail
+            .get(i / 64)
+            .is_some_and(|bits| bits & (1 << (i % 64)) != 0)
+    }
+}
+
+/// How the word initials of a name match an `initials:` argument.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum InitialsMatch {
+    /// The argument is exactly the initials of the name without its extension.
+    Full,
+    /// The argument's letters are among the initials, in order.
+    Partial,
+}
+
+/// Letters and digits of an `initials:` argument.
+pub(crate) struct InitialsQuery {
+    letters: Vec<char>,
+    case_insensitive: bool,
+}
+
+impl InitialsQuery {
+    /// `None` if `argument` has no letter or digit.
+    pub(crate) fn new(argument: &str, options: SearchOptions) -> Option<Self> {
+        let letters: Vec<char> = to_nfc(argument)
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .map(|c| fold(c, options.case_insensitive))
+            .collect();
+        (!letters.is_empty()).then_some(Self {
+            letters,
+            case_insensitive: options.case_insensitive,
+        })
+    }
+}
+
+fn fold(c: char, case_insensitive: bool) -> char {
+    if case_insensitive {
+        c.to_lowercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+/// The boundary cache, locked for the length of a search term.
+pub(crate) struct InitialsCache(MutexGuard<'static, HashMap<usize, Boundaries>>);
+
+impl InitialsCache {
+    pub(crate) fn lock() -> Self {
+        Self(BOUNDARIES.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// How `name`, a name from the pool, matches `query`. Its boundaries are
+    /// extracted the first time any search asks.
+    pub(crate) fn classify(
+        &mut self,
+        query: &InitialsQuery,
+        name: &'static str,
+    ) -> Option<InitialsMatch> {
+        let boundaries = self
+            .0
+            .entry(name.as_ptr() as usize)
+            .or_insert_with(|| Boundaries::of(name));
+        let mut letters = query.letters.iter().peekable();
+        let mut initials = 0;
+        let mut exact = true;
+        for (i, c) in to_nfc(name).chars().enumerate() {
+            if !boundaries.contains(i) {
+                continue;
+            }
+            let Some(&&letter) = letters.peek() else {
+                break;
+            };
+            if fold(c, query.case_insensitive) == letter {
+                letters.next();
+            } else {
+                exact = false;
+            }
+            initials += 1;
+        }
+        if letters.peek().is_some() {
+            return None;
+        }
+        if exact && initials == usize::from(boundaries.stem_words) {
+            Some(InitialsMatch::Full)
+        } else {
+            Some(InitialsMatch::Partial)
+        }
+    }
+}
@@ -1,26 +1,94 @@
 #![feature(str_from_raw_parts)]
 mod cache;
+mod cache_set;
+mod checksum;
+mod compact;
+mod cursors;
+mod debug_snapshot;
+mod error;
+mod event_ids;
+mod export;
 mod file_nodes;
+mod filter_source;
+mod finder_tags;
+mod first_seen;
+mod folder_size;
+mod hidden;
 mod highlight;
+mod initials;
+mod link_checks;
+mod metadata_budget;
 mod metadata_cache;
+mod metadata_errors;
+mod mmap_cache;
 mod name_index;
+mod path_display;
 mod persistent;
 mod query;
+mod query_history;
+mod query_notes;
 mod query_preprocessor;
+mod query_variables;
+mod rank;
+mod renames;
+mod result_budget;
+mod scoring;
+#[cfg(feature = "snapshots")]
+mod search_snapshot;
 mod segment;
+mod set_ops;
+#[cfg(feature = "snapshots")]
+mod shared_name_map;
+#[cfg(feature = "snapshots")]
+mod shared_slab;
+mod size_buckets;
 mod slab;
 mod slab_node;
+mod sorting;
+mod subnodes;
+mod tags;
+#[cfg(any(test, feature = "testkit"))]
+pub mod testkit;
+mod tombstones;
 mod type_and_size;
 
 pub use cache::*;
+pub use cache_set::*;
+pub use cardinal_syntax::Precedence;
+pub use compact::*;
+pub use cursors::{CURSOR_CAPACITY, CURSOR_MEMORY_BUDGET, CursorId};
+pub use debug_snapshot::*;
+pub use error::*;
+pub use event_ids::{APPLIED_EVENT_IDS, EventIdStats};
+pub use export::*;
 pub use file_nodes::*;
-pub use fswalk::WalkData;
+pub use finder_tags::*;
+pub use first_seen::*;
+pub use folder_size::*;
+pub use fswalk::{WalkData, WalkProgress, WalkTotals};
 pub use metadata_cache::*;
+pub use metadata_errors::MetadataErrorCounts;
+pub use mmap_cache::*;
 pub use name_index::*;
+pub use path_display::*;
 pub use persistent::*;
+pub use query_history::*;
+pub use query_notes::*;
+pub use rank::*;
+pub use renames::{RECENT_RENAMES_CAPACITY, ValidationState};
+pub use scoring::*;
+#[cfg(feature = "snapshots")]
+pub use search_snapshot::*;
 pub use segment::*;
+#[cfg(feature = "snapshots")]
+pub use shared_slab::*;
+pub use size_buckets::*;
 pub use slab::*;
 pub use slab_node::*;
+pub use sorting::*;
+pub use subnodes::*;
+pub use tags::*;
+pub use tombstones::*;
 pub use type_and_size::*;
 
 #[cfg(test)]
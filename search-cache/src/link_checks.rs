@@ -0,0 +1,71 @@
+use crate::{SearchCache, SlabIndex};
+use fswalk::NodeFileType;
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::PoisonError,
+};
+
+/// Outcome of checking a symlink's target for `is:brokenlink`.
+#[derive(Debug, Clone)]
+pub(crate) struct LinkCheck {
+    /// Where the link pointed, absolute, so events there can drop the check.
+    target: PathBuf,
+    broken: bool,
+}
+
+impl SearchCache {
+    /// Whether `index` is a symlink whose target doesn't exist. Only symlinks
+    /// are read, and their result is kept until the link is replaced or an
+    /// event touches the path it pointed to.
+    pub fn is_broken_link(&self, index: SlabIndex) -> bool {
+        let cached = self
+            .link_checks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&index)
+            .map(|check| check.broken);
+        if let Some(broken) = cached {
+            return broken;
+        }
+        let is_symlink = self
+            .ensure_metadata(index)
+            .as_ref()
+            .is_some_and(|metadata| metadata.r#type() == NodeFileType::Symlink);
+        if !is_symlink {
+            return false;
+        }
+        let Some(path) = self.node_path(index) else {
+            return false;
+        };
+        let Ok(target) = fs::read_link(&path) else {
+            return false;
+        };
+        // A relative target is relative to the link's folder, `join` keeps an
+        // absolute one as it is.
+        let target = path.parent().unwrap_or(&path).join(target);
+        // Follows the whole chain; only a missing target makes the link broken,
+        // not one that can't be read.
+        let broken = matches!(fs::metadata(&path), Err(e) if e.kind() == ErrorKind::NotFound);
+        self.link_checks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(index, LinkCheck { target, broken });
+        broken
+    }
+
+    /// Drop the link checks whose target is at or below one of `paths`.
+    pub(crate) fn forget_link_checks_under<'p>(&mut self, paths: impl Iterator<Item = &'p Path>) {
+        let checks = self
+            .link_checks
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if checks.is_empty() {
+            return;
+        }
+        for path in paths {
+            checks.retain(|_, check| !check.target.starts_with(path));
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use crate::{FileNodes, IndexRemap, SlabIndex, cache::ExtrasTable};
+use hashbrown::HashSet;
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard, PoisonError},
+};
+
+/// Caps how many nodes hold fetched metadata, see
+/// [`crate::SearchCache::set_metadata_budget`]. A clock approximates LRU: the
+/// hand passes over nodes in the order their metadata was fetched, gives the
+/// ones read since its last pass a second chance and evicts the others.
+/// Evicting a node drops its [`crate::MetadataExtras`] from the cache's side
+/// table, the memory the budget actually gives back; the node's own words
+/// only revert to `State::None`.
+#[derive(Debug, Default)]
+pub(crate) struct MetadataBudget {
+    max_entries: Option<usize>,
+    /// Never evicted, e.g. the results on screen.
+    pinned: HashSet<SlabIndex>,
+    /// Grown by searches through `&self`, like the query history.
+    clock: Mutex<Clock>,
+}
+
+#[derive(Debug, Default)]
+struct Clock {
+    /// Tracked nodes, the hand is at the front.
+    ring: VecDeque<SlabIndex>,
+    evicted: usize,
+}
+
+impl MetadataBudget {
+    pub(crate) fn max_entries(&self) -> Option<usize> {
+        self.max_entries
+    }
+
+    pub(crate) fn evicted(&self) -> usize {
+        self.clock().evicted
+    }
+
+    /// Track every node of `nodes` holding metadata under the new budget and
+    /// evict what doesn't fit; `None` stops tracking.
+    pub(crate) fn set_max_entries(
+        &mut self,
+        max_entries: Option<usize>,
+        nodes: &FileNodes,
+        extras: &Mutex<ExtrasTable>,
+    ) {
+        self.max_entries = max_entries;
+        let mut clock = self.clock();
+        for index in clock.ring.drain(..) {
+            if let Some(node) = nodes.get(index) {
+                node.metadata.untrack();
+            }
+        }
+        if max_entries.is_none() {
+            return;
+        }
+        for (index, node) in nodes.iter() {
+            if node.metadata.is_some() && node.metadata.track() {
+                clock.ring.push_back(index);
+            }
+        }
+        self.trim(&mut clock, nodes, extras);
+    }
+
+    /// Start over on a new tree; pins don't carry over since its indices differ.
+    pub(crate) fn reset(&mut self, nodes: &FileNodes, extras: &Mutex<ExtrasTable>) {
+        self.pinned.clear();
+        self.set_max_entries(self.max_entries, nodes, extras);
+    }
+
+    /// Follow a [`crate::SearchCache::compact`], pins included.
+    pub(crate) fn remap(&mut self, remap: &IndexRemap) {
+        self.pinned = self
+            .pinned
+            .iter()
+            .filter_map(|&index| remap.get(index))
+            .collect();
+        let mut clock = self.clock();
+        clock.ring = clock
+            .ring
+            .iter()
+            .filter_map(|&index| remap.get(index))
+            .collect();
+    }
+
+    pub(crate) fn pin(&mut self, index: SlabIndex) {
+        self.pinned.insert(index);
+    }
+
+    pub(crate) fn unpin(&mut self, index: SlabIndex) {
+        self.pinned.remove(&index);
+    }
+
+    /// Record that `index` got its metadata and evict others if it's over budget.
+    /// Must not be called with `extras` locked.
+    pub(crate) fn fetched(&self, nodes: &FileNodes, extras: &Mutex<ExtrasTable>, index: SlabIndex) {
+        if self.max_entries.is_none() || !nodes[index].metadata.track() {
+            return;
+        }
+        let mut clock = self.clock();
+        clock.ring.push_back(index);
+        self.trim(&mut clock, nodes, extras);
+    }
+
+    fn trim(&self, clock: &mut Clock, nodes: &FileNodes, extras: &Mutex<ExtrasTable>) {
+        let Some(max_entries) = self.max_entries else {
+            return;
+        };
+        if clock.ring.len() <= max_entries {
+            return;
+        }
+        // Held across each eviction, so readers of a node's metadata see
+        // either the stored words with their extras or `State::None`.
+        let mut extras = extras.lock().unwrap_or_else(PoisonError::into_inner);
+        // Two passes at most, in case everything left is pinned.
+        let mut visits = clock.ring.len() * 2;
+        while clock.ring.len() > max_entries && visits > 0 {
+            visits -= 1;
+            let Some(index) = clock.ring.pop_front() else {
+                break;
+            };
+            // Removed nodes, and nodes whose slot was reused since, are dropped.
+            let Some(node) = nodes.get(index) else {
+                continue;
+            };
+            if !node.metadata.is_tracked() {
+                continue;
+            }
+            if !node.metadata.is_some() {
+                node.metadata.untrack();
+                continue;
+            }
+            if self.pinned.contains(&index) || node.metadata.take_reference() {
+                clock.ring.push_back(index);
+                continue;
+            }
+            if node.metadata.evict() {
+                extras.remove(&index);
+                clock.evicted += 1;
+                node.metadata.untrack();
+            } else {
+                // Fetched again in the meantime.
+                clock.ring.push_back(index);
+            }
+        }
+    }
+
+    fn clock(&self) -> MutexGuard<'_, Clock> {
+        self.clock.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
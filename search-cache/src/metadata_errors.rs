@@ -0,0 +1,109 @@
+use crate::SearchCache;
+use cardinal_sdk::{EventFlag, FsEvent};
+use serde::Serialize;
+use std::{
+    io::{self, ErrorKind},
+    mem,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+/// Nodes a search couldn't stat, by why, see
+/// [`crate::SearchOutcome::metadata_errors`]. Such nodes fail the `size:` and
+/// date filters as if they had no metadata.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataErrorCounts {
+    /// `EACCES` and `EPERM`, folders the process may not list: permissions,
+    /// or TCC on macOS until Full Disk Access is granted.
+    pub permission_denied: usize,
+    /// The node is gone from the disk but not from the tree yet, see
+    /// [`SearchCache::take_vanished`].
+    pub not_found: usize,
+    pub other: usize,
+}
+
+impl MetadataErrorCounts {
+    pub fn total(&self) -> usize {
+        self.permission_denied + self.not_found + self.other
+    }
+
+    /// Counts added to `self` since `earlier`.
+    pub(crate) fn since(self, earlier: Self) -> Self {
+        Self {
+            permission_denied: self
+                .permission_denied
+                .saturating_sub(earlier.permission_denied),
+            not_found: self.not_found.saturating_sub(earlier.not_found),
+            other: self.other.saturating_sub(earlier.other),
+        }
+    }
+
+    pub(crate) fn add(&mut self, other: Self) {
+        self.permission_denied += other.permission_denied;
+        self.not_found += other.not_found;
+        self.other += other.other;
+    }
+}
+
+/// Every failed stat of a cache since it was built. A search reports the
+/// difference between before and after it ran, so searches of one cache
+/// running at the same time count each other's errors.
+#[derive(Debug, Default)]
+pub(crate) struct MetadataErrors {
+    permission_denied: AtomicUsize,
+    not_found: AtomicUsize,
+    other: AtomicUsize,
+    /// Paths stat'ed as not found, for [`SearchCache::take_vanished`].
+    vanished: Mutex<Vec<PathBuf>>,
+}
+
+impl MetadataErrors {
+    pub(crate) fn record(&self, path: &Path, err: &io::Error) {
+        let counter = match err.kind() {
+            ErrorKind::PermissionDenied => &self.permission_denied,
+            ErrorKind::NotFound => {
+                self.vanished().push(path.to_path_buf());
+                &self.not_found
+            }
+            _ => &self.other,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn counts(&self) -> MetadataErrorCounts {
+        MetadataErrorCounts {
+            permission_denied: self.permission_denied.load(Ordering::Relaxed),
+            not_found: self.not_found.load(Ordering::Relaxed),
+            other: self.other.load(Ordering::Relaxed),
+        }
+    }
+
+    fn vanished(&self) -> std::sync::MutexGuard<'_, Vec<PathBuf>> {
+        self.vanished.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl SearchCache {
+    /// Removal events for the paths searches found missing on disk since the
+    /// last call, deleted while events were dropped or not delivered yet.
+    /// Hand them to [`Self::handle_fs_events`] like any batch: the paths are
+    /// stat'ed again there, so a node that came back in the meantime stays.
+    /// They carry [`Self::last_event_id`] and don't move it.
+    pub fn take_vanished(&self) -> Vec<FsEvent> {
+        let mut paths = mem::take(&mut *self.metadata_errors.vanished());
+        paths.sort();
+        paths.dedup();
+        paths
+            .into_iter()
+            .map(|path| FsEvent {
+                path,
+                flag: EventFlag::ItemRemoved,
+                id: self.last_event_id,
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,696 @@
+//! Read-only index served straight from a memory-mapped file, so the first
+//! queries don't wait for the zstd cache to be decoded into the heap.
+
+use crate::{
+    FileNodes, NAME_POOL, NameIndex, NodeSlab, RawNames, SearchCache, SearchError, SearchOptions,
+    SearchResult, SegmentMatcher, SlabIndex, SlabNode, SlabNodeMetadataCompact, State,
+    query::{phrase_matchers, regex_matcher},
+    query_preprocessor::expand_query_home_dirs,
+    query_variables::expand_variables,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use cardinal_sdk::{FsEvent, ScanType};
+use cardinal_syntax::{Expr, Term, optimize_query, parse_query_with_options};
+use fswalk::{Node, WalkData, split_file_name, walk_it};
+use hashbrown::{HashMap, HashSet};
+use memmap2::Mmap;
+use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
+use std::{
+    ffi::OsStr,
+    fs::{self, File},
+    io::{BufWriter, Write},
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::atomic::AtomicBool,
+    time::Instant,
+};
+use thin_vec::ThinVec;
+use tracing::info;
+
+/// Layout, all little endian:
+///
+/// - header: magic, version, node count, raw name count, last event id, names
+///   blob length, root path length, then the root path bytes;
+/// - node records, [`RECORD_LEN`] bytes each, in preorder with siblings sorted
+///   by name, the root first;
+/// - raw name table: `(node, len, offset)` for the names that aren't valid
+///   UTF-8, sorted by node;
+/// - the names blob, every node's (lossy) name back to back;
+/// - the raw names blob.
+const MAGIC: [u8; 8] = *b"CRDNLMAP";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 40;
+const RECORD_LEN: usize = 40;
+const RAW_ENTRY_LEN: usize = 16;
+const NO_NODE: u32 = u32::MAX;
+
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    name_offset: u64,
+    name_len: u32,
+    parent: u32,
+    first_child: u32,
+    next_sibling: u32,
+    /// [`SlabNodeMetadataCompact::to_words`], so type filters keep working
+    /// after [`MmapCache::promote`].
+    metadata: (u64, u32, u32),
+}
+
+impl Record {
+    fn to_bytes(self) -> [u8; RECORD_LEN] {
+        let mut bytes = [0; RECORD_LEN];
+        bytes[..8].copy_from_slice(&self.name_offset.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.name_len.to_le_bytes());
+        bytes[12..16].copy_from_slice(&self.parent.to_le_bytes());
+        bytes[16..20].copy_from_slice(&self.first_child.to_le_bytes());
+        bytes[20..24].copy_from_slice(&self.next_sibling.to_le_bytes());
+        let (state_type_and_size, ctime, mtime) = self.metadata;
+        bytes[24..32].copy_from_slice(&state_type_and_size.to_le_bytes());
+        bytes[32..36].copy_from_slice(&ctime.to_le_bytes());
+        bytes[36..40].copy_from_slice(&mtime.to_le_bytes());
+        bytes
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Self {
+        Self {
+            name_offset: u64_at(bytes, 0),
+            name_len: u32_at(bytes, 8),
+            parent: u32_at(bytes, 12),
+            first_child: u32_at(bytes, 16),
+            next_sibling: u32_at(bytes, 20),
+            metadata: (u64_at(bytes, 24), u32_at(bytes, 32), u32_at(bytes, 36)),
+        }
+    }
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(bytes: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap())
+}
+
+fn link(index: u32) -> Option<u32> {
+    (index != NO_NODE).then_some(index)
+}
+
+impl SearchCache {
+    /// Write the tree in the format [`SearchCache::open_mmap`] maps. Only names,
+    /// structure and the metadata already fetched are stored: the allocated
+    /// sizes, the query history and the other trailing sections of
+    /// [`Self::flush_to_file`] stay with the regular cache file, which this
+    /// doesn't replace.
+    pub fn flush_to_mmap_file(&self, path: &Path) -> Result<()> {
+        let write_time = Instant::now();
+        // Preorder with children sorted by their exact name is ascending path
+        // order, which `MmapCache::promote` relies on.
+        let sorted_children = |index: SlabIndex| {
+            let mut children = self.file_nodes[index].children.to_vec();
+            children.sort_unstable_by(|&a, &b| {
+                self.file_nodes
+                    .node_name(a)
+                    .cmp(self.file_nodes.node_name(b))
+            });
+            children
+        };
+        let mut order = Vec::with_capacity(self.file_nodes.len());
+        let mut ids = HashMap::with_capacity(self.file_nodes.len());
+        let mut stack = vec![self.file_nodes.root()];
+        while let Some(index) = stack.pop() {
+            let id = u32::try_from(order.len())
+                .ok()
+                .filter(|&id| id != NO_NODE)
+                .context("Too many nodes for an mmap index")?;
+            ids.insert(index, id);
+            order.push(index);
+            stack.extend(sorted_children(index).into_iter().rev());
+        }
+
+        let mut records = Vec::with_capacity(order.len());
+        let mut names = Vec::new();
+        let mut raw_table = Vec::new();
+        let mut raw_names = Vec::new();
+        for (id, &index) in order.iter().enumerate() {
+            let name_and_parent = &self.file_nodes[index].name_and_parent;
+            let name = name_and_parent.as_str();
+            records.push(Record {
+                name_offset: names.len() as u64,
+                name_len: u32::try_from(name.len()).context("File name too long")?,
+                parent: name_and_parent
+                    .parent()
+                    .map_or(NO_NODE, |parent| ids[&parent]),
+                first_child: NO_NODE,
+                next_sibling: NO_NODE,
+                metadata: self.file_nodes[index].metadata.get().to_words(),
+            });
+            names.extend_from_slice(name.as_bytes());
+            let exact = self.file_nodes.node_name(index).as_bytes();
+            if exact != name.as_bytes() {
+                raw_table.push((id as u32, exact.len() as u32, raw_names.len() as u64));
+                raw_names.extend_from_slice(exact);
+            }
+        }
+        for &index in &order {
+            let children = sorted_children(index);
+            if let Some(&first) = children.first() {
+                records[ids[&index] as usize].first_child = ids[&first];
+            }
+            for pair in children.windows(2) {
+                records[ids[&pair[0]] as usize].next_sibling = ids[&pair[1]];
+            }
+        }
+
+        let root = self.file_nodes.path().as_os_str().as_bytes();
+        let _ = fs::create_dir_all(path.parent().unwrap());
+        let tmp_path = path.with_extension("mmtmp");
+        {
+            let file = File::create(&tmp_path).context("Failed to create mmap index")?;
+            let mut output = BufWriter::new(file);
+            output.write_all(&MAGIC)?;
+            output.write_all(&VERSION.to_le_bytes())?;
+            output.write_all(&(records.len() as u32).to_le_bytes())?;
+            output.write_all(&(raw_table.len() as u32).to_le_bytes())?;
+            output.write_all(&self.last_event_id.to_le_bytes())?;
+            output.write_all(&(names.len() as u64).to_le_bytes())?;
+            output.write_all(&u32::try_from(root.len())?.to_le_bytes())?;
+            output.write_all(root)?;
+            for record in records {
+                output.write_all(&record.to_bytes())?;
+            }
+            for (id, len, offset) in raw_table {
+                output.write_all(&id.to_le_bytes())?;
+                output.write_all(&len.to_le_bytes())?;
+                output.write_all(&offset.to_le_bytes())?;
+            }
+            output.write_all(&names)?;
+            output.write_all(&raw_names)?;
+            output.flush().context("Failed to write mmap index")?;
+        }
+        fs::rename(&tmp_path, path).context("Failed to rename mmap index")?;
+        info!("Mmap index write time: {:?}", write_time.elapsed());
+        Ok(())
+    }
+
+    /// Map an index written by [`Self::flush_to_mmap_file`]. Only the header
+    /// and the node records are checked, nothing is copied to the heap.
+    /// `ignore_paths` and `cancel` are those of the walks of the overlay and
+    /// of the promoted cache, as in [`Self::try_read_persistent_cache`].
+    pub fn open_mmap(
+        path: &Path,
+        ignore_paths: Option<Vec<PathBuf>>,
+        cancel: Option<&'static AtomicBool>,
+    ) -> Result<MmapCache> {
+        let mut cache = MmapCache::open(path)?;
+        cache.ignore_paths = ignore_paths;
+        cache.stop = cancel;
+        Ok(cache)
+    }
+}
+
+/// An index served from a memory-mapped file, see [`SearchCache::open_mmap`].
+///
+/// Answers name queries (words, phrases, `regex:` and their AND/OR/NOT
+/// combinations) by scanning the node records. The records carry each node's
+/// state, type, size and times for the promoted cache, but filters aren't
+/// evaluated on the mapping and fail until the cache is
+/// [promoted](Self::promote). Events
+/// applied with [`Self::apply_fs_events`] go to an in-memory overlay on top of
+/// the read-only mapping.
+pub struct MmapCache {
+    mmap: Mmap,
+    root: PathBuf,
+    last_event_id: u64,
+    len: u32,
+    records_start: usize,
+    raw_table_start: usize,
+    raw_count: usize,
+    names_start: usize,
+    names_len: usize,
+    raw_names_start: usize,
+    ignore_paths: Option<Vec<PathBuf>>,
+    stop: Option<&'static AtomicBool>,
+    overlay: Overlay,
+}
+
+/// Changes since the file was written.
+#[derive(Debug, Default)]
+struct Overlay {
+    /// Records removed or replaced, their descendants included.
+    hidden: HashSet<u32>,
+    /// Paths that appeared or were rescanned, with their display names.
+    added: Vec<(PathBuf, Vec<Box<str>>)>,
+    /// Everything applied, replayed by [`MmapCache::promote`].
+    events: Vec<FsEvent>,
+}
+
+impl std::fmt::Debug for MmapCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MmapCache")
+            .field("root", &self.root)
+            .field("last_event_id", &self.last_event_id)
+            .field("len", &self.len)
+            .field("overlay.hidden.len()", &self.overlay.hidden.len())
+            .field("overlay.added.len()", &self.overlay.added.len())
+            .finish()
+    }
+}
+
+/// What a query compiles to: a predicate over a node's names.
+enum Matcher {
+    All,
+    Names(Vec<SegmentMatcher>),
+    Not(Box<Matcher>),
+    And(Vec<Matcher>),
+    Or(Vec<Matcher>),
+}
+
+impl Matcher {
+    fn compile(expr: &Expr, options: SearchOptions) -> SearchResult<Self> {
+        Ok(match expr {
+            Expr::Empty => Self::All,
+            Expr::Term(Term::Word(text) | Term::Phrase(text)) => {
+                Self::Names(phrase_matchers(text, options)?)
+            }
+            Expr::Term(Term::Regex(pattern)) => Self::Names(vec![regex_matcher(pattern, options)?]),
+            Expr::Term(Term::Filter(_)) => {
+                return Err(
+                    anyhow!("Filters are unavailable until the index is fully loaded").into(),
+                );
+            }
+            Expr::Not(inner) => Self::Not(Box::new(Self::compile(inner, options)?)),
+            Expr::And(parts) => Self::And(
+                parts
+                    .iter()
+                    .map(|part| Self::compile(part, options))
+                    .collect::<SearchResult<_>>()?,
+            ),
+            Expr::Or(parts) => Self::Or(
+                parts
+                    .iter()
+                    .map(|part| Self::compile(part, options))
+                    .collect::<SearchResult<_>>()?,
+            ),
+        })
+    }
+
+    /// `tail_matches` tells whether the last names on the node's path match
+    /// the given matchers one to one, like `SearchCache::node_tail_matches`.
+    fn matches(&self, tail_matches: &impl Fn(&[SegmentMatcher]) -> bool) -> bool {
+        match self {
+            Self::All => true,
+            Self::Names(matchers) => tail_matches(matchers),
+            Self::Not(inner) => !inner.matches(tail_matches),
+            Self::And(parts) => parts.iter().all(|part| part.matches(tail_matches)),
+            Self::Or(parts) => parts.iter().any(|part| part.matches(tail_matches)),
+        }
+    }
+}
+
+impl MmapCache {
+    fn open(path: &Path) -> Result<Self> {
+        let open_time = Instant::now();
+        let file = File::open(path).context("Failed to open mmap index")?;
+        // SAFETY: the file is only ever replaced by a rename, never written in place.
+        let mmap = unsafe { Mmap::map(&file) }.context("Failed to map mmap index")?;
+        if mmap.len() < HEADER_LEN || mmap[..8] != MAGIC {
+            bail!("Not an mmap index");
+        }
+        let version = u32_at(&mmap, 8);
+        if version != VERSION {
+            bail!("Unsupported mmap index version {version}");
+        }
+        let len = u32_at(&mmap, 12);
+        let raw_count = u32_at(&mmap, 16) as usize;
+        let last_event_id = u64_at(&mmap, 20);
+        let names_len = usize::try_from(u64_at(&mmap, 28))?;
+        let root_len = u32_at(&mmap, 36) as usize;
+        let records_start = HEADER_LEN + root_len;
+        let raw_table_start = records_start + len as usize * RECORD_LEN;
+        let names_start = raw_table_start + raw_count * RAW_ENTRY_LEN;
+        // The names length is read as is, a damaged one mustn't wrap around.
+        let Some(raw_names_start) = names_start.checked_add(names_len) else {
+            bail!("Mmap index names length {names_len} is out of range");
+        };
+        if len == 0 || mmap.len() < raw_names_start {
+            bail!("Mmap index is truncated");
+        }
+        let root = PathBuf::from(OsStr::from_bytes(&mmap[HEADER_LEN..records_start]));
+        let cache = Self {
+            mmap,
+            root,
+            last_event_id,
+            len,
+            records_start,
+            raw_table_start,
+            raw_count,
+            names_start,
+            names_len,
+            raw_names_start,
+            ignore_paths: None,
+            stop: None,
+            overlay: Overlay::default(),
+        };
+        cache.validate()?;
+        info!(
+            "Mmap index open time: {:?}, nodes: {}",
+            open_time.elapsed(),
+            cache.len
+        );
+        Ok(cache)
+    }
+
+    /// Check every offset and link once, so lookups can index without checks.
+    fn validate(&self) -> Result<()> {
+        let names = std::str::from_utf8(&self.mmap[self.names_start..self.raw_names_start])
+            .context("Mmap index names aren't UTF-8")?;
+        for id in 0..self.len {
+            let record = self.record(id);
+            let start = usize::try_from(record.name_offset)?;
+            let end = start + record.name_len as usize;
+            if end > self.names_len
+                || !names.is_char_boundary(start)
+                || !names.is_char_boundary(end)
+            {
+                bail!("Mmap index name of node {id} is out of bounds");
+            }
+            let links = [record.parent, record.first_child, record.next_sibling];
+            if links
+                .iter()
+                .any(|&link| link != NO_NODE && link >= self.len)
+            {
+                bail!("Mmap index links of node {id} are out of bounds");
+            }
+            // Preorder: parents come before their children, children and later
+            // siblings after, so following links always terminates.
+            let parent_ok = match link(record.parent) {
+                Some(parent) => parent < id,
+                None => id == 0,
+            };
+            let forward = [record.first_child, record.next_sibling]
+                .iter()
+                .all(|&link| link == NO_NODE || link > id);
+            if !parent_ok || !forward {
+                bail!("Mmap index links of node {id} aren't in preorder");
+            }
+            if State::n((record.metadata.0 >> 62) as u8).is_none() {
+                bail!("Mmap index metadata of node {id} is invalid");
+            }
+        }
+        let raw_names_len = self.mmap.len() - self.raw_names_start;
+        for entry in 0..self.raw_count {
+            let (id, len, offset) = self.raw_entry(entry);
+            if id >= self.len || offset.saturating_add(len as u64) > raw_names_len as u64 {
+                bail!("Mmap index raw name {entry} is out of bounds");
+            }
+        }
+        Ok(())
+    }
+
+    /// Root path of the indexed tree.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Number of nodes in the file, the overlay not included.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn record(&self, id: u32) -> Record {
+        let start = self.records_start + id as usize * RECORD_LEN;
+        Record::from_bytes(&self.mmap[start..start + RECORD_LEN])
+    }
+
+    fn raw_entry(&self, entry: usize) -> (u32, u32, u64) {
+        let bytes = &self.mmap[self.raw_table_start + entry * RAW_ENTRY_LEN..];
+        (u32_at(bytes, 0), u32_at(bytes, 4), u64_at(bytes, 8))
+    }
+
+    /// The (lossy) name queries match against.
+    fn name(&self, id: u32) -> &str {
+        let record = self.record(id);
+        let start = self.names_start + record.name_offset as usize;
+        // SAFETY: `validate` checked the blob is UTF-8 and the range is on char boundaries.
+        unsafe {
+            std::str::from_utf8_unchecked(&self.mmap[start..start + record.name_len as usize])
+        }
+    }
+
+    /// Exact on-disk name.
+    fn exact_name(&self, id: u32) -> &OsStr {
+        let found = self.raw_lookup(id);
+        match found {
+            Some((len, offset)) => {
+                let start = self.raw_names_start + offset as usize;
+                OsStr::from_bytes(&self.mmap[start..start + len as usize])
+            }
+            None => OsStr::new(self.name(id)),
+        }
+    }
+
+    fn raw_lookup(&self, id: u32) -> Option<(u32, u64)> {
+        let (mut low, mut high) = (0, self.raw_count);
+        while low < high {
+            let mid = (low + high) / 2;
+            let (node, len, offset) = self.raw_entry(mid);
+            match node.cmp(&id) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Some((len, offset)),
+            }
+        }
+        None
+    }
+
+    fn children(&self, id: u32) -> impl Iterator<Item = u32> + '_ {
+        std::iter::successors(link(self.record(id).first_child), |&child| {
+            link(self.record(child).next_sibling)
+        })
+    }
+
+    fn node_path(&self, id: u32) -> PathBuf {
+        let mut segments = Vec::new();
+        let mut current = id;
+        while let Some(parent) = link(self.record(current).parent) {
+            segments.push(self.exact_name(current));
+            current = parent;
+        }
+        self.root.iter().chain(segments.into_iter().rev()).collect()
+    }
+
+    /// The record at `relative` (to the root) as written, ignoring the overlay.
+    fn record_at(&self, relative: &Path) -> Option<u32> {
+        relative.components().try_fold(0, |current, component| {
+            let name = component.as_os_str();
+            self.children(current)
+                .find(|&child| self.exact_name(child) == name)
+        })
+    }
+
+    fn is_hidden(&self, id: u32) -> bool {
+        if self.overlay.hidden.is_empty() {
+            return false;
+        }
+        std::iter::successors(Some(id), |&id| link(self.record(id).parent))
+            .any(|id| self.overlay.hidden.contains(&id))
+    }
+
+    fn record_tail_matches(
+        &self,
+        id: u32,
+        matchers: &[SegmentMatcher],
+        options: SearchOptions,
+    ) -> bool {
+        let mut current = Some(id);
+        for matcher in matchers.iter().rev() {
+            let Some(id) = current else {
+                return false;
+            };
+            if !matcher.matches_name(self.name(id), options) {
+                return false;
+            }
+            current = link(self.record(id).parent);
+        }
+        true
+    }
+
+    /// Like [`Self::record_tail_matches`] for an overlay node: its own names
+    /// below the root, then the root record's.
+    fn overlay_tail_matches(
+        &self,
+        names: &[Box<str>],
+        matchers: &[SegmentMatcher],
+        options: SearchOptions,
+    ) -> bool {
+        let root_name = self.name(0);
+        let mut tail = names.iter().map(|name| &**name).rev().chain([root_name]);
+        matchers.iter().rev().all(|matcher| {
+            tail.next()
+                .is_some_and(|name| matcher.matches_name(name, options))
+        })
+    }
+
+    /// Paths of the nodes matching `line`, files from the overlay last.
+    /// Honors the case, byte-exact and precedence options; results aren't
+    /// ranked, scoped or recorded in any history.
+    pub fn search_paths(
+        &self,
+        line: &str,
+        options: SearchOptions,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<PathBuf>> {
+        let with_variables = expand_variables(line, self.root(), options)?;
+        let parsed = parse_query_with_options(&with_variables, options.parse_options())?;
+        let optimized = optimize_query(expand_query_home_dirs(parsed));
+        let matcher = Matcher::compile(&optimized.expr, options)?;
+        let search_time = Instant::now();
+        let mut paths = Vec::new();
+        for id in 0..self.len {
+            if id as usize % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
+                return Err(SearchError::Cancelled);
+            }
+            let tail_matches =
+                |matchers: &[SegmentMatcher]| self.record_tail_matches(id, matchers, options);
+            if matcher.matches(&tail_matches) && !self.is_hidden(id) {
+                paths.push(self.node_path(id));
+            }
+        }
+        for (path, names) in &self.overlay.added {
+            let tail_matches =
+                |matchers: &[SegmentMatcher]| self.overlay_tail_matches(names, matchers, options);
+            if matcher.matches(&tail_matches) {
+                paths.push(path.clone());
+            }
+        }
+        info!("Mmap search time: {:?}", search_time.elapsed());
+        Ok(paths)
+    }
+
+    /// Record an FSEvents batch in the overlay: the nodes at the event paths
+    /// are hidden, and whatever is there now is walked into the overlay. Events
+    /// that call for a full rescan are only kept for [`Self::promote`].
+    pub fn apply_fs_events(&mut self, events: Vec<FsEvent>) {
+        let walk_data = WalkData::builder()
+            .ignore_directories(self.ignore_paths.clone())
+            .cancel(self.stop)
+            .build()
+            .expect("walk options without limits are valid");
+        for event in &events {
+            if !matches!(
+                event.flag.scan_type(),
+                ScanType::SingleNode | ScanType::Folder
+            ) {
+                continue;
+            }
+            let Ok(relative) = event.path.strip_prefix(&self.root) else {
+                continue;
+            };
+            if relative.as_os_str().is_empty() {
+                continue;
+            }
+            if let Some(id) = self.record_at(relative) {
+                self.overlay.hidden.insert(id);
+            }
+            self.overlay
+                .added
+                .retain(|(path, _)| !path.starts_with(&event.path));
+            if event.path.symlink_metadata().is_err() {
+                continue;
+            }
+            if let Some(node) = walk_it(&event.path, &walk_data) {
+                let names = relative
+                    .components()
+                    .map(|component| split_file_name(component.as_os_str()).0)
+                    .collect();
+                push_overlay_nodes(&mut self.overlay.added, event.path.clone(), names, &node);
+            }
+        }
+        self.overlay.events.extend(events);
+    }
+
+    /// Build the regular heap-backed cache from the mapping and replay the
+    /// events applied so far, e.g. on a background thread while this one keeps
+    /// answering queries. Events applied after the call have to be handed to
+    /// the returned cache as well. Allocated sizes and link counts are fetched
+    /// again on demand. The cache walks with the ignore paths and cancel flag
+    /// given to [`SearchCache::open_mmap`].
+    pub fn promote(&self) -> SearchCache {
+        let promote_time = Instant::now();
+        let mut slab = NodeSlab::new();
+        let mut name_index = NameIndex::default();
+        for id in 0..self.len {
+            let record = self.record(id);
+            let parent = link(record.parent).map(|parent| SlabIndex::new(parent as usize));
+            let name = NAME_POOL.push(self.name(id));
+            let metadata = SlabNodeMetadataCompact::from_words(record.metadata);
+            let index = slab.insert(SlabNode::new(parent, name, metadata));
+            debug_assert_eq!(index.get(), id as usize);
+            unsafe {
+                // SAFETY: records are in ascending path order, see `flush_to_mmap_file`.
+                name_index.add_index_ordered(name, index);
+            }
+        }
+        for id in 0..self.len {
+            let children: ThinVec<SlabIndex> = self
+                .children(id)
+                .map(|child| SlabIndex::new(child as usize))
+                .collect();
+            slab[SlabIndex::new(id as usize)].children = children;
+        }
+        let raw_names: RawNames = (0..self.raw_count)
+            .map(|entry| {
+                let (id, _, _) = self.raw_entry(entry);
+                (
+                    SlabIndex::new(id as usize),
+                    self.exact_name(id).as_bytes().into(),
+                )
+            })
+            .collect();
+        let file_nodes = FileNodes::new(self.root.clone(), slab, SlabIndex::new(0), raw_names);
+        let mut cache = SearchCache::new(
+            file_nodes,
+            self.last_event_id,
+            name_index,
+            self.ignore_paths.clone(),
+            self.stop,
+        );
+        let events: Vec<FsEvent> = self
+            .overlay
+            .events
+            .iter()
+            .map(|event| FsEvent {
+                path: event.path.clone(),
+                flag: event.flag,
+                id: event.id,
+            })
+            .collect();
+        if !events.is_empty() && cache.handle_fs_events(events).is_err() {
+            cache.rescan();
+        }
+        info!("Mmap promote time: {:?}", promote_time.elapsed());
+        cache
+    }
+}
+
+fn push_overlay_nodes(
+    added: &mut Vec<(PathBuf, Vec<Box<str>>)>,
+    path: PathBuf,
+    names: Vec<Box<str>>,
+    node: &Node,
+) {
+    for child in &node.children {
+        let child_name = match &child.raw_name {
+            Some(raw) => OsStr::from_bytes(raw),
+            None => OsStr::new(&*child.name),
+        };
+        let mut child_names = names.clone();
+        child_names.push(child.name.clone());
+        push_overlay_nodes(added, path.join(child_name), child_names, child);
+    }
+    added.push((path, names));
+}
@@ -1,7 +1,7 @@
-use crate::{FileNodes, NAME_POOL, SlabIndex};
+use crate::{FileNodes, IndexRemap, NAME_POOL, SlabIndex};
 use itertools::Itertools;
 use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, ser::SerializeMap};
 use std::{collections::BTreeMap, time::Instant};
 use thin_vec::ThinVec;
 use tracing::info;
@@ -32,17 +32,22 @@ impl SortedSlabIndices {
         self.indices.iter()
     }
 
+    /// Insert `index` at its place by full path. A node sharing the path of
+    /// one listed already, like a rescanned node next to the one it replaces
+    /// until that one is removed, goes after it.
     pub fn insert(&mut self, index: SlabIndex, slab: &FileNodes) {
         let Some(target_path) = slab.node_path(index) else {
             return;
         };
-        if let Err(pos) = self.indices.binary_search_by(|existing| {
+        if self.indices.contains(&index) {
+            return;
+        }
+        let pos = self.indices.partition_point(|existing| {
             slab.node_path(*existing)
                 .expect("node in name index must resolve to a path")
-                .cmp(&target_path)
-        }) {
-            self.indices.insert(pos, index);
-        }
+                <= target_path
+        });
+        self.indices.insert(pos, index);
     }
 
     /// # Safety
@@ -52,6 +57,15 @@ impl SortedSlabIndices {
         self.indices.push(index);
     }
 
+    /// Path order doesn't change with the indexes, so neither does the order here.
+    fn remap(&mut self, remap: &IndexRemap) {
+        self.indices = self
+            .indices
+            .iter()
+            .filter_map(|&index| remap.get(index))
+            .collect();
+    }
+
     pub fn remove(&mut self, index: SlabIndex) -> bool {
         if let Some(pos) = self.indices.iter().position(|&existing| existing == index) {
             self.indices.remove(pos);
@@ -62,9 +76,35 @@ impl SortedSlabIndices {
     }
 }
 
+/// The nodes bearing each name, which searches go through once they found
+/// the matching names in [`NAME_POOL`]. Many nodes share a name (every
+/// `index.js`), so this is a multimap.
+///
+/// Every change of the tree keeps it so that, as
+/// [`crate::SearchCache::verify_name_index`] checks:
+/// - every node of the slab is listed exactly once, under its own name;
+/// - nothing else is listed, and no name is left with an empty list;
+/// - each list is ordered by the full paths of its nodes.
 #[derive(Clone, Default)]
 pub struct NameIndex {
-    map: BTreeMap<&'static str, SortedSlabIndices>,
+    map: NameMap,
+}
+
+#[cfg(not(feature = "snapshots"))]
+type NameMap = BTreeMap<&'static str, SortedSlabIndices>;
+/// Shared chunk by chunk with the [`crate::SearchSnapshot`]s taken since.
+#[cfg(feature = "snapshots")]
+type NameMap = crate::shared_name_map::SharedNameMap;
+
+/// Encoded like [`NameIndex::into_persistent`]'s map, without copying the names.
+impl Serialize for NameIndex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.map.len()))?;
+        for (name, indices) in self.map.iter() {
+            map.serialize_entry(name, indices)?;
+        }
+        map.end()
+    }
 }
 
 impl NameIndex {
@@ -92,6 +132,19 @@ impl NameIndex {
             .ok()
     }
 
+    /// Follow a [`crate::SearchCache::compact`]; names are kept in the pool.
+    pub(crate) fn remap(&mut self, remap: &IndexRemap) {
+        for indices in self.map.values_mut() {
+            indices.remap(remap);
+        }
+        self.map.retain(|_, indices| !indices.is_empty());
+    }
+
+    /// Names in order, with their nodes.
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&'static str, &SortedSlabIndices)> {
+        self.map.iter().map(|(&name, indices)| (name, indices))
+    }
+
     pub fn get(&self, name: &str) -> Option<&SortedSlabIndices> {
         self.map.get(name)
     }
@@ -104,32 +157,35 @@ impl NameIndex {
     ///
     /// The index must be inserted with it's full path ordered.
     pub unsafe fn add_index_ordered(&mut self, name: &str, index: SlabIndex) {
-        if let Some(existing) = self.map.get_mut(name) {
+        let map = &mut self.map;
+        if let Some(existing) = map.get_mut(name) {
             unsafe {
                 existing.insert_ordered(index);
             }
         } else {
             let interned = NAME_POOL.push(name);
-            self.map.insert(interned, SortedSlabIndices::new(index));
+            map.insert(interned, SortedSlabIndices::new(index));
         }
     }
 
     pub fn add_index(&mut self, name: &str, index: SlabIndex, slab: &FileNodes) {
-        if let Some(existing) = self.map.get_mut(name) {
+        let map = &mut self.map;
+        if let Some(existing) = map.get_mut(name) {
             existing.insert(index, slab);
         } else {
             let interned = NAME_POOL.push(name);
-            self.map.insert(interned, SortedSlabIndices::new(index));
+            map.insert(interned, SortedSlabIndices::new(index));
         }
     }
 
     pub fn remove_index(&mut self, name: &str, index: SlabIndex) -> bool {
-        let Some(indices) = self.map.get_mut(name) else {
+        let map = &mut self.map;
+        let Some(indices) = map.get_mut(name) else {
             return false;
         };
         let removed = indices.remove(index);
         if indices.is_empty() {
-            self.map.remove(name);
+            map.remove(name);
         }
         removed
     }
@@ -147,7 +203,7 @@ impl NameIndex {
 
     pub fn construct_name_pool(data: BTreeMap<Box<str>, SortedSlabIndices>) -> Self {
         let name_pool_time = Instant::now();
-        let mut map = BTreeMap::new();
+        let mut map = NameMap::default();
         for (name, indices) in data {
             let interned = NAME_POOL.push(&name);
             map.insert(interned, indices);
@@ -159,4 +215,16 @@ impl NameIndex {
         );
         Self { map }
     }
+
+    /// Chunks of the map `self` and `other` still share, out of
+    /// [`Self::chunk_count`].
+    #[cfg(feature = "snapshots")]
+    pub(crate) fn shared_chunks(&self, other: &Self) -> usize {
+        self.map.shared_chunks(&other.map)
+    }
+
+    #[cfg(feature = "snapshots")]
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.map.chunk_count()
+    }
 }
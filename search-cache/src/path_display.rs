@@ -0,0 +1,74 @@
+use crate::query_preprocessor::home_dir;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// How [`crate::SearchResultNode::display_path`] shows result paths. The
+/// absolute `path` is kept either way, for acting on the results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PathDisplay {
+    /// The whole path, with the home directory abbreviated to `~`.
+    #[default]
+    Absolute,
+    /// Relative to the watch root, `.` for the root itself.
+    RootRelative,
+    /// Relative to the innermost folder the search was scoped to, by
+    /// [`crate::SearchOptions::scope_to`] or an `infolder:`/`parent:` filter
+    /// ANDed with the rest of the query; relative to the root without one.
+    ScopeRelative,
+}
+
+/// Turns result paths into display strings, see [`PathDisplay`].
+#[derive(Debug, Clone)]
+pub(crate) struct PathDisplayer {
+    /// The folder paths are shown relative to, `None` for absolute paths.
+    base: Option<PathBuf>,
+    home: Option<PathBuf>,
+}
+
+impl PathDisplayer {
+    pub(crate) fn absolute() -> Self {
+        Self::new(None)
+    }
+
+    pub(crate) fn relative_to(base: PathBuf) -> Self {
+        Self::new(Some(base))
+    }
+
+    fn new(base: Option<PathBuf>) -> Self {
+        Self {
+            base,
+            home: home_dir().map(PathBuf::from),
+        }
+    }
+
+    /// `path` for display, non-UTF-8 bytes replaced. Paths outside the base
+    /// folder are shown as absolute ones.
+    pub(crate) fn display(&self, path: &Path) -> String {
+        if let Some(relative) = self
+            .base
+            .as_deref()
+            .and_then(|base| path.strip_prefix(base).ok())
+        {
+            return if relative.as_os_str().is_empty() {
+                ".".to_string()
+            } else {
+                relative.to_string_lossy().into_owned()
+            };
+        }
+        display_absolute(path, self.home.as_deref())
+    }
+}
+
+/// `path` with `home` abbreviated to `~`.
+pub(crate) fn display_absolute(path: &Path, home: Option<&Path>) -> String {
+    // A home of `/` would abbreviate everything.
+    let relative = home
+        .filter(|home| home.parent().is_some())
+        .and_then(|home| path.strip_prefix(home).ok());
+    match relative {
+        Some(relative) if relative.as_os_str().is_empty() => "~".to_string(),
+        Some(relative) => format!("~/{}", relative.to_string_lossy()),
+        None => path.to_string_lossy().into_owned(),
+    }
+}
@@ -1,18 +1,95 @@
-use crate::{SlabIndex, SlabNode, ThinSlab, name_index::SortedSlabIndices};
-use anyhow::{Context, Result};
+use crate::{
+    AccessLog, CacheError, NodeSlab, QueryHistory, RawNames, SlabIndex, Tags, Tombstones,
+    checksum::ChecksumWriter, name_index::SortedSlabIndices,
+};
+use anyhow::{Context, Result, anyhow};
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
     fs::{self, File},
-    io::{BufReader, BufWriter},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
     path::{Path, PathBuf},
     thread::available_parallelism,
     time::Instant,
 };
-use tracing::info;
+use tracing::{info, warn};
 use typed_num::Num;
 
-const LSF_VERSION: i64 = 2;
+const LSF_VERSION: i64 = 3;
+
+/// Files start with an envelope: magic, envelope version, then the length and
+/// XXH64 of the zstd payload that follows, all little endian. Files without
+/// the magic predate the envelope and are read as a bare zstd stream.
+const ENVELOPE_MAGIC: [u8; 8] = *b"CRDNLCCH";
+const ENVELOPE_VERSION: u32 = 1;
+const ENVELOPE_LEN: usize = 28;
+
+struct Envelope {
+    payload_len: u64,
+    checksum: u64,
+}
+
+impl Envelope {
+    fn to_bytes(&self) -> [u8; ENVELOPE_LEN] {
+        let mut bytes = [0; ENVELOPE_LEN];
+        bytes[..8].copy_from_slice(&ENVELOPE_MAGIC);
+        bytes[8..12].copy_from_slice(&ENVELOPE_VERSION.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes[20..28].copy_from_slice(&self.checksum.to_le_bytes());
+        bytes
+    }
+
+    /// `Ok(None)` when the file doesn't start with the magic.
+    fn read(input: &mut File) -> Result<Option<Self>, CacheError> {
+        let mut bytes = [0; ENVELOPE_LEN];
+        let mut filled = 0;
+        while filled < ENVELOPE_LEN {
+            match input.read(&mut bytes[filled..]) {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    return Err(anyhow::Error::new(e)
+                        .context("Failed to read cache file")
+                        .into());
+                }
+            }
+        }
+        if filled < ENVELOPE_MAGIC.len() || bytes[..8] != ENVELOPE_MAGIC {
+            return Ok(None);
+        }
+        if filled < ENVELOPE_LEN {
+            return Err(anyhow!("Cache file envelope is truncated").into());
+        }
+        let version = u32::from_le_bytes(bytes[8..12].try_into().unwrap());
+        if version != ENVELOPE_VERSION {
+            return Err(anyhow!("Unsupported cache envelope version {version}").into());
+        }
+        Ok(Some(Self {
+            payload_len: u64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+            checksum: u64::from_le_bytes(bytes[20..28].try_into().unwrap()),
+        }))
+    }
+
+    /// Hashes the rest of `input`, which must be exactly the payload the
+    /// envelope describes.
+    fn verify(&self, input: &mut File) -> Result<(), CacheError> {
+        let mut hasher = ChecksumWriter::new(io::sink());
+        io::copy(input, &mut hasher).context("Failed to read cache file")?;
+        let (_, payload_len, checksum) = hasher.finish();
+        if payload_len != self.payload_len || checksum != self.checksum {
+            warn!(
+                "Cache payload mismatch: {payload_len} bytes with checksum {checksum:016x}, expected {} bytes with {:016x}",
+                self.payload_len, self.checksum
+            );
+            return Err(CacheError::Corrupt {
+                expected: self.checksum,
+                actual: checksum,
+            });
+        }
+        Ok(())
+    }
+}
 
 #[derive(Serialize, Deserialize)]
 pub struct PersistentStorage {
@@ -23,36 +100,302 @@ pub struct PersistentStorage {
     pub path: PathBuf,
     /// Root index of the slab
     pub slab_root: SlabIndex,
-    pub slab: ThinSlab<SlabNode>,
+    pub slab: NodeSlab,
     pub name_index: BTreeMap<Box<str>, SortedSlabIndices>,
+    pub raw_names: RawNames,
+    /// Stored as a separate trailing section so that a missing or unreadable
+    /// history never invalidates the file tree.
+    #[serde(skip)]
+    pub query_history: QueryHistory,
+    /// Trailing section after the query history, for the same reason.
+    #[serde(skip)]
+    pub tombstones: Tombstones,
+    /// UUID of the volume's FSEvents stream when the file was written, the
+    /// trailing section after the tombstones. `None` for older files and
+    /// volumes without an event history: the event id can then only be checked
+    /// against the current one.
+    #[serde(skip)]
+    pub event_stream_uuid: Option<[u8; 16]>,
+    /// Allocated sizes of the nodes with fetched metadata, the trailing
+    /// section after the event stream UUID. Older files lack it and their
+    /// nodes' allocated size stays unknown.
+    #[serde(skip)]
+    pub allocated_sizes: Vec<(SlabIndex, u64)>,
+    /// `(index, inode, nlink)` of the nodes with fetched metadata, the
+    /// trailing section after the allocated sizes. Older files lack it, their
+    /// nodes are stat'ed again when the inode is needed.
+    #[serde(skip)]
+    pub links: Vec<(SlabIndex, u64, u32)>,
+    /// Nodes the user opened, the trailing section after the links. Older
+    /// files start with an empty log.
+    #[serde(skip)]
+    pub access_log: AccessLog,
+    /// First-seen stamps of the nodes FSEvents reported, the trailing section
+    /// after the access log. Older files have none.
+    #[serde(skip)]
+    pub first_seen: Vec<(SlabIndex, i64)>,
+    /// User tags, the trailing section after the first-seen stamps. Older
+    /// files have none.
+    #[serde(skip)]
+    pub tags: Tags,
+    /// Nonzero file flags of the nodes with fetched metadata, the last
+    /// trailing section. Older files lack it and their hidden nodes count as
+    /// hidden by name only until they are stat'ed again.
+    #[serde(skip)]
+    pub file_flags: Vec<(SlabIndex, u8)>,
+}
+
+/// Borrowed form of [`PersistentStorage`], encoded to the same bytes, so a
+/// cache can be written without taking it apart.
+#[derive(Serialize)]
+pub(crate) struct StorageSections<'a, N> {
+    pub version: Num<LSF_VERSION>,
+    pub last_event_id: u64,
+    pub path: &'a Path,
+    pub slab_root: SlabIndex,
+    pub slab: &'a NodeSlab,
+    pub name_index: &'a N,
+    pub raw_names: &'a RawNames,
+    #[serde(skip)]
+    pub query_history: &'a QueryHistory,
+    #[serde(skip)]
+    pub tombstones: &'a Tombstones,
+    #[serde(skip)]
+    pub event_stream_uuid: Option<[u8; 16]>,
+    #[serde(skip)]
+    pub allocated_sizes: &'a [(SlabIndex, u64)],
+    #[serde(skip)]
+    pub links: &'a [(SlabIndex, u64, u32)],
+    #[serde(skip)]
+    pub access_log: &'a AccessLog,
+    #[serde(skip)]
+    pub first_seen: &'a [(SlabIndex, i64)],
+    #[serde(skip)]
+    pub tags: &'a Tags,
+    #[serde(skip)]
+    pub file_flags: &'a [(SlabIndex, u8)],
+}
+
+impl PersistentStorage {
+    fn sections(&self) -> StorageSections<'_, BTreeMap<Box<str>, SortedSlabIndices>> {
+        StorageSections {
+            version: Num,
+            last_event_id: self.last_event_id,
+            path: &self.path,
+            slab_root: self.slab_root,
+            slab: &self.slab,
+            name_index: &self.name_index,
+            raw_names: &self.raw_names,
+            query_history: &self.query_history,
+            tombstones: &self.tombstones,
+            event_stream_uuid: self.event_stream_uuid,
+            allocated_sizes: &self.allocated_sizes,
+            links: &self.links,
+            access_log: &self.access_log,
+            first_seen: &self.first_seen,
+            tags: &self.tags,
+            file_flags: &self.file_flags,
+        }
+    }
+}
+
+/// A cache encoded in memory by [`crate::SearchCache::snapshot`], to be
+/// compressed and written while the cache goes on changing.
+pub struct CacheSnapshot {
+    bytes: Vec<u8>,
+    last_event_id: u64,
+}
+
+impl CacheSnapshot {
+    pub(crate) fn encode<N: Serialize>(storage: &StorageSections<'_, N>) -> Result<Self> {
+        let mut bytes = Vec::new();
+        encode_storage(&mut bytes, storage)?;
+        Ok(Self {
+            bytes,
+            last_event_id: storage.last_event_id,
+        })
+    }
+
+    /// Event id the cache had seen when the snapshot was taken.
+    pub fn last_event_id(&self) -> u64 {
+        self.last_event_id
+    }
+
+    /// Size of the encoded, uncompressed cache.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Write the snapshot in the format of [`crate::SearchCache::flush_to_file`],
+    /// through a temporary file renamed over `path`.
+    pub fn write_to_file(&self, path: &Path) -> Result<()> {
+        write_payload(path, |output| {
+            output
+                .write_all(&self.bytes)
+                .context("Failed to write cache snapshot")
+        })
+        .context("Write cache snapshot to file failed.")
+    }
 }
 
-pub fn read_cache_from_file(path: &Path) -> Result<PersistentStorage> {
+/// A stored event id can only be resumed from on the same FSEvents stream, and
+/// only if the stream's ids haven't gone back past it. An unknown UUID on either
+/// side leaves the event id check alone.
+pub(crate) fn check_event_stream(
+    stored_uuid: Option<[u8; 16]>,
+    stored_event_id: u64,
+    current_uuid: Option<[u8; 16]>,
+    current_event_id: u64,
+) -> Result<(), CacheError> {
+    let same_stream = match (stored_uuid, current_uuid) {
+        (Some(stored), Some(current)) => stored == current,
+        _ => true,
+    };
+    if same_stream && stored_event_id <= current_event_id {
+        Ok(())
+    } else {
+        warn!(
+            "Event stream reset: stored {stored_uuid:?}@{stored_event_id}, current {current_uuid:?}@{current_event_id}"
+        );
+        Err(CacheError::EventStreamReset)
+    }
+}
+
+/// Fails with [`CacheError::Corrupt`] when the payload doesn't match the
+/// envelope's length and checksum; nothing is decompressed in that case.
+pub fn read_cache_from_file(path: &Path) -> Result<PersistentStorage, CacheError> {
     let cache_decode_time = Instant::now();
     let mut bytes = vec![0u8; 4 * 1024];
-    let input = File::open(path).context("Failed to open cache file")?;
+    let mut input = File::open(path).context("Failed to open cache file")?;
+    match Envelope::read(&mut input)? {
+        Some(envelope) => {
+            envelope.verify(&mut input)?;
+            input
+                .seek(SeekFrom::Start(ENVELOPE_LEN as u64))
+                .context("Failed to seek cache file")?;
+        }
+        None => {
+            info!("Cache file has no envelope, reading it unverified");
+            input.rewind().context("Failed to seek cache file")?;
+        }
+    }
     let input = zstd::Decoder::new(input).context("Failed to create zstd decoder")?;
     let mut input = BufReader::new(input);
-    let storage: PersistentStorage = postcard::from_io((&mut input, &mut bytes))
-        .context("Failed to decode cache, maybe the cache is corrupted")?
-        .0;
+    let (mut storage, (mut input, bytes)): (PersistentStorage, _) =
+        postcard::from_io((&mut input, &mut bytes))
+            .context("Failed to decode cache, maybe the cache is corrupted")?;
+    match postcard::from_io::<QueryHistory, _>((&mut input, &mut *bytes)) {
+        Ok((query_history, _)) => storage.query_history = query_history,
+        Err(e) => warn!("Query history section unreadable, starting empty: {e:?}"),
+    }
+    match postcard::from_io::<Tombstones, _>((&mut input, &mut *bytes)) {
+        Ok((tombstones, _)) => storage.tombstones = tombstones,
+        Err(e) => warn!("Tombstone section unreadable, starting empty: {e:?}"),
+    }
+    match postcard::from_io::<Option<[u8; 16]>, _>((&mut input, &mut *bytes)) {
+        Ok((uuid, _)) => storage.event_stream_uuid = uuid,
+        Err(e) => warn!("Event stream section unreadable, event id is unverified: {e:?}"),
+    }
+    match postcard::from_io::<Vec<(SlabIndex, u64)>, _>((&mut input, &mut *bytes)) {
+        Ok((allocated_sizes, _)) => storage.allocated_sizes = allocated_sizes,
+        Err(e) => warn!("Allocated size section unreadable, sizes are refetched: {e:?}"),
+    }
+    match postcard::from_io::<Vec<(SlabIndex, u64, u32)>, _>((&mut input, &mut *bytes)) {
+        Ok((links, _)) => storage.links = links,
+        Err(e) => warn!("Link section unreadable, inodes are refetched: {e:?}"),
+    }
+    match postcard::from_io::<AccessLog, _>((&mut input, &mut *bytes)) {
+        Ok((access_log, _)) => storage.access_log = access_log,
+        Err(e) => warn!("Access log section unreadable, starting empty: {e:?}"),
+    }
+    match postcard::from_io::<Vec<(SlabIndex, i64)>, _>((&mut input, &mut *bytes)) {
+        Ok((first_seen, _)) => storage.first_seen = first_seen,
+        Err(e) => warn!("First seen section unreadable, starting empty: {e:?}"),
+    }
+    match postcard::from_io::<Tags, _>((&mut input, &mut *bytes)) {
+        Ok((tags, _)) => storage.tags = tags,
+        Err(e) => warn!("Tag section unreadable, starting without tags: {e:?}"),
+    }
+    match postcard::from_io::<Vec<(SlabIndex, u8)>, _>((&mut input, bytes)) {
+        Ok((file_flags, _)) => storage.file_flags = file_flags,
+        Err(e) => warn!("File flag section unreadable, flags are refetched: {e:?}"),
+    }
     info!("Cache decode time: {:?}", cache_decode_time.elapsed());
     Ok(storage)
 }
 
 pub fn write_cache_to_file(path: &Path, storage: PersistentStorage) -> Result<()> {
+    write_storage_to_file(path, &storage.sections())
+}
+
+pub(crate) fn write_storage_to_file<N: Serialize>(
+    path: &Path,
+    storage: &StorageSections<'_, N>,
+) -> Result<()> {
+    write_payload(path, |output| encode_storage(output, storage))
+}
+
+/// Write every section of `storage`, uncompressed, in the order
+/// [`read_cache_from_file`] reads them.
+fn encode_storage<N: Serialize>(
+    mut output: impl Write,
+    storage: &StorageSections<'_, N>,
+) -> Result<()> {
+    postcard::to_io(storage, &mut output).context("Failed to encode cache")?;
+    postcard::to_io(storage.query_history, &mut output)
+        .context("Failed to encode query history")?;
+    postcard::to_io(storage.tombstones, &mut output).context("Failed to encode tombstones")?;
+    postcard::to_io(&storage.event_stream_uuid, &mut output)
+        .context("Failed to encode event stream uuid")?;
+    postcard::to_io(storage.allocated_sizes, &mut output)
+        .context("Failed to encode allocated sizes")?;
+    postcard::to_io(storage.links, &mut output).context("Failed to encode links")?;
+    postcard::to_io(storage.access_log, &mut output).context("Failed to encode access log")?;
+    postcard::to_io(storage.first_seen, &mut output)
+        .context("Failed to encode first seen stamps")?;
+    postcard::to_io(storage.tags, &mut output).context("Failed to encode tags")?;
+    postcard::to_io(storage.file_flags, &mut output).context("Failed to encode file flags")?;
+    Ok(())
+}
+
+/// Compress what `encode` writes behind an envelope into a temporary file,
+/// then rename it over `path`.
+fn write_payload(path: &Path, encode: impl FnOnce(&mut dyn Write) -> Result<()>) -> Result<()> {
     let cache_encode_time = Instant::now();
     let _ = fs::create_dir_all(path.parent().unwrap());
     let tmp_path = &path.with_extension(".sctmp");
     {
-        let output = File::create(tmp_path).context("Failed to create cache file")?;
-        let mut output = zstd::Encoder::new(output, 6).context("Failed to create zstd encoder")?;
-        output
+        let mut file = File::create(tmp_path).context("Failed to create cache file")?;
+        // Reserve the envelope; it's filled in once the payload is written.
+        file.write_all(&[0; ENVELOPE_LEN])
+            .context("Failed to write cache envelope")?;
+        let payload = ChecksumWriter::new(BufWriter::new(file));
+        let mut encoder =
+            zstd::Encoder::new(payload, 6).context("Failed to create zstd encoder")?;
+        encoder
             .multithread(available_parallelism().map(|x| x.get() as u32).unwrap_or(4))
             .context("Failed to create parallel zstd encoder")?;
-        let output = output.auto_finish();
-        let mut output = BufWriter::new(output);
-        postcard::to_io(&storage, &mut output).context("Failed to encode cache")?;
+        let mut output = BufWriter::new(&mut encoder);
+        encode(&mut output)?;
+        output.flush().context("Failed to flush cache")?;
+        drop(output);
+        let payload = encoder.finish().context("Failed to finish zstd stream")?;
+        let (file, payload_len, checksum) = payload.finish();
+        let mut file = file
+            .into_inner()
+            .map_err(|e| e.into_error())
+            .context("Failed to flush cache file")?;
+        let envelope = Envelope {
+            payload_len,
+            checksum,
+        };
+        file.rewind().context("Failed to seek cache file")?;
+        file.write_all(&envelope.to_bytes())
+            .context("Failed to write cache envelope")?;
     }
     fs::rename(tmp_path, path).context("Failed to rename cache file")?;
     info!("Cache encode time: {:?}", cache_encode_time.elapsed());
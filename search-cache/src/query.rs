@@ -1,70 +1,155 @@
 use crate::{
-    SearchCache, SearchOptions, SegmentKind, SegmentMatcher, SlabIndex, SlabNodeMetadataCompact,
-    build_segment_matchers, cache::NAME_POOL,
+    MetadataExtras, SearchCache, SearchError, SearchOptions, SearchResult, SegmentKind,
+    SegmentMatcher, SizeBuckets, SlabIndex, SlabNodeMetadataCompact, build_segment_matchers,
+    cache::{ExtrasTable, NAME_POOL},
+    initials::{InitialsCache, InitialsMatch, InitialsQuery},
+    literal_matcher,
+    query_history::unix_now,
+    query_notes::{QueryNote, QueryNotes},
+    result_budget::ResultBudget,
+    set_ops::{difference_in_place, intersect_in_place, union_in_place},
+    tags::normalize_tag,
 };
 use anyhow::{Result, anyhow, bail};
+use cardinal_sdk::date_added_of_path;
 use cardinal_syntax::{
     ArgumentKind, ComparisonOp, Expr, Filter, FilterArgument, FilterKind, RangeSeparator, Term,
 };
 use fswalk::NodeFileType;
-use hashbrown::HashSet;
+use hashbrown::{HashMap, HashSet};
 use jiff::{Timestamp, civil::Date, tz::TimeZone};
 use memchr::arch::all::rabinkarp;
-use query_segmentation::query_segmentation;
+use namepool::to_nfc;
+use query_segmentation::{Segment, query_segmentation};
 use rayon::iter::{ParallelBridge, ParallelIterator};
 use regex::RegexBuilder;
 use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
-use std::{collections::BTreeSet, fs::File, io::Read, path::Path};
+use std::{
+    borrow::Cow,
+    collections::BTreeSet,
+    fs::File,
+    io::Read,
+    path::Path,
+    slice,
+    sync::{MutexGuard, PoisonError},
+    time::{Instant, UNIX_EPOCH},
+};
 
 pub(crate) const CONTENT_BUFFER_BYTES: usize = 64 * 1024;
+/// Compiled size a `regex:` pattern may reach, a tenth of the regex crate's default.
+const REGEX_SIZE_LIMIT: usize = 1024 * 1024;
+
+#[cfg(test)]
+thread_local! {
+    /// How many times this thread scanned the whole name pool for a term.
+    pub(crate) static NAME_POOL_SCANS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// Scan every name instead of the pool's buckets, to compare both.
+    pub(crate) static NAME_BUCKETS_DISABLED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+    /// How many times this thread read a node's metadata from the filesystem.
+    pub(crate) static METADATA_STATS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// How many times this thread collected every node for a filter to narrow.
+    pub(crate) static UNIVERSE_COLLECTS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
+    /// Collect every node for filters without a base, to compare with
+    /// `SearchCache::filter_all`.
+    pub(crate) static FILTER_SOURCES_DISABLED: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+#[cfg(test)]
+pub(crate) fn count_metadata_stat() {
+    METADATA_STATS.with(|stats| stats.set(stats.get() + 1));
+}
 
 impl SearchCache {
+    /// The lists returned by the `evaluate_*` methods aren't counted in
+    /// `budget`, the caller charges what it keeps.
     pub(crate) fn evaluate_expr(
-        &mut self,
+        &self,
         expr: &Expr,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+        notes: &QueryNotes,
+        budget: &ResultBudget,
+    ) -> SearchResult<Vec<SlabIndex>> {
         match expr {
-            Expr::Empty => Ok(self.search_empty(token)),
-            Expr::Term(term) => self.evaluate_term(term, options, token),
-            Expr::Not(inner) => self.evaluate_not(inner, None, options, token),
-            Expr::And(parts) => self.evaluate_and(parts, options, token),
-            Expr::Or(parts) => self.evaluate_or(parts, options, token),
+            Expr::Empty => self.search_empty(token).ok_or(SearchError::Cancelled),
+            Expr::Term(term) => self.evaluate_term(term, options, token, notes),
+            Expr::Not(inner) => self.evaluate_not(inner, None, options, token, notes, budget),
+            Expr::And(parts) => self.evaluate_and(parts, options, token, notes, budget),
+            Expr::Or(parts) => self.evaluate_or(parts, options, token, notes, budget),
         }
     }
 
     fn evaluate_and(
-        &mut self,
+        &self,
         parts: &[Expr],
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+        notes: &QueryNotes,
+        budget: &ResultBudget,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        // On cancellation the intersection computed so far is returned as the
+        // partial result unless asked not to.
+        let partial = |current: Option<Vec<SlabIndex>>| match current {
+            Some(nodes) if !options.strict_cancellation => {
+                notes.cut_short();
+                Ok(nodes)
+            }
+            _ => Err(SearchError::Cancelled),
+        };
+        let depth_root = self.depth_root(parts, options.scope_to);
         let mut current: Option<Vec<SlabIndex>> = None;
         for part in parts {
+            if current.is_some() && token.is_cancelled() {
+                return partial(current);
+            }
             match part {
                 Expr::Not(inner) => {
-                    let Some(x) = self.evaluate_not(inner, current, options, token)? else {
-                        return Ok(None);
+                    // Narrowing steps consume their base, keep a copy to fall back to.
+                    let fallback = if options.strict_cancellation {
+                        None
+                    } else {
+                        current.clone()
+                    };
+                    budget.release(current.as_ref().map_or(0, Vec::len));
+                    let result = self.evaluate_not(inner, current, options, token, notes, budget);
+                    let nodes = match result {
+                        Err(SearchError::Cancelled) => return partial(fallback),
+                        result => result?,
                     };
-                    current = Some(x);
+                    budget.charge(nodes.len())?;
+                    current = Some(nodes);
                 }
                 Expr::Term(Term::Filter(filter)) => {
+                    let fallback = if options.strict_cancellation {
+                        None
+                    } else {
+                        current.clone()
+                    };
                     let base = current.take();
-                    let Some(nodes) = self.evaluate_filter(filter, base, options, token)? else {
-                        return Ok(None);
+                    budget.release(base.as_ref().map_or(0, Vec::len));
+                    let result =
+                        self.evaluate_filter(filter, base, depth_root, options, token, notes);
+                    let nodes = match result {
+                        Err(SearchError::Cancelled) => return partial(fallback),
+                        result => result?,
                     };
+                    budget.charge(nodes.len())?;
                     current = Some(nodes);
                 }
                 _ => {
-                    let Some(nodes) = self.evaluate_expr(part, options, token)? else {
-                        return Ok(None);
+                    let nodes = match self.evaluate_expr(part, options, token, notes, budget) {
+                        Err(SearchError::Cancelled) => return partial(current),
+                        result => result?,
                     };
+                    budget.charge(nodes.len())?;
                     current = Some(match current {
                         Some(mut existing) => {
-                            if intersect_in_place(&mut existing, &nodes, token).is_none() {
-                                return Ok(None);
+                            let before = existing.len();
+                            // Left untouched when cancelled midway.
+                            if intersect_in_place(&mut existing, &nodes, token).is_err() {
+                                return partial(Some(existing));
                             }
+                            budget.release(before + nodes.len() - existing.len());
                             existing
                         }
                         None => nodes,
@@ -72,64 +157,282 @@ impl SearchCache {
                 }
             }
         }
-        Ok(Some(current.expect("at least one part in AND expression")))
+        let current = current.expect("at least one part in AND expression");
+        budget.release(current.len());
+        Ok(current)
     }
 
     fn evaluate_or(
-        &mut self,
+        &self,
         parts: &[Expr],
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+        notes: &QueryNotes,
+        budget: &ResultBudget,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        // On cancellation the union computed so far is returned as the partial
+        // result once at least one branch completed.
+        let partial = |result: Vec<SlabIndex>, completed: bool| {
+            if options.strict_cancellation || !completed {
+                Err(SearchError::Cancelled)
+            } else {
+                notes.cut_short();
+                Ok(result)
+            }
+        };
+        // Filters statting every node are bounded next to name terms, which
+        // go first so the filters skip what they matched.
+        let bounded = options.filter_only_scan_limit.filter(|_| {
+            parts.iter().any(has_name_term)
+                && parts.iter().any(|part| probing_filter(part).is_some())
+        });
+        let mut arms: Vec<&Expr> = parts.iter().collect();
+        if bounded.is_some() {
+            arms.sort_by_key(|part| !has_name_term(part));
+        }
         let mut result: Vec<SlabIndex> = Vec::new();
-        for part in parts {
-            let candidate = self.evaluate_expr(part, options, token)?;
-            let Some(nodes) = candidate else {
-                return Ok(None);
+        for (i, part) in arms.into_iter().enumerate() {
+            let nodes = match (bounded, probing_filter(part)) {
+                (Some(limit), Some(filter)) => {
+                    self.evaluate_bounded_filter(filter, &result, limit, options, token, notes)
+                }
+                _ => self.evaluate_expr(part, options, token, notes, budget),
             };
-            if union_in_place(&mut result, &nodes, token).is_none() {
-                return Ok(None);
+            let nodes = match nodes {
+                Err(SearchError::Cancelled) => return partial(result, i > 0),
+                candidate => candidate?,
+            };
+            budget.charge(nodes.len())?;
+            if result.is_empty() {
+                result = nodes;
+                continue;
+            }
+            let before = result.len();
+            if union_in_place(&mut result, &nodes, token).is_err() {
+                return partial(result, true);
+            }
+            budget.release(before + nodes.len() - result.len());
+        }
+        budget.release(result.len());
+        Ok(result)
+    }
+
+    /// Evaluate `filter`, an OR arm that stats nodes, over the nodes the
+    /// other arms didn't match. Nodes whose metadata is cached are checked
+    /// for free, at most `limit` of the others are statted and the rest are
+    /// skipped, noting it.
+    fn evaluate_bounded_filter(
+        &self,
+        filter: &Filter,
+        matched: &[SlabIndex],
+        limit: usize,
+        options: SearchOptions,
+        token: CancellationToken,
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let mut candidates = self.search_empty(token).ok_or(SearchError::Cancelled)?;
+        difference_in_place(&mut candidates, matched, token)?;
+        // Read from the filesystem every time, see `node_timestamp`.
+        let always_stats = matches!(
+            filter.kind,
+            FilterKind::DateAccessed | FilterKind::DateAdded
+        );
+        // Size filters skip folders without looking at them.
+        let files_only = matches!(filter.kind, FilterKind::Size | FilterKind::DiskSize);
+        let mut stats = 0;
+        let mut truncated = false;
+        candidates.retain(|&index| {
+            let metadata = &self.file_nodes[index].metadata;
+            let skipped = files_only && metadata.file_type_hint() != NodeFileType::File;
+            let stats_node = always_stats || !(metadata.is_some() || skipped);
+            if !stats_node || stats < limit {
+                stats += usize::from(stats_node);
+                return true;
             }
+            truncated = true;
+            false
+        });
+        if truncated {
+            notes.push(QueryNote::FilterArmTruncated);
         }
-        Ok(Some(result))
+        self.evaluate_filter(filter, Some(candidates), None, options, token, notes)
     }
 
     fn evaluate_not(
-        &mut self,
+        &self,
         inner: &Expr,
         base: Option<Vec<SlabIndex>>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let mut universe = if let Some(current) = base {
-            current
-        } else {
-            match self.search_empty(token) {
-                Some(nodes) => nodes,
-                None => return Ok(None),
+        notes: &QueryNotes,
+        budget: &ResultBudget,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let mut universe = self.nodes_from_base(base, token)?;
+        budget.charge(universe.len())?;
+        let negated = self.evaluate_expr(inner, options, token, notes, budget)?;
+        budget.charge(negated.len())?;
+        let before = universe.len();
+        difference_in_place(&mut universe, &negated, token)?;
+        budget.release(before + negated.len());
+        Ok(universe)
+    }
+
+    /// Evaluate `expr` over the subtree of `scope`, as if the query were ANDed
+    /// with `infolder:` of its path.
+    pub(crate) fn evaluate_scoped(
+        &self,
+        expr: &Expr,
+        scope: SlabIndex,
+        options: SearchOptions,
+        token: CancellationToken,
+        notes: &QueryNotes,
+        budget: &ResultBudget,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        if has_name_term(expr) {
+            // Name terms only look at the name pool, which is cheaper than
+            // listing a subtree that may hold most of the volume.
+            // Not cancellable, so a partial result stays a partial result.
+            let mut nodes = self.evaluate_expr(expr, options, token, notes, budget)?;
+            let scope = HashSet::from([scope]);
+            let mut ancestors = self.ancestors_in(&scope);
+            nodes.retain(|&index| ancestors.contains(index));
+            return Ok(nodes);
+        }
+        let result = self
+            .all_subnodes(scope, token)
+            .ok_or(SearchError::Cancelled)
+            .and_then(|subtree| self.evaluate_within(expr, subtree, options, token, notes, budget));
+        match result {
+            // Nothing usable is left of a cut short subtree evaluation, an empty
+            // partial result lets the caller finish the search in full.
+            Err(SearchError::Cancelled) if !options.strict_cancellation => {
+                notes.cut_short();
+                Ok(Vec::new())
+            }
+            result => result,
+        }
+    }
+
+    /// Evaluate `expr` over `base` only. Names are checked node by node instead
+    /// of scanning the name pool, and filters narrow `base` directly.
+    pub(crate) fn evaluate_within(
+        &self,
+        expr: &Expr,
+        base: Vec<SlabIndex>,
+        options: SearchOptions,
+        token: CancellationToken,
+        notes: &QueryNotes,
+        budget: &ResultBudget,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        match expr {
+            Expr::Empty => Ok(base),
+            Expr::Term(term) => self.evaluate_term_within(term, base, options, token, notes),
+            Expr::Not(inner) => {
+                let mut base = base;
+                // The copy handed to `inner`.
+                budget.charge(base.len())?;
+                let negated =
+                    self.evaluate_within(inner, base.clone(), options, token, notes, budget)?;
+                budget.release(base.len());
+                difference_in_place(&mut base, &negated, token)?;
+                Ok(base)
+            }
+            Expr::And(parts) => {
+                let depth_root = self.depth_root(parts, options.scope_to);
+                parts.iter().try_fold(base, |current, part| match part {
+                    Expr::Term(Term::Filter(filter)) => self.evaluate_filter(
+                        filter,
+                        Some(current),
+                        depth_root,
+                        options,
+                        token,
+                        notes,
+                    ),
+                    _ => self.evaluate_within(part, current, options, token, notes, budget),
+                })
+            }
+            Expr::Or(parts) => {
+                let mut result: Vec<SlabIndex> = Vec::new();
+                for part in parts {
+                    budget.charge(base.len())?;
+                    let nodes =
+                        self.evaluate_within(part, base.clone(), options, token, notes, budget)?;
+                    budget.release(base.len());
+                    budget.charge(nodes.len())?;
+                    if result.is_empty() {
+                        result = nodes;
+                        continue;
+                    }
+                    let before = result.len();
+                    union_in_place(&mut result, &nodes, token)?;
+                    budget.release(before + nodes.len() - result.len());
+                }
+                budget.release(result.len());
+                Ok(result)
+            }
+        }
+    }
+
+    fn evaluate_term_within(
+        &self,
+        term: &Term,
+        base: Vec<SlabIndex>,
+        options: SearchOptions,
+        token: CancellationToken,
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let matchers = match term {
+            Term::Word(text) | Term::Phrase(text) => phrase_matchers(text, options)?,
+            Term::Regex(pattern) => {
+                let matcher = regex_matcher(pattern, options)?;
+                let result = filter_nodes(base, regex_token(token, options), |index| {
+                    self.node_tail_matches(index, std::slice::from_ref(&matcher), options)
+                });
+                return over_regex_budget(result, token, options);
+            }
+            Term::Filter(filter) => {
+                return self.evaluate_filter(filter, Some(base), None, options, token, notes);
             }
         };
-        if let Some(negated) = self.evaluate_expr(inner, options, token)? {
-            if difference_in_place(&mut universe, &negated, token).is_none() {
-                return Ok(None);
+        filter_nodes(base, token, |index| {
+            self.node_tail_matches(index, &matchers, options)
+        })
+    }
+
+    /// Whether the last names on the path of `index` match `matchers` one to
+    /// one, which is what [`Self::execute_matchers`] finds from the name pool.
+    fn node_tail_matches(
+        &self,
+        index: SlabIndex,
+        matchers: &[SegmentMatcher],
+        options: SearchOptions,
+    ) -> bool {
+        let mut current = Some(index);
+        for matcher in matchers.iter().rev() {
+            let Some(node) = current else {
+                return false;
+            };
+            let name_and_parent = &self.file_nodes[node].name_and_parent;
+            if !matcher.matches_name(name_and_parent.as_str(), options) {
+                return false;
             }
-        } else {
-            return Ok(None);
+            current = name_and_parent.parent();
         }
-        Ok(Some(universe))
+        true
     }
 
     fn evaluate_term(
-        &mut self,
+        &self,
         term: &Term,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
         match term {
             Term::Word(text) => self.evaluate_word(text, options, token),
             Term::Phrase(text) => self.evaluate_phrase(text, options, token),
             Term::Regex(pattern) => self.evaluate_regex(pattern, options, token),
-            Term::Filter(filter) => self.evaluate_filter(filter, None, options, token),
+            Term::Filter(filter) => self.evaluate_filter(filter, None, None, options, token, notes),
         }
     }
 
@@ -138,7 +441,7 @@ impl SearchCache {
         text: &str,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         self.evaluate_phrase(text, options, token)
     }
 
@@ -147,23 +450,19 @@ impl SearchCache {
         text: &str,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let segments = query_segmentation(text);
-        if segments.is_empty() {
-            bail!("Unprocessable term: {text:?}");
-        }
-        let matchers = build_segment_matchers(&segments, options)
-            .map_err(|err| anyhow!("Invalid regex pattern: {err}"))?;
-        self.execute_matchers(&matchers, token)
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let matchers = phrase_matchers(text, options)?;
+        self.execute_matchers(&matchers, options, token)
     }
 
     fn execute_matchers(
         &self,
         matchers: &[SegmentMatcher],
+        options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         if matchers.is_empty() {
-            return Ok(Some(Vec::new()));
+            return Ok(Vec::new());
         }
         let mut node_set: Option<Vec<SlabIndex>> = None;
         for matcher in matchers {
@@ -171,14 +470,14 @@ impl SearchCache {
                 let mut new_node_set = Vec::with_capacity(nodes.len());
                 for (i, &node) in nodes.iter().enumerate() {
                     if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
-                        return Ok(None);
+                        return Err(SearchError::Cancelled);
                     }
                     let mut child_matches = self.file_nodes[node]
                         .children
                         .iter()
                         .filter_map(|&child| {
                             let name = self.file_nodes[child].name_and_parent.as_str();
-                            if matcher.matches(name) {
+                            if matcher.matches_name(name, options) {
                                 Some((name, child))
                             } else {
                                 None
@@ -190,22 +489,40 @@ impl SearchCache {
                 }
                 node_set = Some(new_node_set);
             } else {
-                let names: Option<BTreeSet<_>> = match matcher {
-                    SegmentMatcher::Plain { kind, needle } => match kind {
-                        SegmentKind::Substr => NAME_POOL.search_substr(needle, token),
-                        SegmentKind::Prefix => NAME_POOL.search_prefix(needle, token),
-                        SegmentKind::Suffix => NAME_POOL.search_suffix(needle, token),
-                        SegmentKind::Exact => NAME_POOL.search_exact(needle, token),
-                    },
-                    SegmentMatcher::Regex { regex } => NAME_POOL.search_regex(regex, token),
-                };
-                let Some(names) = names else {
-                    return Ok(None);
+                #[cfg(test)]
+                NAME_POOL_SCANS.with(|scans| scans.set(scans.get() + 1));
+                #[cfg(test)]
+                let hint = matcher
+                    .pool_hint()
+                    .filter(|_| !NAME_BUCKETS_DISABLED.with(|disabled| disabled.get()));
+                #[cfg(not(test))]
+                let hint = matcher.pool_hint();
+                // Only the names holding the hint's rarest character pair are
+                // checked, see `NamePool::search_nfc_hinted`.
+                let names: Option<BTreeSet<_>> = if let Some(hint) = hint {
+                    if options.byte_exact {
+                        NAME_POOL.search_hinted(hint, |name| matcher.matches(name), token)
+                    } else {
+                        NAME_POOL.search_nfc_hinted(hint, |name| matcher.matches(name), token)
+                    }
+                } else if options.byte_exact {
+                    match matcher {
+                        SegmentMatcher::Plain { kind, needle } => match kind {
+                            SegmentKind::Substr => NAME_POOL.search_substr(needle, token),
+                            SegmentKind::Prefix => NAME_POOL.search_prefix(needle, token),
+                            SegmentKind::Suffix => NAME_POOL.search_suffix(needle, token),
+                            SegmentKind::Exact => NAME_POOL.search_exact(needle, token),
+                        },
+                        SegmentMatcher::Regex { regex, .. } => NAME_POOL.search_regex(regex, token),
+                    }
+                } else {
+                    NAME_POOL.search_nfc(|name| matcher.matches(name), token)
                 };
+                let names = names.ok_or(SearchError::Cancelled)?;
                 let mut nodes = Vec::with_capacity(names.len());
                 for (i, name) in names.iter().enumerate() {
                     if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
-                        return Ok(None);
+                        return Err(SearchError::Cancelled);
                     }
                     if let Some(indices) = self.name_index.get(name) {
                         nodes.extend(indices.iter().copied());
@@ -214,7 +531,7 @@ impl SearchCache {
                 node_set = Some(nodes);
             }
         }
-        Ok(node_set)
+        Ok(node_set.unwrap_or_default())
     }
 
     fn evaluate_regex(
@@ -222,23 +539,26 @@ impl SearchCache {
         pattern: &str,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let mut builder = RegexBuilder::new(pattern);
-        builder.case_insensitive(options.case_insensitive);
-        let regex = builder
-            .build()
-            .map_err(|err| anyhow!("Invalid regex pattern: {err}"))?;
-        let matcher = SegmentMatcher::Regex { regex };
-        self.execute_matchers(std::slice::from_ref(&matcher), token)
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let matcher = regex_matcher(pattern, options)?;
+        let result = self.execute_matchers(
+            std::slice::from_ref(&matcher),
+            options,
+            regex_token(token, options),
+        );
+        over_regex_budget(result, token, options)
     }
 
+    /// `depth_root` is the folder `depth:` counts from, see [`Self::depth_root`].
     fn evaluate_filter(
-        &mut self,
+        &self,
         filter: &Filter,
         base: Option<Vec<SlabIndex>>,
+        depth_root: Option<SlabIndex>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
         match filter.kind {
             FilterKind::File => self.evaluate_type_filter(
                 NodeFileType::File,
@@ -259,28 +579,64 @@ impl SearchCache {
                     .argument
                     .as_ref()
                     .ok_or_else(|| anyhow!("ext: requires at least one extension"))?;
-                self.evaluate_extension_filter(argument, base, token)
+                self.evaluate_extension_filter(argument, base, options, token, notes)
             }
             FilterKind::Parent => {
                 let argument = filter
                     .argument
                     .as_ref()
                     .ok_or_else(|| anyhow!("parent: requires a folder path"))?;
-                self.evaluate_parent_filter(argument, base, token)
+                self.evaluate_parent_filter(argument, base, token, notes)
             }
             FilterKind::InFolder => {
                 let argument = filter
                     .argument
                     .as_ref()
                     .ok_or_else(|| anyhow!("infolder: requires a folder path"))?;
-                self.evaluate_infolder_filter(argument, base, token)
+                self.evaluate_infolder_filter(argument, base, token, notes)
+            }
+            FilterKind::Under => {
+                let argument = filter
+                    .argument
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("under: requires a folder name"))?;
+                self.evaluate_under_filter(argument, base, options, token)
             }
             FilterKind::NoSubfolders => {
                 let argument = filter
                     .argument
                     .as_ref()
                     .ok_or_else(|| anyhow!("nosubfolders: requires a folder path"))?;
-                self.evaluate_nosubfolders_filter(argument, base, token)
+                self.evaluate_nosubfolders_filter(argument, base, token, notes)
+            }
+            FilterKind::Depth => {
+                let argument = filter
+                    .argument
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("depth: requires a value"))?;
+                let depth_root = depth_root.or(options.scope_to);
+                self.evaluate_depth_filter(argument, base, depth_root, token)
+            }
+            FilterKind::StartsWith => {
+                let argument = filter
+                    .argument
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("startswith: requires a value"))?;
+                self.evaluate_affix_filter(SegmentKind::Prefix, argument, base, options, token)
+            }
+            FilterKind::EndsWith => {
+                let argument = filter
+                    .argument
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("endswith: requires a value"))?;
+                self.evaluate_affix_filter(SegmentKind::Suffix, argument, base, options, token)
+            }
+            FilterKind::Initials => {
+                let argument = filter
+                    .argument
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("initials: requires letters or digits"))?;
+                self.evaluate_initials_filter(argument, base, options, token)
             }
             FilterKind::Type => {
                 let argument = filter
@@ -306,21 +662,39 @@ impl SearchCache {
                     .argument
                     .as_ref()
                     .ok_or_else(|| anyhow!("size: requires a value"))?;
-                self.evaluate_size_filter(argument, base, token)
+                self.evaluate_size_filter(SizeField::Logical, argument, base, options, token, notes)
             }
-            FilterKind::DateModified => {
+            FilterKind::DiskSize => {
                 let argument = filter
                     .argument
                     .as_ref()
-                    .ok_or_else(|| anyhow!("dm: requires a date or range"))?;
-                self.evaluate_date_filter(DateField::Modified, argument, base, token)
+                    .ok_or_else(|| anyhow!("disksize: requires a value"))?;
+                self.evaluate_size_filter(
+                    SizeField::Allocated,
+                    argument,
+                    base,
+                    options,
+                    token,
+                    notes,
+                )
             }
-            FilterKind::DateCreated => {
+            FilterKind::Is => {
                 let argument = filter
                     .argument
                     .as_ref()
-                    .ok_or_else(|| anyhow!("dc: requires a date or range"))?;
-                self.evaluate_date_filter(DateField::Created, argument, base, token)
+                    .ok_or_else(|| anyhow!("is: requires a property"))?;
+                self.evaluate_is_filter(argument, base, options, token)
+            }
+            FilterKind::DateModified
+            | FilterKind::DateCreated
+            | FilterKind::DateAccessed
+            | FilterKind::DateAdded => {
+                let field = DateField::of(&filter.kind);
+                let argument = filter
+                    .argument
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("{}: requires a date or range", field.prefix()))?;
+                self.evaluate_date_filter(field, argument, base, token)
             }
             FilterKind::Content => {
                 let argument = filter
@@ -329,7 +703,11 @@ impl SearchCache {
                     .ok_or_else(|| anyhow!("content: requires a value"))?;
                 self.evaluate_content_filter(argument, base, options, token)
             }
-            _ => bail!("Filter {:?} is not supported yet", filter.kind),
+            FilterKind::Tag => self.evaluate_tag_filter(filter.argument.as_ref(), base, token),
+            FilterKind::FinderTag => {
+                self.evaluate_finder_tag_filter(filter.argument.as_ref(), base, token)
+            }
+            _ => Err(anyhow!("Filter {:?} is not supported yet", filter.kind).into()),
         }
     }
 
@@ -340,57 +718,208 @@ impl SearchCache {
         argument: Option<&FilterArgument>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         let (mut nodes, argument_applied) = match (base, argument) {
             (Some(nodes), _) => (nodes, false),
-            (None, Some(arg)) => match self.evaluate_phrase(&arg.raw, options, token)? {
-                Some(nodes) => (nodes, true),
-                None => return Ok(None),
-            },
-            (None, None) => match self.search_empty(token) {
-                Some(nodes) => (nodes, false),
-                None => return Ok(None),
-            },
+            (None, Some(arg)) => (self.evaluate_phrase(&arg.raw, options, token)?, true),
+            (None, None) => {
+                let label = match file_type {
+                    NodeFileType::Dir => "folder",
+                    _ => "file",
+                };
+                return self.filter_base_or_all(
+                    label,
+                    None,
+                    None,
+                    |index| self.file_nodes[index].metadata.file_type_hint() == file_type,
+                    token,
+                );
+            }
         };
 
         if !argument_applied {
             if let Some(arg) = argument {
-                let Some(matches) = self.evaluate_phrase(&arg.raw, options, token)? else {
-                    return Ok(None);
-                };
-                if intersect_in_place(&mut nodes, &matches, token).is_none() {
-                    return Ok(None);
-                }
+                let matches = self.evaluate_phrase(&arg.raw, options, token)?;
+                intersect_in_place(&mut nodes, &matches, token)?;
             }
         }
 
-        Ok(filter_nodes(nodes, token, |index| {
+        filter_nodes(nodes, token, |index| {
             self.file_nodes[index].metadata.file_type_hint() == file_type
-        }))
+        })
+    }
+
+    /// `tag:` keeps nodes with any of the listed tags, or any tag at all
+    /// without an argument.
+    fn evaluate_tag_filter(
+        &self,
+        argument: Option<&FilterArgument>,
+        base: Option<Vec<SlabIndex>>,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let tagged = match argument {
+            Some(argument) => {
+                let items: Vec<&str> = match &argument.kind {
+                    ArgumentKind::List(list) => list.iter().map(String::as_str).collect(),
+                    _ => vec![argument.raw.as_str()],
+                };
+                let wanted = items
+                    .into_iter()
+                    .map(normalize_tag)
+                    .collect::<Result<HashSet<_>>>()?;
+                self.tagged_nodes(|tag| wanted.contains(tag))
+            }
+            None => self.tagged_nodes(|_| true),
+        };
+        match base {
+            Some(mut nodes) => {
+                intersect_in_place(&mut nodes, &tagged, token)?;
+                Ok(nodes)
+            }
+            None => Ok(tagged),
+        }
+    }
+
+    /// Finder tags are only read for the nodes in `base`, so put `fintag:` after
+    /// the filters that narrow the search down.
+    fn evaluate_finder_tag_filter(
+        &self,
+        argument: Option<&FilterArgument>,
+        base: Option<Vec<SlabIndex>>,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let wanted: Vec<&str> = match argument {
+            Some(argument) => match &argument.kind {
+                ArgumentKind::List(list) => list.iter().map(String::as_str).collect(),
+                _ => vec![argument.raw.as_str()],
+            },
+            None => Vec::new(),
+        };
+        let nodes = self.nodes_from_base(base, token)?;
+        filter_nodes(nodes, token, |index| {
+            let tags = self.finder_tags(index);
+            if wanted.is_empty() {
+                return !tags.is_empty();
+            }
+            tags.iter()
+                .any(|tag| wanted.iter().any(|wanted| tag.matches(wanted)))
+        })
     }
 
     fn evaluate_extension_filter(
         &self,
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let extensions = normalize_extensions(argument);
-        if extensions.is_empty() {
-            bail!("ext: requires non-empty extensions");
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let spec = ExtensionSpec::parse(argument, options)?;
+        if spec.extensions.is_empty() && !spec.no_extension {
+            notes.push(QueryNote::EmptyExtensionList);
+            return Ok(Vec::new());
+        }
+        if base
+            .as_deref()
+            .is_some_and(|nodes| self.only_folders(nodes, notes))
+        {
+            return Ok(Vec::new());
+        }
+        let all = base.is_none();
+        let nodes = self.filter_base_or_all(
+            "ext",
+            base,
+            Some(&|name| spec.matches(name)),
+            |index| {
+                let node = &self.file_nodes[index];
+                node.metadata.file_type_hint() == NodeFileType::File
+                    && spec.matches(node.name_and_parent.as_str())
+            },
+            token,
+        )?;
+        if all && nodes.is_empty() && self.only_folders_indexed() {
+            notes.push(QueryNote::FilterExcludesAllDirectories);
         }
-        let Some(nodes) = self.nodes_from_base(base, token) else {
-            return Ok(None);
+        Ok(nodes)
+    }
+
+    /// `startswith:` and `endswith:`. The argument is taken literally, wildcards
+    /// included, and goes straight to the name pool's prefix/suffix search
+    /// rather than through a regex unless the search is case-insensitive.
+    fn evaluate_affix_filter(
+        &self,
+        kind: SegmentKind,
+        argument: &FilterArgument,
+        base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let value = argument.raw.as_str();
+        if value.is_empty() {
+            let prefix = match kind {
+                SegmentKind::Prefix => "startswith",
+                _ => "endswith",
+            };
+            return Err(anyhow!("{prefix}: requires a value").into());
+        }
+        let value = if options.byte_exact {
+            Cow::Borrowed(value)
+        } else {
+            to_nfc(value)
         };
-        Ok(filter_nodes(nodes, token, |index| {
-            let node = &self.file_nodes[index];
-            if node.metadata.file_type_hint() != NodeFileType::File {
-                return false;
+        let matcher = literal_matcher(kind, &value, options)
+            .map_err(|err| anyhow!("Invalid regex pattern: {err}"))?;
+        match base {
+            Some(nodes) => filter_nodes(nodes, token, |index| {
+                matcher.matches_name(self.file_nodes[index].name_and_parent.as_str(), options)
+            }),
+            None => self.execute_matchers(std::slice::from_ref(&matcher), options, token),
+        }
+    }
+
+    /// `initials:` keeps the names whose word initials contain the argument's
+    /// letters in order, the names it spells out whole (extension aside) first.
+    fn evaluate_initials_filter(
+        &self,
+        argument: &FilterArgument,
+        base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let query = InitialsQuery::new(&argument.raw, options)
+            .ok_or_else(|| anyhow!("initials: requires letters or digits"))?;
+        let mut cache = InitialsCache::lock();
+        let mut full = Vec::new();
+        let mut partial = Vec::new();
+        match base {
+            Some(nodes) => {
+                for (i, index) in nodes.into_iter().enumerate() {
+                    if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
+                        return Err(SearchError::Cancelled);
+                    }
+                    let name = self.file_nodes[index].name_and_parent.as_str();
+                    match cache.classify(&query, name) {
+                        Some(InitialsMatch::Full) => full.push(index),
+                        Some(InitialsMatch::Partial) => partial.push(index),
+                        None => {}
+                    }
+                }
+            }
+            None => {
+                for (i, (name, indices)) in self.name_index.iter().enumerate() {
+                    if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
+                        return Err(SearchError::Cancelled);
+                    }
+                    match cache.classify(&query, name) {
+                        Some(InitialsMatch::Full) => full.extend(indices.iter().copied()),
+                        Some(InitialsMatch::Partial) => partial.extend(indices.iter().copied()),
+                        None => {}
+                    }
+                }
             }
-            extension_of(node.name_and_parent.as_str())
-                .map(|ext| extensions.contains(ext.as_str()))
-                .unwrap_or(false)
-        }))
+        }
+        full.append(&mut partial);
+        Ok(full)
     }
 
     fn evaluate_parent_filter(
@@ -398,21 +927,17 @@ impl SearchCache {
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let Some(target) = self.node_index_for_raw_path(Path::new(&argument.raw)) else {
-            bail!(
-                "Parent filter {:?} is not found in file system",
-                argument.raw
-            );
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let Some(target) = self.folder_for_filter(argument, notes) else {
+            return Ok(Vec::new());
         };
         let children = self.file_nodes[target].children.to_vec();
         if let Some(mut nodes) = base {
-            if intersect_in_place(&mut nodes, &children, token).is_none() {
-                return Ok(None);
-            }
-            Ok(Some(nodes))
+            intersect_in_place(&mut nodes, &children, token)?;
+            Ok(nodes)
         } else {
-            Ok(Some(children))
+            Ok(children)
         }
     }
 
@@ -421,23 +946,68 @@ impl SearchCache {
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let Some(target) = self.node_index_for_raw_path(Path::new(&argument.raw)) else {
-            bail!(
-                "Parent filter {:?} is not found in file system",
-                argument.raw
-            );
-        };
-        let Some(children) = self.all_subnodes(target, token) else {
-            return Ok(None);
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let Some(target) = self.folder_for_filter(argument, notes) else {
+            return Ok(Vec::new());
         };
+        let children = self
+            .all_subnodes(target, token)
+            .ok_or(SearchError::Cancelled)?;
         if let Some(mut nodes) = base {
-            if intersect_in_place(&mut nodes, &children, token).is_none() {
-                return Ok(None);
-            }
-            Ok(Some(nodes))
+            intersect_in_place(&mut nodes, &children, token)?;
+            Ok(nodes)
         } else {
-            Ok(Some(children))
+            Ok(children)
+        }
+    }
+
+    fn evaluate_under_filter(
+        &self,
+        argument: &FilterArgument,
+        base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let name = argument.raw.trim();
+        if name.is_empty() {
+            return Err(anyhow!("under: requires a folder name").into());
+        }
+        if name.contains('/') {
+            return Err(anyhow!("under: takes a folder name, use infolder: for paths").into());
+        }
+        let matchers = build_segment_matchers(&[Segment::Exact(name)], options)
+            .map_err(|err| anyhow!("Invalid regex pattern: {err}"))?;
+        let candidates = self.execute_matchers(&matchers, options, token)?;
+        let folders: HashSet<SlabIndex> = candidates
+            .into_iter()
+            .filter(|&index| self.file_nodes[index].metadata.file_type_hint() == NodeFileType::Dir)
+            .collect();
+
+        let mut ancestors = self.ancestors_in(&folders);
+        if let Some(nodes) = base {
+            return filter_nodes(nodes, token, |index| ancestors.contains(index));
+        }
+        let mut nodes = Vec::new();
+        for &folder in &folders {
+            // Nested matches are already covered by their outermost matching ancestor.
+            if ancestors.contains(folder) {
+                continue;
+            }
+            let subnodes = self
+                .all_subnodes(folder, token)
+                .ok_or(SearchError::Cancelled)?;
+            nodes.extend(subnodes);
+        }
+        Ok(nodes)
+    }
+
+    /// Answers "is any ancestor of this node in `folders`" for many nodes at once.
+    pub(crate) fn ancestors_in<'a>(&'a self, folders: &'a HashSet<SlabIndex>) -> AncestorCheck<'a> {
+        AncestorCheck {
+            cache: self,
+            folders,
+            known: HashMap::new(),
         }
     }
 
@@ -446,15 +1016,13 @@ impl SearchCache {
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let Some(target) = self.node_index_for_raw_path(Path::new(&argument.raw)) else {
-            bail!(
-                "nosubfolders filter {:?} is not found in file system",
-                argument.raw
-            );
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let Some(target) = self.folder_for_filter(argument, notes) else {
+            return Ok(Vec::new());
         };
         if self.file_nodes[target].metadata.file_type_hint() != NodeFileType::Dir {
-            bail!("nosubfolders path {:?} is not a folder", argument.raw);
+            return Err(anyhow!("nosubfolders path {:?} is not a folder", argument.raw).into());
         }
 
         let nodes = if let Some(nodes) = base
@@ -465,9 +1033,24 @@ impl SearchCache {
             self.file_nodes[target].children.to_vec()
         };
 
-        Ok(filter_nodes(nodes, token, |index| {
+        filter_nodes(nodes, token, |index| {
             self.keep_node_for_nosubfolders(index, target)
-        }))
+        })
+    }
+
+    /// The node at the path of a `parent:`, `infolder:` or `nosubfolders:`
+    /// filter, noting when it isn't indexed.
+    fn folder_for_filter(
+        &self,
+        argument: &FilterArgument,
+        notes: &QueryNotes,
+    ) -> Option<SlabIndex> {
+        let path = Path::new(&argument.raw);
+        let target = self.node_index_for_raw_path(path);
+        if target.is_none() {
+            notes.push(QueryNote::ParentNotIndexed(path.to_path_buf()));
+        }
+        target
     }
 
     fn keep_node_for_nosubfolders(&self, index: SlabIndex, root: SlabIndex) -> bool {
@@ -478,20 +1061,66 @@ impl SearchCache {
         }
     }
 
+    /// The folder `depth:` counts from in an AND group: the innermost folder
+    /// named by the group's own `infolder:` and `parent:` filters or the
+    /// search's `scope`, `None` for the search root. Filters nested in OR
+    /// groups or negations don't count.
+    fn depth_root(&self, parts: &[Expr], scope: Option<SlabIndex>) -> Option<SlabIndex> {
+        let mut depths = NodeDepths::new(self, self.file_nodes.root());
+        parts
+            .iter()
+            .filter_map(|part| match part {
+                Expr::Term(Term::Filter(Filter {
+                    kind: FilterKind::InFolder | FilterKind::Parent,
+                    argument: Some(argument),
+                })) => self.node_index_for_raw_path(Path::new(&argument.raw)),
+                _ => None,
+            })
+            .chain(scope)
+            .max_by_key(|&index| depths.depth(index))
+    }
+
+    /// The innermost folder a search of `expr` is scoped to, as for
+    /// [`Self::depth_root`] with the top level AND group of `expr`.
+    pub(crate) fn scope_root(&self, expr: &Expr, scope: Option<SlabIndex>) -> Option<SlabIndex> {
+        match expr {
+            Expr::And(parts) => self.depth_root(parts, scope),
+            expr => self.depth_root(slice::from_ref(expr), scope),
+        }
+    }
+
+    fn evaluate_depth_filter(
+        &self,
+        argument: &FilterArgument,
+        base: Option<Vec<SlabIndex>>,
+        depth_root: Option<SlabIndex>,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let predicate = DepthPredicate::parse(argument)?;
+        let root = depth_root.unwrap_or_else(|| self.file_nodes.root());
+        let nodes = self.nodes_from_base(base, token)?;
+        let mut depths = NodeDepths::new(self, root);
+        filter_nodes(nodes, token, |index| {
+            depths
+                .depth(index)
+                .is_some_and(|depth| predicate.matches(depth))
+        })
+    }
+
     fn evaluate_named_type_filter(
         &self,
         raw: &str,
         base: Option<Vec<SlabIndex>>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         let name = raw.trim();
         if name.is_empty() {
-            bail!("type: requires a category");
+            return Err(anyhow!("type: requires a category").into());
         }
         let normalized = name.to_ascii_lowercase();
         let Some(target) = lookup_type_group(&normalized) else {
-            bail!("Unknown type category: {name}");
+            return Err(anyhow!("Unknown type category: {name}").into());
         };
         self.apply_type_group(target, base, options, token)
     }
@@ -503,26 +1132,19 @@ impl SearchCache {
         argument: Option<&FilterArgument>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let group_nodes = self.apply_type_group(
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let mut nodes = self.apply_type_group(
             lookup_type_group(name).expect("built-in macro should map to a known type group"),
             base,
             options,
             token,
         )?;
-        let Some(mut nodes) = group_nodes else {
-            return Ok(None);
-        };
         let Some(argument) = argument else {
-            return Ok(Some(nodes));
-        };
-        let Some(matches) = self.evaluate_phrase(&argument.raw, options, token)? else {
-            return Ok(None);
+            return Ok(nodes);
         };
-        if intersect_in_place(&mut nodes, &matches, token).is_none() {
-            return Ok(None);
-        }
-        Ok(Some(nodes))
+        let matches = self.evaluate_phrase(&argument.raw, options, token)?;
+        intersect_in_place(&mut nodes, &matches, token)?;
+        Ok(nodes)
     }
 
     fn apply_type_group(
@@ -531,12 +1153,14 @@ impl SearchCache {
         base: Option<Vec<SlabIndex>>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         match target {
             TypeFilterTarget::NodeType(file_type) => {
                 self.evaluate_type_filter(file_type, base, None, options, token)
             }
-            TypeFilterTarget::Extensions(list) => self.filter_static_extensions(list, base, token),
+            TypeFilterTarget::Extensions(list) => {
+                self.filter_static_extensions(list, base, options, token)
+            }
         }
     }
 
@@ -544,76 +1168,170 @@ impl SearchCache {
         &self,
         extensions: &'static [&'static str],
         base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        if extensions.is_empty() {
-            return Ok(Some(Vec::new()));
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let spec = ExtensionSpec::from_list(extensions, options);
+        if spec.extensions.is_empty() {
+            return Ok(Vec::new());
         }
-        let Some(nodes) = self.nodes_from_base(base, token) else {
-            return Ok(None);
-        };
-        Ok(filter_nodes(nodes, token, |index| {
-            let node = &self.file_nodes[index];
-            if node.metadata.file_type_hint() != NodeFileType::File {
-                return false;
-            }
-            if let Some(ext) = extension_of(node.name_and_parent.as_str()) {
-                extensions.iter().any(|needle| *needle == ext)
-            } else {
-                false
-            }
-        }))
+        self.filter_base_or_all(
+            "type",
+            base,
+            Some(&|name| spec.matches(name)),
+            |index| {
+                let node = &self.file_nodes[index];
+                node.metadata.file_type_hint() == NodeFileType::File
+                    && spec.matches(node.name_and_parent.as_str())
+            },
+            token,
+        )
     }
 
     fn evaluate_size_filter(
-        &mut self,
+        &self,
+        field: SizeField,
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
-        let predicate = SizePredicate::parse(argument)?;
-        let Some(nodes) = self.nodes_from_base(base, token) else {
-            return Ok(None);
-        };
-        Ok(filter_nodes(nodes, token, |index| {
-            let node = &self.file_nodes[index];
-            if node.metadata.file_type_hint() != NodeFileType::File {
-                return false;
+        notes: &QueryNotes,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        let predicate = SizePredicate::parse(argument, field, &options.size_buckets)?;
+        if base
+            .as_deref()
+            .is_some_and(|nodes| self.only_folders(nodes, notes))
+        {
+            return Ok(Vec::new());
+        }
+        let all = base.is_none();
+        let nodes = self.filter_base_or_all(
+            field.prefix(),
+            base,
+            None,
+            |index| {
+                let node = &self.file_nodes[index];
+                if node.metadata.file_type_hint() != NodeFileType::File {
+                    return false;
+                }
+                let size = match field {
+                    SizeField::Logical => self.node_size_bytes(index),
+                    SizeField::Allocated => self.node_allocated_bytes(index),
+                };
+                let Some(size) = size else {
+                    return false;
+                };
+                predicate.matches(size)
+            },
+            token,
+        )?;
+        if all && nodes.is_empty() && self.only_folders_indexed() {
+            notes.push(QueryNote::FilterExcludesAllDirectories);
+        }
+        Ok(nodes)
+    }
+
+    /// Whether a filter matching files only was handed nothing but folders,
+    /// noting it if so.
+    fn only_folders(&self, nodes: &[SlabIndex], notes: &QueryNotes) -> bool {
+        let only_folders = !nodes.is_empty()
+            && nodes.iter().all(|&index| {
+                self.file_nodes[index].metadata.file_type_hint() == NodeFileType::Dir
+            });
+        if only_folders {
+            notes.push(QueryNote::FilterExcludesAllDirectories);
+        }
+        only_folders
+    }
+
+    fn evaluate_is_filter(
+        &self,
+        argument: &FilterArgument,
+        base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        match argument.raw.to_ascii_lowercase().as_str() {
+            "hardlinked" => {}
+            "new" => return self.evaluate_is_new(base, options, token),
+            "emptyfolder" => {
+                let nodes = self.nodes_from_base(base, token)?;
+                return filter_nodes(nodes, token, |index| {
+                    let node = &self.file_nodes[index];
+                    node.children.is_empty() && node.metadata.file_type_hint() == NodeFileType::Dir
+                });
+            }
+            "brokenlink" => {
+                let nodes = self.nodes_from_base(base, token)?;
+                return filter_nodes(nodes, token, |index| self.is_broken_link(index));
+            }
+            "hidden" => {
+                let nodes = self.nodes_from_base(base, token)?;
+                return filter_nodes(nodes, token, |index| self.is_hidden(index));
+            }
+            other => {
+                return Err(anyhow!(
+                    "is: unknown property `{other}`, expected hardlinked, new, emptyfolder, brokenlink or hidden"
+                )
+                .into());
             }
-            let Some(size) = self.node_size_bytes(index) else {
+        }
+        let nodes = self.nodes_from_base(base, token)?;
+        filter_nodes(nodes, token, |index| {
+            // Folders count their subfolders' `..` entries as links.
+            if self.file_nodes[index].metadata.file_type_hint() == NodeFileType::Dir {
                 return false;
-            };
-            predicate.matches(size)
-        }))
+            }
+            self.node_links(index).is_some_and(|(_, nlink)| nlink > 1)
+        })
     }
 
+    /// Nodes first seen by FSEvents within `options.new_window`.
+    fn evaluate_is_new(
+        &self,
+        base: Option<Vec<SlabIndex>>,
+        options: SearchOptions,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        if self.first_seen.is_empty() {
+            return Ok(Vec::new());
+        }
+        let since = unix_now().saturating_sub(options.new_window.as_secs()) as i64;
+        let nodes = self.nodes_from_base(base, token)?;
+        filter_nodes(nodes, token, |index| {
+            self.first_seen(index).is_some_and(|stamp| stamp >= since)
+        })
+    }
     fn evaluate_date_filter(
-        &mut self,
+        &self,
         field: DateField,
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         let context = DateContext::capture();
         let predicate = DatePredicate::parse(argument, &context)?;
-        let Some(nodes) = self.nodes_from_base(base, token) else {
-            return Ok(None);
-        };
-        Ok(filter_nodes(nodes, token, |index| {
-            let Some(timestamp) = self.node_timestamp(index, field) else {
-                return false;
-            };
-            predicate.matches(timestamp)
-        }))
+        self.filter_base_or_all(
+            field.prefix(),
+            base,
+            None,
+            |index| {
+                let Some(timestamp) = self.node_timestamp(index, field) else {
+                    return false;
+                };
+                predicate.matches(timestamp)
+            },
+            token,
+        )
     }
 
     fn evaluate_content_filter(
-        &mut self,
+        &self,
         argument: &FilterArgument,
         base: Option<Vec<SlabIndex>>,
         options: SearchOptions,
         token: CancellationToken,
-    ) -> Result<Option<Vec<SlabIndex>>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
         let ghost;
         let needle = if options.case_insensitive {
             ghost = argument.raw.to_ascii_lowercase().into_bytes();
@@ -622,12 +1340,10 @@ impl SearchCache {
             argument.raw.as_bytes()
         };
         if needle.is_empty() {
-            bail!("content: requires a value");
+            return Err(anyhow!("content: requires a value").into());
         }
 
-        let Some(nodes) = self.nodes_from_base(base, token) else {
-            return Ok(None);
-        };
+        let nodes = self.nodes_from_base(base, token)?;
 
         let matched_indices = nodes
             .into_iter()
@@ -640,7 +1356,10 @@ impl SearchCache {
             })
             .collect();
 
-        Ok((!token.is_cancelled()).then_some(matched_indices))
+        if token.is_cancelled() {
+            return Err(SearchError::Cancelled);
+        }
+        Ok(matched_indices)
     }
 
     /// user need to ensure that needle is lowercased when case_insensitive is set
@@ -747,75 +1466,292 @@ impl SearchCache {
         &self,
         base: Option<Vec<SlabIndex>>,
         token: CancellationToken,
-    ) -> Option<Vec<SlabIndex>> {
+    ) -> SearchResult<Vec<SlabIndex>> {
+        match base {
+            Some(nodes) => Ok(nodes),
+            None => {
+                #[cfg(test)]
+                UNIVERSE_COLLECTS.with(|collects| collects.set(collects.get() + 1));
+                self.search_empty(token).ok_or(SearchError::Cancelled)
+            }
+        }
+    }
+
+    /// Filter `base`, or every node when there's none without collecting them
+    /// first, see [`Self::filter_all`]. `name_matches` rejects no name whose
+    /// nodes `node_matches` would keep.
+    fn filter_base_or_all(
+        &self,
+        filter: &str,
+        base: Option<Vec<SlabIndex>>,
+        name_matches: Option<&dyn Fn(&str) -> bool>,
+        node_matches: impl FnMut(SlabIndex) -> bool,
+        token: CancellationToken,
+    ) -> SearchResult<Vec<SlabIndex>> {
+        #[cfg(test)]
+        let base = match base {
+            None if FILTER_SOURCES_DISABLED.with(|disabled| disabled.get()) => {
+                Some(self.nodes_from_base(None, token)?)
+            }
+            base => base,
+        };
         match base {
-            Some(nodes) => Some(nodes),
-            None => self.search_empty(token),
+            Some(nodes) => filter_nodes(nodes, token, node_matches),
+            None => self.filter_all(filter, name_matches, node_matches, token),
         }
     }
 
-    fn node_size_bytes(&mut self, index: SlabIndex) -> Option<u64> {
+    pub(crate) fn node_size_bytes(&self, index: SlabIndex) -> Option<u64> {
         self.ensure_metadata(index).as_ref().map(|x| x.size())
     }
 
-    fn node_timestamp(&mut self, index: SlabIndex, field: DateField) -> Option<i64> {
-        let metadata = self.ensure_metadata(index);
-        let meta = metadata.as_ref()?;
+    fn node_allocated_bytes(&self, index: SlabIndex) -> Option<u64> {
+        self.ensure_metadata(index).as_ref()?;
+        if let Some(allocated) = self.node_metadata(index).as_ref()?.allocated() {
+            return Some(allocated);
+        }
+        // Metadata loaded from a cache file that predates allocated sizes.
+        self.refetch_metadata(index)?.as_ref()?.allocated()
+    }
+
+    /// `(inode, nlink)` of a node.
+    pub(crate) fn node_links(&self, index: SlabIndex) -> Option<(u64, u32)> {
+        self.ensure_metadata(index).as_ref()?;
+        let metadata = self.node_metadata(index);
+        let metadata = match metadata.as_ref()?.inode() {
+            Some(_) => metadata,
+            // Metadata loaded from a cache file that predates inodes.
+            None => self.refetch_metadata(index)?,
+        };
+        let metadata = metadata.as_ref()?;
+        Some((metadata.inode()?, metadata.nlink()?))
+    }
+
+    fn refetch_metadata(&self, index: SlabIndex) -> Option<SlabNodeMetadataCompact> {
+        let path = self.node_path(index)?;
+        #[cfg(test)]
+        count_metadata_stat();
+        let metadata = match std::fs::symlink_metadata(&path) {
+            Ok(data) => SlabNodeMetadataCompact::some(data.into()),
+            Err(err) => {
+                self.metadata_errors.record(&path, &err);
+                return None;
+            }
+        };
+        self.store_metadata(index, metadata);
+        self.metadata_budget
+            .fetched(&self.file_nodes, &self.metadata_extras, index);
+        Some(metadata)
+    }
+
+    /// The `field` timestamp of a node in seconds. Modified and created come from
+    /// the cached metadata; accessed and added are rarely filtered on, so they're
+    /// read from the filesystem each time rather than stored for every node.
+    fn node_timestamp(&self, index: SlabIndex, field: DateField) -> Option<i64> {
         match field {
-            DateField::Modified => meta.mtime(),
-            DateField::Created => meta.ctime(),
+            DateField::Modified | DateField::Created => {
+                let metadata = self.ensure_metadata(index);
+                let meta = metadata.as_ref()?;
+                let value = match field {
+                    DateField::Modified => meta.mtime(),
+                    _ => meta.ctime(),
+                };
+                value.map(|value| value.get() as i64)
+            }
+            DateField::Accessed => {
+                let path = self.node_path(index)?;
+                #[cfg(test)]
+                count_metadata_stat();
+                let metadata = std::fs::symlink_metadata(&path)
+                    .inspect_err(|err| self.metadata_errors.record(&path, err))
+                    .ok()?;
+                let accessed = metadata.accessed().ok()?;
+                let since_epoch = accessed.duration_since(UNIX_EPOCH).ok()?;
+                Some(since_epoch.as_secs() as i64)
+            }
+            DateField::Added => {
+                #[cfg(test)]
+                count_metadata_stat();
+                let added = self
+                    .node_path(index)
+                    .and_then(|path| date_added_of_path(&path).ok().flatten());
+                // Not every volume records it, use the creation date there.
+                added.or_else(|| self.node_timestamp(index, DateField::Created))
+            }
         }
-        .map(|value| value.get() as i64)
     }
 
-    fn ensure_metadata(&mut self, index: SlabIndex) -> SlabNodeMetadataCompact {
-        let current = self.file_nodes[index].metadata;
+    pub(crate) fn ensure_metadata(&self, index: SlabIndex) -> SlabNodeMetadataCompact {
+        let current = self.file_nodes[index].metadata.get();
         if current.is_some() {
+            self.file_nodes[index].metadata.touch();
             return current;
         }
         let path = self
             .node_path(index)
             .expect("node index is not present in slab");
+        #[cfg(test)]
+        count_metadata_stat();
         let metadata = match std::fs::symlink_metadata(&path) {
             Ok(data) => SlabNodeMetadataCompact::some(data.into()),
-            Err(_) => SlabNodeMetadataCompact::unaccessible(),
+            Err(err) => {
+                self.metadata_errors.record(&path, &err);
+                SlabNodeMetadataCompact::unaccessible()
+            }
         };
-        self.file_nodes[index].metadata = metadata;
+        self.store_metadata(index, metadata);
+        self.metadata_budget
+            .fetched(&self.file_nodes, &self.metadata_extras, index);
         metadata
     }
+
+    /// Metadata of a node along with its [`MetadataExtras`], which only
+    /// metadata that was fetched has. Read under the lock [`Self::store_metadata`]
+    /// writes with, so the extras are those of the stored fetch.
+    pub(crate) fn node_metadata(&self, index: SlabIndex) -> SlabNodeMetadataCompact {
+        let extras = self.lock_metadata_extras();
+        let metadata = self.file_nodes[index].metadata.get();
+        match extras.get(&index) {
+            Some(&extras) if metadata.is_some() => metadata.with_extras(extras),
+            _ => metadata,
+        }
+    }
+
+    /// Store fetched metadata in the node and its extras in the side table,
+    /// replacing both as one.
+    pub(crate) fn store_metadata(&self, index: SlabIndex, metadata: SlabNodeMetadataCompact) {
+        let mut extras = self.lock_metadata_extras();
+        if metadata.extras().is_empty() {
+            extras.remove(&index);
+        } else {
+            extras.insert(index, metadata.extras());
+        }
+        self.file_nodes[index].metadata.set(metadata);
+    }
+
+    pub(crate) fn lock_metadata_extras(&self) -> MutexGuard<'_, ExtrasTable> {
+        self.metadata_extras
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// [`Self::store_metadata`]'s side of a node being created with `extras`.
+    pub(crate) fn set_metadata_extras(&mut self, index: SlabIndex, extras: MetadataExtras) {
+        let table = self
+            .metadata_extras
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner);
+        if extras.is_empty() {
+            table.remove(&index);
+        } else {
+            table.insert(index, extras);
+        }
+    }
 }
 
-fn normalize_extensions(argument: &FilterArgument) -> HashSet<String> {
-    let mut values = HashSet::new();
-    match &argument.kind {
-        ArgumentKind::List(list) => {
-            for item in list {
-                if let Some(ext) = normalize_extension(item) {
-                    values.insert(ext);
-                }
+/// Parsed `ext:` argument: `ext:txt;md`, `ext:-` / `ext:none` for names without an
+/// extension, and a leading `!` (`ext:!png;jpg`) to exclude the listed ones instead.
+struct ExtensionSpec {
+    extensions: HashSet<String>,
+    no_extension: bool,
+    negated: bool,
+    /// Compare extensions in NFC, see `SearchOptions::byte_exact`.
+    nfc: bool,
+}
+
+impl ExtensionSpec {
+    fn empty(options: SearchOptions) -> Self {
+        Self {
+            extensions: HashSet::new(),
+            no_extension: false,
+            negated: false,
+            nfc: !options.byte_exact,
+        }
+    }
+
+    /// Split on `;` here rather than taking the parser's list, which drops
+    /// empty entries: `txt;;md` and `txt;` are mistakes, not "any extension".
+    fn parse(argument: &FilterArgument, options: SearchOptions) -> SearchResult<Self> {
+        let mut spec = Self::empty(options);
+        let raw = argument.raw.trim();
+        let raw = match raw.strip_prefix('!') {
+            // Like a missing argument: there's nothing to exclude.
+            Some(rest) if rest.trim().is_empty() => {
+                return Err(anyhow!("ext: `!` needs extensions to exclude").into());
+            }
+            Some(rest) => {
+                spec.negated = true;
+                rest
+            }
+            None => raw,
+        };
+        let items: Vec<&str> = raw.split(';').map(str::trim).collect();
+        if items.len() > 1 && items.iter().any(|item| item.is_empty()) {
+            return Err(anyhow!("ext: empty entry in `{raw}`, remove the extra `;`").into());
+        }
+        for item in items {
+            if item == "-" || item.eq_ignore_ascii_case("none") {
+                spec.no_extension = true;
+            } else if let Some(ext) = normalize_extension(item)? {
+                spec.extensions.insert(spec.compose(ext));
             }
         }
-        _ => {
-            if let Some(ext) = normalize_extension(&argument.raw) {
-                values.insert(ext);
+        Ok(spec)
+    }
+
+    /// The built-in extensions of a `type:` category.
+    fn from_list(extensions: &[&str], options: SearchOptions) -> Self {
+        let mut spec = Self::empty(options);
+        for ext in extensions {
+            if let Ok(Some(ext)) = normalize_extension(ext) {
+                spec.extensions.insert(spec.compose(ext));
             }
         }
+        spec
+    }
+
+    fn matches(&self, name: &str) -> bool {
+        let listed = match extension_of(name) {
+            Some(ext) => self.extensions.contains(&self.compose(ext)),
+            None => self.no_extension,
+        };
+        listed != self.negated
+    }
+
+    fn compose(&self, ext: String) -> String {
+        if !self.nfc {
+            return ext;
+        }
+        match to_nfc(&ext) {
+            Cow::Owned(composed) => composed,
+            Cow::Borrowed(_) => ext,
+        }
     }
-    values
 }
 
-fn normalize_extension(raw: &str) -> Option<String> {
-    let trimmed = raw.trim().trim_start_matches('.');
-    if trimmed.is_empty() {
-        None
-    } else {
-        Some(trimmed.to_ascii_lowercase())
+/// An extension as `ext:` and `type:` compare it with [`extension_of`]: ASCII
+/// lowercased, without one leading dot. `None` for a lone `.`, which names no
+/// extension.
+pub(crate) fn normalize_extension(raw: &str) -> SearchResult<Option<String>> {
+    let raw = raw.trim();
+    let ext = raw.strip_prefix('.').unwrap_or(raw);
+    if ext.contains('/') {
+        return Err(anyhow!("ext: `{raw}` is not an extension, it contains a `/`").into());
+    }
+    if ext.contains(char::is_whitespace) {
+        return Err(anyhow!("ext: `{raw}` is not an extension, it contains whitespace").into());
     }
+    if ext.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(ext.to_ascii_lowercase()))
 }
 
+/// Lowercased text after the last dot. Names without a dot, with only a leading dot
+/// (`.gitignore`) or ending in a dot (`file.`) have none.
 fn extension_of(name: &str) -> Option<String> {
     let pos = name.rfind('.')?;
-    if pos + 1 >= name.len() {
+    if pos == 0 || pos + 1 >= name.len() {
         return None;
     }
     Some(name[pos + 1..].to_ascii_lowercase())
@@ -894,10 +1830,53 @@ const EXECUTABLE_EXTENSIONS: &[&str] = &[
     "pkg",
 ];
 
+/// Which size `size:` and `disksize:` compare.
+#[derive(Clone, Copy)]
+enum SizeField {
+    /// The file length.
+    Logical,
+    /// Bytes allocated on disk, see `NodeMetadata::allocated`.
+    Allocated,
+}
+
+impl SizeField {
+    fn prefix(self) -> &'static str {
+        match self {
+            SizeField::Logical => "size",
+            SizeField::Allocated => "disksize",
+        }
+    }
+}
+
+/// Which timestamp a date filter looks at.
 #[derive(Clone, Copy)]
 enum DateField {
     Modified,
     Created,
+    Accessed,
+    /// When the file was added to its folder, see `kMDItemDateAdded`.
+    Added,
+}
+
+impl DateField {
+    fn of(kind: &FilterKind) -> Self {
+        match kind {
+            FilterKind::DateModified => DateField::Modified,
+            FilterKind::DateCreated => DateField::Created,
+            FilterKind::DateAccessed => DateField::Accessed,
+            FilterKind::DateAdded => DateField::Added,
+            _ => unreachable!("{kind:?} is not a date filter"),
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            DateField::Modified => "dm",
+            DateField::Created => "dc",
+            DateField::Accessed => "da",
+            DateField::Added => "dt",
+        }
+    }
 }
 
 struct DateContext {
@@ -1186,32 +2165,35 @@ enum SizePredicateKind {
 }
 
 impl SizePredicate {
-    fn parse(argument: &FilterArgument) -> Result<Self> {
+    fn parse(argument: &FilterArgument, field: SizeField, buckets: &SizeBuckets) -> Result<Self> {
         match &argument.kind {
             ArgumentKind::Comparison(comp) => {
-                if size_keyword(&comp.value).is_some() {
+                if buckets.range(&comp.value).is_some() {
                     bail!("size keywords cannot be used with comparison operators");
                 }
-                let value = parse_size_literal(&comp.value)?;
+                let value = parse_size_literal(&comp.value, field)?;
                 Ok(SizePredicate {
                     kind: SizePredicateKind::Comparison { op: comp.op, value },
                 })
             }
             ArgumentKind::Range(range) => {
                 if range.separator != RangeSeparator::Dots {
-                    bail!("size: only .. ranges are supported");
+                    bail!("{}: only .. ranges are supported", field.prefix());
                 }
                 let start = match &range.start {
-                    Some(value) => Some(parse_size_literal(value)?),
+                    Some(value) => Some(parse_size_literal(value, field)?),
                     None => None,
                 };
                 let end = match &range.end {
-                    Some(value) => Some(parse_size_literal(value)?),
+                    Some(value) => Some(parse_size_literal(value, field)?),
                     None => None,
                 };
                 if let (Some(s), Some(e)) = (start, end) {
                     if s > e {
-                        bail!("size range start must be less than or equal to the end");
+                        bail!(
+                            "{} range start must be less than or equal to the end",
+                            field.prefix()
+                        );
                     }
                 }
                 Ok(SizePredicate {
@@ -1221,25 +2203,25 @@ impl SizePredicate {
                     },
                 })
             }
-            ArgumentKind::List(_) => bail!("size: lists are not supported"),
-            _ => SizePredicate::from_bare_value(&argument.raw),
+            ArgumentKind::List(_) => bail!("{}: lists are not supported", field.prefix()),
+            _ => SizePredicate::from_bare_value(&argument.raw, field, buckets),
         }
     }
 
-    fn from_bare_value(raw: &str) -> Result<Self> {
+    fn from_bare_value(raw: &str, field: SizeField, buckets: &SizeBuckets) -> Result<Self> {
         let trimmed = raw.trim();
         if trimmed.is_empty() {
-            bail!("size: requires a value");
+            bail!("{}: requires a value", field.prefix());
         }
-        if let Some(range) = size_keyword(trimmed) {
+        if let Some(bucket) = buckets.range(trimmed) {
             return Ok(SizePredicate {
                 kind: SizePredicateKind::Range {
-                    min: range.min,
-                    max: range.max,
+                    min: Some(bucket.min),
+                    max: bucket.max,
                 },
             });
         }
-        let value = parse_size_literal(trimmed)?;
+        let value = parse_size_literal(trimmed, field)?;
         Ok(SizePredicate {
             kind: SizePredicateKind::Comparison {
                 op: ComparisonOp::Eq,
@@ -1275,53 +2257,127 @@ impl SizePredicate {
     }
 }
 
-struct SizeKeywordRange {
-    min: Option<u64>,
-    max: Option<u64>,
+/// Levels below a root folder, memoized so nodes share the walk up to their
+/// common ancestors instead of each climbing to the root.
+struct NodeDepths<'a> {
+    cache: &'a SearchCache,
+    root: SlabIndex,
+    /// `None` for nodes outside of `root`.
+    memo: HashMap<SlabIndex, Option<u32>>,
 }
 
-fn size_keyword(name: &str) -> Option<SizeKeywordRange> {
-    let normalized = name.trim().to_ascii_lowercase();
-    match normalized.as_str() {
-        "empty" => Some(SizeKeywordRange {
-            min: Some(0),
-            max: Some(0),
-        }),
-        "tiny" => Some(SizeKeywordRange {
-            min: Some(0),
-            max: Some(10 * KB),
-        }),
-        "small" => Some(SizeKeywordRange {
-            min: Some(10 * KB + 1),
-            max: Some(100 * KB),
-        }),
-        "medium" => Some(SizeKeywordRange {
-            min: Some(100 * KB + 1),
-            max: Some(MB),
-        }),
-        "large" => Some(SizeKeywordRange {
-            min: Some(MB + 1),
-            max: Some(16 * MB),
-        }),
-        "huge" => Some(SizeKeywordRange {
-            min: Some(16 * MB + 1),
-            max: Some(128 * MB),
-        }),
-        "gigantic" | "giant" => Some(SizeKeywordRange {
-            min: Some(128 * MB + 1),
-            max: None,
-        }),
-        _ => None,
+impl<'a> NodeDepths<'a> {
+    fn new(cache: &'a SearchCache, root: SlabIndex) -> Self {
+        Self {
+            cache,
+            root,
+            memo: HashMap::new(),
+        }
+    }
+
+    /// 0 for the root itself, 1 for its children and so on.
+    fn depth(&mut self, index: SlabIndex) -> Option<u32> {
+        let mut chain = Vec::new();
+        let mut current = Some(index);
+        let mut depth = loop {
+            let Some(node) = current else {
+                break None;
+            };
+            if node == self.root {
+                break Some(0);
+            }
+            if let Some(&known) = self.memo.get(&node) {
+                break known;
+            }
+            chain.push(node);
+            current = self
+                .cache
+                .file_nodes
+                .get(node)
+                .and_then(|node| node.name_and_parent.parent());
+        };
+        for &node in chain.iter().rev() {
+            depth = depth.map(|depth| depth + 1);
+            self.memo.insert(node, depth);
+        }
+        depth
+    }
+}
+
+/// Parsed `depth:` argument. A bare number is the deepest level allowed, so
+/// `depth:2` keeps the first two levels; `depth:=2` keeps only the second.
+enum DepthPredicate {
+    Comparison { op: ComparisonOp, value: u32 },
+    Range { min: Option<u32>, max: Option<u32> },
+}
+
+impl DepthPredicate {
+    fn parse(argument: &FilterArgument) -> Result<Self> {
+        match &argument.kind {
+            ArgumentKind::Comparison(comp) => Ok(DepthPredicate::Comparison {
+                op: comp.op,
+                value: parse_depth_literal(&comp.value)?,
+            }),
+            ArgumentKind::Range(range) => {
+                if range.separator != RangeSeparator::Dots {
+                    bail!("depth: only .. ranges are supported");
+                }
+                let min = range
+                    .start
+                    .as_deref()
+                    .map(parse_depth_literal)
+                    .transpose()?;
+                let max = range.end.as_deref().map(parse_depth_literal).transpose()?;
+                if let (Some(min), Some(max)) = (min, max) {
+                    if min > max {
+                        bail!("depth range start must be less than or equal to the end");
+                    }
+                }
+                Ok(DepthPredicate::Range { min, max })
+            }
+            ArgumentKind::List(_) => bail!("depth: lists are not supported"),
+            _ => Ok(DepthPredicate::Range {
+                min: None,
+                max: Some(parse_depth_literal(&argument.raw)?),
+            }),
+        }
+    }
+
+    fn matches(&self, depth: u32) -> bool {
+        match self {
+            DepthPredicate::Comparison { op, value } => match op {
+                ComparisonOp::Lt => depth < *value,
+                ComparisonOp::Lte => depth <= *value,
+                ComparisonOp::Gt => depth > *value,
+                ComparisonOp::Gte => depth >= *value,
+                ComparisonOp::Eq => depth == *value,
+                ComparisonOp::Ne => depth != *value,
+            },
+            DepthPredicate::Range { min, max } => {
+                min.is_none_or(|min| depth >= min) && max.is_none_or(|max| depth <= max)
+            }
+        }
     }
 }
 
-const KB: u64 = 1024;
-const MB: u64 = 1024 * 1024;
+fn parse_depth_literal(raw: &str) -> Result<u32> {
+    let trimmed = raw.trim();
+    if trimmed.is_empty() {
+        bail!("depth: expected a number");
+    }
+    if trimmed.starts_with('-') {
+        bail!("depth: value {raw:?} must not be negative");
+    }
+    trimmed
+        .parse()
+        .map_err(|_| anyhow!("depth: failed to parse number in {raw:?}"))
+}
 
-fn parse_size_literal(raw: &str) -> Result<u64> {
+fn parse_size_literal(raw: &str, field: SizeField) -> Result<u64> {
+    let prefix = field.prefix();
     let trimmed = raw.trim();
     if trimmed.is_empty() {
-        bail!("size: expected a number");
+        bail!("{prefix}: expected a number");
     }
     let mut split = trimmed.len();
     for (idx, ch) in trimmed.char_indices() {
@@ -1334,15 +2390,15 @@ fn parse_size_literal(raw: &str) -> Result<u64> {
 
     let (value_part, unit_part) = trimmed.split_at(split);
     if value_part.is_empty() {
-        bail!("size: expected a numeric value in {raw:?}");
+        bail!("{prefix}: expected a numeric value in {raw:?}");
     }
     let value: f64 = value_part
         .parse()
-        .map_err(|_| anyhow!("size: failed to parse number in {raw:?}"))?;
+        .map_err(|_| anyhow!("{prefix}: failed to parse number in {raw:?}"))?;
     let multiplier = size_unit_multiplier(unit_part)?;
     let bytes = (value * multiplier as f64).round();
     if !bytes.is_finite() || bytes < 0.0 {
-        bail!("size: value {raw:?} is out of range");
+        bail!("{prefix}: value {raw:?} is out of range");
     }
     if bytes > u64::MAX as f64 {
         Ok(u64::MAX)
@@ -1365,84 +2421,177 @@ fn size_unit_multiplier(unit: &str) -> Result<u64> {
     Ok(multiplier)
 }
 
-fn filter_nodes(
-    nodes: Vec<SlabIndex>,
-    token: CancellationToken,
-    mut predicate: impl FnMut(SlabIndex) -> bool,
-) -> Option<Vec<SlabIndex>> {
-    let mut filtered = Vec::with_capacity(nodes.len());
-    for (i, index) in nodes.into_iter().enumerate() {
-        // While filtering dc: dm:, lstat is slow. Thus we check cancellation more frequently.
-        if i % (CANCEL_CHECK_INTERVAL / 4) == 0 && token.is_cancelled() {
-            return None;
-        }
-        if predicate(index) {
-            filtered.push(index);
-        }
+pub(crate) fn phrase_matchers(
+    text: &str,
+    options: SearchOptions,
+) -> SearchResult<Vec<SegmentMatcher>> {
+    let segments = query_segmentation(text);
+    if segments.is_empty() {
+        return Err(anyhow!("Unprocessable term: {text:?}").into());
     }
-    Some(filtered)
+    Ok(build_segment_matchers(&segments, options)
+        .map_err(|err| anyhow!("Invalid regex pattern: {err}"))?)
 }
 
-fn intersect_in_place(
-    values: &mut Vec<SlabIndex>,
-    rhs: &[SlabIndex],
-    token: CancellationToken,
-) -> Option<()> {
-    if values.is_empty() {
-        return Some(());
-    }
-    let rhs_set: HashSet<SlabIndex> = rhs.iter().copied().collect();
-    let mut filtered = Vec::with_capacity(values.len().min(rhs.len()));
-    for (i, index) in values.iter().copied().enumerate() {
-        if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
-            return None;
-        }
-        if rhs_set.contains(&index) {
-            filtered.push(index);
+/// Matcher of a `regex:` term. Anchored literals take the plain prefix, suffix
+/// or exact match path, anything else is compiled with [`REGEX_SIZE_LIMIT`].
+pub(crate) fn regex_matcher(pattern: &str, options: SearchOptions) -> SearchResult<SegmentMatcher> {
+    if let Some((kind, literal)) = anchored_literal(pattern) {
+        return Ok(literal_matcher(kind, &literal, options)
+            .map_err(|err| anyhow!("Invalid regex pattern: {err}"))?);
+    }
+    let mut builder = RegexBuilder::new(pattern);
+    builder
+        .case_insensitive(options.case_insensitive)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_SIZE_LIMIT);
+    let regex = builder.build().map_err(|err| match err {
+        regex::Error::CompiledTooBig(limit) => anyhow!(
+            "Regex pattern is too complex: it needs more than {} KiB compiled, try a simpler one",
+            limit / 1024
+        ),
+        err => anyhow!("Invalid regex pattern: {err}"),
+    })?;
+    Ok(SegmentMatcher::Regex { regex, hint: None })
+}
+
+/// `^literal`, `literal$` or `^literal$` with only literal characters, escaped
+/// or not, in between: what a prefix, suffix or exact name match does.
+fn anchored_literal(pattern: &str) -> Option<(SegmentKind, String)> {
+    let (start, rest) = match pattern.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let mut literal = String::with_capacity(rest.len());
+    let mut end = false;
+    let mut chars = rest.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                let escaped = chars.next()?;
+                if !r"\.+*?()|[]{}^$#&-~".contains(escaped) {
+                    // A class like `\d` or an assertion like `\b`.
+                    return None;
+                }
+                literal.push(escaped);
+            }
+            '$' if chars.as_str().is_empty() => end = true,
+            '.' | '+' | '*' | '?' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                return None;
+            }
+            _ => literal.push(ch),
         }
     }
-    *values = filtered;
-    Some(())
+    let kind = match (start, end) {
+        (true, true) => SegmentKind::Exact,
+        (true, false) => SegmentKind::Prefix,
+        (false, true) => SegmentKind::Suffix,
+        (false, false) => return None,
+    };
+    Some((kind, literal))
+}
+
+/// `token` that also fires once the query's regex time budget is spent.
+fn regex_token(token: CancellationToken, options: SearchOptions) -> CancellationToken {
+    token.with_deadline(Instant::now() + options.regex_time_budget)
 }
 
-fn difference_in_place(
-    values: &mut Vec<SlabIndex>,
-    rhs: &[SlabIndex],
+/// Turn a cancellation caused by the regex time budget rather than `token`
+/// into an error the user can act on.
+fn over_regex_budget<T>(
+    result: SearchResult<T>,
     token: CancellationToken,
-) -> Option<()> {
-    if values.is_empty() || rhs.is_empty() {
-        return Some(());
-    }
-    let rhs_set: HashSet<SlabIndex> = rhs.iter().copied().collect();
-    let mut filtered = Vec::with_capacity(values.len());
-    for (i, index) in values.iter().copied().enumerate() {
-        if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
-            return None;
-        }
-        if !rhs_set.contains(&index) {
-            filtered.push(index);
+    options: SearchOptions,
+) -> SearchResult<T> {
+    match result {
+        Err(SearchError::Cancelled) if !token.is_cancelled() => Err(anyhow!(
+            "Regex pattern took longer than {:?} to match, try a simpler one",
+            options.regex_time_budget
+        )
+        .into()),
+        result => result,
+    }
+}
+
+/// The filter `expr` consists of if it reads metadata of every node it's
+/// handed, statting those whose metadata isn't cached.
+fn probing_filter(expr: &Expr) -> Option<&Filter> {
+    match expr {
+        Expr::Term(Term::Filter(filter))
+            if matches!(
+                filter.kind,
+                FilterKind::Size
+                    | FilterKind::DiskSize
+                    | FilterKind::DateModified
+                    | FilterKind::DateCreated
+                    | FilterKind::DateAccessed
+                    | FilterKind::DateAdded
+            ) =>
+        {
+            Some(filter)
         }
+        _ => None,
     }
-    *values = filtered;
-    Some(())
 }
 
-fn union_in_place(
-    values: &mut Vec<SlabIndex>,
-    rhs: &[SlabIndex],
-    token: CancellationToken,
-) -> Option<()> {
-    if rhs.is_empty() {
-        return Some(());
+/// Whether every node `expr` can match has to match a name term, so the
+/// candidates come from the name pool rather than from every node.
+fn has_name_term(expr: &Expr) -> bool {
+    match expr {
+        Expr::Term(Term::Word(_) | Term::Phrase(_) | Term::Regex(_)) => true,
+        Expr::Term(Term::Filter(_)) | Expr::Empty | Expr::Not(_) => false,
+        Expr::And(parts) => parts.iter().any(has_name_term),
+        Expr::Or(parts) => parts.iter().all(has_name_term),
     }
-    let mut seen: HashSet<SlabIndex> = values.iter().copied().collect();
-    for (i, index) in rhs.iter().copied().enumerate() {
-        if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
-            return None;
+}
+
+/// Memoized ancestor lookup: every node on a walked parent chain remembers the
+/// answer, so checking N nodes visits each ancestor once instead of once per node.
+pub(crate) struct AncestorCheck<'a> {
+    cache: &'a SearchCache,
+    folders: &'a HashSet<SlabIndex>,
+    known: HashMap<SlabIndex, bool>,
+}
+
+impl AncestorCheck<'_> {
+    pub(crate) fn contains(&mut self, index: SlabIndex) -> bool {
+        let mut chain = Vec::new();
+        let mut current = self.cache.file_nodes[index].name_and_parent.parent();
+        let found = loop {
+            let Some(parent) = current else {
+                break false;
+            };
+            if self.folders.contains(&parent) {
+                break true;
+            }
+            if let Some(&known) = self.known.get(&parent) {
+                break known;
+            }
+            chain.push(parent);
+            current = self.cache.file_nodes[parent].name_and_parent.parent();
+        };
+        // `chain` holds nodes that are not folders themselves, so whether one of
+        // their ancestors is a folder is exactly `found`.
+        self.known
+            .extend(chain.into_iter().map(|node| (node, found)));
+        found
+    }
+}
+
+fn filter_nodes(
+    nodes: Vec<SlabIndex>,
+    token: CancellationToken,
+    mut predicate: impl FnMut(SlabIndex) -> bool,
+) -> SearchResult<Vec<SlabIndex>> {
+    let mut filtered = Vec::with_capacity(nodes.len());
+    for (i, index) in nodes.into_iter().enumerate() {
+        // While filtering dc: dm:, lstat is slow. Thus we check cancellation more frequently.
+        if i % (CANCEL_CHECK_INTERVAL / 4) == 0 && token.is_cancelled() {
+            return Err(SearchError::Cancelled);
         }
-        if seen.insert(index) {
-            values.push(index);
+        if predicate(index) {
+            filtered.push(index);
         }
     }
-    Some(())
+    Ok(filtered)
 }
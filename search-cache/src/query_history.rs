@@ -0,0 +1,221 @@
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum number of distinct queries kept; the lowest scoring ones are evicted first.
+pub const QUERY_HISTORY_CAPACITY: usize = 1000;
+/// A use loses half of its weight after this many seconds.
+const HALF_LIFE_SECS: f64 = 3.0 * 24.0 * 60.0 * 60.0;
+
+/// Recently and frequently used queries, ranked by frecency (recency-decayed frequency).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QueryHistory {
+    entries: HashMap<String, HistoryEntry>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub(crate) struct HistoryEntry {
+    /// Decayed use count as of `last_used`.
+    score: f64,
+    /// Unix timestamp in seconds.
+    last_used: u64,
+}
+
+impl HistoryEntry {
+    pub(crate) fn new(now: u64) -> Self {
+        Self {
+            score: 1.0,
+            last_used: now,
+        }
+    }
+
+    /// Count another use at `now`.
+    pub(crate) fn record(&mut self, now: u64) {
+        self.score = self.score_at(now) + 1.0;
+        self.last_used = self.last_used.max(now);
+    }
+
+    pub(crate) fn score_at(&self, now: u64) -> f64 {
+        let elapsed = now.saturating_sub(self.last_used) as f64;
+        self.score * 0.5f64.powf(elapsed / HALF_LIFE_SECS)
+    }
+}
+
+impl QueryHistory {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn record(&mut self, query: &str) {
+        self.record_at(query, unix_now());
+    }
+
+    /// Record a use of `query` at unix time `now`. Blank queries are ignored.
+    pub fn record_at(&mut self, query: &str, now: u64) {
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        match self.entries.get_mut(query) {
+            Some(entry) => entry.record(now),
+            None => {
+                if self.entries.len() >= QUERY_HISTORY_CAPACITY {
+                    self.evict_lowest(now);
+                }
+                self.entries
+                    .insert(query.to_string(), HistoryEntry::new(now));
+            }
+        }
+    }
+
+    pub fn suggest(&self, prefix: &str, limit: usize) -> Vec<String> {
+        self.suggest_at(prefix, limit, unix_now())
+    }
+
+    /// Queries starting with `prefix` (case-insensitively), best frecency first.
+    pub fn suggest_at(&self, prefix: &str, limit: usize, now: u64) -> Vec<String> {
+        let prefix = prefix.trim_start().to_lowercase();
+        let mut candidates: Vec<(&String, f64, u64)> = self
+            .entries
+            .iter()
+            .filter(|(query, _)| query.to_lowercase().starts_with(&prefix))
+            .map(|(query, entry)| (query, entry.score_at(now), entry.last_used))
+            .collect();
+        candidates.sort_unstable_by(|(query_a, score_a, used_a), (query_b, score_b, used_b)| {
+            score_b
+                .total_cmp(score_a)
+                .then_with(|| used_b.cmp(used_a))
+                .then_with(|| query_a.cmp(query_b))
+        });
+        candidates
+            .into_iter()
+            .take(limit)
+            .map(|(query, ..)| query.clone())
+            .collect()
+    }
+
+    fn evict_lowest(&mut self, now: u64) {
+        let lowest = self
+            .entries
+            .iter()
+            .min_by(|(_, a), (_, b)| {
+                a.score_at(now)
+                    .total_cmp(&b.score_at(now))
+                    .then_with(|| a.last_used.cmp(&b.last_used))
+            })
+            .map(|(query, _)| query.clone());
+        if let Some(lowest) = lowest {
+            self.entries.remove(&lowest);
+        }
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn test_frequent_beats_single_use() {
+        let mut history = QueryHistory::default();
+        let now = 100 * DAY;
+        history.record_at("ext:rs", now);
+        history.record_at("ext:rs", now);
+        history.record_at("ext:rs", now);
+        history.record_at("ext:md", now);
+        assert_eq!(history.suggest_at("", 10, now), ["ext:rs", "ext:md"]);
+    }
+
+    #[test]
+    fn test_recent_beats_stale_frequent() {
+        let mut history = QueryHistory::default();
+        let start = 100 * DAY;
+        for _ in 0..4 {
+            history.record_at("old favourite", start);
+        }
+        let now = start + 30 * DAY;
+        history.record_at("fresh", now);
+        assert_eq!(history.suggest_at("", 10, now), ["fresh", "old favourite"]);
+        // Right after the burst the frequent query still wins.
+        assert_eq!(history.suggest_at("", 1, start + DAY), ["old favourite"]);
+    }
+
+    #[test]
+    fn test_mixed_usage_ordering() {
+        let mut history = QueryHistory::default();
+        let start = 100 * DAY;
+        history.record_at("a", start);
+        history.record_at("b", start + DAY);
+        history.record_at("a", start + 2 * DAY);
+        history.record_at("c", start + 3 * DAY);
+        history.record_at("c", start + 3 * DAY);
+        history.record_at("c", start + 3 * DAY);
+        let now = start + 3 * DAY;
+        assert_eq!(history.suggest_at("", 10, now), ["c", "a", "b"]);
+    }
+
+    #[test]
+    fn test_equal_scores_prefer_most_recent() {
+        let mut history = QueryHistory::default();
+        history.record_at("first", 10);
+        history.record_at("second", 10);
+        assert_eq!(history.suggest_at("", 10, 10), ["first", "second"]);
+        history.record_at("third", 20);
+        assert_eq!(history.suggest_at("", 1, 20), ["third"]);
+    }
+
+    #[test]
+    fn test_prefix_filtering_and_limit() {
+        let mut history = QueryHistory::default();
+        for query in ["report ext:pdf", "Readme", "src/", "regex:^a"] {
+            history.record_at(query, DAY);
+        }
+        let mut suggestions = history.suggest_at("re", 10, DAY);
+        suggestions.sort();
+        assert_eq!(suggestions, ["Readme", "regex:^a", "report ext:pdf"]);
+        assert_eq!(history.suggest_at("RE", 2, DAY).len(), 2);
+        assert!(history.suggest_at("zzz", 10, DAY).is_empty());
+        assert!(history.suggest_at("", 0, DAY).is_empty());
+    }
+
+    #[test]
+    fn test_blank_queries_are_ignored_and_trimmed() {
+        let mut history = QueryHistory::default();
+        history.record_at("   ", DAY);
+        history.record_at("", DAY);
+        assert!(history.is_empty());
+        history.record_at("  foo ", DAY);
+        history.record_at("foo", DAY);
+        assert_eq!(history.len(), 1);
+        assert_eq!(history.suggest_at("", 10, DAY), ["foo"]);
+    }
+
+    #[test]
+    fn test_capacity_evicts_lowest_score() {
+        let mut history = QueryHistory::default();
+        history.record_at("keeper", DAY);
+        history.record_at("keeper", DAY);
+        for i in 0..QUERY_HISTORY_CAPACITY - 1 {
+            history.record_at(&format!("q{i}"), DAY + i as u64);
+        }
+        assert_eq!(history.len(), QUERY_HISTORY_CAPACITY);
+        history.record_at("newcomer", 2 * DAY);
+        assert_eq!(history.len(), QUERY_HISTORY_CAPACITY);
+        // The oldest single-use entry is gone, the frequent and the new ones stay.
+        assert!(history.suggest_at("q0", 10, 2 * DAY).is_empty());
+        assert_eq!(history.suggest_at("keeper", 10, 2 * DAY), ["keeper"]);
+        assert_eq!(history.suggest_at("newcomer", 10, 2 * DAY), ["newcomer"]);
+    }
+}
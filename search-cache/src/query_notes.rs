@@ -0,0 +1,59 @@
+use serde::Serialize;
+use std::{
+    cell::{Cell, RefCell},
+    path::PathBuf,
+};
+
+/// Something a filter found out while evaluating a query that explains an
+/// empty or surprising result, see [`crate::SearchOutcome::notes`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+#[serde(tag = "kind", content = "path", rename_all = "camelCase")]
+pub enum QueryNote {
+    /// The folder of a `parent:`, `infolder:` or `nosubfolders:` filter isn't
+    /// in the index, so the filter matched nothing.
+    ParentNotIndexed(PathBuf),
+    /// A filter that only matches files (`size:`, `disksize:`, `ext:`) was
+    /// handed folders only, e.g. `folder: size:>0`.
+    FilterExcludesAllDirectories,
+    /// `ext:` was given no usable extension, e.g. `ext:.` or `ext:.;.`.
+    EmptyExtensionList,
+    /// A `size:` or date filter ORed with name terms stopped statting nodes
+    /// once it reached [`crate::SearchOptions::filter_only_scan_limit`], so
+    /// it may have missed some.
+    FilterArmTruncated,
+    /// Filters couldn't stat some nodes because the process may not read
+    /// their folder, on macOS usually for lack of Full Disk Access. How many
+    /// is in [`crate::SearchOutcome::metadata_errors`].
+    MetadataPermissionDenied,
+}
+
+/// Notes collected while evaluating one query, each kept once in the order
+/// they were first pushed, and whether the evaluation was cut short.
+#[derive(Debug, Default)]
+pub(crate) struct QueryNotes {
+    notes: RefCell<Vec<QueryNote>>,
+    cut_short: Cell<bool>,
+}
+
+impl QueryNotes {
+    pub(crate) fn push(&self, note: QueryNote) {
+        let mut notes = self.notes.borrow_mut();
+        if !notes.contains(&note) {
+            notes.push(note);
+        }
+    }
+
+    /// Record that cancellation made a step return what it had so far
+    /// instead of its full result.
+    pub(crate) fn cut_short(&self) {
+        self.cut_short.set(true);
+    }
+
+    pub(crate) fn was_cut_short(&self) -> bool {
+        self.cut_short.get()
+    }
+
+    pub(crate) fn into_vec(self) -> Vec<QueryNote> {
+        self.notes.into_inner()
+    }
+}
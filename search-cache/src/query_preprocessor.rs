@@ -127,7 +127,7 @@ fn expand_home_prefix(value: &str, home: &str) -> Option<String> {
     }
 }
 
-fn home_dir() -> Option<String> {
+pub(crate) fn home_dir() -> Option<String> {
     env::var("HOME").ok()
 }
 
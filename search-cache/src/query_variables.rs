@@ -0,0 +1,190 @@
+//! `%name%` variables in filter arguments, replaced in the query text before
+//! it's parsed so saved searches and deep links don't bake in dates and paths:
+//! `dm:%today% infolder:%home%/Downloads`. See [`SearchOptions::expand_variables`].
+
+use crate::{SearchError, SearchOptions, SearchResult, query_preprocessor::home_dir};
+use cardinal_syntax::ParseError;
+use jiff::{Timestamp, civil::Date, tz::TimeZone};
+use std::{borrow::Cow, path::Path};
+
+#[cfg(test)]
+thread_local! {
+    /// What `%today%` is on this thread instead of the system clock's date.
+    pub(crate) static TODAY: std::cell::Cell<Option<Date>> = const { std::cell::Cell::new(None) };
+}
+
+/// `line` with the variables of its filter arguments replaced, `root` being
+/// what `%root%` stands for. A bare argument whose value now holds spaces or
+/// operators is quoted. Words, phrases and `regex:` patterns are left as
+/// they are, and so is a `%` not followed by a name and a closing `%`
+/// (`100%`). Fails with [`SearchError::UnknownVariable`] for other names.
+pub(crate) fn expand_variables<'a>(
+    line: &'a str,
+    root: &Path,
+    options: SearchOptions,
+) -> SearchResult<Cow<'a, str>> {
+    if !options.expand_variables || !line.contains('%') {
+        return Ok(Cow::Borrowed(line));
+    }
+    let mut expanded = String::with_capacity(line.len());
+    let mut pos = 0;
+    while let Some(ch) = line[pos..].chars().next() {
+        if ch == '"' {
+            // A phrase, up to its closing quote.
+            let end = closing_quote(line, pos).map_or(line.len(), |close| close + 1);
+            expanded.push_str(&line[pos..end]);
+            pos = end;
+            continue;
+        }
+        let at_token_start = line[..pos]
+            .chars()
+            .next_back()
+            .is_none_or(|prev| prev.is_whitespace() || matches!(prev, '(' | '<' | '|' | '!'));
+        let name_len = line[pos..]
+            .find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-')))
+            .unwrap_or(line.len() - pos);
+        if !at_token_start || name_len == 0 || !line[pos + name_len..].starts_with(':') {
+            expanded.push(ch);
+            pos += ch.len_utf8();
+            continue;
+        }
+        let argument_start = pos + name_len + 1;
+        expanded.push_str(&line[pos..argument_start]);
+        let filter = &line[pos..pos + name_len];
+        let argument_end = argument_end(line, argument_start);
+        let argument = &line[argument_start..argument_end];
+        if filter.eq_ignore_ascii_case("regex") {
+            expanded.push_str(argument);
+        } else if let Some(inner) = argument.strip_prefix('"') {
+            // An unterminated phrase stays so, for the parser to report.
+            let (inner, closed) = match inner.strip_suffix('"') {
+                Some(inner) => (inner, true),
+                None => (inner, false),
+            };
+            expanded.push('"');
+            expanded.push_str(&expand_text(inner, argument_start + 1, root)?.0);
+            if closed {
+                expanded.push('"');
+            }
+        } else {
+            let (value, needs_quotes) = expand_text(argument, argument_start, root)?;
+            if needs_quotes {
+                expanded.push('"');
+                expanded.push_str(&value);
+                expanded.push('"');
+            } else {
+                expanded.push_str(&value);
+            }
+        }
+        pos = argument_end;
+    }
+    Ok(Cow::Owned(expanded))
+}
+
+/// Byte offset of the quote closing the phrase opened at `open`.
+fn closing_quote(line: &str, open: usize) -> Option<usize> {
+    line[open + 1..].find('"').map(|offset| open + 1 + offset)
+}
+
+/// Where the filter argument starting at `start` ends, roughly where the
+/// parser stops it: after its closing quote, or at whitespace, `|`, a group
+/// closing or a `<`/`>` past its first character.
+fn argument_end(line: &str, start: usize) -> usize {
+    if line[start..].starts_with('"') {
+        return closing_quote(line, start).map_or(line.len(), |close| close + 1);
+    }
+    line[start..]
+        .char_indices()
+        .find(|&(offset, ch)| {
+            ch.is_whitespace() || matches!(ch, '|' | ')') || (offset > 0 && matches!(ch, '<' | '>'))
+        })
+        .map_or(line.len(), |(offset, _)| start + offset)
+}
+
+/// Characters a bare argument can't hold, Everything has no escapes so the
+/// argument is quoted instead.
+fn breaks_argument(ch: char) -> bool {
+    ch.is_whitespace() || matches!(ch, '|' | '<' | '>' | '(' | ')' | '!')
+}
+
+/// `text` with its `%name%` variables replaced, `offset` being where it
+/// starts in the query, for the errors, and whether a value holds characters
+/// that end a bare argument.
+fn expand_text<'a>(
+    text: &'a str,
+    offset: usize,
+    root: &Path,
+) -> SearchResult<(Cow<'a, str>, bool)> {
+    if !text.contains('%') {
+        return Ok((Cow::Borrowed(text), false));
+    }
+    let mut expanded = String::with_capacity(text.len());
+    let mut needs_quotes = false;
+    let mut rest = text;
+    while let Some(percent) = rest.find('%') {
+        expanded.push_str(&rest[..percent]);
+        let after = &rest[percent + 1..];
+        let name = after
+            .find('%')
+            .map(|close| &after[..close])
+            .filter(|name| is_variable_name(name));
+        let Some(name) = name else {
+            expanded.push('%');
+            rest = after;
+            continue;
+        };
+        let position = offset + (text.len() - rest.len()) + percent;
+        let value = variable_value(name, root).ok_or_else(|| SearchError::UnknownVariable {
+            name: name.to_string(),
+            position,
+        })?;
+        if value.contains('"') {
+            return Err(SearchError::Parse(ParseError {
+                message: format!("%{name}% holds a double quote, which can't be quoted"),
+                position,
+                too_deep: None,
+            }));
+        }
+        needs_quotes |= value.contains(breaks_argument);
+        expanded.push_str(&value);
+        rest = &after[name.len() + 1..];
+    }
+    expanded.push_str(rest);
+    Ok((Cow::Owned(expanded), needs_quotes))
+}
+
+fn is_variable_name(name: &str) -> bool {
+    name.chars()
+        .next()
+        .is_some_and(|ch| ch.is_ascii_alphabetic() || ch == '_')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+/// What the variable `name` stands for, case-insensitively, `None` for an
+/// unknown one. Without `HOME` the folders are unknown too.
+fn variable_value(name: &str, root: &Path) -> Option<String> {
+    let home_subdir = |subdir: &str| {
+        let home = home_dir()?;
+        Some(Path::new(&home).join(subdir).to_string_lossy().into_owned())
+    };
+    match name.to_ascii_lowercase().as_str() {
+        "today" => Some(today().to_string()),
+        "yesterday" => today().yesterday().ok().map(|date| date.to_string()),
+        "home" => home_dir(),
+        "root" => Some(root.to_string_lossy().into_owned()),
+        "desktop" => home_subdir("Desktop"),
+        "documents" => home_subdir("Documents"),
+        "downloads" => home_subdir("Downloads"),
+        _ => None,
+    }
+}
+
+fn today() -> Date {
+    #[cfg(test)]
+    if let Some(today) = TODAY.with(|today| today.get()) {
+        return today;
+    }
+    Timestamp::now().to_zoned(TimeZone::system()).date()
+}
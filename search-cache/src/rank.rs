@@ -0,0 +1,199 @@
+use crate::{
+    IndexRemap, SearchCache, SlabIndex, natural_cmp,
+    query_history::{HistoryEntry, unix_now},
+};
+use hashbrown::HashMap;
+use serde::{Deserialize, Serialize};
+use std::{
+    num::NonZeroU32,
+    sync::{MutexGuard, PoisonError},
+};
+
+/// Maximum number of opened nodes remembered; the lowest scoring ones are evicted first.
+pub const ACCESS_LOG_CAPACITY: usize = 1000;
+
+/// Order in which search results are returned, applied after filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RankMode {
+    /// Slab order, as the evaluation produced them.
+    #[default]
+    None,
+    /// Most recently modified first. Only metadata that was fetched already is
+    /// looked at, nodes without it rank as oldest, so ranking never stats.
+    RecentFirst,
+    /// Nodes opened often and lately first (see [`SearchCache::record_access`]),
+    /// then as [`RankMode::RecentFirst`].
+    Frecency,
+    /// Most recently first seen first (see [`SearchCache::first_seen`]),
+    /// nodes from the initial walk last.
+    RecentlyAdded,
+    /// By name in Finder-like order, see [`natural_cmp`].
+    NameNatural,
+}
+
+/// Nodes the user opened, ranked by frecency like the query history.
+///
+/// Keyed by slab index: removed nodes are forgotten, and a rescan carries
+/// the entries over to the nodes at the same paths.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AccessLog {
+    entries: HashMap<SlabIndex, HistoryEntry>,
+}
+
+impl AccessLog {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Record an access of `index` at unix time `now`.
+    pub fn record_at(&mut self, index: SlabIndex, now: u64) {
+        match self.entries.get_mut(&index) {
+            Some(entry) => entry.record(now),
+            None => {
+                if self.entries.len() >= ACCESS_LOG_CAPACITY {
+                    self.evict_lowest(now);
+                }
+                self.entries.insert(index, HistoryEntry::new(now));
+            }
+        }
+    }
+
+    /// Decayed access count of `index` at `now`, 0 for nodes never opened.
+    pub fn score_at(&self, index: SlabIndex, now: u64) -> f64 {
+        self.entries
+            .get(&index)
+            .map_or(0.0, |entry| entry.score_at(now))
+    }
+
+    pub(crate) fn forget(&mut self, index: SlabIndex) {
+        self.entries.remove(&index);
+    }
+
+    pub(crate) fn remap(&mut self, remap: &IndexRemap) {
+        remap.rekey(&mut self.entries);
+    }
+
+    /// Take every entry out, e.g. to key them again by path.
+    pub(crate) fn drain(&mut self) -> impl Iterator<Item = (SlabIndex, HistoryEntry)> + '_ {
+        self.entries.drain()
+    }
+
+    /// Put back an entry taken by [`Self::drain`], at most as many as before.
+    pub(crate) fn restore(&mut self, index: SlabIndex, entry: HistoryEntry) {
+        self.entries.insert(index, entry);
+    }
+
+    fn evict_lowest(&mut self, now: u64) {
+        let lowest = self
+            .entries
+            .iter()
+            .min_by(|(_, a), (_, b)| a.score_at(now).total_cmp(&b.score_at(now)))
+            .map(|(&index, _)| index);
+        if let Some(lowest) = lowest {
+            self.entries.remove(&lowest);
+        }
+    }
+}
+
+impl SearchCache {
+    /// Locks the access log, don't hold the guard across a search.
+    pub fn access_log(&self) -> MutexGuard<'_, AccessLog> {
+        self.access_log
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Note that the user opened `index`, boosting it in [`RankMode::Frecency`] results.
+    pub fn record_access(&self, index: SlabIndex) {
+        if self.file_nodes.get(index).is_some() {
+            self.access_log().record_at(index, unix_now());
+        }
+    }
+
+    /// Reorder `nodes` according to `mode`. The sort is stable: nodes with
+    /// equal keys keep their slab order.
+    pub(crate) fn rank(&self, nodes: &mut Vec<SlabIndex>, mode: RankMode) {
+        match mode {
+            RankMode::None => {}
+            RankMode::RecentFirst => {
+                nodes.sort_by_cached_key(|&index| std::cmp::Reverse(self.cached_mtime(index)));
+            }
+            RankMode::Frecency => {
+                let now = unix_now();
+                let access_log = self.access_log();
+                let mut keyed: Vec<(f64, u32, SlabIndex)> = nodes
+                    .iter()
+                    .map(|&index| {
+                        let score = access_log.score_at(index, now);
+                        (score, self.cached_mtime(index), index)
+                    })
+                    .collect();
+                keyed.sort_by(|(score_a, mtime_a, _), (score_b, mtime_b, _)| {
+                    score_b
+                        .total_cmp(score_a)
+                        .then_with(|| mtime_b.cmp(mtime_a))
+                });
+                *nodes = keyed.into_iter().map(|(.., index)| index).collect();
+            }
+            RankMode::RecentlyAdded => {
+                nodes.sort_by_cached_key(|&index| std::cmp::Reverse(self.first_seen(index)));
+            }
+            RankMode::NameNatural => {
+                nodes.sort_by(|&a, &b| natural_cmp(self.sort_name(a), self.sort_name(b)));
+            }
+        }
+    }
+
+    /// Modification time from already fetched metadata, 0 when there is none.
+    fn cached_mtime(&self, index: SlabIndex) -> u32 {
+        self.file_nodes
+            .get(index)
+            .and_then(|node| node.metadata.get().as_ref()?.mtime())
+            .map_or(0, NonZeroU32::get)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    #[test]
+    fn test_scores_decay_and_accumulate() {
+        let mut log = AccessLog::default();
+        let index = SlabIndex::new(7);
+        assert_eq!(log.score_at(index, DAY), 0.0);
+        log.record_at(index, DAY);
+        log.record_at(index, DAY);
+        assert_eq!(log.score_at(index, DAY), 2.0);
+        // Halved every three days.
+        assert_eq!(log.score_at(index, 4 * DAY), 1.0);
+        assert!(log.score_at(index, 5 * DAY) < 1.0);
+        log.forget(index);
+        assert!(log.is_empty());
+    }
+
+    #[test]
+    fn test_capacity_evicts_lowest_score() {
+        let mut log = AccessLog::default();
+        let keeper = SlabIndex::new(0);
+        log.record_at(keeper, DAY);
+        log.record_at(keeper, DAY);
+        for i in 1..ACCESS_LOG_CAPACITY {
+            log.record_at(SlabIndex::new(i), DAY + i as u64);
+        }
+        assert_eq!(log.len(), ACCESS_LOG_CAPACITY);
+        let newcomer = SlabIndex::new(ACCESS_LOG_CAPACITY);
+        log.record_at(newcomer, 2 * DAY);
+        assert_eq!(log.len(), ACCESS_LOG_CAPACITY);
+        assert_eq!(log.score_at(SlabIndex::new(1), 2 * DAY), 0.0);
+        assert!(log.score_at(keeper, 2 * DAY) > 1.0);
+        assert!(log.score_at(newcomer, 2 * DAY) > 0.0);
+    }
+}
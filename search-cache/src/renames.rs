@@ -0,0 +1,210 @@
+use crate::{IndexRemap, SearchCache, SlabIndex};
+use cardinal_sdk::{EventFlag, FsEvent};
+use hashbrown::HashSet;
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+};
+
+/// Number of renamed nodes remembered for [`SearchCache::validate`]; the oldest
+/// ones are dropped first.
+pub const RECENT_RENAMES_CAPACITY: usize = 256;
+
+/// What became of a node a search returned, see [`SearchCache::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationState {
+    /// Still in the tree.
+    Valid,
+    /// Renamed or moved within the tree, now at this path.
+    Renamed(PathBuf),
+    /// Deleted, or moved somewhere that isn't indexed.
+    Gone,
+}
+
+#[derive(Debug)]
+struct RenamedNode {
+    index: SlabIndex,
+    old_path: PathBuf,
+    new_path: PathBuf,
+}
+
+/// Bounded, oldest-first record of nodes that FSEvents reported renamed.
+#[derive(Debug, Default)]
+pub(crate) struct RecentRenames {
+    entries: VecDeque<RenamedNode>,
+    /// Indexes of `entries`, each at most once.
+    indexes: HashSet<SlabIndex>,
+}
+
+impl RecentRenames {
+    fn record(&mut self, index: SlabIndex, old_path: PathBuf, new_path: PathBuf) {
+        self.forget(index);
+        if self.entries.len() == RECENT_RENAMES_CAPACITY {
+            if let Some(oldest) = self.entries.pop_front() {
+                self.indexes.remove(&oldest.index);
+            }
+        }
+        self.indexes.insert(index);
+        self.entries.push_back(RenamedNode {
+            index,
+            old_path,
+            new_path,
+        });
+    }
+
+    /// Called when `index` is handed to a new node, so the rename recorded for
+    /// the node that had it isn't reported for the new one.
+    pub(crate) fn forget(&mut self, index: SlabIndex) {
+        if self.indexes.remove(&index) {
+            self.entries.retain(|entry| entry.index != index);
+        }
+    }
+
+    pub(crate) fn remap(&mut self, remap: &IndexRemap) {
+        self.entries
+            .retain_mut(|entry| match remap.get(entry.index) {
+                Some(index) => {
+                    entry.index = index;
+                    true
+                }
+                None => false,
+            });
+        self.indexes = self.entries.iter().map(|entry| entry.index).collect();
+    }
+
+    fn of_index(&self, index: SlabIndex) -> Option<&RenamedNode> {
+        self.entries.iter().find(|entry| entry.index == index)
+    }
+
+    /// Where the node last seen at `path` was renamed to.
+    fn renamed_from(&self, path: &Path) -> Option<&Path> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|entry| entry.old_path == path)
+            .map(|entry| entry.new_path.as_path())
+    }
+}
+
+/// A rename of `old_path` to `new_path`, found in an event batch before it's
+/// applied, with the nodes it moves.
+#[derive(Debug)]
+pub(crate) struct PendingRename {
+    old_path: PathBuf,
+    new_path: PathBuf,
+    /// The renamed node and as many of its descendants as are remembered, with
+    /// their path relative to `old_path`.
+    moved: Vec<(SlabIndex, PathBuf)>,
+}
+
+/// `ItemRenamed` events FSEvents reports back to back for one rename, the old
+/// path first.
+pub(crate) fn rename_pairs(events: &[FsEvent]) -> Vec<(PathBuf, PathBuf)> {
+    let mut renamed: Vec<&FsEvent> = events
+        .iter()
+        .filter(|event| event.flag.contains(EventFlag::ItemRenamed))
+        .collect();
+    renamed.sort_by_key(|event| event.id);
+    let mut pairs = Vec::new();
+    let mut i = 0;
+    while i + 1 < renamed.len() {
+        let (old, new) = (renamed[i], renamed[i + 1]);
+        if new.id == old.id + 1 && old.path != new.path {
+            pairs.push((old.path.clone(), new.path.clone()));
+            i += 2;
+        } else {
+            i += 1;
+        }
+    }
+    pairs
+}
+
+fn join_relative(base: &Path, relative: &Path) -> PathBuf {
+    if relative.as_os_str().is_empty() {
+        base.to_path_buf()
+    } else {
+        base.join(relative)
+    }
+}
+
+impl SearchCache {
+    /// What became of each of `indexes` since a search returned them: still
+    /// there, renamed (following renames of its ancestors and later renames
+    /// of its new path), or gone. Renames are known for the last
+    /// [`RECENT_RENAMES_CAPACITY`] nodes FSEvents reported renamed, so a node
+    /// found under its new path already may be reported `Renamed` to it too.
+    pub fn validate(&self, indexes: &[SlabIndex]) -> Vec<ValidationState> {
+        indexes
+            .iter()
+            .zip(self.node_paths(indexes))
+            .map(|(&index, path)| match self.recent_renames.of_index(index) {
+                Some(renamed) => self.follow_renames(&renamed.new_path),
+                None if path.is_some() => ValidationState::Valid,
+                None => ValidationState::Gone,
+            })
+            .collect()
+    }
+
+    fn follow_renames<'a>(&'a self, mut path: &'a Path) -> ValidationState {
+        for _ in 0..=RECENT_RENAMES_CAPACITY {
+            if self.node_index_for_raw_path(path).is_some() {
+                return ValidationState::Renamed(path.to_path_buf());
+            }
+            match self.recent_renames.renamed_from(path) {
+                Some(next) => path = next,
+                None => break,
+            }
+        }
+        ValidationState::Gone
+    }
+
+    /// The renames among `events` of paths in the tree, read before the batch
+    /// is applied.
+    pub(crate) fn pending_renames(&self, events: &[FsEvent]) -> Vec<PendingRename> {
+        rename_pairs(events)
+            .into_iter()
+            .filter_map(|(old_path, new_path)| {
+                let index = self.node_index_for_raw_path(&old_path)?;
+                let mut moved = Vec::new();
+                let mut stack = vec![index];
+                while let Some(current) = stack.pop() {
+                    if moved.len() == RECENT_RENAMES_CAPACITY {
+                        break;
+                    }
+                    let Some(relative) = self
+                        .node_path(current)
+                        .and_then(|path| Some(path.strip_prefix(&old_path).ok()?.to_path_buf()))
+                    else {
+                        continue;
+                    };
+                    moved.push((current, relative));
+                    stack.extend_from_slice(&self.file_nodes[current].children);
+                }
+                Some(PendingRename {
+                    old_path,
+                    new_path,
+                    moved,
+                })
+            })
+            .collect()
+    }
+
+    /// Remember the renames that the applied batch confirmed: the old path is
+    /// gone and the new one is indexed.
+    pub(crate) fn record_renames(&mut self, renames: Vec<PendingRename>) {
+        for rename in renames {
+            if self.node_index_for_raw_path(&rename.old_path).is_some()
+                || self.node_index_for_raw_path(&rename.new_path).is_none()
+            {
+                continue;
+            }
+            for (index, relative) in rename.moved {
+                self.recent_renames.record(
+                    index,
+                    join_relative(&rename.old_path, &relative),
+                    join_relative(&rename.new_path, &relative),
+                );
+            }
+        }
+    }
+}
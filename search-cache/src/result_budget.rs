@@ -0,0 +1,42 @@
+use crate::{SearchError, SearchResult};
+use std::cell::Cell;
+
+/// Entries held in the intermediate result lists of one query, see
+/// [`crate::SearchOptions::max_intermediate_results`]. A list is charged
+/// when the evaluator receives it and released when it's combined away or
+/// handed up, so the count follows what AND, OR and NOT hold at once.
+#[derive(Debug)]
+pub(crate) struct ResultBudget {
+    live: Cell<usize>,
+    max: Option<usize>,
+}
+
+impl ResultBudget {
+    pub(crate) fn new(max: Option<usize>) -> Self {
+        Self {
+            live: Cell::new(0),
+            max,
+        }
+    }
+
+    /// Count `entries` more, failing with [`SearchError::TooBroad`] once
+    /// past the cap.
+    pub(crate) fn charge(&self, entries: usize) -> SearchResult<()> {
+        let live = self.live.get() + entries;
+        self.live.set(live);
+        match self.max {
+            Some(max) if live > max => Err(SearchError::TooBroad { entries: live }),
+            _ => Ok(()),
+        }
+    }
+
+    pub(crate) fn release(&self, entries: usize) {
+        self.live.set(self.live.get().saturating_sub(entries));
+    }
+
+    /// Entries held right now.
+    #[cfg(test)]
+    pub(crate) fn live(&self) -> usize {
+        self.live.get()
+    }
+}
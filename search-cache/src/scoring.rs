@@ -0,0 +1,152 @@
+use crate::{SearchCache, SlabIndex, SlabNodeMetadata, query_history::unix_now};
+use fswalk::NodeFileType;
+use std::fmt;
+
+/// How a node's name relates to the query's terms, judged from the
+/// highlight terms and ignoring case like them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// The name is one of the terms.
+    Exact,
+    /// The name starts with a term.
+    Prefix,
+    /// A term is somewhere in the name.
+    Substring,
+    /// No term in the name: matched by filters alone, `initials:` or a path
+    /// segment.
+    Other,
+}
+
+/// What a [`Scorer`] gets to look at for one result. Nothing here stats the
+/// node: `metadata` is whatever was fetched already.
+pub struct ScoreContext<'a> {
+    pub index: SlabIndex,
+    pub name: &'a str,
+    /// Folders between the node and the cache root, 0 for the root itself.
+    pub depth: usize,
+    /// From the metadata if fetched, else what the walk saw.
+    pub file_type: NodeFileType,
+    pub metadata: Option<SlabNodeMetadata<'a>>,
+    pub match_kind: MatchKind,
+    /// See [`SearchCache::first_seen`].
+    pub first_seen: Option<i64>,
+    /// Decayed count of opens, see [`SearchCache::record_access`], 0 for
+    /// nodes never opened.
+    pub access_score: f64,
+    /// Unix time the search was ranked at, the same for every node.
+    pub now: u64,
+}
+
+/// Ranking function of [`crate::SearchOptions::scorer`]: results are ordered
+/// by descending score, ties in slab order. A `'static` reference keeps the
+/// options `Copy`; [`Scorer::leak`] makes one out of a closure, once per
+/// scorer rather than per search.
+#[derive(Clone, Copy)]
+pub struct Scorer(&'static (dyn Fn(&ScoreContext<'_>) -> f32 + Send + Sync));
+
+impl Scorer {
+    /// [`recent_first`] as a scorer.
+    pub const RECENT_FIRST: Self = Self(&recent_first);
+    /// [`shallow_first`] as a scorer.
+    pub const SHALLOW_FIRST: Self = Self(&shallow_first);
+
+    pub const fn new(score: &'static (dyn Fn(&ScoreContext<'_>) -> f32 + Send + Sync)) -> Self {
+        Self(score)
+    }
+
+    pub fn leak(score: impl Fn(&ScoreContext<'_>) -> f32 + Send + Sync + 'static) -> Self {
+        Self(Box::leak(Box::new(score)))
+    }
+
+    pub fn score(&self, context: &ScoreContext<'_>) -> f32 {
+        (self.0)(context)
+    }
+}
+
+impl fmt::Debug for Scorer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Scorer(..)")
+    }
+}
+
+/// Most recently modified first, by negated age so recent files keep their
+/// precision in an `f32`. Nodes without fetched metadata come last.
+pub fn recent_first(context: &ScoreContext<'_>) -> f32 {
+    match context
+        .metadata
+        .as_ref()
+        .and_then(|metadata| metadata.mtime())
+    {
+        Some(mtime) => -(context.now.saturating_sub(u64::from(mtime.get())) as f32),
+        None => f32::NEG_INFINITY,
+    }
+}
+
+/// Fewest folders deep first, nodes right under the root before the rest.
+pub fn shallow_first(context: &ScoreContext<'_>) -> f32 {
+    -(context.depth as f32)
+}
+
+impl SearchCache {
+    /// Order `nodes` by `scorer`, called once per node. `terms` are the
+    /// query's highlight terms, for [`ScoreContext::match_kind`].
+    pub(crate) fn rank_by_score(
+        &self,
+        nodes: &mut Vec<SlabIndex>,
+        scorer: Scorer,
+        terms: &[String],
+    ) {
+        let now = unix_now();
+        let access_log = self.access_log();
+        let mut scored: Vec<(f32, SlabIndex)> = nodes
+            .iter()
+            .map(|&index| {
+                let node = &self.file_nodes[index];
+                let metadata = node.metadata.get();
+                let name = node.name_and_parent.as_str();
+                let context = ScoreContext {
+                    index,
+                    name,
+                    depth: self.node_depth(index),
+                    file_type: metadata.file_type_hint(),
+                    metadata: metadata.as_ref(),
+                    match_kind: match_kind(name, terms),
+                    first_seen: self.first_seen(index),
+                    access_score: access_log.score_at(index, now),
+                    now,
+                };
+                (scorer.score(&context), index)
+            })
+            .collect();
+        scored.sort_by(|(score_a, index_a), (score_b, index_b)| {
+            score_b
+                .total_cmp(score_a)
+                .then_with(|| index_a.cmp(index_b))
+        });
+        *nodes = scored.into_iter().map(|(_, index)| index).collect();
+    }
+
+    fn node_depth(&self, index: SlabIndex) -> usize {
+        let mut depth = 0;
+        let mut current = index;
+        while let Some(parent) = self.file_nodes[current].name_and_parent.parent() {
+            depth += 1;
+            current = parent;
+        }
+        depth
+    }
+}
+
+fn match_kind(name: &str, terms: &[String]) -> MatchKind {
+    let name = name.to_lowercase();
+    let best = |kind: MatchKind, matches: fn(&str, &str) -> bool| {
+        terms
+            .iter()
+            .any(|term| matches(&name, term))
+            .then_some(kind)
+    };
+    best(MatchKind::Exact, |name, term| name == term)
+        .or_else(|| best(MatchKind::Prefix, |name, term| name.starts_with(term)))
+        .or_else(|| best(MatchKind::Substring, |name, term| name.contains(term)))
+        .unwrap_or(MatchKind::Other)
+}
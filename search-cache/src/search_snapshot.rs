@@ -0,0 +1,148 @@
+use crate::{
+    CacheSnapshot, SearchCache, SearchOptions, SearchOutcome, SearchResult, SearchResultNode,
+    SlabIndex,
+};
+use anyhow::Result;
+use search_cancel::CancellationToken;
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// The tree of a [`SearchCache`] as it was when [`SearchCache::search_snapshot`]
+/// was called, for searching and saving on another thread while the cache
+/// goes on handling events.
+///
+/// Taking one clones `Arc`s: the nodes stay shared chunk by chunk until the
+/// cache writes to one, and so do the chunks of the name index. The query
+/// history, access log, tags, metadata extras and the like are copied, they
+/// are small. Nothing flows back: searches aren't recorded in the cache's
+/// history, and cursors, renames and vanished files stay with the cache. The
+/// type, size and times of fetched metadata are the exception, they land in
+/// the nodes both sides still share.
+pub struct SearchSnapshot {
+    cache: SearchCache,
+}
+
+impl SearchCache {
+    /// A [`SearchSnapshot`] of the tree as it is now.
+    pub fn search_snapshot(&self) -> SearchSnapshot {
+        let mut cache = SearchCache::new(
+            self.file_nodes.clone(),
+            self.last_event_id,
+            self.name_index.clone(),
+            self.ignore_paths.clone(),
+            None,
+        );
+        cache.firmlinks = self.firmlinks.clone();
+        cache.query_history = Mutex::new(self.query_history().clone());
+        cache.access_log = Mutex::new(self.access_log().clone());
+        cache.tombstones = self.tombstones.clone();
+        cache.first_seen = self.first_seen.clone();
+        cache.walk_totals = self.walk_totals;
+        cache.offline_roots = self.offline_roots.clone();
+        cache.tags = self.tags.clone();
+        cache.metadata_extras = Mutex::new(self.lock_metadata_extras().clone());
+        cache.tree_version = self.tree_version;
+        SearchSnapshot { cache }
+    }
+}
+
+impl SearchSnapshot {
+    /// [`SearchCache::search_with_options`], never recorded in the history.
+    pub fn search_with_options(
+        &self,
+        line: &str,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<SearchOutcome> {
+        self.cache
+            .search_with_options(line, options.with_skip_history(true), cancellation_token)
+    }
+
+    /// [`SearchCache::search_within`].
+    pub fn search_within(
+        &self,
+        base: &[SlabIndex],
+        line: &str,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<SearchOutcome> {
+        self.cache
+            .search_within(base, line, options, cancellation_token)
+    }
+
+    /// [`SearchCache::search_empty`].
+    pub fn search_empty(&self, cancellation_token: CancellationToken) -> Option<Vec<SlabIndex>> {
+        self.cache.search_empty(cancellation_token)
+    }
+
+    /// [`SearchCache::query_files_with_options`], never recorded in the history.
+    pub fn query_files_with_options(
+        &self,
+        query: String,
+        options: SearchOptions,
+        cancellation_token: CancellationToken,
+    ) -> SearchResult<Vec<SearchResultNode>> {
+        self.cache.query_files_with_options(
+            query,
+            options.with_skip_history(true),
+            cancellation_token,
+        )
+    }
+
+    /// [`SearchCache::expand_file_nodes`].
+    pub fn expand_file_nodes(&self, nodes: &[SlabIndex]) -> Vec<SearchResultNode> {
+        self.cache.expand_file_nodes(nodes)
+    }
+
+    pub fn node_path(&self, index: SlabIndex) -> Option<PathBuf> {
+        self.cache.node_path(index)
+    }
+
+    pub fn node_paths(&self, indexes: &[SlabIndex]) -> Vec<Option<PathBuf>> {
+        self.cache.node_paths(indexes)
+    }
+
+    pub fn node_index_for_raw_path(&self, raw_path: &Path) -> Option<SlabIndex> {
+        self.cache.node_index_for_raw_path(raw_path)
+    }
+
+    pub fn get_total_files(&self) -> usize {
+        self.cache.get_total_files()
+    }
+
+    pub fn last_event_id(&self) -> u64 {
+        self.cache.last_event_id
+    }
+
+    pub fn root(&self) -> &Path {
+        self.cache.root()
+    }
+
+    /// [`SearchCache::snapshot`] of the tree as it was, to encode it off the
+    /// thread handling events.
+    pub fn encode(&self) -> Result<CacheSnapshot> {
+        self.cache.snapshot()
+    }
+
+    /// Node chunks still shared with `cache`, out of
+    /// [`Self::chunk_count`]. The others were copied by changes since.
+    pub fn shared_chunks(&self, cache: &SearchCache) -> usize {
+        self.cache.file_nodes.shared_chunks(&cache.file_nodes)
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.cache.file_nodes.chunk_count()
+    }
+
+    /// Chunks of the name index still shared with `cache`, out of
+    /// [`Self::name_chunk_count`].
+    pub fn shared_name_chunks(&self, cache: &SearchCache) -> usize {
+        self.cache.name_index.shared_chunks(&cache.name_index)
+    }
+
+    pub fn name_chunk_count(&self) -> usize {
+        self.cache.name_index.chunk_count()
+    }
+}
@@ -1,9 +1,239 @@
+use crate::{DEFAULT_NEW_WINDOW, PathDisplay, RankMode, Scorer, SizeBuckets, SlabIndex};
+use cardinal_syntax::{MAX_GROUP_DEPTH, ParseOptions, Precedence};
+use namepool::to_nfc;
 use query_segmentation::Segment;
 use regex::{Regex, RegexBuilder};
-
-#[derive(Debug, Clone, Copy, Default)]
+use std::{borrow::Cow, ops::Range, time::Duration};
+
+/// How [`crate::SearchCache::search_with_options`] evaluates a query.
+///
+/// Fields may be added in any release, so outside this crate options start
+/// from [`SearchOptions::default`] and are adjusted with the `with_*` methods:
+///
+/// ```
+/// use search_cache::{RankMode, SearchOptions};
+/// use std::time::Duration;
+///
+/// let options = SearchOptions::default()
+///     .with_case_insensitive(true)
+///     .with_rank(RankMode::RecentFirst)
+///     .with_deadline(Duration::from_millis(200));
+/// assert!(options.case_insensitive);
+/// assert_eq!(options.deadline, Some(Duration::from_millis(200)));
+/// ```
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
 pub struct SearchOptions {
+    /// Match names ignoring case, off by default.
     pub case_insensitive: bool,
+    /// Don't record the query in the query history, e.g. for programmatic queries.
+    pub skip_history: bool,
+    /// Stop evaluating once this much time has passed, see `strict_cancellation`.
+    pub deadline: Option<Duration>,
+    /// On cancellation (or deadline) return no nodes at all instead of the
+    /// partial result accumulated so far.
+    pub strict_cancellation: bool,
+    /// Compare names byte for byte. By default a precomposed query (`café`)
+    /// also finds decomposed names (`cafe\u{301}`, as macOS stores them) and
+    /// the other way around.
+    pub byte_exact: bool,
+    /// Keep nodes on unmounted volumes, see [`crate::SearchCache::offline_roots`].
+    pub include_offline: bool,
+    /// Keep hidden nodes, dotfiles and the ones flagged `UF_HIDDEN`, off by
+    /// default like in Everything. Queries with `is:hidden` keep them anyway.
+    /// Files whose metadata isn't cached yet are only left out by name, the
+    /// flag is checked once they are stat'ed, see
+    /// [`crate::SearchCache::is_hidden`].
+    pub include_hidden: bool,
+    /// Replace the `%today%`, `%home%`, ... variables of filter arguments
+    /// before parsing, on by default. Turn it off for text to search as typed.
+    pub expand_variables: bool,
+    /// How AND and OR group. Defaults to Everything's rules, where OR binds
+    /// tighter than AND.
+    pub precedence: Precedence,
+    /// Order of the returned nodes, slab order by default.
+    pub rank: RankMode,
+    /// Order the nodes by descending score instead, in place of `rank`. Only
+    /// the nodes left after the filters are scored, once each.
+    pub scorer: Option<Scorer>,
+    /// Only return nodes below this folder, like an implicit `infolder:` of
+    /// its path. Fails with [`crate::SearchError::InvalidScope`] once the
+    /// folder is gone.
+    pub scope_to: Option<SlabIndex>,
+    /// How far back `is:new` looks, a day by default.
+    pub new_window: Duration,
+    /// Longest a `regex:` term may take before the search fails with an
+    /// error instead, see [`REGEX_TIME_BUDGET`].
+    pub regex_time_budget: Duration,
+    /// Boundaries of the `size:` keywords, `tiny` up to 10 KB and so on.
+    pub size_buckets: SizeBuckets,
+    /// How many nodes without cached metadata a `size:` or date filter may
+    /// stat when it's an arm of an OR with name terms (`report | size:>1gb`),
+    /// [`FILTER_ONLY_SCAN_LIMIT`] by default. Past it the remaining ones are
+    /// skipped and [`crate::QueryNote::FilterArmTruncated`] is noted. `None`
+    /// checks every node.
+    pub filter_only_scan_limit: Option<usize>,
+    /// How many entries the intermediate result lists of AND, OR and NOT may
+    /// hold at once, [`MAX_INTERMEDIATE_RESULTS`] by default. Past it the
+    /// search fails with [`crate::SearchError::TooBroad`]. `None` doesn't
+    /// count them.
+    pub max_intermediate_results: Option<usize>,
+    /// How deep `(...)` and `<...>` groups may nest, [`MAX_GROUP_DEPTH`] by
+    /// default. Deeper queries fail with [`crate::SearchError::TooDeep`]
+    /// before anything recurses through them. `None` doesn't limit it.
+    pub max_query_depth: Option<usize>,
+    /// Fill [`crate::SearchResultNode::highlights`] with what the name terms
+    /// matched, off by default.
+    pub collect_highlights: bool,
+    /// How [`crate::SearchResultNode::display_path`] shows the results,
+    /// absolute paths by default.
+    pub path_display: PathDisplay,
+}
+
+/// Default [`SearchOptions::regex_time_budget`].
+pub const REGEX_TIME_BUDGET: Duration = Duration::from_secs(2);
+
+/// Default [`SearchOptions::filter_only_scan_limit`].
+pub const FILTER_ONLY_SCAN_LIMIT: usize = 200_000;
+
+/// Default [`SearchOptions::max_intermediate_results`], about 80 MB of slab
+/// indexes.
+pub const MAX_INTERMEDIATE_RESULTS: usize = 20_000_000;
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            case_insensitive: false,
+            skip_history: false,
+            deadline: None,
+            strict_cancellation: false,
+            byte_exact: false,
+            include_offline: false,
+            include_hidden: false,
+            expand_variables: true,
+            precedence: Precedence::default(),
+            rank: RankMode::default(),
+            scorer: None,
+            scope_to: None,
+            new_window: DEFAULT_NEW_WINDOW,
+            regex_time_budget: REGEX_TIME_BUDGET,
+            size_buckets: SizeBuckets::default(),
+            filter_only_scan_limit: Some(FILTER_ONLY_SCAN_LIMIT),
+            max_intermediate_results: Some(MAX_INTERMEDIATE_RESULTS),
+            max_query_depth: Some(MAX_GROUP_DEPTH),
+            collect_highlights: false,
+            path_display: PathDisplay::Absolute,
+        }
+    }
+}
+
+impl SearchOptions {
+    pub fn with_case_insensitive(mut self, case_insensitive: bool) -> Self {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+
+    pub fn with_skip_history(mut self, skip_history: bool) -> Self {
+        self.skip_history = skip_history;
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn with_strict_cancellation(mut self, strict_cancellation: bool) -> Self {
+        self.strict_cancellation = strict_cancellation;
+        self
+    }
+
+    pub fn with_byte_exact(mut self, byte_exact: bool) -> Self {
+        self.byte_exact = byte_exact;
+        self
+    }
+
+    pub fn with_include_offline(mut self, include_offline: bool) -> Self {
+        self.include_offline = include_offline;
+        self
+    }
+
+    pub fn with_include_hidden(mut self, include_hidden: bool) -> Self {
+        self.include_hidden = include_hidden;
+        self
+    }
+
+    pub fn with_expand_variables(mut self, expand_variables: bool) -> Self {
+        self.expand_variables = expand_variables;
+        self
+    }
+
+    pub fn with_precedence(mut self, precedence: Precedence) -> Self {
+        self.precedence = precedence;
+        self
+    }
+
+    pub fn with_rank(mut self, rank: RankMode) -> Self {
+        self.rank = rank;
+        self
+    }
+
+    pub fn with_scorer(mut self, scorer: Scorer) -> Self {
+        self.scorer = Some(scorer);
+        self
+    }
+
+    pub fn with_scope_to(mut self, folder: SlabIndex) -> Self {
+        self.scope_to = Some(folder);
+        self
+    }
+
+    pub fn with_new_window(mut self, new_window: Duration) -> Self {
+        self.new_window = new_window;
+        self
+    }
+
+    pub fn with_regex_time_budget(mut self, budget: Duration) -> Self {
+        self.regex_time_budget = budget;
+        self
+    }
+
+    pub fn with_size_buckets(mut self, size_buckets: SizeBuckets) -> Self {
+        self.size_buckets = size_buckets;
+        self
+    }
+
+    pub fn with_filter_only_scan_limit(mut self, limit: Option<usize>) -> Self {
+        self.filter_only_scan_limit = limit;
+        self
+    }
+
+    pub fn with_max_intermediate_results(mut self, max: Option<usize>) -> Self {
+        self.max_intermediate_results = max;
+        self
+    }
+
+    pub fn with_max_query_depth(mut self, max: Option<usize>) -> Self {
+        self.max_query_depth = max;
+        self
+    }
+
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            precedence: self.precedence,
+            max_depth: self.max_query_depth,
+        }
+    }
+
+    pub fn with_collect_highlights(mut self, collect_highlights: bool) -> Self {
+        self.collect_highlights = collect_highlights;
+        self
+    }
+
+    pub fn with_path_display(mut self, path_display: PathDisplay) -> Self {
+        self.path_display = path_display;
+        self
+    }
 }
 
 #[derive(Clone, Copy, Debug)]
@@ -16,11 +246,27 @@ pub(crate) enum SegmentKind {
 
 #[derive(Clone, Debug)]
 pub(crate) enum SegmentMatcher {
-    Plain { kind: SegmentKind, needle: String },
-    Regex { regex: Regex },
+    Plain {
+        kind: SegmentKind,
+        needle: String,
+    },
+    Regex {
+        regex: Regex,
+        /// Literal every name the regex matches contains, ignoring ASCII case.
+        hint: Option<String>,
+    },
 }
 
 impl SegmentMatcher {
+    /// What every name the matcher accepts contains, ignoring ASCII case, to
+    /// narrow the name pool scan with, see [`namepool::NamePool::search_nfc_hinted`].
+    pub(crate) fn pool_hint(&self) -> Option<&str> {
+        match self {
+            SegmentMatcher::Plain { needle, .. } => Some(needle),
+            SegmentMatcher::Regex { hint, .. } => hint.as_deref(),
+        }
+    }
+
     pub(crate) fn matches(&self, candidate: &str) -> bool {
         match self {
             SegmentMatcher::Plain { kind, needle } => match kind {
@@ -29,7 +275,46 @@ impl SegmentMatcher {
                 SegmentKind::Suffix => candidate.ends_with(needle),
                 SegmentKind::Exact => candidate == needle,
             },
-            SegmentMatcher::Regex { regex } => regex.is_match(candidate),
+            SegmentMatcher::Regex { regex, .. } => regex.is_match(candidate),
+        }
+    }
+
+    /// Byte ranges of `candidate` the matcher matched, empty when it doesn't
+    /// match. Empty matches are left out.
+    pub(crate) fn match_ranges(&self, candidate: &str) -> Vec<Range<usize>> {
+        match self {
+            SegmentMatcher::Plain { needle, .. } if needle.is_empty() => Vec::new(),
+            SegmentMatcher::Plain {
+                kind: SegmentKind::Substr,
+                needle,
+            } => candidate
+                .match_indices(needle.as_str())
+                .map(|(start, found)| start..start + found.len())
+                .collect(),
+            SegmentMatcher::Plain { .. } if !self.matches(candidate) => Vec::new(),
+            SegmentMatcher::Plain { kind, needle } => {
+                let range = match kind {
+                    SegmentKind::Prefix => 0..needle.len(),
+                    SegmentKind::Suffix => candidate.len() - needle.len()..candidate.len(),
+                    SegmentKind::Substr | SegmentKind::Exact => 0..candidate.len(),
+                };
+                vec![range]
+            }
+            SegmentMatcher::Regex { regex, .. } => regex
+                .find_iter(candidate)
+                .map(|found| found.range())
+                .filter(|range| !range.is_empty())
+                .collect(),
+        }
+    }
+
+    /// [`Self::matches`] for a file name, ignoring how it is composed unless
+    /// `options.byte_exact` is set.
+    pub(crate) fn matches_name(&self, name: &str, options: SearchOptions) -> bool {
+        if options.byte_exact {
+            self.matches(name)
+        } else {
+            self.matches(&to_nfc(name))
         }
     }
 }
@@ -61,34 +346,63 @@ pub(crate) fn build_segment_matchers(
         .map(|segment| {
             let kind = segment_kind(segment);
             let value = segment_value(segment);
+            let value = if options.byte_exact {
+                Cow::Borrowed(value)
+            } else {
+                to_nfc(value)
+            };
+            let value = value.as_ref();
             let is_wildcard = value.contains("*") || value.contains('?');
-            if options.case_insensitive || is_wildcard {
-                let pattern = if is_wildcard {
-                    // Wildcard pattern is /exact/ by default, so we don't need to
-                    // adjust it based on SegmentKind.
-                    wildcard_to_regex(value)
-                } else {
-                    let base = regex::escape(value);
-                    match kind {
-                        SegmentKind::Substr => base,
-                        SegmentKind::Prefix => format!("^(?:{base})"),
-                        SegmentKind::Suffix => format!("(?:{base})$"),
-                        SegmentKind::Exact => format!("^(?:{base})$"),
-                    }
-                };
-                let mut builder = RegexBuilder::new(&pattern);
+            if is_wildcard {
+                // Wildcard pattern is /exact/ by default, so we don't need to
+                // adjust it based on SegmentKind.
+                let mut builder = RegexBuilder::new(&wildcard_to_regex(value));
                 builder.case_insensitive(options.case_insensitive);
-                builder.build().map(|regex| SegmentMatcher::Regex { regex })
+                // Its longest literal run is in every name it matches.
+                let hint = value
+                    .split(['*', '?'])
+                    .max_by_key(|run| run.len())
+                    .filter(|run| !run.is_empty())
+                    .map(str::to_string);
+                builder
+                    .build()
+                    .map(|regex| SegmentMatcher::Regex { regex, hint })
             } else {
-                Ok(SegmentMatcher::Plain {
-                    kind,
-                    needle: value.to_string(),
-                })
+                literal_matcher(kind, value, options)
             }
         })
         .collect()
 }
 
+/// Matcher for `value` taken literally, `*` and `?` included. `value` should
+/// already be NFC unless `options.byte_exact` is set.
+pub(crate) fn literal_matcher(
+    kind: SegmentKind,
+    value: &str,
+    options: SearchOptions,
+) -> Result<SegmentMatcher, regex::Error> {
+    if !options.case_insensitive {
+        return Ok(SegmentMatcher::Plain {
+            kind,
+            needle: value.to_string(),
+        });
+    }
+    let base = regex::escape(value);
+    let pattern = match kind {
+        SegmentKind::Substr => base,
+        SegmentKind::Prefix => format!("^(?:{base})"),
+        SegmentKind::Suffix => format!("(?:{base})$"),
+        SegmentKind::Exact => format!("^(?:{base})$"),
+    };
+    RegexBuilder::new(&pattern)
+        .case_insensitive(true)
+        .build()
+        .map(|regex| SegmentMatcher::Regex {
+            regex,
+            hint: Some(value.to_string()),
+        })
+}
+
 fn segment_kind(segment: &Segment<'_>) -> SegmentKind {
     match segment {
         Segment::Substr(_) => SegmentKind::Substr,
@@ -196,6 +510,7 @@ mod tests {
         ];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).expect("ok");
         assert_eq!(matchers.len(), 4);
@@ -223,13 +538,14 @@ mod tests {
         ];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).expect("ok");
         assert_eq!(matchers.len(), 4);
         let patterns: Vec<_> = matchers
             .iter()
             .map(|m| match m {
-                SegmentMatcher::Regex { regex } => regex.as_str().to_string(),
+                SegmentMatcher::Regex { regex, .. } => regex.as_str().to_string(),
                 _ => panic!("Expected Regex matcher"),
             })
             .collect();
@@ -246,11 +562,12 @@ mod tests {
         let segments = [Segment::Exact("foo*bar?baz")];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).expect("ok");
         assert_eq!(matchers.len(), 1);
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert_eq!(regex.as_str(), "^foo.*bar.baz$");
             }
             _ => panic!("Expected regex for wildcard segment"),
@@ -262,10 +579,11 @@ mod tests {
         let segments = [Segment::Substr("A*B")];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).expect("ok");
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(!regex.is_match("aXXb"));
                 assert!(regex.is_match("AXXB"));
             }
@@ -278,10 +596,11 @@ mod tests {
         let segments = [Segment::Substr("A*B")];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).expect("ok");
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("aXXb"));
                 assert!(regex.is_match("AXXB"));
             }
@@ -338,10 +657,11 @@ mod tests {
         let segments = [Segment::Substr("abc")];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let m = build_segment_matchers(&segments, opts).unwrap().remove(0);
         match m {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("zzzAbCzzz"));
             }
             _ => panic!("Expected regex matcher"),
@@ -353,10 +673,11 @@ mod tests {
         let segments = [Segment::Prefix("abc")];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let m = build_segment_matchers(&segments, opts).unwrap().remove(0);
         match m {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("AbCzzz"));
                 assert!(!regex.is_match("zzzabc"));
             }
@@ -369,10 +690,11 @@ mod tests {
         let segments = [Segment::Suffix("abc")];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let m = build_segment_matchers(&segments, opts).unwrap().remove(0);
         match m {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("zzzAbC"));
                 assert!(!regex.is_match("AbCzzz"));
             }
@@ -385,10 +707,11 @@ mod tests {
         let segments = [Segment::Exact("abc")];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let m = build_segment_matchers(&segments, opts).unwrap().remove(0);
         match m {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("AbC"));
                 assert!(!regex.is_match("xabc"));
             }
@@ -408,6 +731,7 @@ mod tests {
         ];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         assert_eq!(matchers.len(), 4);
@@ -427,6 +751,7 @@ mod tests {
         ];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         for m in matchers {
@@ -441,10 +766,11 @@ mod tests {
         let segments = [Segment::Exact("a+b*(c?)")];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 // '?' is treated as wildcard -> '.'
                 assert_eq!(regex.as_str(), "^a\\+b.*\\(c.\\)$");
                 assert!(regex.is_match("a+bZZZ(c?)"));
@@ -461,6 +787,7 @@ mod tests {
         let segments = [Segment::Substr("Café")];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         match &matchers[0] {
@@ -476,10 +803,11 @@ mod tests {
         let segments = [Segment::Exact("Café")];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("café"));
                 // Basic ASCII case fold works; regex crate may not fold é to É on all platforms, so we only check lowercase.
             }
@@ -498,19 +826,20 @@ mod tests {
         ];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         assert_eq!(matchers.len(), 3);
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => assert!(regex.as_str().starts_with("^(?:")),
+            SegmentMatcher::Regex { regex, .. } => assert!(regex.as_str().starts_with("^(?:")),
             _ => panic!("regex expected"),
         }
         match &matchers[1] {
-            SegmentMatcher::Regex { regex } => assert!(regex.as_str().ends_with(")$")),
+            SegmentMatcher::Regex { regex, .. } => assert!(regex.as_str().ends_with(")$")),
             _ => panic!("regex expected"),
         }
         match &matchers[2] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.as_str().starts_with("^(?:"));
                 assert!(regex.as_str().ends_with(")$"));
             }
@@ -526,11 +855,12 @@ mod tests {
         let segments = [Segment::Exact(&long)];
         let opts = SearchOptions {
             case_insensitive: true,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         assert_eq!(matchers.len(), 1);
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.as_str().starts_with("^(?:"));
             }
             _ => panic!("Expected regex"),
@@ -544,10 +874,11 @@ mod tests {
         let segments = [Segment::Exact("a*b*c?d")];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         match &matchers[0] {
-            SegmentMatcher::Regex { regex } => {
+            SegmentMatcher::Regex { regex, .. } => {
                 assert!(regex.is_match("aZZbYYcXd"));
                 assert!(!regex.is_match("abYcXdX"));
             }
@@ -562,6 +893,7 @@ mod tests {
         let segments = [Segment::Substr("mid")];
         let opts = SearchOptions {
             case_insensitive: false,
+            ..Default::default()
         };
         let matchers = build_segment_matchers(&segments, opts).unwrap();
         match &matchers[0] {
@@ -0,0 +1,385 @@
+//! Set operations on candidate lists.
+//!
+//! Candidate lists hold every node at most once, in the order they're shown
+//! (name order, from the name index), so the left-hand side keeps its order.
+//! When both sides happen to be sorted by slab index they're merged linearly,
+//! galloping through the larger one once it's [`GALLOP_RATIO`] times the
+//! smaller. Otherwise the other side is turned into a [`Bitmap`], which unlike
+//! sorting it or hashing it costs a single pass.
+
+use crate::{SearchError, SearchResult, SlabIndex};
+use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
+
+/// Size ratio from which merging gallops through the larger side instead of
+/// stepping through it.
+const GALLOP_RATIO: usize = 64;
+
+/// Keep the nodes of `values` that are also in `rhs`.
+pub(crate) fn intersect_in_place(
+    values: &mut Vec<SlabIndex>,
+    rhs: &[SlabIndex],
+    token: CancellationToken,
+) -> SearchResult<()> {
+    if values.is_empty() {
+        return Ok(());
+    }
+    *values = if values.is_sorted() && rhs.is_sorted() {
+        intersect_sorted(values, rhs, token)?
+    } else {
+        retain_by_bitmap(values, rhs, true, token)?
+    };
+    Ok(())
+}
+
+/// Drop the nodes of `values` that are in `rhs`.
+pub(crate) fn difference_in_place(
+    values: &mut Vec<SlabIndex>,
+    rhs: &[SlabIndex],
+    token: CancellationToken,
+) -> SearchResult<()> {
+    if values.is_empty() || rhs.is_empty() {
+        return Ok(());
+    }
+    *values = if values.is_sorted() && rhs.is_sorted() {
+        difference_sorted(values, rhs, token)?
+    } else {
+        retain_by_bitmap(values, rhs, false, token)?
+    };
+    Ok(())
+}
+
+/// Append the nodes of `rhs` that aren't in `values` yet, in `rhs` order.
+pub(crate) fn union_in_place(
+    values: &mut Vec<SlabIndex>,
+    rhs: &[SlabIndex],
+    token: CancellationToken,
+) -> SearchResult<()> {
+    if rhs.is_empty() {
+        return Ok(());
+    }
+    let fresh = if values.is_sorted() && rhs.is_sorted() {
+        difference_sorted(rhs, values, token)?
+    } else {
+        retain_by_bitmap(rhs, values, false, token)?
+    };
+    values.extend(fresh);
+    Ok(())
+}
+
+fn check(i: usize, token: CancellationToken) -> SearchResult<()> {
+    if i % CANCEL_CHECK_INTERVAL == 0 && token.is_cancelled() {
+        Err(SearchError::Cancelled)
+    } else {
+        Ok(())
+    }
+}
+
+/// Position of the first element of `sorted` not less than `target`, probing
+/// 1, 2, 4, ... elements ahead before binary searching the last step.
+fn gallop(sorted: &[SlabIndex], target: SlabIndex) -> usize {
+    let mut end = 1;
+    while end < sorted.len() && sorted[end - 1] < target {
+        end *= 2;
+    }
+    let start = end / 2;
+    let end = end.min(sorted.len());
+    start + sorted[start..end].partition_point(|&x| x < target)
+}
+
+/// Nodes of `values` whose presence in `other` is `keep_members`.
+fn retain_by_bitmap(
+    values: &[SlabIndex],
+    other: &[SlabIndex],
+    keep_members: bool,
+    token: CancellationToken,
+) -> SearchResult<Vec<SlabIndex>> {
+    let bitmap = Bitmap::new(other, token)?;
+    let mut kept = Vec::with_capacity(values.len());
+    for (i, &index) in values.iter().enumerate() {
+        check(i, token)?;
+        if bitmap.contains(index) == keep_members {
+            kept.push(index);
+        }
+    }
+    Ok(kept)
+}
+
+/// Membership of slab indexes, one bit per index up to the largest one.
+/// Slab indexes are dense, so this stays within a bit per node of the cache.
+struct Bitmap(Vec<u64>);
+
+impl Bitmap {
+    fn new(indexes: &[SlabIndex], token: CancellationToken) -> SearchResult<Self> {
+        let words = indexes
+            .iter()
+            .map(|index| index.get() / 64 + 1)
+            .max()
+            .unwrap_or_default();
+        let mut bits = vec![0u64; words];
+        for (i, index) in indexes.iter().enumerate() {
+            check(i, token)?;
+            bits[index.get() / 64] |= 1 << (index.get() % 64);
+        }
+        Ok(Self(bits))
+    }
+
+    fn contains(&self, index: SlabIndex) -> bool {
+        self.0
+            .get(index.get() / 64)
+            .is_some_and(|word| word & (1 << (index.get() % 64)) != 0)
+    }
+}
+
+fn intersect_sorted(
+    lhs: &[SlabIndex],
+    rhs: &[SlabIndex],
+    token: CancellationToken,
+) -> SearchResult<Vec<SlabIndex>> {
+    let (small, mut large) = if lhs.len() <= rhs.len() {
+        (lhs, rhs)
+    } else {
+        (rhs, lhs)
+    };
+    let mut result = Vec::with_capacity(small.len());
+    if large.len() / GALLOP_RATIO >= small.len() {
+        for (i, &index) in small.iter().enumerate() {
+            check(i, token)?;
+            large = &large[gallop(large, index)..];
+            match large.first() {
+                None => break,
+                Some(&found) if found == index => result.push(index),
+                Some(_) => {}
+            }
+        }
+        return Ok(result);
+    }
+    let (mut i, mut j) = (0, 0);
+    for step in 0.. {
+        if i == lhs.len() || j == rhs.len() {
+            break;
+        }
+        check(step, token)?;
+        match lhs[i].cmp(&rhs[j]) {
+            std::cmp::Ordering::Less => i += 1,
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                result.push(lhs[i]);
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+fn difference_sorted(
+    lhs: &[SlabIndex],
+    rhs: &[SlabIndex],
+    token: CancellationToken,
+) -> SearchResult<Vec<SlabIndex>> {
+    let mut result = Vec::with_capacity(lhs.len());
+    if rhs.len() / GALLOP_RATIO >= lhs.len() {
+        // Few nodes to keep or drop: gallop through the removed ones.
+        let mut rhs = rhs;
+        for (i, &index) in lhs.iter().enumerate() {
+            check(i, token)?;
+            rhs = &rhs[gallop(rhs, index)..];
+            if rhs.first() != Some(&index) {
+                result.push(index);
+            }
+        }
+        return Ok(result);
+    }
+    if lhs.len() / GALLOP_RATIO >= rhs.len() {
+        // Few nodes to drop: copy the runs between them.
+        let mut lhs = lhs;
+        for (i, &removed) in rhs.iter().enumerate() {
+            check(i, token)?;
+            let run = gallop(lhs, removed);
+            result.extend_from_slice(&lhs[..run]);
+            lhs = &lhs[run..];
+            if lhs.first() == Some(&removed) {
+                lhs = &lhs[1..];
+            }
+        }
+        result.extend_from_slice(lhs);
+        return Ok(result);
+    }
+    let (mut i, mut j) = (0, 0);
+    for step in 0.. {
+        if i == lhs.len() {
+            break;
+        }
+        check(step, token)?;
+        if j == rhs.len() {
+            result.extend_from_slice(&lhs[i..]);
+            break;
+        }
+        match lhs[i].cmp(&rhs[j]) {
+            std::cmp::Ordering::Less => {
+                result.push(lhs[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => j += 1,
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::BTreeSet,
+        time::{Duration, Instant},
+    };
+
+    /// xorshift64*, enough to shuffle test inputs reproducibly.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+
+        fn below(&mut self, bound: usize) -> usize {
+            (self.next() % bound as u64) as usize
+        }
+
+        /// `len` distinct indexes below `universe`, sorted or shuffled.
+        fn candidates(&mut self, len: usize, universe: usize, shuffled: bool) -> Vec<SlabIndex> {
+            let mut set = BTreeSet::new();
+            while set.len() < len.min(universe) {
+                set.insert(self.below(universe));
+            }
+            let mut values: Vec<_> = set.into_iter().map(SlabIndex::new).collect();
+            if shuffled {
+                for i in (1..values.len()).rev() {
+                    values.swap(i, self.below(i + 1));
+                }
+            }
+            values
+        }
+    }
+
+    fn reference(
+        values: &[SlabIndex],
+        rhs: &[SlabIndex],
+    ) -> (Vec<SlabIndex>, Vec<SlabIndex>, Vec<SlabIndex>) {
+        let lhs_set: BTreeSet<_> = values.iter().copied().collect();
+        let rhs_set: BTreeSet<_> = rhs.iter().copied().collect();
+        let intersection = values
+            .iter()
+            .copied()
+            .filter(|index| rhs_set.contains(index))
+            .collect();
+        let difference = values
+            .iter()
+            .copied()
+            .filter(|index| !rhs_set.contains(index))
+            .collect();
+        let mut union = values.to_vec();
+        union.extend(rhs.iter().copied().filter(|index| !lhs_set.contains(index)));
+        (intersection, difference, union)
+    }
+
+    #[test]
+    fn matches_btreeset_reference_on_random_inputs() {
+        let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+        let sizes = [0, 1, 2, 7, 64, 300, 5_000];
+        for &lhs_len in &sizes {
+            for &rhs_len in &sizes {
+                for round in 0..8 {
+                    let universe = [16, 1_000, 100_000][round % 3];
+                    let values = rng.candidates(lhs_len, universe, round & 4 != 0);
+                    let rhs = rng.candidates(rhs_len, universe, round & 1 != 0);
+                    let (intersection, difference, union) = reference(&values, &rhs);
+                    let token = CancellationToken::noop();
+
+                    let mut actual = values.clone();
+                    intersect_in_place(&mut actual, &rhs, token).unwrap();
+                    assert_eq!(actual, intersection, "{lhs_len} & {rhs_len} #{round}");
+                    let mut actual = values.clone();
+                    difference_in_place(&mut actual, &rhs, token).unwrap();
+                    assert_eq!(actual, difference, "{lhs_len} - {rhs_len} #{round}");
+                    let mut actual = values.clone();
+                    union_in_place(&mut actual, &rhs, token).unwrap();
+                    assert_eq!(actual, union, "{lhs_len} | {rhs_len} #{round}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gallop_finds_the_lower_bound() {
+        let sorted: Vec<_> = [1, 3, 3, 8, 20, 21, 40]
+            .into_iter()
+            .map(SlabIndex::new)
+            .collect();
+        for target in 0..45 {
+            let target = SlabIndex::new(target);
+            assert_eq!(
+                gallop(&sorted, target),
+                sorted.partition_point(|&x| x < target),
+                "{target:?}"
+            );
+        }
+        assert_eq!(gallop(&[], SlabIndex::new(1)), 0);
+    }
+
+    #[test]
+    fn cancelled_operations_leave_values_untouched() {
+        let token = CancellationToken::noop().with_deadline(Instant::now());
+        let values: Vec<_> = (0..10).map(SlabIndex::new).collect();
+        let rhs: Vec<_> = (5..15).map(SlabIndex::new).collect();
+        let mut actual = values.clone();
+        assert!(intersect_in_place(&mut actual, &rhs, token).is_err());
+        assert!(difference_in_place(&mut actual, &rhs, token).is_err());
+        assert!(union_in_place(&mut actual, &rhs, token).is_err());
+        assert_eq!(actual, values);
+    }
+
+    /// What the operations were before: hash set lookups.
+    fn intersect_with_hash_set(values: &mut Vec<SlabIndex>, rhs: &[SlabIndex]) {
+        let rhs: hashbrown::HashSet<_> = rhs.iter().copied().collect();
+        values.retain(|index| rhs.contains(index));
+    }
+
+    fn time(mut f: impl FnMut()) -> Duration {
+        let started = Instant::now();
+        for _ in 0..10 {
+            f();
+        }
+        started.elapsed() / 10
+    }
+
+    #[test]
+    #[ignore]
+    fn bench_skewed_intersections() {
+        let mut rng = Rng(42);
+        let universe = 4_000_000;
+        let token = CancellationToken::noop();
+        for (lhs_len, rhs_len) in [
+            (2_000_000, 2_000_000),
+            (2_000_000, 1_000),
+            (1_000, 2_000_000),
+        ] {
+            for shuffled in [false, true] {
+                let values = rng.candidates(lhs_len, universe, shuffled);
+                let rhs = rng.candidates(rhs_len, universe, shuffled);
+                let hashed = time(|| intersect_with_hash_set(&mut values.clone(), &rhs));
+                let merged = time(|| intersect_in_place(&mut values.clone(), &rhs, token).unwrap());
+                println!(
+                    "{lhs_len:>9} & {rhs_len:>9} (shuffled: {shuffled:>5}): hash set {hashed:?}, now {merged:?}"
+                );
+            }
+        }
+    }
+}
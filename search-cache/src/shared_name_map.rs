@@ -0,0 +1,145 @@
+use crate::SortedSlabIndices;
+use std::{collections::BTreeMap, sync::Arc};
+
+type Chunk = BTreeMap<&'static str, SortedSlabIndices>;
+
+/// The map of a [`crate::NameIndex`] with the `snapshots` feature, a
+/// `BTreeMap` split by the first two bytes of the names into chunks behind
+/// `Arc`s. Cloning one clones the `Arc`s, and a change to a chunk still
+/// shared with a clone copies that chunk alone, the names sharing its
+/// prefix, rather than the whole index.
+///
+/// Byte order of the prefixes is the order of the names, so walking the
+/// chunks in order walks the names in order.
+#[derive(Clone, Default)]
+pub(crate) struct SharedNameMap {
+    chunks: BTreeMap<u16, Arc<Chunk>>,
+    len: usize,
+}
+
+/// Key of the chunk holding `name`, missing bytes counting as zero.
+fn chunk_key(name: &str) -> u16 {
+    let bytes = name.as_bytes();
+    let byte = |i| u16::from(bytes.get(i).copied().unwrap_or(0));
+    (byte(0) << 8) | byte(1)
+}
+
+impl SharedNameMap {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (&&'static str, &SortedSlabIndices)> {
+        self.chunks.values().flat_map(|chunk| chunk.iter())
+    }
+
+    pub(crate) fn values(&self) -> impl Iterator<Item = &SortedSlabIndices> {
+        self.chunks.values().flat_map(|chunk| chunk.values())
+    }
+
+    /// Copies every chunk still shared.
+    pub(crate) fn values_mut(&mut self) -> impl Iterator<Item = &mut SortedSlabIndices> {
+        self.chunks
+            .values_mut()
+            .flat_map(|chunk| Arc::make_mut(chunk).values_mut())
+    }
+
+    pub(crate) fn get(&self, name: &str) -> Option<&SortedSlabIndices> {
+        self.chunks.get(&chunk_key(name))?.get(name)
+    }
+
+    /// Copies the chunk of `name` if it's shared, only when `name` is in it.
+    pub(crate) fn get_mut(&mut self, name: &str) -> Option<&mut SortedSlabIndices> {
+        let chunk = self.chunks.get_mut(&chunk_key(name))?;
+        if !chunk.contains_key(name) {
+            return None;
+        }
+        Arc::make_mut(chunk).get_mut(name)
+    }
+
+    pub(crate) fn insert(
+        &mut self,
+        name: &'static str,
+        indices: SortedSlabIndices,
+    ) -> Option<SortedSlabIndices> {
+        let chunk = self.chunks.entry(chunk_key(name)).or_default();
+        let replaced = Arc::make_mut(chunk).insert(name, indices);
+        if replaced.is_none() {
+            self.len += 1;
+        }
+        replaced
+    }
+
+    pub(crate) fn remove(&mut self, name: &str) -> Option<SortedSlabIndices> {
+        let key = chunk_key(name);
+        let chunk = self.chunks.get_mut(&key)?;
+        if !chunk.contains_key(name) {
+            return None;
+        }
+        let chunk = Arc::make_mut(chunk);
+        let removed = chunk.remove(name);
+        if chunk.is_empty() {
+            self.chunks.remove(&key);
+        }
+        self.len -= 1;
+        removed
+    }
+
+    /// Copies every chunk still shared.
+    pub(crate) fn retain(
+        &mut self,
+        mut keep: impl FnMut(&&'static str, &mut SortedSlabIndices) -> bool,
+    ) {
+        for chunk in self.chunks.values_mut() {
+            Arc::make_mut(chunk).retain(&mut keep);
+        }
+        self.chunks.retain(|_, chunk| !chunk.is_empty());
+        self.len = self.chunks.values().map(|chunk| chunk.len()).sum();
+    }
+
+    pub(crate) fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Chunks `self` and `other` still share, the ones neither changed since
+    /// one was cloned from the other.
+    pub(crate) fn shared_chunks(&self, other: &Self) -> usize {
+        self.chunks
+            .iter()
+            .filter(|(key, chunk)| {
+                other
+                    .chunks
+                    .get(key)
+                    .is_some_and(|theirs| Arc::ptr_eq(chunk, theirs))
+            })
+            .count()
+    }
+}
+
+impl FromIterator<(&'static str, SortedSlabIndices)> for SharedNameMap {
+    fn from_iter<I: IntoIterator<Item = (&'static str, SortedSlabIndices)>>(iter: I) -> Self {
+        let mut map = Self::default();
+        for (name, indices) in iter {
+            map.insert(name, indices);
+        }
+        map
+    }
+}
+
+impl IntoIterator for SharedNameMap {
+    type Item = (&'static str, SortedSlabIndices);
+    type IntoIter = std::iter::FlatMap<
+        std::collections::btree_map::IntoValues<u16, Arc<Chunk>>,
+        Chunk,
+        fn(Arc<Chunk>) -> Chunk,
+    >;
+
+    /// Takes the chunks still shared by copying them.
+    fn into_iter(self) -> Self::IntoIter {
+        self.chunks.into_values().flat_map(Arc::unwrap_or_clone)
+    }
+}
@@ -0,0 +1,359 @@
+use crate::SlabIndex;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{MapAccess, Visitor},
+    ser::SerializeMap,
+};
+use std::{fmt, io, marker::PhantomData, mem, sync::Arc};
+
+/// Slots per chunk of a [`SharedSlab`].
+pub const CHUNK_SLOTS: usize = 1024;
+
+#[derive(Clone)]
+enum Entry<T> {
+    /// Free slot, holding the next free one.
+    Vacant(usize),
+    Occupied(T),
+}
+
+type Chunk<T> = Arc<Vec<Entry<T>>>;
+
+/// In-memory slab with the API of [`crate::ThinSlab`], its slots split in
+/// chunks of [`CHUNK_SLOTS`] behind `Arc`s. Cloning one clones the `Arc`s,
+/// and a write to a chunk still shared with a clone copies that chunk first,
+/// so a [`crate::SearchSnapshot`] costs one chunk per chunk changed since.
+///
+/// Indexes are handed out like the mmap slab does, freed slots first, last
+/// freed first, so a tree gets the same indexes whichever slab holds it.
+pub struct SharedSlab<T> {
+    chunks: Vec<Chunk<T>>,
+    /// Occupied slots.
+    len: usize,
+    /// Slots in use or on the freelist.
+    slots: usize,
+    /// Head of the freelist, `slots` when it's empty.
+    next: usize,
+}
+
+impl<T> Clone for SharedSlab<T> {
+    fn clone(&self) -> Self {
+        Self {
+            chunks: self.chunks.clone(),
+            len: self.len,
+            slots: self.slots,
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Default for SharedSlab<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> SharedSlab<T> {
+    pub fn new() -> Self {
+        Self {
+            chunks: Vec::new(),
+            len: 0,
+            slots: 0,
+            next: 0,
+        }
+    }
+
+    /// Never fails, for the signature of [`crate::ThinSlab::try_new`].
+    pub fn try_new() -> io::Result<Self> {
+        Ok(Self::new())
+    }
+
+    pub fn get(&self, index: SlabIndex) -> Option<&T> {
+        match self.entry(index.get())? {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Slots in use or on the freelist, like [`crate::ThinSlab::slots`].
+    pub fn slots(&self) -> usize {
+        self.slots
+    }
+
+    /// Bytes of the chunks, shared ones included.
+    pub fn mapped_bytes(&self) -> usize {
+        self.chunks.len() * CHUNK_SLOTS * mem::size_of::<Entry<T>>()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Chunks `self` and `other` still share, the ones neither wrote to
+    /// since one was cloned from the other.
+    pub fn shared_chunks(&self, other: &Self) -> usize {
+        self.chunks
+            .iter()
+            .zip(&other.chunks)
+            .filter(|(a, b)| Arc::ptr_eq(a, b))
+            .count()
+    }
+
+    pub fn iter(&self) -> SharedSlabIter<'_, T> {
+        SharedSlabIter {
+            slab: self,
+            index: 0,
+        }
+    }
+
+    fn entry(&self, index: usize) -> Option<&Entry<T>> {
+        self.chunks
+            .get(index / CHUNK_SLOTS)?
+            .get(index % CHUNK_SLOTS)
+    }
+}
+
+impl<T: Clone> SharedSlab<T> {
+    pub fn insert(&mut self, value: T) -> SlabIndex {
+        let index = self.next;
+        if index == self.slots {
+            if index % CHUNK_SLOTS == 0 {
+                self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_SLOTS)));
+            }
+            self.chunk_mut(index).push(Entry::Occupied(value));
+            self.slots += 1;
+            self.next = self.slots;
+        } else {
+            let entry = self.entry_mut(index).expect("free slots exist");
+            let Entry::Vacant(next) = mem::replace(entry, Entry::Occupied(value)) else {
+                unreachable!("slot on the freelist is occupied");
+            };
+            self.next = next;
+        }
+        self.len += 1;
+        SlabIndex::new(index)
+    }
+
+    /// Never fails, for the signature of [`crate::ThinSlab::try_insert`].
+    pub fn try_insert(&mut self, value: T) -> io::Result<SlabIndex> {
+        Ok(self.insert(value))
+    }
+
+    pub fn get_mut(&mut self, index: SlabIndex) -> Option<&mut T> {
+        if !matches!(self.entry(index.get())?, Entry::Occupied(_)) {
+            return None;
+        }
+        match self.entry_mut(index.get())? {
+            Entry::Occupied(value) => Some(value),
+            Entry::Vacant(_) => None,
+        }
+    }
+
+    pub fn try_remove(&mut self, index: SlabIndex) -> Option<T> {
+        if !matches!(self.entry(index.get())?, Entry::Occupied(_)) {
+            return None;
+        }
+        let next = self.next;
+        let entry = self.entry_mut(index.get())?;
+        let Entry::Occupied(value) = mem::replace(entry, Entry::Vacant(next)) else {
+            unreachable!("checked above");
+        };
+        self.len -= 1;
+        self.next = index.get();
+        Some(value)
+    }
+
+    /// The chunk of `index`, copied first if a clone still shares it.
+    fn chunk_mut(&mut self, index: usize) -> &mut Vec<Entry<T>> {
+        Arc::make_mut(&mut self.chunks[index / CHUNK_SLOTS])
+    }
+
+    fn entry_mut(&mut self, index: usize) -> Option<&mut Entry<T>> {
+        if index >= self.slots {
+            return None;
+        }
+        self.chunk_mut(index).get_mut(index % CHUNK_SLOTS)
+    }
+
+    /// Put `value` at `index`, growing the slab with free slots up to it,
+    /// for deserializing. [`Self::rebuild_freelist`] once all are in.
+    fn place(&mut self, index: usize, value: T) {
+        while self.slots <= index {
+            if self.slots % CHUNK_SLOTS == 0 {
+                self.chunks.push(Arc::new(Vec::with_capacity(CHUNK_SLOTS)));
+            }
+            let slots = self.slots;
+            self.chunk_mut(slots).push(Entry::Vacant(0));
+            self.slots += 1;
+        }
+        let entry = self.entry_mut(index).expect("grown above");
+        if let Entry::Occupied(_) = mem::replace(entry, Entry::Occupied(value)) {
+            return;
+        }
+        self.len += 1;
+    }
+
+    /// Chain the free slots lowest first, as the mmap slab does on load.
+    fn rebuild_freelist(&mut self) {
+        let mut next = self.slots;
+        for index in (0..self.slots).rev() {
+            let entry = self.entry_mut(index).expect("slots are initialized");
+            if let Entry::Vacant(free) = entry {
+                *free = next;
+                next = index;
+            }
+        }
+        self.next = next;
+    }
+}
+
+impl<T> std::ops::Index<SlabIndex> for SharedSlab<T> {
+    type Output = T;
+
+    fn index(&self, index: SlabIndex) -> &Self::Output {
+        self.get(index).expect("invalid slab index")
+    }
+}
+
+impl<T: Clone> std::ops::IndexMut<SlabIndex> for SharedSlab<T> {
+    fn index_mut(&mut self, index: SlabIndex) -> &mut Self::Output {
+        self.get_mut(index).expect("invalid slab index")
+    }
+}
+
+impl<T> fmt::Debug for SharedSlab<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedSlab")
+            .field("len", &self.len)
+            .field("slots", &self.slots)
+            .field("chunks", &self.chunks.len())
+            .finish()
+    }
+}
+
+pub struct SharedSlabIter<'a, T> {
+    slab: &'a SharedSlab<T>,
+    index: usize,
+}
+
+impl<'a, T> Iterator for SharedSlabIter<'a, T> {
+    type Item = (SlabIndex, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.index < self.slab.slots {
+            let index = self.index;
+            self.index += 1;
+            if let Some(Entry::Occupied(value)) = self.slab.entry(index) {
+                return Some((SlabIndex::new(index), value));
+            }
+        }
+        None
+    }
+}
+
+/// The mmap slab's encoding, a map from index to value, so cache files don't
+/// depend on the feature.
+impl<T: Serialize> Serialize for SharedSlab<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.len))?;
+        for (index, value) in self.iter() {
+            map.serialize_entry(&index.get(), value)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de, T: Deserialize<'de> + Clone> Deserialize<'de> for SharedSlab<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SharedSlabVisitor<T>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de> + Clone> Visitor<'de> for SharedSlabVisitor<T> {
+            type Value = SharedSlab<T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a map")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut slab = SharedSlab::new();
+                while let Some((index, value)) = map.next_entry::<usize, T>()? {
+                    slab.place(index, value);
+                }
+                slab.rebuild_freelist();
+                Ok(slab)
+            }
+        }
+
+        deserializer.deserialize_map(SharedSlabVisitor(PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ThinSlab;
+
+    #[test]
+    fn shared_slab_reuses_slots_like_thin_slab() {
+        let mut shared = SharedSlab::new();
+        let mut thin = ThinSlab::new();
+        for value in 0..2 * CHUNK_SLOTS as u32 + 5 {
+            assert_eq!(shared.insert(value), thin.insert(value));
+        }
+        for index in [3, CHUNK_SLOTS + 1, 7, 2 * CHUNK_SLOTS] {
+            let index = SlabIndex::new(index);
+            assert_eq!(shared.try_remove(index), thin.try_remove(index));
+        }
+        assert_eq!(shared.try_remove(SlabIndex::new(3)), None);
+        for value in 0..6 {
+            assert_eq!(shared.insert(value), thin.insert(value));
+        }
+        assert_eq!(shared.len(), thin.len());
+        assert_eq!(shared.slots(), thin.slots());
+        assert!(shared.iter().eq(thin.iter()));
+    }
+
+    #[test]
+    fn shared_slab_round_trips_with_holes() {
+        let mut slab = SharedSlab::new();
+        for value in 0..10u32 {
+            slab.insert(value);
+        }
+        slab.try_remove(SlabIndex::new(6));
+        slab.try_remove(SlabIndex::new(2));
+        let bytes = postcard::to_allocvec(&slab).unwrap();
+        let thin: ThinSlab<u32> = postcard::from_bytes(&bytes).unwrap();
+        let mut loaded: SharedSlab<u32> = postcard::from_bytes(&bytes).unwrap();
+        assert!(loaded.iter().eq(thin.iter()));
+        // Holes are refilled lowest first after a load.
+        assert_eq!(loaded.insert(20), SlabIndex::new(2));
+        assert_eq!(loaded.insert(21), SlabIndex::new(6));
+        assert_eq!(loaded.insert(22), SlabIndex::new(10));
+    }
+
+    #[test]
+    fn shared_slab_clones_are_independent() {
+        let mut slab = SharedSlab::new();
+        for value in 0..CHUNK_SLOTS as u32 * 3 {
+            slab.insert(value);
+        }
+        let clone = slab.clone();
+        assert_eq!(clone.shared_chunks(&slab), 3);
+        slab[SlabIndex::new(CHUNK_SLOTS + 1)] = 100;
+        slab.try_remove(SlabIndex::new(0));
+        assert_eq!(clone.shared_chunks(&slab), 1);
+        assert_eq!(
+            clone[SlabIndex::new(CHUNK_SLOTS + 1)],
+            CHUNK_SLOTS as u32 + 1
+        );
+        assert_eq!(clone.get(SlabIndex::new(0)), Some(&0));
+        assert_eq!(clone.len(), 3 * CHUNK_SLOTS);
+    }
+}
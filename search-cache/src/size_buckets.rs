@@ -0,0 +1,111 @@
+use std::fmt;
+
+const KB: u64 = 1024;
+const MB: u64 = 1024 * 1024;
+
+/// Keywords of the buckets, smallest first. `gigantic` (and its `giant`
+/// alias) is everything above the last boundary.
+const KEYWORDS: [&str; 6] = ["tiny", "small", "medium", "large", "huge", "gigantic"];
+
+/// Upper bounds, inclusive and in bytes, of the `size:` keyword buckets
+/// `tiny`, `small`, `medium`, `large` and `huge`. `gigantic` holds larger
+/// files and `empty` is always exactly 0 bytes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBuckets {
+    bounds: [u64; 5],
+}
+
+impl SizeBuckets {
+    /// Fails unless `bounds` is strictly increasing.
+    pub fn new(bounds: [u64; 5]) -> Result<Self, SizeBucketsError> {
+        for (index, pair) in bounds.windows(2).enumerate() {
+            if pair[1] <= pair[0] {
+                return Err(SizeBucketsError::NotIncreasing {
+                    keyword: KEYWORDS[index + 1],
+                    bound: pair[1],
+                    previous: pair[0],
+                });
+            }
+        }
+        Ok(Self { bounds })
+    }
+
+    pub fn bounds(&self) -> [u64; 5] {
+        self.bounds
+    }
+
+    /// Every bucket with the sizes it covers, smallest first, e.g. for a
+    /// legend of the keywords.
+    pub fn legend(&self) -> [SizeBucket; 6] {
+        std::array::from_fn(|index| SizeBucket {
+            keyword: KEYWORDS[index],
+            min: match index {
+                0 => 0,
+                _ => self.bounds[index - 1] + 1,
+            },
+            max: self.bounds.get(index).copied(),
+        })
+    }
+
+    /// Inclusive range of a keyword, `None` for anything but a bucket name
+    /// or `empty`.
+    pub(crate) fn range(&self, keyword: &str) -> Option<SizeBucket> {
+        match keyword.trim().to_ascii_lowercase().as_str() {
+            "empty" => Some(SizeBucket {
+                keyword: "empty",
+                min: 0,
+                max: Some(0),
+            }),
+            "giant" => self.legend().last().copied(),
+            keyword => self
+                .legend()
+                .into_iter()
+                .find(|bucket| bucket.keyword == keyword),
+        }
+    }
+}
+
+impl Default for SizeBuckets {
+    fn default() -> Self {
+        Self {
+            bounds: [10 * KB, 100 * KB, MB, 16 * MB, 128 * MB],
+        }
+    }
+}
+
+/// A `size:` keyword and the sizes, in bytes, it matches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeBucket {
+    pub keyword: &'static str,
+    pub min: u64,
+    /// `None` for the open-ended `gigantic`.
+    pub max: Option<u64>,
+}
+
+/// Why [`SizeBuckets::new`] refused its boundaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SizeBucketsError {
+    /// The upper bound of `keyword` isn't above the one of the bucket before.
+    NotIncreasing {
+        keyword: &'static str,
+        bound: u64,
+        previous: u64,
+    },
+}
+
+impl fmt::Display for SizeBucketsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SizeBucketsError::NotIncreasing {
+                keyword,
+                bound,
+                previous,
+            } => write!(
+                f,
+                "Size bucket {keyword:?} ends at {bound} bytes, not above the previous bucket's {previous}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SizeBucketsError {}
@@ -50,6 +50,12 @@ impl SlabIndex {
     pub fn get(&self) -> usize {
         self.0 as usize
     }
+
+    /// Unlike [`Self::new`], doesn't panic on `u32::MAX`: indices coming from
+    /// outside may be anything, lookups just won't find them.
+    pub(crate) fn from_u32(index: u32) -> Self {
+        Self(index)
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -111,6 +117,15 @@ impl<T> ThinSlab<T> {
         self.0.is_empty()
     }
 
+    /// Slots in use or on the freelist, see [`Slab::slots`].
+    pub fn slots(&self) -> usize {
+        self.0.slots()
+    }
+
+    pub fn mapped_bytes(&self) -> usize {
+        self.0.mapped_bytes()
+    }
+
     pub fn iter(&self) -> ThinSlabIter<'_, T> {
         ThinSlabIter(self.0.iter())
     }
@@ -5,7 +5,12 @@ use serde::{
     de::{self, SeqAccess, Visitor},
     ser::SerializeTuple,
 };
-use std::{fmt, num::NonZeroU32};
+use std::{
+    fmt,
+    num::NonZeroU32,
+    os::unix::ffi::OsStrExt,
+    sync::atomic::{AtomicU8, AtomicU64, Ordering},
+};
 use thin_vec::ThinVec;
 
 #[derive(Debug, Clone, Copy)]
@@ -96,11 +101,11 @@ impl NameAndParent {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SlabNode {
     pub name_and_parent: NameAndParent,
     pub children: ThinVec<SlabIndex>,
-    pub metadata: SlabNodeMetadataCompact,
+    pub metadata: LazyMetadata,
 }
 
 impl SlabNode {
@@ -118,7 +123,7 @@ impl SlabNode {
         Self {
             name_and_parent: NameAndParent::new(name, OptionSlabIndex::from_option(parent)),
             children: ThinVec::new(),
-            metadata,
+            metadata: LazyMetadata::new(metadata),
         }
     }
 }
@@ -142,6 +147,46 @@ impl<'a> SlabNodeMetadata<'a> {
     pub fn mtime(&self) -> Option<NonZeroU32> {
         NonZeroU32::new(self.0.mtime)
     }
+
+    /// Bytes allocated on disk, `None` if the metadata was read from a cache
+    /// file that didn't record it.
+    pub fn allocated(&self) -> Option<u64> {
+        self.0.extras.allocated
+    }
+
+    /// Inode number, `None` if the metadata was read from a cache file that
+    /// didn't record it.
+    pub fn inode(&self) -> Option<u64> {
+        self.0.extras.links.map(|(inode, _)| inode)
+    }
+
+    /// Number of hard links, known whenever [`Self::inode`] is.
+    pub fn nlink(&self) -> Option<u32> {
+        self.0.extras.links.map(|(_, nlink)| nlink)
+    }
+
+    /// [`fswalk::NodeMetadata::file_flags`], 0 if the metadata was read from
+    /// a cache file that didn't record them.
+    pub fn file_flags(&self) -> u8 {
+        self.0.extras.file_flags
+    }
+}
+
+/// The part of a node's metadata that isn't stored in its [`LazyMetadata`]:
+/// only nodes whose metadata was fetched have it, so it lives in a table of
+/// the cache keyed by [`SlabIndex`] instead of taking room in every node.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct MetadataExtras {
+    pub(crate) allocated: Option<u64>,
+    /// `(inode, nlink)`.
+    pub(crate) links: Option<(u64, u32)>,
+    pub(crate) file_flags: u8,
+}
+
+impl MetadataExtras {
+    pub(crate) fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
 }
 
 /// Use a compact form so that
@@ -151,6 +196,10 @@ pub struct SlabNodeMetadataCompact {
     // Actually a Option<NonZeroU32>, but using u32 here due to https://github.com/serde-rs/serde/issues/1834
     ctime: u32,
     mtime: u32,
+    /// Not part of the slab's wire format, stored in trailing sections of the
+    /// cache file instead so that older files still load.
+    #[serde(skip)]
+    extras: MetadataExtras,
 }
 
 impl SlabNodeMetadataCompact {
@@ -159,6 +208,7 @@ impl SlabNodeMetadataCompact {
             state_type_and_size: StateTypeSize::unaccessible(),
             ctime: 0,
             mtime: 0,
+            extras: MetadataExtras::default(),
         }
     }
 
@@ -166,8 +216,12 @@ impl SlabNodeMetadataCompact {
         fswalk::NodeMetadata {
             r#type,
             size,
+            allocated,
+            inode,
+            nlink,
             ctime,
             mtime,
+            file_flags,
         }: fswalk::NodeMetadata,
     ) -> Self {
         Self {
@@ -180,6 +234,11 @@ impl SlabNodeMetadataCompact {
                 .and_then(|x| std::num::NonZeroU32::try_from(x).ok())
                 .map(|x| x.get())
                 .unwrap_or_default(),
+            extras: MetadataExtras {
+                allocated: Some(allocated),
+                links: Some((inode, nlink)),
+                file_flags,
+            },
         }
     }
 
@@ -188,6 +247,7 @@ impl SlabNodeMetadataCompact {
             state_type_and_size: StateTypeSize::none(),
             ctime: 0,
             mtime: 0,
+            extras: MetadataExtras::default(),
         }
     }
 
@@ -217,10 +277,208 @@ impl SlabNodeMetadataCompact {
     pub fn file_type_hint(&self) -> NodeFileType {
         self.state_type_and_size.r#type()
     }
+
+    /// The wire fields as `(state_type_and_size, ctime, mtime)`, for formats
+    /// that lay them out by hand.
+    pub(crate) fn to_words(self) -> (u64, u32, u32) {
+        (self.state_type_and_size.to_bits(), self.ctime, self.mtime)
+    }
+
+    /// Inverse of [`Self::to_words`]. The allocated size and links stay
+    /// unknown, the file flags 0.
+    pub(crate) fn from_words((bits, ctime, mtime): (u64, u32, u32)) -> Self {
+        Self {
+            state_type_and_size: StateTypeSize::from_bits(bits),
+            ctime,
+            mtime,
+            extras: MetadataExtras::default(),
+        }
+    }
+
+    pub(crate) fn extras(&self) -> MetadataExtras {
+        self.extras
+    }
+
+    pub(crate) fn with_extras(self, extras: MetadataExtras) -> Self {
+        Self { extras, ..self }
+    }
+}
+
+/// Metadata of a [`SlabNode`], fetched on demand by searches holding `&SearchCache`.
+///
+/// Only the type, size and times live here; [`MetadataExtras`] are kept by
+/// the cache, see `SearchCache::node_metadata`. Concurrent fetches of the
+/// same node store the same stat result, so the last writer wins without
+/// harm. Times are stored before the state word, a reader that sees
+/// `State::Some` also sees the times that came with it. Eviction swaps the
+/// state word back to `State::None`, the budget drops the extras with it.
+pub struct LazyMetadata {
+    state_type_and_size: AtomicU64,
+    /// `ctime` in the low half, `mtime` in the high half.
+    times: AtomicU64,
+    /// [`REFERENCED`] and [`TRACKED`], for the metadata budget's clock.
+    flags: AtomicU8,
+}
+
+/// Metadata was read since the clock hand last passed the node.
+const REFERENCED: u8 = 1;
+/// The node is in the metadata budget's ring.
+const TRACKED: u8 = 2;
+
+impl LazyMetadata {
+    /// Keeps what a node stores, the extras of `metadata` are the cache's to keep.
+    pub fn new(metadata: SlabNodeMetadataCompact) -> Self {
+        Self {
+            state_type_and_size: AtomicU64::new(metadata.state_type_and_size.to_bits()),
+            times: AtomicU64::new(Self::pack_times(metadata)),
+            flags: AtomicU8::new(0),
+        }
+    }
+
+    /// The stored metadata, without extras.
+    pub fn get(&self) -> SlabNodeMetadataCompact {
+        let state_type_and_size =
+            StateTypeSize::from_bits(self.state_type_and_size.load(Ordering::Acquire));
+        let times = self.times.load(Ordering::Relaxed);
+        SlabNodeMetadataCompact {
+            state_type_and_size,
+            ctime: times as u32,
+            mtime: (times >> 32) as u32,
+            extras: MetadataExtras::default(),
+        }
+    }
+
+    pub fn set(&self, metadata: SlabNodeMetadataCompact) {
+        self.times
+            .store(Self::pack_times(metadata), Ordering::Relaxed);
+        self.state_type_and_size
+            .store(metadata.state_type_and_size.to_bits(), Ordering::Release);
+    }
+
+    pub fn state(&self) -> State {
+        self.get().state()
+    }
+
+    pub fn is_some(&self) -> bool {
+        self.get().is_some()
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.get().is_none()
+    }
+
+    pub fn is_unaccessible(&self) -> bool {
+        self.get().is_unaccessible()
+    }
+
+    pub fn file_type_hint(&self) -> NodeFileType {
+        self.get().file_type_hint()
+    }
+
+    /// Mark the metadata as used, sparing it from the clock hand's next pass.
+    pub(crate) fn touch(&self) {
+        if self.flags.load(Ordering::Relaxed) & REFERENCED == 0 {
+            self.flags.fetch_or(REFERENCED, Ordering::Relaxed);
+        }
+    }
+
+    /// Clear the referenced mark, returning whether it was set.
+    pub(crate) fn take_reference(&self) -> bool {
+        self.flags.fetch_and(!REFERENCED, Ordering::Relaxed) & REFERENCED != 0
+    }
+
+    /// Mark the node as tracked by the budget, returning whether it wasn't yet.
+    pub(crate) fn track(&self) -> bool {
+        self.flags.fetch_or(TRACKED, Ordering::Relaxed) & TRACKED == 0
+    }
+
+    pub(crate) fn is_tracked(&self) -> bool {
+        self.flags.load(Ordering::Relaxed) & TRACKED != 0
+    }
+
+    pub(crate) fn untrack(&self) {
+        self.flags.fetch_and(!TRACKED, Ordering::Relaxed);
+    }
+
+    /// Revert fetched metadata to `State::None`, keeping the file type, so it
+    /// is stat'ed again when needed. Fails if another thread stored new
+    /// metadata in the meantime.
+    pub(crate) fn evict(&self) -> bool {
+        let current = self.state_type_and_size.load(Ordering::Acquire);
+        let state_type_and_size = StateTypeSize::from_bits(current);
+        if state_type_and_size.state() != State::Some {
+            return false;
+        }
+        let evicted = StateTypeSize::none_of(state_type_and_size.r#type()).to_bits();
+        self.state_type_and_size
+            .compare_exchange(current, evicted, Ordering::AcqRel, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    fn pack_times(metadata: SlabNodeMetadataCompact) -> u64 {
+        u64::from(metadata.ctime) | (u64::from(metadata.mtime) << 32)
+    }
+}
+
+/// Copies every word, the budget's flags too: the copy of a chunk shared
+/// with a [`crate::SearchSnapshot`] takes the place of the original.
+impl Clone for LazyMetadata {
+    fn clone(&self) -> Self {
+        let clone = Self::new(self.get());
+        clone
+            .flags
+            .store(self.flags.load(Ordering::Relaxed), Ordering::Relaxed);
+        clone
+    }
+}
+
+impl fmt::Debug for LazyMetadata {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.get().fmt(f)
+    }
+}
+
+impl Serialize for LazyMetadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        self.get().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LazyMetadata {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        SlabNodeMetadataCompact::deserialize(deserializer).map(Self::new)
+    }
 }
 
 #[derive(Debug)]
 pub struct SearchResultNode {
     pub path: std::path::PathBuf,
     pub metadata: SlabNodeMetadataCompact,
+    /// Source id of the [`crate::CacheSet`] member the node comes from, 0 for
+    /// a lone cache.
+    pub source: usize,
+    /// User tags of the node, sorted, see [`crate::SearchCache::add_tag`].
+    pub tags: Vec<String>,
+    /// Byte ranges of the file name (the last component of `path`) matched by
+    /// the name terms of the query, sorted and disjoint. Only filled in by
+    /// the `query_files*` methods when [`crate::SearchOptions::collect_highlights`]
+    /// is set, and for names that are valid UTF-8.
+    pub highlights: Option<Vec<(u32, u32)>>,
+    /// `path` as [`crate::SearchOptions::path_display`] asks to show it, lossy
+    /// for non-UTF-8 names. Absolute, with `~` for the home directory, unless
+    /// filled in by the `query_files*` methods.
+    pub display_path: String,
+}
+
+impl SearchResultNode {
+    /// Exact bytes of `path`, unlike `to_string_lossy` this survives non-UTF-8 file names.
+    pub fn path_bytes(&self) -> &[u8] {
+        self.path.as_os_str().as_bytes()
+    }
 }
@@ -0,0 +1,68 @@
+use crate::{SearchCache, SlabIndex};
+use std::cmp::Ordering;
+
+/// Finder-like order of names: runs of ASCII digits compare by their numeric
+/// value (`file2` before `file10`) and ASCII letters ignore case. Other bytes,
+/// UTF-8 included, compare as they are. Names equal by these rules (`a01` and
+/// `a1`, `Read` and `read`) fall back to their bytes, so the order is total.
+///
+/// Doesn't allocate, whatever the length of the digit runs.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (a_bytes, b_bytes) = (a.as_bytes(), b.as_bytes());
+    let (mut i, mut j) = (0, 0);
+    while i < a_bytes.len() && j < b_bytes.len() {
+        if a_bytes[i].is_ascii_digit() && b_bytes[j].is_ascii_digit() {
+            let a_run = digit_run(&a_bytes[i..]);
+            let b_run = digit_run(&b_bytes[j..]);
+            let ordering = cmp_numbers(a_run, b_run);
+            if ordering.is_ne() {
+                return ordering;
+            }
+            i += a_run.len();
+            j += b_run.len();
+        } else {
+            let ordering = a_bytes[i]
+                .to_ascii_lowercase()
+                .cmp(&b_bytes[j].to_ascii_lowercase());
+            if ordering.is_ne() {
+                return ordering;
+            }
+            i += 1;
+            j += 1;
+        }
+    }
+    (a_bytes.len() - i)
+        .cmp(&(b_bytes.len() - j))
+        .then_with(|| a_bytes.cmp(b_bytes))
+}
+
+/// The ASCII digits `bytes` starts with.
+fn digit_run(bytes: &[u8]) -> &[u8] {
+    let len = bytes
+        .iter()
+        .position(|byte| !byte.is_ascii_digit())
+        .unwrap_or(bytes.len());
+    &bytes[..len]
+}
+
+/// Compare two runs of digits by value, leading zeros aside.
+fn cmp_numbers(a: &[u8], b: &[u8]) -> Ordering {
+    let a = trim_leading_zeros(a);
+    let b = trim_leading_zeros(b);
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+fn trim_leading_zeros(digits: &[u8]) -> &[u8] {
+    let zeros = digits.iter().take_while(|&&digit| digit == b'0').count();
+    &digits[zeros..]
+}
+
+impl SearchCache {
+    /// Name of `index` as [`natural_cmp`] sees it, lossy for non-UTF-8 names
+    /// and empty for nodes that aren't in the cache.
+    pub(crate) fn sort_name(&self, index: SlabIndex) -> &str {
+        self.file_nodes
+            .get(index)
+            .map_or("", |node| node.name_and_parent.as_str())
+    }
+}
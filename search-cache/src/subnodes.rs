@@ -0,0 +1,202 @@
+use crate::{SearchCache, SlabIndex, natural_cmp};
+use search_cancel::{CANCEL_CHECK_INTERVAL, CancellationToken};
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SubnodeOrder {
+    /// Pre-order: every folder is directly followed by its descendants.
+    #[default]
+    DepthFirst,
+    /// Level by level: all children come before any grandchild.
+    BreadthFirst,
+    /// Pre-order like [`SubnodeOrder::DepthFirst`], with the children of each
+    /// folder by name in Finder-like order (see [`natural_cmp`]). Children
+    /// with equal names keep their slab order.
+    NaturalName,
+}
+
+/// Where a paged traversal stopped.
+///
+/// The cursor holds the folders still being listed, so resuming doesn't walk
+/// the skipped nodes again. It's only meaningful as long as the cache isn't
+/// modified in between.
+#[derive(Debug, Clone)]
+pub struct SubnodeCursor {
+    order: SubnodeOrder,
+    /// Folders with children left to yield, each with the position of the
+    /// next child. Depth-first works off the back, breadth-first off the front.
+    pending: VecDeque<(SlabIndex, usize)>,
+    /// Children of the folders in `pending` sorted for
+    /// [`SubnodeOrder::NaturalName`], indexed by position in place of the
+    /// slab's children.
+    sorted: Vec<(SlabIndex, Vec<SlabIndex>)>,
+    /// Number of nodes yielded before this cursor.
+    offset: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubnodePage {
+    pub items: Vec<SlabIndex>,
+    /// Number of descendants in total, only known on the last page.
+    pub total: Option<usize>,
+    /// Cursor for the next page, `None` on the last one.
+    pub next: Option<SubnodeCursor>,
+}
+
+impl SubnodeCursor {
+    fn new(index: SlabIndex, order: SubnodeOrder) -> Self {
+        Self {
+            order,
+            pending: VecDeque::from([(index, 0)]),
+            sorted: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    pub fn order(&self) -> SubnodeOrder {
+        self.order
+    }
+
+    /// Number of nodes yielded before this cursor.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn current(&mut self) -> Option<&mut (SlabIndex, usize)> {
+        match self.order {
+            SubnodeOrder::DepthFirst | SubnodeOrder::NaturalName => self.pending.back_mut(),
+            SubnodeOrder::BreadthFirst => self.pending.front_mut(),
+        }
+    }
+
+    /// Drop exhausted folders and return the next node without consuming it.
+    fn peek(&mut self, cache: &SearchCache) -> Option<SlabIndex> {
+        loop {
+            let &mut (parent, position) = self.current()?;
+            let child = if self.order == SubnodeOrder::NaturalName {
+                self.sorted_children(cache, parent).get(position).copied()
+            } else {
+                // Removed folders of a stale cursor are treated as empty.
+                cache
+                    .file_nodes
+                    .get(parent)
+                    .and_then(|node| node.children.get(position).copied())
+            };
+            if child.is_some() {
+                return child;
+            }
+            match self.order {
+                SubnodeOrder::DepthFirst | SubnodeOrder::NaturalName => {
+                    self.pending.pop_back();
+                    if self
+                        .sorted
+                        .last()
+                        .is_some_and(|(folder, _)| *folder == parent)
+                    {
+                        self.sorted.pop();
+                    }
+                }
+                SubnodeOrder::BreadthFirst => {
+                    self.pending.pop_front();
+                }
+            };
+        }
+    }
+
+    /// Children of `folder`, the innermost folder being listed, in natural
+    /// order. Sorted once per folder: the folders being listed form a stack.
+    fn sorted_children(&mut self, cache: &SearchCache, folder: SlabIndex) -> &[SlabIndex] {
+        if self
+            .sorted
+            .last()
+            .is_none_or(|(sorted, _)| *sorted != folder)
+        {
+            self.sorted
+                .push((folder, cache.children_of(folder, SubnodeOrder::NaturalName)));
+        }
+        &self.sorted.last().expect("pushed above").1
+    }
+
+    fn advance(&mut self, cache: &SearchCache) -> Option<SlabIndex> {
+        let child = self.peek(cache)?;
+        if let Some((_, position)) = self.current() {
+            *position += 1;
+        }
+        self.pending.push_back((child, 0));
+        self.offset += 1;
+        Some(child)
+    }
+}
+
+impl SearchCache {
+    /// Children of `index`, by name in Finder-like order for
+    /// [`SubnodeOrder::NaturalName`] and in slab order otherwise. Empty for
+    /// files and nodes that aren't in the cache.
+    pub fn children_of(&self, index: SlabIndex, order: SubnodeOrder) -> Vec<SlabIndex> {
+        let mut children = self
+            .file_nodes
+            .get(index)
+            .map(|node| node.children.to_vec())
+            .unwrap_or_default();
+        if order == SubnodeOrder::NaturalName {
+            // Stable: equal names keep their slab order.
+            children.sort_by(|&a, &b| natural_cmp(self.sort_name(a), self.sort_name(b)));
+        }
+        children
+    }
+
+    /// Return up to `limit` descendants of `index` after skipping the first
+    /// `offset` of them in `order`. Returns `None` when cancelled.
+    pub fn subnodes_page(
+        &self,
+        index: SlabIndex,
+        offset: usize,
+        limit: usize,
+        order: SubnodeOrder,
+        cancel: CancellationToken,
+    ) -> Option<SubnodePage> {
+        let mut cursor = SubnodeCursor::new(index, order);
+        for i in 0..offset {
+            if i % CANCEL_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+                return None;
+            }
+            if cursor.advance(self).is_none() {
+                break;
+            }
+        }
+        self.subnodes_page_after(cursor, limit, cancel)
+    }
+
+    /// Continue a traversal from the `next` cursor of a previous page.
+    pub fn subnodes_page_after(
+        &self,
+        mut cursor: SubnodeCursor,
+        limit: usize,
+        cancel: CancellationToken,
+    ) -> Option<SubnodePage> {
+        let mut items = Vec::new();
+        while items.len() < limit {
+            if items.len() % CANCEL_CHECK_INTERVAL == 0 && cancel.is_cancelled() {
+                return None;
+            }
+            let Some(child) = cursor.advance(self) else {
+                break;
+            };
+            items.push(child);
+        }
+        let page = if cursor.peek(self).is_some() {
+            SubnodePage {
+                items,
+                total: None,
+                next: Some(cursor),
+            }
+        } else {
+            SubnodePage {
+                items,
+                total: Some(cursor.offset),
+                next: None,
+            }
+        };
+        Some(page)
+    }
+}
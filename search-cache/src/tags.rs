@@ -0,0 +1,131 @@
+use crate::{SearchCache, SlabIndex};
+use anyhow::{Result, anyhow, bail};
+use namepool::to_nfc;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    ffi::OsStr,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+};
+
+/// Labels the user put on files and folders ("keep", "review"), found again
+/// with `tag:`. Independent of Finder tags.
+///
+/// Keyed by the exact bytes of the full path rather than by slab index, which
+/// changes with every rescan: a tag belongs to whatever node has its path when
+/// it's looked up. Tags of paths that aren't in the tree are kept, and apply
+/// again once the path is back.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Tags {
+    entries: BTreeMap<Box<[u8]>, BTreeSet<Box<str>>>,
+}
+
+impl Tags {
+    /// Number of tagged paths.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Tag `path` with `tag`, already normalized. Returns whether it's new.
+    fn insert(&mut self, path: &Path, tag: Box<str>) -> bool {
+        self.entries
+            .entry(path.as_os_str().as_bytes().into())
+            .or_default()
+            .insert(tag)
+    }
+
+    fn remove(&mut self, path: &Path, tag: &str) -> bool {
+        let key = path.as_os_str().as_bytes();
+        let Some(tags) = self.entries.get_mut(key) else {
+            return false;
+        };
+        let removed = tags.remove(tag);
+        if tags.is_empty() {
+            self.entries.remove(key);
+        }
+        removed
+    }
+
+    /// Tags of `path`, sorted.
+    pub fn get(&self, path: &Path) -> impl Iterator<Item = &str> {
+        self.entries
+            .get(path.as_os_str().as_bytes())
+            .into_iter()
+            .flatten()
+            .map(|tag| &**tag)
+    }
+
+    /// Tagged paths with their tags, in path byte order.
+    pub fn iter(&self) -> impl Iterator<Item = (&Path, &BTreeSet<Box<str>>)> {
+        self.entries
+            .iter()
+            .map(|(path, tags)| (Path::new(OsStr::from_bytes(path)), tags))
+    }
+}
+
+/// The form tags are stored and matched in: trimmed, lowercased and NFC, so
+/// `tag:Keep` finds `keep`. Fails for tags a `tag:` filter couldn't name:
+/// empty ones and those with whitespace, `;`, `|` or quotes.
+pub fn normalize_tag(tag: &str) -> Result<Box<str>> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        bail!("tag is empty");
+    }
+    if let Some(c) = tag
+        .chars()
+        .find(|&c| c.is_whitespace() || matches!(c, ';' | '|' | '"' | '<' | '>'))
+    {
+        bail!("tag {tag:?} contains {c:?}");
+    }
+    Ok(to_nfc(&tag.to_lowercase()).into())
+}
+
+impl SearchCache {
+    pub fn tags(&self) -> &Tags {
+        &self.tags
+    }
+
+    /// Tag `index` with `tag`, see [`normalize_tag`]. Returns whether the
+    /// node didn't have the tag yet.
+    pub fn add_tag(&mut self, index: SlabIndex, tag: &str) -> Result<bool> {
+        let tag = normalize_tag(tag)?;
+        let path = self
+            .node_path(index)
+            .ok_or_else(|| anyhow!("node {index:?} is not in the tree"))?;
+        Ok(self.tags.insert(&path, tag))
+    }
+
+    /// Returns whether `index` had `tag`.
+    pub fn remove_tag(&mut self, index: SlabIndex, tag: &str) -> bool {
+        let (Ok(tag), Some(path)) = (normalize_tag(tag), self.node_path(index)) else {
+            return false;
+        };
+        self.tags.remove(&path, &tag)
+    }
+
+    /// Tags of `index`, sorted.
+    pub fn tags_of(&self, index: SlabIndex) -> Vec<&str> {
+        match self.node_path(index) {
+            Some(path) => self.tags.get(&path).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Nodes having any tag `matches` accepts, sorted by slab index. Paths
+    /// that aren't in the tree are skipped.
+    pub(crate) fn tagged_nodes(&self, mut matches: impl FnMut(&str) -> bool) -> Vec<SlabIndex> {
+        let mut nodes: Vec<SlabIndex> = self
+            .tags
+            .iter()
+            .filter(|(_, tags)| tags.iter().any(|tag| matches(tag)))
+            .filter_map(|(path, _)| self.node_index_for_raw_path(path))
+            .collect();
+        nodes.sort_unstable();
+        nodes
+    }
+}
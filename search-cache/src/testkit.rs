@@ -0,0 +1,314 @@
+//! Scaffolding for tests of this crate and of the crates built on it, behind
+//! the `testkit` feature: trees declared up front and created in a temporary
+//! folder, FSEvents numbered after what a cache applied, and assertions on
+//! search results and the tree's consistency.
+//!
+//! ```
+//! use search_cache::testkit::{EventScript, TreeSpec, assert_integrity, assert_query_names};
+//!
+//! let (tmp, mut cache) = TreeSpec::new()
+//!     .file("docs/report.txt")
+//!     .sized_file("photos/beach.png", 4096)
+//!     .index("my_test");
+//! let mut script = EventScript::after(&cache);
+//! script.rename(&tmp.path().join("docs/report.txt"), &tmp.path().join("docs/final.txt"));
+//! script.apply(&mut cache).unwrap();
+//! assert_query_names(&cache, "final", &["final.txt"]);
+//! assert_integrity(&cache);
+//! ```
+
+use crate::{HandleFSEError, SearchCache, SearchOptions, SlabIndex, SlabNodeMetadataCompact};
+use cardinal_sdk::{EventFlag, FsEvent};
+use fswalk::{NodeFileType, NodeMetadata};
+use jiff::{civil::Date, tz::TimeZone};
+use search_cancel::CancellationToken;
+use std::{
+    fs::{self, File, FileTimes},
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+use tempdir::TempDir;
+
+pub const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+#[derive(Debug, Clone)]
+enum EntryKind {
+    Dir,
+    File { len: u64 },
+    Symlink { target: PathBuf },
+}
+
+/// Files and folders to create, by path relative to the tree's root. Parent
+/// folders are created as needed.
+#[derive(Debug, Clone, Default)]
+pub struct TreeSpec {
+    entries: Vec<(PathBuf, EntryKind)>,
+    /// Unix times, set once everything is created.
+    mtimes: Vec<(PathBuf, i64)>,
+}
+
+impl TreeSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn dir(mut self, path: impl AsRef<Path>) -> Self {
+        self.entries
+            .push((path.as_ref().to_path_buf(), EntryKind::Dir));
+        self
+    }
+
+    /// An empty file.
+    pub fn file(self, path: impl AsRef<Path>) -> Self {
+        self.sized_file(path, 0)
+    }
+
+    /// A file of `len` zero bytes.
+    pub fn sized_file(mut self, path: impl AsRef<Path>, len: u64) -> Self {
+        self.entries
+            .push((path.as_ref().to_path_buf(), EntryKind::File { len }));
+        self
+    }
+
+    /// Empty files, one per path.
+    pub fn files<P: AsRef<Path>>(self, paths: impl IntoIterator<Item = P>) -> Self {
+        paths.into_iter().fold(self, Self::file)
+    }
+
+    /// A symbolic link to `target`, taken as is: a relative target resolves
+    /// against the link's folder and needn't exist.
+    pub fn symlink(mut self, path: impl AsRef<Path>, target: impl AsRef<Path>) -> Self {
+        let target = target.as_ref().to_path_buf();
+        self.entries
+            .push((path.as_ref().to_path_buf(), EntryKind::Symlink { target }));
+        self
+    }
+
+    /// Set the modification time of `path`, a file or folder of the tree, to
+    /// the unix time `secs`.
+    pub fn modified(mut self, path: impl AsRef<Path>, secs: i64) -> Self {
+        self.mtimes.push((path.as_ref().to_path_buf(), secs));
+        self
+    }
+
+    /// Create the tree in a new temporary folder named after `prefix`.
+    pub fn create(&self, prefix: &str) -> TempDir {
+        let tmp = TempDir::new(prefix).unwrap();
+        let root = tmp.path();
+        for (path, kind) in &self.entries {
+            let path = root.join(path);
+            match kind {
+                EntryKind::Dir => fs::create_dir_all(&path).unwrap(),
+                EntryKind::File { len } => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).unwrap();
+                    }
+                    File::create(&path).unwrap().set_len(*len).unwrap();
+                }
+                EntryKind::Symlink { target } => {
+                    if let Some(parent) = path.parent() {
+                        fs::create_dir_all(parent).unwrap();
+                    }
+                    std::os::unix::fs::symlink(target, &path).unwrap();
+                }
+            }
+        }
+        // Last, creating a child would bump its folder's time.
+        for (path, secs) in &self.mtimes {
+            let time = UNIX_EPOCH + Duration::from_secs(u64::try_from(*secs).unwrap());
+            File::open(root.join(path))
+                .unwrap()
+                .set_times(FileTimes::new().set_modified(time))
+                .unwrap();
+        }
+        tmp
+    }
+
+    /// [`Self::create`] the tree and index it.
+    pub fn index(&self, prefix: &str) -> (TempDir, SearchCache) {
+        let tmp = self.create(prefix);
+        let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+        (tmp, cache)
+    }
+}
+
+/// Changes made on disk along with the FSEvents macOS reports for them,
+/// numbered after the last event a cache applied.
+#[derive(Debug)]
+pub struct EventScript {
+    next_id: u64,
+    events: Vec<FsEvent>,
+}
+
+impl EventScript {
+    /// Events following the last one `cache` applied.
+    pub fn after(cache: &SearchCache) -> Self {
+        Self::after_id(cache.last_event_id)
+    }
+
+    /// Events following `last_id`, e.g. the `last_event_id` of a
+    /// [`crate::CacheSet`] member.
+    pub fn after_id(last_id: u64) -> Self {
+        Self {
+            next_id: last_id + 1,
+            events: Vec::new(),
+        }
+    }
+
+    /// Report `flag` on `path` without touching the disk, for events the
+    /// other methods don't cover (mounts, xattr changes, the end of the history).
+    pub fn event(&mut self, path: &Path, flag: EventFlag) -> &mut Self {
+        self.events.push(FsEvent {
+            path: path.to_path_buf(),
+            id: self.next_id,
+            flag,
+        });
+        self.next_id += 1;
+        self
+    }
+
+    /// Write `contents` to a new file at `path`.
+    pub fn create_file(&mut self, path: &Path, contents: &[u8]) -> &mut Self {
+        fs::write(path, contents).unwrap();
+        self.event(path, EventFlag::ItemCreated | EventFlag::ItemIsFile)
+    }
+
+    /// Create a folder at `path`, with its missing parents.
+    pub fn create_dir(&mut self, path: &Path) -> &mut Self {
+        fs::create_dir_all(path).unwrap();
+        self.event(path, EventFlag::ItemCreated | EventFlag::ItemIsDir)
+    }
+
+    /// Overwrite the file at `path` with `contents`.
+    pub fn modify(&mut self, path: &Path, contents: &[u8]) -> &mut Self {
+        fs::write(path, contents).unwrap();
+        self.event(path, EventFlag::ItemModified | EventFlag::ItemIsFile)
+    }
+
+    /// Remove the file, folder (with its contents) or symlink at `path`.
+    pub fn remove(&mut self, path: &Path) -> &mut Self {
+        let kind = kind_flag(path);
+        if kind == EventFlag::ItemIsDir {
+            fs::remove_dir_all(path).unwrap();
+        } else {
+            fs::remove_file(path).unwrap();
+        }
+        self.event(path, EventFlag::ItemRemoved | kind)
+    }
+
+    /// Rename `from` to `to`, reported as one event for each path.
+    pub fn rename(&mut self, from: &Path, to: &Path) -> &mut Self {
+        let kind = kind_flag(from);
+        fs::rename(from, to).unwrap();
+        self.event(from, EventFlag::ItemRenamed | kind)
+            .event(to, EventFlag::ItemRenamed | kind)
+    }
+
+    pub fn events(&self) -> &[FsEvent] {
+        &self.events
+    }
+
+    pub fn into_events(self) -> Vec<FsEvent> {
+        self.events
+    }
+
+    /// Hand the events to `cache` in one batch.
+    pub fn apply(self, cache: &mut SearchCache) -> Result<(), HandleFSEError> {
+        cache.handle_fs_events(self.events)
+    }
+}
+
+/// The `ItemIs*` flag of what is at `path`, not following symlinks.
+fn kind_flag(path: &Path) -> EventFlag {
+    let file_type = fs::symlink_metadata(path).unwrap().file_type();
+    if file_type.is_symlink() {
+        EventFlag::ItemIsSymlink
+    } else if file_type.is_dir() {
+        EventFlag::ItemIsDir
+    } else {
+        EventFlag::ItemIsFile
+    }
+}
+
+/// Give the file at `index` these creation and modification unix times
+/// without touching the disk, e.g. dates no file system would allow.
+pub fn set_file_times(cache: &mut SearchCache, index: SlabIndex, created: i64, modified: i64) {
+    let metadata = NodeMetadata {
+        r#type: NodeFileType::File,
+        size: 0,
+        allocated: 0,
+        inode: 0,
+        nlink: 1,
+        ctime: NonZeroU64::new(created as u64),
+        mtime: NonZeroU64::new(modified as u64),
+        file_flags: 0,
+    };
+    cache.store_metadata(index, SlabNodeMetadataCompact::some(metadata));
+}
+
+/// Unix time of noon on this day in the system's time zone, safely inside
+/// the day whatever the offset.
+pub fn ts_for_date(year: i32, month: u32, day: u32) -> i64 {
+    let tz = TimeZone::system();
+    let date = Date::new(
+        i16::try_from(year).expect("year fits in range"),
+        month as i8,
+        day as i8,
+    )
+    .expect("valid date components");
+    tz.to_zoned(date.at(12, 0, 0, 0))
+        .expect("valid local date")
+        .timestamp()
+        .as_second()
+}
+
+/// Names of the files among `indices`, folders left out, sorted.
+pub fn list_file_names(cache: &SearchCache, indices: &[SlabIndex]) -> Vec<String> {
+    let mut names: Vec<String> = indices
+        .iter()
+        .filter(|&&index| cache.file_nodes[index].metadata.file_type_hint() == NodeFileType::File)
+        .map(|&index| node_name(cache, index))
+        .collect();
+    names.sort();
+    names
+}
+
+/// File name of the node at `index`.
+pub fn node_name(cache: &SearchCache, index: SlabIndex) -> String {
+    cache
+        .node_path(index)
+        .unwrap()
+        .file_name()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Assert that `query`, with the default options, finds nodes with exactly
+/// these names, files and folders alike, in any order.
+#[track_caller]
+pub fn assert_query_names(cache: &SearchCache, query: &str, expected: &[&str]) {
+    let nodes = cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    let mut names: Vec<String> = nodes.iter().map(|&index| node_name(cache, index)).collect();
+    names.sort();
+    let mut expected: Vec<&str> = expected.to_vec();
+    expected.sort_unstable();
+    assert_eq!(names, expected, "results of {query:?}");
+}
+
+/// Assert that no folder has two children of one name and that the name
+/// index lists every node once, see [`SearchCache::verify_integrity`] and
+/// [`SearchCache::verify_name_index`].
+#[track_caller]
+pub fn assert_integrity(cache: &SearchCache) {
+    if let Err(e) = cache.verify_integrity() {
+        panic!("tree is inconsistent: {e}");
+    }
+    if let Err(e) = cache.verify_name_index() {
+        panic!("name index is inconsistent: {e}");
+    }
+}
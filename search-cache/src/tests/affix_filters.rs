@@ -0,0 +1,153 @@
+use super::prelude::*;
+use crate::{SearchOptions, SlabIndex, testkit::TreeSpec};
+
+const NAMES: &[&str] = &[
+    "IMG_0001.jpg",
+    "IMG_0002.JPG",
+    "img_lower.png",
+    "report.txt",
+    "Report-final.txt",
+    "notes.md",
+    "archive.tar.gz",
+    "backup.tar.gz.part",
+    "a+b (1).txt",
+    "star*name.txt",
+];
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(NAMES.iter().map(|name| format!("one/{name}")))
+        .files(NAMES.iter().map(|name| format!("two/{name}")))
+        .index("affix_filters")
+}
+
+fn search(cache: &SearchCache, query: &str, case_insensitive: bool) -> Vec<SlabIndex> {
+    let options = SearchOptions {
+        case_insensitive,
+        ..Default::default()
+    };
+    let mut nodes = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    nodes.sort_unstable();
+    nodes
+}
+
+fn names(cache: &SearchCache, nodes: &[SlabIndex]) -> Vec<String> {
+    let mut names: Vec<String> = nodes
+        .iter()
+        .map(|&index| cache.file_nodes[index].name_and_parent.as_str().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_affix_filters_match_equivalent_regex() {
+    let (_tmp, cache) = build_tree();
+    let needles = [
+        "IMG_", "img_", "Rep", "report", ".txt", ".gz", "tar", "a+b (", "star*", "JPG", ".md",
+    ];
+    for case_insensitive in [false, true] {
+        for needle in needles {
+            let escaped = regex::escape(needle);
+            assert_eq!(
+                search(
+                    &cache,
+                    &format!("startswith:\"{needle}\""),
+                    case_insensitive
+                ),
+                search(&cache, &format!("regex:\"^{escaped}\""), case_insensitive),
+                "startswith:{needle} (case_insensitive: {case_insensitive})"
+            );
+            assert_eq!(
+                search(&cache, &format!("endswith:\"{needle}\""), case_insensitive),
+                search(&cache, &format!("regex:\"{escaped}$\""), case_insensitive),
+                "endswith:{needle} (case_insensitive: {case_insensitive})"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_affix_filters_return_every_node_sharing_a_name() {
+    let (tmp, cache) = build_tree();
+    let nodes = search(&cache, "startswith:report", false);
+    let mut paths: Vec<PathBuf> = nodes
+        .iter()
+        .filter_map(|&index| cache.node_path(index))
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            tmp.path().join("one/report.txt"),
+            tmp.path().join("two/report.txt"),
+        ]
+    );
+    assert_eq!(search(&cache, "endswith:.gz", false).len(), 2);
+}
+
+#[test]
+fn test_affix_filters_follow_case_option() {
+    let (_tmp, cache) = build_tree();
+    let sensitive = search(&cache, "startswith:img_", false);
+    assert_eq!(
+        names(&cache, &sensitive),
+        ["img_lower.png", "img_lower.png"]
+    );
+    let insensitive = search(&cache, "startswith:img_", true);
+    assert_eq!(insensitive.len(), 6);
+    assert_eq!(search(&cache, "endswith:.jpg", false).len(), 2);
+    assert_eq!(search(&cache, "endswith:.jpg", true).len(), 4);
+}
+
+#[test]
+fn test_affix_filters_take_wildcards_literally() {
+    let (_tmp, cache) = build_tree();
+    let nodes = search(&cache, "startswith:star*", false);
+    assert_eq!(names(&cache, &nodes), ["star*name.txt", "star*name.txt"]);
+    assert!(search(&cache, "startswith:IMG_*2", false).is_empty());
+}
+
+#[test]
+fn test_affix_filters_compose_with_other_terms() {
+    let (_tmp, cache) = build_tree();
+    let nodes = search(&cache, "startswith:IMG_ ext:jpg", true);
+    assert_eq!(
+        names(&cache, &nodes),
+        [
+            "IMG_0001.jpg",
+            "IMG_0001.jpg",
+            "IMG_0002.JPG",
+            "IMG_0002.JPG"
+        ]
+    );
+    // Narrowing an existing result goes through the base path.
+    let nodes = search(&cache, "ext:txt endswith:final.txt", false);
+    assert_eq!(
+        names(&cache, &nodes),
+        ["Report-final.txt", "Report-final.txt"]
+    );
+    let nodes = search(&cache, "startswith:notes | endswith:.part", false);
+    assert_eq!(nodes.len(), 4);
+    let nodes = search(&cache, "endswith:.txt !startswith:report", true);
+    assert_eq!(
+        names(&cache, &nodes),
+        [
+            "a+b (1).txt",
+            "a+b (1).txt",
+            "star*name.txt",
+            "star*name.txt"
+        ]
+    );
+}
+
+#[test]
+fn test_affix_filters_require_a_value() {
+    let (_tmp, cache) = build_tree();
+    let error = |query: &str| cache.search(query).unwrap_err().to_string();
+    assert!(error("startswith:").contains("startswith: requires a value"));
+    assert!(error("endswith:").contains("endswith: requires a value"));
+}
@@ -1,5 +1,9 @@
 use super::prelude::*;
-use cardinal_sdk::{EventFlag, FsEvent};
+use crate::{
+    CacheError, PersistentStorage, QueryHistory, SearchOptions, persistent::check_event_stream,
+    read_cache_from_file, write_cache_to_file,
+};
+use cardinal_sdk::{EventFlag, FsEvent, dev_of_path, event_stream_uuid};
 
 #[test]
 fn test_search_empty_returns_all_nodes() {
@@ -13,12 +17,34 @@ fn test_search_empty_returns_all_nodes() {
     assert_eq!(all.len(), cache.get_total_files());
 }
 
+#[test]
+fn test_walk_fs_with_progress_reports_final_counts() {
+    let tmp = TempDir::new("walk_progress").unwrap();
+    for i in 0..20 {
+        let dir = tmp.path().join(format!("dir{i}"));
+        fs::create_dir(&dir).unwrap();
+        for j in 0..10 {
+            fs::File::create(dir.join(format!("file{j}.txt"))).unwrap();
+        }
+    }
+    let reports = std::sync::Mutex::new(Vec::new());
+    let cache = SearchCache::walk_fs_with_progress(tmp.path().to_path_buf(), |progress| {
+        reports.lock().unwrap().push(progress)
+    });
+    let reports = reports.into_inner().unwrap();
+    assert!(!reports.is_empty());
+    let last = reports.last().unwrap();
+    assert_eq!(last.files_seen, 200);
+    assert_eq!(last.dirs_seen, 21);
+    assert_eq!(last.files_seen + last.dirs_seen, cache.get_total_files());
+}
+
 #[test]
 fn test_node_path_root_and_child() {
     let tmp = TempDir::new("node_path").unwrap();
     fs::create_dir(tmp.path().join("dir1")).unwrap();
     fs::File::create(tmp.path().join("dir1/file_x")).unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
     let idxs = cache.search("file_x").unwrap();
     assert_eq!(idxs.len(), 1);
     let full = cache.node_path(idxs.into_iter().next().unwrap()).unwrap();
@@ -58,14 +84,13 @@ fn test_remove_node_path_nonexistent_returns_none() {
 fn test_expand_file_nodes_fetch_metadata() {
     let tmp = TempDir::new("expand_meta").unwrap();
     fs::write(tmp.path().join("meta.txt"), b"hello world").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
     let idxs = cache.search("meta.txt").unwrap();
     assert_eq!(idxs.len(), 1);
     // First query_files returns metadata None
     let q1 = cache
         .query_files("meta.txt".into(), CancellationToken::noop())
-        .expect("query should succeed")
-        .expect("noop cancellation token should not cancel");
+        .expect("query should succeed");
     assert_eq!(q1.len(), 1);
     assert!(q1[0].metadata.is_none());
     // expand_file_nodes should fetch metadata
@@ -92,3 +117,462 @@ fn test_persistent_roundtrip() {
         SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
     assert_eq!(loaded.get_total_files(), original_total);
 }
+
+#[test]
+fn test_snapshot_writes_the_same_cache_as_a_flush() {
+    let tmp = TempDir::new("persist_snapshot").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let snapshot_path = tmp.path().join("snapshot.zstd");
+    let cache_path = tmp.path().join("cache.zstd");
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let expected = cache.search("alpha").unwrap();
+    cache.expand_file_nodes(&expected);
+
+    let snapshot = cache.snapshot().unwrap();
+    assert!(!snapshot.is_empty());
+    snapshot.write_to_file(&snapshot_path).unwrap();
+    // The cache keeps answering, later changes aren't in the snapshot.
+    cache.search("beta").unwrap();
+    cache.flush_to_file(&cache_path).unwrap();
+
+    let from_snapshot =
+        SearchCache::try_read_persistent_cache(tmp.path(), &snapshot_path, None, None).unwrap();
+    let flushed =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(from_snapshot.get_total_files(), flushed.get_total_files());
+    assert_eq!(from_snapshot.search("alpha").unwrap(), expected);
+    assert_eq!(from_snapshot.suggest_queries("", 10), ["alpha"]);
+    assert_eq!(flushed.suggest_queries("", 10), ["alpha", "beta"]);
+    assert!(from_snapshot.file_nodes[expected[0]].metadata.is_some());
+}
+
+/// Walks a one-file tree under `name` and persists it.
+fn flushed_cache(name: &str) -> (TempDir, PathBuf) {
+    let tmp = TempDir::new(name).unwrap();
+    fs::write(tmp.path().join("a.bin"), b"data").unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    SearchCache::walk_fs(tmp.path().to_path_buf())
+        .flush_to_file(&cache_path)
+        .unwrap();
+    (tmp, cache_path)
+}
+
+#[test]
+fn test_flipped_byte_is_reported_as_corrupt() {
+    let (tmp, cache_path) = flushed_cache("persist_flipped");
+    let mut bytes = fs::read(&cache_path).unwrap();
+    let middle = bytes.len() / 2;
+    bytes[middle] ^= 0x40;
+    fs::write(&cache_path, &bytes).unwrap();
+    let loaded = SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None);
+    assert!(matches!(loaded, Err(CacheError::Corrupt { expected, actual }) if expected != actual));
+}
+
+#[test]
+fn test_truncated_cache_is_reported_as_corrupt() {
+    let (tmp, cache_path) = flushed_cache("persist_truncated");
+    let bytes = fs::read(&cache_path).unwrap();
+    fs::write(&cache_path, &bytes[..bytes.len() - 5]).unwrap();
+    let loaded = SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None);
+    assert!(matches!(loaded, Err(CacheError::Corrupt { .. })));
+    // Cut inside the envelope, there is no checksum left to compare.
+    fs::write(&cache_path, &bytes[..12]).unwrap();
+    let loaded = SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None);
+    assert!(matches!(loaded, Err(CacheError::Unusable(_))));
+}
+
+#[test]
+fn test_cache_without_envelope_still_loads() {
+    let (tmp, cache_path) = flushed_cache("persist_no_envelope");
+    let bytes = fs::read(&cache_path).unwrap();
+    assert_eq!(&bytes[..8], b"CRDNLCCH");
+    // Older files are the bare zstd stream that follows the envelope.
+    fs::write(&cache_path, &bytes[28..]).unwrap();
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.search("a.bin").unwrap().len(), 1);
+}
+
+#[test]
+fn test_search_records_query_history() {
+    let tmp = TempDir::new("history_record").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    cache.search("alpha").unwrap();
+    cache.search("alpha").unwrap();
+    cache.search("ext:txt").unwrap();
+    assert!(cache.search("regex:(").is_err());
+    assert_eq!(cache.query_history().len(), 2);
+    assert_eq!(cache.suggest_queries("", 10), ["alpha", "ext:txt"]);
+    assert_eq!(cache.suggest_queries("ext", 10), ["ext:txt"]);
+}
+
+#[test]
+fn test_skip_history_option() {
+    let tmp = TempDir::new("history_skip").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let opts = SearchOptions {
+        skip_history: true,
+        ..Default::default()
+    };
+    let outcome = cache
+        .search_with_options("alpha", opts, CancellationToken::noop())
+        .unwrap();
+    assert_eq!(outcome.nodes.len(), 1);
+    assert!(cache.query_history().is_empty());
+}
+
+#[test]
+fn test_query_history_survives_rescan_and_persistence() {
+    let tmp = TempDir::new("history_persist").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    cache.search("alpha").unwrap();
+    cache.search("alpha").unwrap();
+    cache.search("beta").unwrap();
+    cache.rescan();
+    assert_eq!(cache.suggest_queries("", 10), ["alpha", "beta"]);
+    cache.flush_to_file(&cache_path).unwrap();
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.suggest_queries("", 10), ["alpha", "beta"]);
+}
+
+#[test]
+fn test_cache_without_history_section_still_loads() {
+    let tmp = TempDir::new("history_missing").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let total = cache.get_total_files();
+    let (path, slab_root, slab, raw_names) = cache.file_nodes.into_parts();
+    let storage = PersistentStorage {
+        version: typed_num::Num,
+        last_event_id: 0,
+        path,
+        slab_root,
+        slab,
+        name_index: cache.name_index.into_persistent(),
+        raw_names,
+        query_history: QueryHistory::default(),
+        tombstones: Default::default(),
+        event_stream_uuid: None,
+        allocated_sizes: Vec::new(),
+        links: Vec::new(),
+        access_log: Default::default(),
+        first_seen: Default::default(),
+        tags: Default::default(),
+        file_flags: Vec::new(),
+    };
+    // Older cache files end right after the tree.
+    {
+        let output = fs::File::create(&cache_path).unwrap();
+        let output = zstd::Encoder::new(output, 1).unwrap().auto_finish();
+        postcard::to_io(&storage, output).unwrap();
+    }
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.get_total_files(), total);
+    assert!(loaded.query_history().is_empty());
+    assert!(loaded.tombstones().is_empty());
+}
+
+#[test]
+fn test_allocated_sizes_roundtrip() {
+    let tmp = TempDir::new("allocated_roundtrip").unwrap();
+    fs::write(tmp.path().join("alpha.bin"), vec![1u8; 64 * 1024]).unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let index = cache.search("disksize:>1kb").unwrap()[0];
+    let allocated = cache.node_metadata(index).as_ref().unwrap().allocated();
+    assert!(allocated.unwrap() >= 64 * 1024);
+    cache.flush_to_file(&cache_path).unwrap();
+
+    let storage = read_cache_from_file(&cache_path).unwrap();
+    // Folders get their metadata during the walk too.
+    assert!(
+        storage
+            .allocated_sizes
+            .contains(&(index, allocated.unwrap()))
+    );
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    let nodes = loaded.expand_file_nodes(&[index]);
+    assert_eq!(nodes[0].metadata.as_ref().unwrap().allocated(), allocated);
+}
+
+#[test]
+fn test_cache_without_allocated_sizes_refetches_them() {
+    let tmp = TempDir::new("allocated_missing").unwrap();
+    fs::write(tmp.path().join("alpha.bin"), vec![1u8; 64 * 1024]).unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let index = cache.search("size:>1kb").unwrap()[0];
+    let (path, slab_root, slab, raw_names) = cache.file_nodes.into_parts();
+    let storage = PersistentStorage {
+        version: typed_num::Num,
+        last_event_id: 0,
+        path,
+        slab_root,
+        slab,
+        name_index: cache.name_index.into_persistent(),
+        raw_names,
+        query_history: QueryHistory::default(),
+        tombstones: Default::default(),
+        event_stream_uuid: None,
+        allocated_sizes: Vec::new(),
+        links: Vec::new(),
+        access_log: Default::default(),
+        first_seen: Default::default(),
+        tags: Default::default(),
+        file_flags: Vec::new(),
+    };
+    // Files written before allocated sizes end right after the event stream UUID.
+    {
+        let output = fs::File::create(&cache_path).unwrap();
+        let mut output = zstd::Encoder::new(output, 1).unwrap().auto_finish();
+        postcard::to_io(&storage, &mut output).unwrap();
+        postcard::to_io(&storage.query_history, &mut output).unwrap();
+        postcard::to_io(&storage.tombstones, &mut output).unwrap();
+        postcard::to_io(&storage.event_stream_uuid, &mut output).unwrap();
+    }
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    let metadata = loaded.node_metadata(index);
+    assert_eq!(metadata.as_ref().unwrap().size(), 64 * 1024);
+    assert_eq!(metadata.as_ref().unwrap().allocated(), None);
+
+    assert_eq!(loaded.search("disksize:>1kb").unwrap(), [index]);
+    let metadata = loaded.node_metadata(index);
+    assert!(metadata.as_ref().unwrap().allocated().unwrap() >= 64 * 1024);
+}
+
+#[test]
+fn test_links_roundtrip() {
+    let tmp = TempDir::new("links_roundtrip").unwrap();
+    fs::write(tmp.path().join("alpha.bin"), b"a").unwrap();
+    fs::hard_link(tmp.path().join("alpha.bin"), tmp.path().join("beta.bin")).unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let mut nodes = cache.search("is:hardlinked").unwrap();
+    nodes.sort_unstable();
+    assert_eq!(nodes.len(), 2);
+    let inode = cache
+        .node_metadata(nodes[0])
+        .as_ref()
+        .unwrap()
+        .inode()
+        .unwrap();
+    cache.flush_to_file(&cache_path).unwrap();
+
+    let storage = read_cache_from_file(&cache_path).unwrap();
+    // Folders get their metadata during the walk too.
+    let file_links: Vec<_> = storage
+        .links
+        .iter()
+        .filter(|(index, ..)| nodes.contains(index))
+        .copied()
+        .collect();
+    assert_eq!(file_links, [(nodes[0], inode, 2), (nodes[1], inode, 2)]);
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.group_by_inode(&nodes), [nodes.clone()]);
+}
+
+#[test]
+fn test_cache_without_links_refetches_them() {
+    let tmp = TempDir::new("links_missing").unwrap();
+    fs::write(tmp.path().join("alpha.bin"), b"a").unwrap();
+    fs::hard_link(tmp.path().join("alpha.bin"), tmp.path().join("beta.bin")).unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let index = cache.search("alpha size:>0").unwrap()[0];
+    let (path, slab_root, slab, raw_names) = cache.file_nodes.into_parts();
+    let storage = PersistentStorage {
+        version: typed_num::Num,
+        last_event_id: 0,
+        path,
+        slab_root,
+        slab,
+        name_index: cache.name_index.into_persistent(),
+        raw_names,
+        query_history: QueryHistory::default(),
+        tombstones: Default::default(),
+        event_stream_uuid: None,
+        allocated_sizes: Vec::new(),
+        links: Vec::new(),
+        access_log: Default::default(),
+        first_seen: Default::default(),
+        tags: Default::default(),
+        file_flags: Vec::new(),
+    };
+    // Files written before links end right after the allocated sizes.
+    {
+        let output = fs::File::create(&cache_path).unwrap();
+        let mut output = zstd::Encoder::new(output, 1).unwrap().auto_finish();
+        postcard::to_io(&storage, &mut output).unwrap();
+        postcard::to_io(&storage.query_history, &mut output).unwrap();
+        postcard::to_io(&storage.tombstones, &mut output).unwrap();
+        postcard::to_io(&storage.event_stream_uuid, &mut output).unwrap();
+        postcard::to_io(&storage.allocated_sizes, &mut output).unwrap();
+    }
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    let metadata = loaded.node_metadata(index);
+    assert!(metadata.is_some());
+    assert_eq!(metadata.as_ref().unwrap().nlink(), None);
+
+    assert_eq!(loaded.search("alpha is:hardlinked").unwrap(), [index]);
+    let metadata = loaded.node_metadata(index);
+    assert_eq!(metadata.as_ref().unwrap().nlink(), Some(2));
+}
+
+#[test]
+fn test_event_stream_uuid_roundtrip() {
+    let tmp = TempDir::new("event_stream_roundtrip").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    SearchCache::walk_fs(tmp.path().to_path_buf())
+        .flush_to_file(&cache_path)
+        .unwrap();
+    let storage = read_cache_from_file(&cache_path).unwrap();
+    let current = event_stream_uuid(dev_of_path(tmp.path()).unwrap());
+    assert_eq!(storage.event_stream_uuid, current);
+    SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+}
+
+/// Write the cache of `root` with a made up event stream.
+fn write_with_event_stream(
+    root: &std::path::Path,
+    uuid: Option<[u8; 16]>,
+    last_event_id: u64,
+) -> PathBuf {
+    let cache_path = root.join("cache.zstd");
+    let cache = SearchCache::walk_fs(root.to_path_buf());
+    let (path, slab_root, slab, raw_names) = cache.file_nodes.into_parts();
+    let storage = PersistentStorage {
+        version: typed_num::Num,
+        last_event_id,
+        path,
+        slab_root,
+        slab,
+        name_index: cache.name_index.into_persistent(),
+        raw_names,
+        query_history: QueryHistory::default(),
+        tombstones: Default::default(),
+        event_stream_uuid: uuid,
+        allocated_sizes: Vec::new(),
+        links: Vec::new(),
+        access_log: Default::default(),
+        first_seen: Default::default(),
+        tags: Default::default(),
+        file_flags: Vec::new(),
+    };
+    write_cache_to_file(&cache_path, storage).unwrap();
+    cache_path
+}
+
+#[test]
+fn test_cache_from_another_event_stream_is_rejected() {
+    let tmp = TempDir::new("event_stream_mismatch").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    let cache_path = write_with_event_stream(tmp.path(), Some([0xab; 16]), 0);
+    let loaded = SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None);
+    // Volumes without an event history have nothing to compare against.
+    if event_stream_uuid(dev_of_path(tmp.path()).unwrap()).is_some() {
+        assert!(matches!(loaded, Err(CacheError::EventStreamReset)));
+    } else {
+        loaded.unwrap();
+    }
+}
+
+#[test]
+fn test_event_id_ahead_of_the_stream_is_rejected() {
+    let tmp = TempDir::new("event_stream_regressed").unwrap();
+    fs::write(tmp.path().join("alpha.txt"), b"a").unwrap();
+    // Older files have no UUID, only the event id can be probed.
+    let cache_path = write_with_event_stream(tmp.path(), None, u64::MAX);
+    let loaded = SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None);
+    assert!(matches!(loaded, Err(CacheError::EventStreamReset)));
+    let cache_path = write_with_event_stream(tmp.path(), None, 0);
+    SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+}
+
+#[test]
+fn test_check_event_stream() {
+    let a = Some([1; 16]);
+    let b = Some([2; 16]);
+    assert!(check_event_stream(a, 10, a, 20).is_ok());
+    assert!(check_event_stream(a, 20, a, 20).is_ok());
+    assert!(matches!(
+        check_event_stream(a, 10, b, 20),
+        Err(CacheError::EventStreamReset)
+    ));
+    assert!(matches!(
+        check_event_stream(a, 30, a, 20),
+        Err(CacheError::EventStreamReset)
+    ));
+    assert!(check_event_stream(None, 10, b, 20).is_ok());
+    assert!(check_event_stream(a, 10, None, 20).is_ok());
+    assert!(check_event_stream(None, 30, None, 20).is_err());
+}
+
+#[test]
+fn test_walk_totals_follow_the_walk() {
+    let tmp = TempDir::new("walk_totals").unwrap();
+    fs::create_dir(tmp.path().join("sub")).unwrap();
+    fs::write(tmp.path().join("a.bin"), [0u8; 30]).unwrap();
+    fs::write(tmp.path().join("sub/b.bin"), [0u8; 12]).unwrap();
+    let walk_data = crate::WalkData::simple(true);
+    let mut cache =
+        SearchCache::walk_fs_with_walk_data(tmp.path().to_path_buf(), &walk_data, None, None)
+            .unwrap();
+    let totals = cache.walk_totals().unwrap();
+    assert_eq!((totals.files, totals.dirs), (2, 2));
+    assert_eq!((totals.bytes, totals.unsized_files), (42, 0));
+
+    // Rescans walk without metadata: every file is counted, none is sized.
+    fs::write(tmp.path().join("c.bin"), [0u8; 5]).unwrap();
+    cache.rescan();
+    let totals = cache.walk_totals().unwrap();
+    assert_eq!(
+        (totals.files, totals.bytes, totals.unsized_files),
+        (3, 0, 3)
+    );
+
+    let cache_path = tmp.path().join("cache.zstd");
+    cache.flush_to_file(&cache_path).unwrap();
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.walk_totals(), None);
+}
+
+#[test]
+fn test_rewalk_moves_root_and_applies_ignore_paths() {
+    let tmp = TempDir::new("rewalk_root").unwrap();
+    fs::create_dir_all(tmp.path().join("first/skip")).unwrap();
+    fs::create_dir_all(tmp.path().join("second/skip")).unwrap();
+    fs::write(tmp.path().join("first/one.txt"), b"1").unwrap();
+    fs::write(tmp.path().join("second/two.txt"), b"2").unwrap();
+    fs::write(tmp.path().join("second/skip/hidden.txt"), b"h").unwrap();
+    let mut cache = SearchCache::walk_fs(tmp.path().join("first"));
+    cache.search("one").unwrap();
+
+    let second = tmp.path().join("second");
+    cache.set_ignore_paths(Some(vec![second.join("skip")]));
+    let walk_data = cache.walk_data();
+    cache
+        .rewalk_with_walk_data(second.clone(), &walk_data)
+        .unwrap();
+    assert_eq!(cache.root(), second);
+    assert!(cache.search("one").unwrap().is_empty());
+    assert_eq!(cache.search("two").unwrap().len(), 1);
+    assert!(cache.search("hidden").unwrap().is_empty());
+    assert_eq!(cache.suggest_queries("o", 10), ["one"]);
+
+    cache.set_ignore_paths(None);
+    cache.rescan();
+    assert_eq!(cache.search("hidden").unwrap().len(), 1);
+}
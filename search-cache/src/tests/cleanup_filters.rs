@@ -0,0 +1,153 @@
+use super::prelude::*;
+use crate::testkit::{TreeSpec, node_name};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::{os::unix::fs::symlink, path::Path};
+
+fn build_tree() -> (TempDir, SearchCache) {
+    let tmp = TreeSpec::new()
+        .dir("empty")
+        .file("full/a.txt")
+        .dir("outer/inner")
+        .file("target.txt")
+        .symlink("valid", "target.txt")
+        .symlink("dangling", "missing.txt")
+        .create("cleanup_filters");
+    // Absolute, so it can only be made once the temp folder exists.
+    symlink(tmp.path().join("gone.txt"), tmp.path().join("absolute")).unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    (tmp, cache)
+}
+
+fn sorted_names(cache: &SearchCache, query: &str) -> Vec<String> {
+    let mut names: Vec<String> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect();
+    names.sort();
+    names
+}
+
+fn event(cache: &mut SearchCache, path: &Path, flag: EventFlag) -> FsEvent {
+    FsEvent {
+        path: path.to_path_buf(),
+        id: cache.last_event_id() + 1,
+        flag,
+    }
+}
+
+#[test]
+fn test_empty_folders() {
+    let (tmp, cache) = build_tree();
+    assert_eq!(sorted_names(&cache, "is:emptyfolder"), ["empty", "inner"]);
+    assert_eq!(sorted_names(&cache, "is:EmptyFolder empty"), ["empty"]);
+
+    let outer = tmp.path().join("outer");
+    let query = format!("infolder:{} is:emptyfolder", outer.display());
+    assert_eq!(sorted_names(&cache, &query), ["inner"]);
+    assert_eq!(sorted_names(&cache, "full !is:emptyfolder"), ["full"]);
+    assert!(sorted_names(&cache, "inner !is:emptyfolder").is_empty());
+    // Files have no children either.
+    assert!(sorted_names(&cache, "a.txt is:emptyfolder").is_empty());
+}
+
+#[test]
+fn test_empty_folders_follow_events() {
+    let (tmp, mut cache) = build_tree();
+    let a = tmp.path().join("full/a.txt");
+    fs::remove_file(&a).unwrap();
+    let removed = event(
+        &mut cache,
+        &a,
+        EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert_eq!(
+        sorted_names(&cache, "is:emptyfolder"),
+        ["empty", "full", "inner"]
+    );
+
+    let b = tmp.path().join("empty/b.txt");
+    fs::write(&b, b"b").unwrap();
+    let created = event(
+        &mut cache,
+        &b,
+        EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(vec![created]).unwrap();
+    assert_eq!(sorted_names(&cache, "is:emptyfolder"), ["full", "inner"]);
+}
+
+#[test]
+fn test_broken_links() {
+    let (tmp, cache) = build_tree();
+    assert_eq!(
+        sorted_names(&cache, "is:brokenlink"),
+        ["absolute", "dangling"]
+    );
+    assert_eq!(sorted_names(&cache, "dang is:brokenlink"), ["dangling"]);
+    assert!(sorted_names(&cache, "target is:brokenlink").is_empty());
+
+    let outer = tmp.path().join("outer");
+    let query = format!("infolder:{} is:brokenlink", outer.display());
+    assert!(sorted_names(&cache, &query).is_empty());
+    let query = format!("infolder:{} is:brokenlink", tmp.path().display());
+    assert_eq!(sorted_names(&cache, &query), ["absolute", "dangling"]);
+    assert_eq!(sorted_names(&cache, "va !is:brokenlink"), ["valid"]);
+    // Only the symlinks were read.
+    assert_eq!(cache.link_checks.lock().unwrap().len(), 3);
+}
+
+#[test]
+fn test_broken_links_follow_their_targets() {
+    let (tmp, mut cache) = build_tree();
+    assert_eq!(
+        sorted_names(&cache, "is:brokenlink"),
+        ["absolute", "dangling"]
+    );
+
+    // Creating the missing target fixes the link without an event for it.
+    let missing = tmp.path().join("missing.txt");
+    fs::write(&missing, b"m").unwrap();
+    let created = event(
+        &mut cache,
+        &missing,
+        EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(vec![created]).unwrap();
+    assert_eq!(sorted_names(&cache, "is:brokenlink"), ["absolute"]);
+
+    // Removing a target breaks the link pointing at it.
+    let target = tmp.path().join("target.txt");
+    fs::remove_file(&target).unwrap();
+    let removed = event(
+        &mut cache,
+        &target,
+        EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert_eq!(sorted_names(&cache, "is:brokenlink"), ["absolute", "valid"]);
+
+    // Repointing a link is an event for the link itself.
+    let absolute = tmp.path().join("absolute");
+    fs::remove_file(&absolute).unwrap();
+    symlink(&missing, &absolute).unwrap();
+    let changed = event(
+        &mut cache,
+        &absolute,
+        EventFlag::ItemCreated | EventFlag::ItemIsSymlink,
+    );
+    cache.handle_fs_events(vec![changed]).unwrap();
+    assert_eq!(sorted_names(&cache, "is:brokenlink"), ["valid"]);
+}
+
+#[test]
+fn test_unknown_is_property_lists_all() {
+    let (_tmp, cache) = build_tree();
+    let error = cache.search("is:dusty").unwrap_err().to_string();
+    assert!(
+        error.contains("expected hardlinked, new, emptyfolder, brokenlink or hidden"),
+        "{error}"
+    );
+}
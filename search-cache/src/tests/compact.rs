@@ -0,0 +1,161 @@
+use super::prelude::*;
+use crate::{IndexRemap, SetIndex, SlabIndex, query_history::unix_now, testkit::TreeSpec};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::path::Path;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    let dropped = ["a", "b", "c"]
+        .into_iter()
+        .flat_map(|folder| (0..500).map(move |i| format!("drop/{folder}/d{i}.txt")));
+    TreeSpec::new()
+        .file("keep/sub/s.md")
+        .files((0..300).map(|i| format!("keep/k{i}.txt")))
+        .files(dropped)
+        .index("compact")
+}
+
+fn remove_drop(tmp: &TempDir, cache: &mut SearchCache) {
+    let drop = tmp.path().join("drop");
+    fs::remove_dir_all(&drop).unwrap();
+    let removed = FsEvent {
+        path: drop,
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsDir,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+}
+
+fn result_paths(cache: &SearchCache, query: &str) -> Vec<Option<PathBuf>> {
+    cache.node_paths(&cache.search(query).unwrap())
+}
+
+fn index_of(cache: &SearchCache, path: &Path) -> SlabIndex {
+    cache.node_index_for_raw_path(path).unwrap()
+}
+
+#[test]
+fn test_compact_keeps_results() {
+    let (tmp, mut cache) = build_tree();
+    remove_drop(&tmp, &mut cache);
+    let queries = ["txt", "k1", "ext:md", "sub", "d1"];
+    let before: Vec<_> = queries.iter().map(|q| result_paths(&cache, q)).collect();
+    let survivors: Vec<SlabIndex> = cache.search_empty(CancellationToken::noop()).unwrap();
+    let survivor_paths = cache.node_paths(&survivors);
+    let nodes = cache.file_nodes.len();
+    let slots = cache.file_nodes.slots();
+    assert!(slots > nodes);
+    assert!(cache.hole_ratio() > 0.5);
+
+    let stats = cache.compact();
+    assert_eq!(stats.slots_before, slots);
+    assert_eq!(stats.slots_after, nodes);
+    assert_eq!(stats.slots_reclaimed(), slots - nodes);
+    assert!(stats.bytes_reclaimed > 0);
+    assert_eq!(cache.file_nodes.len(), nodes);
+    assert_eq!(cache.file_nodes.slots(), nodes);
+    assert_eq!(cache.hole_ratio(), 0.0);
+
+    let after: Vec<_> = queries.iter().map(|q| result_paths(&cache, q)).collect();
+    assert_eq!(after, before);
+    assert_eq!(
+        cache.search_empty(CancellationToken::noop()).unwrap().len(),
+        survivors.len()
+    );
+
+    // Every survivor is where the remap says, at the same path.
+    let remapped: Vec<SlabIndex> = survivors
+        .iter()
+        .map(|&old| stats.remap.get(old).unwrap())
+        .collect();
+    assert_eq!(cache.node_paths(&remapped), survivor_paths);
+    assert!(remapped.iter().all(|index| index.get() < nodes));
+    assert_eq!(stats.remap.get(SlabIndex::new(slots + 10)), None);
+
+    // Lookups walk the rewritten children, and the tree keeps taking events.
+    let k7 = tmp.path().join("keep/k7.txt");
+    assert_eq!(cache.node_path(index_of(&cache, &k7)), Some(k7));
+    let new_file = tmp.path().join("keep/sub/new.txt");
+    fs::write(&new_file, b"n").unwrap();
+    let created = FsEvent {
+        path: new_file.clone(),
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![created]).unwrap();
+    assert_eq!(result_paths(&cache, "new.txt"), [Some(new_file)]);
+    assert_eq!(cache.file_nodes.slots(), nodes + 1);
+}
+
+#[test]
+fn test_compact_remaps_dropped_and_side_state() {
+    let (tmp, mut cache) = build_tree();
+    let dropped = index_of(&cache, &tmp.path().join("drop/b/d3.txt"));
+    let k5 = tmp.path().join("keep/k5.txt");
+    cache.record_access(index_of(&cache, &k5));
+    cache.ensure_metadata(index_of(&cache, &k5));
+    cache.ensure_metadata(dropped);
+    assert!(cache.lock_metadata_extras().contains_key(&dropped));
+    remove_drop(&tmp, &mut cache);
+    assert!(!cache.lock_metadata_extras().contains_key(&dropped));
+
+    let stats = cache.compact();
+    assert_eq!(stats.remap.get(dropped), None);
+    let k5_index = index_of(&cache, &k5);
+    assert_eq!(cache.access_log().len(), 1);
+    assert!(cache.access_log().score_at(k5_index, unix_now()) > 0.0);
+    let metadata = cache.node_metadata(k5_index);
+    assert!(metadata.as_ref().unwrap().allocated().is_some());
+    let root = cache.file_nodes.root();
+    assert_eq!(cache.node_path(root), Some(tmp.path().to_path_buf()));
+
+    // A compact slab has nothing to give back.
+    let again = cache.compact();
+    assert_eq!(again.slots_reclaimed(), 0);
+    assert_eq!(again.remap.get(k5_index), Some(k5_index));
+}
+
+#[test]
+fn test_auto_compact_after_events() {
+    let (tmp, mut cache) = build_tree();
+    assert_eq!(cache.auto_compact(), None);
+    cache.set_auto_compact(Some(0.5));
+    let k1 = tmp.path().join("keep/k1.txt");
+    let old_k1 = index_of(&cache, &k1);
+
+    // Too few holes.
+    fs::remove_file(tmp.path().join("keep/k0.txt")).unwrap();
+    let removed = FsEvent {
+        path: tmp.path().join("keep/k0.txt"),
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert!(cache.take_compaction().is_none());
+    assert!(cache.hole_ratio() > 0.0);
+
+    remove_drop(&tmp, &mut cache);
+    let stats = cache.take_compaction().unwrap();
+    assert_eq!(cache.hole_ratio(), 0.0);
+    assert_eq!(stats.remap.get(old_k1), Some(index_of(&cache, &k1)));
+    assert!(cache.take_compaction().is_none());
+}
+
+#[test]
+fn test_remap_set_indexes() {
+    let (tmp, mut cache) = build_tree();
+    let k1 = index_of(&cache, &tmp.path().join("keep/k1.txt"));
+    let d1 = index_of(&cache, &tmp.path().join("drop/a/d1.txt"));
+    remove_drop(&tmp, &mut cache);
+    let stats = cache.compact();
+    let other = SetIndex::new(1, d1);
+    let mut nodes = vec![SetIndex::new(0, k1), SetIndex::new(0, d1), other];
+    stats.remap.apply_to_set(0, &mut nodes);
+    assert_eq!(
+        nodes,
+        [SetIndex::new(0, stats.remap.get(k1).unwrap()), other]
+    );
+
+    let mut nodes = vec![SetIndex::new(0, k1)];
+    IndexRemap::default().apply_to_set(0, &mut nodes);
+    assert!(nodes.is_empty());
+}
@@ -0,0 +1,242 @@
+use super::prelude::*;
+use crate::{
+    CURSOR_CAPACITY, CURSOR_MEMORY_BUDGET, CacheSet, CursorId, SearchError, SearchOptions,
+    SearchResultNode, SlabIndex, query::METADATA_STATS, testkit::TreeSpec,
+};
+use cardinal_sdk::{EventFlag, FsEvent};
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files((0..30).map(|i| format!("docs/f{i:02}.txt")))
+        .file("other.md")
+        .index("cursors")
+}
+
+fn open(cache: &SearchCache, query: &str) -> CursorId {
+    cache
+        .open_cursor(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+}
+
+fn paths(nodes: Vec<SearchResultNode>) -> Vec<PathBuf> {
+    nodes.into_iter().map(|node| node.path).collect()
+}
+
+fn page(cache: &SearchCache, cursor: CursorId, offset: usize, limit: usize) -> Vec<PathBuf> {
+    paths(cache.cursor_page(cursor, offset, limit).unwrap().unwrap())
+}
+
+fn stats() -> usize {
+    METADATA_STATS.with(|stats| stats.get())
+}
+
+#[test]
+fn test_pages_split_the_results_in_order() {
+    let (_tmp, cache) = build_tree();
+    let cursor = open(&cache, "txt");
+    let expected = paths(
+        cache
+            .query_files("txt".to_string(), CancellationToken::noop())
+            .unwrap(),
+    );
+    assert_eq!(cache.cursor_len(cursor), Some(30));
+
+    let mut paged = Vec::new();
+    for offset in (0..30).step_by(7) {
+        paged.extend(page(&cache, cursor, offset, 7));
+    }
+    assert_eq!(paged, expected);
+    // The same page again, and the ends clamped.
+    assert_eq!(page(&cache, cursor, 7, 7), expected[7..14]);
+    assert_eq!(page(&cache, cursor, 28, 10), expected[28..]);
+    assert!(page(&cache, cursor, 30, 10).is_empty());
+    assert!(page(&cache, cursor, usize::MAX, usize::MAX).is_empty());
+}
+
+#[test]
+fn test_pages_carry_highlights_and_display_paths() {
+    let (tmp, cache) = build_tree();
+    let options = SearchOptions::default()
+        .with_collect_highlights(true)
+        .with_path_display(crate::PathDisplay::RootRelative);
+    let cursor = cache
+        .open_cursor("f07", options, CancellationToken::noop())
+        .unwrap();
+    let nodes = cache.cursor_page(cursor, 0, 10).unwrap().unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].path, tmp.path().join("docs/f07.txt"));
+    assert_eq!(nodes[0].display_path, "docs/f07.txt");
+    assert_eq!(nodes[0].highlights, Some(vec![(0, 3)]));
+}
+
+#[test]
+fn test_least_recently_used_cursor_is_evicted() {
+    let (_tmp, cache) = build_tree();
+    let cursors: Vec<_> = (0..CURSOR_CAPACITY).map(|_| open(&cache, "txt")).collect();
+    // Paging the oldest makes the second one the least recently used.
+    assert_eq!(page(&cache, cursors[0], 0, 1).len(), 1);
+    let newest = open(&cache, "md");
+    assert!(cache.cursor_page(cursors[1], 0, 1).unwrap().is_none());
+    assert_eq!(cache.cursor_len(cursors[1]), None);
+    for cursor in [cursors[0], cursors[2], newest] {
+        assert!(cache.cursor_page(cursor, 0, 1).unwrap().is_some());
+    }
+
+    assert!(cache.close_cursor(newest));
+    assert!(!cache.close_cursor(newest));
+    assert!(cache.cursor_page(newest, 0, 1).unwrap().is_none());
+}
+
+#[test]
+fn test_cursors_beyond_the_memory_budget_are_evicted() {
+    let (_tmp, mut cache) = build_tree();
+    assert_eq!(cache.cursor_memory_budget(), CURSOR_MEMORY_BUDGET);
+    let results = size_of::<SlabIndex>() * 30;
+    cache.set_cursor_memory_budget(results * 2);
+    let first = open(&cache, "txt");
+    let second = open(&cache, "txt");
+    assert_eq!(cache.cursor_memory(), results * 2);
+    // A third doesn't fit with both, the least recently paged goes.
+    assert_eq!(page(&cache, first, 0, 1).len(), 1);
+    let third = open(&cache, "txt");
+    assert_eq!(cache.cursor_len(second), None);
+    assert_eq!(cache.cursor_len(first), Some(30));
+    assert_eq!(cache.cursor_memory(), results * 2);
+
+    // Small cursors still fit beside a big one, reading a length counts as use.
+    let small = open(&cache, "md");
+    assert_eq!(cache.cursor_len(small), Some(1));
+    assert_eq!(cache.cursor_len(third), None);
+    assert_eq!(cache.cursor_len(first), Some(30));
+
+    // Only the most recent cursor is left when the budget shrinks below it.
+    cache.set_cursor_memory_budget(0);
+    assert_eq!(cache.cursor_len(first), Some(30));
+    assert_eq!(cache.cursor_len(small), None);
+    let last = open(&cache, "md");
+    assert_eq!(cache.cursor_len(first), None);
+    assert_eq!(cache.cursor_len(last), Some(1));
+    assert!(cache.close_cursor(last));
+    assert_eq!(cache.cursor_memory(), 0);
+}
+
+#[test]
+fn test_events_invalidate_cursors() {
+    let (tmp, mut cache) = build_tree();
+    let cursor = open(&cache, "txt");
+    let path = tmp.path().join("docs/new.txt");
+    fs::write(&path, b"x").unwrap();
+    let id = cache.last_event_id() + 1;
+    cache
+        .handle_fs_events(vec![FsEvent {
+            path,
+            id,
+            flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+        }])
+        .unwrap();
+    assert!(matches!(
+        cache.cursor_page(cursor, 0, 10),
+        Err(SearchError::CursorInvalidated)
+    ));
+    // The cursor is still open, a new one sees the new file.
+    assert_eq!(cache.cursor_len(cursor), Some(30));
+    let cursor = open(&cache, "txt");
+    assert_eq!(page(&cache, cursor, 0, 100).len(), 31);
+
+    // Nothing to apply leaves cursors alone.
+    cache.handle_fs_events(Vec::new()).unwrap();
+    assert_eq!(page(&cache, cursor, 0, 100).len(), 31);
+
+    // Renumbering the nodes invalidates them as well.
+    cache.compact();
+    assert!(matches!(
+        cache.cursor_page(cursor, 0, 10),
+        Err(SearchError::CursorInvalidated)
+    ));
+
+    let cursor = open(&cache, "txt");
+    cache.rescan();
+    assert!(matches!(
+        cache.cursor_page(cursor, 0, 10),
+        Err(SearchError::CursorInvalidated)
+    ));
+}
+
+#[test]
+fn test_metadata_is_fetched_per_page() {
+    let (_tmp, cache) = build_tree();
+    let before = stats();
+    let cursor = open(&cache, "txt");
+    assert_eq!(stats(), before, "opening doesn't stat");
+
+    let nodes = cache.cursor_page(cursor, 0, 5).unwrap().unwrap();
+    assert!(nodes.iter().all(|node| node.metadata.is_some()));
+    assert_eq!(stats(), before + 5);
+    cache.cursor_page(cursor, 5, 5).unwrap();
+    assert_eq!(stats(), before + 10);
+    // Fetched metadata is kept.
+    cache.cursor_page(cursor, 0, 10).unwrap();
+    assert_eq!(stats(), before + 10);
+}
+
+#[test]
+fn test_partial_searches_open_no_cursor() {
+    let (_tmp, cache) = build_tree();
+    let token = CancellationToken::noop().with_deadline(std::time::Instant::now());
+    assert!(matches!(
+        cache.open_cursor("txt", SearchOptions::default(), token),
+        Err(SearchError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_cache_set_cursors() {
+    let (tmp, cache) = build_tree();
+    let other = TempDir::new("cursors_other").unwrap();
+    fs::write(other.path().join("more.txt"), b"x").unwrap();
+    let mut set = CacheSet::new();
+    set.insert(cache, tmp.path().join("cache.zstd")).unwrap();
+    set.insert(
+        SearchCache::walk_fs(other.path().to_path_buf()),
+        other.path().join("cache.zstd"),
+    )
+    .unwrap();
+
+    let cursor = set
+        .open_cursor(
+            "txt",
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert_eq!(set.cursor_len(cursor), Some(31));
+    let first = set.cursor_page(cursor, 0, 30).unwrap().unwrap();
+    let rest = set.cursor_page(cursor, 30, 30).unwrap().unwrap();
+    assert_eq!(rest.len(), 1);
+    assert_eq!(rest[0].path, other.path().join("more.txt"));
+    assert_eq!(rest[0].source, 1);
+    assert!(first.iter().all(|node| node.source == 0));
+
+    // A member changing, or leaving, invalidates the set's cursors.
+    let path = other.path().join("later.txt");
+    fs::write(&path, b"x").unwrap();
+    let id = set.member(1).unwrap().last_event_id + 1;
+    set.handle_fs_events(vec![FsEvent {
+        path,
+        id,
+        flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    }]);
+    assert!(matches!(
+        set.cursor_page(cursor, 0, 1),
+        Err(SearchError::CursorInvalidated)
+    ));
+    let cursor = set.open_cursor_over(Vec::new());
+    assert!(set.cursor_page(cursor, 0, 1).unwrap().unwrap().is_empty());
+    set.remove(1);
+    assert!(matches!(
+        set.cursor_page(cursor, 0, 1),
+        Err(SearchError::CursorInvalidated)
+    ));
+    assert!(set.close_cursor(cursor));
+}
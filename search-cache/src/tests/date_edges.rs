@@ -1,10 +1,8 @@
 //! Additional edge & boundary tests for date filters.
 //! Focus: open ranges, leap day, ambiguous formats, boolean combos.
 
-use super::{
-    prelude::*,
-    support::{SECONDS_PER_DAY, set_file_times, ts_for_date},
-};
+use super::{prelude::*, support::assert_file_hits};
+use crate::testkit::{SECONDS_PER_DAY, set_file_times, ts_for_date};
 
 #[test]
 fn explicit_range_instead_of_open_range() {
@@ -176,3 +174,26 @@ fn single_point_range_equivalence() {
     let range_hits = cache.search("dm:2024-05-10-2024-05-10").unwrap();
     assert_eq!(eq_hits.len(), range_hits.len());
 }
+
+#[test]
+fn missing_creation_time_is_unmatched() {
+    // Some volumes (SMB, FUSE) can't report a creation time at all.
+    let tmp = TempDir::new("missing_ctime").unwrap();
+    fs::write(tmp.path().join("smb.txt"), b"x").unwrap();
+    fs::write(tmp.path().join("local.txt"), b"x").unwrap();
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let smb = cache.search("smb.txt").unwrap()[0];
+    let local = cache.search("local.txt").unwrap()[0];
+    let day = ts_for_date(2024, 5, 1);
+    set_file_times(&mut cache, smb, 0, day);
+    set_file_times(&mut cache, local, day, day);
+
+    let hits = cache.search("dc:2024-05-01").unwrap();
+    assert_file_hits(&cache, &hits, &["local.txt"]);
+    let hits = cache.search("dc:<2025-01-01").unwrap();
+    assert_file_hits(&cache, &hits, &["local.txt"]);
+    let hits = cache.search("dm:2024-05-01").unwrap();
+    assert_file_hits(&cache, &hits, &["local.txt", "smb.txt"]);
+    let hits = cache.search("dc:2024-05-01 OR dm:2024-05-01").unwrap();
+    assert_file_hits(&cache, &hits, &["local.txt", "smb.txt"]);
+}
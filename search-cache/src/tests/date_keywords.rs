@@ -1,7 +1,5 @@
-use super::{
-    prelude::*,
-    support::{SECONDS_PER_DAY, assert_file_hits, set_file_times, ts_for_date},
-};
+use super::{prelude::*, support::assert_file_hits};
+use crate::testkit::{SECONDS_PER_DAY, set_file_times, ts_for_date};
 
 #[test]
 fn test_date_filters_cover_keywords_and_ranges() {
@@ -2,9 +2,9 @@
 //! Added in ~200 line segments to exceed 2000 lines in a single file.
 //! Segments exercise: keyword dates, ranges, comparisons, inequality, formats, metadata loading.
 
-use super::{
-    prelude::*,
-    support::{SECONDS_PER_DAY, list_file_names as list_names, set_file_times, ts_for_date as ts},
+use super::prelude::*;
+use crate::testkit::{
+    SECONDS_PER_DAY, list_file_names as list_names, set_file_times, ts_for_date as ts,
 };
 use jiff::{civil::Date, tz::TimeZone};
 
@@ -443,7 +443,7 @@ fn segment_8_created_vs_modified() {
 // Error conditions (invalid, reversed range, empty value) should result in parse/eval errors.
 #[test]
 fn segment_9_error_conditions() {
-    let mut cache = SearchCache::walk_fs(TempDir::new("seg9_errors").unwrap().path().to_path_buf());
+    let cache = SearchCache::walk_fs(TempDir::new("seg9_errors").unwrap().path().to_path_buf());
     // reversed range
     let reversed = cache.search("dm:2024-10-10-2024-09-10");
     assert!(reversed.is_err(), "reversed date range should error");
@@ -492,7 +492,7 @@ fn segment_10_stress_queries() {
             token,
         )
         .unwrap();
-    assert!(outcome.nodes.unwrap().len() >= 50); // all 50 within range
+    assert!(outcome.nodes.len() >= 50); // all 50 within range
 }
 
 // Segment 11 ----------------------------------------------------------------
@@ -554,3 +554,73 @@ fn segment_11_created_keyword_filters() {
     let dm_lastweek_names = list_names(&cache, &dm_lastweek_hits);
     assert!(dm_lastweek_names.is_empty());
 }
+
+fn set_accessed(path: &std::path::Path, timestamp: i64) {
+    let time = std::time::UNIX_EPOCH + std::time::Duration::from_secs(timestamp as u64);
+    let file = fs::File::options().write(true).open(path).unwrap();
+    file.set_times(fs::FileTimes::new().set_accessed(time))
+        .unwrap();
+}
+
+// Segment 12 ----------------------------------------------------------------
+// Accessed vs Modified field distinction; da: reads the access time from disk.
+#[test]
+fn segment_12_accessed_vs_modified() {
+    let tmp = TempDir::new("seg12_accessed_modified").unwrap();
+    let long_ago = tmp.path().join("opened_long_ago.txt");
+    let recently = tmp.path().join("opened_recently.txt");
+    fs::write(&long_ago, b"x").unwrap();
+    fs::write(&recently, b"x").unwrap();
+    set_accessed(&long_ago, ts(2024, 3, 10));
+    set_accessed(&recently, ts(2024, 9, 20));
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let long_ago_idx = cache.search("opened_long_ago.txt").unwrap()[0];
+    let recently_idx = cache.search("opened_recently.txt").unwrap()[0];
+    // The other way around for created/modified, so a mixed up field shows.
+    set_file_times(&mut cache, long_ago_idx, ts(2024, 9, 20), ts(2024, 9, 20));
+    set_file_times(&mut cache, recently_idx, ts(2024, 3, 10), ts(2024, 3, 10));
+
+    let da_hits = cache.search("da:2024-03-10").unwrap();
+    assert_eq!(list_names(&cache, &da_hits), vec!["opened_long_ago.txt"]);
+    let da_range = cache.search("da:2024-09-01-2024-09-30").unwrap();
+    assert_eq!(list_names(&cache, &da_range), vec!["opened_recently.txt"]);
+    let da_before = cache.search("da:<2024-06-01").unwrap();
+    assert_eq!(list_names(&cache, &da_before), vec!["opened_long_ago.txt"]);
+    let dm_hits = cache.search("dm:2024-03-10").unwrap();
+    assert_eq!(list_names(&cache, &dm_hits), vec!["opened_recently.txt"]);
+    let dc_hits = cache
+        .search("dateaccessed:2024-09-20 dc:2024-09-20")
+        .unwrap();
+    assert!(list_names(&cache, &dc_hits).is_empty());
+}
+
+// Segment 13 ----------------------------------------------------------------
+// Added vs Created field distinction; dt: falls back to created without an added date.
+#[test]
+fn segment_13_added_vs_created() {
+    let tmp = TempDir::new("seg13_added_created").unwrap();
+    let path = tmp.path().join("downloaded.txt");
+    fs::write(&path, b"x").unwrap();
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let idx = cache.search("downloaded.txt").unwrap()[0];
+    // A file created long ago and only just copied here.
+    set_file_times(&mut cache, idx, ts(2020, 1, 15), ts(2020, 1, 15));
+
+    let dc_hits = cache.search("dc:2020-01-15").unwrap();
+    assert_eq!(list_names(&cache, &dc_hits), vec!["downloaded.txt"]);
+    let dc_today = cache.search("dc:today").unwrap();
+    assert!(list_names(&cache, &dc_today).is_empty());
+
+    let recorded = cardinal_sdk::date_added_of_path(&path).unwrap().is_some();
+    let (added_day, other_day) = if recorded {
+        ("dt:today", "dt:2020-01-15")
+    } else {
+        ("dt:2020-01-15", "dt:today")
+    };
+    let dt_hits = cache.search(added_day).unwrap();
+    assert_eq!(list_names(&cache, &dt_hits), vec!["downloaded.txt"]);
+    let dt_other = cache.search(other_day).unwrap();
+    assert!(list_names(&cache, &dt_other).is_empty());
+    let dt_range = cache.search("dateadded:2020-01-01-2020-01-31").unwrap();
+    assert_eq!(list_names(&cache, &dt_range).is_empty(), recorded);
+}
@@ -0,0 +1,206 @@
+use super::prelude::*;
+use crate::{
+    SnapshotNames,
+    testkit::{node_name, set_file_times, ts_for_date},
+};
+use std::path::Path;
+
+const QUERIES: &[&str] = &[
+    "report",
+    "*.md",
+    "notes docs",
+    "ext:txt",
+    "ext:md;rs",
+    "size:>1kb",
+    "size:empty",
+    "type:code",
+    "folder:",
+    "dm:2024-05-01-2024-05-15",
+    "dc:<2020-01-01",
+    "!ext:txt size:<1kb",
+];
+
+/// Tree with a `project` folder to export and a sibling left out of it.
+fn project_tree(tmp: &TempDir) -> SearchCache {
+    let project = tmp.path().join("project");
+    fs::create_dir_all(project.join("docs/drafts")).unwrap();
+    fs::create_dir_all(project.join("src")).unwrap();
+    fs::write(project.join("report.txt"), vec![b'r'; 4096]).unwrap();
+    fs::write(project.join("docs/notes.md"), b"notes").unwrap();
+    fs::write(project.join("docs/drafts/report.md"), vec![b'd'; 2048]).unwrap();
+    fs::write(project.join("docs/drafts/empty.txt"), b"").unwrap();
+    fs::write(project.join("src/main.rs"), b"fn main() {}").unwrap();
+    fs::write(project.join(".hidden"), b"x").unwrap();
+    fs::write(project.join("may.log"), b"").unwrap();
+    fs::write(project.join("old.log"), b"").unwrap();
+    fs::write(tmp.path().join("report-outside.txt"), vec![b'o'; 4096]).unwrap();
+
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let may = cache.search("may.log").unwrap()[0];
+    let may_10 = ts_for_date(2024, 5, 10);
+    set_file_times(&mut cache, may, may_10, may_10);
+    let old = cache.search("old.log").unwrap()[0];
+    set_file_times(
+        &mut cache,
+        old,
+        ts_for_date(2010, 1, 1),
+        ts_for_date(2011, 1, 1),
+    );
+    cache
+}
+
+fn export(cache: &SearchCache, scope: &Path, names: SnapshotNames) -> Vec<u8> {
+    let scope = cache.node_index_for_raw_path(scope).unwrap();
+    let mut out = Vec::new();
+    cache.export_debug_snapshot(scope, names, &mut out).unwrap();
+    out
+}
+
+fn result_paths(cache: &SearchCache, query: &str, under: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .filter_map(|index| cache.node_path(index))
+        .filter(|path| path.starts_with(under))
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// Extensions of the matched names, with their lengths in characters.
+fn result_shapes(cache: &SearchCache, query: &str) -> Vec<(Option<String>, usize)> {
+    let mut shapes: Vec<_> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .filter_map(|index| cache.node_path(index))
+        .map(|path| {
+            let name = path.file_name().unwrap().to_string_lossy().into_owned();
+            let extension = path
+                .extension()
+                .map(|extension| extension.to_string_lossy().into_owned());
+            (extension, name.chars().count())
+        })
+        .collect();
+    shapes.sort();
+    shapes
+}
+
+#[test]
+fn test_plain_snapshot_reproduces_queries() {
+    let tmp = TempDir::new("debug_snapshot_plain").unwrap();
+    let cache = project_tree(&tmp);
+    let project = tmp.path().join("project");
+
+    let snapshot = export(&cache, &project, SnapshotNames::Plain);
+    let imported = SearchCache::import_debug_snapshot(snapshot.as_slice()).unwrap();
+
+    assert_eq!(imported.root(), project);
+    // project, docs, drafts, src and their eight files.
+    assert_eq!(imported.get_total_files(), 12);
+    imported.verify_integrity().unwrap();
+    imported.verify_name_index().unwrap();
+    for query in QUERIES {
+        assert_eq!(
+            result_paths(&imported, query, &project),
+            result_paths(&cache, query, &project),
+            "query {query:?}"
+        );
+    }
+    assert!(imported.search("report-outside").unwrap().is_empty());
+}
+
+#[test]
+fn test_imported_snapshot_does_not_read_the_disk() {
+    let tmp = TempDir::new("debug_snapshot_offline").unwrap();
+    let cache = project_tree(&tmp);
+    let project = tmp.path().join("project");
+    let snapshot = export(&cache, &project, SnapshotNames::Plain);
+    let expected = result_paths(&cache, "size:>1kb", &project);
+
+    fs::remove_dir_all(&project).unwrap();
+    let imported = SearchCache::import_debug_snapshot(snapshot.as_slice()).unwrap();
+    assert_eq!(result_paths(&imported, "size:>1kb", &project), expected);
+}
+
+#[test]
+fn test_snapshot_export_is_deterministic() {
+    let tmp = TempDir::new("debug_snapshot_deterministic").unwrap();
+    let cache = project_tree(&tmp);
+    let project = tmp.path().join("project");
+
+    assert_eq!(
+        export(&cache, &project, SnapshotNames::Plain),
+        export(&cache, &project, SnapshotNames::Plain)
+    );
+    let hashed = export(&cache, &project, SnapshotNames::Hashed { key: 7 });
+    assert_eq!(
+        hashed,
+        export(&cache, &project, SnapshotNames::Hashed { key: 7 })
+    );
+    assert_ne!(
+        hashed,
+        export(&cache, &project, SnapshotNames::Hashed { key: 8 })
+    );
+}
+
+#[test]
+fn test_hashed_snapshot_keeps_extensions_sizes_and_dates() {
+    let tmp = TempDir::new("debug_snapshot_hashed").unwrap();
+    let cache = project_tree(&tmp);
+    let project = tmp.path().join("project");
+
+    let snapshot = export(&cache, &project, SnapshotNames::Hashed { key: 42 });
+    let text = String::from_utf8(snapshot.clone()).unwrap();
+    for secret in [
+        "report",
+        "notes",
+        "drafts",
+        "project",
+        "debug_snapshot_hashed",
+    ] {
+        assert!(!text.contains(secret), "{secret} leaked into {text}");
+    }
+
+    let imported = SearchCache::import_debug_snapshot(snapshot.as_slice()).unwrap();
+    assert_eq!(imported.get_total_files(), 12);
+    imported.verify_integrity().unwrap();
+    imported.verify_name_index().unwrap();
+    for query in [
+        "ext:txt",
+        "ext:md;rs",
+        "size:>1kb",
+        "size:empty",
+        "folder:",
+        "dm:2024-05-01-2024-05-15",
+        "dc:<2020-01-01",
+    ] {
+        let original = cache
+            .search(query)
+            .unwrap()
+            .into_iter()
+            .filter(|&index| cache.node_path(index).unwrap().starts_with(&project))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            imported.search(query).unwrap().len(),
+            original.len(),
+            "query {query:?}"
+        );
+    }
+    // `.hidden` keeps its dot, `report.txt` its extension and length.
+    assert_eq!(
+        result_shapes(&imported, "ext:txt size:>3kb"),
+        vec![(Some("txt".to_string()), "report.txt".len())]
+    );
+    assert_eq!(imported.search("report").unwrap(), Vec::new());
+    let dotted: Vec<String> = imported
+        .search_empty(CancellationToken::noop())
+        .unwrap()
+        .into_iter()
+        .map(|index| node_name(&imported, index))
+        .filter(|name| name.starts_with('.'))
+        .collect();
+    assert_eq!(dotted.len(), 1);
+    assert_eq!(dotted[0].len(), ".hidden".len());
+}
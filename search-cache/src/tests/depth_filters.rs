@@ -0,0 +1,155 @@
+use super::prelude::*;
+use crate::testkit::TreeSpec;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["a.txt", "one/b.txt", "one/two/c.txt", "one/two/three/d.txt"])
+        .index("depth_filters")
+}
+
+/// Paths of the hits relative to the temp dir, sorted.
+fn hits(tmp: &TempDir, cache: &SearchCache, query: &str) -> Vec<String> {
+    let mut paths: Vec<String> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .filter_map(|index| cache.node_path(index))
+        .filter_map(|path| {
+            path.strip_prefix(tmp.path())
+                .ok()
+                .map(|path| path.to_string_lossy().into_owned())
+        })
+        .filter(|path| !path.is_empty())
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_depth_counts_from_search_root() {
+    let (tmp, cache) = build_tree();
+    assert_eq!(hits(&tmp, &cache, "depth:1"), ["a.txt", "one"]);
+    assert_eq!(
+        hits(&tmp, &cache, "depth:2"),
+        ["a.txt", "one", "one/b.txt", "one/two"]
+    );
+    assert_eq!(
+        hits(&tmp, &cache, ".txt depth:3"),
+        ["a.txt", "one/b.txt", "one/two/c.txt"]
+    );
+}
+
+#[test]
+fn test_depth_comparisons_and_ranges() {
+    let (tmp, cache) = build_tree();
+    assert_eq!(hits(&tmp, &cache, ".txt depth:=2"), ["one/b.txt"]);
+    assert_eq!(hits(&tmp, &cache, ".txt depth:<2"), ["a.txt"]);
+    assert_eq!(hits(&tmp, &cache, ".txt depth:<=2"), ["a.txt", "one/b.txt"]);
+    assert_eq!(
+        hits(&tmp, &cache, ".txt depth:>2"),
+        ["one/two/c.txt", "one/two/three/d.txt"]
+    );
+    assert_eq!(
+        hits(&tmp, &cache, ".txt depth:>=4"),
+        ["one/two/three/d.txt"]
+    );
+    assert_eq!(
+        hits(&tmp, &cache, ".txt depth:!=2"),
+        ["a.txt", "one/two/c.txt", "one/two/three/d.txt"]
+    );
+    assert_eq!(
+        hits(&tmp, &cache, ".txt depth:2..3"),
+        ["one/b.txt", "one/two/c.txt"]
+    );
+    assert_eq!(
+        hits(&tmp, &cache, ".txt depth:3.."),
+        ["one/two/c.txt", "one/two/three/d.txt"]
+    );
+    assert_eq!(hits(&tmp, &cache, ".txt depth:..1"), ["a.txt"]);
+}
+
+#[test]
+fn test_depth_counts_from_infolder() {
+    let (tmp, cache) = build_tree();
+    let one = tmp.path().join("one");
+    assert_eq!(
+        hits(&tmp, &cache, &format!("infolder:{} depth:1", one.display())),
+        ["one/b.txt", "one/two"]
+    );
+    // Filter order doesn't matter.
+    assert_eq!(
+        hits(
+            &tmp,
+            &cache,
+            &format!("depth:=2 infolder:{}", one.display())
+        ),
+        ["one/two/c.txt", "one/two/three"]
+    );
+    // The innermost folder of the group wins.
+    let two = one.join("two");
+    assert_eq!(
+        hits(
+            &tmp,
+            &cache,
+            &format!(
+                "infolder:{} infolder:{} depth:1",
+                one.display(),
+                two.display()
+            )
+        ),
+        ["one/two/c.txt", "one/two/three"]
+    );
+    // Folders in other groups don't move the root.
+    assert_eq!(
+        hits(
+            &tmp,
+            &cache,
+            &format!(".txt depth:2 <infolder:{} | a.txt>", one.display())
+        ),
+        ["a.txt", "one/b.txt"]
+    );
+}
+
+#[test]
+fn test_depth_counts_from_parent() {
+    let (tmp, cache) = build_tree();
+    let two = tmp.path().join("one/two");
+    assert_eq!(
+        hits(&tmp, &cache, &format!("parent:{} depth:1", two.display())),
+        ["one/two/c.txt", "one/two/three"]
+    );
+    assert!(hits(&tmp, &cache, &format!("parent:{} depth:=2", two.display())).is_empty());
+}
+
+#[test]
+fn test_depth_within_results() {
+    let (tmp, cache) = build_tree();
+    let base = cache.search(".txt").unwrap();
+    let one = tmp.path().join("one");
+    let outcome = cache
+        .search_within(
+            &base,
+            &format!("infolder:{} depth:1", one.display()),
+            Default::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let paths: Vec<PathBuf> = outcome
+        .nodes
+        .iter()
+        .filter_map(|&index| cache.node_path(index))
+        .collect();
+    assert_eq!(paths, [one.join("b.txt")]);
+}
+
+#[test]
+fn test_depth_rejects_invalid_arguments() {
+    let (_tmp, cache) = build_tree();
+    let error = |query: &str| cache.search(query).unwrap_err().to_string();
+    assert!(error("depth:").contains("requires a value"));
+    assert!(error("depth:-1").contains("must not be negative"));
+    assert!(error("depth:>-1").contains("must not be negative"));
+    assert!(error("depth:deep").contains("failed to parse number"));
+    assert!(error("depth:1.5").contains("failed to parse number"));
+    assert!(error("depth:3..1").contains("less than or equal"));
+}
@@ -0,0 +1,199 @@
+use super::prelude::*;
+use crate::{
+    EventIdStats,
+    testkit::{EventScript, TreeSpec},
+};
+use cardinal_sdk::{EventFlag, FsEvent};
+
+fn found(cache: &SearchCache, name: &str) -> bool {
+    !cache.search(name).unwrap().is_empty()
+}
+
+#[test]
+fn test_out_of_order_and_duplicate_ids_never_lower_the_id() {
+    let (tmp, mut cache) = TreeSpec::new().file("a.txt").index("event_ids_order");
+    let root = tmp.path();
+    let start = cache.last_event_id();
+    assert!(!cache.replaying());
+
+    let mut script = EventScript::after_id(start + 100);
+    script.create_file(&root.join("new.txt"), b"n");
+    script.apply(&mut cache).unwrap();
+    assert_eq!(cache.last_event_id(), start + 101);
+
+    // Delivered late, numbered before the batch above: applied all the same.
+    let mut script = EventScript::after_id(start);
+    script
+        .create_file(&root.join("late.txt"), b"l")
+        .modify(&root.join("a.txt"), b"changed");
+    script.apply(&mut cache).unwrap();
+    assert!(found(&cache, "late.txt"));
+    assert_eq!(cache.last_event_id(), start + 101);
+
+    // The last batch again: its id is the stored one, which isn't late.
+    let mut script = EventScript::after_id(start + 100);
+    script.event(
+        &root.join("new.txt"),
+        EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    );
+    script.apply(&mut cache).unwrap();
+    assert_eq!(cache.last_event_id(), start + 101);
+    assert_eq!(
+        cache.event_id_stats(),
+        EventIdStats {
+            max_seen: start + 101,
+            replayed_duplicates: 0,
+            out_of_order: 2,
+            replaying: false,
+        }
+    );
+}
+
+#[test]
+fn test_replay_skips_history_the_cache_holds() {
+    let (tmp, mut cache) = TreeSpec::new().file("kept.txt").index("event_ids_replay");
+    let root = tmp.path();
+    let start = cache.last_event_id();
+    cache.begin_replay();
+    assert!(cache.replaying());
+
+    // The cache is newer than this event, so it must hold what it reports.
+    let mut script = EventScript::after_id(start - 3);
+    script.create_file(&root.join("old.txt"), b"o");
+    script.apply(&mut cache).unwrap();
+    assert!(!found(&cache, "old.txt"));
+
+    let mut script = EventScript::after(&cache);
+    script.create_file(&root.join("fresh.txt"), b"f");
+    script.apply(&mut cache).unwrap();
+    assert!(found(&cache, "fresh.txt"));
+    assert_eq!(cache.event_id_stats().replayed_duplicates, 1);
+    assert!(cache.replaying());
+
+    // Events after `HistoryDone` in its batch are live, older or not.
+    let created = |id| FsEvent {
+        path: root.join("old.txt"),
+        flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+        id,
+    };
+    let history_done = FsEvent {
+        path: root.to_path_buf(),
+        flag: EventFlag::HistoryDone,
+        id: start + 2,
+    };
+    cache
+        .handle_fs_events(vec![created(start - 2), history_done, created(start - 1)])
+        .unwrap();
+    assert!(!cache.replaying());
+    assert!(found(&cache, "old.txt"));
+    assert_eq!(cache.last_event_id(), start + 2);
+    assert_eq!(
+        cache.event_id_stats(),
+        EventIdStats {
+            max_seen: start + 2,
+            replayed_duplicates: 2,
+            out_of_order: 1,
+            replaying: false,
+        }
+    );
+}
+
+#[test]
+fn test_replay_after_a_restart_skips_only_applied_events() {
+    let (tmp, mut cache) = TreeSpec::new().file("a.txt").index("event_ids_restart");
+    let root = tmp.path();
+    let start = cache.last_event_id();
+    let mut script = EventScript::after_id(start + 10);
+    script.create_file(&root.join("seen.txt"), b"s");
+    script.apply(&mut cache).unwrap();
+    let mut script = EventScript::after_id(start + 20);
+    script.create_file(&root.join("last.txt"), b"l");
+    script.apply(&mut cache).unwrap();
+    assert_eq!(cache.last_event_id(), start + 21);
+
+    // The restarted watcher replays an applied event and one the cache
+    // never got, both older than the stored id.
+    cache.begin_replay();
+    fs::remove_file(root.join("seen.txt")).unwrap();
+    fs::write(root.join("missed.txt"), b"m").unwrap();
+    let event = |name: &str, id| FsEvent {
+        path: root.join(name),
+        flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+        id,
+    };
+    cache
+        .handle_fs_events(vec![
+            event("seen.txt", start + 11),
+            event("missed.txt", start + 15),
+        ])
+        .unwrap();
+    assert!(found(&cache, "seen.txt"));
+    assert!(found(&cache, "missed.txt"));
+    assert_eq!(cache.last_event_id(), start + 21);
+    assert_eq!(
+        cache.event_id_stats(),
+        EventIdStats {
+            max_seen: start + 21,
+            replayed_duplicates: 1,
+            out_of_order: 1,
+            replaying: true,
+        }
+    );
+}
+
+#[test]
+fn test_loaded_cache_resumes_from_its_stored_id() {
+    let (tmp, mut cache) = TreeSpec::new().file("a.txt").index("event_ids_persist");
+    let root = tmp.path();
+    let mut script = EventScript::after(&cache);
+    script
+        .create_file(&root.join("gone.txt"), b"g")
+        .remove(&root.join("gone.txt"))
+        .create_file(&root.join("b.txt"), b"b");
+    let history: Vec<(PathBuf, EventFlag, u64)> = script
+        .events()
+        .iter()
+        .map(|event| (event.path.clone(), event.flag, event.id))
+        .collect();
+    script.apply(&mut cache).unwrap();
+    let stored = cache.last_event_id();
+
+    let cache_path = root.join("cache.zstd");
+    cache.flush_to_file(&cache_path).unwrap();
+    let mut cache = SearchCache::try_read_persistent_cache(root, &cache_path, None, None).unwrap();
+    assert_eq!(cache.last_event_id(), stored);
+    assert!(cache.replaying());
+
+    // The watcher replays the history from before the flush.
+    let replay = history
+        .into_iter()
+        .map(|(path, flag, id)| FsEvent { path, flag, id })
+        .collect();
+    cache.handle_fs_events(replay).unwrap();
+    assert_eq!(cache.event_id_stats().replayed_duplicates, 2);
+    assert_eq!(cache.last_event_id(), stored);
+    assert!(!found(&cache, "gone.txt"));
+
+    let mut script = EventScript::after(&cache);
+    script
+        .event(root, EventFlag::HistoryDone)
+        .create_file(&root.join("c.txt"), b"c");
+    script.apply(&mut cache).unwrap();
+    assert!(!cache.replaying());
+    assert_eq!(cache.last_event_id(), stored + 2);
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert!(found(&cache, name), "{name}");
+    }
+}
+
+#[test]
+fn test_rescan_keeps_a_newer_stored_id() {
+    let (_tmp, mut cache) = TreeSpec::new().file("a.txt").index("event_ids_rescan");
+    // Ahead of the clock the rescan reads its id from, as after the clock
+    // was set back.
+    let ahead = cache.last_event_id() + 1_000_000_000;
+    cache.last_event_id = ahead;
+    cache.rescan();
+    assert_eq!(cache.last_event_id(), ahead);
+    assert_eq!(cache.event_id_stats().max_seen, ahead);
+}
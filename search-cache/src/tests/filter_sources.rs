@@ -0,0 +1,174 @@
+use super::prelude::*;
+use crate::{
+    QueryNote, SearchError, SearchOptions,
+    query::{FILTER_SOURCES_DISABLED, UNIVERSE_COLLECTS},
+    testkit::TreeSpec,
+};
+use std::time::Instant;
+
+const QUERIES: &[&str] = &[
+    "ext:png",
+    "ext:PNG",
+    "ext:png;txt",
+    "ext:!png",
+    "ext:none",
+    "type:picture",
+    "type:folder",
+    "type:file",
+    "file:",
+    "folder:",
+    "size:>1kb",
+    "size:<=5",
+    "size:empty",
+    "disksize:>0",
+    "dm:today",
+    "dm:<2000-01-01",
+    "ext:png size:>1kb",
+    "size:>1kb ext:png",
+    "ext:txt dm:today",
+    "type:picture size:<1kb",
+    "ext:png | ext:txt",
+    "!ext:png",
+    "ext:png report",
+];
+
+fn build_tree() -> (TempDir, SearchCache) {
+    [
+        ("photos/beach.png", 4096),
+        ("photos/2024/sunset.PNG", 10),
+        ("photos/2024/report.png", 0),
+        ("docs/report.txt", 2048),
+        ("docs/notes.txt", 5),
+        ("docs/README", 100),
+        ("empty.txt", 0),
+        ("icon.jpg", 3000),
+    ]
+    .into_iter()
+    .fold(TreeSpec::new().dir("album.png"), |spec, (path, len)| {
+        spec.sized_file(path, len)
+    })
+    .index("filter_sources")
+}
+
+/// Nodes of `query`, with or without the filter sources.
+fn search(cache: &SearchCache, query: &str, options: SearchOptions, sources: bool) -> Vec<usize> {
+    FILTER_SOURCES_DISABLED.with(|disabled| disabled.set(!sources));
+    let result = cache.search_with_options(query, options, CancellationToken::noop());
+    FILTER_SOURCES_DISABLED.with(|disabled| disabled.set(false));
+    result
+        .unwrap()
+        .nodes
+        .into_iter()
+        .map(|index| index.get())
+        .collect()
+}
+
+fn collects() -> usize {
+    UNIVERSE_COLLECTS.with(|collects| collects.get())
+}
+
+#[test]
+fn test_sources_give_the_same_results_as_collecting_every_node() {
+    let (_tmp, cache) = build_tree();
+    for case_insensitive in [false, true] {
+        let options = SearchOptions::default().with_case_insensitive(case_insensitive);
+        for query in QUERIES {
+            assert_eq!(
+                search(&cache, query, options, true),
+                search(&cache, query, options, false),
+                "{query:?} case_insensitive: {case_insensitive}"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_filter_only_queries_find_the_expected_nodes() {
+    let (tmp, cache) = build_tree();
+    let names = |query: &str| {
+        let mut names: Vec<_> = cache
+            .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+            .unwrap()
+            .nodes
+            .into_iter()
+            .map(|index| {
+                let path = cache.node_path(index).unwrap();
+                path.strip_prefix(tmp.path())
+                    .unwrap()
+                    .to_string_lossy()
+                    .into_owned()
+            })
+            .collect();
+        names.sort();
+        names
+    };
+    // Folders named like files aren't files.
+    assert_eq!(
+        names("ext:png"),
+        vec![
+            "photos/2024/report.png",
+            "photos/2024/sunset.PNG",
+            "photos/beach.png"
+        ]
+    );
+    assert_eq!(names("ext:png size:>1kb"), vec!["photos/beach.png"]);
+    assert_eq!(
+        names("size:>1kb"),
+        vec!["docs/report.txt", "icon.jpg", "photos/beach.png"]
+    );
+    assert_eq!(
+        names("type:picture size:<1kb"),
+        vec!["photos/2024/report.png", "photos/2024/sunset.PNG"]
+    );
+}
+
+#[test]
+fn test_filter_only_queries_never_collect_every_node() {
+    let (_tmp, cache) = build_tree();
+    for query in [
+        "ext:png",
+        "ext:!png",
+        "type:picture",
+        "type:folder",
+        "folder:",
+        "size:>1kb",
+        "dm:today",
+        "ext:png size:>1kb",
+        "size:>1kb ext:png",
+    ] {
+        let before = collects();
+        search(&cache, query, SearchOptions::default(), true);
+        assert_eq!(collects(), before, "{query:?} collected every node");
+    }
+    // The old path did, which the parity test relies on.
+    let before = collects();
+    search(&cache, "ext:png", SearchOptions::default(), false);
+    assert_eq!(collects(), before + 1);
+}
+
+#[test]
+fn test_filter_sources_stop_when_cancelled() {
+    let (_tmp, cache) = build_tree();
+    let token = CancellationToken::noop().with_deadline(Instant::now());
+    let by_name: &dyn Fn(&str) -> bool = &|_| true;
+    for name_matches in [Some(by_name), None] {
+        assert!(matches!(
+            cache.filter_all("test", name_matches, |_| true, token),
+            Err(SearchError::Cancelled)
+        ));
+    }
+}
+
+#[test]
+fn test_files_only_filter_notes_a_tree_of_folders() {
+    let tmp = TempDir::new("filter_sources_folders").unwrap();
+    fs::create_dir_all(tmp.path().join("a/b")).unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    for query in ["ext:png", "size:>1kb"] {
+        let outcome = cache
+            .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+            .unwrap();
+        assert!(outcome.nodes.is_empty());
+        assert_eq!(outcome.notes, vec![QueryNote::FilterExcludesAllDirectories]);
+    }
+}
@@ -0,0 +1,141 @@
+#[cfg(target_os = "macos")]
+use super::prelude::*;
+#[cfg(target_os = "macos")]
+use crate::testkit::{TreeSpec, node_name};
+use crate::{FinderColor, FinderTag, finder_tags::parse_string_array};
+#[cfg(target_os = "macos")]
+use std::{ffi::CString, os::unix::ffi::OsStrExt, path::Path};
+
+/// A binary plist holding an array of `strings`, as Finder writes them.
+fn bplist(strings: &[&str]) -> Vec<u8> {
+    let mut data = b"bplist00".to_vec();
+    let mut offsets = vec![data.len()];
+    data.push(0xA0 | strings.len() as u8);
+    data.extend((1..=strings.len()).map(|object| object as u8));
+    for string in strings {
+        offsets.push(data.len());
+        let (marker, len, bytes) = if string.is_ascii() {
+            (0x50, string.len(), string.as_bytes().to_vec())
+        } else {
+            let units: Vec<u16> = string.encode_utf16().collect();
+            let bytes = units.iter().flat_map(|unit| unit.to_be_bytes()).collect();
+            (0x60, units.len(), bytes)
+        };
+        if len < 15 {
+            data.push(marker | len as u8);
+        } else {
+            data.extend([marker | 0xF, 0x10, len as u8]);
+        }
+        data.extend(bytes);
+    }
+    let table = data.len();
+    for offset in &offsets {
+        data.extend((*offset as u16).to_be_bytes());
+    }
+    data.extend([0; 6]);
+    data.extend([2, 1]);
+    data.extend((offsets.len() as u64).to_be_bytes());
+    data.extend(0u64.to_be_bytes());
+    data.extend((table as u64).to_be_bytes());
+    data
+}
+
+/// Linux only allows `user.` xattrs on regular files, so tagging is macOS only.
+#[cfg(target_os = "macos")]
+fn set_finder_tags(path: &Path, tags: &[&str]) {
+    let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    let value = bplist(tags);
+    let ret = unsafe {
+        libc::setxattr(
+            path.as_ptr(),
+            c"com.apple.metadata:_kMDItemUserTags".as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+            0,
+        )
+    };
+    assert_eq!(ret, 0, "{}", std::io::Error::last_os_error());
+}
+
+#[cfg(target_os = "macos")]
+fn build_tree() -> (TempDir, SearchCache) {
+    let tmp = TreeSpec::new()
+        .files(["red.txt", "work.txt", "blue.txt", "plain.txt"])
+        .create("finder_tags");
+    set_finder_tags(&tmp.path().join("red.txt"), &["Red\n6"]);
+    set_finder_tags(&tmp.path().join("work.txt"), &["Work\n6", "Later"]);
+    set_finder_tags(&tmp.path().join("blue.txt"), &["Projects\n4"]);
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    (tmp, cache)
+}
+
+#[cfg(target_os = "macos")]
+fn sorted_names(cache: &SearchCache, query: &str) -> Vec<String> {
+    let mut names: Vec<String> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_parse_finder_tag_lists() {
+    let long = "A tag name longer than fifteen";
+    let strings = ["Red\n6", "Später", long];
+    assert_eq!(parse_string_array(&bplist(&strings)).unwrap(), strings);
+    assert_eq!(
+        parse_string_array(&bplist(&[])).unwrap(),
+        Vec::<String>::new()
+    );
+    assert!(parse_string_array(b"bplist00").is_none());
+    assert!(parse_string_array(b"<?xml version=\"1.0\"?><plist/>").is_none());
+    let mut truncated = bplist(&strings);
+    truncated.drain(10..20);
+    assert!(parse_string_array(&truncated).is_none());
+
+    let tag = FinderTag::parse("Work\n6");
+    assert_eq!(tag.name, "Work");
+    assert_eq!(tag.color, Some(FinderColor::Red));
+    assert_eq!(FinderTag::parse("Later").color, None);
+    assert_eq!(FinderTag::parse("Odd\n9").color, None);
+    assert!(tag.matches("work") && tag.matches("RED") && !tag.matches("blue"));
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn test_fintag_filter_by_name_and_color() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(sorted_names(&cache, "fintag:red"), ["red.txt", "work.txt"]);
+    assert_eq!(sorted_names(&cache, "fintag:Work"), ["work.txt"]);
+    assert_eq!(sorted_names(&cache, "fintag:LATER"), ["work.txt"]);
+    assert_eq!(sorted_names(&cache, "fintag:blue"), ["blue.txt"]);
+    assert_eq!(
+        sorted_names(&cache, "fintag:projects;later"),
+        ["blue.txt", "work.txt"]
+    );
+    assert_eq!(
+        sorted_names(&cache, "ext:txt fintag:"),
+        ["blue.txt", "red.txt", "work.txt"]
+    );
+    assert_eq!(sorted_names(&cache, "ext:txt !fintag:"), ["plain.txt"]);
+    assert!(sorted_names(&cache, "fintag:green").is_empty());
+    assert!(sorted_names(&cache, "plain fintag:").is_empty());
+}
+
+#[test]
+#[cfg(target_os = "macos")]
+fn test_finder_tags_are_read_for_candidates_only() {
+    let (tmp, cache) = build_tree();
+    assert_eq!(sorted_names(&cache, "work fintag:red"), ["work.txt"]);
+    assert_eq!(cache.finder_tags.lock().unwrap().len(), 1);
+
+    // Gone since the walk: untagged, not an error.
+    fs::remove_file(tmp.path().join("red.txt")).unwrap();
+    assert_eq!(sorted_names(&cache, "ext:txt fintag:red"), ["work.txt"]);
+    let red = cache.search("red.txt").unwrap()[0];
+    assert!(cache.finder_tags(red).is_empty());
+}
@@ -0,0 +1,99 @@
+use super::prelude::*;
+use cardinal_sdk::{EventFlag, Firmlinks, FsEvent};
+use std::path::Path;
+
+/// root/{Users/me/a.txt, Data/Users/me/a.txt}, `Data/Users` standing in for
+/// the data volume side of the `Users` firmlink.
+fn build_tree() -> (TempDir, Firmlinks) {
+    let tmp = TempDir::new("firmlinks").unwrap();
+    for side in ["Users/me", "Data/Users/me"] {
+        fs::create_dir_all(tmp.path().join(side)).unwrap();
+        fs::write(tmp.path().join(side).join("a.txt"), b"a").unwrap();
+    }
+    let table = format!("{}\tUsers\n", tmp.path().join("Users").display());
+    let firmlinks = Firmlinks::parse(&table, &tmp.path().join("Data"));
+    (tmp, firmlinks)
+}
+
+fn found(cache: &SearchCache, query: &str) -> Vec<PathBuf> {
+    let nodes = cache.search(query).unwrap();
+    let mut paths: Vec<PathBuf> = cache.node_paths(&nodes).into_iter().flatten().collect();
+    paths.sort();
+    paths
+}
+
+fn create(cache: &mut SearchCache, on_disk: &Path, reported: &Path) {
+    fs::write(on_disk, b"new").unwrap();
+    let event = FsEvent {
+        path: reported.to_path_buf(),
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![event]).unwrap();
+}
+
+#[test]
+fn test_shadow_is_skipped_and_its_events_land_on_the_canonical_side() {
+    let (tmp, firmlinks) = build_tree();
+    let root = tmp.path();
+    let ignore_paths = firmlinks.within(root).ignoring_shadows(Vec::new());
+    assert_eq!(ignore_paths, [root.join("Data/Users")]);
+    let mut cache = SearchCache::walk_fs_with_ignore(root.to_path_buf(), ignore_paths);
+    cache.set_firmlinks(firmlinks);
+    assert_eq!(found(&cache, "a.txt"), [root.join("Users/me/a.txt")]);
+
+    create(
+        &mut cache,
+        &root.join("Users/me/b.txt"),
+        &root.join("Data/Users/me/b.txt"),
+    );
+    assert_eq!(found(&cache, "b.txt"), [root.join("Users/me/b.txt")]);
+
+    // The table outlives rescans.
+    cache.rescan();
+    create(
+        &mut cache,
+        &root.join("Users/me/c.txt"),
+        &root.join("Data/Users/me/c.txt"),
+    );
+    assert_eq!(found(&cache, "c.txt"), [root.join("Users/me/c.txt")]);
+}
+
+#[test]
+fn test_both_sides_are_kept_when_the_shadow_is_walked() {
+    let (tmp, firmlinks) = build_tree();
+    let root = tmp.path();
+    let mut cache = SearchCache::walk_fs_with_ignore(root.to_path_buf(), Vec::new());
+    cache.set_firmlinks(firmlinks);
+    assert_eq!(
+        found(&cache, "a.txt"),
+        [
+            root.join("Data/Users/me/a.txt"),
+            root.join("Users/me/a.txt")
+        ]
+    );
+
+    // The shadow is indexed, its events are applied where they happened.
+    create(
+        &mut cache,
+        &root.join("Data/Users/me/b.txt"),
+        &root.join("Data/Users/me/b.txt"),
+    );
+    assert_eq!(found(&cache, "b.txt"), [root.join("Data/Users/me/b.txt")]);
+}
+
+#[test]
+fn test_links_outside_the_root_change_nothing() {
+    let (tmp, firmlinks) = build_tree();
+    // Only the data volume side is walked, nothing is duplicated.
+    let root = tmp.path().join("Data");
+    assert!(firmlinks.within(&root).is_empty());
+    let mut cache = SearchCache::walk_fs_with_ignore(root.clone(), Vec::new());
+    cache.set_firmlinks(firmlinks);
+    create(
+        &mut cache,
+        &root.join("Users/me/b.txt"),
+        &root.join("Users/me/b.txt"),
+    );
+    assert_eq!(found(&cache, "b.txt"), [root.join("Users/me/b.txt")]);
+}
@@ -0,0 +1,175 @@
+use super::prelude::*;
+use crate::{
+    RankMode, SearchOptions, SlabIndex,
+    testkit::{TreeSpec, node_name},
+};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::{path::Path, time::Duration};
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["old.txt", "dir/old.txt"])
+        .index("first_seen")
+}
+
+fn event(cache: &mut SearchCache, path: &Path, flag: EventFlag) -> FsEvent {
+    FsEvent {
+        path: path.to_path_buf(),
+        id: cache.last_event_id() + 1,
+        flag,
+    }
+}
+
+fn create(cache: &mut SearchCache, path: &Path) {
+    fs::write(path, b"new").unwrap();
+    let created = event(cache, path, EventFlag::ItemCreated | EventFlag::ItemIsFile);
+    cache.handle_fs_events(vec![created]).unwrap();
+}
+
+fn index_of(cache: &SearchCache, path: &Path) -> SlabIndex {
+    cache.node_index_for_raw_path(path).unwrap()
+}
+
+fn names(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<String> {
+    cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect()
+}
+
+fn sorted_names(cache: &SearchCache, query: &str) -> Vec<String> {
+    let mut names = names(cache, query, SearchOptions::default());
+    names.sort();
+    names
+}
+
+#[test]
+fn test_created_files_are_new() {
+    let (tmp, mut cache) = build_tree();
+    assert!(cache.search("is:new").unwrap().is_empty());
+    create(&mut cache, &tmp.path().join("fresh.txt"));
+    create(&mut cache, &tmp.path().join("dir/nested.txt"));
+    assert_eq!(sorted_names(&cache, "is:new"), ["fresh.txt", "nested.txt"]);
+    assert_eq!(sorted_names(&cache, "is:NEW nested"), ["nested.txt"]);
+    assert_eq!(
+        sorted_names(&cache, "old.txt !is:new"),
+        ["old.txt", "old.txt"]
+    );
+    let stamp = cache
+        .first_seen(index_of(&cache, &tmp.path().join("fresh.txt")))
+        .unwrap();
+    assert!(stamp.abs_diff(Timestamp::now().as_second()) < 60, "{stamp}");
+}
+
+#[test]
+fn test_walked_nodes_are_not_stamped() {
+    let (tmp, mut cache) = build_tree();
+    let old = tmp.path().join("old.txt");
+    assert_eq!(cache.first_seen(index_of(&cache, &old)), None);
+    // Modifying a file replaces its node, which isn't new either.
+    fs::write(&old, b"modified").unwrap();
+    let modified = event(
+        &mut cache,
+        &old,
+        EventFlag::ItemModified | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(vec![modified]).unwrap();
+    assert_eq!(cache.first_seen(index_of(&cache, &old)), None);
+    assert!(cache.search("is:new").unwrap().is_empty());
+}
+
+#[test]
+fn test_rescanned_folders_keep_stamps() {
+    let (tmp, mut cache) = build_tree();
+    let dir = tmp.path().join("dir");
+    create(&mut cache, &dir.join("nested.txt"));
+    let stamp = cache.first_seen(index_of(&cache, &dir.join("nested.txt")));
+    assert!(stamp.is_some());
+    fs::write(dir.join("other.txt"), b"other").unwrap();
+    let changed = event(
+        &mut cache,
+        &dir,
+        EventFlag::ItemModified | EventFlag::ItemIsDir,
+    );
+    cache.handle_fs_events(vec![changed]).unwrap();
+    assert_eq!(
+        cache.first_seen(index_of(&cache, &dir.join("nested.txt"))),
+        stamp
+    );
+    assert_eq!(
+        cache.first_seen(index_of(&cache, &dir.join("old.txt"))),
+        None
+    );
+    assert!(
+        cache
+            .first_seen(index_of(&cache, &dir.join("other.txt")))
+            .is_some()
+    );
+
+    cache.rescan();
+    assert_eq!(
+        cache.first_seen(index_of(&cache, &dir.join("nested.txt"))),
+        stamp
+    );
+}
+
+#[test]
+fn test_new_window_and_ranking() {
+    let (tmp, mut cache) = build_tree();
+    create(&mut cache, &tmp.path().join("a.txt"));
+    create(&mut cache, &tmp.path().join("b.txt"));
+    let now = Timestamp::now().as_second();
+    let a = index_of(&cache, &tmp.path().join("a.txt"));
+    let b = index_of(&cache, &tmp.path().join("b.txt"));
+    cache.first_seen.insert(a, now - 2 * 60 * 60);
+    cache.first_seen.insert(b, now - 10);
+
+    assert_eq!(sorted_names(&cache, "is:new"), ["a.txt", "b.txt"]);
+    let last_hour = SearchOptions {
+        new_window: Duration::from_secs(60 * 60),
+        ..Default::default()
+    };
+    assert_eq!(names(&cache, "is:new", last_hour), ["b.txt"]);
+
+    let recently_added = SearchOptions {
+        rank: RankMode::RecentlyAdded,
+        ..Default::default()
+    };
+    assert_eq!(
+        names(&cache, ".txt", recently_added)[..2],
+        ["b.txt", "a.txt"]
+    );
+}
+
+#[test]
+fn test_stamps_persist_and_forget_removed_nodes() {
+    let (tmp, mut cache) = build_tree();
+    let fresh = tmp.path().join("fresh.txt");
+    create(&mut cache, &fresh);
+    let stamp = cache.first_seen(index_of(&cache, &fresh));
+    let cache_path = tmp.path().join("cache.zstd");
+    cache.flush_to_file(&cache_path).unwrap();
+    let mut cache =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(cache.first_seen(index_of(&cache, &fresh)), stamp);
+    assert_eq!(sorted_names(&cache, "is:new"), ["fresh.txt"]);
+
+    fs::remove_file(&fresh).unwrap();
+    let removed = event(
+        &mut cache,
+        &fresh,
+        EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert!(cache.first_seen.is_empty());
+}
+
+#[test]
+fn test_unknown_is_property_lists_new() {
+    let (_tmp, cache) = build_tree();
+    let error = cache.search("is:shiny").unwrap_err().to_string();
+    assert!(error.contains("expected hardlinked, new,"), "{error}");
+}
@@ -0,0 +1,63 @@
+use super::prelude::*;
+use crate::{FolderSize, testkit::TreeSpec};
+use std::time::Instant;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .sized_file("a/one.bin", 1000)
+        .sized_file("a/b/two.bin", 234)
+        .file("a/b/empty")
+        .sized_file("outside.bin", 5000)
+        .index("folder_size_job")
+}
+
+#[test]
+fn test_folder_size_job_adds_up_files_below() {
+    let (tmp, cache) = build_tree();
+    let a = cache
+        .node_index_for_raw_path(&tmp.path().join("a"))
+        .unwrap();
+    let job = cache.folder_size_job(a, CancellationToken::noop()).unwrap();
+    // Nothing was stat'ed by the walk, the job does it.
+    assert_eq!(job.files_to_stat(), 3);
+    assert_eq!(
+        job.run(CancellationToken::noop()),
+        Some(FolderSize {
+            bytes: 1234,
+            file_count: 3,
+        })
+    );
+    assert_eq!(
+        cache.folder_size(a, false, CancellationToken::noop()),
+        Some(1234)
+    );
+}
+
+#[test]
+fn test_folder_size_job_reuses_known_sizes() {
+    let (tmp, cache) = build_tree();
+    let a = cache
+        .node_index_for_raw_path(&tmp.path().join("a"))
+        .unwrap();
+    cache.folder_size(a, false, CancellationToken::noop());
+    let job = cache.folder_size_job(a, CancellationToken::noop()).unwrap();
+    assert_eq!(job.files_to_stat(), 0);
+    // Known sizes aren't read again, a grown file only counts once refetched.
+    fs::write(tmp.path().join("a/one.bin"), vec![0u8; 2000]).unwrap();
+    assert_eq!(
+        job.run(CancellationToken::noop()).map(|size| size.bytes),
+        Some(1234)
+    );
+}
+
+#[test]
+fn test_folder_size_job_stops_when_cancelled() {
+    let (tmp, cache) = build_tree();
+    let a = cache
+        .node_index_for_raw_path(&tmp.path().join("a"))
+        .unwrap();
+    let job = cache.folder_size_job(a, CancellationToken::noop()).unwrap();
+    let cancelled = CancellationToken::noop().with_deadline(Instant::now());
+    assert_eq!(job.run(cancelled), None);
+    assert!(cache.folder_size_job(a, cancelled).is_none());
+}
@@ -0,0 +1,98 @@
+use super::prelude::*;
+use crate::{SlabIndex, testkit::TreeSpec};
+use std::os::unix::fs::MetadataExt;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    let tmp = TreeSpec::new()
+        .sized_file("a/original.bin", 4096)
+        .sized_file("b/single.bin", 100)
+        .create("hard_links");
+    fs::hard_link(
+        tmp.path().join("a/original.bin"),
+        tmp.path().join("b/linked.bin"),
+    )
+    .unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    (tmp, cache)
+}
+
+fn names(cache: &SearchCache, nodes: &[SlabIndex]) -> Vec<String> {
+    let mut names: Vec<String> = nodes
+        .iter()
+        .map(|&index| cache.file_nodes[index].name_and_parent.as_str().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_is_hardlinked_matches_every_link() {
+    let (_tmp, cache) = build_tree();
+    let nodes = cache.search("is:hardlinked").unwrap();
+    assert_eq!(names(&cache, &nodes), ["linked.bin", "original.bin"]);
+    let nodes = cache.search("is:HardLinked linked").unwrap();
+    assert_eq!(names(&cache, &nodes), ["linked.bin"]);
+    let nodes = cache.search(".bin !is:hardlinked").unwrap();
+    assert_eq!(names(&cache, &nodes), ["single.bin"]);
+}
+
+#[test]
+fn test_is_rejects_unknown_properties() {
+    let (_tmp, cache) = build_tree();
+    let error = |query: &str| cache.search(query).unwrap_err().to_string();
+    assert!(error("is:").contains("is: requires a property"));
+    assert!(error("is:shiny").contains("unknown property `shiny`"));
+}
+
+#[test]
+fn test_metadata_exposes_inode_and_link_count() {
+    let (_tmp, cache) = build_tree();
+    let nodes = cache.search(".bin").unwrap();
+    let expanded = cache.expand_file_nodes(&nodes);
+    for node in expanded {
+        let metadata = node.metadata.as_ref().unwrap();
+        let expected = fs::symlink_metadata(&node.path).unwrap();
+        assert_eq!(metadata.inode(), Some(expected.ino()));
+        let nlink = if node.path.ends_with("single.bin") {
+            1
+        } else {
+            2
+        };
+        assert_eq!(metadata.nlink(), Some(nlink));
+    }
+}
+
+#[test]
+fn test_group_by_inode_puts_links_together() {
+    let (_tmp, cache) = build_tree();
+    let nodes = cache.search(".bin").unwrap();
+    let mut groups: Vec<Vec<String>> = cache
+        .group_by_inode(&nodes)
+        .iter()
+        .map(|group| names(&cache, group))
+        .collect();
+    groups.sort();
+    assert_eq!(
+        groups,
+        [vec!["linked.bin", "original.bin"], vec!["single.bin"]]
+    );
+    // Every node lands in exactly one group, in input order.
+    let single = cache.search("single").unwrap();
+    let linked = cache.search("linked").unwrap();
+    let input = [single[0], linked[0]];
+    assert_eq!(cache.group_by_inode(&input), [[single[0]], [linked[0]]]);
+}
+
+#[test]
+fn test_folder_size_counts_hard_links_once_on_request() {
+    let (tmp, cache) = build_tree();
+    let root = cache.node_index_for_raw_path(tmp.path()).unwrap();
+    let token = CancellationToken::noop();
+    assert_eq!(cache.folder_size(root, false, token), Some(4096 * 2 + 100));
+    assert_eq!(cache.folder_size(root, true, token), Some(4096 + 100));
+    // A link whose other name is outside the folder still counts there.
+    let b = cache
+        .node_index_for_raw_path(&tmp.path().join("b"))
+        .unwrap();
+    assert_eq!(cache.folder_size(b, true, token), Some(4096 + 100));
+}
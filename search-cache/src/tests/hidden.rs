@@ -0,0 +1,156 @@
+use super::prelude::*;
+use crate::{
+    SearchOptions, SlabIndex, SlabNodeMetadataCompact,
+    query::METADATA_STATS,
+    testkit::{TreeSpec, node_name},
+};
+use fswalk::{HIDDEN_FLAG, NodeMetadata};
+
+/// root/{.env, notes.txt, flagged.txt, .config/{app.txt}}
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .sized_file(".config/app.txt", 1)
+        .sized_file(".env", 1)
+        .sized_file("notes.txt", 1)
+        .sized_file("flagged.txt", 1)
+        .index("hidden_nodes")
+}
+
+fn names(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<String> {
+    let mut names: Vec<String> = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .iter()
+        .map(|&index| node_name(cache, index))
+        .collect();
+    names.sort();
+    names
+}
+
+fn index_of(cache: &SearchCache, name: &str) -> SlabIndex {
+    let options = SearchOptions::default().with_include_hidden(true);
+    cache
+        .search_with_options(name, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .into_iter()
+        .find(|&index| node_name(cache, index) == name)
+        .unwrap()
+}
+
+/// Stand in for `chflags hidden`, which only macOS has.
+fn flag_hidden(cache: &SearchCache, index: SlabIndex) {
+    let metadata = NodeMetadata {
+        r#type: NodeFileType::File,
+        size: 1,
+        allocated: 4096,
+        inode: 0,
+        nlink: 1,
+        ctime: None,
+        mtime: None,
+        file_flags: HIDDEN_FLAG,
+    };
+    cache.store_metadata(index, SlabNodeMetadataCompact::some(metadata));
+}
+
+#[test]
+fn test_dotfiles_are_left_out_by_default() {
+    let (_tmp, cache) = build_tree();
+    let options = SearchOptions::default();
+    assert_eq!(
+        names(&cache, "txt", options),
+        ["app.txt", "flagged.txt", "notes.txt"],
+        "only the node itself counts, not its folders"
+    );
+    assert_eq!(names(&cache, "env", options), Vec::<String>::new());
+    assert_eq!(
+        names(&cache, "env", options.with_include_hidden(true)),
+        [".env"]
+    );
+}
+
+#[test]
+fn test_is_hidden_matches_dotfiles_and_keeps_them() {
+    let (_tmp, cache) = build_tree();
+    let options = SearchOptions::default();
+    assert_eq!(names(&cache, "is:hidden", options), [".config", ".env"]);
+    assert_eq!(
+        names(&cache, "env | is:hidden", options),
+        [".config", ".env"]
+    );
+    // Mentioning it at all turns the default off, NOT then drops them itself.
+    assert_eq!(
+        names(&cache, "!is:hidden txt", options),
+        ["app.txt", "flagged.txt", "notes.txt"]
+    );
+    assert!(cache.search("is:HIDDEN").is_ok());
+}
+
+#[test]
+fn test_hidden_flag_counts_like_a_dot() {
+    let (_tmp, cache) = build_tree();
+    flag_hidden(&cache, index_of(&cache, "flagged.txt"));
+    let options = SearchOptions::default();
+    assert_eq!(names(&cache, "txt", options), ["app.txt", "notes.txt"]);
+    assert_eq!(
+        names(&cache, "txt", options.with_include_hidden(true)),
+        ["app.txt", "flagged.txt", "notes.txt"]
+    );
+    assert_eq!(
+        names(&cache, "is:hidden", options),
+        [".config", ".env", "flagged.txt"]
+    );
+    assert_eq!(names(&cache, "txt is:hidden", options), ["flagged.txt"]);
+}
+
+#[test]
+fn test_default_exclusion_does_not_stat() {
+    let (_tmp, cache) = build_tree();
+    let before = METADATA_STATS.with(|stats| stats.get());
+    assert_eq!(
+        names(&cache, "txt", SearchOptions::default()),
+        ["app.txt", "flagged.txt", "notes.txt"]
+    );
+    assert_eq!(METADATA_STATS.with(|stats| stats.get()), before);
+    // `is:hidden` does, to find the flags of files the walk didn't stat.
+    cache.search("txt is:hidden").unwrap();
+    assert!(METADATA_STATS.with(|stats| stats.get()) > before);
+}
+
+#[test]
+fn test_hidden_flag_survives_a_reload() {
+    let (tmp, cache) = build_tree();
+    flag_hidden(&cache, index_of(&cache, "flagged.txt"));
+    let cache_path = tmp.path().join("cache.zstd");
+    cache.flush_to_file(&cache_path).unwrap();
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(
+        names(&loaded, "txt", SearchOptions::default()),
+        ["app.txt", "notes.txt"]
+    );
+}
+
+#[cfg(target_os = "macos")]
+#[test]
+fn test_chflags_hidden_file() {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let (tmp, _) = build_tree();
+    let path = tmp.path().join("flagged.txt");
+    let c_path = CString::new(path.as_os_str().as_bytes()).unwrap();
+    assert_eq!(
+        unsafe { libc::chflags(c_path.as_ptr(), libc::UF_HIDDEN) },
+        0
+    );
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let options = SearchOptions::default();
+    // The walk doesn't stat files, `is:hidden` does and finds the flag.
+    assert_eq!(names(&cache, "flagged is:hidden", options), ["flagged.txt"]);
+    assert_eq!(names(&cache, "flagged", options), Vec::<String>::new());
+    assert_eq!(
+        names(&cache, "flagged", options.with_include_hidden(true)),
+        ["flagged.txt"]
+    );
+}
@@ -0,0 +1,139 @@
+use super::prelude::*;
+use crate::{SearchOptions, SlabIndex, initials::BOUNDARY_COMPUTATIONS, testkit::TreeSpec};
+
+const NAMES: &[&str] = &[
+    "SearchCache.swift",
+    "SecretCodeBook.txt",
+    "search_cache.rs",
+    "sidecar.txt",
+    "static-config.json",
+    "Slab Cursor.md",
+    "HTTPServer.go",
+    "app_v2_final.txt",
+    "v2notes.md",
+    "ÉtéChaud.txt",
+    "ΑλφαΒήτα.txt",
+];
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new().files(NAMES).index("initials")
+}
+
+/// Names in result order, leaving out the root, whose random name could match.
+fn search(cache: &SearchCache, query: &str, case_insensitive: bool) -> Vec<String> {
+    let options = SearchOptions {
+        case_insensitive,
+        ..Default::default()
+    };
+    let nodes = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    names(cache, &nodes)
+}
+
+fn names(cache: &SearchCache, nodes: &[SlabIndex]) -> Vec<String> {
+    nodes
+        .iter()
+        .filter(|&&index| index != cache.file_nodes.root())
+        .map(|&index| cache.file_nodes[index].name_and_parent.as_str().to_string())
+        .collect()
+}
+
+#[test]
+fn test_camel_case_initials() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(
+        search(&cache, "initials:SC", false),
+        ["SearchCache.swift", "Slab Cursor.md", "SecretCodeBook.txt"]
+    );
+    assert_eq!(search(&cache, "initials:HS", false), ["HTTPServer.go"]);
+    assert_eq!(
+        search(&cache, "initials:sc", false),
+        ["search_cache.rs", "static-config.json"]
+    );
+}
+
+#[test]
+fn test_separator_initials_full_first() {
+    let (_tmp, cache) = build_tree();
+    // Full matches in name order, then the partial ones.
+    assert_eq!(
+        search(&cache, "initials:sc", true),
+        [
+            "SearchCache.swift",
+            "Slab Cursor.md",
+            "search_cache.rs",
+            "static-config.json",
+            "SecretCodeBook.txt",
+        ]
+    );
+    // Initials of the extension only make partial matches.
+    assert_eq!(
+        search(&cache, "initials:st", true),
+        ["SecretCodeBook.txt", "sidecar.txt"]
+    );
+    assert_eq!(search(&cache, "initials:scr", true), ["search_cache.rs"]);
+    // Separators in the argument are ignored.
+    assert_eq!(
+        search(&cache, "initials:s_c ext:rs", true),
+        ["search_cache.rs"]
+    );
+}
+
+#[test]
+fn test_digit_initials() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(
+        search(&cache, "initials:v2", false),
+        ["app_v2_final.txt", "v2notes.md"]
+    );
+    assert_eq!(search(&cache, "initials:v2n", false), ["v2notes.md"]);
+    assert_eq!(search(&cache, "initials:av2f", false), ["app_v2_final.txt"]);
+}
+
+#[test]
+fn test_unicode_initials() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(search(&cache, "initials:ÉC", false), ["ÉtéChaud.txt"]);
+    assert_eq!(search(&cache, "initials:éc", true), ["ÉtéChaud.txt"]);
+    // Decomposed arguments match composed names.
+    assert_eq!(
+        search(&cache, "initials:E\u{301}C", false),
+        ["ÉtéChaud.txt"]
+    );
+    assert_eq!(search(&cache, "initials:ΑΒ", false), ["ΑλφαΒήτα.txt"]);
+    assert_eq!(search(&cache, "initials:αβ", true), ["ΑλφαΒήτα.txt"]);
+}
+
+#[test]
+fn test_initials_with_base_and_errors() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(
+        search(&cache, "ext:md initials:sc", true),
+        ["Slab Cursor.md"]
+    );
+    assert!(cache.search("initials:").is_err());
+    assert!(cache.search("initials:_-").is_err());
+}
+
+#[test]
+fn test_boundaries_computed_once_per_name() {
+    let tmp = TempDir::new("initials_cache").unwrap();
+    // Names no other test uses, the cache is shared by the whole process.
+    for i in 0..20 {
+        fs::write(tmp.path().join(format!("QuxBoundaryProbe{i}.txt")), b"x").unwrap();
+    }
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let computations = || BOUNDARY_COMPUTATIONS.with(|computations| computations.get());
+
+    let before = computations();
+    assert_eq!(search(&cache, "initials:QBP", false).len(), 20);
+    let first = computations() - before;
+    assert!(first >= 20, "{first}");
+
+    for query in ["initials:QBP", "initials:qbp", "initials:xyz"] {
+        search(&cache, query, true);
+    }
+    assert_eq!(computations() - before, first);
+}
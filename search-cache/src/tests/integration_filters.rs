@@ -1,4 +1,6 @@
 use super::prelude::*;
+use crate::testkit::TreeSpec;
+use hashbrown::HashSet;
 
 #[test]
 fn test_combined_filters_all_match() {
@@ -7,7 +9,7 @@ fn test_combined_filters_all_match() {
     fs::write(tmp.path().join("report_small.pdf"), vec![0u8; 1_000]).unwrap();
     fs::write(tmp.path().join("data.csv"), vec![0u8; 100_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("report type:pdf size:>10kb").unwrap();
     assert_eq!(results.len(), 1);
@@ -24,7 +26,7 @@ fn test_complex_boolean_with_filters() {
     fs::write(tmp.path().join("image.jpg"), vec![0u8; 100_000]).unwrap();
     fs::write(tmp.path().join("small_image.jpg"), vec![0u8; 1_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache
         .search("(report OR type:picture) size:>10kb !txt")
@@ -45,7 +47,7 @@ fn test_combined_filters_no_matches() {
     fs::write(tmp.path().join("small.jpg"), vec![0u8; 100]).unwrap();
     fs::write(tmp.path().join("large.txt"), vec![0u8; 100_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Picture that is large (but our picture is small)
     let results = cache.search("type:picture size:>10kb").unwrap();
@@ -60,7 +62,7 @@ fn test_complex_query_with_precedence() {
     fs::write(tmp.path().join("photo_a.jpg"), vec![0u8; 100_000]).unwrap();
     fs::write(tmp.path().join("photo_b.jpg"), vec![0u8; 1_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Test: (report OR photo_a) AND type:picture
     let results = cache.search("report OR photo_a type:picture").unwrap();
@@ -77,7 +79,7 @@ fn test_multiple_filters_intersection_complex() {
     fs::write(tmp.path().join("photos/small.jpg"), vec![0u8; 1_000]).unwrap();
     fs::write(tmp.path().join("document.pdf"), vec![0u8; 100_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let photos_dir = tmp.path().join("photos");
     let results = cache
@@ -97,7 +99,7 @@ fn test_deeply_nested_boolean_with_filters() {
     fs::write(tmp.path().join("c.mp3"), vec![0u8; 100_000]).unwrap();
     fs::write(tmp.path().join("d.mp3"), vec![0u8; 1_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache
         .search("((type:picture OR type:audio) size:>10kb)")
@@ -115,7 +117,7 @@ fn test_complex_or_chain_with_types() {
     fs::write(tmp.path().join("archive.zip"), b"x").unwrap();
     fs::write(tmp.path().join("code.rs"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache
         .search("type:picture OR type:video OR type:audio OR type:doc")
@@ -129,7 +131,7 @@ fn test_combined_filters_empty_intersection() {
     fs::write(tmp.path().join("photo.jpg"), vec![0u8; 100]).unwrap();
     fs::write(tmp.path().join("large.txt"), vec![0u8; 100_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Looking for large pictures, but the picture is small
     let results = cache.search("type:picture size:>10kb").unwrap();
@@ -156,7 +158,7 @@ fn test_complex_real_world_query() {
     .unwrap();
     fs::write(tmp.path().join("old_photo_2023.jpg"), vec![0u8; 500_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Find large vacation photos from 2024
     let results = cache
@@ -175,7 +177,7 @@ fn test_combined_all_filter_types() {
     fs::write(tmp.path().join("photos/vacation.jpg"), vec![0u8; 100_000]).unwrap();
     fs::write(tmp.path().join("photos/small.jpg"), vec![0u8; 1_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let photos_dir = tmp.path().join("photos");
     let results = cache
@@ -197,7 +199,7 @@ fn test_stress_many_filters_combined() {
     )
     .unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let media_dir = tmp.path().join("media");
     let results = cache
@@ -236,7 +238,7 @@ fn test_final_integration_comprehensive() {
     .unwrap();
     fs::write(tmp.path().join("Code/main.rs"), vec![0u8; 10_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Test 1: Find large documents
     let docs = cache.search("type:doc size:>100kb").unwrap();
@@ -264,7 +266,7 @@ fn test_nosubfolders_keeps_only_direct_files() {
     fs::create_dir(projects.join("Nested")).unwrap();
     fs::write(projects.join("Nested/deep.txt"), b"deep").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
     let results = cache
         .search(&format!("nosubfolders:{}", projects.display()))
         .unwrap();
@@ -289,7 +291,7 @@ fn test_nosubfolders_only_filters_target_tree() {
     fs::write(projects.join("Nested/report.txt"), b"deep report").unwrap();
     fs::write(tmp.path().join("report.txt"), b"global report").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
     let query = format!("report nosubfolders:{}", projects.display());
     let paths: Vec<_> = cache
         .search(&query)
@@ -301,3 +303,128 @@ fn test_nosubfolders_only_filters_target_tree() {
     assert_eq!(paths.len(), 1);
     assert_eq!(paths[0], projects.join("report.txt"));
 }
+
+fn relative_hits(cache: &SearchCache, root: &std::path::Path, query: &str) -> Vec<String> {
+    let mut paths: Vec<String> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .filter_map(|idx| cache.node_path(idx))
+        .filter_map(|path| {
+            path.strip_prefix(root)
+                .ok()
+                .map(|x| x.to_string_lossy().into_owned())
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn under_fixture(name: &str) -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .dir("app/.git/objects/ab")
+        .files([
+            "app/node_modules/lodash/index.js",
+            "app/node_modules/lodash/node_modules/inner/deep.js",
+            "app/src/main.js",
+            // A file named like the folders.
+            "app/src/node_modules",
+            "app/.git/objects/ab/cdef.js",
+            "app/.git/HEAD",
+        ])
+        .index(name)
+}
+
+#[test]
+fn test_under_matches_descendants_of_nested_named_folders() {
+    let (tmp, cache) = under_fixture("under_nested");
+
+    let hits = relative_hits(&cache, tmp.path(), "under:node_modules");
+    assert_eq!(
+        hits,
+        [
+            "app/node_modules/lodash",
+            "app/node_modules/lodash/index.js",
+            "app/node_modules/lodash/node_modules",
+            "app/node_modules/lodash/node_modules/inner",
+            "app/node_modules/lodash/node_modules/inner/deep.js",
+        ]
+    );
+
+    // Inside a base the ancestor check applies too; a file named like the folder doesn't count.
+    let hits = relative_hits(&cache, tmp.path(), "js under:node_modules");
+    assert_eq!(
+        hits,
+        [
+            "app/node_modules/lodash/index.js",
+            "app/node_modules/lodash/node_modules/inner/deep.js",
+        ]
+    );
+}
+
+#[test]
+fn test_ancestor_check_reuses_walked_chains() {
+    let (tmp, cache) = under_fixture("under_ancestors");
+    let index = |path: &str| {
+        cache
+            .node_index_for_raw_path(&tmp.path().join(path))
+            .unwrap()
+    };
+    let folders = HashSet::from([index("app/node_modules/lodash")]);
+    let mut ancestors = cache.ancestors_in(&folders);
+
+    // Each answer must not depend on what earlier lookups remembered.
+    let expected = [
+        ("app/node_modules/lodash/node_modules/inner/deep.js", true),
+        ("app/src/main.js", false),
+        ("app/.git/HEAD", false),
+        ("app/node_modules/lodash", false),
+        ("app/node_modules/lodash/node_modules/inner", true),
+        ("app/node_modules/lodash/index.js", true),
+        ("app/node_modules", false),
+    ];
+    for (path, under) in expected {
+        assert_eq!(ancestors.contains(index(path)), under, "{path}");
+    }
+}
+
+#[test]
+fn test_negated_under_excludes_whole_subtree() {
+    let (tmp, cache) = under_fixture("under_negated");
+
+    let hits = relative_hits(&cache, tmp.path(), "ext:js !under:.git !under:node_modules");
+    assert_eq!(hits, ["app/src/main.js"]);
+
+    let hits = relative_hits(&cache, tmp.path(), "!under:.git ext:js");
+    assert_eq!(
+        hits,
+        [
+            "app/node_modules/lodash/index.js",
+            "app/node_modules/lodash/node_modules/inner/deep.js",
+            "app/src/main.js",
+        ]
+    );
+}
+
+#[test]
+fn test_under_combines_with_ext_and_other_under() {
+    let (tmp, cache) = under_fixture("under_combined");
+
+    let hits = relative_hits(&cache, tmp.path(), "under:.git ext:js");
+    assert_eq!(hits, ["app/.git/objects/ab/cdef.js"]);
+
+    let hits = relative_hits(&cache, tmp.path(), "under:node_modules under:inner");
+    assert_eq!(hits, ["app/node_modules/lodash/node_modules/inner/deep.js"]);
+
+    let hits = relative_hits(&cache, tmp.path(), "under:node_*s ext:js");
+    assert_eq!(
+        hits,
+        [
+            "app/node_modules/lodash/index.js",
+            "app/node_modules/lodash/node_modules/inner/deep.js",
+        ]
+    );
+
+    assert!(cache.search("under:app/src").is_err());
+    assert!(relative_hits(&cache, tmp.path(), "under:missing").is_empty());
+}
@@ -0,0 +1,133 @@
+use super::prelude::*;
+use crate::SlabIndex;
+
+fn nodes_with_metadata(cache: &SearchCache) -> usize {
+    cache
+        .file_nodes
+        .iter()
+        .filter(|(_, node)| node.metadata.is_some())
+        .count()
+}
+
+/// root/{file00.bin..file19.bin, folder/}, `file{i}` holding `i` KiB and more.
+fn sized_tree(name: &str) -> (TempDir, SearchCache) {
+    let tmp = TempDir::new(name).unwrap();
+    for i in 0..20 {
+        fs::write(
+            tmp.path().join(format!("file{i:02}.bin")),
+            vec![0u8; i * 1024 + 1],
+        )
+        .unwrap();
+    }
+    fs::create_dir(tmp.path().join("folder")).unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    (tmp, cache)
+}
+
+fn one(cache: &SearchCache, query: &str) -> SlabIndex {
+    let results = cache.search(query).unwrap();
+    assert_eq!(results.len(), 1, "{query}");
+    results[0]
+}
+
+#[test]
+fn test_metadata_beyond_the_budget_is_evicted() {
+    let (_tmp, mut cache) = sized_tree("metadata_budget_fill");
+    assert_eq!(cache.metadata_budget(), None);
+    cache.set_metadata_budget(Some(5));
+    assert!(nodes_with_metadata(&cache) <= 5);
+    let evicted = cache.metadata_evictions();
+
+    assert_eq!(cache.search("size:>0").unwrap().len(), 20);
+    assert!(nodes_with_metadata(&cache) <= 5);
+    assert!(cache.metadata_evictions() >= evicted + 15);
+
+    // Without a budget fetched metadata stays.
+    cache.set_metadata_budget(None);
+    assert_eq!(cache.search("size:>0").unwrap().len(), 20);
+    assert!(nodes_with_metadata(&cache) >= 20);
+}
+
+#[test]
+fn test_eviction_frees_the_metadata_extras() {
+    let (_tmp, mut cache) = sized_tree("metadata_budget_extras");
+    assert_eq!(cache.search("size:>0").unwrap().len(), 20);
+    assert!(cache.lock_metadata_extras().len() >= 20);
+
+    cache.set_metadata_budget(Some(5));
+    assert!(cache.lock_metadata_extras().len() <= 5);
+    assert_eq!(cache.search("size:>0").unwrap().len(), 20);
+    let extras = cache.lock_metadata_extras();
+    assert!(extras.len() <= 5);
+    for index in extras.keys() {
+        assert!(cache.file_nodes[*index].metadata.is_some());
+    }
+}
+
+#[test]
+fn test_pinned_metadata_survives_eviction() {
+    let (_tmp, mut cache) = sized_tree("metadata_budget_pin");
+    cache.set_metadata_budget(Some(4));
+    let pinned = [one(&cache, "file03.bin"), one(&cache, "file07.bin")];
+    cache.pin_metadata(&pinned);
+    cache.expand_file_nodes(&pinned);
+
+    for _ in 0..3 {
+        assert_eq!(cache.search("size:>0").unwrap().len(), 20);
+    }
+    assert!(cache.metadata_evictions() >= 16);
+    for index in pinned {
+        assert!(cache.file_nodes[index].metadata.is_some());
+    }
+
+    cache.unpin_metadata(&pinned);
+    let evicted = cache.metadata_evictions();
+    for _ in 0..3 {
+        cache.search("size:>0").unwrap();
+    }
+    assert!(cache.metadata_evictions() > evicted);
+    assert!(nodes_with_metadata(&cache) <= 4);
+}
+
+#[test]
+fn test_queries_after_eviction_fetch_metadata_again() {
+    let (tmp, mut cache) = sized_tree("metadata_budget_refetch");
+    let folders = cache.search("folder:").unwrap();
+    let expected = cache.search("size:>10kb").unwrap();
+    assert_eq!(expected.len(), 10);
+
+    cache.set_metadata_budget(Some(0));
+    assert_eq!(nodes_with_metadata(&cache), 0);
+    assert_eq!(cache.search("size:>10kb").unwrap(), expected);
+    assert_eq!(nodes_with_metadata(&cache), 0);
+    // Eviction keeps the file type, type filters don't need the metadata.
+    assert_eq!(cache.search("folder:").unwrap(), folders);
+    assert_eq!(
+        cache.file_nodes[one(&cache, "folder")]
+            .metadata
+            .file_type_hint(),
+        NodeFileType::Dir
+    );
+
+    let index = one(&cache, "file12.bin");
+    let [node] = cache.expand_file_nodes(&[index]).try_into().unwrap();
+    assert_eq!(node.path, tmp.path().join("file12.bin"));
+    assert_eq!(node.metadata.as_ref().unwrap().size(), 12 * 1024 + 1);
+}
+
+#[test]
+fn test_eviction_while_searching_from_several_threads() {
+    let (_tmp, mut cache) = sized_tree("metadata_budget_threads");
+    let expected = cache.search("size:>10kb").unwrap();
+    cache.set_metadata_budget(Some(3));
+    std::thread::scope(|s| {
+        for _ in 0..4 {
+            s.spawn(|| {
+                for _ in 0..20 {
+                    assert_eq!(cache.search("size:>10kb").unwrap(), expected);
+                }
+            });
+        }
+    });
+    assert!(nodes_with_metadata(&cache) <= 3);
+}
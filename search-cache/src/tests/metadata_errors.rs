@@ -0,0 +1,102 @@
+use super::prelude::*;
+use crate::{MetadataErrorCounts, QueryNote, SearchOptions, testkit::TreeSpec};
+use std::os::unix::fs::PermissionsExt;
+
+fn search(cache: &SearchCache, query: &str) -> crate::SearchOutcome {
+    cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+}
+
+#[test]
+fn test_locked_folder_counts_permission_errors() {
+    let (tmp, cache) = TreeSpec::new()
+        .file("open/a.txt")
+        .file("locked/b.txt")
+        .file("locked/c.txt")
+        .index("metadata_errors_locked");
+    let locked = tmp.path().join("locked");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o000)).unwrap();
+    if fs::read_dir(&locked).is_ok() {
+        // Running as root, permissions aren't enforced.
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+
+    let outcome = search(&cache, "ext:txt size:>=0");
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    assert_eq!(outcome.nodes.len(), 1, "only a.txt could be stat'ed");
+    assert_eq!(
+        outcome.metadata_errors,
+        MetadataErrorCounts {
+            permission_denied: 2,
+            not_found: 0,
+            other: 0,
+        }
+    );
+    assert_eq!(outcome.notes, [QueryNote::MetadataPermissionDenied]);
+    assert!(cache.take_vanished().is_empty());
+}
+
+#[test]
+fn test_counts_are_per_search() {
+    let (tmp, cache) = TreeSpec::new()
+        .file("kept.txt")
+        .file("gone.txt")
+        .index("metadata_errors_per_search");
+    fs::remove_file(tmp.path().join("gone.txt")).unwrap();
+
+    let outcome = search(&cache, "ext:txt size:>=0");
+    assert_eq!(outcome.metadata_errors.not_found, 1);
+    assert_eq!(outcome.metadata_errors.total(), 1);
+    assert!(outcome.notes.is_empty(), "only permission errors are noted");
+
+    // Names alone don't stat.
+    let outcome = search(&cache, "ext:txt");
+    assert_eq!(outcome.metadata_errors, MetadataErrorCounts::default());
+}
+
+#[test]
+fn test_file_deleted_behind_the_cache_is_removed() {
+    let (tmp, mut cache) = TreeSpec::new()
+        .file("docs/kept.txt")
+        .file("docs/gone.txt")
+        .index("metadata_errors_vanished");
+    let last_event_id = cache.last_event_id();
+    let gone = tmp.path().join("docs/gone.txt");
+    fs::remove_file(&gone).unwrap();
+    assert_eq!(cache.search("gone").unwrap().len(), 1);
+
+    let outcome = search(&cache, "gone dm:>2000-01-01");
+    assert!(outcome.nodes.is_empty());
+    assert_eq!(outcome.metadata_errors.not_found, 1);
+    // Stat'ed twice, queued once.
+    search(&cache, "gone size:>=0");
+
+    let vanished = cache.take_vanished();
+    assert_eq!(vanished.len(), 1);
+    assert_eq!(vanished[0].path, gone);
+    assert_eq!(vanished[0].id, last_event_id);
+    assert!(cache.take_vanished().is_empty());
+
+    cache.handle_fs_events(vanished).unwrap();
+    assert!(cache.search("gone").unwrap().is_empty());
+    assert_eq!(cache.search("kept").unwrap().len(), 1);
+    assert_eq!(cache.last_event_id(), last_event_id);
+}
+
+#[test]
+fn test_vanished_file_that_came_back_stays() {
+    let (tmp, mut cache) = TreeSpec::new()
+        .file("flaky.txt")
+        .index("metadata_errors_returned");
+    let path = tmp.path().join("flaky.txt");
+    fs::remove_file(&path).unwrap();
+    search(&cache, "flaky size:>=0");
+    fs::write(&path, b"back").unwrap();
+
+    cache.handle_fs_events(cache.take_vanished()).unwrap();
+    let outcome = search(&cache, "flaky size:>=0");
+    assert_eq!(outcome.nodes.len(), 1);
+    assert_eq!(outcome.metadata_errors, MetadataErrorCounts::default());
+}
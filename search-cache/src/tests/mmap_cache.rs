@@ -0,0 +1,332 @@
+use super::prelude::*;
+use crate::{MmapCache, SearchError, SearchOptions, testkit::TreeSpec};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::{path::Path, time::Instant};
+
+fn build_tree() -> (TempDir, SearchCache) {
+    let tmp = TreeSpec::new()
+        .files([
+            "root/readme.md",
+            "root/main file.rs",
+            "root/one/a.txt",
+            "root/one/B.TXT",
+            "root/one/two/a.txt",
+            "root/one/two/c.rs",
+        ])
+        .dir("root/empty")
+        .create("mmap_cache");
+    let cache = SearchCache::walk_fs(tmp.path().join("root"));
+    (tmp, cache)
+}
+
+fn open(tmp: &TempDir, cache: &SearchCache) -> MmapCache {
+    let path = tmp.path().join("cache.mmap");
+    cache.flush_to_mmap_file(&path).unwrap();
+    SearchCache::open_mmap(&path, None, None).unwrap()
+}
+
+fn classic_paths(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .into_iter()
+        .filter_map(|index| cache.node_path(index))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn mmap_paths(cache: &MmapCache, query: &str, options: SearchOptions) -> Vec<PathBuf> {
+    let mut paths = cache
+        .search_paths(query, options, CancellationToken::noop())
+        .unwrap();
+    paths.sort();
+    paths
+}
+
+fn event(path: &Path, id: u64, flag: EventFlag) -> FsEvent {
+    FsEvent {
+        path: path.to_path_buf(),
+        id,
+        flag,
+    }
+}
+
+const QUERIES: &[&str] = &[
+    "a",
+    ".txt",
+    "a.txt",
+    "one/a.txt",
+    "two/",
+    "/two/c",
+    "\"main file\"",
+    "txt !two",
+    "rs | md",
+    "<one | two> txt",
+    "regex:^[a-c]\\.",
+    "nothing-matches",
+    "",
+];
+
+#[test]
+fn test_queries_match_the_classic_cache() {
+    let (tmp, cache) = build_tree();
+    let mmap = open(&tmp, &cache);
+    assert_eq!(mmap.root(), cache.root());
+    assert_eq!(mmap.len(), cache.get_total_files());
+    for case_insensitive in [false, true] {
+        let options = SearchOptions {
+            case_insensitive,
+            skip_history: true,
+            ..Default::default()
+        };
+        for query in QUERIES {
+            assert_eq!(
+                mmap_paths(&mmap, query, options),
+                classic_paths(&cache, query, options),
+                "`{query}` (case_insensitive: {case_insensitive})"
+            );
+        }
+    }
+}
+
+#[test]
+fn test_filters_need_the_full_index() {
+    let (tmp, cache) = build_tree();
+    let mmap = open(&tmp, &cache);
+    let error = mmap
+        .search_paths(
+            "ext:txt",
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap_err();
+    assert!(error.to_string().contains("fully loaded"), "{error}");
+    let error = mmap
+        .search_paths("<a", SearchOptions::default(), CancellationToken::noop())
+        .unwrap_err();
+    assert!(matches!(error, SearchError::Parse(_)));
+    let token = CancellationToken::noop().with_deadline(Instant::now());
+    assert!(matches!(
+        mmap.search_paths("a", SearchOptions::default(), token),
+        Err(SearchError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_overlay_applies_events() {
+    let (tmp, mut cache) = build_tree();
+    let mut mmap = open(&tmp, &cache);
+    let root = cache.root().to_path_buf();
+    let id = cache.last_event_id();
+
+    fs::write(root.join("one/new.txt"), b"new").unwrap();
+    fs::create_dir_all(root.join("added/deep")).unwrap();
+    fs::write(root.join("added/deep/a.txt"), b"a").unwrap();
+    fs::remove_file(root.join("one/two/a.txt")).unwrap();
+    let events = || {
+        vec![
+            event(
+                &root.join("one/new.txt"),
+                id + 1,
+                EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            ),
+            event(
+                &root.join("added"),
+                id + 2,
+                EventFlag::ItemCreated | EventFlag::ItemIsDir,
+            ),
+            event(
+                &root.join("one/two/a.txt"),
+                id + 3,
+                EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+            ),
+        ]
+    };
+    mmap.apply_fs_events(events());
+    cache.handle_fs_events(events()).unwrap();
+    let options = SearchOptions {
+        skip_history: true,
+        ..Default::default()
+    };
+    for query in ["txt", "a.txt", "deep/a", "new", "added", "two"] {
+        assert_eq!(
+            mmap_paths(&mmap, query, options),
+            classic_paths(&cache, query, options),
+            "`{query}`"
+        );
+    }
+
+    // Removing a folder hides the records below it too.
+    fs::remove_dir_all(root.join("one")).unwrap();
+    let removed = vec![event(
+        &root.join("one"),
+        id + 4,
+        EventFlag::ItemRemoved | EventFlag::ItemIsDir,
+    )];
+    mmap.apply_fs_events(removed);
+    assert_eq!(
+        mmap_paths(&mmap, ".txt", options),
+        [root.join("added/deep/a.txt")]
+    );
+
+    let promoted = mmap.promote();
+    let fresh = SearchCache::walk_fs(root);
+    assert_eq!(promoted.get_total_files(), fresh.get_total_files());
+    for query in QUERIES {
+        assert_eq!(
+            classic_paths(&promoted, query, options),
+            classic_paths(&fresh, query, options),
+            "`{query}`"
+        );
+    }
+}
+
+#[test]
+fn test_overlay_and_promote_keep_the_ignore_paths() {
+    let (tmp, mut cache) = build_tree();
+    let root = cache.root().to_path_buf();
+    let path = tmp.path().join("cache.mmap");
+    cache.flush_to_mmap_file(&path).unwrap();
+    let skip = root.join("added/skip");
+    let mut mmap = SearchCache::open_mmap(&path, Some(vec![skip.clone()]), None).unwrap();
+
+    fs::create_dir_all(&skip).unwrap();
+    fs::write(skip.join("x.txt"), b"x").unwrap();
+    fs::write(root.join("added/keep.txt"), b"k").unwrap();
+    mmap.apply_fs_events(vec![event(
+        &root.join("added"),
+        cache.last_event_id() + 1,
+        EventFlag::ItemCreated | EventFlag::ItemIsDir,
+    )]);
+    let options = SearchOptions {
+        skip_history: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        mmap_paths(&mmap, "keep | x.txt", options),
+        [root.join("added/keep.txt")]
+    );
+    let mut promoted = mmap.promote();
+    assert_eq!(
+        classic_paths(&promoted, "keep | x.txt", options),
+        [root.join("added/keep.txt")]
+    );
+    promoted.rescan();
+    assert_eq!(
+        classic_paths(&promoted, "x.txt", options),
+        Vec::<PathBuf>::new()
+    );
+}
+
+#[test]
+fn test_promote_matches_the_classic_cache() {
+    let (tmp, cache) = build_tree();
+    let mmap = open(&tmp, &cache);
+    let mut promoted = mmap.promote();
+    let mut cache = cache;
+    assert_eq!(promoted.last_event_id(), cache.last_event_id());
+    let options = SearchOptions {
+        skip_history: true,
+        ..Default::default()
+    };
+    for query in QUERIES
+        .iter()
+        .chain(&["ext:txt", "folder:", "infolder:one"])
+    {
+        assert_eq!(
+            classic_paths(&promoted, query, options),
+            classic_paths(&cache, query, options),
+            "`{query}`"
+        );
+    }
+}
+
+#[test]
+fn test_rejects_damaged_files() {
+    let (tmp, cache) = build_tree();
+    let path = tmp.path().join("cache.mmap");
+    cache.flush_to_mmap_file(&path).unwrap();
+    let bytes = fs::read(&path).unwrap();
+
+    fs::write(&path, &bytes[..bytes.len() / 2]).unwrap();
+    assert!(SearchCache::open_mmap(&path, None, None).is_err());
+    fs::write(&path, b"not an index").unwrap();
+    let error = SearchCache::open_mmap(&path, None, None)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("Not an mmap index"), "{error}");
+    // A names length that wraps the offsets around.
+    let mut huge_names = bytes.clone();
+    huge_names[28..36].copy_from_slice(&u64::MAX.to_le_bytes());
+    fs::write(&path, &huge_names).unwrap();
+    let error = SearchCache::open_mmap(&path, None, None)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("out of range"), "{error}");
+    // The root's first child pointing back at the root.
+    let mut looped = bytes.clone();
+    let root_len = u32::from_le_bytes(bytes[36..40].try_into().unwrap()) as usize;
+    let first_child = 40 + root_len + 16;
+    looped[first_child..first_child + 4].copy_from_slice(&0u32.to_le_bytes());
+    fs::write(&path, &looped).unwrap();
+    let error = SearchCache::open_mmap(&path, None, None)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("preorder"), "{error}");
+    // A state word with both state bits set doesn't name a state.
+    let mut bad_state = bytes.clone();
+    bad_state[first_child + 15] = 0xff;
+    fs::write(&path, &bad_state).unwrap();
+    let error = SearchCache::open_mmap(&path, None, None)
+        .unwrap_err()
+        .to_string();
+    assert!(error.contains("metadata of node 0"), "{error}");
+}
+
+/// Cold start of the mmap index against decoding the regular cache file.
+/// `cargo test -p search-cache -- --ignored --nocapture mmap_cold_start`
+#[test]
+#[ignore = "timing"]
+fn test_mmap_cold_start() {
+    let tmp = TempDir::new("mmap_cold_start").unwrap();
+    let root = tmp.path().join("root");
+    for dir in 0..200 {
+        let dir = root.join(format!("dir{dir}"));
+        fs::create_dir_all(&dir).unwrap();
+        for file in 0..250 {
+            fs::write(dir.join(format!("file{file}.txt")), b"").unwrap();
+        }
+    }
+    let cache = SearchCache::walk_fs(root.clone());
+    let mmap_path = tmp.path().join("cache.mmap");
+    let zstd_path = tmp.path().join("cache.zstd");
+    cache.flush_to_mmap_file(&mmap_path).unwrap();
+    cache.flush_to_file(&zstd_path).unwrap();
+    let options = SearchOptions {
+        skip_history: true,
+        ..Default::default()
+    };
+
+    let start = Instant::now();
+    let mmap = SearchCache::open_mmap(&mmap_path, None, None).unwrap();
+    let hits = mmap
+        .search_paths("file17.txt", options, CancellationToken::noop())
+        .unwrap()
+        .len();
+    let mmap_time = start.elapsed();
+
+    let start = Instant::now();
+    let classic = SearchCache::try_read_persistent_cache(&root, &zstd_path, None, None).unwrap();
+    let classic_hits = classic
+        .search_with_options("file17.txt", options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .len();
+    let classic_time = start.elapsed();
+
+    println!("mmap: {mmap_time:?}, zstd: {classic_time:?}");
+    assert_eq!(hits, classic_hits);
+    assert!(mmap_time < classic_time);
+}
@@ -11,12 +11,51 @@ mod prelude {
 
 mod support;
 
+mod affix_filters;
 mod cache_flow;
+mod cleanup_filters;
+mod compact;
+mod cursors;
 mod date_edges;
 mod date_keywords;
 mod date_volume;
+mod debug_snapshot;
+mod depth_filters;
+mod event_ids;
+mod filter_sources;
+mod finder_tags;
+mod firmlinks;
+mod first_seen;
+mod folder_size;
+mod hard_links;
+mod hidden;
+mod initials;
 mod integration_filters;
+mod metadata_budget;
+mod metadata_errors;
+mod mmap_cache;
+mod name_buckets;
+mod name_highlights;
+mod natural_sort;
+mod node_paths;
+mod or_filter_arms;
+mod path_display;
+mod path_lookup;
+mod precedence;
+mod query_depth;
 mod query_logic;
+mod query_notes;
+mod query_variables;
+mod ranking;
+mod regex_terms;
+mod result_budget;
+mod scoring;
+mod search_scope;
+#[cfg(feature = "snapshots")]
+mod search_snapshot;
+mod search_within;
 mod size_filters;
+mod tags;
 mod traversal;
 mod type_filters;
+mod validate;
@@ -0,0 +1,93 @@
+use super::prelude::*;
+use crate::{NAME_POOL, SearchOptions, query::NAME_BUCKETS_DISABLED, testkit::TreeSpec};
+
+const QUERIES: &[&str] = &[
+    "r",
+    "R",
+    "re",
+    "report",
+    "Report",
+    "port",
+    "/docs/",
+    "docs/report",
+    "*.md",
+    "re*t",
+    "?otes.md",
+    "notes docs",
+    "report | main",
+    "!report",
+    "café",
+    "cafe\u{301}",
+    "é",
+    "ext:txt report",
+    "regex:^rep",
+    "regex:ort\\.txt$",
+    "regex:r.p",
+    "\"quarterly report\"",
+    "xyz",
+    "",
+];
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files([
+            "report.txt",
+            "REPORT.md",
+            "quarterly report.docx",
+            "docs/notes.md",
+            "docs/drafts/report.md",
+            "docs/Rapport.txt",
+            "src/main.rs",
+            "cafe\u{301}.txt",
+            "caf\u{e9} menu.txt",
+            "\u{212A}elvin.txt",
+        ])
+        .index("name_buckets")
+}
+
+fn search(cache: &SearchCache, query: &str, options: SearchOptions, buckets: bool) -> Vec<usize> {
+    NAME_BUCKETS_DISABLED.with(|disabled| disabled.set(!buckets));
+    let result = cache.search_with_options(query, options, CancellationToken::noop());
+    NAME_BUCKETS_DISABLED.with(|disabled| disabled.set(false));
+    result
+        .unwrap()
+        .nodes
+        .into_iter()
+        .map(|index| index.get())
+        .collect()
+}
+
+#[test]
+fn test_buckets_give_the_same_results_as_full_scans() {
+    let (_tmp, cache) = build_tree();
+    for case_insensitive in [false, true] {
+        for byte_exact in [false, true] {
+            let options = SearchOptions {
+                case_insensitive,
+                byte_exact,
+                ..SearchOptions::default()
+            };
+            for query in QUERIES {
+                assert_eq!(
+                    search(&cache, query, options, true),
+                    search(&cache, query, options, false),
+                    "{query:?} case_insensitive: {case_insensitive}, byte_exact: {byte_exact}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_buckets_narrow_the_scan() {
+    let (_tmp, cache) = build_tree();
+    assert!(!search(&cache, "Rapport", SearchOptions::default(), true).is_empty());
+    // Other tests fill the pool too, but few of its names hold "pp".
+    let candidates = NAME_POOL.hinted_candidates("rapport").unwrap();
+    assert!(
+        candidates < NAME_POOL.len() / 2,
+        "{candidates} of {}",
+        NAME_POOL.len()
+    );
+    assert_eq!(NAME_POOL.hinted_candidates("\u{e9}"), None);
+}
@@ -0,0 +1,132 @@
+use super::prelude::*;
+use crate::{SearchOptions, testkit::TreeSpec};
+use std::collections::BTreeMap;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files([
+            "report.txt",
+            "Report Final.pdf",
+            "notes.md",
+            "abcabc.txt",
+            "cafe\u{301}.txt",
+            "naïve.txt",
+        ])
+        .index("name_highlights")
+}
+
+/// File name to highlights of the results of `query`.
+fn highlights(
+    cache: &SearchCache,
+    query: &str,
+    options: SearchOptions,
+) -> BTreeMap<String, Option<Vec<(u32, u32)>>> {
+    cache
+        .query_files_with_options(query.to_string(), options, CancellationToken::noop())
+        .unwrap()
+        .into_iter()
+        .map(|node| {
+            let name = node.path.file_name().unwrap().to_str().unwrap().to_string();
+            (name, node.highlights)
+        })
+        .collect()
+}
+
+fn collecting() -> SearchOptions {
+    SearchOptions::default().with_collect_highlights(true)
+}
+
+fn highlighted<'a>(name: &'a str, ranges: &[(u32, u32)]) -> Vec<&'a str> {
+    ranges
+        .iter()
+        .map(|&(start, end)| &name[start as usize..end as usize])
+        .collect()
+}
+
+#[test]
+fn test_substring_ranges() {
+    let (_tmp, cache) = build_tree();
+    let found = highlights(&cache, "port", collecting());
+    assert_eq!(found["report.txt"], Some(vec![(2, 6)]));
+    assert_eq!(found["Report Final.pdf"], Some(vec![(2, 6)]));
+
+    // Repeated matches are all there, touching ones merged.
+    let found = highlights(&cache, "abc", collecting());
+    assert_eq!(found["abcabc.txt"], Some(vec![(0, 6)]));
+    let found = highlights(&cache, "bc", collecting());
+    assert_eq!(found["abcabc.txt"], Some(vec![(1, 3), (4, 6)]));
+}
+
+#[test]
+fn test_wildcard_highlights_its_literal_runs() {
+    let (_tmp, cache) = build_tree();
+    let found = highlights(&cache, "re*t.txt", collecting());
+    let ranges = found["report.txt"].clone().unwrap();
+    assert_eq!(ranges, [(0, 2), (5, 10)]);
+    assert_eq!(highlighted("report.txt", &ranges), ["re", "t.txt"]);
+
+    let found = highlights(&cache, "*.md", collecting());
+    assert_eq!(found["notes.md"], Some(vec![(5, 8)]));
+}
+
+#[test]
+fn test_case_insensitive_ranges() {
+    let (_tmp, cache) = build_tree();
+    let options = collecting().with_case_insensitive(true);
+    let found = highlights(&cache, "REPORT", options);
+    assert_eq!(found["report.txt"], Some(vec![(0, 6)]));
+    assert_eq!(found["Report Final.pdf"], Some(vec![(0, 6)]));
+}
+
+#[test]
+fn test_unicode_ranges_sit_on_char_boundaries() {
+    let (_tmp, cache) = build_tree();
+    let found = highlights(&cache, "ve", collecting());
+    let ranges = found["naïve.txt"].clone().unwrap();
+    assert_eq!(ranges, [(4, 6)]);
+    assert_eq!(highlighted("naïve.txt", &ranges), ["ve"]);
+
+    // Matched in NFC, the decomposed name gets the whole `e\u{301}`.
+    let found = highlights(&cache, "fé", collecting());
+    let name = "cafe\u{301}.txt";
+    let ranges = found[name].clone().unwrap();
+    assert_eq!(ranges, [(2, 6)]);
+    assert_eq!(highlighted(name, &ranges), ["fe\u{301}"]);
+}
+
+#[test]
+fn test_only_positive_name_terms_count() {
+    let (_tmp, cache) = build_tree();
+    let found = highlights(&cache, "report | notes", collecting());
+    assert_eq!(found["report.txt"], Some(vec![(0, 6)]));
+    assert_eq!(found["notes.md"], Some(vec![(0, 5)]));
+
+    let found = highlights(&cache, "ext:txt", collecting());
+    assert!(!found.is_empty());
+    assert!(found.values().all(|ranges| ranges == &Some(Vec::new())));
+
+    let found = highlights(&cache, "t !abc ext:txt", collecting());
+    assert_eq!(found["report.txt"], Some(vec![(5, 6), (7, 8), (9, 10)]));
+    assert!(!found.contains_key("abcabc.txt"));
+
+    let found = highlights(&cache, "regex:^rep", collecting());
+    assert_eq!(found["report.txt"], Some(vec![(0, 3)]));
+}
+
+#[test]
+fn test_highlights_are_off_by_default() {
+    let (_tmp, cache) = build_tree();
+    let found = highlights(&cache, "report", SearchOptions::default());
+    assert!(!found.is_empty());
+    assert!(found.values().all(Option::is_none));
+    let nodes = cache
+        .search_with_options("report", collecting(), CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    assert!(
+        cache
+            .expand_file_nodes(&nodes)
+            .iter()
+            .all(|node| node.highlights.is_none())
+    );
+}
@@ -0,0 +1,236 @@
+use super::prelude::*;
+use crate::{
+    RankMode, SearchOptions, SubnodeOrder, natural_cmp,
+    testkit::{TreeSpec, node_name},
+};
+use std::cmp::Ordering;
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Token {
+    /// Significant digits: a longer run is a larger number.
+    Number(usize, String),
+    Byte(u8),
+}
+
+/// Straightforward, allocating version of [`natural_cmp`].
+fn reference_cmp(a: &str, b: &str) -> Ordering {
+    fn tokens(name: &str) -> Vec<Token> {
+        let bytes = name.as_bytes();
+        let mut tokens = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i].is_ascii_digit() {
+                let len = bytes[i..].iter().take_while(|b| b.is_ascii_digit()).count();
+                let digits = name[i..i + len].trim_start_matches('0').to_string();
+                tokens.push(Token::Number(digits.len(), digits));
+                i += len;
+            } else {
+                tokens.push(Token::Byte(bytes[i].to_ascii_lowercase()));
+                i += 1;
+            }
+        }
+        tokens
+    }
+    // A number only ever meets another number or a non-digit byte, and the
+    // latter sorts by the byte alone.
+    fn cmp(a: &[Token], b: &[Token], a_name: &str, b_name: &str) -> Ordering {
+        for (x, y) in a.iter().zip(b) {
+            let ordering = match (x, y) {
+                (Token::Number(..), Token::Byte(byte)) => b'0'.cmp(byte),
+                (Token::Byte(byte), Token::Number(..)) => byte.cmp(&b'0'),
+                _ => x.cmp(y),
+            };
+            if ordering.is_ne() {
+                return ordering;
+            }
+        }
+        a.len()
+            .cmp(&b.len())
+            .then_with(|| a_name.as_bytes().cmp(b_name.as_bytes()))
+    }
+    cmp(&tokens(a), &tokens(b), a, b)
+}
+
+/// xorshift64*, enough to generate names reproducibly.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn name(&mut self) -> String {
+        const PIECES: &[&str] = &[
+            "a", "B", "file", "File", "0", "00", "1", "2", "9", "10", "007", " ", "_", ".", "é",
+            "É", "日",
+        ];
+        (0..self.below(6))
+            .map(|_| PIECES[self.below(PIECES.len())])
+            .collect()
+    }
+}
+
+#[test]
+fn test_natural_cmp_targeted_cases() {
+    let less = [
+        ("file2", "file10"),
+        ("file9.txt", "file10.txt"),
+        ("File2", "file10"),
+        ("file02", "file10"),
+        // Equal values: the bytes decide.
+        ("file007", "file7"),
+        ("file01", "file1"),
+        ("File1", "file1"),
+        ("abc", "ABD"),
+        ("x9", "x10y"),
+        ("x1", "x1a"),
+        ("", "a"),
+        ("1", "a"),
+        // Non-ASCII bytes are compared as they are.
+        ("z", "é"),
+        ("É", "é"),
+        ("café1", "café02"),
+        (
+            "n99999999999999999999999999999999",
+            "n100000000000000000000000000000000",
+        ),
+    ];
+    for (a, b) in less {
+        assert_eq!(natural_cmp(a, b), Ordering::Less, "{a} < {b}");
+        assert_eq!(natural_cmp(b, a), Ordering::Greater, "{b} > {a}");
+    }
+    for name in ["", "file10", "Read Me", "日本"] {
+        assert_eq!(natural_cmp(name, name), Ordering::Equal);
+    }
+
+    let mut names = vec!["file10", "File1", "file2", "file1", "file01", "file"];
+    names.sort_by(|a, b| natural_cmp(a, b));
+    assert_eq!(
+        names,
+        ["file", "File1", "file01", "file1", "file2", "file10"]
+    );
+}
+
+#[test]
+fn test_natural_cmp_matches_reference() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..20_000 {
+        let (a, b) = (rng.name(), rng.name());
+        assert_eq!(natural_cmp(&a, &b), reference_cmp(&a, &b), "{a:?} vs {b:?}");
+        assert_eq!(natural_cmp(&a, &b), natural_cmp(&b, &a).reverse());
+    }
+}
+
+#[test]
+fn test_natural_cmp_sorts_consistently() {
+    let mut rng = Rng(42);
+    for _ in 0..200 {
+        let mut names: Vec<String> = (0..40).map(|_| rng.name()).collect();
+        names.sort_by(|a, b| natural_cmp(a, b));
+        for pair in names.windows(2) {
+            assert_ne!(reference_cmp(&pair[0], &pair[1]), Ordering::Greater);
+        }
+        let mut reference = names.clone();
+        reference.sort_by(|a, b| reference_cmp(a, b));
+        assert_eq!(names, reference);
+    }
+}
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["file10", "file2", "File1", "docs/b10", "docs/b9", "docs/A1"])
+        .index("natural_sort")
+}
+
+fn names(cache: &SearchCache, nodes: &[crate::SlabIndex]) -> Vec<String> {
+    nodes.iter().map(|&index| node_name(cache, index)).collect()
+}
+
+#[test]
+fn test_children_of_in_natural_order() {
+    let (_tmp, cache) = build_tree();
+    let root = cache.file_nodes.root();
+    assert_eq!(
+        names(&cache, &cache.children_of(root, SubnodeOrder::NaturalName)),
+        ["docs", "File1", "file2", "file10"]
+    );
+    let mut slab = names(&cache, &cache.children_of(root, SubnodeOrder::DepthFirst));
+    assert_eq!(slab, names(&cache, &cache.file_nodes[root].children));
+    slab.sort();
+    assert_eq!(slab, ["File1", "docs", "file10", "file2"]);
+}
+
+#[test]
+fn test_subnodes_page_in_natural_order() {
+    let (_tmp, cache) = build_tree();
+    let root = cache.file_nodes.root();
+    let expected = ["docs", "A1", "b9", "b10", "File1", "file2", "file10"];
+
+    let page = cache
+        .subnodes_page(
+            root,
+            0,
+            usize::MAX,
+            SubnodeOrder::NaturalName,
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert_eq!(names(&cache, &page.items), expected);
+    assert_eq!(page.total, Some(expected.len()));
+
+    // Resuming from any position continues in the same order.
+    for split in 0..expected.len() {
+        let first = cache
+            .subnodes_page(
+                root,
+                0,
+                split,
+                SubnodeOrder::NaturalName,
+                CancellationToken::noop(),
+            )
+            .unwrap();
+        let mut paged = first.items;
+        if let Some(cursor) = first.next {
+            paged.extend(
+                cache
+                    .subnodes_page_after(cursor, usize::MAX, CancellationToken::noop())
+                    .unwrap()
+                    .items,
+            );
+        }
+        assert_eq!(names(&cache, &paged), expected, "split at {split}");
+        let skipped = cache
+            .subnodes_page(
+                root,
+                split,
+                usize::MAX,
+                SubnodeOrder::NaturalName,
+                CancellationToken::noop(),
+            )
+            .unwrap();
+        assert_eq!(names(&cache, &skipped.items), expected[split..]);
+    }
+}
+
+#[test]
+fn test_rank_by_natural_name() {
+    let (_tmp, cache) = build_tree();
+    let options = SearchOptions {
+        rank: RankMode::NameNatural,
+        ..Default::default()
+    };
+    let mut nodes = cache
+        .search_with_options("1", options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    // The name of the temporary root may hold a 1 too.
+    nodes.retain(|&index| index != cache.file_nodes.root());
+    assert_eq!(names(&cache, &nodes), ["A1", "b10", "File1", "file10"]);
+}
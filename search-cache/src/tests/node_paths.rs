@@ -0,0 +1,109 @@
+use super::prelude::*;
+use crate::{SlabIndex, testkit::TreeSpec};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::{path::Path, time::Instant};
+
+/// `depth` nested folders `d0/d1/...`, each holding `files` files and, next to
+/// the chain, a `side` folder with one file.
+fn build_deep_tree(depth: usize, files: usize) -> (TempDir, SearchCache) {
+    let mut spec = TreeSpec::new();
+    let mut folder = PathBuf::new();
+    for level in 0..depth {
+        folder.push(format!("d{level}"));
+        spec = spec
+            .file(folder.join("side/s.txt"))
+            .files((0..files).map(|file| folder.join(format!("f{file}.txt"))));
+    }
+    spec.index("node_paths")
+}
+
+fn all_nodes(cache: &SearchCache) -> Vec<SlabIndex> {
+    let mut nodes = cache.search_empty(CancellationToken::noop()).unwrap();
+    nodes.push(cache.file_nodes.root());
+    nodes
+}
+
+fn one_by_one(cache: &SearchCache, nodes: &[SlabIndex]) -> Vec<Option<PathBuf>> {
+    nodes.iter().map(|&index| cache.node_path(index)).collect()
+}
+
+fn index_of(cache: &SearchCache, path: &Path) -> SlabIndex {
+    cache.node_index_for_raw_path(path).unwrap()
+}
+
+#[test]
+fn test_node_paths_match_node_path() {
+    let (tmp, cache) = build_deep_tree(12, 3);
+    let mut nodes = all_nodes(&cache);
+    assert_eq!(cache.node_paths(&nodes), one_by_one(&cache, &nodes));
+
+    // Any order, repeats included.
+    nodes.reverse();
+    nodes.extend_from_within(..5);
+    let paths = cache.node_paths(&nodes);
+    assert_eq!(paths.len(), nodes.len());
+    assert_eq!(paths, one_by_one(&cache, &nodes));
+
+    let root = cache.file_nodes.root();
+    let d0 = index_of(&cache, &tmp.path().join("d0"));
+    assert_eq!(
+        cache.node_paths(&[root, d0]),
+        [Some(tmp.path().to_path_buf()), Some(tmp.path().join("d0"))]
+    );
+    assert!(cache.node_paths(&[]).is_empty());
+}
+
+#[test]
+fn test_node_paths_of_removed_nodes() {
+    let (tmp, mut cache) = build_deep_tree(6, 2);
+    let nodes = all_nodes(&cache);
+    let deep = tmp.path().join("d0/d1/d2/d3/d4/f0.txt");
+    let deep_index = index_of(&cache, &deep);
+    let d1_file = index_of(&cache, &tmp.path().join("d0/d1/f0.txt"));
+
+    // A folder gone from the slab without its subtree, as a stale result list
+    // may see it mid-update.
+    let d2 = index_of(&cache, &tmp.path().join("d0/d1/d2"));
+    cache.file_nodes.try_remove(d2).unwrap();
+    let paths = cache.node_paths(&nodes);
+    assert_eq!(paths, one_by_one(&cache, &nodes));
+    assert!(paths[nodes.iter().position(|&n| n == deep_index).unwrap()].is_none());
+    assert_eq!(
+        cache.node_paths(&[deep_index, d1_file]),
+        [None, Some(tmp.path().join("d0/d1/f0.txt"))]
+    );
+
+    // Results held across an event that removed their folder.
+    let (tmp, mut cache) = build_deep_tree(6, 2);
+    let nodes = all_nodes(&cache);
+    let d3 = tmp.path().join("d0/d1/d2/d3");
+    fs::remove_dir_all(&d3).unwrap();
+    let removed = FsEvent {
+        path: d3,
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsDir,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+    let paths = cache.node_paths(&nodes);
+    assert_eq!(paths, one_by_one(&cache, &nodes));
+    assert!(paths.iter().any(Option::is_none));
+    assert!(paths.iter().any(Option::is_some));
+}
+
+#[test]
+#[ignore]
+fn bench_node_paths() {
+    let (_tmp, cache) = build_deep_tree(40, 500);
+    let nodes = all_nodes(&cache);
+    let started = Instant::now();
+    let expected = one_by_one(&cache, &nodes);
+    let single = started.elapsed();
+    let started = Instant::now();
+    let paths = cache.node_paths(&nodes);
+    let batched = started.elapsed();
+    assert_eq!(paths, expected);
+    println!(
+        "{} nodes: node_path {single:?}, node_paths {batched:?}",
+        nodes.len()
+    );
+}
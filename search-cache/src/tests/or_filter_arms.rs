@@ -0,0 +1,109 @@
+use super::prelude::*;
+use crate::{
+    QueryNote, SearchOptions, SearchOutcome,
+    query::METADATA_STATS,
+    testkit::{TreeSpec, node_name},
+};
+
+const FILES: usize = 60;
+
+/// `data00..data59` are 1 KB for even numbers, empty otherwise.
+fn build_tree() -> (TempDir, SearchCache) {
+    (0..FILES)
+        .fold(TreeSpec::new().file("report.txt"), |spec, i| {
+            let len = if i % 2 == 0 { 1024 } else { 0 };
+            spec.sized_file(format!("data{i:02}"), len)
+        })
+        .index("or_filter_arms")
+}
+
+fn search(cache: &SearchCache, query: &str, limit: Option<usize>) -> (SearchOutcome, usize) {
+    let before = METADATA_STATS.with(|stats| stats.get());
+    let options = SearchOptions::default().with_filter_only_scan_limit(limit);
+    let outcome = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap();
+    (outcome, METADATA_STATS.with(|stats| stats.get()) - before)
+}
+
+fn names(cache: &SearchCache, outcome: &SearchOutcome) -> Vec<String> {
+    let mut names: Vec<String> = outcome
+        .nodes
+        .iter()
+        .map(|&index| node_name(cache, index))
+        .collect();
+    names.sort();
+    names
+}
+
+fn expected() -> Vec<String> {
+    let mut names: Vec<String> = (0..FILES)
+        .step_by(2)
+        .map(|i| format!("data{i:02}"))
+        .collect();
+    names.push("report.txt".to_string());
+    names.sort();
+    names
+}
+
+#[test]
+fn test_filter_arm_stats_at_most_the_limit() {
+    let (_tmp, cache) = build_tree();
+    let (outcome, stats) = search(&cache, "report | size:>100", Some(10));
+    assert_eq!(stats, 10);
+    assert_eq!(outcome.notes, [QueryNote::FilterArmTruncated]);
+    let found = names(&cache, &outcome);
+    assert!(found.contains(&"report.txt".to_string()));
+    assert!(found.len() < expected().len());
+    assert!(found.iter().all(|name| expected().contains(name)));
+
+    // The arm's position doesn't matter.
+    let (outcome, stats) = search(&cache, "size:>100 | report", Some(10));
+    assert!(stats <= 10);
+    assert!(names(&cache, &outcome).contains(&"report.txt".to_string()));
+}
+
+#[test]
+fn test_filter_arm_under_the_limit_is_complete() {
+    let (_tmp, cache) = build_tree();
+    let (outcome, stats) = search(&cache, "report | size:>100", Some(1_000));
+    assert!(outcome.notes.is_empty());
+    // Files only, the name arm's match isn't looked at again.
+    assert_eq!(stats, FILES);
+    assert_eq!(names(&cache, &outcome), expected());
+
+    // Cached metadata is free, the same query doesn't stat again.
+    let (outcome, stats) = search(&cache, "report | size:>100", Some(1));
+    assert_eq!(stats, 0);
+    assert!(outcome.notes.is_empty());
+    assert_eq!(names(&cache, &outcome), expected());
+}
+
+#[test]
+fn test_unbounded_filter_arm_checks_everything() {
+    let (_tmp, cache) = build_tree();
+    let (outcome, stats) = search(&cache, "report | size:>100", None);
+    assert!(outcome.notes.is_empty());
+    assert!(stats >= FILES);
+    assert_eq!(names(&cache, &outcome), expected());
+}
+
+#[test]
+fn test_date_arms_are_bounded_too() {
+    let (_tmp, cache) = build_tree();
+    let (outcome, stats) = search(&cache, "report | dm:>2000-01-01", Some(5));
+    assert_eq!(stats, 5);
+    assert_eq!(outcome.notes, [QueryNote::FilterArmTruncated]);
+    assert!(names(&cache, &outcome).contains(&"report.txt".to_string()));
+}
+
+#[test]
+fn test_filters_without_name_terms_are_not_bounded() {
+    let (_tmp, cache) = build_tree();
+    for query in ["size:>100", "size:>100 | ext:txt", "report size:>100"] {
+        let (outcome, _) = search(&cache, query, Some(1));
+        assert!(outcome.notes.is_empty(), "{query}");
+    }
+    let (outcome, _) = search(&cache, "size:>100 | ext:txt", Some(1));
+    assert_eq!(names(&cache, &outcome), expected());
+}
@@ -0,0 +1,156 @@
+use super::prelude::*;
+use crate::{
+    PathDisplay, SearchOptions, path_display::display_absolute, query_preprocessor::home_dir,
+    testkit::TreeSpec,
+};
+use std::path::Path;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["docs/drafts/plan.txt", "docs/guide.txt", "notes.txt"])
+        .index("path_display")
+}
+
+/// Display paths of the results of `query`, sorted.
+fn displayed(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<String> {
+    let mut paths: Vec<_> = cache
+        .query_files_with_options(query.to_string(), options, CancellationToken::noop())
+        .unwrap()
+        .into_iter()
+        .map(|node| node.display_path)
+        .collect();
+    paths.sort();
+    paths
+}
+
+/// `path` shown absolute, as for the user running the tests.
+fn absolute(path: &Path) -> String {
+    display_absolute(path, home_dir().as_deref().map(Path::new))
+}
+
+fn showing(path_display: PathDisplay) -> SearchOptions {
+    SearchOptions::default().with_path_display(path_display)
+}
+
+#[test]
+fn test_absolute_by_default() {
+    let (tmp, cache) = build_tree();
+    let found = displayed(&cache, "guide", SearchOptions::default());
+    let expected = tmp.path().join("docs/guide.txt");
+    assert_eq!(found, vec![absolute(&expected)]);
+    assert!(found[0].ends_with("/docs/guide.txt"));
+}
+
+#[test]
+fn test_root_relative() {
+    let (_tmp, cache) = build_tree();
+    let found = displayed(&cache, "txt", showing(PathDisplay::RootRelative));
+    assert_eq!(
+        found,
+        vec!["docs/drafts/plan.txt", "docs/guide.txt", "notes.txt"]
+    );
+    // The absolute path is kept for acting on the result.
+    let nodes = cache
+        .query_files_with_options(
+            "notes".to_string(),
+            showing(PathDisplay::RootRelative),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert_eq!(nodes[0].path, cache.file_nodes.path().join("notes.txt"));
+}
+
+#[test]
+fn test_scope_relative_to_innermost_infolder() {
+    let (tmp, cache) = build_tree();
+    let docs = tmp.path().join("docs");
+    let drafts = docs.join("drafts");
+    let query = format!("infolder:{} txt", docs.display());
+    assert_eq!(
+        displayed(&cache, &query, showing(PathDisplay::ScopeRelative)),
+        vec!["drafts/plan.txt", "guide.txt"]
+    );
+    // Root relative ignores the filter.
+    assert_eq!(
+        displayed(&cache, &query, showing(PathDisplay::RootRelative)),
+        vec!["docs/drafts/plan.txt", "docs/guide.txt"]
+    );
+    // The innermost of the filters and the scope wins.
+    let scope = cache.node_index_for_raw_path(&drafts).unwrap();
+    let options = SearchOptions {
+        scope_to: Some(scope),
+        ..showing(PathDisplay::ScopeRelative)
+    };
+    assert_eq!(displayed(&cache, &query, options), vec!["plan.txt"]);
+    let query = format!("parent:{} drafts", docs.display());
+    assert_eq!(
+        displayed(&cache, &query, showing(PathDisplay::ScopeRelative)),
+        vec!["drafts"]
+    );
+    let query = format!("infolder:{}", drafts.display());
+    assert_eq!(
+        displayed(&cache, &query, showing(PathDisplay::ScopeRelative)),
+        vec!["plan.txt"]
+    );
+}
+
+#[test]
+fn test_scope_relative_without_scope_is_root_relative() {
+    let (tmp, cache) = build_tree();
+    // Filters under an OR don't scope the search.
+    let query = format!(
+        "infolder:{} | notes",
+        tmp.path().join("docs/drafts").display()
+    );
+    assert_eq!(
+        displayed(&cache, &query, showing(PathDisplay::ScopeRelative)),
+        vec!["docs/drafts/plan.txt", "notes.txt"]
+    );
+}
+
+#[test]
+fn test_search_outcome_display_path() {
+    let (tmp, cache) = build_tree();
+    let outcome = cache
+        .search_with_options(
+            "plan",
+            showing(PathDisplay::RootRelative),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let path = cache.node_path(outcome.nodes[0]).unwrap();
+    assert_eq!(outcome.display_path(&path), "docs/drafts/plan.txt");
+    // The base folder itself is `.`.
+    assert_eq!(outcome.display_path(cache.file_nodes.path()), ".");
+    // Paths outside the base are shown whole.
+    let outside = tmp.path().parent().unwrap().join("elsewhere.txt");
+    assert_eq!(outcome.display_path(&outside), absolute(&outside));
+}
+
+#[test]
+fn test_home_is_abbreviated() {
+    let home = Path::new("/Users/alice");
+    assert_eq!(display_absolute(Path::new("/Users/alice"), Some(home)), "~");
+    assert_eq!(
+        display_absolute(Path::new("/Users/alice/Documents/a.txt"), Some(home)),
+        "~/Documents/a.txt"
+    );
+    // Only whole components match.
+    assert_eq!(
+        display_absolute(Path::new("/Users/alicexyz/a.txt"), Some(home)),
+        "/Users/alicexyz/a.txt"
+    );
+    assert_eq!(
+        display_absolute(Path::new("/Users/bob/a.txt"), Some(home)),
+        "/Users/bob/a.txt"
+    );
+    // A home of `/` would abbreviate every path.
+    assert_eq!(
+        display_absolute(Path::new("/tmp/a.txt"), Some(Path::new("/"))),
+        "/tmp/a.txt"
+    );
+    assert_eq!(
+        display_absolute(Path::new("/tmp/a.txt"), None),
+        "/tmp/a.txt"
+    );
+}
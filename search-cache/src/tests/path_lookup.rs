@@ -0,0 +1,100 @@
+use super::prelude::*;
+use crate::{
+    FileNodes, NAME_POOL, NameIndex, NodeSlab, RawNames, SlabNode, SlabNodeMetadataCompact,
+};
+use std::path::Path;
+
+/// Flip the case of every ASCII letter.
+fn flip_case(path: &Path) -> PathBuf {
+    path.to_str()
+        .unwrap()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect::<String>()
+        .into()
+}
+
+#[test]
+fn test_lookup_ignores_case_and_normalization() {
+    let tmp = TempDir::new("path_lookup").unwrap();
+    let reports = tmp.path().join("Reports");
+    let cafe = tmp.path().join("caf\u{e9}");
+    fs::create_dir_all(&reports).unwrap();
+    fs::create_dir_all(&cafe).unwrap();
+    fs::write(reports.join("Q1 Summary.txt"), b"q").unwrap();
+    fs::write(cafe.join("Cr\u{e8}me.txt"), b"c").unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+
+    let summary = reports.join("Q1 Summary.txt");
+    let expected = cache.node_index_for_raw_path(&summary).unwrap();
+    assert_eq!(cache.node_index_for_path_ci(&summary), Some(expected));
+    assert_eq!(cache.node_index_for_raw_path(&flip_case(&summary)), None);
+    // The root's components fold too.
+    assert_eq!(
+        cache.node_index_for_path_ci(&flip_case(&summary)),
+        Some(expected)
+    );
+    assert_eq!(
+        cache.node_index_for_path_ci(&tmp.path().join("REPORTS")),
+        cache.node_index_for_raw_path(&reports)
+    );
+
+    // Decomposed, as some apps hand paths over.
+    let creme = cache
+        .node_index_for_raw_path(&cafe.join("Cr\u{e8}me.txt"))
+        .unwrap();
+    let decomposed = tmp.path().join("cafe\u{301}/cre\u{300}me.TXT");
+    assert_eq!(cache.node_index_for_raw_path(&decomposed), None);
+    assert_eq!(cache.node_index_for_path_ci(&decomposed), Some(creme));
+
+    assert_eq!(
+        cache.node_index_for_path_ci(&tmp.path().join("reports/Q2 Summary.txt")),
+        None
+    );
+    assert_eq!(
+        cache.node_index_for_path_ci(Path::new("/elsewhere/Reports")),
+        None
+    );
+    assert_eq!(
+        cache.node_index_for_path_ci(tmp.path()),
+        cache.node_index_for_raw_path(tmp.path())
+    );
+}
+
+/// A case-sensitive volume can hold names differing only by case, which a
+/// case-insensitive one can't create, so the tree is built by hand.
+#[test]
+fn test_exact_name_wins_among_fold_equivalent_children() {
+    let mut slab = NodeSlab::new();
+    let root = slab.insert(SlabNode::new(
+        None,
+        NAME_POOL.push("root"),
+        SlabNodeMetadataCompact::none(),
+    ));
+    let mut push_child = |name: &str| {
+        let index = slab.insert(SlabNode::new(
+            Some(root),
+            NAME_POOL.push(name),
+            SlabNodeMetadataCompact::none(),
+        ));
+        slab[root].children.push(index);
+        index
+    };
+    let lower = push_child("readme.md");
+    let upper = push_child("README.md");
+    let file_nodes = FileNodes::new(PathBuf::from("/volume/root"), slab, root, RawNames::new());
+    let cache = SearchCache::new(file_nodes, 0, NameIndex::default(), None, None);
+
+    let lookup = |path: &str| cache.node_index_for_path_ci(Path::new(path));
+    assert_eq!(lookup("/volume/root/readme.md"), Some(lower));
+    assert_eq!(lookup("/volume/root/README.md"), Some(upper));
+    let folded = lookup("/volume/root/ReadMe.md");
+    assert!(folded == Some(lower) || folded == Some(upper));
+    assert_eq!(lookup("/volume/root/readme.txt"), None);
+}
@@ -0,0 +1,71 @@
+use super::prelude::*;
+use crate::{Precedence, SearchOptions, testkit::TreeSpec};
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["red.txt", "green.txt", "blue.txt", "green_blue.txt"])
+        .index("precedence")
+}
+
+fn names(cache: &SearchCache, query: &str, precedence: Precedence) -> Vec<String> {
+    let options = SearchOptions {
+        precedence,
+        ..Default::default()
+    };
+    let mut names: Vec<String> = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .iter()
+        .map(|&index| cache.file_nodes[index].name_and_parent.as_str().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_precedence_option_changes_grouping() {
+    let (_tmp, cache) = build_tree();
+    // (red OR green) AND blue
+    assert_eq!(
+        names(&cache, "red OR green blue", Precedence::Everything),
+        ["green_blue.txt"]
+    );
+    // red OR (green AND blue)
+    assert_eq!(
+        names(&cache, "red OR green blue", Precedence::Conventional),
+        ["green_blue.txt", "red.txt"]
+    );
+    assert_eq!(
+        names(&cache, "red OR green AND blue", Precedence::Conventional),
+        names(&cache, "red OR green blue", Precedence::Conventional)
+    );
+    // The default keeps Everything's rules.
+    assert_eq!(cache.search("red OR green blue").unwrap().len(), 1);
+}
+
+#[test]
+fn test_precedence_option_applies_within_results() {
+    let (_tmp, cache) = build_tree();
+    let base = cache.search(".txt").unwrap();
+    let within = |precedence| {
+        let options = SearchOptions {
+            precedence,
+            ..Default::default()
+        };
+        cache
+            .search_within(
+                &base,
+                "blue | red green",
+                options,
+                CancellationToken::noop(),
+            )
+            .unwrap()
+            .nodes
+            .len()
+    };
+    // (blue OR red) AND green
+    assert_eq!(within(Precedence::Everything), 1);
+    // blue OR (red AND green)
+    assert_eq!(within(Precedence::Conventional), 2);
+}
@@ -0,0 +1,75 @@
+use super::prelude::*;
+use crate::{
+    SearchError, SearchOptions,
+    testkit::{TreeSpec, node_name},
+};
+use cardinal_syntax::MAX_GROUP_DEPTH;
+use std::time::{Duration, Instant};
+
+/// `depth` groups, each ANDing `txt` with an OR of a miss and the next
+/// group, so the tree alternates AND and OR all the way down to `alpha`.
+fn nested(depth: usize) -> String {
+    let mut query = "alpha".to_string();
+    for level in 0..depth {
+        query = format!("(txt nope{level} | {query})");
+    }
+    query
+}
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["alpha.txt", "beta.txt", "alpha.md"])
+        .index("query_depth")
+}
+
+fn search(
+    cache: &SearchCache,
+    query: &str,
+    options: SearchOptions,
+) -> Result<Vec<String>, SearchError> {
+    let outcome = cache.search_with_options(query, options, CancellationToken::noop())?;
+    Ok(outcome
+        .nodes
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect())
+}
+
+#[test]
+fn test_deeply_nested_query_fails_fast() {
+    let (_tmp, cache) = build_tree();
+    let started = Instant::now();
+    let error = search(&cache, &nested(1_000), SearchOptions::default()).unwrap_err();
+    assert!(started.elapsed() < Duration::from_secs(1));
+    assert!(matches!(error, SearchError::TooDeep { depth } if depth == MAX_GROUP_DEPTH + 1));
+    assert_eq!(error.code(), "too_deep");
+    assert!(error.span().is_none());
+}
+
+#[test]
+fn test_nested_query_under_the_limit_evaluates() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(
+        search(&cache, &nested(100), SearchOptions::default()).unwrap(),
+        vec!["alpha.txt"]
+    );
+}
+
+#[test]
+fn test_query_depth_limit_is_adjustable() {
+    let (_tmp, cache) = build_tree();
+    let options = SearchOptions::default().with_max_query_depth(Some(10));
+    assert!(matches!(
+        search(&cache, &nested(11), options),
+        Err(SearchError::TooDeep { depth: 11 })
+    ));
+    assert_eq!(
+        search(&cache, &nested(10), options).unwrap(),
+        vec!["alpha.txt"]
+    );
+    // Unmatched groups stay parse errors.
+    assert_eq!(
+        search(&cache, "(alpha", options).unwrap_err().code(),
+        "parse"
+    );
+}
@@ -1,4 +1,14 @@
 use super::prelude::*;
+use crate::{
+    Scorer, SearchError, SearchOptions, SearchResult, SlabIndex, query_notes::QueryNotes,
+    result_budget::ResultBudget, testkit::TreeSpec,
+};
+use cardinal_syntax::parse_query;
+use hashbrown::HashSet;
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
 
 #[test]
 fn test_query_and_or_not_dedup_and_filtering() {
@@ -6,7 +16,7 @@ fn test_query_and_or_not_dedup_and_filtering() {
     fs::write(tmp.path().join("report.txt"), b"r").unwrap();
     fs::write(tmp.path().join("report.md"), b"r").unwrap();
     fs::write(tmp.path().join("other.txt"), b"o").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // OR: union should return 3 distinct results
     let or = cache.search("report OR ext:txt").unwrap();
@@ -29,7 +39,7 @@ fn test_regex_prefix_in_queries() {
     fs::write(tmp.path().join("Report Q1.md"), b"x").unwrap();
     fs::write(tmp.path().join("Report Q2.txt"), b"x").unwrap();
     fs::write(tmp.path().join("notes.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let idxs = cache.search("regex:^Report").unwrap();
     assert_eq!(idxs.len(), 2);
@@ -41,7 +51,7 @@ fn test_ext_list_and_intersection() {
     fs::write(tmp.path().join("a.txt"), b"x").unwrap();
     fs::write(tmp.path().join("b.md"), b"x").unwrap();
     fs::write(tmp.path().join("c.rs"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // ext list
     let list = cache.search("ext:txt;md").unwrap();
@@ -54,6 +64,72 @@ fn test_ext_list_and_intersection() {
     assert!(path.ends_with(PathBuf::from("b.md")));
 }
 
+#[test]
+fn test_ext_mixed_case_arguments_and_names() {
+    let tmp = TempDir::new("query_ext_case").unwrap();
+    for name in [
+        "photo.JPG",
+        "notes.TxT",
+        "readme.md",
+        "archive.tar.GZ",
+        "plain",
+    ] {
+        fs::write(tmp.path().join(name), b"x").unwrap();
+    }
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let names = |query: &str| {
+        let mut names: Vec<String> = cache
+            .search(query)
+            .unwrap()
+            .into_iter()
+            .map(|index| {
+                let path = cache.node_path(index).unwrap();
+                path.file_name().unwrap().to_string_lossy().into_owned()
+            })
+            .collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(names("ext:TxT;Md"), ["notes.TxT", "readme.md"]);
+    assert_eq!(names("ext:txt;MD"), ["notes.TxT", "readme.md"]);
+    assert_eq!(names("ext:.jpg"), ["photo.JPG"]);
+    assert_eq!(names("ext:Gz;.JpG"), ["archive.tar.GZ", "photo.JPG"]);
+    assert_eq!(
+        names("ext:!TXT;-"),
+        ["archive.tar.GZ", "photo.JPG", "readme.md"]
+    );
+    assert_eq!(names("type:picture"), ["photo.JPG"]);
+}
+
+#[test]
+fn test_ext_rejects_malformed_arguments() {
+    let tmp = TempDir::new("query_ext_invalid").unwrap();
+    fs::write(tmp.path().join("a.txt"), b"x").unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let error = |query: &str| cache.search(query).unwrap_err().to_string();
+
+    for query in [
+        "ext:txt;;md",
+        "ext:txt;",
+        "ext:;txt",
+        "ext:!txt;;md",
+        "ext:;",
+    ] {
+        assert!(
+            error(query).contains("empty entry"),
+            "{query}: {}",
+            error(query)
+        );
+    }
+    for query in ["ext:a/b", "ext:txt;docs/md", "ext:./txt"] {
+        assert!(error(query).contains("contains a `/`"), "{query}");
+    }
+    for query in ["ext:\"tar gz\"", "ext:\"txt;m d\""] {
+        assert!(error(query).contains("contains whitespace"), "{query}");
+    }
+}
+
 #[test]
 fn test_or_then_and_intersection_precedence() {
     let tmp = TempDir::new("query_bool_prec").unwrap();
@@ -61,7 +137,7 @@ fn test_or_then_and_intersection_precedence() {
     fs::write(tmp.path().join("b.md"), b"x").unwrap();
     fs::write(tmp.path().join("c.txt"), b"x").unwrap();
     fs::write(tmp.path().join("d.bin"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // OR has higher precedence; then intersect via implicit AND with ext:txt
     let res = cache.search("a OR b ext:txt").unwrap();
@@ -81,7 +157,7 @@ fn test_groups_override_boolean_precedence() {
     let tmp = TempDir::new("query_groups_prec").unwrap();
     fs::write(tmp.path().join("ab.txt"), b"x").unwrap();
     fs::write(tmp.path().join("c.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let res = cache.search("(a b) | c").unwrap();
     let names: Vec<_> = res.iter().map(|i| cache.node_path(*i).unwrap()).collect();
@@ -96,7 +172,7 @@ fn test_not_precedence_with_intersection() {
     fs::write(tmp.path().join("a.txt"), b"x").unwrap();
     fs::write(tmp.path().join("b.txt"), b"x").unwrap();
     fs::write(tmp.path().join("notes.md"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let res = cache.search("ext:txt !a").unwrap();
     assert_eq!(res.len(), 1);
@@ -110,7 +186,7 @@ fn test_regex_and_or_with_ext_intersection() {
     fs::write(tmp.path().join("Report Q1.md"), b"x").unwrap();
     fs::write(tmp.path().join("Report Q2.txt"), b"x").unwrap();
     fs::write(tmp.path().join("notes.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let res = cache.search("regex:^Report OR notes ext:txt").unwrap();
     assert_eq!(res.len(), 2);
@@ -134,8 +210,158 @@ fn test_extension_case_sensitivity_in_type_filter() {
     fs::write(tmp.path().join("image.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("graphic.PNG"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(results.len(), 3, "Should match case-insensitively");
 }
+
+fn partial_fixture(name: &str) -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files([
+            "src/alpha.rs",
+            "src/alpha.txt",
+            "src/beta.rs",
+            "docs/alpha.md",
+        ])
+        .index(name)
+}
+
+/// Evaluates `query` as parsed (without reordering) against an already expired token.
+/// `parent:` never checks the token, so it always completes while everything after it is cut.
+fn evaluate_expired(
+    cache: &mut SearchCache,
+    query: &str,
+    strict_cancellation: bool,
+) -> SearchResult<Vec<SlabIndex>> {
+    let expr = parse_query(query).unwrap().expr;
+    let options = SearchOptions {
+        strict_cancellation,
+        ..Default::default()
+    };
+    let token = CancellationToken::noop().with_deadline(Instant::now());
+    let budget = ResultBudget::new(None);
+    cache.evaluate_expr(&expr, options, token, &QueryNotes::default(), &budget)
+}
+
+fn full(cache: &mut SearchCache, query: &str) -> HashSet<SlabIndex> {
+    let expr = parse_query(query).unwrap().expr;
+    cache
+        .evaluate_expr(
+            &expr,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+            &QueryNotes::default(),
+            &ResultBudget::new(None),
+        )
+        .unwrap()
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn test_partial_and_returns_superset_so_far() {
+    let (tmp, mut cache) = partial_fixture("partial_and");
+    let query = format!("parent:{} alpha", tmp.path().join("src").display());
+
+    let partial: HashSet<_> = evaluate_expired(&mut cache, &query, false)
+        .expect("completed AND parts should be returned")
+        .into_iter()
+        .collect();
+    let full = full(&mut cache, &query);
+    assert_eq!(full.len(), 2);
+    assert_eq!(partial.len(), 3, "only parent: was applied");
+    assert!(partial.is_superset(&full));
+
+    assert!(matches!(
+        evaluate_expired(&mut cache, &query, true),
+        Err(SearchError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_partial_or_returns_subset_so_far() {
+    let (tmp, mut cache) = partial_fixture("partial_or");
+    let query = format!("parent:{} OR beta", tmp.path().join("docs").display());
+
+    let partial: HashSet<_> = evaluate_expired(&mut cache, &query, false)
+        .expect("completed OR branches should be returned")
+        .into_iter()
+        .collect();
+    let full = full(&mut cache, &query);
+    assert_eq!(full.len(), 2);
+    assert_eq!(partial.len(), 1, "only parent: was evaluated");
+    assert!(partial.is_subset(&full));
+
+    assert!(matches!(
+        evaluate_expired(&mut cache, &query, true),
+        Err(SearchError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_nothing_completed_is_not_partial() {
+    let (_tmp, mut cache) = partial_fixture("partial_nothing");
+    assert!(matches!(
+        evaluate_expired(&mut cache, "alpha beta", false),
+        Err(SearchError::Cancelled)
+    ));
+    assert!(matches!(
+        evaluate_expired(&mut cache, "alpha OR beta", false),
+        Err(SearchError::Cancelled)
+    ));
+}
+
+#[test]
+fn test_search_outcome_partial_flag() {
+    let (tmp, cache) = partial_fixture("partial_flag");
+    let query = format!("parent:{} alpha", tmp.path().join("src").display());
+
+    let options = SearchOptions {
+        deadline: Some(Duration::from_secs(3600)),
+        ..Default::default()
+    };
+    let outcome = cache
+        .search_with_options(&query, options, CancellationToken::noop())
+        .unwrap();
+    assert!(!outcome.partial);
+    assert_eq!(outcome.nodes.len(), 2);
+
+    let options = SearchOptions {
+        deadline: Some(Duration::ZERO),
+        ..Default::default()
+    };
+    let result = cache.search_with_options(&query, options, CancellationToken::noop());
+    assert!(matches!(result, Err(SearchError::Cancelled)));
+    // Only the complete search was recorded.
+    assert_eq!(cache.query_history().len(), 1);
+}
+
+#[test]
+fn test_partial_flag_follows_the_evaluation() {
+    let (tmp, cache) = partial_fixture("partial_late");
+    let query = format!("parent:{} alpha", tmp.path().join("src").display());
+
+    // The token fires while ranking, once evaluation is over.
+    static VERSION: AtomicU64 = AtomicU64::new(0);
+    let token = CancellationToken::with_active_version(&VERSION, 1);
+    let scorer = Scorer::leak(|_| {
+        VERSION.store(2, Ordering::Relaxed);
+        0.0
+    });
+    let options = SearchOptions::default().with_scorer(scorer);
+    let outcome = cache.search_with_options(&query, options, token).unwrap();
+    assert!(token.is_cancelled());
+    assert!(!outcome.partial);
+    assert_eq!(outcome.nodes.len(), 2);
+    assert_eq!(cache.query_history().len(), 1);
+
+    let expr = parse_query(&query).unwrap().expr;
+    let expired = CancellationToken::noop().with_deadline(Instant::now());
+    let notes = QueryNotes::default();
+    let budget = ResultBudget::new(None);
+    cache
+        .evaluate_expr(&expr, SearchOptions::default(), expired, &notes, &budget)
+        .unwrap();
+    assert!(notes.was_cut_short());
+}
@@ -0,0 +1,120 @@
+use super::prelude::*;
+use crate::{QueryNote, SearchOptions, SearchOutcome, testkit::TreeSpec};
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .dir("docs/guide")
+        .file("notes.md")
+        .sized_file("big.bin", 4096)
+        .file("docs/readme.md")
+        .index("query_notes")
+}
+
+fn outcome(cache: &SearchCache, query: &str) -> SearchOutcome {
+    cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+}
+
+#[test]
+fn test_missing_folder_is_noted() {
+    let (tmp, cache) = build_tree();
+    let missing = tmp.path().join("archive");
+    for filter in ["parent", "infolder", "nosubfolders"] {
+        let query = format!("{filter}:{}", missing.display());
+        let outcome = outcome(&cache, &query);
+        assert!(outcome.nodes.is_empty(), "{query}");
+        assert_eq!(
+            outcome.notes,
+            [QueryNote::ParentNotIndexed(missing.clone())],
+            "{query}"
+        );
+    }
+
+    // The rest of the query still counts, and each note is given once.
+    let query = format!("readme | parent:{0} | infolder:{0}", missing.display());
+    let outcome = outcome(&cache, &query);
+    assert_eq!(outcome.nodes.len(), 1);
+    assert_eq!(outcome.notes, [QueryNote::ParentNotIndexed(missing)]);
+}
+
+#[test]
+fn test_file_filters_over_folders_only_are_noted() {
+    let (_tmp, cache) = build_tree();
+    for query in ["folder: size:>0", "folder: disksize:>0", "folder: ext:md"] {
+        let outcome = outcome(&cache, query);
+        assert!(outcome.nodes.is_empty(), "{query}");
+        assert_eq!(
+            outcome.notes,
+            [QueryNote::FilterExcludesAllDirectories],
+            "{query}"
+        );
+    }
+    // Folders among files are skipped silently, even when nothing is left.
+    for query in ["size:>1gb", "ext:pdf", "d size:>1gb"] {
+        let outcome = outcome(&cache, query);
+        assert!(outcome.nodes.is_empty(), "{query}");
+        assert!(outcome.notes.is_empty(), "{query}");
+    }
+}
+
+#[test]
+fn test_empty_extension_list_is_noted() {
+    let (_tmp, cache) = build_tree();
+    for query in ["ext:.", "ext:.;."] {
+        let outcome = outcome(&cache, query);
+        assert!(outcome.nodes.is_empty(), "{query}");
+        assert_eq!(outcome.notes, [QueryNote::EmptyExtensionList], "{query}");
+    }
+    // A missing argument is still a mistake in the query.
+    assert!(cache.search("ext:").is_err());
+}
+
+#[test]
+fn test_search_within_results_notes_too() {
+    let (tmp, cache) = build_tree();
+    let base = cache.search("docs").unwrap();
+    let outcome = cache
+        .search_within(
+            &base,
+            "ext:md",
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert!(outcome.nodes.is_empty());
+    assert_eq!(outcome.notes, [QueryNote::FilterExcludesAllDirectories]);
+
+    let query = format!("parent:{}", tmp.path().join("missing").display());
+    let outcome = cache
+        .search_within(
+            &base,
+            &query,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert!(outcome.nodes.is_empty());
+    assert_eq!(outcome.notes.len(), 1);
+}
+
+#[test]
+fn test_ordinary_queries_have_no_notes() {
+    let (tmp, cache) = build_tree();
+    let docs = tmp.path().join("docs");
+    for query in [
+        String::new(),
+        "readme".to_string(),
+        "ext:md".to_string(),
+        "ext:none".to_string(),
+        "size:>1kb".to_string(),
+        "folder:".to_string(),
+        "file: size:>0".to_string(),
+        "missing-name".to_string(),
+        format!("parent:{}", docs.display()),
+        format!("infolder:{} ext:md", docs.display()),
+        format!("nosubfolders:{}", docs.display()),
+    ] {
+        assert!(outcome(&cache, &query).notes.is_empty(), "{query}");
+    }
+}
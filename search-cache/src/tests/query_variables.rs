@@ -0,0 +1,171 @@
+use super::{prelude::*, support::assert_file_hits};
+use crate::{
+    SearchError, SearchOptions,
+    query_preprocessor::home_dir,
+    query_variables::{TODAY, expand_variables},
+    testkit::{set_file_times, ts_for_date},
+};
+use jiff::civil::date;
+use std::path::Path;
+
+fn expand(line: &str, root: &Path) -> String {
+    expand_variables(line, root, SearchOptions::default())
+        .unwrap()
+        .into_owned()
+}
+
+fn set_today(today: jiff::civil::Date) {
+    TODAY.with(|cell| cell.set(Some(today)));
+}
+
+#[test]
+fn test_each_variable_expands() {
+    set_today(date(2024, 3, 1));
+    let root = Path::new("/Volumes/Data");
+    assert_eq!(expand("dm:%today%", root), "dm:2024-03-01");
+    assert_eq!(expand("dm:%yesterday%", root), "dm:2024-02-29");
+    assert_eq!(expand("dm:>=%YESTERDAY%", root), "dm:>=2024-02-29");
+    assert_eq!(
+        expand("infolder:%root%/Projects", root),
+        "infolder:/Volumes/Data/Projects"
+    );
+    let home = home_dir().expect("HOME is set");
+    assert_eq!(expand("parent:%home%", root), format!("parent:{home}"));
+    for (variable, folder) in [
+        ("desktop", "Desktop"),
+        ("documents", "Documents"),
+        ("downloads", "Downloads"),
+    ] {
+        let expected = Path::new(&home).join(folder);
+        assert_eq!(
+            expand(&format!("infolder:%{variable}%"), root),
+            format!("infolder:{}", expected.display())
+        );
+    }
+}
+
+#[test]
+fn test_only_filter_arguments_expand() {
+    set_today(date(2024, 3, 1));
+    let root = Path::new("/data");
+    for line in [
+        "%today%",
+        "\"dm:%today%\"",
+        "regex:%today%",
+        "100% ext:txt",
+        "dm:%20%",
+        "",
+    ] {
+        assert_eq!(expand(line, root), line);
+    }
+    assert_eq!(
+        expand("(dm:%today%|dc:%today%) %today%", root),
+        "(dm:2024-03-01|dc:2024-03-01) %today%"
+    );
+    assert_eq!(
+        expand("<dm:%today%> !dc:%yesterday%", root),
+        "<dm:2024-03-01> !dc:2024-02-29"
+    );
+}
+
+#[test]
+fn test_values_with_spaces_are_quoted() {
+    let root = Path::new("/Volumes/My Data");
+    assert_eq!(
+        expand("infolder:%root%/docs report", root),
+        "infolder:\"/Volumes/My Data/docs\" report"
+    );
+    // Already quoted arguments stay as they are around the value.
+    assert_eq!(
+        expand("infolder:\"%root%/old docs\" report", root),
+        "infolder:\"/Volumes/My Data/old docs\" report"
+    );
+    assert_eq!(
+        expand("infolder:\"%root%", root),
+        "infolder:\"/Volumes/My Data"
+    );
+    // Values without spaces don't need it.
+    assert_eq!(
+        expand("infolder:%root%", Path::new("/data")),
+        "infolder:/data"
+    );
+
+    let quoted = Path::new("/Volumes/\"Data\"");
+    let err = expand_variables("infolder:%root%", quoted, SearchOptions::default()).unwrap_err();
+    assert_eq!(err.code(), "parse");
+    assert_eq!(err.span(), Some(9));
+}
+
+#[test]
+fn test_unknown_variable_points_at_it() {
+    let root = Path::new("/data");
+    let err = expand_variables("report dm:%tomorrow%", root, SearchOptions::default()).unwrap_err();
+    assert!(matches!(
+        &err,
+        SearchError::UnknownVariable { name, position: 10 } if name == "tomorrow"
+    ));
+    assert_eq!(err.code(), "unknown_variable");
+    assert_eq!(err.span(), Some(10));
+    assert_eq!(err.to_string(), "Unknown variable %tomorrow% (at byte 10)");
+
+    let err =
+        expand_variables("infolder:\"%home%/%nope%\"", root, SearchOptions::default()).unwrap_err();
+    assert_eq!(err.span(), Some(17));
+}
+
+#[test]
+fn test_expansion_can_be_turned_off() {
+    let options = SearchOptions::default().with_expand_variables(false);
+    let line = "dm:%today% infolder:%nope%";
+    let expanded = expand_variables(line, Path::new("/data"), options).unwrap();
+    assert_eq!(expanded, line);
+}
+
+#[test]
+fn test_root_variable_in_a_folder_with_spaces() {
+    let tmp = TempDir::new("query variables").unwrap();
+    let root = tmp.path().join("My Files");
+    fs::create_dir_all(root.join("old docs")).unwrap();
+    fs::write(root.join("old docs/report.txt"), b"r").unwrap();
+    fs::write(root.join("report.txt"), b"r").unwrap();
+    let cache = SearchCache::walk_fs(root.clone());
+
+    let nodes = cache.search("report infolder:\"%root%/old docs\"").unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(
+        cache.node_path(nodes[0]).unwrap(),
+        root.join("old docs/report.txt")
+    );
+    assert_eq!(cache.search("report parent:%root%").unwrap().len(), 1);
+    assert!(matches!(
+        cache.search("report parent:%nope%"),
+        Err(SearchError::UnknownVariable { .. })
+    ));
+}
+
+#[test]
+fn test_saved_search_follows_the_clock() {
+    let tmp = TempDir::new("query_variables_clock").unwrap();
+    fs::write(tmp.path().join("monday.txt"), b"m").unwrap();
+    fs::write(tmp.path().join("tuesday.txt"), b"t").unwrap();
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let monday = cache.search("monday.txt").unwrap()[0];
+    let tuesday = cache.search("tuesday.txt").unwrap()[0];
+    let then = ts_for_date(2024, 5, 6);
+    set_file_times(&mut cache, monday, then, then);
+    let then = ts_for_date(2024, 5, 7);
+    set_file_times(&mut cache, tuesday, then, then);
+
+    let saved = "ext:txt dm:%today%";
+    set_today(date(2024, 5, 6));
+    assert_file_hits(&cache, &cache.search(saved).unwrap(), &["monday.txt"]);
+    set_today(date(2024, 5, 7));
+    assert_file_hits(&cache, &cache.search(saved).unwrap(), &["tuesday.txt"]);
+    assert_file_hits(
+        &cache,
+        &cache.search("ext:txt dm:%yesterday%").unwrap(),
+        &["monday.txt"],
+    );
+    set_today(date(2024, 5, 8));
+    assert_file_hits(&cache, &cache.search(saved).unwrap(), &[]);
+}
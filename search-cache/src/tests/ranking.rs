@@ -0,0 +1,179 @@
+use super::prelude::*;
+use crate::{
+    RankMode, SearchOptions, SlabIndex,
+    query_history::unix_now,
+    testkit::{TreeSpec, node_name, set_file_times},
+};
+use cardinal_sdk::{EventFlag, FsEvent};
+
+/// None of the files has metadata.
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["a.txt", "b.txt", "c.txt", "d.txt", "e.txt"])
+        .index("ranking")
+}
+
+fn index_of(cache: &SearchCache, name: &str) -> SlabIndex {
+    let nodes = cache.search(name).unwrap();
+    assert_eq!(nodes.len(), 1, "{name}");
+    nodes[0]
+}
+
+fn ranked(cache: &SearchCache, query: &str, rank: RankMode) -> Vec<String> {
+    let options = SearchOptions {
+        rank,
+        ..Default::default()
+    };
+    cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect()
+}
+
+/// Modification times for a, b and d; c and e stay without metadata.
+fn set_mtimes(cache: &mut SearchCache) {
+    for (name, mtime) in [("a.txt", 200), ("b.txt", 300), ("d.txt", 100)] {
+        let index = index_of(cache, name);
+        set_file_times(cache, index, mtime, mtime);
+    }
+}
+
+/// `names` in the order the unranked search returns them.
+fn slab_order(cache: &SearchCache, names: &[&str]) -> Vec<String> {
+    ranked(cache, ".txt", RankMode::None)
+        .into_iter()
+        .filter(|name| names.contains(&name.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_recent_first_puts_uncached_metadata_last() {
+    let (_tmp, mut cache) = build_tree();
+    set_mtimes(&mut cache);
+    let names = ranked(&cache, ".txt", RankMode::RecentFirst);
+    assert_eq!(names[..3], ["b.txt", "a.txt", "d.txt"]);
+    // Nodes without metadata keep their relative order and aren't stat'ed.
+    assert_eq!(names[3..], slab_order(&cache, &["c.txt", "e.txt"]));
+    let c = index_of(&cache, "c.txt");
+    assert!(cache.file_nodes[c].metadata.is_none());
+}
+
+#[test]
+fn test_no_ranking_by_default() {
+    let (_tmp, mut cache) = build_tree();
+    set_mtimes(&mut cache);
+    assert_eq!(SearchOptions::default().rank, RankMode::None);
+    let names: Vec<String> = cache
+        .search(".txt")
+        .unwrap()
+        .into_iter()
+        .map(|index| node_name(&cache, index))
+        .collect();
+    assert_eq!(ranked(&cache, ".txt", RankMode::None), names);
+}
+
+#[test]
+fn test_frecency_boosts_recorded_accesses() {
+    let (_tmp, mut cache) = build_tree();
+    set_mtimes(&mut cache);
+    let d = index_of(&cache, "d.txt");
+    let e = index_of(&cache, "e.txt");
+    cache.record_access(d);
+    cache.record_access(d);
+    cache.record_access(e);
+    assert_eq!(
+        ranked(&cache, ".txt", RankMode::Frecency),
+        ["d.txt", "e.txt", "b.txt", "a.txt", "c.txt"]
+    );
+    // Other modes ignore the accesses.
+    assert_eq!(
+        ranked(&cache, ".txt", RankMode::RecentFirst)[..3],
+        ["b.txt", "a.txt", "d.txt"]
+    );
+    // Without any access, frecency falls back to recency.
+    let (_tmp, mut fresh) = build_tree();
+    set_mtimes(&mut fresh);
+    assert_eq!(
+        ranked(&fresh, ".txt", RankMode::Frecency),
+        ranked(&fresh, ".txt", RankMode::RecentFirst)
+    );
+}
+
+#[test]
+fn test_ranking_happens_before_truncation() {
+    let (_tmp, mut cache) = build_tree();
+    set_mtimes(&mut cache);
+    // Callers cut the ranked list, as the app's `max_results` does.
+    let mut names = ranked(&cache, ".txt", RankMode::RecentFirst);
+    names.truncate(2);
+    assert_eq!(names, ["b.txt", "a.txt"]);
+
+    // Narrowing results ranks the narrowed list again.
+    let base = cache.search(".txt").unwrap();
+    let options = SearchOptions {
+        rank: RankMode::RecentFirst,
+        ..Default::default()
+    };
+    let nodes = cache
+        .search_within(
+            &base,
+            "a.txt | d.txt | c.txt",
+            options,
+            CancellationToken::noop(),
+        )
+        .unwrap()
+        .nodes;
+    let names: Vec<String> = nodes.iter().map(|&i| node_name(&cache, i)).collect();
+    assert_eq!(names, ["a.txt", "d.txt", "c.txt"]);
+}
+
+#[test]
+fn test_access_log_persists_and_forgets_removed_nodes() {
+    let (tmp, cache) = build_tree();
+    let cache_path = tmp.path().join("cache.zstd");
+    cache.record_access(index_of(&cache, "a.txt"));
+    cache.record_access(index_of(&cache, "b.txt"));
+    cache.flush_to_file(&cache_path).unwrap();
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.access_log().len(), 2);
+    assert_eq!(
+        ranked(&loaded, "a.txt | b.txt | c.txt", RankMode::Frecency)[2],
+        "c.txt"
+    );
+
+    let mut cache = loaded;
+    let a = tmp.path().join("a.txt");
+    fs::remove_file(&a).unwrap();
+    let removed = FsEvent {
+        path: a,
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert_eq!(cache.access_log().len(), 1);
+}
+
+#[test]
+fn test_rescan_keeps_access_scores() {
+    let (tmp, mut cache) = build_tree();
+    let d = index_of(&cache, "d.txt");
+    cache.record_access(d);
+    cache.record_access(d);
+    cache.record_access(index_of(&cache, "b.txt"));
+    let now = unix_now();
+    let score = cache.access_log().score_at(d, now);
+    // A new file before d.txt renumbers the nodes of the next walk.
+    fs::write(tmp.path().join("0.txt"), b"0").unwrap();
+    cache.rescan();
+    let d = index_of(&cache, "d.txt");
+    assert_eq!(cache.access_log().len(), 2);
+    assert_eq!(cache.access_log().score_at(d, now), score);
+    assert_eq!(
+        ranked(&cache, "b.txt | c.txt | d.txt", RankMode::Frecency),
+        ["d.txt", "b.txt", "c.txt"]
+    );
+}
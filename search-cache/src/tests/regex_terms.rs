@@ -0,0 +1,140 @@
+use super::prelude::*;
+use crate::{
+    SearchOptions, SegmentKind, SegmentMatcher, SlabIndex, query::regex_matcher, testkit::TreeSpec,
+};
+use std::time::Duration;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    let names = [
+        "Report.txt",
+        "report.txt",
+        "Annual Report",
+        "notes.md",
+        "a+b (1).txt",
+        "$price",
+        "x^y",
+        "cafe\u{301}.txt",
+        "café",
+    ];
+    // Each name at the root and again inside the `Report` folder.
+    TreeSpec::new()
+        .files(names)
+        .files(names.map(|name| format!("Report/{name}")))
+        .index("regex_terms")
+}
+
+fn search(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<SlabIndex> {
+    let mut nodes = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    nodes.sort_unstable();
+    nodes
+}
+
+fn kind_of(pattern: &str) -> Option<SegmentKind> {
+    match regex_matcher(pattern, SearchOptions::default()).unwrap() {
+        SegmentMatcher::Plain { kind, .. } => Some(kind),
+        SegmentMatcher::Regex { .. } => None,
+    }
+}
+
+#[test]
+fn test_anchored_literals_skip_the_regex_engine() {
+    assert!(matches!(kind_of("^Report"), Some(SegmentKind::Prefix)));
+    assert!(matches!(kind_of("\\.txt$"), Some(SegmentKind::Suffix)));
+    assert!(matches!(
+        kind_of("^Report\\.txt$"),
+        Some(SegmentKind::Exact)
+    ));
+    assert!(matches!(
+        kind_of("^a\\+b \\(1\\)"),
+        Some(SegmentKind::Prefix)
+    ));
+    for pattern in [
+        "Report",
+        "^Rep.rt",
+        "^\\d",
+        "^a|b",
+        "\\$$x",
+        "^(?i)report",
+        "txt$|md$",
+    ] {
+        assert!(kind_of(pattern).is_none(), "{pattern}");
+    }
+}
+
+#[test]
+fn test_anchored_fast_path_matches_the_regex_path() {
+    let (_tmp, cache) = build_tree();
+    let literals = [
+        "Report",
+        "report",
+        "Report\\.txt",
+        "\\.txt",
+        "a\\+b \\(1\\)",
+        "\\$price",
+        "x\\^y",
+        "café",
+        "cafe\u{301}",
+        "Annual Report",
+        "missing",
+    ];
+    for case_insensitive in [false, true] {
+        for byte_exact in [false, true] {
+            let options = SearchOptions {
+                case_insensitive,
+                byte_exact,
+                ..Default::default()
+            };
+            for literal in literals {
+                for (fast, slow) in [
+                    (format!("^{literal}"), format!("^(?:{literal})")),
+                    (format!("{literal}$"), format!("(?:{literal})$")),
+                    (format!("^{literal}$"), format!("^(?:{literal})$")),
+                ] {
+                    assert_eq!(
+                        search(&cache, &format!("regex:\"{fast}\""), options),
+                        search(&cache, &format!("regex:\"{slow}\""), options),
+                        "{fast} (case_insensitive: {case_insensitive}, byte_exact: {byte_exact})"
+                    );
+                }
+            }
+        }
+    }
+    assert_eq!(
+        search(&cache, "regex:\"^Report$\"", SearchOptions::default()).len(),
+        1
+    );
+}
+
+#[test]
+fn test_oversized_regex_is_rejected_with_a_readable_error() {
+    let (_tmp, cache) = build_tree();
+    let err = cache.search("regex:\"\\w{100}\"").unwrap_err();
+    assert!(err.to_string().contains("too complex"), "{err}");
+    assert_eq!(err.code(), "internal");
+    // Nothing changes for ordinary patterns.
+    assert!(!cache.search("regex:\"[a-z]{3}\"").unwrap().is_empty());
+}
+
+#[test]
+fn test_regex_time_budget_stops_the_search() {
+    let (_tmp, cache) = build_tree();
+    let options = SearchOptions {
+        regex_time_budget: Duration::ZERO,
+        ..Default::default()
+    };
+    for query in [
+        "regex:\"(a+)+$\"",
+        "regex:^Report",
+        "Report regex:\"(x+)+y\"",
+    ] {
+        let err = cache
+            .search_with_options(query, options, CancellationToken::noop())
+            .unwrap_err();
+        assert!(err.to_string().contains("took longer"), "{query}: {err}");
+    }
+    // The budget is per query, other terms don't count against it.
+    assert!(!cache.search("regex:\"(e+)+$\"").unwrap().is_empty());
+}
@@ -0,0 +1,84 @@
+use super::prelude::*;
+use crate::{
+    SearchError, SearchOptions, query_notes::QueryNotes, result_budget::ResultBudget,
+    testkit::TreeSpec,
+};
+use cardinal_syntax::{optimize_query, parse_query};
+
+const FILES: usize = 200;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files((0..FILES).map(|i| format!("file{i:03}.txt")))
+        .index("result_budget")
+}
+
+fn search(cache: &SearchCache, query: &str, max: Option<usize>) -> Result<usize, SearchError> {
+    let options = SearchOptions::default().with_max_intermediate_results(max);
+    cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .map(|outcome| outcome.nodes.len())
+}
+
+#[test]
+fn test_broad_or_trips_the_cap() {
+    let (_tmp, cache) = build_tree();
+    // Both arms match every file, the union holds them twice at its peak.
+    let error = search(&cache, "file | txt", Some(300)).unwrap_err();
+    assert!(matches!(error, SearchError::TooBroad { entries: 400 }));
+    assert_eq!(error.code(), "too_broad");
+    assert!(error.span().is_none());
+
+    let error = search(&cache, "file | txt | 1", Some(300)).unwrap_err();
+    assert!(matches!(error, SearchError::TooBroad { .. }));
+    let error = search(&cache, "file !txt", Some(300)).unwrap_err();
+    assert!(matches!(error, SearchError::TooBroad { .. }));
+}
+
+#[test]
+fn test_normal_queries_stay_under_the_cap() {
+    let (_tmp, cache) = build_tree();
+    assert_eq!(search(&cache, "file001", Some(300)).unwrap(), 1);
+    assert_eq!(search(&cache, "file00 | file01", Some(300)).unwrap(), 20);
+    // ANDed lists are released as they shrink.
+    assert_eq!(
+        search(&cache, "file ext:txt file00", Some(300)).unwrap(),
+        10
+    );
+    // The default cap is far above this, and `None` turns it off.
+    assert_eq!(search(&cache, "file | txt", None).unwrap(), FILES);
+    assert_eq!(
+        search(
+            &cache,
+            "file | txt",
+            SearchOptions::default().max_intermediate_results
+        )
+        .unwrap(),
+        FILES
+    );
+}
+
+#[test]
+fn test_every_list_is_released() {
+    let (_tmp, cache) = build_tree();
+    for query in [
+        "file | txt",
+        "file txt",
+        "file !file00",
+        "(file00 | file01) !file001",
+        "ext:txt | file",
+    ] {
+        let expr = optimize_query(parse_query(query).unwrap()).expr;
+        let budget = ResultBudget::new(None);
+        cache
+            .evaluate_expr(
+                &expr,
+                SearchOptions::default(),
+                CancellationToken::noop(),
+                &QueryNotes::default(),
+                &budget,
+            )
+            .unwrap();
+        assert_eq!(budget.live(), 0, "{query}");
+    }
+}
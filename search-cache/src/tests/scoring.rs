@@ -0,0 +1,165 @@
+use super::prelude::*;
+use crate::{
+    MatchKind, RankMode, ScoreContext, Scorer, SearchOptions, SlabIndex,
+    testkit::{TreeSpec, node_name, set_file_times},
+};
+use std::sync::{
+    Mutex,
+    atomic::{AtomicUsize, Ordering},
+};
+
+fn scored(cache: &SearchCache, query: &str, scorer: Scorer) -> Vec<String> {
+    names(cache, query, SearchOptions::default().with_scorer(scorer))
+}
+
+fn names(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<String> {
+    nodes(cache, query, options)
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect()
+}
+
+fn nodes(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<SlabIndex> {
+    cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+}
+
+/// The results of `query` in slab order.
+fn slab_order(cache: &SearchCache, query: &str) -> Vec<String> {
+    let mut indexes = nodes(cache, query, SearchOptions::default());
+    indexes.sort();
+    indexes
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect()
+}
+
+#[test]
+fn test_custom_scorer_reverses_default_order() {
+    let (_tmp, cache) = TreeSpec::new()
+        .file("a.txt")
+        .file("b.txt")
+        .file("c.txt")
+        .file("d.txt")
+        .index("scoring_reverse");
+    let ascending = slab_order(&cache, "ext:txt");
+    assert_eq!(ascending.len(), 4);
+
+    let reversed = scored(
+        &cache,
+        "ext:txt",
+        Scorer::new(&|context| context.index.get() as f32),
+    );
+    let mut expected = ascending;
+    expected.reverse();
+    assert_eq!(reversed, expected);
+}
+
+#[test]
+fn test_ties_keep_slab_order() {
+    let (_tmp, cache) = TreeSpec::new()
+        .file("x/a.txt")
+        .file("y/b.txt")
+        .file("c.txt")
+        .file("z/d.txt")
+        .index("scoring_ties");
+    let ascending = slab_order(&cache, "ext:txt");
+    for _ in 0..3 {
+        assert_eq!(scored(&cache, "ext:txt", Scorer::new(&|_| 1.0)), ascending);
+    }
+    // Two tiers, each in slab order.
+    let by_depth = scored(&cache, "ext:txt", Scorer::SHALLOW_FIRST);
+    assert_eq!(by_depth[0], "c.txt");
+    let deeper: Vec<_> = ascending.iter().filter(|name| *name != "c.txt").collect();
+    assert_eq!(by_depth[1..].iter().collect::<Vec<_>>(), deeper);
+}
+
+static SCORED: AtomicUsize = AtomicUsize::new(0);
+
+fn counting(_: &ScoreContext<'_>) -> f32 {
+    SCORED.fetch_add(1, Ordering::Relaxed);
+    0.0
+}
+
+#[test]
+fn test_scorer_runs_once_per_surviving_node() {
+    let (_tmp, cache) = TreeSpec::new()
+        .sized_file("small.txt", 1)
+        .sized_file("big.txt", 100)
+        .sized_file("bigger.txt", 200)
+        .sized_file("big.log", 300)
+        .sized_file("other.txt", 2)
+        .index("scoring_count");
+    let nodes = scored(&cache, "ext:txt size:>50", Scorer::new(&counting));
+    assert_eq!(nodes.len(), 2);
+    assert_eq!(SCORED.load(Ordering::Relaxed), 2);
+
+    // Nothing left, nothing scored.
+    assert!(scored(&cache, "nomatch", Scorer::new(&counting)).is_empty());
+    assert_eq!(SCORED.load(Ordering::Relaxed), 2);
+}
+
+#[test]
+fn test_scorer_replaces_rank_mode() {
+    let (_tmp, mut cache) = TreeSpec::new()
+        .file("a.txt")
+        .file("b.txt")
+        .file("c.txt")
+        .index("scoring_recent");
+    for (name, mtime) in [("a.txt", 100), ("c.txt", 300)] {
+        let index = cache.search(name).unwrap()[0];
+        set_file_times(&mut cache, index, mtime, mtime);
+    }
+    // b.txt has no metadata and isn't stat'ed for the score.
+    assert_eq!(
+        scored(&cache, "ext:txt", Scorer::RECENT_FIRST),
+        ["c.txt", "a.txt", "b.txt"]
+    );
+    let b = cache.search("b.txt").unwrap()[0];
+    assert!(cache.file_nodes[b].metadata.is_none());
+
+    let options = SearchOptions::default()
+        .with_rank(RankMode::RecentFirst)
+        .with_scorer(Scorer::SHALLOW_FIRST);
+    assert_eq!(
+        names(&cache, "ext:txt", options),
+        slab_order(&cache, "ext:txt")
+    );
+}
+
+static MATCH_KINDS: Mutex<Vec<(String, MatchKind)>> = Mutex::new(Vec::new());
+
+fn recording(context: &ScoreContext<'_>) -> f32 {
+    MATCH_KINDS
+        .lock()
+        .unwrap()
+        .push((context.name.to_string(), context.match_kind));
+    0.0
+}
+
+#[test]
+fn test_match_kind_of_names() {
+    let (_tmp, cache) = TreeSpec::new()
+        .file("Report")
+        .file("report-2024.pdf")
+        .file("old_report.txt")
+        .sized_file("notes.md", 10)
+        .index("scoring_match_kind");
+    let options = SearchOptions::default()
+        .with_case_insensitive(true)
+        .with_scorer(Scorer::new(&recording));
+    names(&cache, "report | size:>5", options);
+    let mut kinds = MATCH_KINDS.lock().unwrap().clone();
+    kinds.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        kinds,
+        [
+            ("Report".to_string(), MatchKind::Exact),
+            ("notes.md".to_string(), MatchKind::Other),
+            ("old_report.txt".to_string(), MatchKind::Substring),
+            ("report-2024.pdf".to_string(), MatchKind::Prefix),
+        ]
+    );
+}
@@ -0,0 +1,149 @@
+use super::prelude::*;
+use crate::{SearchError, SearchOptions, SetIndex, SlabIndex, testkit::TreeSpec};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::path::Path;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files([
+            "My Folder/a.txt",
+            "My Folder/notes.md",
+            "My Folder/sub/b.txt",
+            "quote \"dir\"/c.txt",
+            "other/a.txt",
+        ])
+        .index("search_scope")
+}
+
+fn folder(cache: &SearchCache, path: &Path) -> SlabIndex {
+    cache.node_index_for_raw_path(path).unwrap()
+}
+
+fn scoped(cache: &SearchCache, query: &str, scope: SlabIndex) -> Vec<SlabIndex> {
+    let options = SearchOptions {
+        scope_to: Some(scope),
+        ..Default::default()
+    };
+    let mut nodes = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    nodes.sort_unstable();
+    nodes
+}
+
+fn paths(tmp: &TempDir, cache: &SearchCache, nodes: &[SlabIndex]) -> Vec<String> {
+    let mut paths: Vec<String> = nodes
+        .iter()
+        .filter_map(|&index| cache.node_path(index))
+        .map(|path| {
+            path.strip_prefix(tmp.path())
+                .unwrap()
+                .to_string_lossy()
+                .into_owned()
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+#[test]
+fn test_scope_matches_infolder() {
+    let (tmp, cache) = build_tree();
+    let path = tmp.path().join("My Folder");
+    let scope = folder(&cache, &path);
+    for query in [
+        "",
+        "a.txt",
+        ".txt",
+        "ext:txt",
+        "!notes",
+        "depth:1",
+        "a.txt | notes",
+        "sub",
+        "size:<1kb",
+        "regex:^b",
+    ] {
+        let infolder = if query.is_empty() {
+            format!("infolder:\"{}\"", path.display())
+        } else {
+            format!("infolder:\"{}\" <{query}>", path.display())
+        };
+        let mut expected = cache.search(&infolder).unwrap();
+        expected.sort_unstable();
+        assert_eq!(scoped(&cache, query, scope), expected, "{query:?}");
+    }
+    assert_eq!(
+        paths(&tmp, &cache, &scoped(&cache, ".txt", scope)),
+        ["My Folder/a.txt", "My Folder/sub/b.txt"]
+    );
+}
+
+#[test]
+fn test_scope_needs_no_quoting() {
+    let (tmp, cache) = build_tree();
+    let scope = folder(&cache, &tmp.path().join("quote \"dir\""));
+    assert_eq!(
+        paths(&tmp, &cache, &scoped(&cache, "", scope)),
+        ["quote \"dir\"/c.txt"]
+    );
+    assert_eq!(
+        paths(&tmp, &cache, &scoped(&cache, "c.txt", scope)),
+        ["quote \"dir\"/c.txt"]
+    );
+    assert!(scoped(&cache, "a.txt", scope).is_empty());
+}
+
+#[test]
+fn test_scope_applies_within_results() {
+    let (tmp, cache) = build_tree();
+    let scope = folder(&cache, &tmp.path().join("My Folder"));
+    let base = cache.search(".txt").unwrap();
+    let options = SearchOptions {
+        scope_to: Some(scope),
+        ..Default::default()
+    };
+    let nodes = cache
+        .search_within(&base, "a", options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    assert_eq!(paths(&tmp, &cache, &nodes), ["My Folder/a.txt"]);
+}
+
+#[test]
+fn test_stale_scope_is_rejected() {
+    let (tmp, mut cache) = build_tree();
+    let sub = tmp.path().join("My Folder/sub");
+    let scope = folder(&cache, &sub);
+    let options = SearchOptions {
+        scope_to: Some(scope),
+        ..Default::default()
+    };
+    fs::remove_dir_all(&sub).unwrap();
+    let removed = FsEvent {
+        path: sub,
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsDir,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+    let error = cache
+        .search_with_options("b", options, CancellationToken::noop())
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidScope(index) if index == SetIndex::new(0, scope)));
+    assert_eq!(error.code(), "invalid_scope");
+
+    // Files can't be searched inside of either.
+    let file = folder(&cache, &tmp.path().join("other/a.txt"));
+    let options = SearchOptions {
+        scope_to: Some(file),
+        ..Default::default()
+    };
+    let error = cache
+        .search_with_options("a", options, CancellationToken::noop())
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidScope(_)));
+    let error = cache
+        .search_within(&[], "a", options, CancellationToken::noop())
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidScope(_)));
+}
@@ -0,0 +1,161 @@
+use super::prelude::*;
+use crate::{
+    CHUNK_SLOTS, SearchOptions, SearchSnapshot,
+    testkit::{EventScript, TreeSpec},
+};
+
+fn paths(snapshot: &SearchSnapshot, query: &str) -> Vec<PathBuf> {
+    let nodes = snapshot
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    snapshot
+        .node_paths(&nodes)
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+/// `folders` folders of `files` files each, `f{folder}/n{file}.txt`.
+fn wide_tree(folders: usize, files: usize, prefix: &str) -> (TempDir, SearchCache) {
+    let mut spec = TreeSpec::new();
+    for folder in 0..folders {
+        for file in 0..files {
+            spec = spec.file(format!("f{folder}/n{file}.txt"));
+        }
+    }
+    spec.index(prefix)
+}
+
+#[test]
+fn test_snapshot_is_unchanged_by_later_events() {
+    let (tmp, mut cache) = wide_tree(4, 600, "snapshot_unchanged");
+    let snapshot = cache.search_snapshot();
+    let queries = ["n1", "f2", "ext:txt", "added", "infolder:f0 n5"];
+    let before: Vec<_> = queries
+        .iter()
+        .map(|query| paths(&snapshot, query))
+        .collect();
+    let total = snapshot.get_total_files();
+
+    let root = tmp.path();
+    let mut script = EventScript::after(&cache);
+    script
+        .remove(&root.join("f1"))
+        .create_dir(&root.join("added"));
+    for file in 0..300 {
+        script.create_file(&root.join(format!("added/added{file}.txt")), b"new");
+        script.remove(&root.join(format!("f0/n{file}.txt")));
+    }
+    script.rename(&root.join("f2"), &root.join("renamed"));
+    script.apply(&mut cache).unwrap();
+    cache.compact();
+
+    assert_ne!(cache.get_total_files(), total);
+    assert_eq!(
+        cache
+            .search_with_options("added", SearchOptions::default(), CancellationToken::noop())
+            .unwrap()
+            .nodes
+            .len(),
+        301
+    );
+    assert_eq!(snapshot.get_total_files(), total);
+    for (query, before) in queries.iter().zip(before) {
+        assert_eq!(paths(&snapshot, query), before, "{query}");
+    }
+    assert!(paths(&snapshot, "added").is_empty());
+    assert!(
+        snapshot
+            .node_index_for_raw_path(&root.join("f1/n0.txt"))
+            .is_some()
+    );
+}
+
+#[test]
+fn test_changes_copy_only_the_chunks_they_touch() {
+    let (tmp, mut cache) = wide_tree(10, 1000, "snapshot_chunks");
+    let snapshot = cache.search_snapshot();
+    let chunks = snapshot.chunk_count();
+    assert!(chunks >= 10_000 / CHUNK_SLOTS);
+    assert_eq!(snapshot.shared_chunks(&cache), chunks);
+
+    // Ten new files in one folder write that folder's chunk and the last one.
+    let mut script = EventScript::after(&cache);
+    for file in 0..10 {
+        script.create_file(&tmp.path().join(format!("f3/new{file}.txt")), b"n");
+    }
+    script.apply(&mut cache).unwrap();
+    let copied = chunks - snapshot.shared_chunks(&cache);
+    assert!((1..=2).contains(&copied), "{copied} chunks copied");
+
+    // A later snapshot shares everything again, the earlier one no more.
+    let later = cache.search_snapshot();
+    assert_eq!(later.shared_chunks(&cache), later.chunk_count());
+    assert_eq!(chunks - snapshot.shared_chunks(&cache), copied);
+}
+
+#[test]
+fn test_changes_copy_only_the_name_chunks_they_touch() {
+    let (tmp, mut cache) = TreeSpec::new()
+        .files((0..26u8).flat_map(|letter| {
+            let letter = char::from(b'a' + letter);
+            (0..20).map(move |file| format!("{letter}{letter}/{letter}{file}.txt"))
+        }))
+        .index("snapshot_name_chunks");
+    let snapshot = cache.search_snapshot();
+    let chunks = snapshot.name_chunk_count();
+    assert!(chunks >= 26);
+    assert_eq!(snapshot.shared_name_chunks(&cache), chunks);
+
+    // A new name and a removed one, both starting with "q".
+    let mut script = EventScript::after(&cache);
+    script
+        .create_file(&tmp.path().join("qq/qnew.txt"), b"n")
+        .remove(&tmp.path().join("qq/q3.txt"));
+    script.apply(&mut cache).unwrap();
+    let copied = chunks - snapshot.shared_name_chunks(&cache);
+    assert!((1..=2).contains(&copied), "{copied} name chunks copied");
+    assert!(paths(&snapshot, "qnew").is_empty());
+    assert_eq!(paths(&snapshot, "q3.txt").len(), 1);
+    assert_eq!(
+        cache
+            .search_with_options("qnew", SearchOptions::default(), CancellationToken::noop())
+            .unwrap()
+            .nodes
+            .len(),
+        1
+    );
+    cache.verify_name_index().unwrap();
+}
+
+#[test]
+fn test_snapshot_searches_skip_the_history() {
+    let (_tmp, cache) = TreeSpec::new().file("alpha.txt").index("snapshot_history");
+    let snapshot = cache.search_snapshot();
+    paths(&snapshot, "alpha");
+    assert!(cache.suggest_queries("", 10).is_empty());
+}
+
+#[test]
+fn test_snapshot_encodes_like_the_cache() {
+    let (tmp, mut cache) = TreeSpec::new()
+        .file("docs/report.txt")
+        .file("docs/notes.md")
+        .index("snapshot_encode");
+    let snapshot = cache.search_snapshot();
+    let mut script = EventScript::after(&cache);
+    script.remove(&tmp.path().join("docs/notes.md"));
+    script.apply(&mut cache).unwrap();
+
+    let cache_path = tmp.path().join("cache.zstd");
+    snapshot
+        .encode()
+        .unwrap()
+        .write_to_file(&cache_path)
+        .unwrap();
+    let loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(loaded.get_total_files(), snapshot.get_total_files());
+    assert_eq!(loaded.search("notes").unwrap().len(), 1);
+}
@@ -0,0 +1,106 @@
+use super::prelude::*;
+use crate::{SearchOptions, SlabIndex, query::NAME_POOL_SCANS};
+
+fn corpus() -> (TempDir, SearchCache) {
+    let tmp = TempDir::new("search_within").unwrap();
+    for dir in ["docs/archive", "src", "photos"] {
+        fs::create_dir_all(tmp.path().join(dir)).unwrap();
+    }
+    for file in [
+        "docs/report.md",
+        "docs/report_draft.txt",
+        "docs/notes.txt",
+        "docs/archive/report_2023.pdf",
+        "src/main.rs",
+        "src/report.rs",
+        "photos/report.png",
+        "photos/cat.jpg",
+    ] {
+        fs::write(tmp.path().join(file), file.as_bytes()).unwrap();
+    }
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    (tmp, cache)
+}
+
+fn within(cache: &mut SearchCache, base: &[SlabIndex], query: &str) -> Vec<SlabIndex> {
+    let mut nodes = cache
+        .search_within(
+            base,
+            query,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap()
+        .nodes;
+    nodes.sort_unstable();
+    nodes
+}
+
+fn sorted_search(cache: &mut SearchCache, query: &str) -> Vec<SlabIndex> {
+    let mut nodes = cache.search(query).unwrap();
+    nodes.sort_unstable();
+    nodes
+}
+
+#[test]
+fn search_within_matches_the_anded_query() {
+    let (_tmp, mut cache) = corpus();
+    for first in ["report", "ext:txt;md;rs", "docs/"] {
+        let base = cache.search(first).unwrap();
+        for second in [
+            "md",
+            "rep",
+            "docs/rep",
+            "/report.rs/",
+            "ext:txt",
+            "!draft",
+            "draft | png",
+            "regex:^report_",
+            "report*.pdf",
+            "src/ OR ext:png",
+            "type:picture",
+            "size:>10",
+            "",
+        ] {
+            let expected = if second.is_empty() {
+                sorted_search(&mut cache, first)
+            } else {
+                sorted_search(&mut cache, &format!("{first} ({second})"))
+            };
+            assert_eq!(
+                within(&mut cache, &base, second),
+                expected,
+                "{first:?} then {second:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn search_within_does_not_scan_the_name_pool() {
+    let (_tmp, mut cache) = corpus();
+    let base = cache.search("report").unwrap();
+    let scans = NAME_POOL_SCANS.with(|scans| scans.get());
+    let nodes = within(&mut cache, &base, "docs/rep !draft");
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(NAME_POOL_SCANS.with(|scans| scans.get()), scans);
+
+    // A regular search does.
+    cache.search("report").unwrap();
+    assert!(NAME_POOL_SCANS.with(|scans| scans.get()) > scans);
+}
+
+#[test]
+fn search_within_skips_removed_nodes_and_stays_out_of_history() {
+    let (tmp, mut cache) = corpus();
+    let base = cache.search("report").unwrap();
+    let removed = cache
+        .node_index_for_raw_path(&tmp.path().join("photos/report.png"))
+        .unwrap();
+    cache.file_nodes.try_remove(removed).unwrap();
+
+    let nodes = within(&mut cache, &base, "rep");
+    assert_eq!(nodes.len(), base.len() - 1);
+    assert!(!nodes.contains(&removed));
+    assert_eq!(cache.suggest_queries("rep", 10), ["report"]);
+}
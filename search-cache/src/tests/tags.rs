@@ -0,0 +1,135 @@
+use super::prelude::*;
+use crate::{
+    SlabIndex, normalize_tag,
+    testkit::{TreeSpec, node_name},
+};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::path::Path;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["a.txt", "b.txt", "docs/c.md"])
+        .index("tags")
+}
+
+fn index_of(cache: &SearchCache, path: &Path) -> SlabIndex {
+    cache.node_index_for_raw_path(path).unwrap()
+}
+
+fn sorted_names(cache: &SearchCache, query: &str) -> Vec<String> {
+    let mut names: Vec<String> = cache
+        .search(query)
+        .unwrap()
+        .into_iter()
+        .map(|index| node_name(cache, index))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_tags_are_normalized() {
+    assert_eq!(&*normalize_tag(" Keep ").unwrap(), "keep");
+    assert_eq!(&*normalize_tag("Cafe\u{301}").unwrap(), "caf\u{e9}");
+    for invalid in ["", "  ", "to do", "a;b", "a|b", "\"x\""] {
+        assert!(normalize_tag(invalid).is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn test_add_and_remove_tags() {
+    let (tmp, mut cache) = build_tree();
+    let a = index_of(&cache, &tmp.path().join("a.txt"));
+    assert!(cache.tags_of(a).is_empty());
+
+    assert!(cache.add_tag(a, "Review").unwrap());
+    assert!(cache.add_tag(a, "keep").unwrap());
+    assert!(!cache.add_tag(a, "KEEP").unwrap());
+    assert!(cache.add_tag(a, "to do").is_err());
+    assert_eq!(cache.tags_of(a), ["keep", "review"]);
+    assert_eq!(cache.tags().len(), 1);
+
+    assert!(cache.remove_tag(a, "Review"));
+    assert!(!cache.remove_tag(a, "review"));
+    assert_eq!(cache.tags_of(a), ["keep"]);
+    assert!(cache.remove_tag(a, "keep"));
+    assert!(cache.tags().is_empty());
+
+    let [node] = cache.expand_file_nodes(&[a]).try_into().unwrap();
+    assert!(node.tags.is_empty());
+    cache.add_tag(a, "keep").unwrap();
+    let [node] = cache.expand_file_nodes(&[a]).try_into().unwrap();
+    assert_eq!(node.tags, ["keep"]);
+}
+
+#[test]
+fn test_tag_filter() {
+    let (tmp, mut cache) = build_tree();
+    let a = index_of(&cache, &tmp.path().join("a.txt"));
+    let docs = index_of(&cache, &tmp.path().join("docs"));
+    let c = index_of(&cache, &tmp.path().join("docs/c.md"));
+    cache.add_tag(a, "keep").unwrap();
+    cache.add_tag(docs, "keep").unwrap();
+    cache.add_tag(c, "review").unwrap();
+
+    assert_eq!(sorted_names(&cache, "tag:keep"), ["a.txt", "docs"]);
+    assert_eq!(sorted_names(&cache, "tag:Keep"), ["a.txt", "docs"]);
+    assert_eq!(
+        sorted_names(&cache, "tag:keep;review"),
+        ["a.txt", "c.md", "docs"]
+    );
+    assert_eq!(sorted_names(&cache, "tag:"), ["a.txt", "c.md", "docs"]);
+    assert_eq!(sorted_names(&cache, "tag:keep file:"), ["a.txt"]);
+    assert_eq!(sorted_names(&cache, "txt tag:keep"), ["a.txt"]);
+    assert_eq!(sorted_names(&cache, "ext:txt !tag:keep"), ["b.txt"]);
+    assert!(sorted_names(&cache, "tag:missing").is_empty());
+    assert!(cache.search("tag:\"a b\"").is_err());
+}
+
+#[test]
+fn test_tags_persist() {
+    let (tmp, mut cache) = build_tree();
+    let a_path = tmp.path().join("a.txt");
+    let a = index_of(&cache, &a_path);
+    cache.add_tag(a, "keep").unwrap();
+    let cache_path = tmp.path().join("cache.zstd");
+    cache.flush_to_file(&cache_path).unwrap();
+
+    let cache =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    assert_eq!(cache.tags_of(index_of(&cache, &a_path)), ["keep"]);
+    assert_eq!(sorted_names(&cache, "tag:keep"), ["a.txt"]);
+}
+
+#[test]
+fn test_tags_follow_paths_across_rescans() {
+    let (tmp, mut cache) = build_tree();
+    let b_path = tmp.path().join("b.txt");
+    let c_path = tmp.path().join("docs/c.md");
+    cache.add_tag(index_of(&cache, &b_path), "keep").unwrap();
+    cache.add_tag(index_of(&cache, &c_path), "review").unwrap();
+    let old_b = index_of(&cache, &b_path);
+
+    // New nodes walked first shift the indexes of the rest.
+    for i in 0..20 {
+        fs::write(tmp.path().join(format!("{i:02}.log")), b"x").unwrap();
+    }
+    cache.rescan();
+    assert_ne!(index_of(&cache, &b_path), old_b);
+    assert_eq!(cache.tags_of(index_of(&cache, &b_path)), ["keep"]);
+    assert_eq!(sorted_names(&cache, "tag:"), ["b.txt", "c.md"]);
+
+    // A path that's gone keeps its tags until it's back.
+    fs::rename(&c_path, tmp.path().join("docs/c.bak")).unwrap();
+    cache.rescan();
+    assert_eq!(sorted_names(&cache, "tag:"), ["b.txt"]);
+    assert_eq!(cache.tags().len(), 2);
+    fs::rename(tmp.path().join("docs/c.bak"), &c_path).unwrap();
+    let created = FsEvent {
+        path: c_path.clone(),
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![created]).unwrap();
+    assert_eq!(sorted_names(&cache, "tag:review"), ["c.md"]);
+}
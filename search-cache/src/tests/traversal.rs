@@ -1,4 +1,5 @@
 use super::prelude::*;
+use crate::SubnodeOrder;
 
 #[test]
 fn test_all_subnodes_returns_all_descendants() {
@@ -120,3 +121,178 @@ fn test_all_subnodes_cancellation() {
     let result = cache.all_subnodes(root_idx, token);
     assert!(result.is_none(), "Should return None when cancelled");
 }
+
+/// root/d{0..10}/s{0..10}/f{0..100}.txt: 10110 nodes below the root.
+fn wide_tree() -> (TempDir, SearchCache) {
+    let tmp = TempDir::new("subnodes_page").unwrap();
+    for d in 0..10 {
+        for s in 0..10 {
+            let dir = tmp.path().join(format!("d{d}/s{s}"));
+            fs::create_dir_all(&dir).unwrap();
+            for f in 0..100 {
+                fs::write(dir.join(format!("f{f}.txt")), b"x").unwrap();
+            }
+        }
+    }
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    (tmp, cache)
+}
+
+#[test]
+fn test_subnodes_pages_cover_all_subnodes() {
+    let (_tmp, cache) = wide_tree();
+    let root = cache.file_nodes.root();
+    let all = cache.all_subnodes(root, CancellationToken::noop()).unwrap();
+    assert_eq!(all.len(), 10 + 100 + 10_000);
+
+    // Resuming from cursors.
+    let mut page = cache
+        .subnodes_page(
+            root,
+            0,
+            997,
+            SubnodeOrder::DepthFirst,
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let mut paged = Vec::new();
+    let mut pages = 1;
+    while let Some(cursor) = page.next.take() {
+        assert_eq!(page.items.len(), 997);
+        assert_eq!(page.total, None);
+        assert_eq!(cursor.offset(), paged.len() + 997);
+        paged.append(&mut page.items);
+        page = cache
+            .subnodes_page_after(cursor, 997, CancellationToken::noop())
+            .unwrap();
+        pages += 1;
+    }
+    paged.append(&mut page.items);
+    assert_eq!(pages, all.len().div_ceil(997));
+    assert_eq!(page.total, Some(all.len()));
+    assert_eq!(paged, all);
+
+    // Skipping by offset yields the same pages.
+    for offset in [0, 1, 996, 5_000, all.len() - 3] {
+        let page = cache
+            .subnodes_page(
+                root,
+                offset,
+                50,
+                SubnodeOrder::DepthFirst,
+                CancellationToken::noop(),
+            )
+            .unwrap();
+        let end = (offset + 50).min(all.len());
+        assert_eq!(page.items, all[offset..end]);
+        assert_eq!(page.next.is_none(), end == all.len());
+    }
+    let page = cache
+        .subnodes_page(
+            root,
+            all.len() + 10,
+            50,
+            SubnodeOrder::DepthFirst,
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert!(page.items.is_empty());
+    assert_eq!(page.total, Some(all.len()));
+
+    // A page ending exactly on the last node is the last page.
+    let page = cache
+        .subnodes_page(
+            root,
+            0,
+            all.len(),
+            SubnodeOrder::DepthFirst,
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert!(page.next.is_none());
+    assert_eq!(page.total, Some(all.len()));
+}
+
+#[test]
+fn test_subnodes_breadth_first_goes_level_by_level() {
+    let (tmp, cache) = wide_tree();
+    let root = cache.file_nodes.root();
+    let depth = |index| {
+        cache
+            .node_path(index)
+            .unwrap()
+            .strip_prefix(tmp.path())
+            .unwrap()
+            .components()
+            .count()
+    };
+
+    let mut page = cache
+        .subnodes_page(
+            root,
+            0,
+            333,
+            SubnodeOrder::BreadthFirst,
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let mut paged = Vec::new();
+    while let Some(cursor) = page.next.take() {
+        assert_eq!(cursor.order(), SubnodeOrder::BreadthFirst);
+        paged.append(&mut page.items);
+        page = cache
+            .subnodes_page_after(cursor, 333, CancellationToken::noop())
+            .unwrap();
+    }
+    paged.append(&mut page.items);
+
+    let depths: Vec<_> = paged.iter().map(|&index| depth(index)).collect();
+    assert!(depths.is_sorted());
+    assert_eq!(depths.iter().filter(|&&d| d == 1).count(), 10);
+    assert_eq!(depths.iter().filter(|&&d| d == 2).count(), 100);
+    assert_eq!(depths.iter().filter(|&&d| d == 3).count(), 10_000);
+    // Within a level, siblings stay grouped under their parent's position.
+    let parents: Vec<_> = paged[10..110]
+        .iter()
+        .map(|&index| cache.file_nodes[index].name_and_parent.parent().unwrap())
+        .collect();
+    assert_eq!(parents.chunk_by(|a, b| a == b).count(), 10);
+    assert_eq!(
+        parents
+            .chunk_by(|a, b| a == b)
+            .map(|c| c[0])
+            .collect::<Vec<_>>(),
+        paged[..10]
+    );
+
+    let mut all = cache.all_subnodes(root, CancellationToken::noop()).unwrap();
+    all.sort_unstable();
+    paged.sort_unstable();
+    assert_eq!(paged, all);
+    assert_eq!(page.total, Some(all.len()));
+}
+
+#[test]
+fn test_subnodes_page_cancellation() {
+    let (_tmp, cache) = wide_tree();
+    let root = cache.file_nodes.root();
+    let cursor = cache
+        .subnodes_page(
+            root,
+            0,
+            10,
+            SubnodeOrder::BreadthFirst,
+            CancellationToken::noop(),
+        )
+        .unwrap()
+        .next
+        .unwrap();
+    let token = CancellationToken::new(1);
+    let _newer_token = CancellationToken::new(2);
+    assert!(cache.subnodes_page_after(cursor, 10, token).is_none());
+    assert!(
+        cache
+            .subnodes_page(root, 5_000, 10, SubnodeOrder::DepthFirst, token)
+            .is_none()
+    );
+}
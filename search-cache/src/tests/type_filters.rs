@@ -1,4 +1,5 @@
 use super::prelude::*;
+use crate::{SearchOptions, testkit::TreeSpec};
 
 #[test]
 fn test_type_and_macro_filters() {
@@ -6,7 +7,7 @@ fn test_type_and_macro_filters() {
     fs::write(tmp.path().join("photo.png"), b"x").unwrap();
     fs::write(tmp.path().join("song.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("notes.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pictures = cache.search("type:picture").unwrap();
     assert_eq!(pictures.len(), 1);
@@ -30,7 +31,7 @@ fn test_audio_macro_with_argument_behaves_like_and() {
     fs::write(tmp.path().join("song_beats.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("song_other.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("notes.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("audio:beats").unwrap();
     assert_eq!(results.len(), 1);
@@ -44,7 +45,7 @@ fn test_type_filter_respects_parent_base() {
     fs::create_dir(tmp.path().join("media")).unwrap();
     fs::write(tmp.path().join("media/keep.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("skip.jpg"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let keep_idx = cache.search("keep.jpg").unwrap()[0];
     let skip_idx = cache.search("skip.jpg").unwrap()[0];
@@ -99,7 +100,7 @@ fn test_type_picture_comprehensive() {
     fs::write(tmp.path().join("document.txt"), b"x").unwrap();
     fs::write(tmp.path().join("video.mp4"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pictures = cache.search("type:picture").unwrap();
     assert_eq!(pictures.len(), 18, "Should match all 18 image formats");
@@ -144,7 +145,7 @@ fn test_type_video_comprehensive() {
     fs::write(tmp.path().join("audio.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("doc.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let videos = cache.search("type:video").unwrap();
     assert_eq!(videos.len(), 15);
@@ -177,7 +178,7 @@ fn test_type_audio_comprehensive() {
     fs::write(tmp.path().join("video.mp4"), b"x").unwrap();
     fs::write(tmp.path().join("text.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let audio = cache.search("type:audio").unwrap();
     assert_eq!(audio.len(), 11);
@@ -212,7 +213,7 @@ fn test_type_document_comprehensive() {
     fs::write(tmp.path().join("image.png"), b"x").unwrap();
     fs::write(tmp.path().join("code.rs"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let docs = cache.search("type:doc").unwrap();
     assert_eq!(docs.len(), 10);
@@ -242,7 +243,7 @@ fn test_type_presentation_comprehensive() {
     fs::write(tmp.path().join("opendoc.odp"), b"x").unwrap();
     fs::write(tmp.path().join("document.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pres = cache.search("type:presentation").unwrap();
     assert_eq!(pres.len(), 4);
@@ -267,7 +268,7 @@ fn test_type_spreadsheet_comprehensive() {
     fs::write(tmp.path().join("opendoc.ods"), b"x").unwrap();
     fs::write(tmp.path().join("text.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let sheets = cache.search("type:spreadsheet").unwrap();
     assert_eq!(sheets.len(), 5);
@@ -296,7 +297,7 @@ fn test_type_pdf_filter() {
     fs::write(tmp.path().join("guide.pdf"), b"x").unwrap();
     fs::write(tmp.path().join("doc.docx"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pdfs = cache.search("type:pdf").unwrap();
     assert_eq!(pdfs.len(), 3);
@@ -319,7 +320,7 @@ fn test_type_archive_comprehensive() {
     fs::write(tmp.path().join("macos.dmg"), b"x").unwrap();
     fs::write(tmp.path().join("text.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let archives = cache.search("type:archive").unwrap();
     assert_eq!(archives.len(), 12);
@@ -398,7 +399,7 @@ fn test_type_code_comprehensive() {
     fs::write(tmp.path().join("doc.txt"), b"x").unwrap();
     fs::write(tmp.path().join("image.png"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let code = cache.search("type:code").unwrap();
     // The test creates 47 code files + Cargo.toml = 48 total
@@ -430,7 +431,7 @@ fn test_type_executable_comprehensive() {
     fs::write(tmp.path().join("macos.pkg"), b"x").unwrap();
     fs::write(tmp.path().join("text.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let exe = cache.search("type:exe").unwrap();
     assert_eq!(exe.len(), 14);
@@ -466,7 +467,7 @@ fn test_type_file_folder_filters() {
     fs::create_dir(tmp.path().join("folder2")).unwrap();
     fs::write(tmp.path().join("folder1/nested.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let files = cache.search("type:file").unwrap();
     assert_eq!(files.len(), 3, "Should match only files");
@@ -492,7 +493,7 @@ fn test_type_file_folder_filters() {
 fn test_type_filter_unknown_category_error() {
     let tmp = TempDir::new("type_unknown").unwrap();
     fs::write(tmp.path().join("file.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let result = cache.search("type:unknowncategory");
     assert!(result.is_err());
@@ -508,7 +509,7 @@ fn test_type_filter_unknown_category_error() {
 fn test_type_filter_empty_argument_error() {
     let tmp = TempDir::new("type_empty").unwrap();
     fs::write(tmp.path().join("file.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let result = cache.search("type:");
     assert!(result.is_err());
@@ -524,7 +525,7 @@ fn test_type_filter_empty_argument_error() {
 fn test_audio_macro_no_arguments() {
     let tmp = TempDir::new("audio_macro").unwrap();
     fs::write(tmp.path().join("song.mp3"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let result = cache.search("audio:");
     assert!(result.is_ok());
@@ -535,7 +536,7 @@ fn test_audio_macro_no_arguments() {
 fn test_video_macro_no_arguments() {
     let tmp = TempDir::new("video_macro").unwrap();
     fs::write(tmp.path().join("clip.mp4"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let result = cache.search("video:");
     assert!(result.is_ok());
@@ -546,7 +547,7 @@ fn test_video_macro_no_arguments() {
 fn test_doc_macro_no_arguments() {
     let tmp = TempDir::new("doc_macro").unwrap();
     fs::write(tmp.path().join("note.txt"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let result = cache.search("doc:");
     assert!(result.is_ok());
@@ -557,7 +558,7 @@ fn test_doc_macro_no_arguments() {
 fn test_exe_macro_no_arguments() {
     let tmp = TempDir::new("exe_macro").unwrap();
     fs::write(tmp.path().join("app.exe"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let result = cache.search("exe:");
     assert!(result.is_ok());
@@ -572,7 +573,7 @@ fn test_type_filter_combined_with_name_search() {
     fs::write(tmp.path().join("summary.pdf"), b"x").unwrap();
     fs::write(tmp.path().join("image.png"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("report type:doc").unwrap();
     assert_eq!(results.len(), 2);
@@ -592,7 +593,7 @@ fn test_type_filter_with_or_operator() {
     fs::write(tmp.path().join("clip.mp4"), b"x").unwrap();
     fs::write(tmp.path().join("doc.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:audio OR type:video").unwrap();
     assert_eq!(results.len(), 2);
@@ -612,7 +613,7 @@ fn test_type_filter_with_not_operator() {
     fs::write(tmp.path().join("song.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("doc.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("!type:picture").unwrap();
     assert!(results.len() >= 2);
@@ -634,7 +635,7 @@ fn test_type_filter_multiple_extensions_same_category() {
     fs::write(tmp.path().join("animation.gif"), b"x").unwrap();
     fs::write(tmp.path().join("web.webp"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(results.len(), 4);
@@ -647,7 +648,7 @@ fn test_size_combined_with_type_filter() {
     fs::write(tmp.path().join("small.png"), vec![0u8; 1_000]).unwrap();
     fs::write(tmp.path().join("large.mp3"), vec![0u8; 50_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture size:>10kb").unwrap();
     assert_eq!(results.len(), 1);
@@ -665,7 +666,7 @@ fn test_type_and_size_complex_query() {
     fs::write(tmp.path().join("large_video.mp4"), vec![0u8; 50_000]).unwrap();
     fs::write(tmp.path().join("document.pdf"), vec![0u8; 50_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Pictures over 10KB
     let results = cache.search("type:picture size:>10kb").unwrap();
@@ -694,7 +695,7 @@ fn test_multiple_type_filters_with_or() {
     fs::write(tmp.path().join("photo.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("doc.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache
         .search("type:audio OR type:video OR type:picture")
@@ -719,7 +720,7 @@ fn test_type_filter_with_parent_filter() {
     fs::write(tmp.path().join("videos/photo.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("videos/clip.mp4"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let images_dir = tmp.path().join("images");
     let results = cache
@@ -741,7 +742,7 @@ fn test_type_filter_with_infolder_filter() {
     fs::write(tmp.path().join("media/photos/pic2.png"), b"x").unwrap();
     fs::write(tmp.path().join("doc.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let media_dir = tmp.path().join("media");
     let results = cache
@@ -755,7 +756,7 @@ fn test_type_filter_empty_result() {
     let tmp = TempDir::new("type_empty_result").unwrap();
     fs::write(tmp.path().join("doc.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:audio").unwrap();
     assert_eq!(results.len(), 0);
@@ -770,7 +771,7 @@ fn test_audio_macro_with_all_extensions() {
         fs::write(tmp.path().join(format!("audio.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("audio:").unwrap();
     assert_eq!(results.len(), 11);
@@ -786,7 +787,7 @@ fn test_video_macro_with_all_extensions() {
         fs::write(tmp.path().join(format!("video.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("video:").unwrap();
     assert_eq!(results.len(), 15);
@@ -801,7 +802,7 @@ fn test_doc_macro_with_all_extensions() {
         fs::write(tmp.path().join(format!("document.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("doc:").unwrap();
     assert_eq!(results.len(), 10);
@@ -817,7 +818,7 @@ fn test_exe_macro_with_all_extensions() {
         fs::write(tmp.path().join(format!("program.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("exe:").unwrap();
     assert_eq!(results.len(), 14);
@@ -828,7 +829,7 @@ fn test_type_categories_overlap() {
     let tmp = TempDir::new("type_overlap").unwrap();
     fs::write(tmp.path().join("document.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // PDF is in both doc and pdf categories
     let doc_results = cache.search("type:doc").unwrap();
@@ -846,7 +847,7 @@ fn test_nested_directory_type_filter() {
     fs::write(tmp.path().join("a/b/photo.png"), b"x").unwrap();
     fs::write(tmp.path().join("a/b/c/photo.gif"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(results.len(), 3);
@@ -859,7 +860,7 @@ fn test_wildcard_with_type_filter() {
     fs::write(tmp.path().join("family_photo.png"), b"x").unwrap();
     fs::write(tmp.path().join("work_doc.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("*photo* type:picture").unwrap();
     assert_eq!(results.len(), 2);
@@ -872,7 +873,7 @@ fn test_type_filter_files_without_extensions() {
     fs::write(tmp.path().join("Makefile"), b"x").unwrap();
     fs::write(tmp.path().join("photo.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(
@@ -890,7 +891,7 @@ fn test_type_filter_mixed_case_extensions() {
     fs::write(tmp.path().join("photo3.jPg"), b"x").unwrap();
     fs::write(tmp.path().join("photo4.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(results.len(), 4, "Should handle all case variations");
@@ -901,7 +902,7 @@ fn test_multiple_type_filters_intersection() {
     let tmp = TempDir::new("multi_type_intersect").unwrap();
     fs::write(tmp.path().join("file.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // A file can't be both audio and video, so intersection should be empty
     let results = cache.search("type:audio type:video").unwrap();
@@ -913,7 +914,7 @@ fn test_type_filter_uppercase_category_names() {
     let tmp = TempDir::new("type_uppercase").unwrap();
     fs::write(tmp.path().join("photo.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:PICTURE").unwrap();
     assert_eq!(results.len(), 1);
@@ -928,7 +929,7 @@ fn test_type_code_with_dot_prefixed_files() {
     fs::write(tmp.path().join(".gitignore"), b"x").unwrap();
     fs::write(tmp.path().join("main.rs"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:code").unwrap();
     assert_eq!(results.len(), 1, "Should match main.rs");
@@ -941,7 +942,7 @@ fn test_type_archive_with_nested_structure() {
     fs::write(tmp.path().join("archive.zip"), b"x").unwrap();
     fs::write(tmp.path().join("backups/backup.tar.gz"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:archive").unwrap();
     // Note: .tar.gz might be recognized as .gz extension
@@ -961,7 +962,7 @@ fn test_type_executable_cross_platform() {
     fs::write(tmp.path().join("Calculator.app"), b"x").unwrap();
     fs::write(tmp.path().join("installer.pkg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:executable").unwrap();
     assert_eq!(results.len(), 6);
@@ -973,7 +974,7 @@ fn test_type_spreadsheet_csv_special_case() {
     fs::write(tmp.path().join("data.csv"), b"x").unwrap();
     fs::write(tmp.path().join("sheet.xlsx"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:spreadsheet").unwrap();
     assert_eq!(results.len(), 2, "CSV should be included in spreadsheets");
@@ -988,7 +989,7 @@ fn test_type_filter_performance_many_files() {
         fs::write(tmp.path().join(format!("file_{i}.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert!(!results.is_empty());
@@ -1000,7 +1001,7 @@ fn test_type_with_extension_that_matches_multiple_categories() {
     // PDF is in multiple categories potentially
     fs::write(tmp.path().join("document.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let doc = cache.search("type:doc").unwrap();
     assert!(!doc.is_empty());
@@ -1016,7 +1017,7 @@ fn test_type_filter_with_multiple_dots_in_filename() {
     fs::write(tmp.path().join("backup.tar.bz2"), b"x").unwrap();
     fs::write(tmp.path().join("file.min.js"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Should match based on final extension
     let archives = cache.search("type:archive").unwrap();
@@ -1037,7 +1038,7 @@ fn test_type_filter_all_picture_formats() {
         fs::write(tmp.path().join(format!("image.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(results.len(), picture_exts.len());
@@ -1054,7 +1055,7 @@ fn test_type_filter_all_video_formats() {
         fs::write(tmp.path().join(format!("video.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:video").unwrap();
     assert_eq!(results.len(), video_exts.len());
@@ -1070,7 +1071,7 @@ fn test_type_filter_all_audio_formats() {
         fs::write(tmp.path().join(format!("audio.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:audio").unwrap();
     assert_eq!(results.len(), audio_exts.len());
@@ -1086,7 +1087,7 @@ fn test_type_filter_all_archive_formats() {
         fs::write(tmp.path().join(format!("archive.{ext}")), b"x").unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:archive").unwrap();
     assert_eq!(results.len(), archive_exts.len());
@@ -1117,7 +1118,7 @@ fn test_type_code_comprehensive_languages() {
     fs::write(tmp.path().join("config.json"), b"x").unwrap();
     fs::write(tmp.path().join("data.yaml"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:code").unwrap();
     assert_eq!(results.len(), 13);
@@ -1131,7 +1132,7 @@ fn test_type_with_uncommon_extensions() {
     fs::write(tmp.path().join("audio.opus"), b"x").unwrap();
     fs::write(tmp.path().join("archive.zst"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pictures = cache.search("type:picture").unwrap();
     assert_eq!(pictures.len(), 1);
@@ -1150,7 +1151,7 @@ fn test_type_filter_special_characters_in_names() {
     fs::write(tmp.path().join("song [remix].mp3"), b"x").unwrap();
     fs::write(tmp.path().join("doc & notes.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pictures = cache.search("type:picture").unwrap();
     assert_eq!(pictures.len(), 1);
@@ -1167,7 +1168,7 @@ fn test_type_macros_accept_arguments_as_filters() {
     let tmp = TempDir::new("macro_with_args").unwrap();
     fs::write(tmp.path().join("file_match.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("file_skip.mp3"), b"x").unwrap();
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("audio:match").unwrap();
     assert_eq!(results.len(), 1);
@@ -1182,7 +1183,7 @@ fn test_type_filter_with_wildcard_name() {
     fs::write(tmp.path().join("photo_002.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("image_003.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("photo* type:picture").unwrap();
     assert_eq!(results.len(), 2);
@@ -1199,7 +1200,7 @@ fn test_type_filter_mixed_categories() {
     fs::write(tmp.path().join("f.exe"), b"x").unwrap();
     fs::write(tmp.path().join("g.rs"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let picture = cache.search("type:picture").unwrap();
     assert_eq!(picture.len(), 1);
@@ -1230,7 +1231,7 @@ fn test_type_filter_negation_complex() {
     fs::write(tmp.path().join("video.mp4"), b"x").unwrap();
     fs::write(tmp.path().join("doc.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("!type:picture !type:video").unwrap();
     let has_image = results.iter().any(|&i| {
@@ -1256,7 +1257,7 @@ fn test_type_and_size_with_grouping() {
     fs::write(tmp.path().join("large_video.mp4"), vec![0u8; 100_000]).unwrap();
     fs::write(tmp.path().join("small_video.mp4"), vec![0u8; 1_000]).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache
         .search("(type:picture OR type:video) size:>10kb")
@@ -1271,7 +1272,7 @@ fn test_multiple_ext_and_type_filter() {
     fs::write(tmp.path().join("graphic.png"), b"x").unwrap();
     fs::write(tmp.path().join("document.txt"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // ext: and type: should intersect
     let results = cache.search("ext:jpg;png type:picture").unwrap();
@@ -1285,7 +1286,7 @@ fn test_type_filter_unicode_filenames() {
     fs::write(tmp.path().join("音乐.mp3"), b"x").unwrap();
     fs::write(tmp.path().join("文档.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let pictures = cache.search("type:picture").unwrap();
     assert_eq!(pictures.len(), 1);
@@ -1302,7 +1303,7 @@ fn test_type_folder_with_size_error() {
     let tmp = TempDir::new("type_folder_size").unwrap();
     fs::create_dir(tmp.path().join("folder")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // size: only applies to files, so folders should be excluded
     let results = cache.search("type:folder size:>0").unwrap();
@@ -1315,7 +1316,7 @@ fn test_type_file_basic() {
     fs::write(tmp.path().join("file.txt"), b"x").unwrap();
     fs::create_dir(tmp.path().join("folder")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:file").unwrap();
     assert_eq!(results.len(), 1);
@@ -1330,9 +1331,16 @@ fn test_type_with_hidden_files() {
     fs::write(tmp.path().join(".hidden.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("visible.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
+    assert_eq!(results.len(), 1, "Hidden files are left out by default");
+
+    let options = SearchOptions::default().with_include_hidden(true);
+    let results = cache
+        .search_with_options("type:picture", options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
     assert_eq!(results.len(), 2, "Should match hidden files too");
 }
 
@@ -1341,7 +1349,7 @@ fn test_type_all_alternate_names() {
     let tmp = TempDir::new("type_alt_names").unwrap();
     fs::write(tmp.path().join("image.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Test all alternate names for pictures
     assert_eq!(cache.search("type:picture").unwrap().len(), 1);
@@ -1357,7 +1365,7 @@ fn test_type_with_repeated_filters() {
     let tmp = TempDir::new("type_repeated").unwrap();
     fs::write(tmp.path().join("photo.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Same type filter repeated should still work
     let results = cache.search("type:picture type:picture").unwrap();
@@ -1371,7 +1379,7 @@ fn test_type_extensions_case_normalization() {
     fs::write(tmp.path().join("photo2.JpG"), b"x").unwrap();
     fs::write(tmp.path().join("photo3.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(
@@ -1387,7 +1395,7 @@ fn test_type_empty_extension() {
     fs::write(tmp.path().join("file."), b"x").unwrap();
     fs::write(tmp.path().join("normal.jpg"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(
@@ -1404,7 +1412,7 @@ fn test_type_multiple_extensions_in_filename() {
     fs::write(tmp.path().join("backup.tar.bz2"), b"x").unwrap();
     fs::write(tmp.path().join("data.json.backup"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // Should match based on final extension
     let gz = cache.search("type:archive").unwrap();
@@ -1417,7 +1425,7 @@ fn test_type_and_ext_filter_conflict() {
     fs::write(tmp.path().join("photo.jpg"), b"x").unwrap();
     fs::write(tmp.path().join("document.pdf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // type:picture AND ext:pdf should give empty result
     let results = cache.search("type:picture ext:pdf").unwrap();
@@ -1432,7 +1440,7 @@ fn test_type_code_config_files() {
     fs::write(tmp.path().join("Cargo.toml"), b"x").unwrap();
     fs::write(tmp.path().join("setup.ini"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:code").unwrap();
     assert_eq!(
@@ -1450,7 +1458,7 @@ fn test_type_presentation_all_formats() {
     fs::write(tmp.path().join("keynote.key"), b"x").unwrap();
     fs::write(tmp.path().join("present.odp"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:presentation").unwrap();
     assert_eq!(results.len(), 4);
@@ -1461,7 +1469,7 @@ fn test_type_macros_case_insensitive() {
     let tmp = TempDir::new("macro_case").unwrap();
     fs::write(tmp.path().join("song.mp3"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let lower = cache.search("audio:").unwrap();
     assert_eq!(lower.len(), 1);
@@ -1479,7 +1487,7 @@ fn test_type_file_and_folder_together() {
     fs::write(tmp.path().join("file.txt"), b"x").unwrap();
     fs::create_dir(tmp.path().join("folder")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // file OR folder should return both
     let results = cache.search("type:file OR type:folder").unwrap();
@@ -1493,7 +1501,7 @@ fn test_type_with_no_extension_edge_case() {
     fs::write(tmp.path().join("README"), b"x").unwrap();
     fs::write(tmp.path().join("LICENSE"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     // These files have no extensions, so type filters shouldn't match them
     let results = cache.search("type:doc").unwrap();
@@ -1511,7 +1519,7 @@ fn test_type_picture_raw_formats() {
     fs::write(tmp.path().join("olympus.orf"), b"x").unwrap();
     fs::write(tmp.path().join("fuji.raf"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:picture").unwrap();
     assert_eq!(results.len(), 4, "RAW formats should be recognized");
@@ -1523,7 +1531,7 @@ fn test_type_video_mobile_formats() {
     fs::write(tmp.path().join("video.3gp"), b"x").unwrap();
     fs::write(tmp.path().join("video.3g2"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:video").unwrap();
     assert_eq!(results.len(), 2);
@@ -1538,8 +1546,97 @@ fn test_type_spreadsheet_all_variants() {
     fs::write(tmp.path().join("apple.numbers"), b"x").unwrap();
     fs::write(tmp.path().join("open.ods"), b"x").unwrap();
 
-    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
 
     let results = cache.search("type:spreadsheet").unwrap();
     assert_eq!(results.len(), 5);
 }
+
+fn ext_fixture(name: &str) -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files([
+            "README",
+            "Makefile",
+            ".gitignore",
+            "file.",
+            "photo.png",
+            "scan.JPG",
+            "notes.txt",
+            "docs/guide.md",
+        ])
+        .index(name)
+}
+
+/// Names of the nodes `query` finds, hidden ones included for `.gitignore`.
+fn sorted_names(cache: &SearchCache, query: &str) -> Vec<String> {
+    let options = SearchOptions::default().with_include_hidden(true);
+    let mut names: Vec<String> = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .into_iter()
+        .map(|index| cache.file_nodes[index].name_and_parent.as_str().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn test_ext_none_matches_files_without_extension() {
+    let (_tmp, cache) = ext_fixture("ext_none");
+    let expected = [".gitignore", "Makefile", "README", "file."];
+    assert_eq!(sorted_names(&cache, "ext:-"), expected);
+    assert_eq!(sorted_names(&cache, "ext:none"), expected);
+    assert_eq!(sorted_names(&cache, "ext:NONE"), expected);
+    // Folders never have an extension to match.
+    assert!(!sorted_names(&cache, "ext:-").contains(&"docs".to_string()));
+    assert_eq!(
+        sorted_names(&cache, "ext:none;txt"),
+        [".gitignore", "Makefile", "README", "file.", "notes.txt"]
+    );
+}
+
+#[test]
+fn test_ext_negated_list() {
+    let (_tmp, cache) = ext_fixture("ext_negated");
+    assert_eq!(
+        sorted_names(&cache, "ext:!png;jpg"),
+        [
+            ".gitignore",
+            "Makefile",
+            "README",
+            "file.",
+            "guide.md",
+            "notes.txt"
+        ]
+    );
+    assert_eq!(
+        sorted_names(&cache, "ext:!-"),
+        ["guide.md", "notes.txt", "photo.png", "scan.JPG"]
+    );
+    assert_eq!(
+        sorted_names(&cache, "ext:!png;jpg;none"),
+        ["guide.md", "notes.txt"]
+    );
+    // The NOT operator also drops files, but keeps folders.
+    let not_filter = sorted_names(&cache, "!ext:png;jpg");
+    assert!(not_filter.contains(&"docs".to_string()));
+    assert!(!not_filter.contains(&"photo.png".to_string()));
+    assert!(!not_filter.contains(&"scan.JPG".to_string()));
+    assert!(cache.search("ext:!").is_err());
+}
+
+#[test]
+fn test_ext_variants_combined_with_type_filters() {
+    let (_tmp, cache) = ext_fixture("ext_with_type");
+    assert_eq!(sorted_names(&cache, "type:picture ext:!png"), ["scan.JPG"]);
+    assert!(sorted_names(&cache, "type:picture ext:none").is_empty());
+    assert_eq!(
+        sorted_names(&cache, "file: ext:-"),
+        [".gitignore", "Makefile", "README", "file."]
+    );
+    assert_eq!(
+        sorted_names(&cache, "type:doc ext:!txt"),
+        sorted_names(&cache, "type:doc !ext:txt")
+    );
+}
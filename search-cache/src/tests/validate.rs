@@ -0,0 +1,161 @@
+use super::prelude::*;
+use crate::{SlabIndex, ValidationState, renames::rename_pairs, testkit::TreeSpec};
+use cardinal_sdk::{EventFlag, FsEvent};
+use std::path::Path;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .files(["a.txt", "b.txt", "docs/c.md", "docs/d.md"])
+        .index("validate")
+}
+
+fn index_of(cache: &SearchCache, path: &Path) -> SlabIndex {
+    cache.node_index_for_raw_path(path).unwrap()
+}
+
+/// The two events FSEvents reports for moving `from` to `to`.
+fn rename(cache: &mut SearchCache, from: &Path, to: &Path, kind: EventFlag) -> Vec<FsEvent> {
+    fs::rename(from, to).unwrap();
+    let id = cache.last_event_id() + 1;
+    vec![
+        FsEvent {
+            path: from.to_path_buf(),
+            id,
+            flag: EventFlag::ItemRenamed | kind,
+        },
+        FsEvent {
+            path: to.to_path_buf(),
+            id: id + 1,
+            flag: EventFlag::ItemRenamed | kind,
+        },
+    ]
+}
+
+#[test]
+fn test_validate_removed_nodes() {
+    let (tmp, mut cache) = build_tree();
+    let results = cache.search("txt").unwrap();
+    assert_eq!(results.len(), 2);
+    assert!(
+        cache
+            .validate(&results)
+            .iter()
+            .all(|state| *state == ValidationState::Valid)
+    );
+
+    let a = tmp.path().join("a.txt");
+    let a_index = index_of(&cache, &a);
+    let b_index = index_of(&cache, &tmp.path().join("b.txt"));
+    fs::remove_file(&a).unwrap();
+    let removed = FsEvent {
+        path: a,
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert_eq!(
+        cache.validate(&[a_index, b_index]),
+        [ValidationState::Gone, ValidationState::Valid]
+    );
+    assert!(cache.validate(&[]).is_empty());
+}
+
+#[test]
+fn test_validate_renamed_nodes() {
+    let (tmp, mut cache) = build_tree();
+    let root = tmp.path();
+    let b_index = index_of(&cache, &root.join("b.txt"));
+    let docs_index = index_of(&cache, &root.join("docs"));
+    let c_index = index_of(&cache, &root.join("docs/c.md"));
+    let a_index = index_of(&cache, &root.join("a.txt"));
+
+    let events = rename(
+        &mut cache,
+        &root.join("b.txt"),
+        &root.join("e.txt"),
+        EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(events).unwrap();
+    let events = rename(
+        &mut cache,
+        &root.join("docs"),
+        &root.join("notes"),
+        EventFlag::ItemIsDir,
+    );
+    cache.handle_fs_events(events).unwrap();
+    assert_eq!(
+        cache.validate(&[b_index, docs_index, c_index, a_index]),
+        [
+            ValidationState::Renamed(root.join("e.txt")),
+            ValidationState::Renamed(root.join("notes")),
+            ValidationState::Renamed(root.join("notes/c.md")),
+            ValidationState::Valid,
+        ]
+    );
+
+    // A later rename of the new path is followed.
+    let events = rename(
+        &mut cache,
+        &root.join("e.txt"),
+        &root.join("f.txt"),
+        EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(events).unwrap();
+    assert_eq!(
+        cache.validate(&[b_index]),
+        [ValidationState::Renamed(root.join("f.txt"))]
+    );
+
+    // Then removed for good.
+    fs::remove_file(root.join("f.txt")).unwrap();
+    let removed = FsEvent {
+        path: root.join("f.txt"),
+        id: cache.last_event_id() + 1,
+        flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+    };
+    cache.handle_fs_events(vec![removed]).unwrap();
+    assert_eq!(cache.validate(&[b_index]), [ValidationState::Gone]);
+}
+
+#[test]
+fn test_validate_moved_out_of_tree() {
+    let (tmp, mut cache) = build_tree();
+    let outside = TempDir::new("validate_outside").unwrap();
+    let a = tmp.path().join("a.txt");
+    let a_index = index_of(&cache, &a);
+    let events = rename(
+        &mut cache,
+        &a,
+        &outside.path().join("a.txt"),
+        EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(events).unwrap();
+    assert_eq!(cache.validate(&[a_index]), [ValidationState::Gone]);
+}
+
+#[test]
+fn test_rename_pairs() {
+    let event = |path: &str, id: u64, flag: EventFlag| FsEvent {
+        path: PathBuf::from(path),
+        id,
+        flag,
+    };
+    let renamed = EventFlag::ItemRenamed | EventFlag::ItemIsFile;
+    let events = vec![
+        event("/r/new", 11, renamed),
+        event("/r/x", 12, EventFlag::ItemModified | EventFlag::ItemIsFile),
+        event("/r/old", 10, renamed),
+        // Unpaired: the other half went to a path outside the stream.
+        event("/r/lonely", 20, renamed),
+        event("/r/from", 30, renamed),
+        event("/r/to", 31, renamed),
+    ];
+    assert_eq!(
+        rename_pairs(&events),
+        [
+            (PathBuf::from("/r/old"), PathBuf::from("/r/new")),
+            (PathBuf::from("/r/from"), PathBuf::from("/r/to")),
+        ]
+    );
+    assert!(rename_pairs(&[]).is_empty());
+}
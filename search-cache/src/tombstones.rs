@@ -0,0 +1,269 @@
+use crate::SlabNodeMetadataCompact;
+use fswalk::NodeFileType;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    ffi::OsStr,
+    ops::Bound,
+    os::unix::ffi::OsStrExt,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Default number of deleted entries remembered; the oldest ones are dropped first.
+pub const TOMBSTONE_CAPACITY: usize = 10_000;
+
+/// A file or folder that an FSEvent reported gone.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeletedEntry {
+    /// Exact bytes of the full path, which may not be valid UTF-8.
+    path: Box<[u8]>,
+    pub file_type: NodeFileType,
+    /// Whatever metadata the cache had fetched before the deletion.
+    pub metadata: SlabNodeMetadataCompact,
+    /// Highest event id of the batch that reported the deletion.
+    pub event_id: u64,
+    /// Unix timestamp in seconds.
+    pub deleted_at: u64,
+}
+
+impl DeletedEntry {
+    pub fn new(
+        path: &Path,
+        file_type: NodeFileType,
+        metadata: SlabNodeMetadataCompact,
+        event_id: u64,
+    ) -> Self {
+        Self {
+            path: path.as_os_str().as_bytes().into(),
+            file_type,
+            metadata,
+            event_id,
+            deleted_at: unix_now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        Path::new(OsStr::from_bytes(&self.path))
+    }
+}
+
+/// Bounded, oldest-first record of deleted entries, indexed by path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "TombstoneList", into = "TombstoneList")]
+pub struct Tombstones {
+    /// Keyed by insertion order, so the oldest entry comes first.
+    entries: BTreeMap<u64, DeletedEntry>,
+    /// Path bytes to the key of their entry, for lookups by path and by folder.
+    by_path: BTreeMap<Box<[u8]>, u64>,
+    next_key: u64,
+    capacity: usize,
+}
+
+/// Persisted form of [`Tombstones`]: the entries oldest first, the path index is rebuilt.
+#[derive(Serialize, Deserialize)]
+struct TombstoneList {
+    entries: VecDeque<DeletedEntry>,
+    capacity: usize,
+}
+
+impl From<TombstoneList> for Tombstones {
+    fn from(list: TombstoneList) -> Self {
+        let mut tombstones = Self::with_capacity(list.capacity);
+        for entry in list.entries {
+            tombstones.push(entry);
+        }
+        tombstones
+    }
+}
+
+impl From<Tombstones> for TombstoneList {
+    fn from(tombstones: Tombstones) -> Self {
+        Self {
+            entries: tombstones.entries.into_values().collect(),
+            capacity: tombstones.capacity,
+        }
+    }
+}
+
+impl Default for Tombstones {
+    fn default() -> Self {
+        Self::with_capacity(TOMBSTONE_CAPACITY)
+    }
+}
+
+impl Tombstones {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            by_path: BTreeMap::new(),
+            next_key: 0,
+            capacity,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity, dropping the oldest entries if there are too many.
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.truncate();
+    }
+
+    /// Remember `entry`, replacing an older tombstone of the same path.
+    pub fn push(&mut self, entry: DeletedEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+        let key = self.next_key;
+        self.next_key += 1;
+        if let Some(old) = self.by_path.insert(entry.path.clone(), key) {
+            self.entries.remove(&old);
+        }
+        self.entries.insert(key, entry);
+        self.truncate();
+    }
+
+    /// Drop the tombstone of `path`, e.g. because it was created again.
+    pub fn forget(&mut self, path: &Path) {
+        if let Some(key) = self.by_path.remove(path.as_os_str().as_bytes()) {
+            self.entries.remove(&key);
+        }
+    }
+
+    /// Keep only the tombstones `f` returns true for.
+    pub fn retain(&mut self, mut f: impl FnMut(&DeletedEntry) -> bool) {
+        let by_path = &mut self.by_path;
+        self.entries.retain(|_, entry| {
+            let keep = f(entry);
+            if !keep {
+                by_path.remove(&entry.path);
+            }
+            keep
+        });
+    }
+
+    /// Most recently deleted first.
+    pub fn iter(&self) -> impl Iterator<Item = &DeletedEntry> {
+        self.entries.values().rev()
+    }
+
+    /// Tombstones of `path` and everything below it, in path order.
+    pub fn under<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a DeletedEntry> + 'a {
+        let prefix = path.as_os_str().as_bytes();
+        self.by_path
+            .range::<[u8], _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(entry_path, _)| entry_path.starts_with(prefix))
+            .map(|(_, key)| &self.entries[key])
+            // `/a/b` is a byte prefix of `/a/bc` too.
+            .filter(move |entry| entry.path().starts_with(path))
+    }
+
+    fn truncate(&mut self) {
+        while self.entries.len() > self.capacity {
+            let Some((_, entry)) = self.entries.pop_first() else {
+                break;
+            };
+            self.by_path.remove(&entry.path);
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|x| x.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, event_id: u64) -> DeletedEntry {
+        DeletedEntry::new(
+            Path::new(path),
+            NodeFileType::File,
+            SlabNodeMetadataCompact::none(),
+            event_id,
+        )
+    }
+
+    fn paths(tombstones: &Tombstones) -> Vec<&Path> {
+        tombstones.iter().map(|entry| entry.path()).collect()
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped_at_capacity() {
+        let mut tombstones = Tombstones::with_capacity(2);
+        tombstones.push(entry("/a", 1));
+        tombstones.push(entry("/b", 2));
+        tombstones.push(entry("/c", 3));
+        assert_eq!(paths(&tombstones), [Path::new("/c"), Path::new("/b")]);
+        tombstones.set_capacity(1);
+        assert_eq!(paths(&tombstones), [Path::new("/c")]);
+    }
+
+    #[test]
+    fn test_same_path_keeps_latest_deletion() {
+        let mut tombstones = Tombstones::default();
+        tombstones.push(entry("/a", 1));
+        tombstones.push(entry("/b", 2));
+        tombstones.push(entry("/a", 3));
+        assert_eq!(tombstones.len(), 2);
+        let latest = tombstones.iter().next().unwrap();
+        assert_eq!(latest.path(), Path::new("/a"));
+        assert_eq!(latest.event_id, 3);
+        tombstones.forget(Path::new("/a"));
+        assert_eq!(paths(&tombstones), [Path::new("/b")]);
+    }
+
+    #[test]
+    fn test_zero_capacity_keeps_nothing() {
+        let mut tombstones = Tombstones::with_capacity(0);
+        tombstones.push(entry("/a", 1));
+        assert!(tombstones.is_empty());
+    }
+
+    #[test]
+    fn test_under_lists_a_folder_in_path_order() {
+        let mut tombstones = Tombstones::default();
+        tombstones.push(entry("/a/b/y", 1));
+        tombstones.push(entry("/a/bc", 2));
+        tombstones.push(entry("/a/b", 3));
+        tombstones.push(entry("/a/b/x", 4));
+        tombstones.push(entry("/c", 5));
+        let under: Vec<_> = tombstones
+            .under(Path::new("/a/b"))
+            .map(|entry| entry.path())
+            .collect();
+        assert_eq!(
+            under,
+            [Path::new("/a/b"), Path::new("/a/b/x"), Path::new("/a/b/y")]
+        );
+    }
+
+    #[test]
+    fn test_persisted_form_keeps_the_order() {
+        let mut tombstones = Tombstones::with_capacity(3);
+        tombstones.push(entry("/b", 1));
+        tombstones.push(entry("/a", 2));
+        tombstones.push(entry("/b", 3));
+        let bytes = postcard::to_allocvec(&tombstones).unwrap();
+        let mut loaded: Tombstones = postcard::from_bytes(&bytes).unwrap();
+        assert_eq!(paths(&loaded), [Path::new("/b"), Path::new("/a")]);
+        assert_eq!(loaded.capacity(), 3);
+        loaded.forget(Path::new("/b"));
+        assert_eq!(paths(&loaded), [Path::new("/a")]);
+    }
+}
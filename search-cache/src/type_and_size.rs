@@ -12,6 +12,11 @@ impl StateTypeSize {
         Self::new(State::None, NodeFileType::File, 0)
     }
 
+    /// `State::None` that keeps a known file type.
+    pub(crate) fn none_of(r#type: NodeFileType) -> Self {
+        Self::new(State::None, r#type, 0)
+    }
+
     pub fn unaccessible() -> Self {
         assert_eq!(NodeFileType::File as u8, 0);
         Self::new(State::Unaccessible, NodeFileType::File, 0)
@@ -36,6 +41,14 @@ impl StateTypeSize {
     pub fn size(&self) -> u64 {
         self.0 & ((1u64 << 60) - 1)
     }
+
+    pub(crate) fn to_bits(self) -> u64 {
+        self.0
+    }
+
+    pub(crate) fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
 }
 
 #[derive(Debug, Clone, Copy, enumn::N, PartialEq, Eq)]
@@ -0,0 +1,451 @@
+//! A `CacheSet` searches several independent caches as one, each persisted
+//! to its own file and updated by the events under its root.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{CacheSet, QueryNote, SearchCache, SearchError, SearchOptions, SetIndex};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+struct Volumes {
+    tmp: TempDir,
+    system: PathBuf,
+    external: PathBuf,
+}
+
+/// system/{notes.txt, shared.txt, docs/report.txt}, external/{photo.jpg, shared.txt}
+fn volumes() -> Volumes {
+    let tmp = TempDir::new("cache_set").unwrap();
+    let system = tmp.path().join("system");
+    let external = tmp.path().join("external");
+    fs::create_dir_all(system.join("docs")).unwrap();
+    fs::create_dir_all(&external).unwrap();
+    fs::write(system.join("notes.txt"), b"n").unwrap();
+    fs::write(system.join("shared.txt"), b"s").unwrap();
+    fs::write(system.join("docs/report.txt"), b"r").unwrap();
+    fs::write(external.join("photo.jpg"), b"p").unwrap();
+    fs::write(external.join("shared.txt"), b"s").unwrap();
+    Volumes {
+        tmp,
+        system,
+        external,
+    }
+}
+
+impl Volumes {
+    fn cache_path(&self, name: &str) -> PathBuf {
+        self.tmp.path().join(format!("{name}.zstd"))
+    }
+
+    fn set(&self) -> CacheSet {
+        let mut set = CacheSet::new();
+        let system = SearchCache::walk_fs(self.system.clone());
+        let external = SearchCache::walk_fs(self.external.clone());
+        assert_eq!(set.insert(system, self.cache_path("system")).unwrap(), 0);
+        assert_eq!(
+            set.insert(external, self.cache_path("external")).unwrap(),
+            1
+        );
+        set
+    }
+}
+
+fn paths(set: &CacheSet, query: &str) -> Vec<PathBuf> {
+    let outcome = set
+        .search_with_options(
+            query,
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let mut paths: Vec<PathBuf> = outcome
+        .nodes
+        .iter()
+        .filter_map(|&node| set.node_path(node))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn event(path: &Path, flag: EventFlag) -> FsEvent {
+    FsEvent {
+        path: path.to_path_buf(),
+        id: 1,
+        flag,
+    }
+}
+
+#[test]
+fn queries_hit_every_member() {
+    let volumes = volumes();
+    let set = volumes.set();
+    assert_eq!(set.len(), 2);
+    assert_eq!(
+        set.get_total_files(),
+        set.members()
+            .map(|(_, cache)| cache.get_total_files())
+            .sum::<usize>()
+    );
+    assert_eq!(set.walk_totals().unwrap().files, 5);
+
+    assert_eq!(
+        paths(&set, "shared.txt"),
+        [
+            volumes.external.join("shared.txt"),
+            volumes.system.join("shared.txt"),
+        ]
+    );
+    let nodes = set
+        .query_files_with_options(
+            "shared.txt",
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let mut sources: Vec<(usize, PathBuf)> = nodes
+        .into_iter()
+        .map(|node| (node.source, node.path))
+        .collect();
+    sources.sort();
+    assert_eq!(
+        sources,
+        [
+            (0, volumes.system.join("shared.txt")),
+            (1, volumes.external.join("shared.txt")),
+        ]
+    );
+
+    // Expanded nodes keep their order and source.
+    let outcome = set
+        .search_with_options(
+            "photo.jpg | report.txt",
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let expanded = set.expand_file_nodes(&outcome.nodes);
+    assert_eq!(expanded.len(), 2);
+    for (node, expanded) in outcome.nodes.iter().zip(&expanded) {
+        assert_eq!(expanded.source, node.source());
+        assert_eq!(Some(&expanded.path), set.node_path(*node).as_ref());
+        assert!(expanded.metadata.is_some());
+    }
+}
+
+#[test]
+fn scoped_and_narrowed_searches_stay_in_one_member() {
+    let volumes = volumes();
+    let set = volumes.set();
+    let docs = set
+        .node_index_for_raw_path(&volumes.system.join("docs"))
+        .unwrap();
+    assert_eq!(docs.source(), 0);
+    let outcome = set
+        .search_with_options(
+            ".txt",
+            Some(docs),
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    assert_eq!(outcome.nodes.len(), 1);
+    assert_eq!(
+        set.node_path(outcome.nodes[0]),
+        Some(volumes.system.join("docs/report.txt"))
+    );
+
+    let base = set
+        .search_with_options(
+            ".txt",
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap()
+        .nodes;
+    let narrowed = set
+        .search_within(
+            &base,
+            "shared",
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let mut sources: Vec<usize> = narrowed.nodes.iter().map(|node| node.source()).collect();
+    sources.sort();
+    assert_eq!(sources, [0, 1]);
+}
+
+#[test]
+fn query_files_honor_the_scope() {
+    let volumes = volumes();
+    let set = volumes.set();
+    let docs = set
+        .node_index_for_raw_path(&volumes.system.join("docs"))
+        .unwrap();
+    let nodes = set
+        .query_files_with_options(
+            ".txt",
+            Some(docs),
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap();
+    let paths: Vec<_> = nodes.into_iter().map(|node| node.path).collect();
+    assert_eq!(paths, [volumes.system.join("docs/report.txt")]);
+}
+
+#[test]
+fn invalid_scope_names_its_member() {
+    let volumes = volumes();
+    let set = volumes.set();
+    let photo = set
+        .node_index_for_raw_path(&volumes.external.join("photo.jpg"))
+        .unwrap();
+    let error = set
+        .search_with_options(
+            "shared",
+            Some(photo),
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidScope(scope) if scope == photo));
+    let error = set
+        .query_files_with_options(
+            "shared",
+            Some(photo),
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap_err();
+    assert!(matches!(error, SearchError::InvalidScope(scope) if scope == photo));
+    assert!(error.to_string().contains("source 1"), "{error}");
+}
+
+#[test]
+fn set_indices_of_the_first_member_match_slab_indices() {
+    let volumes = volumes();
+    let set = volumes.set();
+    let report = volumes.system.join("docs/report.txt");
+    let node = set.node_index_for_raw_path(&report).unwrap();
+    let slab_index = set
+        .member(0)
+        .unwrap()
+        .node_index_for_raw_path(&report)
+        .unwrap();
+    assert_eq!(
+        serde_json::to_string(&node).unwrap(),
+        serde_json::to_string(&slab_index).unwrap()
+    );
+    let photo = set
+        .node_index_for_raw_path(&volumes.external.join("photo.jpg"))
+        .unwrap();
+    assert_eq!(photo.source(), 1);
+    let decoded: SetIndex = serde_json::from_str(&serde_json::to_string(&photo).unwrap()).unwrap();
+    assert_eq!(decoded, photo);
+}
+
+#[test]
+fn case_insensitive_lookup_finds_the_owning_member() {
+    let volumes = volumes();
+    let set = volumes.set();
+    let photo = set
+        .node_index_for_raw_path(&volumes.external.join("photo.jpg"))
+        .unwrap();
+    assert_eq!(
+        set.node_index_for_path_ci(&volumes.external.join("PHOTO.JPG")),
+        Some(photo)
+    );
+    // No member's root contains the path as spelled, they're all asked.
+    let shouting = PathBuf::from(volumes.external.to_str().unwrap().to_uppercase());
+    assert_eq!(set.owner(&shouting), None);
+    assert_eq!(
+        set.node_index_for_path_ci(&shouting.join("Photo.jpg")),
+        Some(photo)
+    );
+    assert_eq!(
+        set.node_index_for_path_ci(&volumes.system.join("DOCS/missing.txt")),
+        None
+    );
+}
+
+#[test]
+fn folder_filters_only_note_folders_no_member_indexes() {
+    let volumes = volumes();
+    let set = volumes.set();
+    let search = |query: String| {
+        set.search_with_options(
+            &query,
+            None,
+            SearchOptions::default(),
+            CancellationToken::noop(),
+        )
+        .unwrap()
+    };
+    // Only the system member has the folder, the other one finds nothing quietly.
+    let docs = volumes.system.join("docs");
+    let outcome = search(format!("parent:{}", docs.display()));
+    assert_eq!(outcome.nodes.len(), 1);
+    assert!(outcome.notes.is_empty());
+
+    let missing = volumes.external.join("missing");
+    let outcome = search(format!("infolder:{}", missing.display()));
+    assert!(outcome.nodes.is_empty());
+    assert_eq!(outcome.notes, [QueryNote::ParentNotIndexed(missing)]);
+}
+
+#[test]
+fn members_are_flushed_and_loaded_separately() {
+    let volumes = volumes();
+    let set = volumes.set();
+    set.flush_to_files().unwrap();
+    assert!(volumes.cache_path("system").exists());
+    assert!(volumes.cache_path("external").exists());
+
+    // The external disk's file went missing, the system cache still loads.
+    fs::remove_file(volumes.cache_path("external")).unwrap();
+    let (mut set, failed) = CacheSet::load(
+        [
+            (volumes.system.clone(), volumes.cache_path("system")),
+            (volumes.external.clone(), volumes.cache_path("external")),
+        ],
+        None,
+        None,
+    );
+    assert_eq!(set.len(), 1);
+    assert_eq!(failed.len(), 1);
+    assert_eq!(failed[0].0, volumes.external);
+    assert_eq!(
+        paths(&set, "shared.txt"),
+        [volumes.system.join("shared.txt")]
+    );
+
+    let external = SearchCache::walk_fs(volumes.external.clone());
+    let source = set
+        .insert(external, volumes.cache_path("external"))
+        .unwrap();
+    assert_eq!(
+        set.cache_path(source),
+        Some(volumes.cache_path("external").as_path())
+    );
+    assert_eq!(paths(&set, "shared.txt").len(), 2);
+    assert!(
+        set.insert(
+            SearchCache::walk_fs(volumes.system.clone()),
+            volumes.cache_path("again"),
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn events_go_to_the_member_owning_the_path() {
+    let volumes = volumes();
+    let mut set = volumes.set();
+    let system_total = set.member(0).unwrap().get_total_files();
+    let added = volumes.external.join("video.mov");
+    fs::write(&added, b"v").unwrap();
+    let removed = volumes.system.join("notes.txt");
+    fs::remove_file(&removed).unwrap();
+    let outside = volumes.tmp.path().join("elsewhere.txt");
+    fs::write(&outside, b"e").unwrap();
+
+    let rescans = set.handle_fs_events(vec![
+        event(&added, EventFlag::ItemCreated | EventFlag::ItemIsFile),
+        event(&removed, EventFlag::ItemRemoved | EventFlag::ItemIsFile),
+        event(&outside, EventFlag::ItemCreated | EventFlag::ItemIsFile),
+    ]);
+    assert!(rescans.is_empty());
+    assert_eq!(paths(&set, "video.mov"), [added]);
+    assert!(paths(&set, "notes.txt").is_empty());
+    assert!(paths(&set, "elsewhere").is_empty());
+    assert_eq!(set.member(0).unwrap().get_total_files(), system_total - 1);
+    assert_eq!(set.owner(&volumes.external.join("x")), Some(1));
+    assert_eq!(set.owner(&outside), None);
+}
+
+#[test]
+fn restarted_watchers_replay_into_every_member() {
+    let volumes = volumes();
+    let mut set = volumes.set();
+    assert!(!set.replaying());
+    set.begin_replay();
+    for (_, cache) in set.members() {
+        assert!(cache.replaying());
+    }
+
+    // Numbered before the walks, the members hold what it reports.
+    let added = volumes.external.join("video.mov");
+    fs::write(&added, b"v").unwrap();
+    let created = || event(&added, EventFlag::ItemCreated | EventFlag::ItemIsFile);
+    set.handle_fs_events(vec![created()]);
+    assert!(paths(&set, "video.mov").is_empty());
+    assert_eq!(
+        set.member(1).unwrap().event_id_stats().replayed_duplicates,
+        1
+    );
+
+    let history_done = event(volumes.tmp.path(), EventFlag::HistoryDone);
+    set.handle_fs_events(vec![history_done, created()]);
+    assert!(!set.replaying());
+    assert_eq!(paths(&set, "video.mov"), [added]);
+}
+
+#[test]
+fn nested_member_takes_its_subtree_over() {
+    let tmp = TempDir::new("cache_set_nested").unwrap();
+    let root = tmp.path().join("root");
+    let volume = root.join("Volumes/Ext");
+    fs::create_dir_all(&volume).unwrap();
+    fs::write(root.join("home.txt"), b"h").unwrap();
+    fs::write(volume.join("ext.txt"), b"e").unwrap();
+
+    let mut set = CacheSet::new();
+    set.insert(
+        SearchCache::walk_fs(root.clone()),
+        tmp.path().join("root.zstd"),
+    )
+    .unwrap();
+    let outer_total = set.member(0).unwrap().get_total_files();
+    set.insert(
+        SearchCache::walk_fs(volume.clone()),
+        tmp.path().join("ext.zstd"),
+    )
+    .unwrap();
+    // Ext and ext.txt left the outer member, every file is found once.
+    assert_eq!(set.member(0).unwrap().get_total_files(), outer_total - 2);
+    assert_eq!(paths(&set, "ext.txt"), [volume.join("ext.txt")]);
+    assert_eq!(
+        paths(&set, ".txt"),
+        [root.join("Volumes/Ext/ext.txt"), root.join("home.txt")]
+    );
+
+    let added = volume.join("new.txt");
+    fs::write(&added, b"n").unwrap();
+    assert_eq!(set.owner(&added), Some(1));
+    set.handle_fs_events(vec![event(
+        &added,
+        EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    )]);
+    assert_eq!(paths(&set, "new.txt"), [added]);
+    assert!(
+        set.member(0)
+            .unwrap()
+            .node_index_for_raw_path(&volume)
+            .is_none()
+    );
+
+    // Unplugging the volume leaves the outer member as it was.
+    let (removed, _) = set.remove(1).unwrap();
+    assert_eq!(removed.root(), volume);
+    assert_eq!(set.len(), 1);
+    assert_eq!(paths(&set, ".txt"), [root.join("home.txt")]);
+}
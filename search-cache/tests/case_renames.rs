@@ -0,0 +1,120 @@
+//! Renames that only change the case of a name keep the node, under its new
+//! spelling. On a case-insensitive volume both spellings of such a rename
+//! still resolve on disk, on a case-sensitive one the old spelling is gone;
+//! either way the result must be the same.
+
+use cardinal_sdk::EventFlag;
+use search_cache::{
+    SearchCache, SearchOptions,
+    testkit::{EventScript, assert_integrity},
+};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+fn paths(cache: &SearchCache, query: &str) -> Vec<PathBuf> {
+    let nodes = cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    cache
+        .node_paths(&nodes)
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+fn node_count(cache: &SearchCache) -> usize {
+    cache.search_empty(CancellationToken::noop()).unwrap().len()
+}
+
+/// Rename `from` to `to` on disk and feed the events FSEvents reports for it.
+fn rename(cache: &mut SearchCache, from: &Path, to: &Path) {
+    let mut script = EventScript::after(cache);
+    script.rename(from, to);
+    script.apply(cache).unwrap();
+}
+
+#[test]
+fn case_only_file_rename_keeps_the_node() {
+    let tmp = TempDir::new("case_rename").unwrap();
+    let root = tmp.path();
+    let old = root.join("readme.md");
+    let new = root.join("README.md");
+    fs::write(&old, b"# notes").unwrap();
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    let index = cache.node_index_for_raw_path(&old).unwrap();
+    let count = node_count(&cache);
+
+    rename(&mut cache, &old, &new);
+    assert_integrity(&cache);
+    assert_eq!(node_count(&cache), count);
+    assert_eq!(cache.node_index_for_raw_path(&new), Some(index));
+    assert!(paths(&cache, "readme.md").is_empty());
+    assert_eq!(paths(&cache, "README.md"), vec![new.clone()]);
+    assert_eq!(paths(&cache, "ext:md"), vec![new.clone()]);
+
+    // And back, delivered the other way round.
+    fs::rename(&new, &old).unwrap();
+    let renamed = EventFlag::ItemRenamed | EventFlag::ItemIsFile;
+    let mut script = EventScript::after(&cache);
+    script.event(&old, renamed).event(&new, renamed);
+    script.apply(&mut cache).unwrap();
+    assert_integrity(&cache);
+    assert_eq!(node_count(&cache), count);
+    assert_eq!(cache.node_index_for_raw_path(&old), Some(index));
+    assert!(paths(&cache, "README.md").is_empty());
+    assert_eq!(paths(&cache, "ext:md"), vec![old]);
+}
+
+#[test]
+fn case_only_folder_rename_moves_its_children() {
+    let tmp = TempDir::new("case_rename").unwrap();
+    let root = tmp.path();
+    let old = root.join("photos");
+    let new = root.join("Photos");
+    fs::create_dir(&old).unwrap();
+    fs::write(old.join("a.jpg"), b"a").unwrap();
+    fs::create_dir(old.join("trip")).unwrap();
+    fs::write(old.join("trip/b.jpg"), b"b").unwrap();
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    let index = cache.node_index_for_raw_path(&old).unwrap();
+    let count = node_count(&cache);
+
+    rename(&mut cache, &old, &new);
+    assert_integrity(&cache);
+    assert_eq!(node_count(&cache), count);
+    assert_eq!(cache.node_index_for_raw_path(&new), Some(index));
+    assert!(paths(&cache, "photos").is_empty());
+    let mut jpgs = paths(&cache, "ext:jpg");
+    jpgs.sort();
+    assert_eq!(jpgs, vec![new.join("a.jpg"), new.join("trip/b.jpg")]);
+}
+
+#[test]
+fn names_differing_in_case_stay_apart_when_both_exist() {
+    let tmp = TempDir::new("case_rename").unwrap();
+    let root = tmp.path();
+    let lower = root.join("notes.txt");
+    let upper = root.join("NOTES.txt");
+    fs::write(&lower, b"lower").unwrap();
+    fs::write(&upper, b"upper").unwrap();
+    if fs::read(&lower).unwrap() == b"upper" {
+        // A case-insensitive volume: both names are the same file.
+        return;
+    }
+    fs::remove_file(&upper).unwrap();
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    let index = cache.node_index_for_raw_path(&lower).unwrap();
+
+    let mut script = EventScript::after(&cache);
+    script.create_file(&upper, b"upper");
+    script.apply(&mut cache).unwrap();
+    assert_integrity(&cache);
+    assert_eq!(cache.node_index_for_raw_path(&lower), Some(index));
+    assert_eq!(paths(&cache, "notes.txt"), vec![lower]);
+    assert_eq!(paths(&cache, "NOTES.txt"), vec![upper]);
+}
@@ -0,0 +1,94 @@
+//! Searches only need `&SearchCache`, so readers can share the cache behind a
+//! `RwLock` while FSEvents are applied through the write side.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{SearchCache, SearchOptions};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::Path,
+    sync::{
+        Arc, Barrier, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+};
+use tempdir::TempDir;
+
+const KEPT: usize = 200;
+const CHURNED: usize = 50;
+const ROUNDS: u64 = 20;
+const READERS: usize = 4;
+
+fn count(cache: &SearchCache, query: &str) -> usize {
+    cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .len()
+}
+
+fn churn_events(root: &Path, first_id: u64, create: bool) -> Vec<FsEvent> {
+    (0..CHURNED)
+        .zip(first_id..)
+        .map(|(i, id)| {
+            let path = root.join(format!("churn_{i:03}.txt"));
+            let flag = if create {
+                fs::write(&path, b"churn").unwrap();
+                EventFlag::ItemCreated | EventFlag::ItemIsFile
+            } else {
+                fs::remove_file(&path).unwrap();
+                EventFlag::ItemRemoved | EventFlag::ItemIsFile
+            };
+            FsEvent { path, id, flag }
+        })
+        .collect()
+}
+
+#[test]
+fn readers_search_while_a_writer_applies_events() {
+    let tmp = TempDir::new("concurrent_search").unwrap();
+    let root = tmp.path().to_path_buf();
+    for i in 0..KEPT {
+        fs::write(root.join(format!("keep_{i:03}.txt")), b"kept").unwrap();
+    }
+    let cache = Arc::new(RwLock::new(SearchCache::walk_fs(root.clone())));
+    let done = Arc::new(AtomicBool::new(false));
+    let start = Arc::new(Barrier::new(READERS + 1));
+
+    let readers: Vec<_> = (0..READERS)
+        .map(|_| {
+            let (cache, done, start) = (cache.clone(), done.clone(), start.clone());
+            thread::spawn(move || {
+                start.wait();
+                let mut searches = 0;
+                while !done.load(Ordering::Acquire) || searches == 0 {
+                    let cache = cache.read().unwrap();
+                    assert_eq!(count(&cache, "keep_"), KEPT);
+                    // Sizes aren't known after the walk, each reader may fetch them.
+                    assert_eq!(count(&cache, "keep_ size:>0"), KEPT);
+                    let churned = count(&cache, "churn_");
+                    assert!(churned == 0 || churned == CHURNED, "{churned} churn files");
+                    searches += 1;
+                }
+                searches
+            })
+        })
+        .collect();
+
+    start.wait();
+    for round in 0..ROUNDS {
+        let mut cache = cache.write().unwrap();
+        let first_id = cache.last_event_id() + 1;
+        let events = churn_events(&root, first_id, round % 2 == 0);
+        cache.handle_fs_events(events).unwrap();
+    }
+    done.store(true, Ordering::Release);
+
+    for reader in readers {
+        assert!(reader.join().unwrap() > 0);
+    }
+    let cache = cache.read().unwrap();
+    assert_eq!(count(&cache, "churn_"), 0);
+    assert_eq!(cache.query_history().len(), 3);
+}
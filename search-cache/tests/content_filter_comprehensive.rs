@@ -1,4 +1,4 @@
-use search_cache::{SearchCache, SearchOptions, SlabIndex};
+use search_cache::{SearchCache, SearchOptions, SearchResult, SlabIndex};
 use search_cancel::CancellationToken;
 use std::fs;
 use tempdir::TempDir;
@@ -6,11 +6,8 @@ use tempdir::TempDir;
 // Public constant from the implementation
 const CONTENT_BUFFER_BYTES: usize = 64 * 1024;
 
-fn guard_indices(result: Result<search_cache::SearchOutcome, anyhow::Error>) -> Vec<SlabIndex> {
-    result
-        .expect("search should succeed")
-        .nodes
-        .expect("noop cancellation token should not cancel")
+fn guard_indices(result: SearchResult<search_cache::SearchOutcome>) -> Vec<SlabIndex> {
+    result.expect("search should succeed").nodes
 }
 
 /// Test empty needle rejection
@@ -20,12 +17,10 @@ fn content_filter_rejects_empty_needle() {
     let dir = temp_dir.path();
     fs::write(dir.join("file.txt"), b"content").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let result = cache.search_with_options(
         r#"content:"""#,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     );
     assert!(result.is_err());
@@ -45,14 +40,12 @@ fn content_filter_single_byte_exact_match() {
     fs::write(dir.join("lower.txt"), b"abcdefg").unwrap();
     fs::write(dir.join("upper.txt"), b"ABCDEFG").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Case sensitive: only lowercase 'a' should match
     let indices = guard_indices(cache.search_with_options(
         "content:a",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -62,9 +55,7 @@ fn content_filter_single_byte_exact_match() {
     // Case sensitive: only uppercase 'A' should match
     let indices = guard_indices(cache.search_with_options(
         "content:A",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -81,14 +72,12 @@ fn content_filter_single_byte_case_insensitive() {
     fs::write(dir.join("upper.txt"), b"ABCDEFG").unwrap();
     fs::write(dir.join("none.txt"), b"xyz123").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Case insensitive: both 'a' and 'A' should match
     let indices = guard_indices(cache.search_with_options(
         "content:a",
-        SearchOptions {
-            case_insensitive: true,
-        },
+        SearchOptions::default().with_case_insensitive(true),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 2);
@@ -98,9 +87,7 @@ fn content_filter_single_byte_case_insensitive() {
 
     let indices = guard_indices(cache.search_with_options(
         "content:A",
-        SearchOptions {
-            case_insensitive: true,
-        },
+        SearchOptions::default().with_case_insensitive(true),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 2);
@@ -118,12 +105,10 @@ fn content_filter_needle_spans_exact_buffer_boundary() {
     payload.extend(vec![b'y'; 100]);
     fs::write(dir.join("exact.bin"), &payload).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:BOUNDARY",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -144,12 +129,10 @@ fn content_filter_needle_spans_three_chunks() {
     payload.extend(vec![b'b'; 100]);
     fs::write(dir.join("three_chunks.bin"), &payload).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:LONGNEEDLE",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -169,13 +152,11 @@ fn content_filter_needle_exceeds_buffer_size() {
     payload.extend(vec![b'z'; 50]);
     fs::write(dir.join("long_needle.bin"), &payload).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let query = format!("content:{needle}");
     let indices = guard_indices(cache.search_with_options(
         &query,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -193,12 +174,10 @@ fn content_filter_needle_equals_overlap_size() {
     payload.extend(vec![b'y'; 10]);
     fs::write(dir.join("overlap.bin"), &payload).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:AB",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -212,12 +191,10 @@ fn content_filter_file_smaller_than_buffer() {
 
     fs::write(dir.join("tiny.txt"), b"small content here").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:content",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -231,12 +208,10 @@ fn content_filter_empty_file_returns_no_match() {
 
     fs::write(dir.join("empty.txt"), b"").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:anything",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 0);
@@ -250,12 +225,10 @@ fn content_filter_needle_at_file_start() {
 
     fs::write(dir.join("start.txt"), b"STARTrest of content").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:START",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -269,12 +242,10 @@ fn content_filter_needle_at_file_end() {
 
     fs::write(dir.join("end.txt"), b"content before END").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:END",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -288,12 +259,10 @@ fn content_filter_multiple_occurrences_in_file() {
 
     fs::write(dir.join("multi.txt"), b"foo bar foo baz foo").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:foo",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1); // Still only one file matches
@@ -310,12 +279,10 @@ fn content_filter_binary_with_null_bytes() {
     binary.extend_from_slice(&[0u8, 255u8, 128u8]);
     fs::write(dir.join("binary.bin"), &binary).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:TARGET",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -329,14 +296,12 @@ fn content_filter_utf8_multibyte_characters() {
 
     fs::write(dir.join("utf8.txt"), "Hello 世界 Rust 🦀".as_bytes()).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Search for Chinese characters
     let indices = guard_indices(cache.search_with_options(
         "content:世界",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -344,9 +309,7 @@ fn content_filter_utf8_multibyte_characters() {
     // Search for emoji
     let indices = guard_indices(cache.search_with_options(
         "content:🦀",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -364,12 +327,10 @@ fn content_filter_utf8_split_across_boundary() {
     payload.extend(vec![b'b'; 100]);
     fs::write(dir.join("utf8_boundary.txt"), &payload).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:世界",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -387,14 +348,12 @@ fn content_filter_special_characters() {
     )
     .unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Special symbols (use quotes to preserve the content)
     let indices = guard_indices(cache.search_with_options(
         r#"content:"!@#$%""#,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -402,9 +361,7 @@ fn content_filter_special_characters() {
     // Test parentheses
     let indices = guard_indices(cache.search_with_options(
         r#"content:"&*()""#,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -419,12 +376,10 @@ fn content_filter_ignores_directories() {
     fs::create_dir(dir.join("subdir")).unwrap();
     fs::write(dir.join("subdir/file.txt"), b"content").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:content",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
 
@@ -444,14 +399,12 @@ fn content_filter_combined_with_extension() {
     fs::write(dir.join("match.md"), b"Bearer token").unwrap();
     fs::write(dir.join("nomatch.txt"), b"no token").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Search for .txt files containing "Bearer"
     let indices = guard_indices(cache.search_with_options(
         "*.txt content:Bearer",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -461,9 +414,7 @@ fn content_filter_combined_with_extension() {
     // Search for .md files containing "Bearer"
     let indices = guard_indices(cache.search_with_options(
         "*.md content:Bearer",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -482,15 +433,13 @@ fn content_filter_combined_with_infolder() {
     fs::write(dir.join("target/match.txt"), b"secret key").unwrap();
     fs::write(dir.join("other/nomatch.txt"), b"secret key").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     let target_path = dir.join("target").to_str().unwrap().to_string();
     let query = format!("infolder:{target_path} content:\"secret key\"");
     let indices = guard_indices(cache.search_with_options(
         &query,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -507,14 +456,12 @@ fn content_filter_combined_with_size() {
     fs::write(dir.join("small.txt"), b"test").unwrap(); // 4 bytes
     fs::write(dir.join("large.txt"), vec![b't'; 2000]).unwrap(); // 2000 bytes
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Search for files > 1KB containing 't'
     let indices = guard_indices(cache.search_with_options(
         "size:>1kb content:t",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -531,14 +478,12 @@ fn content_filter_with_not_operator() {
     fs::write(dir.join("has_secret.txt"), b"secret key").unwrap();
     fs::write(dir.join("no_secret.txt"), b"public data").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Search for .txt files that don't contain "secret"
     let indices = guard_indices(cache.search_with_options(
         "*.txt !content:secret",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -556,14 +501,12 @@ fn content_filter_with_or_operator() {
     fs::write(dir.join("fixme.txt"), b"FIXME: urgent").unwrap();
     fs::write(dir.join("clean.txt"), b"all good").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Search for files containing either TODO or FIXME
     let indices = guard_indices(cache.search_with_options(
         "content:TODO | content:FIXME",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 2);
@@ -584,7 +527,7 @@ fn content_filter_respects_cancellation() {
         fs::write(dir.join(format!("large{i}.bin")), content).unwrap();
     }
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Create a cancelled token
     let token = CancellationToken::new(999);
@@ -592,17 +535,12 @@ fn content_filter_respects_cancellation() {
 
     let result = cache.search_with_options(
         "content:needle",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         token,
     );
 
     // Should return cancelled outcome
-    assert!(matches!(
-        result,
-        Ok(search_cache::SearchOutcome { nodes: None, .. })
-    ));
+    assert!(matches!(result, Err(search_cache::SearchError::Cancelled)));
 }
 
 /// Test unreadable file (permission denied scenario simulation)
@@ -623,12 +561,10 @@ fn content_filter_handles_unreadable_file() {
     perms.set_mode(0o000);
     fs::set_permissions(&unreadable, perms).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:secret",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
 
@@ -651,32 +587,26 @@ fn content_filter_regex_special_chars_treated_literally() {
 
     fs::write(dir.join("regex.txt"), b"file.* [test]+ (group)? ^start$").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // These should be treated as literal strings, not regex
     let indices = guard_indices(cache.search_with_options(
         "content:.*",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
 
     let indices = guard_indices(cache.search_with_options(
         "content:[test]+",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
 
     let indices = guard_indices(cache.search_with_options(
         "content:(group)?",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -694,12 +624,10 @@ fn content_filter_handles_long_lines() {
     content.extend(vec![b'b'; 1000]);
     fs::write(dir.join("long_line.txt"), content).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:NEEDLE",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -713,14 +641,12 @@ fn content_filter_whitespace_in_needle() {
 
     fs::write(dir.join("whitespace.txt"), b"line one word three   spaced").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Search with space (use quotes to preserve the space)
     let indices = guard_indices(cache.search_with_options(
         r#"content:"word three""#,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -728,9 +654,7 @@ fn content_filter_whitespace_in_needle() {
     // Search with multiple spaces
     let indices = guard_indices(cache.search_with_options(
         r#"content:"three   spaced""#,
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -744,15 +668,13 @@ fn content_filter_case_insensitive_mixed_case() {
 
     fs::write(dir.join("mixed.txt"), b"ThIsIsMiXeDCaSe").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // All variations should match case-insensitively (using quoted strings)
     for needle in ["thisismixedcase", "THISISMIXEDCASE", "ThIsIsMiXeDCaSe"] {
         let indices = guard_indices(cache.search_with_options(
             &format!(r#"content:"{needle}""#),
-            SearchOptions {
-                case_insensitive: true,
-            },
+            SearchOptions::default().with_case_insensitive(true),
             CancellationToken::noop(),
         ));
         assert_eq!(indices.len(), 1, "Failed for needle: {needle}");
@@ -770,12 +692,10 @@ fn content_filter_file_exact_buffer_size() {
     content.extend(vec![b'y'; 4]); // Total = CONTENT_BUFFER_BYTES
     fs::write(dir.join("exact.bin"), &content).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:TARGET",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -790,12 +710,10 @@ fn content_filter_no_false_positive_at_eof() {
     // File ends with partial match of needle
     fs::write(dir.join("partial.txt"), b"data ends with TARG").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:TARGET",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 0); // Should not match partial
@@ -809,21 +727,17 @@ fn content_filter_repeated_pattern_in_needle() {
 
     fs::write(dir.join("repeat.txt"), b"aaaaaaBBBBBBaaaaaa").unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:aaaaaa",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
 
     let indices = guard_indices(cache.search_with_options(
         "content:BBBBBB",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -839,14 +753,12 @@ fn content_filter_all_ascii_printable() {
     let ascii: Vec<u8> = (32..127).collect();
     fs::write(dir.join("ascii.txt"), &ascii).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
 
     // Test finding a substring
     let indices = guard_indices(cache.search_with_options(
         "content:@ABCDEF",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -861,12 +773,10 @@ fn content_filter_high_bytes() {
     let content: Vec<u8> = vec![0x80, 0x90, 0xFF, b'A', b'B', 0xFE, 0xFD];
     fs::write(dir.join("high.bin"), &content).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "content:AB",
-        SearchOptions {
-            case_insensitive: false,
-        },
+        SearchOptions::default().with_case_insensitive(false),
         CancellationToken::noop(),
     ));
     assert_eq!(indices.len(), 1);
@@ -0,0 +1,139 @@
+//! Entries removed by FSEvents stay findable through `search_deleted` until
+//! they are created again.
+
+use fswalk::NodeFileType;
+use search_cache::{SearchCache, SearchError, testkit::EventScript};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+/// Remove `path` and apply the event, returning its id.
+fn remove(cache: &mut SearchCache, path: &Path) -> u64 {
+    let mut script = EventScript::after(cache);
+    script.remove(path);
+    let id = script.events()[0].id;
+    script.apply(cache).unwrap();
+    id
+}
+
+fn deleted_paths(cache: &SearchCache, query: &str) -> Vec<PathBuf> {
+    cache
+        .search_deleted(query, CancellationToken::noop())
+        .unwrap()
+        .into_iter()
+        .map(|entry| entry.path().to_path_buf())
+        .collect()
+}
+
+#[test]
+fn deleted_files_are_searchable_until_recreated() {
+    let tmp = TempDir::new("deleted_files").unwrap();
+    let root = tmp.path();
+    for name in ["report.pdf", "notes.txt", "keep.txt"] {
+        fs::write(root.join(name), b"x").unwrap();
+    }
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+
+    let report_id = remove(&mut cache, &root.join("report.pdf"));
+    remove(&mut cache, &root.join("notes.txt"));
+
+    // Most recent first, live files never show up.
+    assert_eq!(
+        deleted_paths(&cache, ""),
+        [root.join("notes.txt"), root.join("report.pdf")]
+    );
+    let hits = cache
+        .search_deleted("report", CancellationToken::noop())
+        .unwrap();
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0].path(), root.join("report.pdf"));
+    assert_eq!(hits[0].file_type, NodeFileType::File);
+    assert_eq!(hits[0].event_id, report_id);
+    assert_eq!(deleted_paths(&cache, ".txt"), [root.join("notes.txt")]);
+    assert!(deleted_paths(&cache, "keep").is_empty());
+    assert!(deleted_paths(&cache, "report notes").is_empty());
+
+    let mut script = EventScript::after(&cache);
+    script.create_file(&root.join("report.pdf"), b"back");
+    script.apply(&mut cache).unwrap();
+    assert!(deleted_paths(&cache, "report").is_empty());
+    assert_eq!(deleted_paths(&cache, ""), [root.join("notes.txt")]);
+}
+
+#[test]
+fn deleted_folder_leaves_tombstones_for_its_contents() {
+    let tmp = TempDir::new("deleted_folder").unwrap();
+    let root = tmp.path();
+    fs::create_dir_all(root.join("project/src")).unwrap();
+    fs::write(root.join("project/src/main.rs"), b"fn main() {}").unwrap();
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+
+    remove(&mut cache, &root.join("project"));
+    assert_eq!(
+        deleted_paths(&cache, ""),
+        [
+            root.join("project"),
+            root.join("project/src"),
+            root.join("project/src/main.rs"),
+        ]
+    );
+    assert_eq!(
+        deleted_paths(&cache, "src/main"),
+        [root.join("project/src/main.rs")]
+    );
+    let project = cache
+        .search_deleted("project", CancellationToken::noop())
+        .unwrap();
+    assert_eq!(project[0].file_type, NodeFileType::Dir);
+
+    // Recreating a nested file brings back its ancestors too.
+    fs::create_dir_all(root.join("project/src")).unwrap();
+    let mut script = EventScript::after(&cache);
+    script.create_file(&root.join("project/src/main.rs"), b"fn main() {}");
+    script.apply(&mut cache).unwrap();
+    assert!(cache.tombstones().is_empty());
+}
+
+#[test]
+fn tombstones_are_bounded_and_persisted() {
+    let tmp = TempDir::new("deleted_bounded").unwrap();
+    let root = tmp.path();
+    for i in 0..5 {
+        fs::write(root.join(format!("file{i}.log")), b"x").unwrap();
+    }
+    let cache_dir = TempDir::new("deleted_bounded_cache").unwrap();
+    let cache_path = cache_dir.path().join("cache.zstd");
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    cache.set_tombstone_capacity(3);
+    for i in 0..5 {
+        remove(&mut cache, &root.join(format!("file{i}.log")));
+    }
+    let expected = [
+        root.join("file4.log"),
+        root.join("file3.log"),
+        root.join("file2.log"),
+    ];
+    assert_eq!(deleted_paths(&cache, "file"), expected);
+
+    cache.flush_to_file(&cache_path).unwrap();
+    let loaded = SearchCache::try_read_persistent_cache(root, &cache_path, None, None).unwrap();
+    assert_eq!(deleted_paths(&loaded, "file"), expected);
+    assert_eq!(loaded.tombstones().capacity(), 3);
+}
+
+#[test]
+fn search_deleted_honours_cancellation() {
+    let tmp = TempDir::new("deleted_cancel").unwrap();
+    fs::write(tmp.path().join("gone.txt"), b"x").unwrap();
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    remove(&mut cache, &tmp.path().join("gone.txt"));
+    let token = CancellationToken::new(1);
+    let _ = CancellationToken::new(2);
+    assert!(matches!(
+        cache.search_deleted("gone", token),
+        Err(SearchError::Cancelled)
+    ));
+}
@@ -0,0 +1,284 @@
+//! A path reported several times, within one batch or across batches, maps
+//! to a single node however its events interleave.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{SearchCache, SearchOptions};
+use search_cancel::CancellationToken;
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+fn event(path: &Path, flag: EventFlag, id: u64) -> FsEvent {
+    FsEvent {
+        path: path.to_path_buf(),
+        flag,
+        id,
+    }
+}
+
+/// Every path below `root` on disk.
+fn disk_paths(root: &Path) -> BTreeSet<PathBuf> {
+    let mut paths = BTreeSet::new();
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(folder) = stack.pop() {
+        for entry in fs::read_dir(&folder).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                stack.push(path.clone());
+            }
+            paths.insert(path);
+        }
+    }
+    paths
+}
+
+/// Every path the cache indexes below its root.
+fn indexed_paths(cache: &SearchCache) -> BTreeSet<PathBuf> {
+    let nodes = cache.search_empty(CancellationToken::noop()).unwrap();
+    cache
+        .node_paths(&nodes)
+        .into_iter()
+        .map(Option::unwrap)
+        .filter(|path| path != cache.root())
+        .collect()
+}
+
+fn count(cache: &SearchCache, query: &str) -> usize {
+    cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .len()
+}
+
+#[test]
+fn atomic_save_keeps_one_node() {
+    let tmp = TempDir::new("atomic_save").unwrap();
+    let root = tmp.path();
+    let doc = root.join("doc.txt");
+    fs::write(&doc, b"v1").unwrap();
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+
+    // What an editor's save produces: the new content goes to a temp file
+    // renamed over the original, reported as Created+Renamed+Created.
+    let temp = root.join("doc.txt.sb-1234");
+    fs::write(&temp, b"v2").unwrap();
+    fs::rename(&temp, &doc).unwrap();
+    let id = cache.last_event_id() + 1;
+    let file = EventFlag::ItemIsFile;
+    cache
+        .handle_fs_events(vec![
+            event(&temp, EventFlag::ItemCreated | file, id),
+            event(&doc, EventFlag::ItemCreated | file, id + 1),
+            event(&temp, EventFlag::ItemRenamed | file, id + 2),
+            event(&doc, EventFlag::ItemRenamed | file, id + 3),
+            event(&doc, EventFlag::ItemCreated | file, id + 4),
+        ])
+        .unwrap();
+    cache.verify_integrity().unwrap();
+    assert_eq!(count(&cache, "doc.txt"), 1);
+    assert_eq!(indexed_paths(&cache), disk_paths(root));
+
+    // The same save again, its events spread over two batches.
+    fs::write(&temp, b"v3").unwrap();
+    fs::rename(&temp, &doc).unwrap();
+    let id = cache.last_event_id() + 1;
+    cache
+        .handle_fs_events(vec![
+            event(&doc, EventFlag::ItemCreated | file, id),
+            event(&temp, EventFlag::ItemRenamed | file, id + 1),
+        ])
+        .unwrap();
+    cache
+        .handle_fs_events(vec![
+            event(&doc, EventFlag::ItemRenamed | file, id + 2),
+            event(&doc, EventFlag::ItemCreated | file, id + 3),
+        ])
+        .unwrap();
+    cache.verify_integrity().unwrap();
+    assert_eq!(count(&cache, "doc.txt"), 1);
+    assert_eq!(indexed_paths(&cache), disk_paths(root));
+}
+
+#[test]
+fn new_folder_reported_with_its_files_keeps_one_node() {
+    let tmp = TempDir::new("folder_events").unwrap();
+    let root = tmp.path();
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    let folder = root.join("new");
+    fs::create_dir(&folder).unwrap();
+    fs::write(folder.join("a.txt"), b"a").unwrap();
+    fs::write(folder.join("b.txt"), b"b").unwrap();
+    let id = cache.last_event_id() + 1;
+    let created = EventFlag::ItemCreated;
+    cache
+        .handle_fs_events(vec![
+            event(&folder.join("a.txt"), created | EventFlag::ItemIsFile, id),
+            event(&folder, created | EventFlag::ItemIsDir, id + 1),
+            event(
+                &folder.join("b.txt"),
+                created | EventFlag::ItemIsFile,
+                id + 2,
+            ),
+            event(&folder, created | EventFlag::ItemIsDir, id + 3),
+        ])
+        .unwrap();
+    cache.verify_integrity().unwrap();
+    assert_eq!(count(&cache, "new"), 1);
+    assert_eq!(indexed_paths(&cache), disk_paths(root));
+}
+
+/// xorshift64*, enough to pick operations reproducibly.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound as u64) as usize
+    }
+
+    fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len())]
+    }
+}
+
+const FOLDERS: &[&str] = &["", "d1", "d2", "d1/d3"];
+const NAMES: &[&str] = &["a.txt", "b.txt", "c", "d1", "d2"];
+
+/// Apply a random change below `root`, returning the events FSEvents could
+/// report for it, some of them repeated.
+fn random_change(rng: &mut Rng, root: &Path, id: &mut u64) -> Vec<FsEvent> {
+    let mut events = Vec::new();
+    let mut push = |events: &mut Vec<FsEvent>, path: &Path, flag: EventFlag| {
+        *id += 1;
+        events.push(event(path, flag, *id));
+    };
+    let folder = root.join(rng.pick(FOLDERS));
+    if !folder.is_dir() {
+        // Fails when a file holds the name of one of the folders.
+        if fs::create_dir_all(&folder).is_ok() {
+            push(
+                &mut events,
+                &folder,
+                EventFlag::ItemCreated | EventFlag::ItemIsDir,
+            );
+        }
+        return events;
+    }
+    let path = folder.join(rng.pick(NAMES));
+    match rng.below(4) {
+        // Create or overwrite.
+        0 if !path.is_dir() => {
+            fs::write(&path, b"x").unwrap();
+            push(
+                &mut events,
+                &path,
+                EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            );
+        }
+        // Atomic save.
+        1 if !path.is_dir() => {
+            let temp = folder.join(".tmp-save");
+            fs::write(&temp, b"y").unwrap();
+            fs::rename(&temp, &path).unwrap();
+            push(
+                &mut events,
+                &temp,
+                EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            );
+            push(
+                &mut events,
+                &path,
+                EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            );
+            push(
+                &mut events,
+                &temp,
+                EventFlag::ItemRenamed | EventFlag::ItemIsFile,
+            );
+            push(
+                &mut events,
+                &path,
+                EventFlag::ItemRenamed | EventFlag::ItemIsFile,
+            );
+            push(
+                &mut events,
+                &path,
+                EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            );
+        }
+        // Rename to another name of the same folder.
+        2 if path.exists() => {
+            let to = folder.join(rng.pick(NAMES));
+            if to != path && !to.exists() {
+                let kind = if path.is_dir() {
+                    EventFlag::ItemIsDir
+                } else {
+                    EventFlag::ItemIsFile
+                };
+                fs::rename(&path, &to).unwrap();
+                push(&mut events, &path, EventFlag::ItemRenamed | kind);
+                push(&mut events, &to, EventFlag::ItemRenamed | kind);
+            }
+        }
+        // Remove.
+        3 if path.is_dir() => {
+            fs::remove_dir_all(&path).unwrap();
+            push(
+                &mut events,
+                &path,
+                EventFlag::ItemRemoved | EventFlag::ItemIsDir,
+            );
+        }
+        3 if path.exists() => {
+            fs::remove_file(&path).unwrap();
+            push(
+                &mut events,
+                &path,
+                EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+            );
+        }
+        _ => {}
+    }
+    if !events.is_empty() && rng.below(3) == 0 {
+        let again = rng.below(events.len());
+        let (path, flag) = (events[again].path.clone(), events[again].flag);
+        push(&mut events, &path, flag);
+    }
+    events
+}
+
+#[test]
+fn random_event_sequences_keep_the_tree_consistent() {
+    for seed in 1..=8u64 {
+        let tmp = TempDir::new("random_events").unwrap();
+        let root = tmp.path();
+        let mut cache = SearchCache::walk_fs(root.to_path_buf());
+        let mut rng = Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15));
+        let mut id = cache.last_event_id();
+        for round in 0..60 {
+            let mut batch = Vec::new();
+            for _ in 0..=rng.below(5) {
+                batch.extend(random_change(&mut rng, root, &mut id));
+            }
+            cache.handle_fs_events(batch).unwrap();
+            if let Err(e) = cache.verify_integrity() {
+                panic!("seed {seed}, round {round}: {e}");
+            }
+            assert_eq!(
+                indexed_paths(&cache),
+                disk_paths(root),
+                "seed {seed}, round {round}"
+            );
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Large bursts of FSEvents, e.g. from unpacking an archive, are applied in
+//! one pass with the metadata fetched in parallel.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{SearchCache, SearchOptions};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+use tempdir::TempDir;
+
+const DIRS: usize = 50;
+const FILES_PER_DIR: usize = 100;
+
+fn dir_path(root: &Path, dir: usize) -> PathBuf {
+    root.join(format!("dir_{dir:02}"))
+}
+
+fn file_path(root: &Path, dir: usize, file: usize) -> PathBuf {
+    dir_path(root, dir).join(format!("file_{dir:02}_{file:03}.dat"))
+}
+
+/// Create the burst tree, returning one create event per file in a scrambled
+/// order so files of different directories are interleaved.
+fn create_burst(root: &Path, first_id: u64) -> Vec<FsEvent> {
+    let mut paths = Vec::with_capacity(DIRS * FILES_PER_DIR);
+    for dir in 0..DIRS {
+        fs::create_dir_all(dir_path(root, dir)).unwrap();
+        for file in 0..FILES_PER_DIR {
+            let path = file_path(root, dir, file);
+            fs::write(&path, vec![b'x'; file + 1]).unwrap();
+            paths.push(path);
+        }
+    }
+    let len = paths.len();
+    (0..len)
+        .map(|i| (i * 7919) % len)
+        .zip(first_id..)
+        .map(|(i, id)| FsEvent {
+            path: paths[i].clone(),
+            id,
+            flag: EventFlag::ItemCreated | EventFlag::ItemIsFile,
+        })
+        .collect()
+}
+
+fn count(cache: &SearchCache, query: &str) -> usize {
+    cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .len()
+}
+
+#[test]
+fn burst_of_creates_is_fully_indexed() {
+    let tmp = TempDir::new("event_burst").unwrap();
+    let root = tmp.path();
+    // Half of the directories are indexed before the burst, the other half
+    // only show up through the events of the files they hold.
+    for dir in 0..DIRS / 2 {
+        fs::create_dir(dir_path(root, dir)).unwrap();
+    }
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    let first_id = cache.last_event_id() + 1;
+    let events = create_burst(root, first_id);
+    assert_eq!(events.len(), 5000);
+    let last_id = events.iter().map(|e| e.id).max().unwrap();
+
+    let pending = cache.prepare_fs_events(events).unwrap();
+    assert_eq!(pending.len(), 5000);
+    cache.commit_fs_events(pending.fetch());
+
+    assert_eq!(cache.last_event_id(), last_id);
+    assert_eq!(cache.get_total_files(), 1 + DIRS + DIRS * FILES_PER_DIR);
+    assert_eq!(count(&cache, "file_"), DIRS * FILES_PER_DIR);
+    assert_eq!(count(&cache, "dir_"), DIRS);
+    for dir in [0, DIRS / 2 - 1, DIRS / 2, DIRS - 1] {
+        assert_eq!(count(&cache, &format!("file_{dir:02}_")), FILES_PER_DIR);
+    }
+    // Sizes come from the parallel stat phase.
+    let nodes = cache
+        .query_files("file_07_041.dat".to_string(), CancellationToken::noop())
+        .unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].path, file_path(root, 7, 41));
+    assert_eq!(nodes[0].metadata.as_ref().unwrap().size(), 42);
+
+    // Replaying the burst replaces nodes instead of duplicating them.
+    let mut replay = create_burst(root, last_id + 1);
+    replay.truncate(500);
+    cache.handle_fs_events(replay).unwrap();
+    assert_eq!(count(&cache, "file_"), DIRS * FILES_PER_DIR);
+
+    // Deletions in a burst leave tombstones.
+    let mut removals = Vec::new();
+    for file in 0..FILES_PER_DIR {
+        let path = file_path(root, 3, file);
+        fs::remove_file(&path).unwrap();
+        removals.push(FsEvent {
+            path,
+            id: cache.last_event_id() + 1 + file as u64,
+            flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        });
+    }
+    cache.handle_fs_events(removals).unwrap();
+    assert_eq!(count(&cache, "file_03_"), 0);
+    assert_eq!(count(&cache, "file_"), (DIRS - 1) * FILES_PER_DIR);
+    assert_eq!(cache.tombstones().len(), FILES_PER_DIR);
+}
+
+#[test]
+#[ignore = "timing sensitive"]
+fn cache_answers_searches_while_metadata_is_fetched() {
+    let tmp = TempDir::new("event_burst_timing").unwrap();
+    let root = tmp.path();
+    fs::write(root.join("existing.txt"), b"x").unwrap();
+    for dir in 0..DIRS {
+        fs::create_dir(dir_path(root, dir)).unwrap();
+    }
+    let mut cache = SearchCache::walk_fs(root.to_path_buf());
+    let first_id = cache.last_event_id() + 1;
+    let events = create_burst(root, first_id);
+    let pending = cache.prepare_fs_events(events).unwrap();
+
+    // `pending` doesn't borrow the cache, so searches go on during the stat phase.
+    let started = Instant::now();
+    let (fetched, fetch_time, searches, slowest) = thread::scope(|s| {
+        let fetch = s.spawn(move || {
+            let fetched = pending.fetch();
+            (fetched, started.elapsed())
+        });
+        let mut searches = 0;
+        let mut slowest = Duration::ZERO;
+        while !fetch.is_finished() {
+            let search_started = Instant::now();
+            assert_eq!(count(&cache, "existing"), 1);
+            slowest = slowest.max(search_started.elapsed());
+            searches += 1;
+        }
+        let (fetched, fetch_time) = fetch.join().unwrap();
+        (fetched, fetch_time, searches, slowest)
+    });
+    assert!(searches > 0, "no search ran during the stat phase");
+    assert!(
+        slowest < fetch_time,
+        "a search waited for the stat phase: {slowest:?} vs {fetch_time:?}"
+    );
+
+    cache.commit_fs_events(fetched);
+    assert_eq!(count(&cache, "file_"), DIRS * FILES_PER_DIR);
+}
@@ -0,0 +1,138 @@
+//! Exported rows have to survive names that break naive CSV writers and files
+//! that vanished since they were indexed.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{ExportFormat, ExportSummary, SearchCache, SearchOptions, SlabIndex};
+use search_cancel::CancellationToken;
+use serde_json::Value;
+use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt, path::Path};
+use tempdir::TempDir;
+
+fn search(cache: &SearchCache, query: &str) -> Vec<SlabIndex> {
+    cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes
+}
+
+fn export(
+    cache: &SearchCache,
+    nodes: &[SlabIndex],
+    format: ExportFormat,
+) -> (String, ExportSummary) {
+    let mut out = Vec::new();
+    let summary = cache.export_results(nodes, format, &mut out).unwrap();
+    (String::from_utf8(out).unwrap(), summary)
+}
+
+#[test]
+fn csv_quotes_separators_quotes_and_line_breaks() {
+    let tmp = TempDir::new("export_csv").unwrap();
+    for name in ["a,b.txt", "say \"hi\".txt", "line\nbreak.txt", "plain.txt"] {
+        fs::write(tmp.path().join(name), b"12345").unwrap();
+    }
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let nodes = search(&cache, "ext:txt");
+    let (csv, summary) = export(&cache, &nodes, ExportFormat::Csv);
+
+    assert_eq!(summary.rows, 4);
+    assert!(csv.starts_with("path,name,size,modified,created,type,raw_path\r\n"));
+    assert!(csv.contains(",\"a,b.txt\",5,"));
+    assert!(csv.contains(",\"say \"\"hi\"\".txt\",5,"));
+    assert!(csv.contains(",\"line\nbreak.txt\",5,"));
+    assert!(csv.contains(",plain.txt,5,"));
+    // Line breaks inside quoted fields are plain \n, records end with \r\n.
+    assert_eq!(csv.matches("\r\n").count(), 5);
+    for record in csv.split("\r\n").skip(1).filter(|r| !r.is_empty()) {
+        assert!(record.ends_with(",file,"), "{record:?}");
+    }
+}
+
+#[test]
+fn json_lines_roundtrip() {
+    let tmp = TempDir::new("export_json").unwrap();
+    fs::create_dir(tmp.path().join("docs")).unwrap();
+    fs::write(tmp.path().join("docs/report \"final\".md"), b"# report").unwrap();
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let nodes = search(&cache, "docs OR report");
+    let (json, summary) = export(&cache, &nodes, ExportFormat::JsonLines);
+
+    assert_eq!(summary.rows, 2);
+    assert_eq!(summary.missing_metadata, 0);
+    let rows: Vec<Value> = json
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(rows.len(), 2);
+    let file = rows.iter().find(|row| row["type"] == "file").unwrap();
+    let path = tmp.path().join("docs/report \"final\".md");
+    assert_eq!(file["path"], path.to_str().unwrap());
+    assert_eq!(file["name"], "report \"final\".md");
+    assert_eq!(file["size"], 8);
+    assert!(file["modified"].as_str().unwrap().ends_with('Z'));
+    assert!(file["raw_path"].is_null());
+    let dir = rows.iter().find(|row| row["type"] == "dir").unwrap();
+    assert_eq!(dir["name"], "docs");
+}
+
+#[test]
+fn rows_without_metadata_and_removed_nodes() {
+    let tmp = TempDir::new("export_missing").unwrap();
+    fs::write(tmp.path().join("kept.log"), b"x").unwrap();
+    fs::write(tmp.path().join("gone.log"), b"x").unwrap();
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let nodes = search(&cache, "ext:log");
+    // Indexed but deleted before its metadata was ever fetched.
+    fs::remove_file(tmp.path().join("gone.log")).unwrap();
+
+    let (json, summary) = export(&cache, &nodes, ExportFormat::JsonLines);
+    assert_eq!(summary.rows, 2);
+    assert_eq!(summary.missing_metadata, 1);
+    let gone: Value = json
+        .lines()
+        .map(|line| serde_json::from_str::<Value>(line).unwrap())
+        .find(|row| row["name"] == "gone.log")
+        .unwrap();
+    assert!(gone["size"].is_null());
+    assert!(gone["modified"].is_null());
+    assert!(gone["created"].is_null());
+
+    let (csv, _) = export(&cache, &nodes, ExportFormat::Csv);
+    assert!(csv.contains(",gone.log,,,,"));
+
+    // Nodes that left the cache altogether are skipped.
+    let id = cache.last_event_id() + 1;
+    cache
+        .handle_fs_events(vec![FsEvent {
+            path: tmp.path().join("gone.log"),
+            id,
+            flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        }])
+        .unwrap();
+    let (csv, summary) = export(&cache, &nodes, ExportFormat::Csv);
+    assert_eq!((summary.rows, summary.skipped), (1, 1));
+    assert!(!csv.contains("gone.log"));
+}
+
+#[test]
+fn non_utf8_paths_carry_raw_bytes() {
+    let tmp = TempDir::new("export_non_utf8").unwrap();
+    let raw: &[u8] = b"caf\xe9.txt";
+    if fs::write(tmp.path().join(OsStr::from_bytes(raw)), b"x").is_err() {
+        // The filesystem refuses non-UTF-8 names (e.g. APFS).
+        return;
+    }
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let nodes = search(&cache, "ext:txt");
+    let (json, summary) = export(&cache, &nodes, ExportFormat::JsonLines);
+    assert_eq!(summary.lossy_paths, 1);
+    let row: Value = serde_json::from_str(json.lines().next().unwrap()).unwrap();
+    let hex = row["raw_path"].as_str().unwrap();
+    let expected = tmp.path().join(OsStr::from_bytes(raw));
+    let bytes: Vec<u8> = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect();
+    assert_eq!(Path::new(OsStr::from_bytes(&bytes)), expected);
+    assert!(row["path"].as_str().unwrap().ends_with("caf\u{fffd}.txt"));
+}
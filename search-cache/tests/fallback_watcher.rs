@@ -0,0 +1,103 @@
+//! Changes seen by the inotify fallback of `cardinal-sdk` keep the tree in
+//! step with the disk, like FSEvents' do on macOS.
+#![cfg(target_os = "linux")]
+
+use cardinal_sdk::{EventStreamBuilder, EventStreamHandle};
+use fswalk::NodeFileType;
+use search_cache::{HandleFSEError, SearchCache};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tempdir::TempDir;
+
+/// Every indexed path, with the size of the files.
+fn all_paths(cache: &SearchCache) -> Vec<(PathBuf, Option<u64>)> {
+    let nodes = cache.search_empty(CancellationToken::noop()).unwrap();
+    let mut paths: Vec<_> = cache
+        .expand_file_nodes(&nodes)
+        .into_iter()
+        .map(|node| {
+            let metadata = node.metadata.as_ref().unwrap();
+            let size = (metadata.r#type() == NodeFileType::File).then(|| metadata.size());
+            (node.path, size)
+        })
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn start_watching(root: &Path) -> EventStreamHandle {
+    let (_, handle) = EventStreamBuilder::new([root.to_str().unwrap()])
+        .latency(0.05)
+        .spawn();
+    let batch = handle
+        .recv_timeout(Duration::from_secs(5))
+        .expect("the stream starts");
+    assert!(batch.history_done(), "no history to replay");
+    handle
+}
+
+/// Apply the batches the handle delivers until it has been quiet for a while.
+fn apply_until_quiet(cache: &mut SearchCache, handle: &EventStreamHandle) -> usize {
+    let mut applied = 0;
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while let Ok(batch) = handle.recv_timeout(Duration::from_millis(500)) {
+        assert!(Instant::now() < deadline, "the stream never went quiet");
+        applied += batch.len();
+        cache.handle_fs_events(batch.events).unwrap();
+    }
+    applied
+}
+
+#[test]
+fn watched_changes_match_a_fresh_walk() {
+    let tmp = TempDir::new("fallback_watcher").unwrap();
+    let root = tmp.path().canonicalize().unwrap();
+    fs::create_dir_all(root.join("docs/old")).unwrap();
+    fs::write(root.join("docs/readme.md"), b"readme").unwrap();
+    fs::write(root.join("docs/old/draft.txt"), b"draft").unwrap();
+    fs::write(root.join("notes.txt"), b"notes").unwrap();
+    fs::write(root.join("gone.txt"), b"gone").unwrap();
+    let mut cache = SearchCache::walk_fs(root.clone());
+    let handle = start_watching(&root);
+
+    fs::write(root.join("created.txt"), b"created").unwrap();
+    fs::write(root.join("notes.txt"), b"notes, longer now").unwrap();
+    fs::remove_file(root.join("gone.txt")).unwrap();
+    fs::rename(root.join("docs/readme.md"), root.join("README.md")).unwrap();
+    // A folder moved in the tree, one created with content, one removed.
+    fs::rename(root.join("docs/old"), root.join("archive")).unwrap();
+    fs::create_dir_all(root.join("new/nested")).unwrap();
+    fs::write(root.join("new/nested/deep.txt"), b"deep").unwrap();
+    fs::write(root.join("archive/later.txt"), b"later").unwrap();
+    fs::remove_dir_all(root.join("docs")).unwrap();
+
+    assert!(apply_until_quiet(&mut cache, &handle) > 0);
+    assert_eq!(all_paths(&cache), all_paths(&SearchCache::walk_fs(root)));
+}
+
+#[test]
+fn removing_the_root_asks_for_a_rescan() {
+    let tmp = TempDir::new("fallback_watcher_root").unwrap();
+    let root = tmp.path().canonicalize().unwrap().join("root");
+    fs::create_dir(&root).unwrap();
+    fs::write(root.join("file.txt"), b"file").unwrap();
+    let mut cache = SearchCache::walk_fs(root.clone());
+    let handle = start_watching(&root);
+
+    fs::remove_dir_all(&root).unwrap();
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let rescan = loop {
+        assert!(Instant::now() < deadline, "the root change wasn't reported");
+        let Ok(batch) = handle.recv_timeout(Duration::from_millis(200)) else {
+            continue;
+        };
+        if let Err(error) = cache.handle_fs_events(batch.events) {
+            break error;
+        }
+    };
+    assert!(matches!(rescan, HandleFSEError::Rescan));
+}
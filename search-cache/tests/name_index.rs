@@ -0,0 +1,108 @@
+//! Many nodes share a name; searches fan out from the name to every node
+//! bearing it, so the name index has to follow every change of the tree.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{SearchCache, SearchOptions};
+use search_cancel::CancellationToken;
+use std::{collections::BTreeSet, fs, path::PathBuf};
+use tempdir::TempDir;
+
+const FOLDERS: usize = 1_000;
+
+fn found(cache: &SearchCache, query: &str) -> BTreeSet<PathBuf> {
+    let nodes = cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    cache
+        .node_paths(&nodes)
+        .into_iter()
+        .map(Option::unwrap)
+        .collect()
+}
+
+fn events(cache: &mut SearchCache, paths: &[PathBuf], flag: EventFlag) -> Vec<FsEvent> {
+    let id = cache.last_event_id();
+    paths
+        .iter()
+        .zip(1..)
+        .map(|(path, offset)| FsEvent {
+            path: path.clone(),
+            flag,
+            id: id + offset,
+        })
+        .collect()
+}
+
+#[test]
+fn shared_names_follow_removals_rescans_and_compactions() {
+    let tmp = TempDir::new("shared_names").unwrap();
+    let files: Vec<PathBuf> = (0..FOLDERS)
+        .map(|i| {
+            let folder = tmp.path().join(format!("pkg{i:04}"));
+            fs::create_dir(&folder).unwrap();
+            let file = folder.join("index.js");
+            fs::write(&file, b"module.exports = {}").unwrap();
+            file
+        })
+        .collect();
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    cache.set_invariant_checks(true);
+    cache.verify_name_index().unwrap();
+    assert_eq!(found(&cache, "index.js").len(), FOLDERS);
+
+    // Remove every other one, reported in a few batches.
+    let (removed, survivors): (Vec<_>, Vec<_>) = files
+        .iter()
+        .cloned()
+        .enumerate()
+        .partition(|(i, _)| i % 2 == 0);
+    let removed: Vec<PathBuf> = removed.into_iter().map(|(_, path)| path).collect();
+    let survivors: BTreeSet<PathBuf> = survivors.into_iter().map(|(_, path)| path).collect();
+    for chunk in removed.chunks(128) {
+        for path in chunk {
+            fs::remove_file(path).unwrap();
+        }
+        let batch = events(
+            &mut cache,
+            chunk,
+            EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        );
+        cache.handle_fs_events(batch).unwrap();
+    }
+    assert_eq!(found(&cache, "index.js"), survivors);
+
+    // Modified files are rescanned, their nodes replaced by new ones.
+    let modified: Vec<PathBuf> = survivors.iter().step_by(3).cloned().collect();
+    for path in &modified {
+        fs::write(path, b"module.exports = { changed: true }").unwrap();
+    }
+    let batch = events(
+        &mut cache,
+        &modified,
+        EventFlag::ItemModified | EventFlag::ItemIsFile,
+    );
+    cache.handle_fs_events(batch).unwrap();
+    assert_eq!(found(&cache, "index.js"), survivors);
+
+    // So are folders, with the index.js inside them.
+    let folders: Vec<PathBuf> = survivors
+        .iter()
+        .skip(1)
+        .step_by(5)
+        .map(|path| path.parent().unwrap().to_path_buf())
+        .collect();
+    let batch = events(
+        &mut cache,
+        &folders,
+        EventFlag::ItemModified | EventFlag::ItemIsDir,
+    );
+    cache.handle_fs_events(batch).unwrap();
+    assert_eq!(found(&cache, "index.js"), survivors);
+
+    cache.compact();
+    assert_eq!(found(&cache, "index.js"), survivors);
+    cache.rescan();
+    assert_eq!(found(&cache, "index.js"), survivors);
+    cache.verify_name_index().unwrap();
+}
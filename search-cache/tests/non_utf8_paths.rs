@@ -0,0 +1,108 @@
+//! Result paths must keep non-UTF-8 file names byte for byte, otherwise actions
+//! on them (reveal, open, ...) point at paths that don't exist.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{PathDisplay, SearchCache, SearchOptions, SearchResultNode};
+use search_cancel::CancellationToken;
+use std::{ffi::OsStr, fs, os::unix::ffi::OsStrExt, path::PathBuf};
+use tempdir::TempDir;
+
+const RAW_NAME: &[u8] = b"caf\xe9 menu.txt";
+
+/// Returns None when the filesystem refuses non-UTF-8 names (e.g. APFS).
+fn create_non_utf8_file(root: &std::path::Path) -> Option<PathBuf> {
+    let path = root.join(OsStr::from_bytes(RAW_NAME));
+    fs::write(&path, b"x").ok()?;
+    Some(path)
+}
+
+fn query_single(cache: &SearchCache, query: &str) -> SearchResultNode {
+    let mut nodes = cache
+        .query_files(query.to_string(), CancellationToken::noop())
+        .expect("query should succeed");
+    assert_eq!(nodes.len(), 1, "expected exactly one hit for {query:?}");
+    nodes.pop().unwrap()
+}
+
+#[test]
+fn non_utf8_name_roundtrips_through_result_path() {
+    let tmp = TempDir::new("non_utf8_roundtrip").unwrap();
+    let Some(file) = create_non_utf8_file(tmp.path()) else {
+        return;
+    };
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+
+    // Matching still works on the lossy name.
+    let node = query_single(&cache, "menu");
+    assert_eq!(node.path, file);
+    assert!(node.path_bytes().ends_with(RAW_NAME));
+    assert!(node.path.exists());
+
+    let index = cache
+        .node_index_for_path_bytes(node.path_bytes())
+        .expect("raw path should resolve back to the node");
+    assert_eq!(cache.node_path(index), Some(file.clone()));
+    assert_eq!(cache.node_index_for_raw_path(&file), Some(index));
+}
+
+#[test]
+fn non_utf8_name_survives_events_and_persistence() {
+    let tmp = TempDir::new("non_utf8_events").unwrap();
+    let Some(file) = create_non_utf8_file(tmp.path()) else {
+        return;
+    };
+    let cache_dir = TempDir::new("non_utf8_cache").unwrap();
+    let cache_path = cache_dir.path().join("cache.zstd");
+    let mut cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+
+    // Rescanning the node must replace it, not add a lossy twin.
+    let id = cache.last_event_id() + 1;
+    cache
+        .handle_fs_events(vec![FsEvent {
+            path: file.clone(),
+            id,
+            flag: EventFlag::ItemModified | EventFlag::ItemIsFile,
+        }])
+        .unwrap();
+    assert_eq!(query_single(&cache, "menu").path, file);
+
+    cache.flush_to_file(&cache_path).unwrap();
+    let mut loaded =
+        SearchCache::try_read_persistent_cache(tmp.path(), &cache_path, None, None).unwrap();
+    let node = query_single(&loaded, "menu");
+    assert_eq!(node.path, file);
+    assert!(node.path_bytes().ends_with(RAW_NAME));
+
+    fs::remove_file(&file).unwrap();
+    let id = loaded.last_event_id() + 1;
+    loaded
+        .handle_fs_events(vec![FsEvent {
+            path: file,
+            id,
+            flag: EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        }])
+        .unwrap();
+    assert!(
+        loaded
+            .query_files("menu".to_string(), CancellationToken::noop())
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn non_utf8_name_displays_lossily_but_keeps_exact_path() {
+    let tmp = TempDir::new("non_utf8_display").unwrap();
+    let Some(file) = create_non_utf8_file(tmp.path()) else {
+        return;
+    };
+    let cache = SearchCache::walk_fs(tmp.path().to_path_buf());
+    let options = SearchOptions::default().with_path_display(PathDisplay::RootRelative);
+    let nodes = cache
+        .query_files_with_options("menu".to_string(), options, CancellationToken::noop())
+        .unwrap();
+    assert_eq!(nodes.len(), 1);
+    assert_eq!(nodes[0].display_path, "caf\u{fffd} menu.txt");
+    assert_eq!(nodes[0].path, file);
+    assert!(nodes[0].path_bytes().ends_with(RAW_NAME));
+}
@@ -2,11 +2,24 @@
 //! Verifies the optimized implementation that directly accesses child nodes
 //! instead of scanning the entire file tree.
 
-use search_cache::SearchCache;
+use search_cache::{QueryNote, SearchCache, SearchOptions};
 use search_cancel::CancellationToken;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tempdir::TempDir;
 
+/// Run `query` and check that it matches nothing because `missing` isn't indexed.
+fn assert_not_indexed(cache: &SearchCache, query: &str, missing: &Path) {
+    let outcome = cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap();
+    assert!(outcome.nodes.is_empty(), "{query}");
+    assert_eq!(
+        outcome.notes,
+        [QueryNote::ParentNotIndexed(missing.to_path_buf())],
+        "{query}"
+    );
+}
+
 /// Build a test cache with nested directory structure.
 fn build_nested_cache() -> (SearchCache, PathBuf) {
     let temp_dir = TempDir::new("parent_infolder_test").unwrap();
@@ -51,18 +64,16 @@ fn build_nested_cache() -> (SearchCache, PathBuf) {
 
 #[test]
 fn test_parent_filter_direct_children() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test parent:src - should return only direct children of src
     let src_path = root.join("src");
     let query = format!("parent:{}", src_path.display());
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should only find main.rs, lib.rs, and utils directory (direct children)
     assert_eq!(nodes.len(), 3, "parent:src should return 3 direct children");
 
@@ -80,18 +91,16 @@ fn test_parent_filter_direct_children() {
 
 #[test]
 fn test_infolder_filter_recursive() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test infolder:src - should return all descendants recursively
     let src_path = root.join("src");
     let query = format!("infolder:{}", src_path.display());
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should find: main.rs, lib.rs, utils/, helper.rs, config.rs (5 items)
     assert_eq!(
         nodes.len(),
@@ -112,18 +121,16 @@ fn test_infolder_filter_recursive() {
 
 #[test]
 fn test_parent_filter_with_pattern() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test parent:src *.rs - should find only .rs files directly under src
     let src_path = root.join("src");
     let query = format!("parent:{} *.rs", src_path.display());
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should only find main.rs and lib.rs (not helper.rs or config.rs which are in utils)
     assert_eq!(
         nodes.len(),
@@ -141,18 +148,16 @@ fn test_parent_filter_with_pattern() {
 
 #[test]
 fn test_infolder_filter_with_pattern() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test infolder:src *.rs - should find all .rs files recursively under src
     let src_path = root.join("src");
     let query = format!("infolder:{} *.rs", src_path.display());
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should find: main.rs, lib.rs, helper.rs, config.rs (4 files)
     assert_eq!(
         nodes.len(),
@@ -170,48 +175,39 @@ fn test_infolder_filter_with_pattern() {
 
 #[test]
 fn test_parent_filter_nonexistent_path() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test parent with non-existent directory
     let nonexistent = root.join("nonexistent");
     let query = format!("parent:{}", nonexistent.display());
 
-    let result = cache.query_files(query, CancellationToken::noop());
-
-    // Should return error for non-existent path
-    assert!(result.is_err(), "Should error for non-existent parent path");
+    // Matches nothing and says why
+    assert_not_indexed(&cache, &query, &nonexistent);
 }
 
 #[test]
 fn test_infolder_filter_nonexistent_path() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test infolder with non-existent directory
     let nonexistent = root.join("nonexistent");
     let query = format!("infolder:{}", nonexistent.display());
 
-    let result = cache.query_files(query, CancellationToken::noop());
-
-    // Should return error for non-existent path
-    assert!(
-        result.is_err(),
-        "Should error for non-existent infolder path"
-    );
+    // Matches nothing and says why
+    assert_not_indexed(&cache, &query, &nonexistent);
 }
 
 #[test]
 fn test_parent_filter_root() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     // Test parent at root level
     let query = format!("parent:{}", root.display());
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should find: file1.txt, src/, tests/, docs/ (4 direct children)
     assert_eq!(nodes.len(), 4, "parent at root should return 4 items");
 }
@@ -226,7 +222,7 @@ fn test_infolder_filter_empty_directory() {
     let empty_dir = root_path.join("empty");
     std::fs::create_dir_all(&empty_dir).unwrap();
 
-    let mut cache = SearchCache::walk_fs(root_path);
+    let cache = SearchCache::walk_fs(root_path);
 
     // Test infolder on empty directory
     let query = format!("infolder:{}", empty_dir.display());
@@ -235,16 +231,12 @@ fn test_infolder_filter_empty_directory() {
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    // Should return empty or None for directory with no children
-    match result {
-        None => {} // Acceptable: no results
-        Some(nodes) => assert_eq!(nodes.len(), 0, "Empty directory should have no subnodes"),
-    }
+    assert_eq!(result.len(), 0, "Empty directory should have no subnodes");
 }
 
 #[test]
 fn test_parent_infolder_difference() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     let src_path = root.join("src");
 
@@ -252,15 +244,13 @@ fn test_parent_infolder_difference() {
     let parent_query = format!("parent:{}", src_path.display());
     let parent_result = cache
         .query_files(parent_query, CancellationToken::noop())
-        .expect("Query should succeed")
-        .expect("Should return results");
+        .expect("Query should succeed");
 
     // Get infolder results
     let infolder_query = format!("infolder:{}", src_path.display());
     let infolder_result = cache
         .query_files(infolder_query, CancellationToken::noop())
-        .expect("Query should succeed")
-        .expect("Should return results");
+        .expect("Query should succeed");
 
     // infolder should return more items than parent (includes nested files)
     assert!(
@@ -286,39 +276,29 @@ fn test_parent_infolder_difference() {
 
 #[test]
 fn test_parent_filter_path_validation() {
-    let (mut cache, _root) = build_nested_cache();
+    let (cache, _root) = build_nested_cache();
 
-    // Test with path outside the indexed root (should error)
+    // Test with path outside the indexed root (matches nothing)
     let outside_path = PathBuf::from("/some/random/path");
     let query = format!("parent:{}", outside_path.display());
 
-    let result = cache.query_files(query, CancellationToken::noop());
-
-    assert!(
-        result.is_err(),
-        "Should error for path outside indexed root"
-    );
+    assert_not_indexed(&cache, &query, &outside_path);
 }
 
 #[test]
 fn test_infolder_filter_path_validation() {
-    let (mut cache, _root) = build_nested_cache();
+    let (cache, _root) = build_nested_cache();
 
-    // Test with path outside the indexed root (should error)
+    // Test with path outside the indexed root (matches nothing)
     let outside_path = PathBuf::from("/some/random/path");
     let query = format!("infolder:{}", outside_path.display());
 
-    let result = cache.query_files(query, CancellationToken::noop());
-
-    assert!(
-        result.is_err(),
-        "Should error for path outside indexed root"
-    );
+    assert_not_indexed(&cache, &query, &outside_path);
 }
 
 #[test]
 fn test_parent_with_boolean_operators() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     let src_path = root.join("src");
     let tests_path = root.join("tests");
@@ -330,12 +310,10 @@ fn test_parent_with_boolean_operators() {
         tests_path.display()
     );
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should find children of both src and tests
     // src has 3 children (main.rs, lib.rs, utils), tests has 1 (test1.rs)
     assert_eq!(
@@ -347,19 +325,17 @@ fn test_parent_with_boolean_operators() {
 
 #[test]
 fn test_infolder_with_negation() {
-    let (mut cache, root) = build_nested_cache();
+    let (cache, root) = build_nested_cache();
 
     let src_path = root.join("src");
 
     // Test infolder:src ! ext:rs (all items in src except .rs files)
     let query = format!("infolder:{} ! ext:rs", src_path.display());
 
-    let result = cache
+    let nodes = cache
         .query_files(query, CancellationToken::noop())
         .expect("Query should succeed");
 
-    let nodes = result.expect("Should return results");
-
     // Should only find utils/ directory (not the .rs files)
     assert_eq!(nodes.len(), 1, "Should exclude all .rs files");
 
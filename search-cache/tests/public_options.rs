@@ -0,0 +1,93 @@
+//! `SearchOptions` built the way consumers outside the crate have to, with
+//! its `with_*` methods, reaching the search through the public API only.
+
+use search_cache::{SearchCache, SearchOptions, SizeBuckets, testkit::TreeSpec};
+use search_cancel::CancellationToken;
+use tempdir::TempDir;
+
+fn build_tree() -> (TempDir, SearchCache) {
+    TreeSpec::new()
+        .file("Report.PDF")
+        .file("report.txt")
+        .sized_file("big.bin", 50_000)
+        .files(["docs/report.md", "docs/notes.txt"])
+        .index("public_options")
+}
+
+fn names(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<String> {
+    let outcome = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap();
+    let mut names: Vec<String> = cache
+        .node_paths(&outcome.nodes)
+        .into_iter()
+        .map(|path| {
+            let path = path.unwrap();
+            path.file_name().unwrap().to_string_lossy().into_owned()
+        })
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+fn case_and_scope() {
+    let (tmp, cache) = build_tree();
+    assert_eq!(
+        names(&cache, "report", SearchOptions::default()),
+        ["report.md", "report.txt"]
+    );
+    let insensitive = SearchOptions::default().with_case_insensitive(true);
+    assert_eq!(
+        names(&cache, "report", insensitive),
+        ["Report.PDF", "report.md", "report.txt"]
+    );
+
+    let docs = cache
+        .node_index_for_raw_path(&tmp.path().join("docs"))
+        .unwrap();
+    assert_eq!(
+        names(&cache, "report", insensitive.with_scope_to(docs)),
+        ["report.md"]
+    );
+}
+
+#[test]
+fn history_and_size_buckets() {
+    let (_tmp, cache) = build_tree();
+    let quiet = SearchOptions::default().with_skip_history(true);
+    names(&cache, "notes", quiet);
+    assert!(cache.query_history().is_empty());
+    names(&cache, "notes", SearchOptions::default());
+    assert_eq!(cache.query_history().len(), 1);
+
+    assert_eq!(names(&cache, "size:small", quiet), ["big.bin"]);
+    let buckets = SizeBuckets::new([100_000, 200_000, 300_000, 400_000, 500_000]).unwrap();
+    let options = quiet.with_size_buckets(buckets);
+    assert_eq!(names(&cache, "size:small", options), Vec::<String>::new());
+    assert!(names(&cache, "size:tiny", options).contains(&"big.bin".to_string()));
+}
+
+#[test]
+fn query_files_matches_search_with_options() {
+    let (tmp, cache) = build_tree();
+    let options = SearchOptions::default()
+        .with_case_insensitive(true)
+        .with_skip_history(true);
+    let outcome = cache
+        .search_with_options("ext:txt", options, CancellationToken::noop())
+        .unwrap();
+    assert!(!outcome.partial);
+    let files = cache
+        .query_files_with_options("ext:txt".to_string(), options, CancellationToken::noop())
+        .unwrap();
+    let expected: Vec<_> = cache
+        .node_paths(&outcome.nodes)
+        .into_iter()
+        .flatten()
+        .collect();
+    let paths: Vec<_> = files.into_iter().map(|file| file.path).collect();
+    assert_eq!(paths, expected);
+    assert!(paths.iter().all(|path| path.starts_with(tmp.path())));
+    assert!(paths.contains(&tmp.path().join("docs/notes.txt")));
+}
@@ -2,7 +2,7 @@
 //! Builds a small virtual filesystem and runs many search permutations.
 //! Intentionally verbose for line-count; focuses on correctness + non-panicking behavior.
 
-use search_cache::{SearchCache, SearchOptions};
+use search_cache::{SearchCache, SearchError, SearchOptions};
 use search_cancel::CancellationToken;
 use tempdir::TempDir;
 
@@ -50,8 +50,7 @@ fn build_cache() -> SearchCache {
 fn run(cache: &mut SearchCache, q: &str) -> usize {
     // Use public query_files API (search() helper is unit-test only).
     match cache.query_files(q.to_string(), CancellationToken::noop()) {
-        Ok(Some(nodes)) => nodes.len(),
-        Ok(None) => 0,
+        Ok(nodes) => nodes.len(),
         Err(_) => 0,
     }
 }
@@ -160,38 +159,30 @@ fn wildcard_vs_phrase_behavior_matrix() {
 
 #[test]
 fn case_insensitive_option_matrix() {
-    let mut cache = build_cache();
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let cache = build_cache();
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let insensitive = cache
         .search_with_options("readme.md", opts, CancellationToken::noop())
         .unwrap()
         .nodes
-        .unwrap()
         .len();
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let sensitive = cache
         .search_with_options("readme.md", opts, CancellationToken::noop())
         .unwrap()
         .nodes
-        .unwrap()
         .len();
     assert!(insensitive >= sensitive);
 }
 
 #[test]
 fn cancellation_large_iteration() {
-    let mut cache = build_cache();
+    let cache = build_cache();
     let token = CancellationToken::new(9999);
     let _later = CancellationToken::new(10000); // cancel token
-    let result = cache
-        .search_with_options("src lib tests", SearchOptions::default(), token)
-        .unwrap();
+    let result = cache.search_with_options("src lib tests", SearchOptions::default(), token);
     assert!(
-        result.nodes.is_none(),
-        "Cancellation should propagate and yield None"
+        matches!(result, Err(SearchError::Cancelled)),
+        "Cancellation should propagate"
     );
 }
@@ -0,0 +1,143 @@
+//! `rescan_async` walks on a background thread while the old tree keeps
+//! answering searches, then replays the events that arrived meanwhile.
+
+use cardinal_sdk::{EventFlag, FsEvent};
+use search_cache::{SearchCache, SearchOptions, testkit::TreeSpec};
+use search_cancel::CancellationToken;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread::sleep,
+    time::{Duration, Instant},
+};
+use tempdir::TempDir;
+
+/// root/dir{0..dirs}/file{0..files}.txt
+fn build_tree(dirs: usize, files: usize) -> (TempDir, PathBuf, SearchCache) {
+    let (tmp, cache) = TreeSpec::new()
+        .files(
+            (0..dirs)
+                .flat_map(|dir| (0..files).map(move |file| format!("dir{dir}/file{file}.txt"))),
+        )
+        .index("rescan_async");
+    let root = tmp.path().to_path_buf();
+    (tmp, root, cache)
+}
+
+fn paths(cache: &SearchCache, query: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = cache
+        .search_with_options(query, SearchOptions::default(), CancellationToken::noop())
+        .unwrap()
+        .nodes
+        .iter()
+        .filter_map(|&index| cache.node_path(index))
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn event(cache: &mut SearchCache, path: &Path, flag: EventFlag) -> FsEvent {
+    FsEvent {
+        path: path.to_path_buf(),
+        id: cache.last_event_id() + 1,
+        flag,
+    }
+}
+
+#[test]
+fn searches_are_answered_from_the_old_tree_during_a_rescan() {
+    let (_tmp, root, mut cache) = build_tree(100, 200);
+    let removed = root.join("dir0/file0.txt");
+    let added = root.join("dir0/fresh.txt");
+    fs::remove_file(&removed).unwrap();
+    fs::write(&added, b"x").unwrap();
+
+    let rescan = cache.rescan_async();
+    loop {
+        let search_time = Instant::now();
+        assert!(paths(&cache, "dir0/file0.txt").contains(&removed));
+        assert!(paths(&cache, "fresh.txt").is_empty());
+        assert!(search_time.elapsed() < Duration::from_secs(1));
+        if !rescan.walked().is_empty() {
+            break;
+        }
+    }
+
+    let walked = rescan.walked().recv().unwrap();
+    assert!(walked.is_some());
+    cache.finish_rescan(rescan, walked).unwrap();
+    assert!(!paths(&cache, "dir0/file0.txt").contains(&removed));
+    assert_eq!(paths(&cache, "fresh.txt"), [added]);
+}
+
+#[test]
+fn events_buffered_during_the_rescan_are_replayed() {
+    let (_tmp, root, mut cache) = build_tree(20, 50);
+    paths(&cache, "file1");
+
+    let mut rescan = cache.rescan_async();
+    while rescan.walked().is_empty() {
+        sleep(Duration::from_millis(10));
+    }
+    // The walk is over, only the events can tell the new tree about these.
+    let late = root.join("dir1/late.txt");
+    let removed = root.join("dir1/file1.txt");
+    fs::write(&late, b"x").unwrap();
+    fs::remove_file(&removed).unwrap();
+    let events = vec![
+        event(
+            &mut cache,
+            &late,
+            EventFlag::ItemCreated | EventFlag::ItemIsFile,
+        ),
+        event(
+            &mut cache,
+            &removed,
+            EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        ),
+    ];
+    rescan.buffer_events(events);
+    assert_eq!(rescan.buffered_events(), 2);
+    // Nothing reaches the old tree before the swap.
+    assert!(paths(&cache, "late.txt").is_empty());
+
+    let walked = rescan.walked().recv().unwrap();
+    cache.finish_rescan(rescan, walked).unwrap();
+    assert_eq!(paths(&cache, "late.txt"), [late.clone()]);
+    assert!(!paths(&cache, "dir1/file1.txt").contains(&removed));
+    let index = cache.node_index_for_raw_path(&late).unwrap();
+    assert!(cache.first_seen(index).is_some());
+    // State that isn't derived from the tree survives the swap.
+    assert!(
+        cache
+            .suggest_queries("file", 10)
+            .contains(&"file1".to_string())
+    );
+}
+
+#[test]
+fn cancelled_rescan_keeps_the_old_tree_and_applies_the_events() {
+    let (_tmp, root, mut cache) = build_tree(2, 2);
+    let mut rescan = cache.rescan_async();
+    let late = root.join("dir0/late.txt");
+    fs::write(&late, b"x").unwrap();
+    let created = event(
+        &mut cache,
+        &late,
+        EventFlag::ItemCreated | EventFlag::ItemIsFile,
+    );
+    rescan.buffer_events(vec![created]);
+    let _ = rescan.walked().recv().unwrap();
+
+    cache.finish_rescan(rescan, None).unwrap();
+    assert_eq!(paths(&cache, "late.txt"), [late]);
+    assert_eq!(paths(&cache, ".txt").len(), 5);
+}
+
+#[test]
+fn cancelling_a_pending_rescan_stops_its_walk() {
+    let (_tmp, _root, cache) = build_tree(100, 200);
+    let rescan = cache.rescan_async();
+    rescan.cancel();
+    assert!(rescan.walked().recv().unwrap().is_none());
+}
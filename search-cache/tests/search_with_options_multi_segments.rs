@@ -1,13 +1,10 @@
-use search_cache::{SearchCache, SearchOptions, SlabIndex};
+use search_cache::{SearchCache, SearchOptions, SearchResult, SlabIndex};
 use search_cancel::CancellationToken;
 use std::fs;
 use tempdir::TempDir;
 
-fn guard_indices(result: Result<search_cache::SearchOutcome, anyhow::Error>) -> Vec<SlabIndex> {
-    result
-        .expect("search should succeed")
-        .nodes
-        .expect("noop cancellation token should not cancel")
+fn guard_indices(result: SearchResult<search_cache::SearchOutcome>) -> Vec<SlabIndex> {
+    result.expect("search should succeed").nodes
 }
 
 #[test]
@@ -20,10 +17,8 @@ fn and_space_multi_segments_basic() {
     fs::File::create(dir.join("alpha.txt")).unwrap();
     fs::File::create(dir.join("beta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // Space acts as AND; require both alpha and beta.
     let indices =
         guard_indices(cache.search_with_options("alpha beta", opts, CancellationToken::noop()));
@@ -45,10 +40,8 @@ fn or_operator_multi_segments() {
     fs::File::create(dir.join("gamma_delta.txt")).unwrap();
     fs::File::create(dir.join("epsilon.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("alpha | gamma", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -65,10 +58,8 @@ fn not_operator_excludes_segment() {
     fs::File::create(dir.join("alpha_gamma.txt")).unwrap();
     fs::File::create(dir.join("alpha_delta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("alpha !beta", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -86,10 +77,8 @@ fn mixed_and_or_precedence() {
     fs::File::create(dir.join("beta_gamma.txt")).unwrap();
     fs::File::create(dir.join("gamma_delta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // Current precedence groups left-to-right; validate minimal presence of alpha_beta and any gamma-containing.
     let indices = guard_indices(cache.search_with_options(
         "alpha beta | gamma",
@@ -117,10 +106,8 @@ fn multi_segments_with_wildcards() {
     fs::File::create(dir.join("alphaZ_beta.txt")).unwrap();
     fs::File::create(dir.join("gamma_beta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // Use space-AND with a trailing wildcard on second term to reflect implementation behavior observed.
     let indices =
         guard_indices(cache.search_with_options("alpha* beta*", opts, CancellationToken::noop()));
@@ -142,10 +129,8 @@ fn multi_segments_case_insensitive() {
     fs::File::create(dir.join("ALPHA_beta.md")).unwrap();
     fs::File::create(dir.join("gamma_alpha.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("alpha beta", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -170,10 +155,8 @@ fn regex_plus_plain_segment() {
     fs::File::create(dir.join("alphaXYZ_beta.txt")).unwrap();
     fs::File::create(dir.join("alpha999_gamma.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // regex selects numeric alpha, then AND beta plain segment
     let indices = guard_indices(cache.search_with_options(
         "regex:alpha\\d+ beta",
@@ -193,10 +176,8 @@ fn filter_and_terms_multi_segments() {
     fs::File::create(dir.join("alpha_beta.md")).unwrap();
     fs::File::create(dir.join("alpha_gamma.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // ext:txt intersects with alpha and beta
     let indices = guard_indices(cache.search_with_options(
         "alpha beta ext:txt",
@@ -217,10 +198,8 @@ fn not_with_filter_multi_segments() {
     fs::File::create(dir.join("alpha_beta.rs")).unwrap();
     fs::File::create(dir.join("alpha_gamma.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // alpha AND beta AND NOT (ext:md) => .txt + .rs
     let indices = guard_indices(cache.search_with_options(
         "alpha beta !ext:md",
@@ -242,10 +221,8 @@ fn chained_not_and_or_segments() {
     fs::File::create(dir.join("delta_gamma.txt")).unwrap();
     fs::File::create(dir.join("alpha_delta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // (alpha AND gamma) OR (delta AND NOT beta)
     let indices = guard_indices(cache.search_with_options(
         "alpha gamma | delta !beta",
@@ -272,7 +249,7 @@ fn wildcard_suffix_segment_matches_ending() {
     fs::create_dir_all(dir.join("boo/bar")).unwrap();
     fs::create_dir_all(dir.join("foz/bar")).unwrap(); // should not match *oo
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     // Pattern *oo/bar => first segment ends with 'oo'
     let indices = guard_indices(cache.search_with_options(
         "*oo/bar",
@@ -299,7 +276,7 @@ fn wildcard_prefix_segment_does_not_match_non_prefix() {
     fs::create_dir_all(dir.join("oofoo/bar")).unwrap();
     fs::create_dir_all(dir.join("oof/bar")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     // Pattern oo*/bar => segment starts with 'oo'
     let indices = guard_indices(cache.search_with_options(
         "oo*/bar",
@@ -330,7 +307,7 @@ fn double_sided_wildcard_segment_matches_internal() {
     fs::create_dir_all(dir.join("fo/bar")).unwrap(); // star can be empty
     fs::create_dir_all(dir.join("f/bar")).unwrap(); // missing trailing o
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     let indices = guard_indices(cache.search_with_options(
         "f*o/bar",
         SearchOptions::default(),
@@ -360,7 +337,7 @@ fn single_char_wildcard_prefix_segment() {
     fs::create_dir_all(dir.join("boo/bar")).unwrap();
     fs::create_dir_all(dir.join("bboo/bar")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     // ?oo/bar => exactly one leading char plus 'oo'
     let indices = guard_indices(cache.search_with_options(
         "?oo/bar",
@@ -387,7 +364,7 @@ fn single_char_wildcard_suffix_segment() {
     fs::create_dir_all(dir.join("oo/bar")).unwrap();
     fs::create_dir_all(dir.join("ooba/bar")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     // oo?/bar => prefix 'oo' plus exactly one extra char
     let indices = guard_indices(cache.search_with_options(
         "oo?/bar",
@@ -416,7 +393,7 @@ fn star_does_not_cross_directory_boundary() {
     fs::create_dir_all(dir.join("foobaz/bar")).unwrap(); // single segment variant
     fs::create_dir_all(dir.join("foo/baz_extra/bar")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     // Pattern foo/baz*/bar should only expand within same segment "baz" variations, not merge segments
     let indices = guard_indices(cache.search_with_options(
         "foo/baz*/bar",
@@ -448,10 +425,8 @@ fn partial_file_name_wildcard_extensions() {
     fs::File::create(dir.join("readme1.md")).unwrap();
     fs::File::create(dir.join("xreadme.md")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("readme*.md", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -473,10 +448,8 @@ fn partial_file_name_leading_wildcard() {
     fs::File::create(dir.join("xreadme.md")).unwrap();
     fs::File::create(dir.join("pre_readme.md")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("*readme.md", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -494,7 +467,7 @@ fn partial_segment_hyphen_boundary_variants() {
     fs::create_dir_all(dir.join("src/libcore/mod")).unwrap();
     fs::create_dir_all(dir.join("src/libXcore/mod")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
     // Pattern lib*core should match lib-core and libcore but not libXcore (if * doesn't skip internal capital boundary) — assume inclusive of all.
     let indices = guard_indices(cache.search_with_options(
         "src/lib*core/mod",
@@ -520,10 +493,8 @@ fn case_insensitive_partial_segment_variants() {
     fs::create_dir_all(dir.join("foobar/baz")).unwrap();
     fs::create_dir_all(dir.join("FOOBAR/baz")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("foo*bar/baz", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -542,10 +513,8 @@ fn partial_unicode_segment_wildcard() {
     fs::create_dir_all(dir.join("Cafe/docs")).unwrap();
     fs::create_dir_all(dir.join("caféteria/docs")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("café*/docs", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
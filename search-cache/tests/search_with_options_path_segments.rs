@@ -1,13 +1,10 @@
-use search_cache::{SearchCache, SearchOptions, SlabIndex};
+use search_cache::{SearchCache, SearchOptions, SearchResult, SlabIndex};
 use search_cancel::CancellationToken;
 use std::fs;
 use tempdir::TempDir;
 
-fn guard_indices(result: Result<search_cache::SearchOutcome, anyhow::Error>) -> Vec<SlabIndex> {
-    result
-        .expect("search should succeed")
-        .nodes
-        .expect("noop cancellation token should not cancel")
+fn guard_indices(result: SearchResult<search_cache::SearchOutcome>) -> Vec<SlabIndex> {
+    result.expect("search should succeed").nodes
 }
 
 fn file_names(cache: &mut SearchCache, indices: &[SlabIndex]) -> Vec<String> {
@@ -28,9 +25,7 @@ fn leading_slash_anchors_to_root_segment() {
     fs::File::create(root.join("other/foo/bar/baz.txt")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices = guard_indices(cache.search_with_options(
         "/foo/bar/baz.txt",
         opts,
@@ -55,9 +50,7 @@ fn trailing_slash_requires_exact_last_segment() {
     fs::create_dir_all(root.join("docs/guide_extra")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("docs/guide/", opts, CancellationToken::noop()));
     let names = file_names(&mut cache, &indices);
@@ -96,9 +89,7 @@ fn case_insensitive_segments_match_variants() {
     fs::create_dir_all(root.join("FOO/BAR/Bazooka")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("/foo/bar/baz/", opts, CancellationToken::noop()));
     let names = file_names(&mut cache, &indices);
@@ -117,9 +108,7 @@ fn mixed_prefix_suffix_segments_for_files() {
     fs::File::create(root.join("foo/report_final.txt")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("foo/report.txt", opts, CancellationToken::noop()));
     let names = file_names(&mut cache, &indices);
@@ -161,9 +150,7 @@ fn trailing_slash_deep_exact_directory() {
     fs::create_dir_all(root.join("a/b/cX/d")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("a/b/c/d/", opts, CancellationToken::noop()));
     let names = normalize(&mut cache, &indices, root);
@@ -219,9 +206,7 @@ fn mixed_case_segments_case_sensitive_behavior() {
     fs::create_dir_all(root.join("c/FOO/BAR/BAZ")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("foo/bar/baz/", opts, CancellationToken::noop()));
     let names = normalize(&mut cache, &indices, root);
@@ -247,9 +232,7 @@ fn mixed_case_segments_case_insensitive_behavior() {
     fs::create_dir_all(root.join("foo/bar/Bazooka")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("/foo/bar/baz/", opts, CancellationToken::noop()));
     let names = normalize(&mut cache, &indices, root);
@@ -276,9 +259,7 @@ fn wildcard_last_segment_multiple_extensions() {
     fs::File::create(root.join("b/docs/guide/README.MD")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices = guard_indices(cache.search_with_options(
         "docs/guide/readme.*",
         opts,
@@ -311,9 +292,7 @@ fn wildcard_last_segment_multiple_extensions_case_insensitive() {
     fs::File::create(root.join("c/docs/guide/readmeX.md")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices = guard_indices(cache.search_with_options(
         "docs/guide/readme*.md",
         opts,
@@ -470,9 +449,7 @@ fn unicode_path_segments_case_insensitive() {
     fs::File::create(root.join("CAFÉ/文件/notes.txt")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices = guard_indices(cache.search_with_options(
         "/café/文件/notes.txt",
         opts,
@@ -496,9 +473,7 @@ fn unicode_path_segments_case_sensitive() {
     fs::File::create(root.join("CAFÉ/文件/notes.txt")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices = guard_indices(cache.search_with_options(
         "café/文件/notes.txt",
         opts,
@@ -677,9 +652,7 @@ fn case_sensitive_exact_segment_casing() {
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
     // Case sensitive: only exact lower-case path should be returned for lower-case query.
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("src/lib/core/", opts, CancellationToken::noop()));
     let names = normalize(&mut cache, &indices, root);
@@ -706,9 +679,7 @@ fn case_insensitive_directory_variants() {
     fs::create_dir_all(root.join("c/SRC/LIB/Core")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("/src/lib/core/", opts, CancellationToken::noop()));
     let names = normalize(&mut cache, &indices, root);
@@ -733,9 +704,7 @@ fn mixed_wildcard_case_sensitive_file_variants() {
     fs::File::create(root.join("a/app/config/readme_final.md")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices = guard_indices(cache.search_with_options(
         "app/config/readme.*",
         opts,
@@ -769,9 +738,7 @@ fn mixed_wildcard_case_insensitive_file_variants() {
     fs::File::create(root.join("b/app/config/README.MD")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices = guard_indices(cache.search_with_options(
         "/app/config/readme.*",
         opts,
@@ -797,9 +764,7 @@ fn case_sensitive_file_exact_match_variants() {
     fs::File::create(root.join("guide/readme.md")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices = guard_indices(cache.search_with_options(
         "guide/ReadMe.md",
         opts,
@@ -822,9 +787,7 @@ fn case_insensitive_file_exact_match_variants() {
     fs::File::create(root.join("guide/readme.md")).unwrap();
 
     let mut cache = SearchCache::walk_fs(root.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices = guard_indices(cache.search_with_options(
         "guide/readme.md",
         opts,
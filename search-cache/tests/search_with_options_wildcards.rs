@@ -1,13 +1,10 @@
-use search_cache::{SearchCache, SearchOptions, SlabIndex};
+use search_cache::{SearchCache, SearchOptions, SearchResult, SlabIndex};
 use search_cancel::CancellationToken;
 use std::fs;
 use tempdir::TempDir;
 
-fn guard_indices(result: Result<search_cache::SearchOutcome, anyhow::Error>) -> Vec<SlabIndex> {
-    result
-        .expect("search should succeed")
-        .nodes
-        .expect("noop cancellation token should not cancel")
+fn guard_indices(result: SearchResult<search_cache::SearchOutcome>) -> Vec<SlabIndex> {
+    result.expect("search should succeed").nodes
 }
 
 #[test]
@@ -19,10 +16,8 @@ fn single_segment_wildcard_complex_pattern_case_sensitive() {
     fs::File::create(dir.join("bar_alpha.txt")).unwrap();
     fs::File::create(dir.join("Foo_ALPHA_Bar.TXT")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("foo*alpha*.txt", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -40,10 +35,8 @@ fn single_segment_wildcard_complex_pattern_case_insensitive() {
     fs::File::create(dir.join("foobar_bar.txt")).unwrap();
     fs::File::create(dir.join("FooBar_bar.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices =
         guard_indices(cache.search_with_options("foo*bar*.txt", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -63,10 +56,8 @@ fn leading_wildcard_matches_suffix() {
     fs::File::create(dir.join("beta.txt")).unwrap();
     fs::File::create(dir.join("alpha.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("*beta.txt", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -83,10 +74,8 @@ fn trailing_wildcard_matches_prefix() {
     fs::File::create(dir.join("alpha.txt")).unwrap();
     fs::File::create(dir.join("gamma_alpha.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("alpha*", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -104,10 +93,8 @@ fn question_mark_single_character() {
     fs::File::create(dir.join("file2.txt")).unwrap();
     fs::File::create(dir.join("file10.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices =
         guard_indices(cache.search_with_options("file?.txt", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
@@ -125,10 +112,8 @@ fn star_only_matches_all_files() {
     fs::File::create(dir.join("two.txt")).unwrap();
     fs::File::create(dir.join("three.log")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     let indices = guard_indices(cache.search_with_options("*", opts, CancellationToken::noop()));
     let nodes = cache.expand_file_nodes(&indices);
     // May include root directory; ensure at least the three files are present.
@@ -153,10 +138,8 @@ fn multi_segment_wildcard_intersection_case_sensitive() {
     fs::File::create(dir.join("alpha.txt")).unwrap();
     fs::File::create(dir.join("beta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // Both segments must match: alpha* AND *beta*.txt (beta can appear later)
     let indices = guard_indices(cache.search_with_options(
         "alpha* *beta*.txt",
@@ -181,10 +164,8 @@ fn multi_segment_wildcard_intersection_case_insensitive() {
     fs::File::create(dir.join("alpha_beta.txt")).unwrap();
     fs::File::create(dir.join("alphaGamma_beta.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: true,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(true);
     let indices = guard_indices(cache.search_with_options(
         "alpha* *beta*.txt",
         opts,
@@ -208,10 +189,8 @@ fn complex_mixed_wildcards_and_question_mark() {
     fs::File::create(dir.join("a_b_cx.txt")).unwrap();
     fs::File::create(dir.join("abYYc.txt")).unwrap();
 
-    let mut cache = SearchCache::walk_fs(dir.to_path_buf());
-    let opts = SearchOptions {
-        case_insensitive: false,
-    };
+    let cache = SearchCache::walk_fs(dir.to_path_buf());
+    let opts = SearchOptions::default().with_case_insensitive(false);
     // Pattern: a*b?c*.txt => a then any, b then any single char, c then any, .txt
     let indices =
         guard_indices(cache.search_with_options("a*b?c*.txt", opts, CancellationToken::noop()));
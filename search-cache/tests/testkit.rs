@@ -0,0 +1,81 @@
+//! The `testkit` scaffolding, used the way a downstream crate would.
+
+use cardinal_sdk::EventFlag;
+use search_cache::testkit::{EventScript, TreeSpec, assert_integrity, assert_query_names};
+use std::{
+    fs,
+    time::{Duration, UNIX_EPOCH},
+};
+
+#[test]
+fn tree_spec_creates_what_it_declares() {
+    let tmp = TreeSpec::new()
+        .dir("empty")
+        .file("docs/notes.txt")
+        .sized_file("docs/report.pdf", 2048)
+        .modified("docs/report.pdf", 1_600_000_000)
+        .modified("docs", 1_500_000_000)
+        .create("testkit_tree");
+    let root = tmp.path();
+    assert!(root.join("empty").is_dir());
+    assert_eq!(fs::metadata(root.join("docs/notes.txt")).unwrap().len(), 0);
+    let report = fs::metadata(root.join("docs/report.pdf")).unwrap();
+    assert_eq!(report.len(), 2048);
+    assert_eq!(
+        report.modified().unwrap(),
+        UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+    );
+    // Folder times are set after their children are created.
+    assert_eq!(
+        fs::metadata(root.join("docs")).unwrap().modified().unwrap(),
+        UNIX_EPOCH + Duration::from_secs(1_500_000_000)
+    );
+}
+
+#[test]
+fn event_script_numbers_events_after_the_cache() {
+    let (tmp, mut cache) = TreeSpec::new()
+        .file("a.txt")
+        .file("b.txt")
+        .index("testkit_events");
+    let root = tmp.path();
+    let last = cache.last_event_id();
+    let mut script = EventScript::after(&cache);
+    script
+        .create_dir(&root.join("new"))
+        .create_file(&root.join("new/c.txt"), b"c")
+        .modify(&root.join("a.txt"), b"changed")
+        .rename(&root.join("b.txt"), &root.join("new/b.txt"))
+        .remove(&root.join("a.txt"));
+    let ids: Vec<u64> = script.events().iter().map(|event| event.id).collect();
+    assert_eq!(ids, (last + 1..=last + 6).collect::<Vec<_>>());
+    let flags: Vec<EventFlag> = script.events().iter().map(|event| event.flag).collect();
+    assert_eq!(
+        flags,
+        [
+            EventFlag::ItemCreated | EventFlag::ItemIsDir,
+            EventFlag::ItemCreated | EventFlag::ItemIsFile,
+            EventFlag::ItemModified | EventFlag::ItemIsFile,
+            EventFlag::ItemRenamed | EventFlag::ItemIsFile,
+            EventFlag::ItemRenamed | EventFlag::ItemIsFile,
+            EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        ]
+    );
+    script.apply(&mut cache).unwrap();
+    assert_eq!(cache.last_event_id(), last + 6);
+    assert_query_names(&cache, "txt", &["b.txt", "c.txt"]);
+    assert_query_names(&cache, "new", &["new"]);
+    assert_integrity(&cache);
+
+    // The next script carries on from there.
+    let mut script = EventScript::after(&cache);
+    script.remove(&root.join("new"));
+    assert_eq!(script.events()[0].id, last + 7);
+    assert_eq!(
+        script.events()[0].flag,
+        EventFlag::ItemRemoved | EventFlag::ItemIsDir
+    );
+    script.apply(&mut cache).unwrap();
+    assert_query_names(&cache, "txt", &[]);
+    assert_integrity(&cache);
+}
@@ -0,0 +1,125 @@
+//! macOS hands out decomposed (NFD) file names while people type precomposed
+//! (NFC) text, so name matching has to ignore the difference.
+
+use search_cache::{SearchCache, SearchOptions, testkit::TreeSpec};
+use search_cancel::CancellationToken;
+use std::path::PathBuf;
+use tempdir::TempDir;
+
+const NFC: &str = "caf\u{e9}";
+const NFD: &str = "cafe\u{301}";
+
+struct Fixture {
+    _tmp: TempDir,
+    cache: SearchCache,
+    composed_menu: PathBuf,
+    decomposed_menu: PathBuf,
+    composed_ext: PathBuf,
+    decomposed_ext: PathBuf,
+}
+
+/// Both forms live in their own folder, normalization-insensitive
+/// filesystems would consider them the same name otherwise.
+fn fixture() -> Fixture {
+    let composed_menu = format!("composed/{NFC}-menu.txt");
+    let decomposed_menu = format!("decomposed/{NFD}-menu.txt");
+    let composed_ext = format!("composed/archive.{NFC}");
+    let decomposed_ext = format!("decomposed/backup.{NFD}");
+    let (tmp, cache) = TreeSpec::new()
+        .files([
+            &composed_menu,
+            &decomposed_menu,
+            &composed_ext,
+            &decomposed_ext,
+        ])
+        .index("unicode_normalization");
+    let root = tmp.path().to_path_buf();
+    Fixture {
+        _tmp: tmp,
+        cache,
+        composed_menu: root.join(composed_menu),
+        decomposed_menu: root.join(decomposed_menu),
+        composed_ext: root.join(composed_ext),
+        decomposed_ext: root.join(decomposed_ext),
+    }
+}
+
+fn search_paths(cache: &SearchCache, query: &str, options: SearchOptions) -> Vec<PathBuf> {
+    let nodes = cache
+        .search_with_options(query, options, CancellationToken::noop())
+        .unwrap()
+        .nodes;
+    let mut paths: Vec<_> = nodes
+        .into_iter()
+        .map(|index| cache.node_path(index).unwrap())
+        .collect();
+    paths.sort();
+    paths
+}
+
+fn search(cache: &SearchCache, query: &str) -> Vec<PathBuf> {
+    search_paths(cache, query, SearchOptions::default())
+}
+
+#[test]
+fn bare_terms_match_either_form() {
+    let f = fixture();
+    let both = vec![f.composed_menu.clone(), f.decomposed_menu.clone()];
+    assert_eq!(search(&f.cache, &format!("{NFC}-menu")), both);
+    assert_eq!(search(&f.cache, &format!("{NFD}-menu")), both);
+    assert_eq!(search(&f.cache, &format!("{NFC}-m")), both);
+
+    let options = SearchOptions::default().with_case_insensitive(true);
+    assert_eq!(search_paths(&f.cache, "CAF\u{c9}-MENU", options), both);
+    assert_eq!(
+        search_paths(&f.cache, &format!("{NFD}*.txt"), SearchOptions::default()),
+        both
+    );
+}
+
+#[test]
+fn exact_names_and_path_segments_match_either_form() {
+    let f = fixture();
+    let both = vec![f.composed_menu.clone(), f.decomposed_menu.clone()];
+    assert_eq!(search(&f.cache, &format!("/{NFC}-menu.txt/")), both);
+    assert_eq!(search(&f.cache, &format!("/{NFD}-menu.txt/")), both);
+    // The second segment goes through the per-child matcher.
+    assert_eq!(
+        search(&f.cache, &format!("decomposed/{NFC}")),
+        [f.decomposed_menu.clone()]
+    );
+    assert_eq!(
+        search(&f.cache, &format!("/composed/{NFD}-menu.txt/")),
+        [f.composed_menu.clone()]
+    );
+}
+
+#[test]
+fn accented_extensions_match_either_form() {
+    let f = fixture();
+    let both = vec![f.composed_ext.clone(), f.decomposed_ext.clone()];
+    assert_eq!(search(&f.cache, &format!("ext:{NFC}")), both);
+    assert_eq!(search(&f.cache, &format!("ext:{NFD}")), both);
+    assert_eq!(
+        search(&f.cache, &format!("backup ext:{NFC}")),
+        [f.decomposed_ext.clone()]
+    );
+}
+
+#[test]
+fn byte_exact_option_disables_normalization() {
+    let f = fixture();
+    let options = SearchOptions::default().with_byte_exact(true);
+    assert_eq!(
+        search_paths(&f.cache, &format!("{NFC}-menu"), options),
+        [f.composed_menu.clone()]
+    );
+    assert_eq!(
+        search_paths(&f.cache, &format!("/{NFD}-menu.txt/"), options),
+        [f.decomposed_menu.clone()]
+    );
+    assert_eq!(
+        search_paths(&f.cache, &format!("ext:{NFC}"), options),
+        [f.composed_ext.clone()]
+    );
+}
@@ -0,0 +1,159 @@
+//! Unmounting a volume hides its nodes instead of removing them, remounting
+//! rescans the mount point.
+
+use cardinal_sdk::EventFlag;
+use search_cache::{SearchCache, SearchOptions, testkit::EventScript};
+use search_cancel::CancellationToken;
+use std::{fs, path::PathBuf};
+use tempdir::TempDir;
+
+struct Volume {
+    _tmp: TempDir,
+    cache: SearchCache,
+    mount_point: PathBuf,
+    /// Where the volume's files live while it's "unmounted".
+    detached: PathBuf,
+}
+
+impl Volume {
+    /// root/{home.txt, Volumes/Ext/{a.txt, sub/b.txt}}
+    fn new() -> Self {
+        let tmp = TempDir::new("volume_events").unwrap();
+        let root = tmp.path().join("root");
+        let mount_point = root.join("Volumes/Ext");
+        fs::create_dir_all(mount_point.join("sub")).unwrap();
+        fs::write(root.join("home.txt"), b"home").unwrap();
+        fs::write(mount_point.join("a.txt"), b"a").unwrap();
+        fs::write(mount_point.join("sub/b.txt"), b"b").unwrap();
+        let detached = tmp.path().join("detached");
+        let cache = SearchCache::walk_fs(root);
+        Self {
+            _tmp: tmp,
+            cache,
+            mount_point,
+            detached,
+        }
+    }
+
+    fn unmount(&mut self) {
+        fs::rename(&self.mount_point, &self.detached).unwrap();
+        let mut script = EventScript::after(&self.cache);
+        script.event(&self.mount_point, EventFlag::Unmount);
+        script.apply(&mut self.cache).unwrap();
+    }
+
+    fn mount(&mut self) {
+        fs::rename(&self.detached, &self.mount_point).unwrap();
+        let mut script = EventScript::after(&self.cache);
+        script.event(&self.mount_point, EventFlag::Mount);
+        script.apply(&mut self.cache).unwrap();
+    }
+
+    fn names(&self, query: &str, include_offline: bool) -> Vec<String> {
+        let options = SearchOptions::default().with_include_offline(include_offline);
+        let nodes = self
+            .cache
+            .search_with_options(query, options, CancellationToken::noop())
+            .unwrap()
+            .nodes;
+        let mut names: Vec<String> = nodes
+            .iter()
+            .filter_map(|&index| self.cache.node_path(index))
+            .filter_map(|path| Some(path.file_name()?.to_string_lossy().into_owned()))
+            .collect();
+        names.sort();
+        names
+    }
+}
+
+#[test]
+fn unmounted_volume_is_hidden_but_kept() {
+    let mut volume = Volume::new();
+    let total = volume.cache.get_total_files();
+    assert_eq!(volume.names(".txt", false), ["a.txt", "b.txt", "home.txt"]);
+
+    volume.unmount();
+    assert_eq!(volume.cache.get_total_files(), total);
+    assert_eq!(
+        volume.cache.offline_roots().iter().collect::<Vec<_>>(),
+        [&volume.mount_point]
+    );
+    assert_eq!(volume.names(".txt", false), ["home.txt"]);
+    // The mount point itself is offline too.
+    assert!(volume.names("Ext", false).is_empty());
+    assert_eq!(volume.names("Ext", true), ["Ext"]);
+    assert_eq!(volume.names(".txt", true), ["a.txt", "b.txt", "home.txt"]);
+}
+
+#[test]
+fn remount_reconciles_changes_made_while_detached() {
+    let mut volume = Volume::new();
+    volume.unmount();
+    fs::remove_file(volume.detached.join("a.txt")).unwrap();
+    fs::write(volume.detached.join("sub/c.txt"), b"c").unwrap();
+
+    volume.mount();
+    assert!(volume.cache.offline_roots().is_empty());
+    assert_eq!(volume.names(".txt", false), ["b.txt", "c.txt", "home.txt"]);
+    assert_eq!(volume.names(".txt", true), ["b.txt", "c.txt", "home.txt"]);
+}
+
+#[test]
+fn changes_reported_with_the_unmount_are_left_for_the_remount() {
+    let mut volume = Volume::new();
+    fs::rename(&volume.mount_point, &volume.detached).unwrap();
+    let mut script = EventScript::after(&volume.cache);
+    script
+        .event(
+            &volume.mount_point.join("sub/b.txt"),
+            EventFlag::ItemRemoved | EventFlag::ItemIsFile,
+        )
+        .event(&volume.mount_point, EventFlag::Unmount);
+    script.apply(&mut volume.cache).unwrap();
+    assert_eq!(volume.names(".txt", true), ["a.txt", "b.txt", "home.txt"]);
+
+    volume.mount();
+    assert_eq!(volume.names(".txt", false), ["a.txt", "b.txt", "home.txt"]);
+}
+
+#[test]
+fn unmount_and_remount_in_one_batch_leaves_the_volume_online() {
+    let mut volume = Volume::new();
+    fs::write(volume.mount_point.join("d.txt"), b"d").unwrap();
+    let mut script = EventScript::after(&volume.cache);
+    script
+        .event(&volume.mount_point, EventFlag::Unmount)
+        .event(&volume.mount_point, EventFlag::Mount);
+    script.apply(&mut volume.cache).unwrap();
+    assert!(volume.cache.offline_roots().is_empty());
+    assert_eq!(
+        volume.names(".txt", false),
+        ["a.txt", "b.txt", "d.txt", "home.txt"]
+    );
+}
+
+#[test]
+fn rescan_brings_offline_volumes_back() {
+    let mut volume = Volume::new();
+    volume.unmount();
+    // Back without a mount event.
+    fs::rename(&volume.detached, &volume.mount_point).unwrap();
+    volume.cache.rescan();
+    assert!(volume.cache.offline_roots().is_empty());
+    assert_eq!(volume.names(".txt", false), ["a.txt", "b.txt", "home.txt"]);
+}
+
+#[test]
+fn rescan_keeps_the_volume_offline() {
+    let mut volume = Volume::new();
+    volume.unmount();
+    volume.cache.rescan();
+    assert_eq!(
+        volume.cache.offline_roots().iter().collect::<Vec<_>>(),
+        [&volume.mount_point]
+    );
+    assert_eq!(volume.names(".txt", true), ["home.txt"]);
+    volume.mount();
+    assert!(volume.cache.offline_roots().is_empty());
+    assert_eq!(volume.names(".txt", false), ["a.txt", "b.txt", "home.txt"]);
+}
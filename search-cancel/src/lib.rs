@@ -1,4 +1,7 @@
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
 
 /// How often long-running loops should check whether execution was cancelled.
 pub const CANCEL_CHECK_INTERVAL: usize = 0x10000;
@@ -10,6 +13,7 @@ pub static ACTIVE_SEARCH_VERSION: AtomicU64 = AtomicU64::new(0);
 pub struct CancellationToken {
     active_version: &'static AtomicU64,
     version: u64,
+    deadline: Option<Instant>,
 }
 
 impl CancellationToken {
@@ -18,6 +22,7 @@ impl CancellationToken {
         Self {
             version: 0,
             active_version: &NOOP,
+            deadline: None,
         }
     }
 
@@ -26,10 +31,43 @@ impl CancellationToken {
         Self {
             version,
             active_version: &ACTIVE_SEARCH_VERSION,
+            deadline: None,
+        }
+    }
+
+    /// Like [`Self::new`], following `active_version` instead of
+    /// [`ACTIVE_SEARCH_VERSION`], for work cancelled independently of
+    /// searches. Storing any other version there cancels the token.
+    pub fn with_active_version(active_version: &'static AtomicU64, version: u64) -> Self {
+        active_version.store(version, Ordering::SeqCst);
+        Self {
+            version,
+            active_version,
+            deadline: None,
+        }
+    }
+
+    /// Also treat the token as cancelled once `deadline` has passed. A token
+    /// with a deadline already keeps the earlier one.
+    pub fn with_deadline(self, deadline: Instant) -> Self {
+        Self {
+            deadline: Some(
+                self.deadline
+                    .map_or(deadline, |current| current.min(deadline)),
+            ),
+            ..self
         }
     }
 
     pub fn is_cancelled(&self) -> bool {
+        self.version != self.active_version.load(Ordering::Relaxed)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Whether a newer search superseded this one, ignoring the deadline.
+    pub fn is_superseded(&self) -> bool {
         self.version != self.active_version.load(Ordering::Relaxed)
     }
 }
@@ -37,6 +75,7 @@ impl CancellationToken {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
 
     #[test]
     fn noop_token_is_never_cancelled() {
@@ -53,4 +92,30 @@ mod tests {
         let _token_v2 = CancellationToken::new(2);
         assert!(token_v1.is_cancelled());
     }
+
+    #[test]
+    fn cancelled_through_own_active_version() {
+        static WORKER: AtomicU64 = AtomicU64::new(0);
+        let token = CancellationToken::with_active_version(&WORKER, 7);
+        assert!(!token.is_cancelled());
+
+        WORKER.store(0, Ordering::SeqCst);
+        assert!(token.is_cancelled());
+        assert!(token.is_superseded());
+    }
+
+    #[test]
+    fn cancelled_after_deadline() {
+        let now = Instant::now();
+        let token = CancellationToken::noop().with_deadline(now + Duration::from_secs(3600));
+        assert!(!token.is_cancelled());
+        let expired = CancellationToken::noop().with_deadline(now);
+        assert!(expired.is_cancelled());
+        assert!(!expired.is_superseded());
+        // A later deadline doesn't extend an earlier one.
+        let extended = expired.with_deadline(now + Duration::from_secs(3600));
+        assert!(extended.is_cancelled());
+        let shortened = token.with_deadline(now);
+        assert!(shortened.is_cancelled());
+    }
 }
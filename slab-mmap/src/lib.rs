@@ -239,6 +239,17 @@ impl<T> Slab<T> {
         self.len == 0
     }
 
+    /// Number of initialized slots, vacant ones included. `slots() - len()`
+    /// slots are holes waiting on the freelist.
+    pub fn slots(&self) -> usize {
+        self.entries_len
+    }
+
+    /// Bytes of the backing file, the slots mapped so far.
+    pub fn mapped_bytes(&self) -> usize {
+        self.entries_capacity.get() * mem::size_of::<Entry<T>>()
+    }
+
     pub fn iter(&self) -> SlabIter<'_, T> {
         SlabIter {
             slab: self,
@@ -166,6 +166,29 @@ fn test_capacity_growth() {
     }
 }
 
+#[test]
+fn test_slots_count_holes() {
+    let mut slab = Slab::new().unwrap();
+    assert_eq!(slab.slots(), 0);
+    let initial_bytes = slab.mapped_bytes();
+    assert!(initial_bytes >= 1024 * std::mem::size_of::<u64>());
+
+    let indices: Vec<usize> = (0..10u64).map(|i| slab.insert(i).unwrap()).collect();
+    for &idx in &indices[..4] {
+        slab.try_remove(idx);
+    }
+    assert_eq!(slab.len(), 6);
+    assert_eq!(slab.slots(), 10);
+
+    // Holes are refilled before the slab grows.
+    slab.insert(42).unwrap();
+    assert_eq!(slab.slots(), 10);
+    for i in 0..1024 {
+        slab.insert(i).unwrap();
+    }
+    assert!(slab.mapped_bytes() > initial_bytes);
+}
+
 #[test]
 fn test_index_trait() {
     let mut slab = Slab::new().unwrap();
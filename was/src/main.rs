@@ -1,6 +1,10 @@
-use cardinal_sdk::{EventFlag, EventWatcher, event_id_to_timestamp};
+mod summary;
+
+use cardinal_sdk::{EventBatch, EventWatcher, event_id_to_timestamp, list_volumes, volume_of_path};
 use clap::Parser;
-use std::time::Duration;
+use crossbeam::channel::{select, tick};
+use std::{collections::HashMap, time::Duration};
+use summary::Summary;
 
 #[derive(Parser)]
 struct Cli {
@@ -9,8 +13,23 @@ struct Cli {
     /// Start event id, default to 0.
     #[clap(long, default_value_t = 0)]
     since: u64,
+    /// Instead of printing every event, print a table of the activity every
+    /// this many seconds.
+    #[clap(long, value_name = "SECONDS")]
+    summary: Option<f64>,
+    /// Directories are grouped at this many path components in the summary.
+    #[clap(long, default_value_t = 3)]
+    depth: usize,
+    /// Directories listed in the summary.
+    #[clap(long, default_value_t = 10)]
+    top: usize,
 }
 
+/// Directories and unique paths tracked per summary window, beyond which
+/// events only count toward the totals.
+const MAX_SUMMARY_DIRS: usize = 10_000;
+const MAX_SUMMARY_PATHS: usize = 100_000;
+
 fn main() {
     let cli = Cli::parse();
     let path = cli.path.unwrap_or_else(|| {
@@ -19,25 +38,49 @@ fn main() {
             .to_string_lossy()
             .to_string()
     });
-    let (dev, event_stream) = EventWatcher::spawn(path, cli.since, 0.1);
-    let cache = &mut std::collections::HashMap::new();
+    let volumes = list_volumes().unwrap_or_else(|err| {
+        eprintln!("Failed to list volumes, assuming a single device: {err}");
+        Vec::new()
+    });
+    for volume in &volumes {
+        println!(
+            "volume {:?}: dev {}, {}, history: {}",
+            volume.mount_point, volume.dev, volume.fs_type, volume.supports_history
+        );
+    }
+    let (_, event_stream) = EventWatcher::spawn(path, cli.since, 0.1);
+    if let Some(seconds) = cli.summary {
+        let summary = Summary::new(cli.depth, MAX_SUMMARY_DIRS, MAX_SUMMARY_PATHS);
+        print_summaries(
+            event_stream,
+            Duration::from_secs_f64(seconds),
+            cli.top,
+            summary,
+        );
+        return;
+    }
+    // Event ids map to timestamps per device, so keep one lookup cache each.
+    let mut caches: HashMap<_, HashMap<i64, u64>> = HashMap::new();
     let mut history_done = false;
     let timezone = chrono::Local::now().timezone();
     loop {
-        let events = if history_done {
+        let batch = if history_done {
             // If history is done, we try to drain the event stream with a timeout.
             event_stream.recv_timeout(Duration::from_secs_f32(0.5)).ok()
         } else {
             event_stream.recv().ok()
         };
-        let Some(events) = events else {
+        let Some(batch) = batch else {
             break;
         };
+        history_done |= batch.history_done();
+        let EventBatch { device, events, .. } = batch;
         for event in events {
-            if event.flag.contains(EventFlag::HistoryDone) {
-                history_done = true;
-            }
-            let timestamp = event_id_to_timestamp(dev, event.id, cache);
+            let event_dev = volume_of_path(&volumes, &event.path)
+                .map(|volume| volume.dev)
+                .unwrap_or(device);
+            let cache = caches.entry(event_dev).or_default();
+            let timestamp = event_id_to_timestamp(event_dev, event.id, cache);
             let time = chrono::DateTime::from_timestamp(timestamp, 0)
                 .unwrap()
                 .with_timezone(&timezone);
@@ -45,3 +88,30 @@ fn main() {
         }
     }
 }
+
+/// Aggregate events over windows of `interval`, redrawing the table after each.
+fn print_summaries(
+    event_stream: EventWatcher,
+    interval: Duration,
+    top: usize,
+    mut summary: Summary,
+) {
+    let ticker = tick(interval);
+    loop {
+        select! {
+            recv(event_stream) -> events => {
+                let Ok(events) = events else {
+                    break;
+                };
+                for event in &events {
+                    summary.record(event);
+                }
+            }
+            recv(ticker) -> _ => {
+                // Clear the screen and go home, so the table refreshes in place.
+                print!("\x1b[2J\x1b[H{}", summary.render(interval, top));
+                summary.clear();
+            }
+        }
+    }
+}
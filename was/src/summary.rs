@@ -0,0 +1,232 @@
+use cardinal_sdk::FsEvent;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+/// Event activity over one `--summary` window: counts per directory, grouped
+/// at `depth` components, per flag and in total. Memory stays bounded, events
+/// in directories beyond `max_dirs` go to an "other" bucket and unique paths
+/// stop being counted at `max_paths`.
+#[derive(Debug)]
+pub struct Summary {
+    depth: usize,
+    max_dirs: usize,
+    max_paths: usize,
+    dirs: HashMap<PathBuf, u64>,
+    other: u64,
+    /// An event counts once for each flag it has.
+    flags: BTreeMap<&'static str, u64>,
+    paths: HashSet<PathBuf>,
+    /// Some paths weren't counted since `paths` was full.
+    paths_capped: bool,
+    events: u64,
+}
+
+impl Summary {
+    pub fn new(depth: usize, max_dirs: usize, max_paths: usize) -> Self {
+        Self {
+            depth,
+            max_dirs,
+            max_paths,
+            dirs: HashMap::new(),
+            other: 0,
+            flags: BTreeMap::new(),
+            paths: HashSet::new(),
+            paths_capped: false,
+            events: 0,
+        }
+    }
+
+    pub fn record(&mut self, event: &FsEvent) {
+        self.events += 1;
+        let dir = group(&event.path, self.depth);
+        if let Some(count) = self.dirs.get_mut(&dir) {
+            *count += 1;
+        } else if self.dirs.len() < self.max_dirs {
+            self.dirs.insert(dir, 1);
+        } else {
+            self.other += 1;
+        }
+        for (name, _) in event.flag.iter_names() {
+            *self.flags.entry(name).or_default() += 1;
+        }
+        if !self.paths.contains(&event.path) {
+            if self.paths.len() < self.max_paths {
+                self.paths.insert(event.path.clone());
+            } else {
+                self.paths_capped = true;
+            }
+        }
+    }
+
+    /// Start the next window.
+    pub fn clear(&mut self) {
+        self.dirs.clear();
+        self.other = 0;
+        self.flags.clear();
+        self.paths.clear();
+        self.paths_capped = false;
+        self.events = 0;
+    }
+
+    /// The `n` busiest directories, busiest first, ties by path.
+    pub fn top_dirs(&self, n: usize) -> Vec<(&Path, u64)> {
+        let mut dirs: Vec<(&Path, u64)> = self
+            .dirs
+            .iter()
+            .map(|(dir, &count)| (dir.as_path(), count))
+            .collect();
+        dirs.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        dirs.truncate(n);
+        dirs
+    }
+
+    /// Unique paths seen, and whether more were left uncounted.
+    pub fn unique_paths(&self) -> (usize, bool) {
+        (self.paths.len(), self.paths_capped)
+    }
+
+    /// The window as a table, `interval` being its length.
+    pub fn render(&self, interval: Duration, top: usize) -> String {
+        let mut out = String::new();
+        let (paths, capped) = self.unique_paths();
+        let rate = self.events as f64 / interval.as_secs_f64().max(f64::EPSILON);
+        let _ = writeln!(
+            out,
+            "{:.1}s window: {} events ({rate:.1}/s), {}{paths} unique paths",
+            interval.as_secs_f64(),
+            self.events,
+            if capped { ">=" } else { "" },
+        );
+        let _ = writeln!(out, "\ntop directories (depth {}):", self.depth);
+        for (dir, count) in self.top_dirs(top) {
+            let _ = writeln!(out, "{count:>10}  {}", dir.display());
+        }
+        if self.other > 0 {
+            let _ = writeln!(out, "{:>10}  (other)", self.other);
+        }
+        let _ = writeln!(out, "\nflags:");
+        let mut flags: Vec<_> = self.flags.iter().collect();
+        flags.sort_by(|a, b| b.1.cmp(a.1));
+        for (name, count) in flags {
+            let _ = writeln!(out, "{count:>10}  {name}");
+        }
+        out
+    }
+}
+
+/// `path` cut after its first `depth` named components.
+fn group(path: &Path, depth: usize) -> PathBuf {
+    let mut named = 0;
+    path.components()
+        .take_while(|component| {
+            if let Component::Normal(_) = component {
+                named += 1;
+            }
+            named <= depth
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cardinal_sdk::EventFlag;
+
+    fn event(path: &str, flag: EventFlag) -> FsEvent {
+        FsEvent {
+            path: PathBuf::from(path),
+            flag,
+            id: 1,
+        }
+    }
+
+    fn modified(path: &str) -> FsEvent {
+        event(path, EventFlag::ItemModified | EventFlag::ItemIsFile)
+    }
+
+    #[test]
+    fn test_group_by_depth() {
+        let path = Path::new("/Users/me/Library/Caches/app/db");
+        assert_eq!(group(path, 3), Path::new("/Users/me/Library"));
+        assert_eq!(group(path, 1), Path::new("/Users"));
+        assert_eq!(group(path, 0), Path::new("/"));
+        assert_eq!(group(Path::new("/tmp/a"), 3), Path::new("/tmp/a"));
+    }
+
+    #[test]
+    fn test_top_dirs_and_flags() {
+        let mut summary = Summary::new(2, 100, 100);
+        for _ in 0..3 {
+            summary.record(&modified("/Users/me/notes.txt"));
+        }
+        summary.record(&modified("/Users/me/Library/db"));
+        summary.record(&event(
+            "/private/tmp/x",
+            EventFlag::ItemCreated | EventFlag::ItemIsFile,
+        ));
+        summary.record(&event(
+            "/private/var/y",
+            EventFlag::ItemRemoved | EventFlag::ItemIsDir,
+        ));
+
+        assert_eq!(summary.events, 6);
+        assert_eq!(
+            summary.top_dirs(2),
+            [(Path::new("/Users/me"), 4), (Path::new("/private/tmp"), 1),]
+        );
+        assert_eq!(summary.top_dirs(10).len(), 3);
+        assert_eq!(summary.flags["ItemModified"], 4);
+        assert_eq!(summary.flags["ItemIsFile"], 5);
+        assert_eq!(summary.flags["ItemIsDir"], 1);
+        assert_eq!(summary.unique_paths(), (4, false));
+
+        let table = summary.render(Duration::from_secs(2), 1);
+        assert!(
+            table.contains("6 events (3.0/s), 4 unique paths"),
+            "{table}"
+        );
+        assert!(table.contains("/Users/me"), "{table}");
+        assert!(!table.contains("/private/tmp"), "{table}");
+    }
+
+    #[test]
+    fn test_caps_keep_memory_bounded() {
+        let mut summary = Summary::new(1, 2, 3);
+        for dir in ["a", "b", "c", "d", "a"] {
+            summary.record(&modified(&format!("/{dir}/file")));
+        }
+        assert_eq!(
+            summary.top_dirs(10),
+            [(Path::new("/a"), 2), (Path::new("/b"), 1)]
+        );
+        assert_eq!(summary.other, 2);
+        // Paths already seen are still recognized once the cap is reached.
+        assert_eq!(summary.unique_paths(), (3, true));
+        assert!(
+            summary
+                .render(Duration::from_secs(1), 5)
+                .contains("(other)")
+        );
+    }
+
+    #[test]
+    fn test_clear_starts_a_fresh_window() {
+        let mut summary = Summary::new(1, 1, 1);
+        summary.record(&modified("/a/file"));
+        summary.record(&modified("/b/file"));
+        assert_eq!(summary.unique_paths(), (1, true));
+        summary.clear();
+        assert_eq!(summary.events, 0);
+        assert!(summary.top_dirs(10).is_empty());
+        assert_eq!(summary.other, 0);
+        assert!(summary.flags.is_empty());
+        assert_eq!(summary.unique_paths(), (0, false));
+
+        summary.record(&modified("/b/file"));
+        assert_eq!(summary.top_dirs(10), [(Path::new("/b"), 1)]);
+    }
+}